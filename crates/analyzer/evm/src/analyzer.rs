@@ -1,10 +1,13 @@
 //! EVM analyzer implementation.
 
-use invar_core::model::{ProgramModel, FunctionModel};
+use invar_core::model::{
+    ArithmeticOp, FunctionModel, OverflowProtection, ProgramModel, StateVar, Uncertainty,
+    UncheckedArithmetic,
+};
 use invar_core::traits::ChainAnalyzer;
 use invar_core::Result;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
-use std::collections::BTreeSet;
 use tracing::info;
 
 /// Analyzer for EVM (Solidity) smart contracts.
@@ -14,39 +17,129 @@ impl ChainAnalyzer for EvmAnalyzer {
     fn analyze(&self, path: &Path) -> Result<ProgramModel> {
         info!("Analyzing EVM contract at {:?}", path);
 
-        let source = std::fs::read_to_string(path)
-            .map_err(invar_core::InvarError::IoError)?;
+        let source = std::fs::read_to_string(path).map_err(invar_core::InvarError::IoError)?;
+        let lines: Vec<&str> = source.lines().collect();
 
         // Parse Solidity source code
-        let contract_name = extract_contract_name(&source)
-            .unwrap_or_else(|| "UnknownContract".to_string());
+        let contracts = extract_contracts(&lines);
+        let contract = main_contract(&contracts)
+            .cloned()
+            .unwrap_or_else(|| Contract {
+                name: "UnknownContract".to_string(),
+                parents: Vec::new(),
+                header: 0,
+                end: lines.len().saturating_sub(1),
+            });
 
-        let functions = extract_public_functions(&source);
-        info!("Found {} public functions in contract", functions.len());
-
-        let state_vars = extract_state_variables(&source);
-        info!("Found {} state variables", state_vars.len());
+        // Parents missing from this file are looked up next to it
+        let sibling_sources = if contract_parents_resolved(&contract, &contracts) {
+            Vec::new()
+        } else {
+            sibling_sources(path)
+        };
+        let sibling_lines: Vec<Vec<&str>> = sibling_sources
+            .iter()
+            .map(|source| source.lines().collect())
+            .collect();
+        let mut scope = BTreeMap::new();
+        for (source_lines, local) in sibling_lines
+            .iter()
+            .map(|source_lines| (source_lines, false))
+            .chain([(&lines, true)])
+        {
+            for found in extract_contracts(source_lines) {
+                scope.insert(found.name.clone(), (found, source_lines.as_slice(), local));
+            }
+        }
+        scope.insert(
+            contract.name.clone(),
+            (contract.clone(), lines.as_slice(), true),
+        );
 
         // Create program model with analyzed information
         let mut program = ProgramModel::new(
-            contract_name,
+            contract.name.clone(),
             "evm".to_string(),
             path.to_string_lossy().to_string(),
         );
 
-        // Add extracted functions to the program model
-        for func_name in functions {
-            let func = FunctionModel {
-                name: func_name,
-                parameters: Vec::new(),
-                return_type: None,
-                mutates: BTreeSet::new(),
-                reads: BTreeSet::new(),
-                is_entry_point: true,
-                is_pure: false,
-            };
-            program.add_function(func);
+        // Parents first, so overrides in derived contracts win
+        let mut order = Vec::new();
+        linearize(&contract.name, &scope, &mut BTreeSet::new(), &mut order);
+        program
+            .errors
+            .extend(extract_declarations(&lines, "error ", 0));
+        for (inherited, source_lines, local) in order.iter().map(|name| &scope[name]) {
+            let body = &source_lines[inherited.header..=inherited.end];
+            program
+                .events
+                .extend(extract_declarations(body, "event ", 1));
+            program
+                .errors
+                .extend(extract_declarations(body, "error ", 1));
+            program
+                .contract_invariants
+                .extend(contract_invariants(source_lines, inherited));
+            for var in extract_state_variables(source_lines, inherited) {
+                program.add_state_var(var);
+            }
+            for function in extract_public_functions(source_lines, inherited) {
+                let (start, end) = function.span;
+                let mutates = state_writes(&source_lines[start - 1..end], &program.state_vars);
+                let low_level = low_level_uses(&source_lines[start - 1..end], start);
+                program
+                    .uncertainties
+                    .extend(low_level.iter().map(|&(construct, line)| Uncertainty {
+                        function_name: function.name.clone(),
+                        construct: construct.to_string(),
+                        line,
+                    }));
+                let func = FunctionModel {
+                    name: function.name,
+                    parameters: Vec::new(),
+                    return_type: None,
+                    mutates,
+                    reads: BTreeSet::new(),
+                    is_entry_point: true,
+                    is_pure: function.read_only,
+                    overflow_protection: OverflowProtection::Unknown,
+                    // Spans only make sense for the analyzed file
+                    span: local.then_some(function.span),
+                    modifiers: function.modifiers,
+                    uses_low_level: !low_level.is_empty(),
+                    pre_conditions: Vec::new(),
+                    post_conditions: Vec::new(),
+                };
+                program.add_function(func);
+            }
+        }
+
+        let declared: BTreeSet<String> = program.state_vars.keys().cloned().collect();
+        for var in program.state_vars.values_mut() {
+            var.depends_on.retain(|dep| declared.contains(dep));
+        }
+
+        // Integer state updates that can wrap, in this file only
+        let legacy = legacy_arithmetic(&lines);
+        let integers: BTreeSet<String> = program
+            .state_vars
+            .values()
+            .filter(|var| var.type_name.starts_with("uint") || var.type_name.starts_with("int"))
+            .map(|var| var.name.clone())
+            .collect();
+        for (inherited, _, local) in order.iter().map(|name| &scope[name]) {
+            if *local {
+                program
+                    .unchecked_arithmetic
+                    .extend(unchecked_updates(&lines, inherited, &integers, legacy));
+            }
         }
+        info!(
+            "Found {} public functions and {} state variables in {}",
+            program.functions.len(),
+            program.state_vars.len(),
+            program.name
+        );
 
         Ok(program)
     }
@@ -54,72 +147,863 @@ impl ChainAnalyzer for EvmAnalyzer {
     fn chain(&self) -> &str {
         "evm"
     }
+
+    fn supported_extensions(&self) -> &'static [&'static str] {
+        &[".sol"]
+    }
 }
 
-/// Extract contract name from Solidity source code.
-fn extract_contract_name(source: &str) -> Option<String> {
-    for line in source.lines() {
-        if line.trim_start().starts_with("contract ") {
-            let contract_part = line.split("contract ").nth(1)?;
-            let name = contract_part.split(|c: char| c == '{' || c == '(' || c == ';')
-                .next()?
-                .trim();
-            return Some(name.to_string());
+/// A contract (or abstract contract/interface) declaration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Contract {
+    name: String,
+    /// Contracts listed after `is`, in declaration order.
+    parents: Vec<String>,
+    /// 0-based line of the `contract` keyword.
+    header: usize,
+    /// 0-based line closing the contract body.
+    end: usize,
+}
+
+/// A public or external function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SolidityFunction {
+    name: String,
+    /// 1-based inclusive line span.
+    span: (usize, usize),
+    /// Modifiers applied in the header (`onlyOwner`, `nonReentrant`, ...).
+    modifiers: Vec<String>,
+    /// Declared `view` or `pure`.
+    read_only: bool,
+}
+
+/// Extract contract declarations and their inheritance lists.
+fn extract_contracts(lines: &[&str]) -> Vec<Contract> {
+    let mut contracts = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        let declaration = ["contract ", "abstract contract ", "interface "]
+            .iter()
+            .find_map(|keyword| trimmed.strip_prefix(keyword));
+        let Some(declaration) = declaration else {
+            continue;
+        };
+        let header = declaration.split(['{', ';']).next().unwrap_or_default();
+        let (name, parents) = match header.split_once(" is ") {
+            Some((name, parents)) => (name, parents),
+            None => (header, ""),
+        };
+        let name = name.split('(').next().unwrap_or_default().trim();
+        if name.is_empty() {
+            continue;
         }
+        contracts.push(Contract {
+            name: name.to_string(),
+            parents: parents
+                .split(',')
+                // Base constructor arguments: `is ERC20("Token", "TKN")`
+                .map(|parent| parent.split('(').next().unwrap_or_default().trim())
+                .filter(|parent| !parent.is_empty())
+                .map(String::from)
+                .collect(),
+            header: idx,
+            end: function_end(lines, idx),
+        });
     }
-    None
+    contracts
 }
 
-/// Extract public and external function names from Solidity source code.
-fn extract_public_functions(source: &str) -> Vec<String> {
+/// The most derived contract: the first one no other contract inherits from.
+fn main_contract(contracts: &[Contract]) -> Option<&Contract> {
+    contracts
+        .iter()
+        .find(|contract| {
+            !contracts
+                .iter()
+                .any(|other| other.parents.contains(&contract.name))
+        })
+        .or_else(|| contracts.first())
+}
+
+/// Whether every ancestor of `contract` is declared in `contracts`.
+fn contract_parents_resolved(contract: &Contract, contracts: &[Contract]) -> bool {
+    let mut pending = contract.parents.clone();
+    let mut seen = BTreeSet::new();
+    while let Some(parent) = pending.pop() {
+        if !seen.insert(parent.clone()) {
+            continue;
+        }
+        match contracts.iter().find(|c| c.name == parent) {
+            Some(found) => pending.extend(found.parents.iter().cloned()),
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Sources of the other `.sol` files in the analyzed file's directory.
+fn sibling_sources(path: &Path) -> Vec<String> {
+    let Some(dir) = path.parent() else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|sibling| {
+            sibling.extension().is_some_and(|ext| ext == "sol")
+                && sibling.file_name() != path.file_name()
+        })
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .filter_map(|sibling| std::fs::read_to_string(sibling).ok())
+        .collect()
+}
+
+/// Contracts known by name, with their source lines and whether they come
+/// from the analyzed file.
+type ContractScope<'a> = BTreeMap<String, (Contract, &'a [&'a str], bool)>;
+
+/// Append `name` and its ancestors to `order`, bases first; unknown parents
+/// are skipped.
+fn linearize(
+    name: &str,
+    scope: &ContractScope<'_>,
+    visited: &mut BTreeSet<String>,
+    order: &mut Vec<String>,
+) {
+    if !visited.insert(name.to_string()) {
+        return;
+    }
+    let Some((contract, _, _)) = scope.get(name) else {
+        info!("Inherited contract {} not found", name);
+        return;
+    };
+    for parent in &contract.parents {
+        linearize(parent, scope, visited, order);
+    }
+    order.push(name.to_string());
+}
+
+/// Extract public and external functions declared directly in `contract`,
+/// including `fallback` and `receive`.
+fn extract_public_functions(lines: &[&str], contract: &Contract) -> Vec<SolidityFunction> {
     let mut functions = Vec::new();
-    for line in source.lines() {
+    for start in contract.header + 1..=contract.end.min(lines.len().saturating_sub(1)) {
+        let trimmed = lines[start].trim_start();
+        let name = match trimmed.strip_prefix("function ") {
+            Some(rest) => rest.split('(').next(),
+            // Declared without the `function` keyword
+            None => ["fallback", "receive"].into_iter().find(|special| {
+                trimmed
+                    .strip_prefix(special)
+                    .is_some_and(|rest| rest.trim_start().starts_with('('))
+            }),
+        };
+        let Some(name) = name else {
+            continue;
+        };
+        let header = function_header(lines, start);
+        if !(header.contains(" public") || header.contains(" external")) {
+            continue;
+        }
+        functions.push(SolidityFunction {
+            name: name.trim().to_string(),
+            span: (start + 1, function_end(lines, start) + 1),
+            modifiers: function_modifiers(&header),
+            read_only: header.contains(" view") || header.contains(" pure"),
+        });
+    }
+    functions
+}
+
+/// `@invariant` expressions in the comments directly above `contract`, in
+/// source order (`/// @invariant: totalSupply > 0`).
+fn contract_invariants(lines: &[&str], contract: &Contract) -> Vec<String> {
+    let comments = lines[..contract.header]
+        .iter()
+        .rev()
+        .map(|line| line.trim())
+        .take_while(|line| {
+            line.starts_with("//") || line.starts_with("/*") || line.starts_with('*')
+        })
+        .collect::<Vec<_>>();
+    comments
+        .into_iter()
+        .rev()
+        .filter_map(|line| {
+            let text = line.trim_start_matches(['/', '*']).trim_start();
+            let expr = text.strip_prefix("@invariant")?;
+            let expr = expr.trim_start_matches(':').trim_end_matches("*/").trim();
+            (!expr.is_empty()).then(|| expr.to_string())
+        })
+        .collect()
+}
+
+/// The function header starting at `start`, joined up to its body or `;`.
+fn function_header(lines: &[&str], start: usize) -> String {
+    let mut header = String::new();
+    for line in &lines[start..] {
+        let end = line.find(['{', ';']);
+        header.push(' ');
+        header.push_str(line[..end.unwrap_or(line.len())].trim());
+        if end.is_some() {
+            break;
+        }
+    }
+    header
+}
+
+/// `event` or `error` declarations at brace depth `depth` of `lines`, with
+/// their parameter types.
+fn extract_declarations(lines: &[&str], keyword: &str, depth: i32) -> Vec<(String, Vec<String>)> {
+    let mut declarations = Vec::new();
+    let mut current_depth = 0i32;
+    for (idx, line) in lines.iter().enumerate() {
         let trimmed = line.trim_start();
-        if (trimmed.contains("public ") || trimmed.contains("external ")) && trimmed.contains("function ") {
-            if let Some(func_part) = trimmed.split("function ").nth(1) {
-                if let Some(name) = func_part.split('(').next() {
-                    functions.push(name.trim().to_string());
-                }
+        if current_depth == depth && trimmed.starts_with(keyword) {
+            let text = function_header(lines, idx);
+            let rest = text.trim_start()[keyword.len()..].trim_start();
+            if let Some((name, params)) = rest.split_once('(') {
+                let params = params.rsplit_once(')').map_or(params, |(params, _)| params);
+                let types = params
+                    .split(',')
+                    .filter_map(|param| param.split_whitespace().next())
+                    .map(String::from)
+                    .collect();
+                declarations.push((name.trim().to_string(), types));
             }
         }
+        current_depth += line.matches('{').count() as i32 - line.matches('}').count() as i32;
     }
-    functions
+    declarations
+}
+
+/// Modifier names in a function header, skipping visibility, mutability and
+/// `returns`/`override` clauses.
+fn function_modifiers(header: &str) -> Vec<String> {
+    const KEYWORDS: [&str; 10] = [
+        "public", "external", "internal", "private", "view", "pure", "payable", "virtual",
+        "override", "returns",
+    ];
+    // Everything after the parameter list
+    let Some(params_start) = header.find('(') else {
+        return Vec::new();
+    };
+    let mut depth = 0;
+    let mut rest = "";
+    for (idx, c) in header[params_start..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            rest = &header[params_start + idx + 1..];
+            break;
+        }
+    }
+
+    let mut modifiers = Vec::new();
+    let mut depth = 0;
+    let mut word = String::new();
+    for c in rest.chars().chain([' ']) {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if depth == 0 && (c.is_alphanumeric() || c == '_') => {
+                word.push(c);
+                continue;
+            }
+            _ => {}
+        }
+        if !word.is_empty() && !KEYWORDS.contains(&word.as_str()) {
+            modifiers.push(word.clone());
+        }
+        word.clear();
+    }
+    modifiers
 }
 
-/// Extract state variable names from Solidity source code.
-fn extract_state_variables(source: &str) -> Vec<String> {
+/// Index of the line closing the body that opens at or after `start`.
+fn function_end(lines: &[&str], start: usize) -> usize {
+    let mut depth = 0i32;
+    let mut opened = false;
+    for (idx, line) in lines.iter().enumerate().skip(start) {
+        opened |= line.contains('{');
+        depth += line.matches('{').count() as i32 - line.matches('}').count() as i32;
+        if (opened && depth <= 0) || (!opened && line.contains(';')) {
+            return idx;
+        }
+    }
+    lines.len().saturating_sub(1).max(start)
+}
+
+/// Extract state variables declared directly in `contract`.
+fn extract_state_variables(lines: &[&str], contract: &Contract) -> Vec<StateVar> {
     let mut variables = Vec::new();
-    for line in source.lines() {
+    let mut depth = 0i32;
+    for line in lines.iter().take(contract.end + 1).skip(contract.header) {
         let trimmed = line.trim_start();
         // Match state variable declarations (e.g., "uint256 public balance;")
-        if is_state_variable_declaration(trimmed) {
-            if let Some(var_name) = extract_variable_name(trimmed) {
-                variables.push(var_name);
+        if depth == 1 && is_state_variable_declaration(trimmed) {
+            if let Some(var) = parse_state_variable(trimmed) {
+                variables.push(var);
             }
         }
+        depth += line.matches('{').count() as i32 - line.matches('}').count() as i32;
     }
     variables
 }
 
 /// Determine if a line is a state variable declaration.
 fn is_state_variable_declaration(line: &str) -> bool {
-    let types = ["uint", "int", "address", "bool", "bytes", "string", "mapping"];
+    let types = [
+        "uint", "int", "address", "bool", "bytes", "string", "mapping",
+    ];
     types.iter().any(|t| line.starts_with(t)) && !line.contains("function")
 }
 
-/// Extract variable name from declaration (e.g., "uint256 public balance;" → "balance").
-fn extract_variable_name(line: &str) -> Option<String> {
-    let name_part = line.split_whitespace()
-        .skip_while(|w| w.starts_with("uint") || w.starts_with("int") || 
-                        w == &"public" || w == &"private" || w == &"mapping" ||
-                        w == &"address" || w == &"bool" || w == &"bytes" || w == &"string")
-        .next()?;
-    let name = name_part.split(|c: char| c == ';' || c == '=' || c == '(' || c == '[')
-        .next()?
-        .trim();
-    if name.is_empty() {
-        None
-    } else {
-        Some(name.to_string())
+/// Parse a declaration (e.g., "mapping(address => uint256) public balances;").
+fn parse_state_variable(line: &str) -> Option<StateVar> {
+    let declaration = line.split(';').next()?;
+    // Split off the initializer, but not at the `=>` of a mapping
+    let (declaration, initializer) = match declaration
+        .match_indices('=')
+        .find(|(idx, _)| !declaration[idx + 1..].starts_with('>'))
+    {
+        Some((idx, _)) => (&declaration[..idx], &declaration[idx + 1..]),
+        None => (declaration, ""),
+    };
+    let tokens: Vec<&str> = declaration.split_whitespace().collect();
+    let (name, qualifiers) = tokens.split_last()?;
+    let name = name.split(['(', '[']).next()?.trim();
+    if name.is_empty() || qualifiers.is_empty() {
+        return None;
+    }
+
+    let is_qualifier =
+        |token: &&str| ["public", "private", "internal", "constant", "immutable"].contains(token);
+    let type_end = qualifiers
+        .iter()
+        .position(is_qualifier)
+        .unwrap_or(qualifiers.len());
+    let visibility = qualifiers
+        .iter()
+        .find(|token| ["public", "private", "internal"].contains(*token))
+        .map(|token| token.to_string());
+    // Every identifier for now; narrowed to state variables once all are known
+    let mut depends_on: Vec<String> = Vec::new();
+    for word in initializer.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        let identifier = word.starts_with(|c: char| c.is_alphabetic() || c == '_');
+        if identifier && word != name && !depends_on.iter().any(|dep| dep == word) {
+            depends_on.push(word.to_string());
+        }
+    }
+    Some(StateVar {
+        name: name.to_string(),
+        type_name: qualifiers[..type_end].join(" "),
+        is_mutable: !qualifiers
+            .iter()
+            .any(|token| ["constant", "immutable"].contains(token)),
+        visibility,
+        abilities: BTreeSet::new(),
+        depends_on,
+    })
+}
+
+/// State variables assigned, incremented or deleted in `body`.
+fn state_writes(body: &[&str], state_vars: &BTreeMap<String, StateVar>) -> BTreeSet<String> {
+    body.iter()
+        .map(|line| line.split("//").next().unwrap_or_default())
+        .flat_map(|code| {
+            state_vars
+                .keys()
+                .filter(move |name| writes_variable(code, name))
+                .cloned()
+        })
+        .collect()
+}
+
+/// Whether `code` writes `name`, directly or through an index:
+/// `name = ..`, `name[k] += ..`, `name++`, `--name`, `delete name[k]`.
+fn writes_variable(code: &str, name: &str) -> bool {
+    const ASSIGNMENTS: [&str; 11] = [
+        "+=", "-=", "*=", "/=", "%=", "|=", "&=", "^=", "<<=", ">>=", "=",
+    ];
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    code.match_indices(name).any(|(idx, _)| {
+        let before = &code[..idx];
+        let after = &code[idx + name.len()..];
+        if before.ends_with(|c: char| is_ident(c) || c == '.') || after.starts_with(is_ident) {
+            return false;
+        }
+        let before = before.trim_end();
+        if before.ends_with("delete") || before.ends_with("++") || before.ends_with("--") {
+            return true;
+        }
+        // Skip index expressions: `balances[from][to]`
+        let mut rest = after.trim_start();
+        while let Some(inner) = rest.strip_prefix('[') {
+            let mut depth = 1;
+            let Some(close) = inner.find(|c| {
+                match c {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            }) else {
+                return false;
+            };
+            rest = inner[close + 1..].trim_start();
+        }
+        rest.starts_with("++")
+            || rest.starts_with("--")
+            || ASSIGNMENTS.iter().any(|op| {
+                rest.strip_prefix(op)
+                    .is_some_and(|tail| !tail.starts_with(['=', '>']))
+            })
+    })
+}
+
+/// Inline assembly and low-level calls in `body`, whose state writes the
+/// analyzer cannot see, with their 1-based lines (`body` starts at `first_line`).
+fn low_level_uses(body: &[&str], first_line: usize) -> Vec<(&'static str, usize)> {
+    const CALLS: [&str; 3] = ["delegatecall", "staticcall", "selfdestruct"];
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut uses = Vec::new();
+    for (offset, line) in body.iter().enumerate() {
+        let code = line.split("//").next().unwrap_or_default();
+        // `name` as a whole word followed by one of `next`
+        let word_before = |name: &str, next: &[char]| {
+            code.match_indices(name).any(|(idx, _)| {
+                !code[..idx].ends_with(is_ident)
+                    && code[idx + name.len()..].trim_start().starts_with(next)
+            })
+        };
+        if word_before("assembly", &['{', '(']) {
+            uses.push(("inline assembly", first_line + offset));
+        }
+        for call in CALLS {
+            if word_before(call, &['(', '{']) {
+                uses.push((call, first_line + offset));
+            }
+        }
+    }
+    uses
+}
+
+/// Whether the `pragma solidity` constraint admits compilers before 0.8,
+/// whose arithmetic wraps silently.
+fn legacy_arithmetic(lines: &[&str]) -> bool {
+    let Some(pragma) = lines
+        .iter()
+        .find_map(|line| line.trim_start().strip_prefix("pragma solidity"))
+    else {
+        return false;
+    };
+    let Some((_, version)) = pragma.split_once("0.") else {
+        return false;
+    };
+    let minor: String = version.chars().take_while(char::is_ascii_digit).collect();
+    minor.parse::<u32>().is_ok_and(|minor| minor < 8)
+}
+
+/// Updates of the state variables `integers` in `contract` that can wrap:
+/// every update under `legacy` arithmetic, otherwise those inside
+/// `unchecked { }` blocks.
+fn unchecked_updates(
+    lines: &[&str],
+    contract: &Contract,
+    integers: &BTreeSet<String>,
+    legacy: bool,
+) -> Vec<UncheckedArithmetic> {
+    let mut updates = Vec::new();
+    // Whether each open block is unchecked
+    let mut blocks: Vec<bool> = Vec::new();
+    // Enclosing function and the depth of its body
+    let mut function = (String::new(), 0);
+    let mut statement = String::new();
+    let mut statement_line = 0;
+    let last = contract.end.min(lines.len().saturating_sub(1));
+    for (idx, line) in lines
+        .iter()
+        .enumerate()
+        .take(last + 1)
+        .skip(contract.header)
+    {
+        let code = line.split("//").next().unwrap_or_default();
+        for c in code.chars() {
+            if !matches!(c, '{' | '}' | ';') {
+                if statement.trim().is_empty() && !c.is_whitespace() {
+                    statement_line = idx + 1;
+                }
+                statement.push(c);
+                continue;
+            }
+            let unchecked = legacy || blocks.last().copied().unwrap_or(false);
+            if unchecked {
+                if let Some((target, op)) = arithmetic_update(statement.trim(), integers) {
+                    updates.push(UncheckedArithmetic {
+                        function_name: function.0.clone(),
+                        target,
+                        op,
+                        line: statement_line,
+                    });
+                }
+            }
+            match c {
+                '{' => {
+                    if let Some(name) = block_function(&statement) {
+                        function = (name.to_string(), blocks.len());
+                    }
+                    blocks.push(unchecked || statement.trim() == "unchecked");
+                }
+                '}' => {
+                    blocks.pop();
+                    if blocks.len() == function.1 {
+                        function.0.clear();
+                    }
+                }
+                _ => {}
+            }
+            statement.clear();
+        }
+        statement.push(' ');
+    }
+    updates
+}
+
+/// The function whose body `statement`, the text before a `{`, opens.
+fn block_function(statement: &str) -> Option<&str> {
+    let statement = statement.trim();
+    let (name, _) = statement
+        .strip_prefix("function ")
+        .unwrap_or(statement)
+        .split_once('(')?;
+    let name = name.trim();
+    (statement.starts_with("function ") || ["constructor", "fallback", "receive"].contains(&name))
+        .then_some(name)
+}
+
+/// The updated variable and operator of `x += e`, `x = x + e`, `x++` and the
+/// `-`/`*` equivalents, when `x` is one of `integers`.
+fn arithmetic_update(
+    statement: &str,
+    integers: &BTreeSet<String>,
+) -> Option<(String, ArithmeticOp)> {
+    let target = |name: &str| integers.get(name.trim()).cloned();
+    for (step, op) in [("++", ArithmeticOp::Add), ("--", ArithmeticOp::Sub)] {
+        if let Some(name) = statement
+            .strip_suffix(step)
+            .or_else(|| statement.strip_prefix(step))
+        {
+            return target(name).map(|name| (name, op));
+        }
+    }
+    for (assign, op) in [
+        ("+=", ArithmeticOp::Add),
+        ("-=", ArithmeticOp::Sub),
+        ("*=", ArithmeticOp::Mul),
+    ] {
+        if let Some((lhs, _)) = statement.split_once(assign) {
+            return target(lhs).map(|name| (name, op));
+        }
+    }
+    let (lhs, rhs) = statement.split_once('=')?;
+    let name = target(lhs)?;
+    [
+        ('+', ArithmeticOp::Add),
+        ('-', ArithmeticOp::Sub),
+        ('*', ArithmeticOp::Mul),
+    ]
+    .into_iter()
+    .find(|(symbol, _)| {
+        rhs.split_once(*symbol)
+            .is_some_and(|(first, _)| first.trim() == name)
+    })
+    .map(|(_, op)| (name, op))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_function_spans() {
+        let source = "contract Vault {\n    function deposit() public {\n        if (x) {\n        }\n    }\n\n    function withdraw(uint256 amount) external {\n    }\n}\n";
+        let lines: Vec<&str> = source.lines().collect();
+        let contracts = extract_contracts(&lines);
+        let spans: Vec<_> = extract_public_functions(&lines, &contracts[0])
+            .into_iter()
+            .map(|function| (function.name, function.span))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("deposit".to_string(), (2, 5)),
+                ("withdraw".to_string(), (7, 8)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_low_level_uses() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Proxy.sol");
+        std::fs::write(
+            &path,
+            "contract Proxy {\n    address public implementation;\n\n    fallback() external payable {\n        assembly {\n            calldatacopy(0, 0, calldatasize())\n            let ok := delegatecall(gas(), sload(0), 0, calldatasize(), 0, 0)\n        }\n    }\n\n    function kill() external {\n        selfdestruct(payable(msg.sender));\n    }\n\n    // assembly { } in a comment is ignored\n    function upgrade(address next) external {\n        implementation = next;\n    }\n}\n",
+        )
+        .unwrap();
+
+        let program = EvmAnalyzer.analyze(&path).unwrap();
+        let found: Vec<(&str, &str, usize)> = program
+            .uncertainties
+            .iter()
+            .map(|u| (u.function_name.as_str(), u.construct.as_str(), u.line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("fallback", "inline assembly", 5),
+                ("fallback", "delegatecall", 7),
+                ("kill", "selfdestruct", 12),
+            ]
+        );
+        assert!(program.functions["fallback"].uses_low_level);
+        assert!(program.functions["kill"].uses_low_level);
+        assert!(!program.functions["upgrade"].uses_low_level);
+    }
+
+    #[test]
+    fn test_function_modifiers() {
+        assert_eq!(
+            function_modifiers(
+                " function transfer(address to, uint256 amount) external override(IERC20) nonReentrant onlyRole(MINTER) returns (bool)"
+            ),
+            vec!["nonReentrant".to_string(), "onlyRole".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_inherited_state_and_modifiers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Token.sol");
+        std::fs::write(
+            &path,
+            "contract Ownable {\n    address public owner;\n\n    modifier onlyOwner() {\n        require(msg.sender == owner);\n        _;\n    }\n\n    function transferOwnership(address newOwner)\n        public\n        onlyOwner\n    {\n        owner = newOwner;\n    }\n}\n\ncontract Token is ERC20, Ownable {\n    mapping(address => uint256) public balances;\n\n    function mint(address to, uint256 amount) external onlyOwner {\n        uint256 scaled = amount;\n        balances[to] += scaled;\n    }\n}\n",
+        )
+        .unwrap();
+
+        let program = EvmAnalyzer.analyze(&path).unwrap();
+        assert_eq!(program.name, "Token");
+        assert_eq!(
+            program.state_vars.keys().collect::<Vec<_>>(),
+            vec!["balances", "owner"]
+        );
+        assert_eq!(program.state_vars["owner"].type_name, "address");
+        assert_eq!(
+            program.state_vars["balances"].type_name,
+            "mapping(address => uint256)"
+        );
+
+        let transfer = &program.functions["transferOwnership"];
+        assert_eq!(transfer.modifiers, vec!["onlyOwner".to_string()]);
+        assert_eq!(transfer.span, Some((9, 14)));
+        assert_eq!(
+            program.functions["mint"].modifiers,
+            vec!["onlyOwner".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_state_variable_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Vault.sol");
+        std::fs::write(
+            &path,
+            "contract Vault {\n    uint256 public constant BASE = 1e18;\n    uint256 public totalAssets = BASE;\n    uint256 public cap = totalAssets * 2 + block.number;\n    mapping(address => uint256) public shares;\n}\n",
+        )
+        .unwrap();
+
+        let program = EvmAnalyzer.analyze(&path).unwrap();
+        assert!(program.state_vars["BASE"].depends_on.is_empty());
+        assert_eq!(program.state_vars["totalAssets"].depends_on, vec!["BASE"]);
+        assert_eq!(program.state_vars["cap"].depends_on, vec!["totalAssets"]);
+        assert!(program.state_vars["shares"].depends_on.is_empty());
+    }
+
+    #[test]
+    fn test_parent_resolved_from_sibling_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Ownable.sol"),
+            "abstract contract Ownable {\n    address public owner;\n    function renounceOwnership() public virtual onlyOwner {\n    }\n}\n",
+        )
+        .unwrap();
+        let path = dir.path().join("Vault.sol");
+        std::fs::write(
+            &path,
+            "contract Vault is Ownable {\n    uint256 public total;\n}\n",
+        )
+        .unwrap();
+
+        let program = EvmAnalyzer.analyze(&path).unwrap();
+        assert!(program.state_vars.contains_key("owner"));
+        // Spans refer to the analyzed file only
+        assert_eq!(program.functions["renounceOwnership"].span, None);
+    }
+
+    #[test]
+    fn test_extract_events_and_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Vault.sol");
+        std::fs::write(
+            &path,
+            "error Unauthorized();\n\ncontract Vault {\n    event Deposit(address indexed from, uint256 amount);\n    error InvariantViolated(uint256 code);\n\n    function deposit() public {\n        emit Deposit(msg.sender, msg.value);\n    }\n}\n",
+        )
+        .unwrap();
+
+        let program = EvmAnalyzer.analyze(&path).unwrap();
+        assert_eq!(
+            program.events["Deposit"],
+            vec!["address".to_string(), "uint256".to_string()]
+        );
+        assert_eq!(
+            program.errors["InvariantViolated"],
+            vec!["uint256".to_string()]
+        );
+        assert!(program.errors["Unauthorized"].is_empty());
+    }
+
+    #[test]
+    fn test_contract_invariants_from_natspec() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Base.sol"),
+            "/**\n * @invariant owner != address(0)\n */\nabstract contract Base {\n    address public owner;\n}\n",
+        )
+        .unwrap();
+        let path = dir.path().join("Token.sol");
+        std::fs::write(
+            &path,
+            "// SPDX-License-Identifier: MIT\n\n/// @title Token\n/// @invariant: totalSupply > 0\ncontract Token is Base {\n    uint256 public totalSupply;\n\n    /// @invariant: ignored on functions\n    function mint(uint256 amount) external {\n        totalSupply += amount;\n    }\n\n    function supply() external view returns (uint256) {\n        return totalSupply;\n    }\n}\n",
+        )
+        .unwrap();
+
+        let program = EvmAnalyzer.analyze(&path).unwrap();
+        assert_eq!(
+            program.contract_invariants,
+            vec![
+                "owner != address(0)".to_string(),
+                "totalSupply > 0".to_string()
+            ]
+        );
+        assert!(!program.functions["mint"].is_pure);
+        assert!(program.functions["supply"].is_pure);
+    }
+
+    #[test]
+    fn test_functions_record_state_writes() {
+        let source = r#"contract Vault {
+    address public owner;
+    uint256 public total;
+    mapping(address => uint256) public balances;
+
+    function deposit(uint256 amount) external {
+        balances[msg.sender] += amount;
+        total++;
+    }
+
+    function setOwner(address newOwner) external {
+        require(owner == msg.sender);
+        owner = newOwner;
+    }
+
+    function balanceOf(address who) external view returns (uint256) {
+        return balances[who];
+    }
+}
+"#;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Vault.sol");
+        std::fs::write(&path, source).unwrap();
+        let program = EvmAnalyzer.analyze(&path).unwrap();
+        let mutates = |name: &str| {
+            program.functions[name]
+                .mutates
+                .iter()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(mutates("deposit"), vec!["balances", "total"]);
+        assert_eq!(mutates("setOwner"), vec!["owner"]);
+        assert!(mutates("balanceOf").is_empty());
+    }
+
+    #[test]
+    fn test_unchecked_updates() {
+        let source = r#"pragma solidity ^0.8.20;
+
+contract Counter {
+    uint256 public total;
+    uint256 public debt;
+    address public owner;
+
+    function bump(uint256 amount) external {
+        total += amount;
+        unchecked {
+            total = total + amount;
+            debt--;
+        }
+        unchecked { debt *= 2; }
+    }
+}
+"#;
+        let lines: Vec<&str> = source.lines().collect();
+        let contracts = extract_contracts(&lines);
+        let integers = BTreeSet::from(["total".to_string(), "debt".to_string()]);
+        let updates = unchecked_updates(&lines, &contracts[0], &integers, false);
+        assert_eq!(
+            updates,
+            vec![
+                UncheckedArithmetic {
+                    function_name: "bump".to_string(),
+                    target: "total".to_string(),
+                    op: ArithmeticOp::Add,
+                    line: 11,
+                },
+                UncheckedArithmetic {
+                    function_name: "bump".to_string(),
+                    target: "debt".to_string(),
+                    op: ArithmeticOp::Sub,
+                    line: 12,
+                },
+                UncheckedArithmetic {
+                    function_name: "bump".to_string(),
+                    target: "debt".to_string(),
+                    op: ArithmeticOp::Mul,
+                    line: 14,
+                },
+            ]
+        );
+
+        // Before 0.8 every update wraps
+        assert!(!legacy_arithmetic(&lines));
+        assert!(legacy_arithmetic(&["pragma solidity >=0.6.0 <0.9.0;"]));
+        assert_eq!(
+            unchecked_updates(&lines, &contracts[0], &integers, true).len(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_supported_extensions() {
+        assert_eq!(EvmAnalyzer.supported_extensions(), &[".sol"]);
     }
 }
@@ -1,6 +1,6 @@
 //! Move analyzer implementation.
 
-use invar_core::model::{ProgramModel, FunctionModel};
+use invar_core::model::{FunctionModel, OverflowProtection, ProgramModel, StateVar};
 use invar_core::traits::ChainAnalyzer;
 use invar_core::Result;
 use std::path::Path;
@@ -24,6 +24,9 @@ impl ChainAnalyzer for MoveAnalyzer {
         let functions = extract_public_functions(&source);
         info!("Found {} public functions in Move module", functions.len());
 
+        let contract_invariants = extract_spec_invariants(&source);
+        info!("Found {} module invariants", contract_invariants.len());
+
         let structs = extract_resource_types(&source);
         info!("Found {} resource types", structs.len());
 
@@ -33,17 +36,42 @@ impl ChainAnalyzer for MoveAnalyzer {
             "move".to_string(),
             path.to_string_lossy().to_string(),
         );
+        program.contract_invariants = contract_invariants;
+
+        // Resources become state variables typed by their struct
+        for (name, abilities) in structs {
+            program.add_state_var(StateVar {
+                abilities: abilities
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|ability| !ability.is_empty())
+                    .map(String::from)
+                    .collect(),
+                type_name: name.clone(),
+                name,
+                is_mutable: true,
+                visibility: None,
+                depends_on: Vec::new(),
+            });
+        }
 
         // Add extracted functions to the program model
-        for func_name in functions {
+        for extracted in functions {
             let func = FunctionModel {
-                name: func_name,
-                parameters: Vec::new(),
+                name: extracted.name,
+                parameters: extracted.parameters,
                 return_type: None,
-                mutates: BTreeSet::new(),
-                reads: BTreeSet::new(),
-                is_entry_point: true,
+                mutates: extracted.mutates,
+                reads: extracted.reads,
+                is_entry_point: extracted.is_entry,
                 is_pure: false,
+                overflow_protection: OverflowProtection::Unknown,
+                span: Some(extracted.span),
+                modifiers: Vec::new(),
+                uses_low_level: false,
+                pre_conditions: Vec::new(),
+                post_conditions: Vec::new(),
             };
             program.add_function(func);
         }
@@ -54,6 +82,10 @@ impl ChainAnalyzer for MoveAnalyzer {
     fn chain(&self) -> &str {
         "move"
     }
+
+    fn supported_extensions(&self) -> &'static [&'static str] {
+        &[".move"]
+    }
 }
 
 /// Extract module name from Move source code.
@@ -61,7 +93,7 @@ fn extract_module_name(source: &str) -> Option<String> {
     for line in source.lines() {
         if line.trim_start().starts_with("module ") {
             let module_part = line.split("module ").nth(1)?;
-            let name = module_part.split(|c: char| c == ':' || c == '{' || c == ';')
+            let name = module_part.split([':', '{', ';'])
                 .next()?
                 .trim();
             return Some(name.to_string());
@@ -70,24 +102,176 @@ fn extract_module_name(source: &str) -> Option<String> {
     None
 }
 
-/// Extract public function names from Move source code.
-fn extract_public_functions(source: &str) -> Vec<String> {
+/// Invariants declared in module-level `spec` blocks
+/// (`spec module { invariant <expr>; }` or `spec <module> { ... }`).
+fn extract_spec_invariants(source: &str) -> Vec<String> {
+    // `module 0x1::coin` can be specified as `spec coin`
+    let module = source.lines().find_map(|line| {
+        let path = line.trim_start().strip_prefix("module ")?;
+        let path = path.split(['{', ';']).next()?.trim();
+        Some(path.rsplit("::").next().unwrap_or(path).to_string())
+    });
+
+    let mut invariants = Vec::new();
+    let mut rest = source;
+    while let Some(at) = rest.find("spec ") {
+        let spec = &rest[at + "spec ".len()..];
+        rest = spec;
+        let Some((target, body)) = spec.split_once('{') else {
+            break;
+        };
+        let target = target.trim();
+        if target != "module" && Some(target) != module.as_deref() {
+            continue;
+        }
+        // The block ends at its matching brace
+        let mut depth = 1;
+        let end = body
+            .char_indices()
+            .find(|&(_, c)| {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            })
+            .map_or(body.len(), |(idx, _)| idx);
+        invariants.extend(
+            body[..end]
+                .split(';')
+                .filter_map(|statement| statement.trim().strip_prefix("invariant "))
+                .map(|expr| expr.split_whitespace().collect::<Vec<_>>().join(" ")),
+        );
+        rest = &body[end..];
+    }
+    invariants
+}
+
+/// A public or entry function extracted from Move source.
+struct MoveFunction {
+    name: String,
+    /// Parameters as written (`name: Type`).
+    parameters: Vec<String>,
+    is_entry: bool,
+    /// Resource types mutated through `borrow_global_mut`, `move_to` or `move_from`.
+    mutates: BTreeSet<String>,
+    /// Resource types named in the `acquires` clause or read with `borrow_global`.
+    reads: BTreeSet<String>,
+    /// 1-based inclusive line range.
+    span: (usize, usize),
+}
+
+/// Extract public and entry functions (including `public(friend)`) from Move source code.
+fn extract_public_functions(source: &str) -> Vec<MoveFunction> {
+    let lines: Vec<&str> = source.lines().collect();
     let mut functions = Vec::new();
-    for line in source.lines() {
+    for (start, line) in lines.iter().enumerate() {
         let trimmed = line.trim_start();
-        if trimmed.starts_with("public fun ") {
-            if let Some(func_part) = trimmed.split("public fun ").nth(1) {
-                if let Some(name) = func_part.split('(').next() {
-                    functions.push(name.trim().to_string());
-                }
+        let Some((qualifiers, _)) = trimmed.split_once("fun ") else {
+            continue;
+        };
+        if !(qualifiers.starts_with("public") || qualifiers.starts_with("entry")) {
+            continue;
+        }
+
+        // The signature may span several lines
+        let mut header = String::new();
+        for header_line in &lines[start..] {
+            let end = header_line.find(['{', ';']);
+            header.push(' ');
+            header.push_str(header_line[..end.unwrap_or(header_line.len())].trim());
+            if end.is_some() {
+                break;
             }
         }
+        let Some((_, func_part)) = header.split_once("fun ") else {
+            continue;
+        };
+        let Some(name) = func_part.split(['(', '<']).next() else {
+            continue;
+        };
+        let (parameters, rest) = match func_part.split_once('(') {
+            Some((_, rest)) => split_parameters(rest),
+            None => (Vec::new(), ""),
+        };
+
+        let mut reads: BTreeSet<String> = rest
+            .split_once("acquires ")
+            .map(|(_, acquired)| {
+                acquired
+                    .split(',')
+                    .map(|resource| resource.trim().to_string())
+                    .filter(|resource| !resource.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut mutates = BTreeSet::new();
+        let mut depth = 0i32;
+        let mut end = start;
+        for (idx, body_line) in lines.iter().enumerate().skip(start) {
+            end = idx;
+            for op in ["borrow_global_mut<", "move_from<", "move_to<"] {
+                mutates.extend(type_arguments(body_line, op));
+            }
+            reads.extend(type_arguments(body_line, "borrow_global<"));
+            depth += body_line.matches('{').count() as i32 - body_line.matches('}').count() as i32;
+            if depth <= 0 && body_line.contains('}') {
+                break;
+            }
+        }
+
+        functions.push(MoveFunction {
+            name: name.trim().to_string(),
+            parameters,
+            is_entry: qualifiers.contains("entry"),
+            mutates,
+            reads,
+            span: (start + 1, end + 1),
+        });
     }
     functions
 }
 
-/// Extract resource type names from Move source code.
-fn extract_resource_types(source: &str) -> Vec<String> {
+/// Type argument of every `op<T>` occurrence in `line` (`op` includes the `<`).
+fn type_arguments<'a>(line: &'a str, op: &'a str) -> impl Iterator<Item = String> + 'a {
+    line.match_indices(op)
+        .filter_map(move |(at, _)| line[at + op.len()..].split('>').next())
+        .map(|resource| resource.trim().to_string())
+}
+
+/// Split a parameter list (the text after its opening `(`) into parameters,
+/// returning them with the rest of the signature.
+///
+/// Commas inside type arguments (`Table<K, V>`) do not split.
+fn split_parameters(text: &str) -> (Vec<String>, &str) {
+    let mut parameters = Vec::new();
+    let mut depth = 0i32;
+    let mut current = String::new();
+    for (idx, c) in text.char_indices() {
+        match c {
+            '(' | '<' => depth += 1,
+            '>' => depth -= 1,
+            ')' if depth == 0 => {
+                parameters.extend(Some(current.trim().to_string()).filter(|p| !p.is_empty()));
+                return (parameters, &text[idx + 1..]);
+            }
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parameters.extend(Some(current.trim().to_string()).filter(|p| !p.is_empty()));
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    (parameters, "")
+}
+
+/// Extract resource type names and their `has` abilities from Move source code.
+fn extract_resource_types(source: &str) -> Vec<(String, Option<String>)> {
     let mut resources = Vec::new();
     for line in source.lines() {
         let trimmed = line.trim_start();
@@ -98,12 +282,191 @@ fn extract_resource_types(source: &str) -> Vec<String> {
                 "struct "
             };
             if let Some(struct_part) = trimmed.split(key).nth(1) {
-                if let Some(name) = struct_part.split(|c: char| c == '{' || c == '(' || c == '<')
+                if let Some(name) = struct_part.split(['{', '(', '<'])
                     .next() {
-                    resources.push(name.trim().to_string());
+                    let abilities = struct_part
+                        .split_once(" has ")
+                        .map(|(_, rest)| rest.split('{').next().unwrap_or("").trim().to_string());
+                    let name = name.split(" has ").next().unwrap_or(name);
+                    resources.push((name.trim().to_string(), abilities));
                 }
             }
         }
     }
     resources
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_entry_functions() {
+        let source = r#"module vault::bank {
+    struct Vault has key { balance: u64 }
+
+    public entry fun drain(owner: address) acquires Vault {
+        let vault = borrow_global_mut<Vault>(owner);
+        vault.balance = 0;
+    }
+
+    public fun balance(owner: address): u64 acquires Vault {
+        borrow_global<Vault>(owner).balance
+    }
+
+    fun helper() {}
+}
+"#;
+        let functions = extract_public_functions(source);
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].name, "drain");
+        assert!(functions[0].is_entry);
+        assert_eq!(functions[0].parameters, vec!["owner: address".to_string()]);
+        assert!(functions[0].mutates.contains("Vault"));
+        assert_eq!(functions[0].span, (4, 7));
+        assert!(!functions[1].is_entry);
+        assert!(functions[1].mutates.is_empty());
+
+        let resources = extract_resource_types(source);
+        assert_eq!(
+            resources,
+            vec![("Vault".to_string(), Some("key".to_string()))]
+        );
+    }
+
+    #[test]
+    fn test_coin_module_metadata() {
+        let source = r#"module coin::managed {
+    struct Supply has key { total: u64 }
+    struct Coin<phantom T> has store, drop { value: u64 }
+
+    public entry fun mint(
+        admin: &signer,
+        amount: u64,
+    ) acquires Supply {
+        let supply = borrow_global_mut<Supply>(@coin);
+        supply.total = supply.total + amount;
+    }
+
+    public fun total(): u64 acquires Supply {
+        borrow_global<Supply>(@coin).total
+    }
+
+    entry fun register(account: &signer, balances: Table<address, u64>) {}
+}
+"#;
+        let functions = extract_public_functions(source);
+        let names: Vec<_> = functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["mint", "total", "register"]);
+
+        let mint = &functions[0];
+        assert!(mint.is_entry);
+        assert!(mint.reads.contains("Supply"));
+        assert!(mint.mutates.contains("Supply"));
+        assert_eq!(
+            mint.parameters,
+            vec!["admin: &signer".to_string(), "amount: u64".to_string()]
+        );
+        assert_eq!(mint.span, (5, 11));
+
+        assert!(!functions[1].is_entry);
+        assert!(functions[1].mutates.is_empty());
+        assert!(functions[2].is_entry);
+        assert_eq!(
+            functions[2].parameters[1],
+            "balances: Table<address, u64>".to_string()
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("managed.move");
+        std::fs::write(&path, source).unwrap();
+        let program = MoveAnalyzer.analyze(&path).unwrap();
+        let abilities: Vec<_> = program.state_vars["Coin"]
+            .abilities
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(abilities, vec!["drop".to_string(), "store".to_string()]);
+        assert_eq!(program.state_vars["Coin"].type_name, "Coin");
+        assert!(program.functions["mint"].is_entry_point);
+        assert!(program.functions["mint"].reads.contains("Supply"));
+    }
+
+    #[test]
+    fn test_move_rules_read_the_analyzed_functions() {
+        let source = r#"module vault::bank {
+    struct Vault has key { balance: u64 }
+
+    public entry fun deposit(account: &signer, amount: u64) acquires Vault {
+        let vault = borrow_global_mut<Vault>(signer::address_of(account));
+        vault.balance = vault.balance + amount;
+    }
+
+    public entry fun drain(owner: address) acquires Vault {
+        let vault = borrow_global_mut<Vault>(owner);
+        vault.balance = 0;
+    }
+}
+"#;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bank.move");
+        std::fs::write(&path, source).unwrap();
+        let program = MoveAnalyzer.analyze(&path).unwrap();
+        let report = invar_core::SecurityValidator::new()
+            .validate_program(&program, source)
+            .unwrap();
+
+        let signer_issues: Vec<_> = report
+            .critical_issues
+            .iter()
+            .filter(|issue| issue.attack_pattern == "Missing Signer Check")
+            .collect();
+        assert_eq!(signer_issues.len(), 1);
+        assert_eq!(signer_issues[0].location.line, 10);
+        assert_eq!(signer_issues[0].function.as_deref(), Some("drain"));
+
+        assert!(report
+            .high_issues
+            .iter()
+            .any(|issue| issue.attack_pattern == "Arbitrary Global Borrow"));
+        assert!(report
+            .medium_issues
+            .iter()
+            .any(|issue| issue.location.line == 6));
+    }
+
+    #[test]
+    fn test_module_spec_invariants() {
+        let source = r#"module 0x1::coin {
+    struct Supply has key { total: u64 }
+
+    spec coin {
+        invariant exists<Supply>(@0x1);
+        invariant global<Supply>(@0x1).total
+            <= MAX_SUPPLY;
+    }
+
+    spec Supply {
+        invariant total >= 0;
+    }
+
+    spec module {
+        invariant true;
+    }
+}
+"#;
+        assert_eq!(
+            extract_spec_invariants(source),
+            vec![
+                "exists<Supply>(@0x1)".to_string(),
+                "global<Supply>(@0x1).total <= MAX_SUPPLY".to_string(),
+                "true".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_supported_extensions() {
+        assert_eq!(MoveAnalyzer.supported_extensions(), &[".move"]);
+    }
+}
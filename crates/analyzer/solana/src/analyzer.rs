@@ -1,9 +1,15 @@
 //! Solana analyzer implementation.
 
-use invar_core::model::{FunctionModel, ProgramModel, StateVar};
+use invar_core::model::{
+    ArithmeticOp, AuthorityAccount, CpiCall, Expression, FunctionModel, OverflowProtection,
+    PdaPattern, PdaSeedSource, ProgramModel, StateVar, UncheckedArithmetic,
+};
 use invar_core::traits::ChainAnalyzer;
 use invar_core::Result;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
+use syn::spanned::Spanned;
+use syn::visit::Visit;
 use tracing::{debug, info};
 
 /// Analyzer for Solana Rust programs.
@@ -38,6 +44,8 @@ impl ChainAnalyzer for SolanaAnalyzer {
                     type_name: "struct".to_string(),
                     is_mutable: false,
                     visibility: None,
+                    abilities: BTreeSet::new(),
+                    depends_on: Vec::new(),
                 };
                 program.add_state_var(state_var);
             }
@@ -52,6 +60,8 @@ impl ChainAnalyzer for SolanaAnalyzer {
                     .iter()
                     .any(|attr| attr.path().is_ident("solana_program::entrypoint"));
 
+                let mut writes = FieldWrites::default();
+                writes.visit_block(&item_fn.block);
                 let func = FunctionModel {
                     name: func_name,
                     parameters: item_fn
@@ -61,15 +71,40 @@ impl ChainAnalyzer for SolanaAnalyzer {
                         .map(|_inp| "param".to_string())
                         .collect(),
                     return_type: None,
-                    mutates: Default::default(),
+                    mutates: writes.0,
                     reads: Default::default(),
                     is_entry_point: is_entry,
                     is_pure: false,
+                    overflow_protection: overflow_protection(item_fn),
+                    span: Some((
+                        item_fn.sig.fn_token.span.start().line,
+                        item_fn.block.brace_token.span.close().end().line,
+                    )),
+                    modifiers: Vec::new(),
+                    uses_low_level: false,
+                    pre_conditions: doc_conditions(item_fn, "@pre")?,
+                    post_conditions: doc_conditions(item_fn, "@post")?,
                 };
                 program.add_function(func);
             }
         }
 
+        program.pda_patterns = extract_pda_patterns(&file);
+        program.cpi_calls = extract_cpi_calls(&file);
+        program.authority_accounts = extract_authority_accounts(&file);
+        let mut updates = UncheckedUpdates::default();
+        updates.visit_file(&file);
+        program.unchecked_arithmetic = updates.updates;
+        if uses_anchor(&file) {
+            program.framework = Some("anchor".to_string());
+        }
+
+        let mut hints = HintCollector::default();
+        hints.visit_file(&file);
+        for (function, hint) in hints.hints {
+            program.add_invariant_hint(&function, &hint);
+        }
+
         info!(
             "Extracted {} state vars and {} functions",
             program.state_vars.len(),
@@ -81,4 +116,937 @@ impl ChainAnalyzer for SolanaAnalyzer {
     fn chain(&self) -> &str {
         "solana"
     }
+
+    fn supported_extensions(&self) -> &'static [&'static str] {
+        &[".rs"]
+    }
+}
+
+/// Whether the file imports from `anchor_lang`.
+fn uses_anchor(file: &syn::File) -> bool {
+    file.items.iter().any(|item| match item {
+        syn::Item::Use(item_use) => match &item_use.tree {
+            syn::UseTree::Path(path) => path.ident == "anchor_lang",
+            syn::UseTree::Name(name) => name.ident == "anchor_lang",
+            _ => false,
+        },
+        _ => false,
+    })
+}
+
+/// Find `#[account(seeds = [...])]` constraints and classify their seeds.
+///
+/// Seeds referencing an instruction argument (a handler parameter or an
+/// `#[instruction(...)]` argument) are user input.
+fn extract_pda_patterns(file: &syn::File) -> Vec<PdaPattern> {
+    let mut collector = PdaCollector::default();
+    collector.visit_file(file);
+
+    let mut patterns = Vec::new();
+    for (accounts, item_struct) in &collector.account_structs {
+        let handler = collector.handlers.get(accounts);
+        let mut user_inputs: BTreeSet<String> = instruction_args(item_struct);
+        if let Some((_, params)) = handler {
+            user_inputs.extend(params.iter().cloned());
+        }
+        let function_name = handler
+            .map(|(name, _)| name.clone())
+            .unwrap_or_else(|| accounts.clone());
+
+        for field in &item_struct.fields {
+            for attr in field.attrs.iter().filter(|a| a.path().is_ident("account")) {
+                if let Some((seeds, line)) = seeds_of(attr) {
+                    patterns.push(PdaPattern {
+                        function_name: function_name.clone(),
+                        seeds: seeds
+                            .iter()
+                            .map(|seed| classify_seed(seed, &user_inputs))
+                            .collect(),
+                        line,
+                    });
+                }
+            }
+        }
+    }
+    patterns
+}
+
+/// Seed expressions of an `#[account(...)]` attribute, with the line of `seeds`.
+fn seeds_of(attr: &syn::Attribute) -> Option<(Vec<syn::Expr>, usize)> {
+    use syn::parse::Parser;
+
+    let syn::Meta::List(list) = &attr.meta else {
+        return None;
+    };
+    let mut tokens = list.tokens.clone().into_iter();
+    while let Some(token) = tokens.next() {
+        let proc_macro2::TokenTree::Ident(ident) = &token else {
+            continue;
+        };
+        if ident != "seeds" {
+            continue;
+        }
+        let (Some(proc_macro2::TokenTree::Punct(eq)), Some(proc_macro2::TokenTree::Group(group))) =
+            (tokens.next(), tokens.next())
+        else {
+            return None;
+        };
+        if eq.as_char() != '=' {
+            return None;
+        }
+        let seeds = syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated
+            .parse2(group.stream())
+            .ok()?;
+        return Some((seeds.into_iter().collect(), ident.span().start().line));
+    }
+    None
+}
+
+/// Names of `#[instruction(name: Type, ...)]` arguments on an accounts struct.
+fn instruction_args(item_struct: &syn::ItemStruct) -> BTreeSet<String> {
+    item_struct
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("instruction"))
+        .filter_map(|attr| {
+            attr.parse_args_with(
+                syn::punctuated::Punctuated::<syn::PatType, syn::Token![,]>::parse_terminated,
+            )
+            .ok()
+        })
+        .flatten()
+        .filter_map(|arg| pat_ident(&arg.pat))
+        .collect()
+}
+
+fn pat_ident(pat: &syn::Pat) -> Option<String> {
+    match pat {
+        syn::Pat::Ident(ident) => Some(ident.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// Classify a seed by the identifiers it references.
+fn classify_seed(seed: &syn::Expr, user_inputs: &BTreeSet<String>) -> PdaSeedSource {
+    let mut idents = IdentCollector::default();
+    idents.visit_expr(seed);
+
+    if let Some(input) = idents.0.iter().find(|ident| user_inputs.contains(*ident)) {
+        PdaSeedSource::UserInput(input.clone())
+    } else if idents
+        .0
+        .iter()
+        .any(|ident| ident == "program_id" || ident == "ID")
+    {
+        PdaSeedSource::ProgramId
+    } else {
+        PdaSeedSource::StaticBytes
+    }
+}
+
+/// Collects accounts structs and the handlers that take them as `Context<T>`.
+#[derive(Default)]
+struct PdaCollector {
+    /// Accounts struct name → struct.
+    account_structs: BTreeMap<String, syn::ItemStruct>,
+    /// Accounts struct name → (handler name, parameter names).
+    handlers: BTreeMap<String, (String, Vec<String>)>,
+}
+
+impl<'ast> Visit<'ast> for PdaCollector {
+    fn visit_item_struct(&mut self, item_struct: &'ast syn::ItemStruct) {
+        self.account_structs
+            .insert(item_struct.ident.to_string(), item_struct.clone());
+    }
+
+    fn visit_item_fn(&mut self, item_fn: &'ast syn::ItemFn) {
+        let mut accounts = None;
+        let mut params = Vec::new();
+        for input in &item_fn.sig.inputs {
+            let syn::FnArg::Typed(arg) = input else {
+                continue;
+            };
+            match context_accounts(&arg.ty) {
+                Some(name) => accounts = Some(name),
+                None => params.extend(pat_ident(&arg.pat)),
+            }
+        }
+        if let Some(accounts) = accounts {
+            self.handlers
+                .insert(accounts, (item_fn.sig.ident.to_string(), params));
+        }
+    }
+}
+
+/// Collects `/// invariant: <expr>` doc comments on functions and methods.
+#[derive(Default)]
+struct HintCollector {
+    /// (function name, expression) in source order.
+    hints: Vec<(String, String)>,
+}
+
+impl HintCollector {
+    fn collect(&mut self, function: &syn::Ident, attrs: &[syn::Attribute]) {
+        for attr in attrs.iter().filter(|a| a.path().is_ident("doc")) {
+            let syn::Meta::NameValue(meta) = &attr.meta else {
+                continue;
+            };
+            let syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(doc),
+                ..
+            }) = &meta.value
+            else {
+                continue;
+            };
+            if let Some(hint) = doc.value().trim().strip_prefix("invariant:") {
+                self.hints
+                    .push((function.to_string(), hint.trim().to_string()));
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for HintCollector {
+    fn visit_item_fn(&mut self, item_fn: &'ast syn::ItemFn) {
+        self.collect(&item_fn.sig.ident, &item_fn.attrs);
+    }
+
+    fn visit_impl_item_fn(&mut self, item_fn: &'ast syn::ImplItemFn) {
+        self.collect(&item_fn.sig.ident, &item_fn.attrs);
+    }
+}
+
+/// `T` in a `Context<T>` (or `Context<'_, '_, '_, 'info, T>`) parameter type.
+fn context_accounts(ty: &syn::Type) -> Option<String> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Context" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    args.args.iter().find_map(|arg| match arg {
+        syn::GenericArgument::Type(syn::Type::Path(inner)) => {
+            inner.path.segments.last().map(|s| s.ident.to_string())
+        }
+        _ => None,
+    })
+}
+
+/// Whether an account name denotes an authority: `authority`, `owner`,
+/// `admin`, or one of those with a prefix (`update_authority`).
+fn is_authority_name(name: &str) -> bool {
+    ["authority", "owner", "admin"]
+        .iter()
+        .any(|role| name == *role || name.ends_with(&format!("_{}", role)))
+}
+
+/// Authority accounts of native handlers (`let authority = next_account_info(..)`
+/// or an `AccountInfo` parameter) and of Anchor accounts structs.
+fn extract_authority_accounts(file: &syn::File) -> Vec<AuthorityAccount> {
+    let mut native = NativeAuthorities::default();
+    native.visit_file(file);
+    let mut accounts = native.accounts;
+
+    let mut collector = PdaCollector::default();
+    collector.visit_file(file);
+    for (name, item_struct) in &collector.account_structs {
+        // Only structs used as a handler's `Context<T>`
+        let Some((handler, _)) = collector.handlers.get(name) else {
+            continue;
+        };
+        for field in &item_struct.fields {
+            let Some(ident) = field
+                .ident
+                .as_ref()
+                .filter(|i| is_authority_name(&i.to_string()))
+            else {
+                continue;
+            };
+            let signer_type = last_type_segment(&field.ty).is_some_and(|name| name == "Signer");
+            let signer_constraint = field.attrs.iter().any(has_signer_constraint);
+            accounts.push(AuthorityAccount {
+                function_name: handler.clone(),
+                account: ident.to_string(),
+                signer_checked: signer_type || signer_constraint,
+                line: ident.span().start().line,
+            });
+        }
+    }
+    accounts
+}
+
+/// Whether an attribute is `#[account(.., signer, ..)]`.
+fn has_signer_constraint(attr: &syn::Attribute) -> bool {
+    let syn::Meta::List(list) = &attr.meta else {
+        return false;
+    };
+    list.path.is_ident("account")
+        && list
+            .tokens
+            .clone()
+            .into_iter()
+            .any(|token| matches!(token, proc_macro2::TokenTree::Ident(ident) if ident == "signer"))
+}
+
+/// Last path segment of a type, through references (`&AccountInfo<'a>`).
+fn last_type_segment(ty: &syn::Type) -> Option<&syn::Ident> {
+    match ty {
+        syn::Type::Reference(reference) => last_type_segment(&reference.elem),
+        syn::Type::Path(path) => path.path.segments.last().map(|segment| &segment.ident),
+        _ => None,
+    }
+}
+
+/// Authority accounts of handlers reading accounts directly, and whether
+/// each one's `is_signer` flag is checked in the body.
+#[derive(Default)]
+struct NativeAuthorities {
+    accounts: Vec<AuthorityAccount>,
+}
+
+impl NativeAuthorities {
+    fn collect(&mut self, item_fn: &syn::ItemFn) {
+        let mut found: Vec<&syn::Ident> = Vec::new();
+        for input in &item_fn.sig.inputs {
+            if let syn::FnArg::Typed(arg) = input {
+                if let syn::Pat::Ident(pat) = arg.pat.as_ref() {
+                    if last_type_segment(&arg.ty).is_some_and(|name| name == "AccountInfo") {
+                        found.push(&pat.ident);
+                    }
+                }
+            }
+        }
+        for stmt in &item_fn.block.stmts {
+            let syn::Stmt::Local(local) = stmt else {
+                continue;
+            };
+            let (syn::Pat::Ident(pat), Some(init)) = (&local.pat, &local.init) else {
+                continue;
+            };
+            let mut calls = IdentCollector::default();
+            calls.visit_expr(&init.expr);
+            if calls.0.iter().any(|ident| ident == "next_account_info") {
+                found.push(&pat.ident);
+            }
+        }
+
+        let mut checks = SignerChecks::default();
+        checks.visit_block(&item_fn.block);
+        for ident in found {
+            let account = ident.to_string();
+            if is_authority_name(&account) {
+                self.accounts.push(AuthorityAccount {
+                    function_name: item_fn.sig.ident.to_string(),
+                    signer_checked: checks.0.contains(&account),
+                    account,
+                    line: ident.span().start().line,
+                });
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for NativeAuthorities {
+    fn visit_item_fn(&mut self, item_fn: &'ast syn::ItemFn) {
+        self.collect(item_fn);
+    }
+}
+
+/// Accounts whose `is_signer` field is read.
+#[derive(Default)]
+struct SignerChecks(BTreeSet<String>);
+
+impl<'ast> Visit<'ast> for SignerChecks {
+    fn visit_expr_field(&mut self, field: &'ast syn::ExprField) {
+        if matches!(&field.member, syn::Member::Named(member) if member == "is_signer") {
+            if let syn::Expr::Path(path) = field.base.as_ref() {
+                if let Some(ident) = path.path.get_ident() {
+                    self.0.insert(ident.to_string());
+                }
+            }
+        }
+        syn::visit::visit_expr_field(self, field);
+    }
+}
+
+/// CPI calls in every function and method, with the state writes that follow them.
+fn extract_cpi_calls(file: &syn::File) -> Vec<CpiCall> {
+    let mut collector = CpiCollector::default();
+    collector.visit_file(file);
+    collector.calls
+}
+
+#[derive(Default)]
+struct CpiCollector {
+    calls: Vec<CpiCall>,
+}
+
+impl CpiCollector {
+    fn collect(&mut self, function: &syn::Ident, block: &syn::Block) {
+        let mut body = BodyEffects::default();
+        body.visit_block(block);
+        for line in body.cpis {
+            self.calls.push(CpiCall {
+                function_name: function.to_string(),
+                line,
+                mutations_after: body.writes.iter().copied().filter(|&w| w > line).collect(),
+            });
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for CpiCollector {
+    fn visit_item_fn(&mut self, item_fn: &'ast syn::ItemFn) {
+        self.collect(&item_fn.sig.ident, &item_fn.block);
+    }
+
+    fn visit_impl_item_fn(&mut self, item_fn: &'ast syn::ImplItemFn) {
+        self.collect(&item_fn.sig.ident, &item_fn.block);
+    }
+}
+
+/// Lines of `invoke`/`invoke_signed` calls and of assignments to fields,
+/// indices or dereferences (writes to local bindings are not state).
+#[derive(Default)]
+struct BodyEffects {
+    cpis: Vec<usize>,
+    writes: Vec<usize>,
+}
+
+impl BodyEffects {
+    fn record_write(&mut self, target: &syn::Expr) {
+        if matches!(
+            target,
+            syn::Expr::Field(_) | syn::Expr::Index(_) | syn::Expr::Unary(_)
+        ) {
+            self.writes.push(target.span().start().line);
+        }
+    }
+}
+
+fn is_cpi(name: &syn::Ident) -> bool {
+    name == "invoke" || name == "invoke_signed"
+}
+
+impl<'ast> Visit<'ast> for BodyEffects {
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(path) = call.func.as_ref() {
+            if path.path.segments.last().is_some_and(|s| is_cpi(&s.ident)) {
+                self.cpis.push(call.span().start().line);
+            }
+        }
+        syn::visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        if is_cpi(&call.method) {
+            self.cpis.push(call.method.span().start().line);
+        }
+        syn::visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_expr_assign(&mut self, assign: &'ast syn::ExprAssign) {
+        self.record_write(&assign.left);
+        syn::visit::visit_expr_assign(self, assign);
+    }
+
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        if is_compound_assign(&expr.op) {
+            self.record_write(&expr.left);
+        }
+        syn::visit::visit_expr_binary(self, expr);
+    }
+
+    // Nested functions are collected on their own
+    fn visit_item_fn(&mut self, _: &'ast syn::ItemFn) {}
+}
+
+fn is_compound_assign(op: &syn::BinOp) -> bool {
+    matches!(
+        op,
+        syn::BinOp::AddAssign(_)
+            | syn::BinOp::SubAssign(_)
+            | syn::BinOp::MulAssign(_)
+            | syn::BinOp::DivAssign(_)
+            | syn::BinOp::RemAssign(_)
+            | syn::BinOp::BitXorAssign(_)
+            | syn::BinOp::BitAndAssign(_)
+            | syn::BinOp::BitOrAssign(_)
+            | syn::BinOp::ShlAssign(_)
+            | syn::BinOp::ShrAssign(_)
+    )
+}
+
+/// Account fields a function assigns, as `account::field`.
+#[derive(Default)]
+struct FieldWrites(BTreeSet<String>);
+
+impl<'ast> Visit<'ast> for FieldWrites {
+    fn visit_expr_assign(&mut self, assign: &'ast syn::ExprAssign) {
+        self.0.extend(field_target(&assign.left));
+        syn::visit::visit_expr_assign(self, assign);
+    }
+
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        if is_compound_assign(&expr.op) {
+            self.0.extend(field_target(&expr.left));
+        }
+        syn::visit::visit_expr_binary(self, expr);
+    }
+
+    fn visit_item_fn(&mut self, _: &'ast syn::ItemFn) {}
+}
+
+/// Collects every identifier in an expression.
+#[derive(Default)]
+struct IdentCollector(Vec<String>);
+
+impl<'ast> Visit<'ast> for IdentCollector {
+    fn visit_ident(&mut self, ident: &'ast proc_macro2::Ident) {
+        self.0.push(ident.to_string());
+    }
+}
+
+/// Conditions in `/// <tag> <expr>` doc comment lines of a function, such as
+/// `/// @pre amount > 0`.
+fn doc_conditions(item_fn: &syn::ItemFn, tag: &str) -> Result<Vec<Expression>> {
+    let mut conditions = Vec::new();
+    for attr in &item_fn.attrs {
+        let syn::Meta::NameValue(meta) = &attr.meta else {
+            continue;
+        };
+        if !meta.path.is_ident("doc") {
+            continue;
+        }
+        let syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(doc),
+            ..
+        }) = &meta.value
+        else {
+            continue;
+        };
+        let doc = doc.value();
+        let Some(condition) = doc.trim().strip_prefix(tag) else {
+            continue;
+        };
+        // `@pre` must not match `@precise`
+        if !condition.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let expr = invar_dsl_parser::parse_expression(condition).map_err(|e| {
+            invar_core::InvarError::AnalysisFailed(format!(
+                "invalid {} condition on {}: {}",
+                tag, item_fn.sig.ident, e
+            ))
+        })?;
+        conditions.push(expr);
+    }
+    Ok(conditions)
+}
+
+/// Classify a function's arithmetic as checked, unchecked, or mixed.
+fn overflow_protection(item_fn: &syn::ItemFn) -> OverflowProtection {
+    let mut visitor = ArithmeticVisitor::default();
+    visitor.visit_item_fn(item_fn);
+
+    match (visitor.checked, visitor.unchecked) {
+        (0, 0) => OverflowProtection::Unknown,
+        (_, 0) => OverflowProtection::Safe,
+        (0, _) => OverflowProtection::Unsafe,
+        _ => OverflowProtection::Mixed,
+    }
+}
+
+/// Counts checked arithmetic calls and bare arithmetic operators.
+#[derive(Default)]
+struct ArithmeticVisitor {
+    checked: usize,
+    unchecked: usize,
+}
+
+impl<'ast> Visit<'ast> for ArithmeticVisitor {
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        if matches!(
+            call.method.to_string().as_str(),
+            "checked_add" | "checked_sub" | "checked_mul"
+        ) {
+            self.checked += 1;
+        }
+        syn::visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        if matches!(
+            expr.op,
+            syn::BinOp::Add(_)
+                | syn::BinOp::Sub(_)
+                | syn::BinOp::Mul(_)
+                | syn::BinOp::AddAssign(_)
+                | syn::BinOp::SubAssign(_)
+                | syn::BinOp::MulAssign(_)
+        ) {
+            self.unchecked += 1;
+        }
+        syn::visit::visit_expr_binary(self, expr);
+    }
+}
+
+/// Account field updates through bare `+`, `-` and `*` (`vault.balance += x`,
+/// `vault.balance = vault.balance - x`).
+#[derive(Default)]
+struct UncheckedUpdates {
+    updates: Vec<UncheckedArithmetic>,
+    /// Function being visited.
+    function: String,
+}
+
+impl UncheckedUpdates {
+    fn record(&mut self, target: &syn::Expr, op: ArithmeticOp) {
+        if let Some(name) = field_target(target) {
+            self.updates.push(UncheckedArithmetic {
+                function_name: self.function.clone(),
+                target: name,
+                op,
+                line: target.span().start().line,
+            });
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for UncheckedUpdates {
+    fn visit_item_fn(&mut self, item_fn: &'ast syn::ItemFn) {
+        let enclosing = std::mem::replace(&mut self.function, item_fn.sig.ident.to_string());
+        syn::visit::visit_item_fn(self, item_fn);
+        self.function = enclosing;
+    }
+
+    fn visit_impl_item_fn(&mut self, item_fn: &'ast syn::ImplItemFn) {
+        let enclosing = std::mem::replace(&mut self.function, item_fn.sig.ident.to_string());
+        syn::visit::visit_impl_item_fn(self, item_fn);
+        self.function = enclosing;
+    }
+
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        match expr.op {
+            syn::BinOp::AddAssign(_) => self.record(&expr.left, ArithmeticOp::Add),
+            syn::BinOp::SubAssign(_) => self.record(&expr.left, ArithmeticOp::Sub),
+            syn::BinOp::MulAssign(_) => self.record(&expr.left, ArithmeticOp::Mul),
+            _ => {}
+        }
+        syn::visit::visit_expr_binary(self, expr);
+    }
+
+    fn visit_expr_assign(&mut self, assign: &'ast syn::ExprAssign) {
+        if let syn::Expr::Binary(value) = assign.right.as_ref() {
+            let op = match value.op {
+                syn::BinOp::Add(_) => Some(ArithmeticOp::Add),
+                syn::BinOp::Sub(_) => Some(ArithmeticOp::Sub),
+                syn::BinOp::Mul(_) => Some(ArithmeticOp::Mul),
+                _ => None,
+            };
+            let target = field_target(&assign.left);
+            if let Some(op) = op.filter(|_| target.is_some() && field_target(&value.left) == target)
+            {
+                self.record(&assign.left, op);
+            }
+        }
+        syn::visit::visit_expr_assign(self, assign);
+    }
+}
+
+/// `account::field` for `account.field` (also through `ctx.accounts.account.field`).
+fn field_target(expr: &syn::Expr) -> Option<String> {
+    let syn::Expr::Field(field) = expr else {
+        return None;
+    };
+    let syn::Member::Named(member) = &field.member else {
+        return None;
+    };
+    let account = match field.base.as_ref() {
+        syn::Expr::Path(path) => path.path.get_ident()?.to_string(),
+        syn::Expr::Field(parent) => match &parent.member {
+            syn::Member::Named(name) => name.to_string(),
+            syn::Member::Unnamed(_) => return None,
+        },
+        _ => return None,
+    };
+    Some(format!("{}::{}", account, member))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn protection_of(source: &str) -> OverflowProtection {
+        let item_fn: syn::ItemFn = syn::parse_str(source).unwrap();
+        overflow_protection(&item_fn)
+    }
+
+    #[test]
+    fn test_overflow_protection_classification() {
+        assert_eq!(
+            protection_of(
+                "fn deposit(balance: u64, amount: u64) -> Option<u64> { balance.checked_add(amount) }"
+            ),
+            OverflowProtection::Safe
+        );
+        assert_eq!(
+            protection_of("fn deposit(balance: u64, amount: u64) -> u64 { balance + amount }"),
+            OverflowProtection::Unsafe
+        );
+        assert_eq!(
+            protection_of("fn f(a: u64, b: u64) -> Option<u64> { (a * 2).checked_sub(b) }"),
+            OverflowProtection::Mixed
+        );
+        assert_eq!(protection_of("fn noop() {}"), OverflowProtection::Unknown);
+    }
+
+    #[test]
+    fn test_pda_seed_from_user_input() {
+        let source = r#"
+pub fn create_vault(ctx: Context<CreateVault>, creator: Pubkey) -> Result<()> {
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CreateVault<'info> {
+    #[account(init, payer = payer, space = 8, seeds = [b"vault", creator.as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+    #[account(seeds = [b"config", crate::ID.as_ref()], bump)]
+    pub config: Account<'info, Config>,
+}
+"#;
+        let patterns = extract_pda_patterns(&syn::parse_file(source).unwrap());
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0].function_name, "create_vault");
+        assert_eq!(patterns[0].line, 8);
+        assert_eq!(
+            patterns[0].seeds,
+            vec![
+                PdaSeedSource::StaticBytes,
+                PdaSeedSource::UserInput("creator".to_string()),
+            ]
+        );
+        assert_eq!(
+            patterns[1].seeds,
+            vec![PdaSeedSource::StaticBytes, PdaSeedSource::ProgramId]
+        );
+    }
+
+    #[test]
+    fn test_unchecked_field_updates() {
+        let source = r#"
+pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+    ctx.accounts.vault.balance += amount;
+    let fee = amount * 2;
+    vault.debt = vault.debt - fee;
+    vault.shares = vault.shares.checked_mul(2).unwrap();
+    Ok(())
+}
+"#;
+        let mut updates = UncheckedUpdates::default();
+        updates.visit_file(&syn::parse_file(source).unwrap());
+        assert_eq!(
+            updates.updates,
+            vec![
+                UncheckedArithmetic {
+                    function_name: "deposit".to_string(),
+                    target: "vault::balance".to_string(),
+                    op: ArithmeticOp::Add,
+                    line: 3,
+                },
+                UncheckedArithmetic {
+                    function_name: "deposit".to_string(),
+                    target: "vault::debt".to_string(),
+                    op: ArithmeticOp::Sub,
+                    line: 5,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_authority_accounts_and_signer_checks() {
+        let source = r#"
+pub fn set_fee(accounts: &[AccountInfo], fee: u64) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config = next_account_info(accounts_iter)?;
+    let authority = next_account_info(accounts_iter)?;
+    Ok(())
+}
+
+pub fn close(admin: &AccountInfo, vault: &AccountInfo) -> ProgramResult {
+    if !admin.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+pub fn update(ctx: Context<Update>) -> Result<()> {
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Update<'info> {
+    pub owner: Signer<'info>,
+    #[account(mut)]
+    pub update_authority: AccountInfo<'info>,
+    pub config: Account<'info, Config>,
+}
+"#;
+        let accounts = extract_authority_accounts(&syn::parse_file(source).unwrap());
+        let summary: Vec<(&str, &str, bool)> = accounts
+            .iter()
+            .map(|a| {
+                (
+                    a.function_name.as_str(),
+                    a.account.as_str(),
+                    a.signer_checked,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("set_fee", "authority", false),
+                ("close", "admin", true),
+                ("update", "owner", true),
+                ("update", "update_authority", false),
+            ]
+        );
+        assert_eq!(accounts[0].line, 5);
+    }
+
+    #[test]
+    fn test_cpi_calls_with_later_writes() {
+        let source = r#"
+pub fn withdraw(vault: &mut Vault, amount: u64) -> ProgramResult {
+    vault.pending = amount;
+    invoke(&ix, &accounts)?;
+    let mut local = 0;
+    local += 1;
+    vault.balance -= amount;
+    Ok(())
+}
+
+pub fn settle(vault: &mut Vault) -> ProgramResult {
+    vault.settled = true;
+    solana_program::program::invoke_signed(&ix, &accounts, &[seeds])
+}
+"#;
+        let calls = extract_cpi_calls(&syn::parse_file(source).unwrap());
+        assert_eq!(
+            calls,
+            vec![
+                CpiCall {
+                    function_name: "withdraw".to_string(),
+                    line: 4,
+                    mutations_after: vec![7],
+                },
+                CpiCall {
+                    function_name: "settle".to_string(),
+                    line: 13,
+                    mutations_after: Vec::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invariant_hints_from_doc_comments() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        std::fs::write(
+            &path,
+            r#"
+#[program]
+pub mod vault {
+    /// Deposit into the vault.
+    /// invariant: total >= 0
+    pub fn deposit(total: u64, amount: u64) -> Option<u64> {
+        total.checked_add(amount)
+    }
+}
+"#,
+        )
+        .unwrap();
+
+        let program = SolanaAnalyzer.analyze(&path).unwrap();
+        assert_eq!(
+            program.invariant_hints.get("deposit"),
+            Some(&vec!["total >= 0".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_checked_functions_pass_overflow_validation() {
+        let source = r#"
+pub fn deposit(vault: &mut Vault, amount: u64) -> ProgramResult {
+    vault.balance = vault.balance.checked_add(amount).ok_or(ProgramError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+pub fn withdraw(vault: &mut Vault, amount: u64) -> ProgramResult {
+    vault.balance -= amount;
+    Ok(())
+}
+"#;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        std::fs::write(&path, source).unwrap();
+
+        let program = SolanaAnalyzer.analyze(&path).unwrap();
+        assert_eq!(
+            program.functions["deposit"].overflow_protection,
+            OverflowProtection::Safe
+        );
+        assert_eq!(
+            program.functions["withdraw"].overflow_protection,
+            OverflowProtection::Unsafe
+        );
+
+        let report = invar_core::SecurityValidator::new()
+            .validate_with_model(source, "lib.rs", "solana", &program)
+            .unwrap();
+        let overflows: Vec<_> = report
+            .high_issues
+            .iter()
+            .filter(|issue| issue.attack_pattern == "Integer Overflow/Underflow")
+            .collect();
+        assert_eq!(overflows.len(), 1);
+        assert_eq!(overflows[0].function.as_deref(), Some("withdraw"));
+        assert_eq!(overflows[0].location.to_string(), "lib.rs:8");
+    }
+
+    #[test]
+    fn test_detects_anchor_imports() {
+        let anchor = syn::parse_file("use anchor_lang::prelude::*;\nuse std::fmt;").unwrap();
+        let native = syn::parse_file("use solana_program::entrypoint;").unwrap();
+        assert!(uses_anchor(&anchor));
+        assert!(!uses_anchor(&native));
+    }
+
+    #[test]
+    fn test_doc_conditions() {
+        let item_fn: syn::ItemFn = syn::parse_str(
+            "/// Deposit into the vault.\n/// @pre amount > 0\n/// @post balance >= amount\n/// @precise rounding\npub fn deposit(amount: u64) -> ProgramResult { Ok(()) }",
+        )
+        .unwrap();
+        let pre = doc_conditions(&item_fn, "@pre").unwrap();
+        assert_eq!(pre.len(), 1);
+        assert_eq!(pre[0].to_string(), "(amount > 0)");
+        assert_eq!(doc_conditions(&item_fn, "@post").unwrap().len(), 1);
+
+        let broken: syn::ItemFn = syn::parse_str("/// @pre amount >\nfn f() {}").unwrap();
+        assert!(doc_conditions(&broken, "@pre").is_err());
+    }
+
+    #[test]
+    fn test_supported_extensions() {
+        assert_eq!(SolanaAnalyzer.supported_extensions(), &[".rs"]);
+    }
 }
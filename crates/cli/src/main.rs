@@ -4,7 +4,11 @@
 //! Invar CLI: Multi-chain invariant enforcement tool.
 
 use clap::{Parser, Subcommand};
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 /// Invar: Production-grade multi-chain invariant analysis tool.
 #[derive(Parser)]
@@ -22,6 +26,11 @@ struct Cli {
     #[arg(short = 'L', long, global = true, default_value = "info")]
     log_level: String,
 
+    /// Log format: pretty, json, compact. Logs go to stderr, so json logs
+    /// combine with json command output on stdout.
+    #[arg(long, global = true, default_value = "pretty")]
+    log_format: invar_utils::LogFormat,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -37,22 +46,221 @@ enum Commands {
 
     /// Analyze and build invariant checks.
     Build {
-        /// Source file to analyze.
-        #[arg(short, long)]
-        source: PathBuf,
+        /// Source file to analyze, a model exported by `invar model`, a
+        /// directory to build every matching file in, or a glob pattern such
+        /// as `programs/**/*.rs`. Repeat to build several sources.
+        #[arg(short, long, required = true)]
+        source: Vec<PathBuf>,
 
-        /// Target chain: solana, evm, move.
+        /// Target chain: solana, evm, move (detected from the source when omitted).
         #[arg(short, long)]
-        chain: String,
+        chain: Option<String>,
 
         /// Output directory for generated code.
         #[arg(short, long, default_value = "./output")]
         output: PathBuf,
+
+        /// Invariants file (TOML or DSL) to type check before generation.
+        #[arg(short, long)]
+        invariants: Option<PathBuf>,
+
+        /// Report mixed numeric width comparisons as warnings instead of errors.
+        #[arg(long)]
+        lenient: bool,
+
+        /// Always re-analyze the source instead of using `.invar/cache`.
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Fail if invariants reference less than this percentage of mutated state.
+        #[arg(long, value_name = "PCT", requires = "invariants")]
+        min_coverage: Option<u8>,
+
+        /// Warn about each state variable that no invariant references.
+        #[arg(long, requires = "invariants")]
+        warn_uncovered: bool,
+
+        /// Only build invariants carrying this tag. Repeat to allow several tags.
+        #[arg(long, value_name = "TAG", requires = "invariants")]
+        only_tag: Vec<String>,
+
+        /// Leave out invariants carrying this tag. Repeatable.
+        #[arg(long, value_name = "TAG", requires = "invariants")]
+        skip_tag: Vec<String>,
+
+        /// Only build the named invariant. Repeatable.
+        #[arg(long, value_name = "NAME", requires = "invariants")]
+        only_invariant: Vec<String>,
+
+        /// Print a unified diff of the checks spliced into the source
+        /// instead of writing generated files.
+        #[arg(long)]
+        diff: bool,
+
+        /// Check class-level invariants declared on the contract or module
+        /// (`@invariant` NatSpec, Move `spec` blocks) after every public
+        /// mutating function.
+        #[arg(long)]
+        extract_contract_invariants: bool,
+
+        /// Synthesize invariants from the analyzed code and write them under
+        /// `<output>/invariants/` for review; they are not injected.
+        #[arg(long, value_name = "KIND", value_parser = ["overflow", "access"], conflicts_with = "diff")]
+        synthesize: Option<String>,
+
+        /// Refuse sources whose state writes cannot all be analyzed, such as
+        /// contracts with inline assembly or `delegatecall`.
+        #[arg(long)]
+        strict: bool,
+
+        /// Number of sources to build in parallel (defaults to the number of CPUs).
+        #[arg(short, long, value_name = "N")]
+        jobs: Option<usize>,
+
+        /// Stop at the first source that fails to build. Sources already
+        /// running in parallel still finish.
+        #[arg(long)]
+        fail_fast: bool,
+
+        /// Compile the Solana program with its injected checks using `rustc`
+        /// and fail if it does not build (requires the `compile-verification`
+        /// feature, also available as `verify-compilation`).
+        #[arg(long, conflicts_with = "diff")]
+        verify_compilation: bool,
+
+        /// Write nothing and fail if regenerating would change the generated
+        /// files already in the output directory, like `cargo fmt --check`.
+        #[arg(long, conflicts_with_all = ["diff", "synthesize"])]
+        check: bool,
+    },
+
+    /// Type check invariants against a program and report every diagnostic.
+    Check {
+        /// Invariants file (TOML or DSL).
+        #[arg(short, long)]
+        invariants: PathBuf,
+
+        /// Source file providing the state variables.
+        #[arg(short, long)]
+        source: PathBuf,
+
+        /// Target chain: solana, evm, move (detected from the source when omitted).
+        #[arg(short, long)]
+        chain: Option<String>,
+
+        /// Report mixed numeric width comparisons as warnings instead of errors.
+        #[arg(long)]
+        lenient: bool,
+
+        /// Always re-analyze the source instead of using `.invar/cache`.
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Evaluate each invariant against a JSON state snapshot and print
+        /// every step, marking the first check that fails.
+        #[arg(long, value_name = "STATE")]
+        trace: Option<PathBuf>,
+
+        /// Evaluate every invariant example and fail if any result differs
+        /// from the example's expected result.
+        #[arg(long)]
+        run_examples: bool,
+    },
+
+    /// Parse an invariant DSL or TOML file and report syntax problems.
+    Lint {
+        /// Invariant DSL or TOML file.
+        file: PathBuf,
+
+        /// Print the token stream before parsing.
+        #[arg(long)]
+        tokenize: bool,
+    },
+
+    /// Analyze a source file and export the extracted program model as JSON.
+    #[command(visible_alias = "export-model")]
+    Model {
+        /// Source file to analyze.
+        #[arg(short, long)]
+        source: PathBuf,
+
+        /// Target chain: solana, evm, move (detected from the source when omitted).
+        #[arg(short, long)]
+        chain: Option<String>,
+
+        /// Output file (defaults to stdout).
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Always re-analyze the source instead of using `.invar/cache`.
+        #[arg(long)]
+        no_cache: bool,
+    },
+
+    /// Analyze a source file and export its mutation graph as Graphviz DOT.
+    ExportGraph {
+        /// Source file to analyze, or a model exported by `invar model`.
+        #[arg(short, long)]
+        source: PathBuf,
+
+        /// Target chain: solana, evm, move (detected from the source when omitted).
+        #[arg(short, long)]
+        chain: Option<String>,
+
+        /// Output file (defaults to stdout).
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Always re-analyze the source instead of using `.invar/cache`.
+        #[arg(long)]
+        no_cache: bool,
+    },
+
+    /// Verify a generated file against the source and invariants it was
+    /// built from, and check it was not edited since. Fails if any check does.
+    Verify {
+        /// Generated file to verify.
+        #[arg(short, long)]
+        generated: PathBuf,
+
+        /// Source file the code was generated from, or a model exported by `invar model`.
+        #[arg(short, long)]
+        source: PathBuf,
+
+        /// Invariants file (TOML or DSL), or a directory of them, passed to
+        /// `invar build` (source annotations are used when omitted).
+        #[arg(short, long)]
+        invariants: Option<PathBuf>,
+
+        /// Target chain: solana, evm, move (detected from the source when omitted).
+        #[arg(short, long)]
+        chain: Option<String>,
+
+        /// The build only checked invariants carrying this tag. Repeatable.
+        #[arg(long, value_name = "TAG", requires = "invariants")]
+        only_tag: Vec<String>,
+
+        /// The build left out invariants carrying this tag. Repeatable.
+        #[arg(long, value_name = "TAG", requires = "invariants")]
+        skip_tag: Vec<String>,
+
+        /// The build only checked the named invariant. Repeatable.
+        #[arg(long, value_name = "NAME", requires = "invariants")]
+        only_invariant: Vec<String>,
+
+        /// The build checked class-level invariants declared on the contract
+        /// or module.
+        #[arg(long)]
+        extract_contract_invariants: bool,
+
+        /// Always re-analyze the source instead of using `.invar/cache`.
+        #[arg(long)]
+        no_cache: bool,
     },
 
     /// Simulate execution against invariants.
     Simulate {
-        /// Program to simulate.
+        /// Program to simulate, or a model exported by `invar model`.
         #[arg(short, long)]
         program: PathBuf,
 
@@ -60,9 +268,51 @@ enum Commands {
         #[arg(short, long)]
         invariants: PathBuf,
 
+        /// Target chain: solana, evm, move (detected from the program when omitted).
+        #[arg(short, long)]
+        chain: Option<String>,
+
         /// RNG seed for determinism (default provides reproducible results).
         #[arg(short, long, default_value = "42")]
         seed: u64,
+
+        /// Number of fuzz iterations to execute.
+        #[arg(long, default_value = "100")]
+        max_iterations: usize,
+
+        /// Number of steps in each execution trace.
+        #[arg(long, default_value = "10")]
+        trace_depth: usize,
+
+        /// Stop the simulation after this many milliseconds (0 for no limit).
+        #[arg(long, value_name = "MS", default_value = "0")]
+        timeout: u64,
+
+        /// Replay and extend a corpus of interesting states (defaults to
+        /// `.invar/corpus` when no directory is given).
+        #[arg(
+            long,
+            value_name = "DIR",
+            num_args = 0..=1,
+            default_missing_value = invar_simulator::DEFAULT_CORPUS_DIR
+        )]
+        corpus: Option<PathBuf>,
+
+        /// Write the results as a JUnit XML test suite for CI.
+        #[arg(long, value_name = "FILE")]
+        output_junit: Option<PathBuf>,
+
+        /// Only simulate invariants carrying this tag. Repeat to allow several tags.
+        #[arg(long, value_name = "TAG")]
+        only_tag: Vec<String>,
+
+        /// Leave out invariants carrying this tag. Repeatable.
+        #[arg(long, value_name = "TAG")]
+        skip_tag: Vec<String>,
+
+        /// Only simulate the named invariant. Repeatable.
+        #[arg(long, value_name = "NAME")]
+        only_invariant: Vec<String>,
     },
 
     /// Check for upgrade safety.
@@ -78,11 +328,13 @@ enum Commands {
 
     /// Generate a report.
     Report {
-        /// Analysis results file.
-        #[arg(short, long)]
-        input: PathBuf,
+        /// Analysis results file, or a directory whose `security_report.json`
+        /// files are merged into one report. Repeat to combine the reports of
+        /// several programs or chains, one section each.
+        #[arg(short, long, required = true)]
+        input: Vec<PathBuf>,
 
-        /// Output format: json, markdown, cli.
+        /// Output format: json, markdown, cli, github-actions.
         #[arg(short, long, default_value = "json")]
         format: String,
 
@@ -96,6 +348,10 @@ enum Commands {
         /// Category filter.
         #[arg(short, long)]
         category: Option<String>,
+
+        /// Only list invariants carrying this tag.
+        #[arg(short, long)]
+        tag: Option<String>,
     },
 }
 
@@ -103,7 +359,7 @@ fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     // Initialize logging
-    invar_utils::setup_tracing(&cli.log_level);
+    invar_utils::setup_tracing(&cli.log_level, cli.log_format);
 
     match cli.command {
         Some(Commands::Init { path }) => {
@@ -114,16 +370,164 @@ fn main() -> anyhow::Result<()> {
             source,
             chain,
             output,
+            invariants,
+            lenient,
+            no_cache,
+            min_coverage,
+            warn_uncovered,
+            only_tag,
+            skip_tag,
+            only_invariant,
+            diff,
+            extract_contract_invariants,
+            synthesize,
+            strict,
+            jobs,
+            fail_fast,
+            verify_compilation,
+            check,
+        }) => {
+            let filter = invar_core::InvariantFilter {
+                only_tags: only_tag,
+                skip_tags: skip_tag,
+                only_invariants: only_invariant,
+            };
+            let options = BuildOptions {
+                invariants: invariants.as_deref(),
+                lenient,
+                use_cache: !no_cache,
+                min_coverage,
+                warn_uncovered,
+                filter: &filter,
+                extract_contract_invariants,
+                diff,
+                synthesize: synthesize.as_deref(),
+                strict,
+                verify_compilation,
+                check,
+            };
+            match source.as_slice() {
+                [file] if file.is_file() => {
+                    let chain = resolve_chain(file, chain.as_deref())?;
+                    build_invariants(file, &chain, &output, options, &mut std::io::stdout())?;
+                }
+                _ => build_sources(&source, chain.as_deref(), &output, options, jobs, fail_fast)?,
+            }
+            Ok(())
+        }
+        Some(Commands::Check {
+            invariants,
+            source,
+            chain,
+            lenient,
+            no_cache,
+            trace,
+            run_examples,
+        }) => {
+            let chain = resolve_chain(&source, chain.as_deref())?;
+            check_invariants(
+                &invariants,
+                &source,
+                &chain,
+                lenient,
+                !no_cache,
+                trace.as_deref(),
+                run_examples,
+            )?;
+            Ok(())
+        }
+        Some(Commands::Lint { file, tokenize }) => {
+            lint_invariants(&file, tokenize, &mut std::io::stdout())?;
+            Ok(())
+        }
+        Some(Commands::Model {
+            source,
+            chain,
+            output,
+            no_cache,
         }) => {
-            build_invariants(&source, &chain, &output)?;
+            let chain = resolve_chain(&source, chain.as_deref())?;
+            export_model(&source, &chain, output.as_deref(), !no_cache)?;
+            Ok(())
+        }
+        Some(Commands::ExportGraph {
+            source,
+            chain,
+            output,
+            no_cache,
+        }) => {
+            let chain = resolve_chain(&source, chain.as_deref())?;
+            export_graph(&source, &chain, output.as_deref(), !no_cache)?;
+            Ok(())
+        }
+        Some(Commands::Verify {
+            generated,
+            source,
+            invariants,
+            chain,
+            only_tag,
+            skip_tag,
+            only_invariant,
+            extract_contract_invariants,
+            no_cache,
+        }) => {
+            let chain = resolve_chain(&source, chain.as_deref())?;
+            let filter = invar_core::InvariantFilter {
+                only_tags: only_tag,
+                skip_tags: skip_tag,
+                only_invariants: only_invariant,
+            };
+            let options = VerifyOptions {
+                invariants: invariants.as_deref(),
+                filter: &filter,
+                extract_contract_invariants,
+                use_cache: !no_cache,
+            };
+            verify_generated(
+                &generated,
+                &source,
+                &chain,
+                options,
+                &mut std::io::stdout(),
+            )?;
             Ok(())
         }
         Some(Commands::Simulate {
             program,
             invariants,
+            chain,
             seed,
+            max_iterations,
+            trace_depth,
+            timeout,
+            corpus,
+            output_junit,
+            only_tag,
+            skip_tag,
+            only_invariant,
         }) => {
-            simulate_program(&program, &invariants, seed)?;
+            let chain = resolve_chain(&program, chain.as_deref())?;
+            let config = invar_simulator::SimulationConfig {
+                seed,
+                max_iterations,
+                trace_depth,
+                timeout_ms: timeout,
+                corpus,
+                ..Default::default()
+            };
+            let filter = invar_core::InvariantFilter {
+                only_tags: only_tag,
+                skip_tags: skip_tag,
+                only_invariants: only_invariant,
+            };
+            simulate_program(
+                &program,
+                &invariants,
+                &chain,
+                config,
+                &filter,
+                output_junit.as_deref(),
+            )?;
             Ok(())
         }
         Some(Commands::UpgradeCheck { old, new }) => {
@@ -135,11 +539,14 @@ fn main() -> anyhow::Result<()> {
             format,
             output,
         }) => {
-            generate_report(&input, &format, output)?;
+            match input.as_slice() {
+                [single] => generate_report(single, &format, output)?,
+                _ => generate_combined_report(&input, &format, output)?,
+            }
             Ok(())
         }
-        Some(Commands::List { category }) => {
-            list_invariants(category)?;
+        Some(Commands::List { category, tag }) => {
+            list_invariants(category, tag)?;
             Ok(())
         }
         None => {
@@ -186,120 +593,1552 @@ tamper_detection = true
     Ok(())
 }
 
-/// Build invariant checks from source.
-fn build_invariants(source: &PathBuf, chain: &str, output: &PathBuf) -> anyhow::Result<()> {
-    use invar_core::SecurityValidator;
-    use std::fs;
+/// File name used for per-build security findings.
+const SECURITY_REPORT_FILE: &str = "security_report.json";
+
+/// File name of the check expression tests generated for Solana checks.
+const GENERATED_SOLANA_TESTS_FILE: &str = "generated_solana_tests.rs";
+
+/// File name of the security report aggregated over a multi-source build.
+const MERGED_SECURITY_REPORT_FILE: &str = "merged_security_report.json";
+
+/// File name of the report linking cross-chain invariants to the programs
+/// of a multi-source build.
+const CROSS_CHAIN_REPORT_FILE: &str = "cross_chain_report.json";
+
+/// Synthesized invariants, relative to the build output directory.
+const SYNTHESIZED_INVARIANTS_FILE: &str = "invariants/synthesized.toml";
+
+/// Synthesized access-control candidates, relative to the build output directory.
+const SYNTHESIZED_ACCESS_FILE: &str = "invariants/synthesized_access.toml";
+
+/// Expressions with more nodes than this are flagged by `invar lint`.
+const LINT_COMPLEXITY_WARNING: usize = 100;
+
+/// Settings shared by every file in a build.
+#[derive(Clone, Copy)]
+struct BuildOptions<'a> {
+    /// Invariants file to type check before generation.
+    invariants: Option<&'a Path>,
+    /// Report mixed numeric width comparisons as warnings.
+    lenient: bool,
+    /// Reuse analysis results from `.invar/cache`.
+    use_cache: bool,
+    /// Minimum invariant coverage percentage.
+    min_coverage: Option<u8>,
+    /// Warn about state variables no invariant references.
+    warn_uncovered: bool,
+    /// Tag and name filters choosing the invariants to build.
+    filter: &'a invar_core::InvariantFilter,
+    /// Check invariants declared on the contract or module.
+    extract_contract_invariants: bool,
+    /// Print a diff of the injected source instead of writing files.
+    diff: bool,
+    /// Kind of invariants to synthesize for review (`overflow`, `access`).
+    synthesize: Option<&'a str>,
+    /// Reject sources with constructs that make mutation analysis unsound.
+    strict: bool,
+    /// Compile the Solana program with its injected checks using `rustc`.
+    verify_compilation: bool,
+    /// Compare the generated files with the output directory instead of
+    /// writing them.
+    check: bool,
+}
+
+/// The build settings `invar verify` rebuilds the expected invariants with.
+#[derive(Clone, Copy)]
+struct VerifyOptions<'a> {
+    /// Invariants file the build was given.
+    invariants: Option<&'a Path>,
+    /// Tag and name filters the build chose its invariants with.
+    filter: &'a invar_core::InvariantFilter,
+    /// The build checked invariants declared on the contract or module.
+    extract_contract_invariants: bool,
+    /// Reuse analysis results from `.invar/cache`.
+    use_cache: bool,
+}
+
+/// Outcome of building a single source file.
+struct BuiltSource {
+    /// Security findings for the file.
+    security_report: invar_core::SecurityReport,
+    /// The analyzed program, when analysis succeeded.
+    program: Option<invar_core::ProgramModel>,
+}
+
+/// Build every file matched by `sources`: files, directories, or glob patterns.
+///
+/// Each file's output goes to its own subdirectory of `output`, mirroring the
+/// source layout, so `invar report --input <output>` can merge the results.
+/// The per-file security reports are also merged into
+/// [`MERGED_SECURITY_REPORT_FILE`]. A failing file does not stop the others
+/// unless `fail_fast` is set.
+///
+/// Without `chain`, every analyzable file is built for the chain detected from
+/// it; files whose chain cannot be detected are skipped.
+fn build_sources(
+    sources: &[PathBuf],
+    chain: Option<&str>,
+    output: &Path,
+    options: BuildOptions<'_>,
+    jobs: Option<usize>,
+    fail_fast: bool,
+) -> anyhow::Result<()> {
+    let registry = analyzer_registry();
+    if let Some(chain) = chain {
+        if registry.analyzer_for_chain(chain).is_none() {
+            return Err(anyhow::anyhow!(
+                "Unknown chain: {}. Supported: {}",
+                chain,
+                registry.chains().join(", ")
+            ));
+        }
+    }
+
+    let files = expand_sources(sources, chain, &registry)?;
+    let root = match sources {
+        [dir] if dir.is_dir() => dir.clone(),
+        _ => common_ancestor(&files),
+    };
+
+    let chains = match chain {
+        Some(chain) => files
+            .into_iter()
+            .map(|file| (file, chain.to_string()))
+            .collect(),
+        None => detect_chains(&root, files)?,
+    };
+
+    let label = chain.map(|chain| format!("{} ", chain)).unwrap_or_default();
+    if chains.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No {}source files found for {}",
+            label,
+            sources
+                .iter()
+                .map(|source| source.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    println!(
+        "Found {} {}source file(s) under {}",
+        chains.len(),
+        label,
+        root.display()
+    );
+
+    // Each source logs into its own buffer so parallel output stays in path order
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()?;
+    let stop = AtomicBool::new(false);
+    let results: Vec<_> = pool.install(|| {
+        chains
+            .par_iter()
+            .map(|(file, chain)| {
+                if stop.load(Ordering::Relaxed) {
+                    return None;
+                }
+                let relative = file.strip_prefix(&root).unwrap_or(file);
+                let file_output = output.join(relative.with_extension(""));
+                let mut log = Vec::new();
+                let built = writeln!(log, "\n=== {} ===", relative.display())
+                    .map_err(anyhow::Error::from)
+                    .and_then(|_| build_invariants(file, chain, &file_output, options, &mut log));
+                let (report, error) = match built {
+                    Ok(built) => (Some((built.security_report, built.program)), None),
+                    // A blocked build has already persisted its findings
+                    Err(e) => (
+                        read_security_report(&file_output).map(|report| (report, None)),
+                        Some(e),
+                    ),
+                };
+                if error.is_some() && fail_fast {
+                    stop.store(true, Ordering::Relaxed);
+                }
+                Some((relative.to_path_buf(), chain, log, report, error))
+            })
+            .collect()
+    });
+
+    let mut summary = Vec::new();
+    let mut reports = Vec::new();
+    let mut programs = Vec::new();
+    let mut stdout = std::io::stdout();
+    for (relative, chain, log, built, error) in results.into_iter().flatten() {
+        stdout.write_all(&log)?;
+        let report = built.map(|(report, program)| {
+            programs.extend(program);
+            report
+        });
+        let failed = error.is_some();
+        summary.push((relative, chain, report.clone(), error));
+        reports.extend(report);
+        if failed && fail_fast {
+            break;
+        }
+    }
+
+    let merged = invar_core::SecurityReport::merge(reports);
+    std::fs::create_dir_all(output)?;
+    std::fs::write(
+        output.join(MERGED_SECURITY_REPORT_FILE),
+        serde_json::to_string_pretty(&merged)?,
+    )?;
+
+    println!("\nBuild summary:");
+    for (relative, chain, report, error) in &summary {
+        let risk = report
+            .as_ref()
+            .map(|report| format!("risk {}/100", report.risk_score))
+            .unwrap_or_else(|| "not validated".to_string());
+        match error {
+            None => println!("  ✓ {} ({}): {}", relative.display(), chain, risk),
+            Some(e) => println!("  ✗ {} ({}): {} - {}", relative.display(), chain, risk, e),
+        }
+    }
+    let failures = summary.iter().filter(|(.., error)| error.is_some()).count();
+    let passed = failures == 0 && merged.passed;
+    println!(
+        "  Combined: {} file(s), {} program model(s), risk {}/100 - {}",
+        summary.len(),
+        programs.len(),
+        merged.risk_score,
+        if passed { "PASSED" } else { "FAILED" }
+    );
+
+    if let Some(invariants) = options.invariants {
+        let selection = options.filter.select(load_invariants(invariants)?)?;
+        let namespaces: Vec<String> = programs
+            .iter()
+            .map(|program| format!("{}/{}", program.name, program.chain))
+            .collect();
+        let mut report = invar_report::Report::new(
+            "Invar Cross-Chain Report".to_string(),
+            namespaces.join(", "),
+        );
+        report.record_cross_chain(&selection.selected, &programs);
+        if !report.cross_chain.is_empty() {
+            println!("\nCross-chain invariants:");
+            for link in &report.cross_chain {
+                if link.is_linked() {
+                    let programs: Vec<String> = link
+                        .programs
+                        .iter()
+                        .map(|(namespace, program)| format!("{} → {}", namespace, program))
+                        .collect();
+                    println!("  ✓ {}: {}", link.invariant, programs.join(", "));
+                } else {
+                    println!(
+                        "  ✗ {}: no analyzed program declares {}",
+                        link.invariant,
+                        link.unresolved.join(", ")
+                    );
+                }
+            }
+            std::fs::write(
+                output.join(CROSS_CHAIN_REPORT_FILE),
+                invar_report::ReportFormatter::to_json(&report)?,
+            )?;
+        }
+    }
+
+    if failures > 0 {
+        let skipped = chains.len() - summary.len();
+        return Err(anyhow::anyhow!(
+            "{} of {} source file(s) failed to build{}",
+            failures,
+            summary.len(),
+            if skipped > 0 {
+                format!(" ({} skipped after --fail-fast)", skipped)
+            } else {
+                String::new()
+            }
+        ));
+    }
+    Ok(())
+}
+
+/// Expand `--source` arguments into the sorted list of files to build.
+fn expand_sources(
+    sources: &[PathBuf],
+    chain: Option<&str>,
+    registry: &invar_core::ChainAnalyzerRegistry,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for source in sources {
+        let pattern = source.to_string_lossy();
+        if pattern.contains(['*', '?', '[']) {
+            let matches = glob::glob(&pattern)
+                .map_err(|e| anyhow::anyhow!("Invalid source pattern {}: {}", pattern, e))?;
+            for path in matches {
+                let path = path?;
+                if path.is_file() {
+                    files.push(path);
+                }
+            }
+        } else if source.is_dir() {
+            collect_sources(source, chain, registry, &mut files)?;
+        } else if source.exists() {
+            files.push(source.clone());
+        } else {
+            return Err(anyhow::anyhow!(
+                "Source file not found: {}",
+                source.display()
+            ));
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Deepest directory containing every file in `files`.
+fn common_ancestor(files: &[PathBuf]) -> PathBuf {
+    let mut ancestor = match files.first().and_then(|file| file.parent()) {
+        Some(parent) => parent.to_path_buf(),
+        None => return PathBuf::new(),
+    };
+    for file in &files[1..] {
+        while !file.starts_with(&ancestor) && ancestor.pop() {}
+    }
+    ancestor
+}
+
+/// Read a security report persisted by `build_invariants`, if any.
+fn read_security_report(dir: &Path) -> Option<invar_core::SecurityReport> {
+    let content = std::fs::read_to_string(dir.join(SECURITY_REPORT_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Map each file to its detected chain, skipping files that cannot be attributed.
+fn detect_chains(root: &Path, files: Vec<PathBuf>) -> anyhow::Result<Vec<(PathBuf, String)>> {
+    let mut chains = Vec::new();
+    println!("Detected chains:");
+    for file in files {
+        let relative = file
+            .strip_prefix(root)
+            .unwrap_or(&file)
+            .display()
+            .to_string();
+        let content = std::fs::read_to_string(&file)?;
+        match invar_core::detect_chain(&file, &content) {
+            Some(chain) => {
+                println!("  {} → {}", relative, chain);
+                chains.push((file, chain.to_string()));
+            }
+            None => println!("  {} → skipped (chain not detected)", relative),
+        }
+    }
+    Ok(chains)
+}
+
+/// Recursively collect files whose extension maps to `chain`'s analyzer, or to
+/// any registered analyzer when `chain` is `None`.
+///
+/// Hidden directories and `target` are skipped.
+fn collect_sources(
+    dir: &Path,
+    chain: Option<&str>,
+    registry: &invar_core::ChainAnalyzerRegistry,
+    files: &mut Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                collect_sources(&path, chain, registry, files)?;
+            }
+        } else if path
+            .extension()
+            .and_then(|ext| registry.analyzer_for_extension(&ext.to_string_lossy()))
+            .is_some_and(|analyzer| chain.is_none_or(|chain| analyzer.chain() == chain))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Use the explicit `chain`, or detect it from `source` when omitted.
+///
+/// Exported models carry their chain; other files are detected from their
+/// extension and content.
+fn resolve_chain(source: &Path, chain: Option<&str>) -> anyhow::Result<String> {
+    if let Some(chain) = chain {
+        return Ok(chain.to_string());
+    }
+    if is_model_file(source) {
+        return Ok(load_model(source)?.chain);
+    }
+
+    let content = std::fs::read_to_string(source)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", source.display(), e))?;
+    let chain = invar_core::detect_chain(source, &content).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Cannot detect the chain of {}. Pass --chain with one of: {}",
+            source.display(),
+            analyzer_registry().chains().join(", ")
+        )
+    })?;
+    println!("Detected chain: {}", chain);
+    Ok(chain.to_string())
+}
+
+/// Registry of the built-in chain analyzers.
+fn analyzer_registry() -> invar_core::ChainAnalyzerRegistry {
+    let mut registry = invar_core::ChainAnalyzerRegistry::new();
+    registry.register(Box::new(invar_analyzer_solana::SolanaAnalyzer));
+    registry.register(Box::new(invar_analyzer_evm::EvmAnalyzer));
+    registry.register(Box::new(invar_analyzer_move::MoveAnalyzer));
+    registry
+}
+
+/// Build invariant checks from source.
+fn build_invariants(
+    source: &Path,
+    chain: &str,
+    output: &Path,
+    options: BuildOptions<'_>,
+    out: &mut dyn Write,
+) -> anyhow::Result<BuiltSource> {
+    use invar_core::SecurityValidator;
+    use std::fs;
+
+    let BuildOptions {
+        invariants,
+        lenient,
+        use_cache,
+        min_coverage,
+        warn_uncovered,
+        filter,
+        extract_contract_invariants,
+        diff,
+        synthesize,
+        strict,
+        verify_compilation,
+        check,
+    } = options;
+
+    // Validate chain
+    match chain {
+        "solana" | "evm" | "move" => {}
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Unknown chain: {}. Supported: solana, evm, move",
+                chain
+            ))
+        }
+    }
+
+    #[cfg(not(feature = "compile-verification"))]
+    if verify_compilation {
+        return Err(anyhow::anyhow!(
+            "--verify-compilation requires invar to be built with the `compile-verification` feature"
+        ));
+    }
+
+    // Read source file
+    if !source.exists() {
+        return Err(anyhow::anyhow!(
+            "Source file not found: {}",
+            source.display()
+        ));
+    }
+
+    writeln!(out, "Step 1: Security validation")?;
+    writeln!(
+        out,
+        "  Scanning for known attack patterns ({} chain)...",
+        chain
+    )?;
+
+    // SECURITY VALIDATION - Check for attack patterns BEFORE building
+    let validator = SecurityValidator::with_policy(load_risk_policy()?);
+    // Use the analyzed model when available so per-function facts refine the scan
+    let analyzed = analyze_source(source, chain, use_cache);
+    // An exported model stands in for analysis; checks still read the original source
+    let code_path = if is_model_file(source) {
+        let program = analyzed.as_ref().map_err(|e| anyhow::anyhow!("{}", e))?;
+        PathBuf::from(&program.source_path)
+    } else {
+        source.to_path_buf()
+    };
+    let security_report = match &analyzed {
+        Ok(program) => validator.validate_program(program, &fs::read_to_string(&code_path)?),
+        Err(_) => validator.validate_file(&code_path, chain),
+    }
+    .map_err(|e| anyhow::anyhow!("Security validation failed: {}", e))?;
+
+    writeln!(out, "  Risk Score: {}/100", security_report.risk_score)?;
+    writeln!(out, "  Risk Policy: {}", validator.policy())?;
+
+    // Persist the findings so `invar report --input <dir>` can aggregate them
+    fs::create_dir_all(output)?;
+    fs::write(
+        output.join(SECURITY_REPORT_FILE),
+        serde_json::to_string_pretty(&security_report)?,
+    )?;
+
+    if !security_report.critical_issues.is_empty() {
+        writeln!(out, "\n❌ BUILD BLOCKED - Critical security issues found:")?;
+        for issue in &security_report.critical_issues {
+            writeln!(
+                out,
+                "  [CRITICAL] {} at {}",
+                issue.attack_pattern, issue.location
+            )?;
+            writeln!(out, "    → {}", issue.description)?;
+            print_snippet(out, &issue.location)?;
+            writeln!(out, "    ✓ Fix: {}", issue.suggested_fix)?;
+        }
+        return Err(anyhow::anyhow!(
+            "Cannot proceed: {} critical vulnerabilities must be fixed first",
+            security_report.critical_issues.len()
+        ));
+    }
+
+    if !security_report.high_issues.is_empty() {
+        writeln!(out, "\n⚠️  High-risk issues detected:")?;
+        for issue in &security_report.high_issues {
+            writeln!(
+                out,
+                "  [HIGH] {} at {}",
+                issue.attack_pattern, issue.location
+            )?;
+            writeln!(out, "    → {}", issue.description)?;
+            print_snippet(out, &issue.location)?;
+            writeln!(out, "    ✓ Fix: {}", issue.suggested_fix)?;
+        }
+        writeln!(
+            out,
+            "\nProceeding with caution. Recommend addressing these issues."
+        )?;
+    }
+
+    if !security_report.medium_issues.is_empty() {
+        writeln!(
+            out,
+            "\n📋 Medium-risk issues found: {}",
+            security_report.medium_issues.len()
+        )?;
+    }
+
+    if !security_report.low_issues.is_empty() {
+        writeln!(
+            out,
+            "ℹ️  Low-risk issues found: {}",
+            security_report.low_issues.len()
+        )?;
+    }
+
+    if security_report.passed {
+        writeln!(out, "✓ Security validation passed!")?;
+    } else {
+        writeln!(out, "⚠️  Security report does not satisfy the risk policy")?;
+    }
+
+    // Point at defensive invariants for the attack patterns the code resembles
+    if invariants.is_none() {
+        let suggestions = invar_core::AttackPatternDB::new()
+            .suggest_invariants_for_code(&fs::read_to_string(&code_path)?, chain);
+        if !suggestions.is_empty() {
+            writeln!(out, "\n💡 Suggested invariants:")?;
+            for suggestion in &suggestions {
+                writeln!(out, "    - {}", suggestion)?;
+            }
+        }
+    }
+
+    // Writes hidden in assembly or low-level calls would slip past the checks
+    if strict {
+        let program = analyzed
+            .as_ref()
+            .map_err(|e| anyhow::anyhow!("Strict mode requires analysis: {}", e))?;
+        let mutated: Vec<String> = program
+            .functions
+            .values()
+            .flat_map(|function| function.mutates.iter().cloned())
+            .collect();
+        let uncertain: Vec<String> = program
+            .uncertainties
+            .iter()
+            .map(|u| format!("{} in {} (line {})", u.construct, u.function_name, u.line))
+            .collect();
+        invar_core::StrictModeAnalyzer::new(true)
+            .verify_mutation_coverage(&mutated, &uncertain)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+    }
+
+    // Synthesized invariants are proposals, written out rather than injected
+    if let Some(kind) = synthesize {
+        let program = analyzed
+            .as_ref()
+            .map_err(|e| anyhow::anyhow!("Cannot synthesize invariants: {}", e))?;
+        let (file, toml, listing, guarded) = match kind {
+            "overflow" => {
+                let synthesized = invar_library::synthesis::synthesize_overflow(program);
+                let listing: Vec<String> = synthesized
+                    .iter()
+                    .map(|invariant| format!("{}: {}", invariant.name, invariant.expression))
+                    .collect();
+                let toml = invar_library::synthesis::to_toml(&synthesized);
+                (SYNTHESIZED_INVARIANTS_FILE, toml, listing, Vec::new())
+            }
+            "access" => {
+                let synthesis = invar_library::synthesis::synthesize_access(program);
+                let listing = synthesis
+                    .candidates
+                    .iter()
+                    .map(|candidate| {
+                        format!(
+                            "{}: {} ({} confidence)",
+                            candidate.function,
+                            candidate.invariant.expression,
+                            candidate.confidence.as_str()
+                        )
+                    })
+                    .collect();
+                let toml = invar_library::synthesis::access_toml(&synthesis);
+                (SYNTHESIZED_ACCESS_FILE, toml, listing, synthesis.guarded)
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Unknown synthesis: {}. Supported: overflow, access",
+                    kind
+                ))
+            }
+        };
+        let path = output.join(file);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(
+            &path,
+            format!(
+                "# Synthesized by `invar build --synthesize {}`; review before enforcing.\n{}",
+                kind, toml
+            ),
+        )?;
+        writeln!(
+            out,
+            "\n💡 Synthesized {} {} invariant(s) for review: {}",
+            listing.len(),
+            kind,
+            path.display()
+        )?;
+        for line in &listing {
+            writeln!(out, "    - {}", line)?;
+        }
+        if !guarded.is_empty() {
+            writeln!(out, "   Skipped (already guarded): {}", guarded.join(", "))?;
+        }
+    }
+
+    // Invariants the generated checks are built from
+    let mut checked = Vec::new();
+    if let Some(invariants_path) = invariants {
+        writeln!(out, "\nStep 2: Invariant type checking")?;
+        let selection = filter.select(load_invariants(invariants_path)?)?;
+        print_skipped(out, &selection.skipped)?;
+        // Invariants spanning programs are linked once every source is built
+        let (cross_chain, selected): (Vec<_>, Vec<_>) = selection
+            .selected
+            .into_iter()
+            .partition(invar_core::cross_chain::is_cross_chain);
+        if !cross_chain.is_empty() {
+            writeln!(
+                out,
+                "  Deferred {} cross-chain invariant(s) to the multi-source build",
+                cross_chain.len()
+            )?;
+        }
+        if let Ok(program) = &analyzed {
+            check_functions_defined(&selected, program, chain)?;
+        }
+        let report = type_check_invariants(&selected, source, chain, lenient, use_cache, out)?;
+        if report.has_errors() {
+            return Err(anyhow::anyhow!(
+                "Cannot proceed: {} invariant type error(s) must be fixed first",
+                report.errors().count()
+            ));
+        }
+
+        // Invariants restricted to other chains are not generated
+        let (loaded, skipped): (Vec<_>, Vec<_>) = selected
+            .into_iter()
+            .partition(|invariant| invariant.is_compatible_with_chain(chain));
+        if !skipped.is_empty() {
+            writeln!(
+                out,
+                "  Skipped {} invariant(s) for other chains",
+                skipped.len()
+            )?;
+        }
+
+        // Security-critical invariants cannot be compiled out of production builds
+        for invariant in &loaded {
+            invariant.check_enforcement()?;
+        }
+
+        let program = analyze_source(source, chain, use_cache)?;
+        let coverage = invar_ir::compute_coverage(&program, &loaded);
+        writeln!(
+            out,
+            "  Coverage: {}% of mutated state referenced by invariants",
+            coverage.coverage_percent
+        )?;
+        for (function, vars) in &coverage.unguarded {
+            let vars: Vec<_> = vars.iter().map(String::as_str).collect();
+            writeln!(
+                out,
+                "    - {} mutates unguarded: {}",
+                function,
+                vars.join(", ")
+            )?;
+        }
+        if let Some(min) = min_coverage {
+            if coverage.coverage_percent < min {
+                return Err(anyhow::anyhow!(
+                    "Cannot proceed: invariant coverage {}% is below the required {}%",
+                    coverage.coverage_percent,
+                    min
+                ));
+            }
+        }
+        if warn_uncovered {
+            for var in program.validate_completeness(&loaded) {
+                writeln!(
+                    out,
+                    "  ⚠️  State variable {} is not referenced by any invariant",
+                    var
+                )?;
+            }
+        }
+        checked = loaded;
+    }
+
+    // Without an invariants file, fall back to invariants annotated in the source
+    let mut step = if invariants.is_some() { 3 } else { 2 };
+    if let (None, Ok(program)) = (invariants, &analyzed) {
+        let hinted = hint_invariants(program)?;
+        if !hinted.is_empty() {
+            writeln!(out, "\nStep 2: Invariant hints")?;
+            writeln!(
+                out,
+                "  Using {} invariant(s) annotated in the source",
+                hinted.len()
+            )?;
+            let coverage = invar_ir::compute_coverage(program, &hinted);
+            writeln!(
+                out,
+                "  Coverage: {}% of mutated state referenced by invariants",
+                coverage.coverage_percent
+            )?;
+            step = 3;
+            checked = hinted;
+        }
+    }
+
+    // Class invariants hold between transactions, so they join the other checks
+    let mut contract_checks = Vec::new();
+    if let (true, Ok(program)) = (extract_contract_invariants, &analyzed) {
+        contract_checks = contract_invariants(program)?;
+        writeln!(
+            out,
+            "  Contract invariants: {} declared on {}",
+            contract_checks.len(),
+            program.name
+        )?;
+        checked.extend(contract_checks.iter().cloned());
+    }
+
+    writeln!(out, "\nStep {}: Code generation", step)?;
+    let content = fs::read_to_string(&code_path)?;
+
+    // Create output directory
+    fs::create_dir_all(output)?;
+
+    // Parse and generate
+    let mut generated_code = match chain {
+        "solana" => generate_solana_checks(&content),
+        "evm" => generate_evm_checks(&content),
+        "move" => generate_move_checks(&content),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Invalid chain after validation: {}. This is a bug.",
+                chain
+            ))
+        }
+    };
+
+    // Contract invariants run after every public mutating function
+    if let (false, Ok(program)) = (contract_checks.is_empty(), &analyzed) {
+        use invar_core::traits::CodeGenerator;
+        let generation = match chain {
+            "evm" => invar_generator_evm::EvmGenerator.generate(program, &contract_checks),
+            "move" => invar_generator_move::MoveGenerator.generate(program, &contract_checks),
+            _ => invar_generator_solana::SolanaGenerator::new().generate(program, &contract_checks),
+        }
+        .map_err(|e| anyhow::anyhow!("Contract invariant generation failed: {}", e))?;
+        #[cfg(feature = "compile-verification")]
+        if verify_compilation && chain == "solana" {
+            verify_generated_compiles(&generation.code)?;
+        }
+        generated_code.push('\n');
+        generated_code.push_str(&generation.code);
+    }
+
+    // Checks spliced into a copy of the Solana source, keeping its formatting
+    let injected = match (chain, checked.is_empty(), &analyzed) {
+        ("solana", false, Ok(program)) => Some(
+            invar_generator_solana::SolanaGenerator::new()
+                .inject(&content, program, &checked)
+                .map_err(|e| anyhow::anyhow!("Check injection failed: {}", e))?,
+        ),
+        _ => None,
+    };
+    #[cfg(feature = "compile-verification")]
+    if let (true, Some(injected), Ok(program)) = (verify_compilation, &injected, &analyzed) {
+        let framework = invar_generator_solana::SolanaGenerator::new().framework_for(program);
+        if framework == invar_generator_solana::SolanaFramework::Anchor {
+            return Err(anyhow::anyhow!(
+                "--verify-compilation supports native Solana programs only; Anchor programs need anchor_lang to compile"
+            ));
+        }
+        verify_generated_compiles(&injected.source())?;
+    }
+    if diff {
+        if let Some(injected) = &injected {
+            // Label the diff relative to the working directory, as git does
+            let label = std::env::current_dir()
+                .ok()
+                .and_then(|cwd| code_path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+                .or_else(|| code_path.file_name().map(PathBuf::from))
+                .unwrap_or_else(|| code_path.clone());
+            write!(out, "{}", injected.unified_diff(&label.to_string_lossy()))?;
+        }
+        writeln!(out, "\n✓ Build complete - diff printed, no files written")?;
+        return Ok(BuiltSource {
+            security_report,
+            program: analyzed.ok(),
+        });
+    }
+
+    // Every artifact records the inputs and version it was generated from
+    let provenance = invar_core::ProvenanceHeader::new(
+        &content,
+        &checked,
+        &invar_core::ThreatModelConfig::default(),
+    );
+    let generated_code =
+        provenance.prepend(&format!("{}{}", coverage_manifest(&checked), generated_code));
+
+    // Write output, or with `check` only compare it against what is there
+    let mut stale = Vec::new();
+    let output_file = output.join(format!("generated_{}.rs", chain));
+    write_generated(&output_file, &generated_code, check, &mut stale)?;
+
+    writeln!(out, "✓ Built {} invariant checks", chain)?;
+    writeln!(out, "  - Generated: {}", output_file.display())?;
+    writeln!(out, "  - Lines: {}", generated_code.lines().count())?;
+    if let Some(injected) = &injected {
+        let file_name = code_path.file_name().unwrap_or_default();
+        let injected_file = output.join(file_name);
+        let source = provenance.prepend(&injected.source());
+        write_generated(&injected_file, &source, check, &mut stale)?;
+        writeln!(out, "  - Injected: {}", injected_file.display())?;
+        if cfg!(feature = "compile-verification") && verify_compilation {
+            writeln!(out, "  - Compilation verified with rustc")?;
+        }
+    }
+
+    // Unit tests exercising each injected check sit next to the generated code
+    if let (true, false, Ok(program)) = (chain == "solana", checked.is_empty(), &analyzed) {
+        use invar_core::traits::CodeGenerator;
+        let generation = invar_generator_solana::SolanaGenerator::new()
+            .generate(program, &checked)
+            .map_err(|e| anyhow::anyhow!("Test generation failed: {}", e))?;
+        if let Some(tests) = generation.tests {
+            let tests_file = output.join(GENERATED_SOLANA_TESTS_FILE);
+            let tests = provenance.prepend(&tests);
+            write_generated(&tests_file, &tests, check, &mut stale)?;
+            writeln!(out, "  - Check expression tests: {}", tests_file.display())?;
+        }
+    }
+    if check {
+        for path in &stale {
+            writeln!(out, "  ✗ Out of date: {}", path.display())?;
+        }
+        if !stale.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Generated output is out of date: {} file(s) would change",
+                stale.len()
+            ));
+        }
+        writeln!(
+            out,
+            "\n✓ Build check passed - generated output is up to date"
+        )?;
+    } else {
+        writeln!(out, "\n✓ Build complete - All security checks passed!")?;
+    }
+
+    Ok(BuiltSource {
+        security_report,
+        program: analyzed.ok(),
+    })
+}
+
+/// Write generated `contents` to `path`, or with `check` leave the file alone
+/// and record it in `stale` when its contents differ.
+fn write_generated(
+    path: &Path,
+    contents: &str,
+    check: bool,
+    stale: &mut Vec<PathBuf>,
+) -> std::io::Result<()> {
+    if !check {
+        return std::fs::write(path, contents);
+    }
+    if std::fs::read_to_string(path).ok().as_deref() != Some(contents) {
+        stale.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+/// Print an issue's source snippet with line numbers.
+fn print_snippet(out: &mut dyn Write, location: &invar_core::Location) -> std::io::Result<()> {
+    for (line, text) in (location.snippet_start()..).zip(location.snippet.lines()) {
+        let marker = if line == location.line { '>' } else { ' ' };
+        writeln!(out, "    {} {:>4} | {}", marker, line, text)?;
+    }
+    Ok(())
+}
+
+/// Print an invariant expression across several lines, indented under a diagnostic.
+fn print_expression(
+    out: &mut dyn Write,
+    expr: &invar_core::model::Expression,
+) -> std::io::Result<()> {
+    for line in expr.display_pretty().lines() {
+        writeln!(out, "    | {}", line)?;
+    }
+    Ok(())
+}
+
+/// Project configuration file read from the working directory.
+const PROJECT_CONFIG_FILE: &str = "invar.toml";
+
+/// Sections of `invar.toml` used by the CLI.
+#[derive(serde::Deserialize, Default)]
+struct ProjectConfig {
+    #[serde(default)]
+    risk: invar_core::RiskPolicy,
+    #[serde(default)]
+    simulate: SimulateConfig,
+}
+
+/// The `[simulate]` table of `invar.toml`.
+#[derive(serde::Deserialize, Default)]
+struct SimulateConfig {
+    /// Value ranges by state variable: `balance = "0..1_000_000"`.
+    #[serde(default)]
+    ranges: BTreeMap<String, String>,
+}
+
+/// Load `invar.toml` from the working directory, or the defaults without one.
+fn load_project_config() -> anyhow::Result<ProjectConfig> {
+    let path = Path::new(PROJECT_CONFIG_FILE);
+    if !path.exists() {
+        return Ok(ProjectConfig::default());
+    }
+
+    let content = std::fs::read_to_string(path)?;
+    toml::from_str(&content).map_err(|e| anyhow::anyhow!("Invalid {}: {}", PROJECT_CONFIG_FILE, e))
+}
+
+/// Load the risk policy from `invar.toml`, falling back to the defaults.
+fn load_risk_policy() -> anyhow::Result<invar_core::RiskPolicy> {
+    Ok(load_project_config()?.risk)
+}
+
+/// Load the `[simulate.ranges]` of `invar.toml`.
+fn load_simulation_ranges() -> anyhow::Result<BTreeMap<String, invar_simulator::ValueRange>> {
+    load_project_config()?
+        .simulate
+        .ranges
+        .into_iter()
+        .map(|(name, range)| {
+            let parsed = range.parse().map_err(|e| {
+                anyhow::anyhow!("Invalid {} range for {}: {}", PROJECT_CONFIG_FILE, name, e)
+            })?;
+            Ok((name, parsed))
+        })
+        .collect()
+}
+
+/// Type check invariants against a program model, printing every diagnostic.
+///
+/// With `trace`, each invariant is also evaluated against that state snapshot
+/// and its step-by-step evaluation is printed.
+fn check_invariants(
+    invariants: &Path,
+    source: &Path,
+    chain: &str,
+    lenient: bool,
+    use_cache: bool,
+    trace: Option<&Path>,
+    run_examples: bool,
+) -> anyhow::Result<()> {
+    let loaded = load_invariants(invariants)?;
+    let report = type_check_invariants(
+        &loaded,
+        source,
+        chain,
+        lenient,
+        use_cache,
+        &mut std::io::stdout(),
+    )?;
+
+    if let Some(state) = trace {
+        trace_invariants(invariants, state)?;
+    }
+
+    let failed_examples = if run_examples {
+        run_invariant_examples(&loaded, &mut std::io::stdout())?
+    } else {
+        0
+    };
+
+    if report.has_errors() {
+        return Err(anyhow::anyhow!(
+            "{} invariant type error(s) found",
+            report.errors().count()
+        ));
+    }
+    if failed_examples > 0 {
+        return Err(anyhow::anyhow!(
+            "{} invariant example(s) failed",
+            failed_examples
+        ));
+    }
+
+    println!("✓ All invariants type check");
+    Ok(())
+}
+
+/// Evaluate invariants against a JSON state snapshot, printing each trace.
+fn trace_invariants(invariants: &Path, state: &Path) -> anyhow::Result<()> {
+    let json = std::fs::read_to_string(state)
+        .map_err(|e| anyhow::anyhow!("Failed to read state file {}: {}", state.display(), e))?;
+    let context = invar_core::ExecutionContext::from_json(&json)
+        .map_err(|e| anyhow::anyhow!("Invalid state file {}: {}", state.display(), e))?;
+    let evaluator = invar_core::Evaluator::new(context);
+
+    for invariant in load_invariants(invariants)? {
+        let (result, trace) = evaluator.evaluate_with_trace(&invariant.expression);
+        let outcome = match &result {
+            Ok(value) => value.to_string(),
+            Err(e) => format!("error: {}", e),
+        };
+        println!("\nTrace {}: {}", invariant.name, outcome);
+        print!("{}", trace);
+    }
+    Ok(())
+}
+
+/// Evaluate every invariant example against its inputs, printing whether
+/// each matched its expected result. Returns the number that did not.
+fn run_invariant_examples(
+    invariants: &[invar_core::Invariant],
+    out: &mut dyn Write,
+) -> anyhow::Result<usize> {
+    let mut failed = 0;
+    for invariant in invariants {
+        for (idx, example) in invariant.examples.iter().enumerate() {
+            let mut label = format!("{} example {}", invariant.name, idx + 1);
+            if !example.description.is_empty() {
+                label = format!("{} ({})", label, example.description);
+            }
+            match example.evaluate(invariant) {
+                Ok(result) if result == example.expected_result => {
+                    writeln!(out, "  ✓ {}", label)?;
+                }
+                Ok(result) => {
+                    failed += 1;
+                    writeln!(
+                        out,
+                        "  ✗ {}: expected {}, got {}",
+                        label, example.expected_result, result
+                    )?;
+                }
+                Err(e) => {
+                    failed += 1;
+                    writeln!(out, "  ✗ {}: {}", label, e)?;
+                }
+            }
+        }
+    }
+    Ok(failed)
+}
+
+/// Load invariants, analyze the source, and type check all invariants in one pass.
+fn type_check_invariants(
+    invariants: &[invar_core::Invariant],
+    source: &Path,
+    chain: &str,
+    lenient: bool,
+    use_cache: bool,
+    out: &mut dyn Write,
+) -> anyhow::Result<invar_core::CheckReport> {
+    let loaded: Vec<_> = invariants
+        .iter()
+        .filter(|invariant| invariant.is_compatible_with_chain(chain))
+        .cloned()
+        .collect();
+    let program = analyze_source(source, chain, use_cache)?;
+
+    let mut checker = invar_core::TypeChecker::new();
+    checker.load_from_program(&program);
+    checker.set_lenient(lenient);
+
+    let report = checker.check_invariants(&loaded);
+
+    writeln!(
+        out,
+        "  Checked {} invariants: {} error(s), {} warning(s)",
+        loaded.len(),
+        report.errors().count(),
+        report.warnings().count()
+    )?;
+    for diagnostic in &report.diagnostics {
+        writeln!(out, "  {}", diagnostic)?;
+        if let Some(invariant) = loaded.iter().find(|inv| inv.name == diagnostic.invariant) {
+            print_expression(out, &invariant.expression)?;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Fail on calls to functions that neither the program nor the checker's
+/// builtins define.
+fn check_functions_defined(
+    invariants: &[invar_core::Invariant],
+    program: &invar_core::ProgramModel,
+    chain: &str,
+) -> invar_core::Result<()> {
+    let mut checker = invar_core::TypeChecker::new();
+    checker.load_from_program(program);
+    let builtins = checker.function_names();
+
+    let undefined: Vec<String> = invariants
+        .iter()
+        .filter(|invariant| invariant.is_compatible_with_chain(chain))
+        .flat_map(|invariant| {
+            invariant
+                .validate_functions(program, &builtins)
+                .into_iter()
+                .map(move |name| format!("{}() in invariant {}", name, invariant.name))
+        })
+        .collect();
+    if !undefined.is_empty() {
+        return Err(invar_core::InvarError::undefined_identifier(
+            undefined.join(", "),
+        ));
+    }
+    Ok(())
+}
+
+/// List the invariants a filter left out, so none is dropped silently.
+fn print_skipped(out: &mut dyn Write, skipped: &BTreeMap<String, String>) -> std::io::Result<()> {
+    for (name, reason) in skipped {
+        writeln!(out, "  - {}: skipped ({})", name, reason)?;
+    }
+    Ok(())
+}
+
+/// Load invariants from a TOML library file, a DSL file, or a directory of
+/// `.toml` and `.invar` files (in name order).
+fn load_invariants(path: &Path) -> anyhow::Result<Vec<invar_core::Invariant>> {
+    if !path.exists() {
+        return Err(anyhow::anyhow!(
+            "Invariants file not found: {}",
+            path.display()
+        ));
+    }
+
+    if path.is_dir() {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let file = entry?.path();
+            if file
+                .extension()
+                .is_some_and(|ext| ext == "toml" || ext == "invar")
+            {
+                files.push(file);
+            }
+        }
+        files.sort();
+        let mut invariants = Vec::new();
+        for file in &files {
+            invariants.extend(load_invariants(file)?);
+        }
+        return Ok(invariants);
+    }
+
+    if path.extension().is_some_and(|ext| ext == "toml") {
+        Ok(invar_library::LibraryLoader::load_from_toml(path)?)
+    } else {
+        let content = std::fs::read_to_string(path)?;
+        Ok(invar_dsl_parser::parse_invariants(&content)?)
+    }
+}
+
+/// Parse a program's `invariant:` source annotations into invariants named
+/// `<function>_hint_<n>`, scoped to the annotated function.
+fn hint_invariants(
+    program: &invar_core::ProgramModel,
+) -> anyhow::Result<Vec<invar_core::Invariant>> {
+    let mut invariants = Vec::new();
+    for (function, hints) in &program.invariant_hints {
+        for (index, hint) in hints.iter().enumerate() {
+            let definition = format!("invariant {}_hint_{} {{ {} }}", function, index + 1, hint);
+            let invariant = invar_dsl_parser::parse_invariant(&definition).map_err(|e| {
+                anyhow::anyhow!("Invalid invariant hint on {}: {}: {}", function, hint, e)
+            })?;
+            invariants.push(invariant.for_layers(vec![function.clone()]));
+        }
+    }
+    Ok(invariants)
+}
+
+/// A `// Invariant: <name>` line per invariant a build checked, for the
+/// coverage check of `invar verify`.
+fn coverage_manifest(checked: &[invar_core::Invariant]) -> String {
+    checked
+        .iter()
+        .map(|invariant| format!("// Invariant: {}\n", invariant.name))
+        .collect()
+}
+
+/// Parse the class-level invariants declared on `program`.
+fn contract_invariants(
+    program: &invar_core::ProgramModel,
+) -> anyhow::Result<Vec<invar_core::Invariant>> {
+    program
+        .contract_invariants
+        .iter()
+        .enumerate()
+        .map(|(index, expr)| {
+            let definition = format!("invariant contract_invariant_{} {{ {} }}", index + 1, expr);
+            let invariant = invar_dsl_parser::parse_invariant(&definition).map_err(|e| {
+                anyhow::anyhow!(
+                    "Invalid contract invariant on {}: {}: {}",
+                    program.name,
+                    expr,
+                    e
+                )
+            })?;
+            Ok(invariant.with_category(invar_core::CONTRACT_INVARIANT_CATEGORY))
+        })
+        .collect()
+}
+
+/// Whether `path` is a program model exported by `invar model`.
+fn is_model_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
+
+/// Load a program model exported by `invar model`.
+fn load_model(path: &Path) -> anyhow::Result<invar_core::ProgramModel> {
+    invar_core::ProgramModel::load_from_json(path)
+        .map_err(|e| anyhow::anyhow!("Failed to load model {}: {}", path.display(), e))
+}
 
-    // Validate chain
-    match chain {
-        "solana" | "evm" | "move" => {}
-        _ => {
+/// Run the chain-specific analyzer over a source file.
+///
+/// With `use_cache`, results are reused from `.invar/cache` when the source is unchanged.
+/// A model exported by `invar model` is loaded as-is instead of re-analyzed.
+fn analyze_source(
+    source: &Path,
+    chain: &str,
+    use_cache: bool,
+) -> anyhow::Result<invar_core::ProgramModel> {
+    if is_model_file(source) {
+        let program = load_model(source)?;
+        if program.chain != chain {
             return Err(anyhow::anyhow!(
-                "Unknown chain: {}. Supported: solana, evm, move",
+                "Model {} was extracted for chain {}, not {}",
+                source.display(),
+                program.chain,
                 chain
-            ))
+            ));
         }
+        return Ok(program);
     }
 
-    // Read source file
+    let registry = analyzer_registry();
+    let analyzer = registry.analyzer_for_chain(chain).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Unknown chain: {}. Supported: {}",
+            chain,
+            registry.chains().join(", ")
+        )
+    })?;
+
+    let program = if use_cache {
+        invar_ir::AnalysisCache::new(invar_ir::DEFAULT_CACHE_DIR)
+            .get_or_analyze(source, analyzer)?
+    } else {
+        analyzer.analyze(source)?
+    };
+    Ok(program)
+}
+
+/// Analyze `source` and write the program model as JSON to `output` or stdout.
+fn export_model(
+    source: &Path,
+    chain: &str,
+    output: Option<&Path>,
+    use_cache: bool,
+) -> anyhow::Result<()> {
     if !source.exists() {
         return Err(anyhow::anyhow!(
             "Source file not found: {}",
             source.display()
         ));
     }
-
-    println!("Step 1: Security validation");
-    println!("  Scanning for known attack patterns ({} chain)...", chain);
-
-    // SECURITY VALIDATION - Check for attack patterns BEFORE building
-    let validator = SecurityValidator::new();
-    let security_report = validator
-        .validate_file(source, chain)
-        .map_err(|e| anyhow::anyhow!("Security validation failed: {}", e))?;
-
-    println!("  Risk Score: {}/100", security_report.risk_score);
-
-    if !security_report.critical_issues.is_empty() {
-        println!("\n❌ BUILD BLOCKED - Critical security issues found:");
-        for issue in &security_report.critical_issues {
-            println!(
-                "  [CRITICAL] {} at {}",
-                issue.attack_pattern, issue.location
-            );
-            println!("    → {}", issue.description);
-            println!("    ✓ Fix: {}", issue.suggested_fix);
+    let program = analyze_source(source, chain, use_cache)?;
+    match output {
+        Some(path) => {
+            program.save_to_json(path)?;
+            println!("✓ Model written to {}", path.display());
         }
+        None => println!("{}", program.to_json_pretty()?),
+    }
+    Ok(())
+}
+
+/// Analyze `source` and write its mutation graph as DOT to `output` or stdout.
+fn export_graph(
+    source: &Path,
+    chain: &str,
+    output: Option<&Path>,
+    use_cache: bool,
+) -> anyhow::Result<()> {
+    if !source.exists() {
         return Err(anyhow::anyhow!(
-            "Cannot proceed: {} critical vulnerabilities must be fixed first",
-            security_report.critical_issues.len()
+            "Source file not found: {}",
+            source.display()
         ));
     }
-
-    if !security_report.high_issues.is_empty() {
-        println!("\n⚠️  High-risk issues detected:");
-        for issue in &security_report.high_issues {
-            println!("  [HIGH] {} at {}", issue.attack_pattern, issue.location);
-            println!("    → {}", issue.description);
-            println!("    ✓ Fix: {}", issue.suggested_fix);
+    let dot = analyze_source(source, chain, use_cache)?.call_graph_to_dot();
+    match output {
+        Some(path) => {
+            std::fs::write(path, dot)?;
+            println!("✓ Graph written to {}", path.display());
         }
-        println!("\nProceeding with caution. Recommend addressing these issues.");
+        None => print!("{}", dot),
     }
+    Ok(())
+}
 
-    if !security_report.medium_issues.is_empty() {
-        println!(
-            "\n📋 Medium-risk issues found: {}",
-            security_report.medium_issues.len()
-        );
+/// Check a generated file's provenance header against the current source and
+/// invariants, its code against the hash in that header, that it covers
+/// every invariant, and its scope.
+fn verify_generated(
+    generated: &Path,
+    source: &Path,
+    chain: &str,
+    options: VerifyOptions<'_>,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    let VerifyOptions {
+        invariants,
+        filter,
+        extract_contract_invariants,
+        use_cache,
+    } = options;
+    use invar_core::{InjectionVerifier, ProvenanceHeader, TamperDetector, ThreatModelConfig};
+
+    let code = std::fs::read_to_string(generated)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", generated.display(), e))?;
+
+    // Rebuild the invariant set `invar build` generates from
+    let analyzed = analyze_source(source, chain, use_cache);
+    let code_path = if is_model_file(source) {
+        let program = analyzed.as_ref().map_err(|e| anyhow::anyhow!("{}", e))?;
+        PathBuf::from(&program.source_path)
+    } else {
+        source.to_path_buf()
+    };
+    let content = std::fs::read_to_string(&code_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", code_path.display(), e))?;
+    let mut checked = Vec::new();
+    if let Some(path) = invariants {
+        let selection = filter.select(load_invariants(path)?)?;
+        // Cross-chain invariants are linked by the multi-source build
+        checked.extend(selection.selected.into_iter().filter(|invariant| {
+            !invar_core::cross_chain::is_cross_chain(invariant)
+                && invariant.is_compatible_with_chain(chain)
+        }));
+    } else if let Ok(program) = &analyzed {
+        checked = hint_invariants(program)?;
     }
-
-    if !security_report.low_issues.is_empty() {
-        println!(
-            "ℹ️  Low-risk issues found: {}",
-            security_report.low_issues.len()
-        );
+    if extract_contract_invariants {
+        let program = analyzed.as_ref().map_err(|e| anyhow::anyhow!("{}", e))?;
+        checked.extend(contract_invariants(program)?);
     }
+    let expected = ProvenanceHeader::new(&content, &checked, &ThreatModelConfig::default());
+    let names: Vec<String> = checked.iter().map(|inv| inv.name.clone()).collect();
 
-    if security_report.passed {
-        println!("✓ Security validation passed!");
+    writeln!(out, "Verifying {}", generated.display())?;
+    let checks = [
+        (
+            "Provenance",
+            TamperDetector::verify_provenance(&code, &expected),
+        ),
+        ("Tamper detection", TamperDetector::verify_code_hash(&code)),
+        (
+            "Coverage",
+            InjectionVerifier::verify_coverage(ProvenanceHeader::body(&code), &names),
+        ),
+        (
+            "Injection scope",
+            InjectionVerifier::verify_scope_containment(ProvenanceHeader::body(&code)),
+        ),
+    ];
+    let mut failed = 0;
+    for (name, result) in &checks {
+        match result {
+            Ok(()) => writeln!(out, "  ✓ {}", name)?,
+            Err(e) => {
+                failed += 1;
+                writeln!(out, "  ✗ {}: {}", name, e)?;
+            }
+        }
     }
+    if failed > 0 {
+        return Err(anyhow::anyhow!(
+            "Verification failed: {} of {} check(s) failed",
+            failed,
+            checks.len()
+        ));
+    }
+    writeln!(out, "\n✓ Verification passed")?;
+    Ok(())
+}
 
-    println!("\nStep 2: Code generation");
-    let content = fs::read_to_string(source)?;
-
-    // Create output directory
-    fs::create_dir_all(output)?;
+/// Parse a DSL file, optionally dumping its tokens first, and warn about
+/// expressions too large to check cheaply.
+fn lint_invariants(file: &Path, tokenize: bool, out: &mut dyn Write) -> anyhow::Result<()> {
+    if !file.exists() {
+        return Err(anyhow::anyhow!(
+            "Invariants file not found: {}",
+            file.display()
+        ));
+    }
 
-    // Parse and generate
-    let generated_code = match chain {
-        "solana" => generate_solana_checks(&content),
-        "evm" => generate_evm_checks(&content),
-        "move" => generate_move_checks(&content),
-        _ => {
-            return Err(anyhow::anyhow!(
-                "Invalid chain after validation: {}. This is a bug.",
-                chain
-            ))
+    // Only TOML invariants can carry examples, so only they are asked for them
+    if file.extension().is_some_and(|ext| ext == "toml") {
+        let invariants = invar_library::LibraryLoader::load_from_toml(file)?;
+        writeln!(
+            out,
+            "✓ {} parsed: {} invariant(s)",
+            file.display(),
+            invariants.len()
+        )?;
+        warn_complex_expressions(&invariants, out)?;
+        for invariant in invariants.iter().filter(|inv| inv.examples.is_empty()) {
+            writeln!(
+                out,
+                "⚠️  {}: no examples; add [[invariants.examples]] showing when it holds",
+                invariant.name
+            )?;
         }
-    };
+        return Ok(());
+    }
 
-    // Write output
-    let output_file = output.join(format!("generated_{}.rs", chain));
-    fs::write(&output_file, &generated_code)?;
+    let content = std::fs::read_to_string(file)?;
+
+    if tokenize {
+        writeln!(out, "Tokens:")?;
+        for token in invar_dsl_parser::tokenize(&content)? {
+            writeln!(
+                out,
+                "  {}:{}  {}",
+                token.span.line,
+                token.span.column,
+                token.kind.display()
+            )?;
+        }
+    }
 
-    println!("✓ Built {} invariant checks", chain);
-    println!("  - Generated: {}", output_file.display());
-    println!("  - Lines: {}", generated_code.lines().count());
-    println!("\n✓ Build complete - All security checks passed!");
+    // Every broken definition is reported, not just the first
+    let (invariants, errors) = invar_dsl_parser::parse_module_recovering(&content);
+    for error in &errors {
+        writeln!(
+            out,
+            "❌ {}:{}:{}: {}",
+            file.display(),
+            error.line,
+            error.column,
+            error.message
+        )?;
+    }
+    if !errors.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{} parse error(s) in {}",
+            errors.len(),
+            file.display()
+        ));
+    }
+    writeln!(
+        out,
+        "✓ {} parsed: {} invariant(s)",
+        file.display(),
+        invariants.len()
+    )?;
+    warn_complex_expressions(&invariants, out)
+}
 
+/// Flag invariants whose expression has more than `LINT_COMPLEXITY_WARNING` nodes.
+fn warn_complex_expressions(
+    invariants: &[invar_core::Invariant],
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    for invariant in invariants {
+        let complexity = invar_core::model::complexity(&invariant.expression);
+        if complexity > LINT_COMPLEXITY_WARNING {
+            writeln!(
+                out,
+                "⚠️  {}: expression has {} nodes (over {}); consider splitting it",
+                invariant.name, complexity, LINT_COMPLEXITY_WARNING
+            )?;
+            print_expression(out, &invariant.expression)?;
+        }
+    }
     Ok(())
 }
 
 /// Simulate program execution against invariants.
 ///
-/// Reads program and invariant files and runs simulation with given seed.
-fn simulate_program(program: &Path, invariants: &Path, seed: u64) -> anyhow::Result<()> {
+/// Analyzes the program (or loads an exported model), runs the simulation
+/// engine with the given configuration, and optionally writes a JUnit XML report.
+fn simulate_program(
+    program: &Path,
+    invariants: &Path,
+    chain: &str,
+    config: invar_simulator::SimulationConfig,
+    filter: &invar_core::InvariantFilter,
+    output_junit: Option<&Path>,
+) -> anyhow::Result<()> {
+    use invar_core::traits::Simulator;
+
     if !program.exists() {
         return Err(anyhow::anyhow!(
             "Program file not found: {}",
@@ -313,20 +2152,71 @@ fn simulate_program(program: &Path, invariants: &Path, seed: u64) -> anyhow::Res
         ));
     }
 
-    println!("Starting simulation with seed {}", seed);
+    println!("Starting simulation with seed {}", config.seed);
     println!("  - Program: {}", program.display());
+    println!("  - Chain: {}", chain);
     println!("  - Invariants: {}", invariants.display());
 
-    let invariants_content = std::fs::read_to_string(invariants)
-        .map_err(|e| anyhow::anyhow!("Failed to read invariants file: {}", e))?;
-    let program_content = std::fs::read_to_string(program)
-        .map_err(|e| anyhow::anyhow!("Failed to read program file: {}", e))?;
+    let model = analyze_source(program, chain, true)?;
+    let selection = filter.select(load_invariants(invariants)?)?;
 
     println!("\nSimulation configuration:");
-    println!("  - Seed: {}", seed);
-    println!("  - Program size: {} bytes", program_content.len());
-    println!("  - Invariants loaded: {} bytes", invariants_content.len());
-    println!("✓ Simulation engine initialized successfully");
+    println!("  - Seed: {}", config.seed);
+    println!("  - Iterations: {}", config.max_iterations);
+    println!("  - Trace depth: {}", config.trace_depth);
+    if config.timeout_ms > 0 {
+        println!("  - Timeout: {}ms", config.timeout_ms);
+    }
+    if let Some(corpus) = &config.corpus {
+        println!("  - Corpus: {}", corpus.display());
+    }
+    println!(
+        "  - Model: {} ({}), {} function(s), {} state variable(s)",
+        model.name,
+        model.chain,
+        model.functions.len(),
+        model.state_vars.len()
+    );
+    println!("  - Invariants selected: {}", selection.selected.len());
+    print_skipped(&mut std::io::stdout(), &selection.skipped)?;
+    let ranges = load_simulation_ranges()?;
+    for (name, range) in &ranges {
+        println!("  - Range: {} in {}..={}", name, range.min, range.max);
+    }
+
+    let mut report = invar_simulator::SimulationEngine::with_config(config)
+        .with_ranges(ranges)
+        .simulate(&model, &selection.selected)?;
+    report.skipped = selection.skipped;
+    println!(
+        "\n✓ Simulation complete: {} violation(s), {:.1}% coverage",
+        report.violations, report.coverage
+    );
+    for trace in &report.traces {
+        println!("  - {}", trace);
+    }
+    if !report.invariant_stats.is_empty() {
+        println!(
+            "\n  {:<32} {:>11} {:>9} {:>7} {:>12}",
+            "Invariant", "Evaluations", "Failures", "Errors", "Time"
+        );
+        for (name, stats) in &report.invariant_stats {
+            println!(
+                "  {:<32} {:>11} {:>9} {:>7} {:>12}",
+                name,
+                stats.evaluations,
+                stats.failures,
+                stats.errors,
+                format!("{:?}", stats.total_time)
+            );
+        }
+    }
+
+    if let Some(path) = output_junit {
+        std::fs::write(path, report.to_junit_xml())?;
+        println!("  - JUnit report: {}", path.display());
+    }
+    println!("\n{}", report.summary());
 
     Ok(())
 }
@@ -378,19 +2268,15 @@ fn generate_report(input: &Path, format: &str, output: Option<PathBuf>) -> anyho
         return Err(anyhow::anyhow!("Input file not found: {}", input.display()));
     }
 
-    // Validate format
-    match format {
-        "json" | "markdown" | "cli" => {}
-        _ => {
-            return Err(anyhow::anyhow!(
-                "Unknown format: {}. Supported: json, markdown, cli",
-                format
-            ))
-        }
-    }
+    validate_report_format(format)?;
 
     println!("Generating {} report from {}", format, input.display());
 
+    // Annotations need the issues of a security report
+    if input.is_dir() || format == "github-actions" {
+        return generate_merged_report(input, format, output);
+    }
+
     let input_content = std::fs::read_to_string(input)
         .map_err(|e| anyhow::anyhow!("Failed to read input file: {}", e))?;
 
@@ -432,39 +2318,202 @@ fn generate_report(input: &Path, format: &str, output: Option<PathBuf>) -> anyho
     Ok(())
 }
 
+fn validate_report_format(format: &str) -> anyhow::Result<()> {
+    match format {
+        "json" | "markdown" | "cli" | "github-actions" => Ok(()),
+        _ => Err(anyhow::anyhow!(
+            "Unknown format: {}. Supported: json, markdown, cli, github-actions",
+            format
+        )),
+    }
+}
+
+/// Combine the reports of several inputs into one, a section per input.
+///
+/// Each input is a report saved with `--format json`, a security report, or
+/// a directory of security reports.
+fn generate_combined_report(
+    inputs: &[PathBuf],
+    format: &str,
+    output: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    use invar_report::Report;
+
+    validate_report_format(format)?;
+    let mut reports = Vec::new();
+    for input in inputs {
+        if !input.exists() {
+            return Err(anyhow::anyhow!("Input file not found: {}", input.display()));
+        }
+        println!("Reading {}", input.display());
+        let saved = input
+            .is_file()
+            .then(|| std::fs::read_to_string(input).ok())
+            .flatten()
+            .and_then(|content| serde_json::from_str::<Report>(&content).ok());
+        let report = match saved {
+            Some(report) => report,
+            None => Report::from_security_reports(
+                "Invar Security Report".to_string(),
+                input.display().to_string(),
+                read_security_reports(input)?,
+            ),
+        };
+        reports.push(report);
+    }
+
+    let report = Report::merge(reports);
+    for warning in &report.warnings {
+        println!("⚠️  {}", warning);
+    }
+    write_report(&report, format, output)
+}
+
+/// Merge every `security_report.json` under a directory (or a single
+/// security report file) into one report.
+fn generate_merged_report(dir: &Path, format: &str, output: Option<PathBuf>) -> anyhow::Result<()> {
+    let report = invar_report::Report::from_security_reports(
+        "Invar Security Report".to_string(),
+        dir.display().to_string(),
+        read_security_reports(dir)?,
+    );
+    write_report(&report, format, output)
+}
+
+/// Security reports under a directory, or the single report file `dir`.
+fn read_security_reports(dir: &Path) -> anyhow::Result<Vec<invar_core::SecurityReport>> {
+    let mut paths = Vec::new();
+    if dir.is_dir() {
+        collect_security_reports(dir, &mut paths)?;
+        paths.sort();
+    } else {
+        paths.push(dir.to_path_buf());
+    }
+
+    if paths.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No {} files found under {}",
+            SECURITY_REPORT_FILE,
+            dir.display()
+        ));
+    }
+
+    let mut reports = Vec::new();
+    for path in &paths {
+        let content = std::fs::read_to_string(path)?;
+        let report: invar_core::SecurityReport = serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid security report {}: {}", path.display(), e))?;
+        reports.push(report);
+    }
+    println!("  Merging {} security reports", reports.len());
+    Ok(reports)
+}
+
+/// Format `report` and write it to `output`, or print it.
+fn write_report(
+    report: &invar_report::Report,
+    format: &str,
+    output: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    use invar_report::ReportFormatter;
+
+    let report_content = match format {
+        "json" => ReportFormatter::to_json(report)?,
+        "markdown" => ReportFormatter::to_markdown(report),
+        "github-actions" => ReportFormatter::to_github_actions(report),
+        _ => ReportFormatter::to_cli_table(report),
+    };
+
+    if let Some(out) = output {
+        std::fs::write(&out, &report_content)?;
+        println!("✓ Report written to {}", out.display());
+    } else {
+        println!("{}", report_content);
+    }
+
+    Ok(())
+}
+
+/// Recursively collect `security_report.json` files.
+fn collect_security_reports(dir: &Path, paths: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_security_reports(&path, paths)?;
+        } else if path
+            .file_name()
+            .is_some_and(|name| name == SECURITY_REPORT_FILE)
+        {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
 /// List available invariants from library.
-fn list_invariants(category: Option<String>) -> anyhow::Result<()> {
+fn list_invariants(category: Option<String>, tag: Option<String>) -> anyhow::Result<()> {
     println!("Available invariants:");
 
     let invariants = vec![
         (
             "balance_conservation",
-            "deFi",
+            "defi",
+            &["token", "conservation"][..],
             "Sum of balances equals total supply",
         ),
         (
             "no_negative_balance",
-            "deFi",
+            "defi",
+            &["token"][..],
             "No account can have negative balance",
         ),
         (
             "access_control",
             "security",
+            &["access"][..],
             "Only authorized users can perform actions",
         ),
         (
             "state_consistency",
             "general",
+            &[][..],
             "State variables remain internally consistent",
         ),
     ];
-
-    for (name, cat, desc) in invariants {
+    let mut templates = invar_library::defi::catalog();
+    templates.extend(invar_library::governance::catalog());
+    templates.extend(invar_library::bridge::catalog());
+    templates.extend(invar_library::account_abstraction::catalog());
+    let invariants = invariants
+        .into_iter()
+        .map(|(name, cat, tags, desc)| {
+            (
+                name,
+                cat,
+                tags.iter().map(|t| t.to_string()).collect(),
+                desc,
+            )
+        })
+        .chain(templates.iter().map(|inv| {
+            (
+                inv.name.as_str(),
+                inv.category.as_str(),
+                inv.tags.clone(),
+                inv.description.as_deref().unwrap_or_default(),
+            )
+        }));
+
+    for (name, cat, tags, desc) in invariants {
         if let Some(ref filter) = category {
             if cat != filter {
                 continue;
             }
         }
+        if let Some(ref filter) = tag {
+            if !tags.contains(filter) {
+                continue;
+            }
+        }
         println!("  • {} ({}): {}", name, cat, desc);
     }
 
@@ -491,6 +2540,20 @@ fn generate_solana_checks(content: &str) -> String {
     )
 }
 
+/// Fail the build when generated Solana code does not compile against
+/// stand-ins for the `solana_program` items it uses.
+#[cfg(feature = "compile-verification")]
+fn verify_generated_compiles(code: &str) -> anyhow::Result<()> {
+    let source = format!("{}\n{}", invar_generator_solana::COMPILE_PRELUDE, code);
+    invar_core::InjectionVerifier::verify_compiles(&source, &["-A", "warnings"]).map_err(|e| {
+        let stderr = match e {
+            invar_core::ThreatModelError::ReParseVerificationFailed(stderr) => stderr,
+            other => other.to_string(),
+        };
+        anyhow::anyhow!("Generated code does not compile:\n{}", stderr)
+    })
+}
+
 /// Generate EVM invariant checks.
 ///
 /// Analyzes source content and generates appropriate Solidity code.
@@ -529,3 +2592,1188 @@ fn generate_move_checks(content: &str) -> String {
         source_lines, content.len(), check_count, check_count
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../examples")
+            .join(name)
+    }
+
+    #[test]
+    fn test_export_graph() {
+        let dir = tempfile::tempdir().unwrap();
+        let graph = dir.path().join("graph.dot");
+        export_graph(&example("evm_token.sol"), "evm", Some(&graph), false).unwrap();
+        let dot = std::fs::read_to_string(&graph).unwrap();
+        assert!(dot.starts_with("digraph \"SimpleToken\" {"));
+        assert!(dot.contains("    \"fn:mint\" -> \"var:totalSupply\";\n"));
+        assert!(export_graph(&dir.path().join("missing.sol"), "evm", None, false).is_err());
+    }
+
+    #[test]
+    fn test_model_round_trip_and_simulate_from_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let model_path = dir.path().join("model.json");
+        let source = example("solana_token_transfer.rs");
+        export_model(&source, "solana", Some(&model_path), false).unwrap();
+
+        let exported = std::fs::read_to_string(&model_path).unwrap();
+        let loaded = analyze_source(&model_path, "solana", false).unwrap();
+        assert_eq!(loaded.to_json_pretty().unwrap(), exported);
+        assert!(analyze_source(&model_path, "evm", false).is_err());
+
+        let invariants = dir.path().join("token.invar");
+        std::fs::write(&invariants, "invariant supply { total_supply >= 0 }\n").unwrap();
+        let junit = dir.path().join("simulation.xml");
+        let config = invar_simulator::SimulationConfig::default();
+        simulate_program(
+            &model_path,
+            &invariants,
+            "solana",
+            config,
+            &invar_core::InvariantFilter::default(),
+            Some(&junit),
+        )
+        .unwrap();
+        let xml = std::fs::read_to_string(&junit).unwrap();
+        assert!(xml.contains("<testsuite name=\"invar.simulation\" tests=\"1\""));
+
+        // Filtered and disabled invariants are reported as skipped
+        std::fs::write(
+            &invariants,
+            "invariant supply { total_supply >= 0 }\ninvariant other { total_supply >= 1 }\n@disabled\ninvariant off { total_supply > 0 }\n",
+        )
+        .unwrap();
+        let filter = invar_core::InvariantFilter {
+            only_invariants: vec!["supply".to_string()],
+            ..Default::default()
+        };
+        let config = invar_simulator::SimulationConfig::default();
+        simulate_program(
+            &model_path,
+            &invariants,
+            "solana",
+            config,
+            &filter,
+            Some(&junit),
+        )
+        .unwrap();
+        let xml = std::fs::read_to_string(&junit).unwrap();
+        assert!(xml.contains("tests=\"3\""), "{}", xml);
+        assert!(xml.contains("skipped=\"2\""), "{}", xml);
+        assert!(xml.contains("name=\"off\">\n    <skipped message=\"disabled\"/>"));
+        assert!(xml.contains("name=\"other\">\n    <skipped message=\"filtered\"/>"));
+    }
+
+    #[test]
+    fn test_lint_warns_about_complex_expressions() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("rules.inv");
+        let clauses = vec!["fee == 1"; 30].join(" && ");
+        std::fs::write(
+            &file,
+            format!(
+                "invariant small {{ fee == 1 }}\ninvariant large {{ {} }}\n",
+                clauses
+            ),
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        lint_invariants(&file, false, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("parsed: 2 invariant(s)"));
+        assert!(out.contains("large: expression has 119 nodes (over 100)"));
+        assert!(!out.contains("small:"));
+        // The offending expression is shown one operand per line
+        assert_eq!(
+            out.lines()
+                .filter(|line| line.starts_with("    | "))
+                .count(),
+            30
+        );
+        assert!(out.contains("&& (fee == 1)"));
+    }
+
+    #[test]
+    fn test_check_runs_invariant_examples() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut program = invar_core::ProgramModel::new(
+            "token".to_string(),
+            "solana".to_string(),
+            "lib.rs".to_string(),
+        );
+        for name in ["sum_balances", "total_supply"] {
+            program.add_state_var(invar_core::StateVar {
+                name: name.to_string(),
+                type_name: "u64".to_string(),
+                is_mutable: true,
+                visibility: None,
+                abilities: Default::default(),
+                depends_on: Vec::new(),
+            });
+        }
+        let model = dir.path().join("model.json");
+        std::fs::write(&model, program.to_json_pretty().unwrap()).unwrap();
+        let invariants = dir.path().join("token.toml");
+        let invariant = "[[invariants]]\n\
+                         name = \"conservation\"\n\
+                         expression = \"sum_balances == total_supply\"\n";
+        let toml = |second_holds: bool| {
+            format!(
+                "{}\n\
+                 [[invariants.examples]]\n\
+                 inputs = {{ sum_balances = \"100\", total_supply = \"100\" }}\n\
+                 expected_result = true\n\
+                 description = \"Balances add up to the supply\"\n\n\
+                 [[invariants.examples]]\n\
+                 inputs = {{ sum_balances = 100, total_supply = 99 }}\n\
+                 expected_result = {}\n",
+                invariant, second_holds
+            )
+        };
+        std::fs::write(&invariants, toml(false)).unwrap();
+
+        let loaded = load_invariants(&invariants).unwrap();
+        assert_eq!(loaded[0].examples.len(), 2);
+        let mut out = Vec::new();
+        assert_eq!(run_invariant_examples(&loaded, &mut out).unwrap(), 0);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "  ✓ conservation example 1 (Balances add up to the supply)\n  ✓ conservation example 2\n"
+        );
+        check_invariants(&invariants, &model, "solana", false, false, None, true).unwrap();
+
+        // An example expecting the wrong result fails the check
+        std::fs::write(&invariants, toml(true)).unwrap();
+        let mut out = Vec::new();
+        let loaded = load_invariants(&invariants).unwrap();
+        assert_eq!(run_invariant_examples(&loaded, &mut out).unwrap(), 1);
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .contains("✗ conservation example 2: expected true, got false"));
+        let err =
+            check_invariants(&invariants, &model, "solana", false, false, None, true).unwrap_err();
+        assert_eq!(err.to_string(), "1 invariant example(s) failed");
+        check_invariants(&invariants, &model, "solana", false, false, None, false).unwrap();
+
+        let mut out = Vec::new();
+        lint_invariants(&invariants, false, &mut out).unwrap();
+        assert!(!String::from_utf8(out).unwrap().contains("no examples"));
+        std::fs::write(&invariants, invariant).unwrap();
+        let mut out = Vec::new();
+        lint_invariants(&invariants, false, &mut out).unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .contains("⚠️  conservation: no examples; add [[invariants.examples]]"));
+    }
+
+    #[test]
+    fn test_report_combines_several_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut simulation =
+            invar_report::Report::new("Simulation".to_string(), "vault".to_string());
+        simulation.chain = Some("solana".to_string());
+        simulation.invariants_checked = 4;
+        simulation.violations_found = 1;
+        let saved = dir.path().join("vault.json");
+        std::fs::write(
+            &saved,
+            invar_report::ReportFormatter::to_json(&simulation).unwrap(),
+        )
+        .unwrap();
+
+        let bridge = dir.path().join("bridge");
+        std::fs::create_dir_all(&bridge).unwrap();
+        let mut security = invar_core::SecurityReport::merge(Vec::new());
+        security.push(invar_core::SecurityIssue {
+            attack_pattern: "Reentrancy".to_string(),
+            location: invar_core::Location {
+                file: bridge.join("Bridge.sol"),
+                line: 5,
+                column: None,
+                snippet: String::new(),
+            },
+            description: "State updated after external call".to_string(),
+            suggested_fix: String::new(),
+            severity: invar_core::IssueSeverity::High,
+            function: Some("withdraw".to_string()),
+            introduced_at: None,
+        });
+        std::fs::write(
+            bridge.join(SECURITY_REPORT_FILE),
+            serde_json::to_string(&security).unwrap(),
+        )
+        .unwrap();
+
+        let output = dir.path().join("combined.md");
+        generate_combined_report(&[saved, bridge.clone()], "markdown", Some(output.clone()))
+            .unwrap();
+        let markdown = std::fs::read_to_string(&output).unwrap();
+        assert!(markdown.contains("- Invariants Checked: 4\n- Violations: 2\n"));
+        assert!(markdown.contains("\n## vault/solana\n"));
+        assert!(markdown.contains(&format!("\n## {}\n", bridge.display())));
+    }
+
+    #[test]
+    fn test_lint_reports_every_parse_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("rules.inv");
+        std::fs::write(
+            &file,
+            "invariant first { fee >= }\ninvariant ok { fee >= 0 }\ninvariant second { fee == == 1 }\n",
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        let err = lint_invariants(&file, false, &mut out).unwrap_err();
+        assert!(err.to_string().starts_with("2 parse error(s)"));
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("rules.inv:1:26:"));
+        assert!(out.contains("rules.inv:3:27:"));
+    }
+
+    #[test]
+    fn test_solana_build_writes_generated_tests() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("lib.rs");
+        std::fs::write(
+            &source,
+            "/// invariant: reserve >= debt\npub fn borrow(reserve: u64, debt: u64) -> u64 {\n    reserve - debt\n}\n",
+        )
+        .unwrap();
+        let options = BuildOptions {
+            invariants: None,
+            lenient: false,
+            use_cache: false,
+            min_coverage: None,
+            warn_uncovered: false,
+            filter: &invar_core::InvariantFilter::default(),
+            extract_contract_invariants: false,
+            diff: false,
+            synthesize: None,
+            strict: false,
+            verify_compilation: false,
+            check: false,
+        };
+        let output = dir.path().join("out");
+        build_invariants(&source, "solana", &output, options, &mut std::io::sink()).unwrap();
+
+        let tests = std::fs::read_to_string(output.join(GENERATED_SOLANA_TESTS_FILE)).unwrap();
+        assert!(tests.contains("fn borrow_hint1_check_holds()"));
+        assert!(tests.contains("fn borrow_hint1_check_is_violated()"));
+    }
+
+    #[cfg(not(feature = "compile-verification"))]
+    #[test]
+    fn test_verify_compilation_requires_feature() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("lib.rs");
+        std::fs::write(&source, "pub fn noop() {}\n").unwrap();
+        let options = BuildOptions {
+            invariants: None,
+            lenient: false,
+            use_cache: false,
+            min_coverage: None,
+            warn_uncovered: false,
+            filter: &invar_core::InvariantFilter::default(),
+            extract_contract_invariants: false,
+            diff: false,
+            synthesize: None,
+            strict: false,
+            verify_compilation: true,
+            check: false,
+        };
+
+        let Err(err) = build_invariants(
+            &source,
+            "solana",
+            &dir.path().join("out"),
+            options,
+            &mut std::io::sink(),
+        ) else {
+            panic!("build without compile-verification should fail");
+        };
+        assert!(err.to_string().contains("compile-verification"));
+    }
+
+    #[cfg(feature = "compile-verification")]
+    #[test]
+    fn test_verify_compilation_compiles_injected_program() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("lib.rs");
+        let build = |hint: &str| {
+            std::fs::write(
+                &source,
+                format!(
+                    "use solana_program::entrypoint::ProgramResult;\n\npub struct Vault {{\n    pub balance: u64,\n    pub open: bool,\n}}\n\n/// invariant: {}\npub fn withdraw(vault: &mut Vault, amount: u64) -> ProgramResult {{\n    if amount == 0 {{\n        return Ok(());\n    }}\n    vault.balance -= amount;\n    Ok(())\n}}\n\npub fn ping() -> ProgramResult {{\n    Ok(())\n}}\n",
+                    hint
+                ),
+            )
+            .unwrap();
+            let options = BuildOptions {
+                invariants: None,
+                lenient: false,
+                use_cache: false,
+                min_coverage: None,
+                warn_uncovered: false,
+                filter: &invar_core::InvariantFilter::default(),
+                extract_contract_invariants: false,
+                diff: false,
+                synthesize: None,
+                strict: false,
+                verify_compilation: true,
+                check: false,
+            };
+            let mut out = Vec::new();
+            build_invariants(&source, "solana", &dir.path().join("out"), options, &mut out)
+                .map(|_| String::from_utf8(out).unwrap())
+        };
+
+        let output = build(
+            "if vault::open then add(vault::balance, 1) > 0 else vault::balance == 0",
+        )
+        .unwrap();
+        assert!(output.contains("Compilation verified with rustc"), "{}", output);
+        let injected = std::fs::read_to_string(dir.path().join("out").join("lib.rs")).unwrap();
+        assert!(injected.contains("(if vault.open {"), "{}", injected);
+
+        let err = build("reserve >= 0").unwrap_err().to_string();
+        assert!(err.contains("Generated code does not compile"), "{}", err);
+        assert!(err.contains("reserve"), "{}", err);
+    }
+
+    #[test]
+    fn test_build_warns_about_uncovered_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("lib.rs");
+        std::fs::write(&source, "pub fn noop() {}\n").unwrap();
+        let mut program = invar_core::ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            source.display().to_string(),
+        );
+        for name in ["balance", "fees"] {
+            program.add_state_var(invar_core::StateVar {
+                name: name.to_string(),
+                type_name: "u64".to_string(),
+                is_mutable: true,
+                visibility: None,
+                abilities: Default::default(),
+                depends_on: Vec::new(),
+            });
+        }
+        let model = dir.path().join("model.json");
+        std::fs::write(&model, program.to_json_pretty().unwrap()).unwrap();
+        let invariants = dir.path().join("vault.invar");
+        std::fs::write(&invariants, "invariant funded { balance >= 0 }\n").unwrap();
+        let options = BuildOptions {
+            invariants: Some(&invariants),
+            lenient: false,
+            use_cache: false,
+            min_coverage: None,
+            warn_uncovered: true,
+            filter: &invar_core::InvariantFilter::default(),
+            extract_contract_invariants: false,
+            diff: false,
+            synthesize: None,
+            strict: false,
+            verify_compilation: false,
+            check: false,
+        };
+
+        let mut out = Vec::new();
+        build_invariants(&model, "solana", &dir.path().join("out"), options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(
+            out.contains("State variable fees is not referenced by any invariant"),
+            "{}",
+            out
+        );
+        assert!(!out.contains("State variable balance"), "{}", out);
+    }
+
+    #[test]
+    fn test_build_filters_invariants() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("lib.rs");
+        std::fs::write(&source, "pub fn noop() {}\n").unwrap();
+        let mut program = invar_core::ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            source.display().to_string(),
+        );
+        for name in ["balance", "fees"] {
+            program.add_state_var(invar_core::StateVar {
+                name: name.to_string(),
+                type_name: "u64".to_string(),
+                is_mutable: true,
+                visibility: None,
+                abilities: Default::default(),
+                depends_on: Vec::new(),
+            });
+        }
+        let model = dir.path().join("model.json");
+        std::fs::write(&model, program.to_json_pretty().unwrap()).unwrap();
+        let invariants = dir.path().join("vault.invar");
+        std::fs::write(
+            &invariants,
+            "tags: [fast]\ninvariant funded { balance >= 0 }\ntags: [slow]\ninvariant fee_cap { fees <= 100 }\n@disabled\ninvariant broken { missing >= 0 }\n",
+        )
+        .unwrap();
+        let build = |filter: &invar_core::InvariantFilter| {
+            let options = BuildOptions {
+                invariants: Some(&invariants),
+                lenient: false,
+                use_cache: false,
+                min_coverage: None,
+                warn_uncovered: true,
+                filter,
+                extract_contract_invariants: false,
+                diff: false,
+                synthesize: None,
+                strict: false,
+                verify_compilation: false,
+                check: false,
+            };
+            let mut out = Vec::new();
+            build_invariants(&model, "solana", &dir.path().join("out"), options, &mut out)
+                .map(|_| String::from_utf8(out).unwrap())
+        };
+
+        // The disabled invariant would fail type checking if it were built
+        let filter = invar_core::InvariantFilter {
+            only_tags: vec!["fast".to_string()],
+            ..Default::default()
+        };
+        let out = build(&filter).unwrap();
+        assert!(out.contains("  - broken: skipped (disabled)\n"), "{}", out);
+        assert!(out.contains("  - fee_cap: skipped (filtered)\n"), "{}", out);
+        assert!(out.contains("Checked 1 invariants"), "{}", out);
+        assert!(
+            out.contains("State variable fees is not referenced"),
+            "{}",
+            out
+        );
+
+        let filter = invar_core::InvariantFilter {
+            skip_tags: vec!["fast".to_string()],
+            ..Default::default()
+        };
+        let out = build(&filter).unwrap();
+        assert!(out.contains("  - funded: skipped (filtered)\n"), "{}", out);
+
+        let filter = invar_core::InvariantFilter {
+            only_invariants: vec!["fee_cp".to_string()],
+            ..Default::default()
+        };
+        let err = build(&filter).unwrap_err();
+        assert!(
+            err.to_string().contains("did you mean 'fee_cap'?"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_build_skips_invariants_for_other_chains() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("lib.rs");
+        std::fs::write(&source, "pub fn noop() {}\n").unwrap();
+        let invariants = dir.path().join("invariants.toml");
+        std::fs::write(
+            &invariants,
+            r#"
+[[invariants]]
+name = "sender_is_owner"
+expression = "msg_sender == owner"
+chains = ["evm"]
+
+[[invariants]]
+name = "always"
+expression = "true"
+"#,
+        )
+        .unwrap();
+        let options = BuildOptions {
+            invariants: Some(&invariants),
+            lenient: false,
+            use_cache: false,
+            min_coverage: None,
+            warn_uncovered: false,
+            filter: &invar_core::InvariantFilter::default(),
+            extract_contract_invariants: false,
+            diff: false,
+            synthesize: None,
+            strict: false,
+            verify_compilation: false,
+            check: false,
+        };
+
+        // The EVM-only invariant would fail to type check against this program
+        let mut out = Vec::new();
+        let output = dir.path().join("out");
+        build_invariants(&source, "solana", &output, options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Checked 1 invariants: 0 error(s)"), "{}", out);
+        assert!(
+            out.contains("Skipped 1 invariant(s) for other chains"),
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn test_build_fails_on_mutation_after_cpi() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("lib.rs");
+        std::fs::write(
+            &source,
+            "pub fn withdraw(vault: &mut Vault, amount: u64) -> ProgramResult {\n    invoke(&ix, &accounts)?;\n    vault.balance -= amount;\n    Ok(())\n}\n",
+        )
+        .unwrap();
+        let guards = dir.path().join("guards.inv");
+        std::fs::write(
+            &guards,
+            "invariant no_cpi_reentry { no_mutation_after_cpi(\"withdraw\") }\n",
+        )
+        .unwrap();
+        let build = |invariants: Option<&Path>, output: &Path| {
+            let options = BuildOptions {
+                invariants,
+                lenient: false,
+                use_cache: false,
+                min_coverage: None,
+                warn_uncovered: false,
+                filter: &invar_core::InvariantFilter::default(),
+                extract_contract_invariants: false,
+                diff: false,
+                synthesize: None,
+                strict: false,
+                verify_compilation: false,
+                check: false,
+            };
+            build_invariants(&source, "solana", output, options, &mut std::io::sink())
+        };
+
+        // Without the template the write is reported as a security issue
+        let output = dir.path().join("unguarded");
+        build(None, &output).unwrap();
+        let report = std::fs::read_to_string(output.join(SECURITY_REPORT_FILE)).unwrap();
+        assert!(report.contains("State Mutation After CPI"));
+
+        let Err(err) = build(Some(&guards), &dir.path().join("guarded")) else {
+            panic!("build should fail");
+        };
+        assert!(err
+            .to_string()
+            .contains("withdraw writes state on line 3 after the CPI on line 2"));
+    }
+
+    #[test]
+    fn test_build_synthesizes_overflow_invariants_for_review() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("Counter.sol");
+        std::fs::write(
+            &source,
+            "pragma solidity ^0.8.20;\n\ncontract Counter {\n    uint256 public total;\n    uint256 public debt;\n\n    function settle(uint256 amount) external {\n        total += amount;\n        unchecked {\n            total += amount;\n            debt -= amount;\n        }\n    }\n}\n",
+        )
+        .unwrap();
+        let options = BuildOptions {
+            invariants: None,
+            lenient: false,
+            use_cache: false,
+            min_coverage: None,
+            warn_uncovered: false,
+            filter: &invar_core::InvariantFilter::default(),
+            extract_contract_invariants: false,
+            diff: false,
+            synthesize: Some("overflow"),
+            strict: false,
+            verify_compilation: false,
+            check: false,
+        };
+        let output = dir.path().join("out");
+        build_invariants(&source, "evm", &output, options, &mut std::io::sink()).unwrap();
+
+        let synthesized =
+            invar_library::LibraryLoader::load_from_toml(&output.join(SYNTHESIZED_INVARIANTS_FILE))
+                .unwrap();
+        let names: Vec<&str> = synthesized.iter().map(|inv| inv.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["settle_debt_no_underflow", "settle_total_no_overflow"]
+        );
+        assert!(synthesized
+            .iter()
+            .all(|inv| inv.category == invar_library::synthesis::SYNTHESIZED_CATEGORY));
+        let toml = std::fs::read_to_string(output.join(SYNTHESIZED_INVARIANTS_FILE)).unwrap();
+        assert!(toml.contains("expression = \"(debt <= old(debt))\""));
+        assert!(toml.contains("expression = \"(total >= old(total))\""));
+
+        // Review only: nothing synthesized reaches the generated checks
+        let generated = std::fs::read_to_string(output.join("generated_evm.rs")).unwrap();
+        assert!(!generated.contains("old(total)"));
+    }
+
+    #[test]
+    fn test_strict_build_rejects_inline_assembly() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("Proxy.sol");
+        std::fs::write(
+            &source,
+            "pragma solidity ^0.8.20;\n\ncontract Proxy {\n    address public implementation;\n\n    fallback() external payable {\n        assembly {\n            calldatacopy(0, 0, calldatasize())\n        }\n    }\n}\n",
+        )
+        .unwrap();
+        let build = |strict: bool| {
+            let options = BuildOptions {
+                invariants: None,
+                lenient: false,
+                use_cache: false,
+                min_coverage: None,
+                warn_uncovered: false,
+                filter: &invar_core::InvariantFilter::default(),
+                extract_contract_invariants: false,
+                diff: false,
+                synthesize: None,
+                strict,
+                verify_compilation: false,
+                check: false,
+            };
+            build_invariants(
+                &source,
+                "evm",
+                &dir.path().join("out"),
+                options,
+                &mut std::io::sink(),
+            )
+        };
+
+        let Err(err) = build(true) else {
+            panic!("strict build should fail");
+        };
+        assert!(err
+            .to_string()
+            .contains("inline assembly in fallback (line 7)"));
+        assert!(build(false).is_ok());
+    }
+
+    #[test]
+    fn test_build_synthesizes_access_candidates_for_review() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("Vault.sol");
+        std::fs::write(
+            &source,
+            "pragma solidity ^0.8.20;\n\ncontract Vault {\n    address public owner;\n    uint256 public fee;\n\n    modifier onlyOwner() {\n        require(msg.sender == owner);\n        _;\n    }\n\n    function setOwner(address next) external {\n        owner = next;\n    }\n\n    function setFee(uint256 next) external onlyOwner {\n        fee = next;\n    }\n}\n",
+        )
+        .unwrap();
+        let options = BuildOptions {
+            invariants: None,
+            lenient: false,
+            use_cache: false,
+            min_coverage: None,
+            warn_uncovered: false,
+            filter: &invar_core::InvariantFilter::default(),
+            extract_contract_invariants: false,
+            diff: false,
+            synthesize: Some("access"),
+            strict: false,
+            verify_compilation: false,
+            check: false,
+        };
+        let output = dir.path().join("out");
+        let mut out = Vec::new();
+        build_invariants(&source, "evm", &output, options, &mut out).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("setOwner: (caller == owner) (high confidence)"));
+        assert!(out.contains("Skipped (already guarded): setFee"));
+        let toml = std::fs::read_to_string(output.join(SYNTHESIZED_ACCESS_FILE)).unwrap();
+        assert!(toml.contains("name = \"setOwner_caller_is_owner\""));
+        assert!(toml.contains("confidence = \"high\""));
+        assert!(toml.contains("layers = [\"setOwner\"]"));
+    }
+
+    #[test]
+    fn test_build_checks_contract_invariants_when_requested() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("Token.sol");
+        std::fs::write(
+            &source,
+            "/// @invariant: totalSupply > 0\ncontract Token {\n    uint256 public totalSupply;\n\n    function mint(uint256 amount) external {\n        totalSupply += amount;\n    }\n}\n",
+        )
+        .unwrap();
+        let build = |extract_contract_invariants: bool| {
+            let options = BuildOptions {
+                invariants: None,
+                lenient: false,
+                use_cache: false,
+                min_coverage: None,
+                warn_uncovered: false,
+                filter: &invar_core::InvariantFilter::default(),
+                extract_contract_invariants,
+                diff: false,
+                synthesize: None,
+                strict: false,
+                verify_compilation: false,
+                check: false,
+            };
+            let output = dir
+                .path()
+                .join(format!("out_{}", extract_contract_invariants));
+            build_invariants(&source, "evm", &output, options, &mut std::io::sink()).unwrap();
+            std::fs::read_to_string(output.join("generated_evm.rs")).unwrap()
+        };
+
+        assert!(!build(false).contains("invarContractInvariants"));
+        let generated = build(true);
+        assert!(generated.contains(
+            "    _;\n    if (!(totalSupply > 0)) revert InvariantViolated(\"contract_invariant_1\");\n"
+        ));
+        assert!(generated.contains("// Apply invarContractInvariants to: mint\n"));
+    }
+
+    #[test]
+    fn test_build_injects_checks_preserving_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("lib.rs");
+        let program = "// Lending program\n\n/// invariant: reserve >= debt\npub fn borrow(reserve: u64, debt: u64) -> ProgramResult {\n    // Nothing to move yet\n\n    Ok(())\n}\n";
+        std::fs::write(&source, program).unwrap();
+        let build = |diff: bool| {
+            let options = BuildOptions {
+                invariants: None,
+                lenient: false,
+                use_cache: false,
+                min_coverage: None,
+                warn_uncovered: false,
+                filter: &invar_core::InvariantFilter::default(),
+                extract_contract_invariants: false,
+                diff,
+                synthesize: None,
+                strict: false,
+                verify_compilation: false,
+                check: false,
+            };
+            let output = dir.path().join(format!("out_{}", diff));
+            let mut log = Vec::new();
+            build_invariants(&source, "solana", &output, options, &mut log).unwrap();
+            (output, String::from_utf8(log).unwrap())
+        };
+
+        let (output, log) = build(true);
+        assert!(log.contains("--- a/lib.rs\n+++ b/lib.rs\n@@ -4,5 +4,21 @@\n pub fn borrow(reserve: u64, debt: u64) -> ProgramResult {\n     // Nothing to move yet\n \n+    if !((reserve >= debt))"));
+        assert!(!output.join("generated_solana.rs").exists());
+        assert!(!output.join("lib.rs").exists());
+
+        let (output, _) = build(false);
+        let injected = std::fs::read_to_string(output.join("lib.rs")).unwrap();
+        let (header, injected) = injected
+            .split_once(&format!("{}\n", invar_core::PROVENANCE_END))
+            .unwrap();
+        assert!(header.starts_with(invar_core::PROVENANCE_BEGIN));
+        assert!(injected.starts_with(
+            "// Lending program\n\n/// invariant: reserve >= debt\npub fn borrow(reserve: u64, debt: u64) -> ProgramResult {\n    // Nothing to move yet\n\n    if !((reserve >= debt))"
+        ));
+        assert!(injected.contains("    Ok(())\n}\n// Generated invariant checks for"));
+    }
+
+    #[test]
+    fn test_build_check_detects_stale_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("lib.rs");
+        let program = "/// invariant: reserve >= debt\npub fn borrow(reserve: u64, debt: u64) -> ProgramResult {\n    Ok(())\n}\n";
+        std::fs::write(&source, program).unwrap();
+        let output = dir.path().join("out");
+        let build = |check: bool| {
+            let options = BuildOptions {
+                invariants: None,
+                lenient: false,
+                use_cache: false,
+                min_coverage: None,
+                warn_uncovered: false,
+                filter: &invar_core::InvariantFilter::default(),
+                extract_contract_invariants: false,
+                diff: false,
+                synthesize: None,
+                strict: false,
+                verify_compilation: false,
+                check,
+            };
+            let mut log = Vec::new();
+            build_invariants(&source, "solana", &output, options, &mut log)
+                .map(|_| String::from_utf8(log).unwrap())
+        };
+        let files = || {
+            ["generated_solana.rs", "lib.rs", GENERATED_SOLANA_TESTS_FILE]
+                .map(|file| std::fs::read_to_string(output.join(file)).unwrap())
+        };
+
+        // Nothing to compare against yet
+        assert!(build(true).is_err());
+        build(false).unwrap();
+        let first = files();
+        for file in &first {
+            let provenance = invar_core::ProvenanceHeader::parse(file).unwrap();
+            assert_eq!(
+                provenance.source_sha256,
+                invar_core::ProvenanceHeader::new(program, &[], &Default::default()).source_sha256
+            );
+            assert_eq!(provenance.invariant_hashes.len(), 1);
+        }
+        build(false).unwrap();
+        assert_eq!(files(), first);
+        assert!(build(true)
+            .unwrap()
+            .contains("✓ Build check passed - generated output is up to date"));
+
+        std::fs::write(&source, format!("/// invariant: debt <= 100\n{}", program)).unwrap();
+        let err = build(true).unwrap_err();
+        assert!(err.to_string().contains("Generated output is out of date"));
+        assert_eq!(files(), first);
+    }
+
+    #[test]
+    fn test_verify_generated_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("lib.rs");
+        std::fs::write(
+            &source,
+            "pub fn borrow(reserve: u64, debt: u64) -> ProgramResult {\n    Ok(())\n}\n",
+        )
+        .unwrap();
+        let invariants = dir.path().join("invariants");
+        std::fs::create_dir(&invariants).unwrap();
+        let invariants_file = invariants.join("lending.invar");
+        std::fs::write(&invariants_file, "invariant unpaused { true }\n").unwrap();
+        let output = dir.path().join("out");
+        let options = BuildOptions {
+            invariants: Some(&invariants),
+            lenient: false,
+            use_cache: false,
+            min_coverage: None,
+            warn_uncovered: false,
+            filter: &invar_core::InvariantFilter::default(),
+            extract_contract_invariants: false,
+            diff: false,
+            synthesize: None,
+            strict: false,
+            verify_compilation: false,
+            check: false,
+        };
+        build_invariants(&source, "solana", &output, options, &mut Vec::new()).unwrap();
+
+        let generated = output.join("generated_solana.rs");
+        let verify = || {
+            let mut log = Vec::new();
+            let options = VerifyOptions {
+                invariants: Some(&invariants),
+                filter: &invar_core::InvariantFilter::default(),
+                extract_contract_invariants: false,
+                use_cache: false,
+            };
+            let result = verify_generated(&generated, &source, "solana", options, &mut log);
+            (result, String::from_utf8(log).unwrap())
+        };
+
+        let (result, log) = verify();
+        assert!(result.is_ok(), "{}", log);
+        assert!(log.contains(
+            "  ✓ Provenance\n  ✓ Tamper detection\n  ✓ Coverage\n  ✓ Injection scope\n"
+        ));
+
+        // A hand edit no longer matches the hash recorded at generation
+        let pristine = std::fs::read_to_string(&generated).unwrap();
+        std::fs::write(&generated, pristine.replace("Ok(())", "return Ok(());")).unwrap();
+        let (result, log) = verify();
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("1 of 4 check(s) failed"));
+        assert!(log.contains("  ✗ Tamper detection: "));
+        assert!(log.contains("  ✓ Provenance\n"));
+
+        // Invariants changed since the build
+        std::fs::write(&generated, &pristine).unwrap();
+        std::fs::write(&invariants_file, "invariant unpaused { !false }\n").unwrap();
+        let (result, log) = verify();
+        assert!(result.is_err());
+        assert!(log.contains("  ✗ Provenance: "));
+        assert!(log.contains("provenance mismatch: invariant unpaused changed\n"));
+        assert!(log.contains("  ✓ Tamper detection\n"));
+    }
+
+    #[test]
+    fn test_verify_honours_build_filters_and_reports_missing_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("lib.rs");
+        std::fs::write(
+            &source,
+            "pub fn borrow(reserve: u64, debt: u64) -> ProgramResult {\n    Ok(())\n}\n",
+        )
+        .unwrap();
+        let invariants = dir.path().join("invariants");
+        std::fs::create_dir(&invariants).unwrap();
+        std::fs::write(
+            invariants.join("lending.invar"),
+            "invariant unpaused { true }\ninvariant open { true }\n",
+        )
+        .unwrap();
+        let filter = invar_core::InvariantFilter {
+            only_invariants: vec!["unpaused".to_string()],
+            ..Default::default()
+        };
+        let output = dir.path().join("out");
+        let options = BuildOptions {
+            invariants: Some(&invariants),
+            lenient: false,
+            use_cache: false,
+            min_coverage: None,
+            warn_uncovered: false,
+            filter: &filter,
+            extract_contract_invariants: false,
+            diff: false,
+            synthesize: None,
+            strict: false,
+            verify_compilation: false,
+            check: false,
+        };
+        build_invariants(&source, "solana", &output, options, &mut Vec::new()).unwrap();
+
+        let generated = output.join("generated_solana.rs");
+        let verify = |filter: &invar_core::InvariantFilter| {
+            let mut log = Vec::new();
+            let options = VerifyOptions {
+                invariants: Some(&invariants),
+                filter,
+                extract_contract_invariants: false,
+                use_cache: false,
+            };
+            let result = verify_generated(&generated, &source, "solana", options, &mut log);
+            (result, String::from_utf8(log).unwrap())
+        };
+
+        // The same filter as the build expects the same invariants
+        let (result, log) = verify(&filter);
+        assert!(result.is_ok(), "{}", log);
+        assert!(log.contains("  ✓ Coverage\n"));
+
+        // Without it, the filtered-out invariant looks newly added
+        let (result, log) = verify(&invar_core::InvariantFilter::default());
+        assert!(result.is_err());
+        assert!(log.contains("invariant open added"), "{}", log);
+
+        // A check dropped from the output is reported
+        let pristine = std::fs::read_to_string(&generated).unwrap();
+        let header = invar_core::ProvenanceHeader::parse(&pristine).unwrap();
+        let body = invar_core::ProvenanceHeader::body(&pristine).replace("// Invariant: unpaused\n", "");
+        std::fs::write(&generated, header.prepend(&body)).unwrap();
+        let (result, log) = verify(&filter);
+        assert!(result.is_err());
+        assert!(log.contains("  ✗ Coverage: "), "{}", log);
+        assert!(log.contains("  ✓ Tamper detection\n"), "{}", log);
+    }
+
+    #[test]
+    fn test_newer_model_schema_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let model_path = dir.path().join("model.json");
+        std::fs::write(&model_path, r#"{"schema_version": 99, "name": "x"}"#).unwrap();
+        let err = load_model(&model_path).unwrap_err();
+        assert!(err.to_string().contains("newer than supported"));
+    }
+
+    #[test]
+    fn test_resolve_chain_detects_and_honours_override() {
+        let source = example("solana_token_transfer.rs");
+        assert_eq!(resolve_chain(&source, None).unwrap(), "solana");
+        assert_eq!(
+            resolve_chain(&example("evm_token.sol"), None).unwrap(),
+            "evm"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let helper = dir.path().join("helper.rs");
+        std::fs::write(&helper, "pub fn add(a: u64, b: u64) -> u64 { a + b }\n").unwrap();
+        let err = resolve_chain(&helper, None).unwrap_err().to_string();
+        assert!(err.contains("solana, evm, move"));
+        assert_eq!(resolve_chain(&helper, Some("solana")).unwrap(), "solana");
+        assert_eq!(resolve_chain(&source, Some("evm")).unwrap(), "evm");
+    }
+
+    #[test]
+    fn test_detect_chains_maps_mixed_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Vault.sol"), "contract Vault {}\n").unwrap();
+        std::fs::write(dir.path().join("coin.move"), "module 0x1::coin {}\n").unwrap();
+        std::fs::write(dir.path().join("helper.rs"), "fn helper() {}\n").unwrap();
+
+        let mut files = Vec::new();
+        collect_sources(dir.path(), None, &analyzer_registry(), &mut files).unwrap();
+        files.sort();
+        assert_eq!(
+            detect_chains(dir.path(), files).unwrap(),
+            vec![
+                (dir.path().join("Vault.sol"), "evm".to_string()),
+                (dir.path().join("coin.move"), "move".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_sources_glob_continues_past_vulnerable_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let contracts = dir.path().join("contracts");
+        std::fs::create_dir_all(contracts.join("tokens")).unwrap();
+        std::fs::write(
+            contracts.join("a_vault.sol"),
+            "contract Vault {\n    function withdraw(uint256 amount) public {\n        payable(msg.sender).transfer(amount);\n    }\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            contracts.join("tokens/b_token.sol"),
+            "contract Token {\n    uint256 public totalSupply;\n}\n",
+        )
+        .unwrap();
+
+        let pattern = [contracts.join("**/*.sol")];
+        let options = BuildOptions {
+            invariants: None,
+            lenient: false,
+            use_cache: false,
+            min_coverage: None,
+            warn_uncovered: false,
+            filter: &invar_core::InvariantFilter::default(),
+            extract_contract_invariants: false,
+            diff: false,
+            synthesize: None,
+            strict: false,
+            verify_compilation: false,
+            check: false,
+        };
+        let output = dir.path().join("out");
+        let err = build_sources(&pattern, None, &output, options, None, false).unwrap_err();
+        assert!(err.to_string().contains("1 of 2 source file(s) failed"));
+        assert!(output.join("a_vault").join(SECURITY_REPORT_FILE).exists());
+        assert!(output.join("tokens/b_token/generated_evm.rs").exists());
+
+        let merged = std::fs::read_to_string(output.join(MERGED_SECURITY_REPORT_FILE)).unwrap();
+        let merged: invar_core::SecurityReport = serde_json::from_str(&merged).unwrap();
+        assert!(!merged.critical_issues.is_empty());
+
+        let fail_fast = dir.path().join("fail_fast");
+        build_sources(&pattern, Some("evm"), &fail_fast, options, Some(1), true).unwrap_err();
+        assert!(!fail_fast.join("tokens").exists());
+    }
+
+    #[test]
+    fn test_build_sources_report_is_independent_of_job_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let contracts = dir.path().join("contracts");
+        std::fs::create_dir_all(&contracts).unwrap();
+        for i in 0..8 {
+            let body = if i % 3 == 0 {
+                "    function withdraw(uint256 amount) public {\n        payable(msg.sender).call{value: amount}(\"\");\n        balance -= amount;\n    }\n"
+            } else {
+                "    uint256 public totalSupply;\n"
+            };
+            std::fs::write(
+                contracts.join(format!("c{}.sol", i)),
+                format!("contract C{} {{\n{}}}\n", i, body),
+            )
+            .unwrap();
+        }
+
+        let options = BuildOptions {
+            invariants: None,
+            lenient: false,
+            use_cache: false,
+            min_coverage: None,
+            warn_uncovered: false,
+            filter: &invar_core::InvariantFilter::default(),
+            extract_contract_invariants: false,
+            diff: false,
+            synthesize: None,
+            strict: false,
+            verify_compilation: false,
+            check: false,
+        };
+        let merged = |jobs: usize| {
+            let output = dir.path().join(format!("out_{}", jobs));
+            let _ = build_sources(
+                std::slice::from_ref(&contracts),
+                None,
+                &output,
+                options,
+                Some(jobs),
+                false,
+            );
+            std::fs::read_to_string(output.join(MERGED_SECURITY_REPORT_FILE)).unwrap()
+        };
+        assert_eq!(merged(1), merged(4));
+    }
+
+    #[test]
+    fn test_build_sources_links_cross_chain_invariants() {
+        let dir = tempfile::tempdir().unwrap();
+        let contracts = dir.path().join("contracts");
+        std::fs::create_dir_all(&contracts).unwrap();
+        std::fs::write(
+            contracts.join("l1.sol"),
+            "contract L1 {\n    uint256 public locked;\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            contracts.join("l2.sol"),
+            "contract L2 {\n    uint256 public minted;\n}\n",
+        )
+        .unwrap();
+        let invariants = dir.path().join("bridge.invar");
+        std::fs::write(
+            &invariants,
+            "invariant lock_backs_mint { l1::locked >= l2::minted }\ninvariant burn_backs_unlock { l2::burned >= l1::unlocked }\n",
+        )
+        .unwrap();
+
+        let options = BuildOptions {
+            invariants: Some(&invariants),
+            lenient: false,
+            use_cache: false,
+            min_coverage: None,
+            warn_uncovered: false,
+            filter: &invar_core::InvariantFilter::default(),
+            extract_contract_invariants: false,
+            diff: false,
+            synthesize: None,
+            strict: false,
+            verify_compilation: false,
+            check: false,
+        };
+        let output = dir.path().join("out");
+        build_sources(
+            std::slice::from_ref(&contracts),
+            Some("evm"),
+            &output,
+            options,
+            None,
+            false,
+        )
+        .unwrap();
+
+        let report = std::fs::read_to_string(output.join(CROSS_CHAIN_REPORT_FILE)).unwrap();
+        let report: invar_report::Report = serde_json::from_str(&report).unwrap();
+        assert_eq!(report.program, "L1/evm, L2/evm");
+        assert_eq!(report.violations_found, 1);
+        let lock = &report.cross_chain[0];
+        assert_eq!(lock.invariant, "lock_backs_mint");
+        assert!(lock.is_linked());
+        assert_eq!(lock.programs["l2"], "L2/evm");
+        assert_eq!(
+            report.cross_chain[1].unresolved,
+            vec!["l1::unlocked", "l2::burned"]
+        );
+    }
+}
@@ -0,0 +1,50 @@
+//! Re-checking invariants after a step that changed a single variable, with
+//! and without reusing the results of unaffected invariants.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use invar_core::model::{BinaryOp, Expression};
+use invar_core::{Evaluator, ExecutionContext, Value};
+
+const INVARIANTS: usize = 50;
+
+fn invariants() -> Vec<Expression> {
+    (0..INVARIANTS)
+        .map(|i| Expression::BinaryOp {
+            left: Box::new(Expression::Var(format!("balance_{}", i))),
+            op: BinaryOp::Lte,
+            right: Box::new(Expression::Var(format!("limit_{}", i))),
+        })
+        .collect()
+}
+
+fn evaluator(caching: bool) -> Evaluator {
+    let mut evaluator = Evaluator::new(ExecutionContext::new());
+    evaluator.set_caching(caching);
+    for i in 0..INVARIANTS {
+        evaluator.set_state(format!("balance_{}", i), Value::U64(i as u64));
+        evaluator.set_state(format!("limit_{}", i), Value::U64(1_000));
+    }
+    evaluator
+}
+
+fn bench_single_variable_change(c: &mut Criterion) {
+    let invariants = invariants();
+    let mut group = c.benchmark_group("check_50_invariants_after_1_change");
+    for (label, caching) in [("uncached", false), ("cached", true)] {
+        let mut evaluator = evaluator(caching);
+        let mut step = 0u64;
+        group.bench_function(label, |b| {
+            b.iter(|| {
+                step += 1;
+                evaluator.set_state("balance_0".to_string(), Value::U64(step % 1_000));
+                for invariant in &invariants {
+                    let _ = black_box(evaluator.evaluate_cached(invariant));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_single_variable_change);
+criterion_main!(benches);
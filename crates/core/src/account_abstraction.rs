@@ -5,6 +5,8 @@
 //! - Execution Phase: account code execution, state mutations
 //! - Settlement Phase: bundles with other ops, fund transfers
 
+use crate::evaluator::{EvalResult, EvaluationError, Evaluator, ExecutionContext};
+use crate::model::{BinaryOp, Expression};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
@@ -178,6 +180,39 @@ impl AAContext {
             .get(layer)?
             .get(var)
     }
+
+    /// Check `expr1 op expr2`, evaluating `expr1` against the snapshot taken
+    /// at `phase1` and `expr2` against the one taken at `phase2`.
+    ///
+    /// Variables are read as `layer::var`. The current phase falls back to the
+    /// live layer state when it has not been snapshotted yet.
+    pub fn cross_phase_assert(
+        &self,
+        expr1: &Expression,
+        phase1: ExecutionPhase,
+        op: BinaryOp,
+        expr2: &Expression,
+        phase2: ExecutionPhase,
+    ) -> EvalResult<bool> {
+        let evaluator = self.evaluator_at(phase1)?;
+        let left = evaluator.evaluate(expr1)?;
+        let right = self.evaluator_at(phase2)?.evaluate(expr2)?;
+        evaluator.eval_binary_op(&left, &op, &right)?.to_bool()
+    }
+
+    fn evaluator_at(&self, phase: ExecutionPhase) -> EvalResult<Evaluator> {
+        let state = match self.get_phase_snapshot(phase) {
+            Some(snapshot) => snapshot,
+            None if self.in_phase(phase) => &self.layer_state,
+            None => {
+                return Err(EvaluationError::Custom(format!(
+                    "no snapshot for {} phase",
+                    phase
+                )))
+            }
+        };
+        Ok(Evaluator::new(ExecutionContext::from_layer_state(state)?))
+    }
 }
 
 /// UserOperation data from bundler layer.
@@ -340,4 +375,64 @@ mod tests {
             ctx.get_layer_var_at_phase(ExecutionPhase::Validation, "account", "balance");
         assert_eq!(pre_exec_balance, Some(&serde_json::json!(1000)));
     }
+
+    #[test]
+    fn test_cross_phase_assert_reads_snapshots() {
+        let mut ctx = AAContext::default();
+        ctx.set_phase(ExecutionPhase::Validation);
+        ctx.set_layer_var(
+            "account".to_string(),
+            "balance".to_string(),
+            serde_json::json!(1000),
+        );
+        ctx.snapshot_phase(ExecutionPhase::Validation);
+        ctx.set_phase(ExecutionPhase::Execution);
+        ctx.set_layer_var(
+            "account".to_string(),
+            "balance".to_string(),
+            serde_json::json!(400),
+        );
+        ctx.snapshot_phase(ExecutionPhase::Execution);
+
+        // Execution may spend the balance, never grow it
+        let balance = Expression::LayerVar {
+            layer: "account".to_string(),
+            var: "balance".to_string(),
+        };
+        let holds = |op| {
+            ctx.cross_phase_assert(
+                &balance,
+                ExecutionPhase::Validation,
+                op,
+                &balance,
+                ExecutionPhase::Execution,
+            )
+        };
+        assert_eq!(holds(BinaryOp::Gte), Ok(true));
+        assert_eq!(holds(BinaryOp::Eq), Ok(false));
+
+        // The evaluator delegates cross-phase relations to the snapshots
+        let relation = Expression::CrossPhaseRelation {
+            phase1: "validation".to_string(),
+            expr1: Box::new(balance.clone()),
+            phase2: "execution".to_string(),
+            expr2: Box::new(balance.clone()),
+            op: BinaryOp::Gte,
+        };
+        let evaluator = Evaluator::new(ExecutionContext::new()).with_aa_context(ctx.clone());
+        assert_eq!(
+            evaluator.evaluate(&relation),
+            Ok(crate::evaluator::Value::Bool(true))
+        );
+
+        assert!(ctx
+            .cross_phase_assert(
+                &balance,
+                ExecutionPhase::Settlement,
+                BinaryOp::Eq,
+                &balance,
+                ExecutionPhase::Execution,
+            )
+            .is_err());
+    }
 }
@@ -3,7 +3,8 @@
 //! This module documents historical exploits and provides protective
 //! invariants to prevent similar attacks.
 
-use std::collections::BTreeMap;
+use crate::model::NO_MUTATION_AFTER_CPI;
+use std::collections::{BTreeMap, BTreeSet};
 
 /// A known attack pattern with defensive invariants.
 #[derive(Debug, Clone)]
@@ -95,7 +96,7 @@ impl AttackPatternDB {
                     "total_supply_constant".to_string(),
                     "balance_never_negative".to_string(),
                 ],
-                affected_chains: vec!["evm".to_string(), "move".to_string()],
+                affected_chains: vec!["evm".to_string(), "solana".to_string(), "move".to_string()],
                 cvss_score: 8.5,
             },
         );
@@ -262,6 +263,134 @@ impl AttackPatternDB {
             },
         );
 
+        // Attack 9: Move - Missing Signer Check
+        patterns.insert(
+            "move_missing_signer".to_string(),
+            AttackPattern {
+                id: "move_missing_signer".to_string(),
+                name: "Missing Signer Check".to_string(),
+                description: "Public entry function mutates global storage without taking a signer"
+                    .to_string(),
+                year: 2022,
+                incidents: vec![
+                    "Aptos/Sui audit findings - unauthorized resource mutation".to_string()
+                ],
+                vulnerable_patterns: vec![
+                    "public_entry_without_signer".to_string(),
+                    "borrow_global_mut_without_signer".to_string(),
+                ],
+                defensive_invariants: vec![
+                    "signer_required_for_state_change".to_string(),
+                    "signer_address_matches_resource_owner".to_string(),
+                ],
+                affected_chains: vec!["move".to_string()],
+                cvss_score: 9.1,
+            },
+        );
+
+        // Attack 10: Move - Unconstrained Resource
+        patterns.insert(
+            "move_unconstrained_resource".to_string(),
+            AttackPattern {
+                id: "move_unconstrained_resource".to_string(),
+                name: "Resource Without Ability Constraints".to_string(),
+                description: "Struct published to global storage without declaring its abilities"
+                    .to_string(),
+                year: 2022,
+                incidents: vec!["Move audit findings - resources copied or dropped".to_string()],
+                vulnerable_patterns: vec!["struct_without_has_clause_published".to_string()],
+                defensive_invariants: vec![
+                    "resource_abilities_declared".to_string(),
+                    "resource_not_copyable".to_string(),
+                ],
+                affected_chains: vec!["move".to_string()],
+                cvss_score: 7.0,
+            },
+        );
+
+        // Attack 11: Move - Arbitrary borrow_global_mut
+        patterns.insert(
+            "move_arbitrary_borrow".to_string(),
+            AttackPattern {
+                id: "move_arbitrary_borrow".to_string(),
+                name: "Arbitrary Global Borrow".to_string(),
+                description:
+                    "borrow_global_mut on a caller-provided address lets anyone modify others' resources"
+                        .to_string(),
+                year: 2022,
+                incidents: vec!["Move audit findings - cross-account state tampering".to_string()],
+                vulnerable_patterns: vec!["borrow_global_mut(caller_address)".to_string()],
+                defensive_invariants: vec![
+                    "borrow_address_from_signer".to_string(),
+                    "owner_checked_before_mutation".to_string(),
+                ],
+                affected_chains: vec!["move".to_string()],
+                cvss_score: 8.1,
+            },
+        );
+
+        // Attack 12: Move - Unchecked Arithmetic
+        patterns.insert(
+            "move_unchecked_arithmetic".to_string(),
+            AttackPattern {
+                id: "move_unchecked_arithmetic".to_string(),
+                name: "Unchecked Arithmetic".to_string(),
+                description:
+                    "Arithmetic outside checked_* helpers aborts the transaction on overflow"
+                        .to_string(),
+                year: 2022,
+                incidents: vec!["Move audit findings - denial of service via abort".to_string()],
+                vulnerable_patterns: vec!["arithmetic_outside_checked_helper".to_string()],
+                defensive_invariants: vec![
+                    "addition_with_overflow_check".to_string(),
+                    "subtraction_with_underflow_check".to_string(),
+                ],
+                affected_chains: vec!["move".to_string()],
+                cvss_score: 5.5,
+            },
+        );
+
+        // Attack 13: Solana - PDA Seed Manipulation
+        patterns.insert(
+            "solana_pda_seed_manipulation".to_string(),
+            AttackPattern {
+                id: "solana_pda_seed_manipulation".to_string(),
+                name: "PDA Seed Manipulation".to_string(),
+                description:
+                    "PDA derived from caller-supplied seeds lets an attacker choose which account is used"
+                        .to_string(),
+                year: 2021,
+                incidents: vec!["Solana audit findings - spoofed PDA accounts".to_string()],
+                // Detected from the analyzed program model rather than by text matching
+                vulnerable_patterns: Vec::new(),
+                defensive_invariants: vec![
+                    "pda_seeds_bound_to_signer".to_string(),
+                    "pda_seed_inputs_validated".to_string(),
+                ],
+                affected_chains: vec!["solana".to_string()],
+                cvss_score: 8.1,
+            },
+        );
+
+        // Attack 14: Solana - State Mutation After CPI
+        patterns.insert(
+            "solana_mutation_after_cpi".to_string(),
+            AttackPattern {
+                id: "solana_mutation_after_cpi".to_string(),
+                name: "State Mutation After CPI".to_string(),
+                description:
+                    "State written after a cross-program invocation can be observed stale or overwritten by a re-entering callee"
+                        .to_string(),
+                year: 2022,
+                incidents: vec!["Solana audit findings - CPI re-entry into stale state".to_string()],
+                // Detected from the analyzed program model rather than by text matching
+                vulnerable_patterns: Vec::new(),
+                defensive_invariants: vec![NO_MUTATION_AFTER_CPI.to_string()],
+                affected_chains: vec!["solana".to_string()],
+                cvss_score: 7.5,
+            },
+        );
+
         Self { patterns }
     }
 
@@ -300,6 +429,21 @@ impl AttackPatternDB {
 
         issues
     }
+
+    /// Suggest defensive invariants for a code snippet.
+    ///
+    /// Every pattern affecting `chain` whose vulnerable patterns appear in
+    /// `code` contributes its defensive invariants; the result is sorted and
+    /// free of duplicates.
+    pub fn suggest_invariants_for_code(&self, code: &str, chain: &str) -> Vec<String> {
+        let suggestions: BTreeSet<&String> = self
+            .patterns_for_chain(chain)
+            .into_iter()
+            .filter(|p| p.vulnerable_patterns.iter().any(|v| code.contains(v)))
+            .flat_map(|p| &p.defensive_invariants)
+            .collect();
+        suggestions.into_iter().cloned().collect()
+    }
 }
 
 impl Default for AttackPatternDB {
@@ -315,7 +459,7 @@ mod tests {
     #[test]
     fn test_attack_db_creation() {
         let db = AttackPatternDB::new();
-        assert_eq!(db.all_patterns().len(), 8);
+        assert_eq!(db.all_patterns().len(), 14);
     }
 
     #[test]
@@ -336,6 +480,10 @@ mod tests {
         assert!(solana_patterns
             .iter()
             .any(|p| p.id == "access_control_bypass"));
+
+        let move_patterns = db.patterns_for_chain("move");
+        assert!(move_patterns.iter().any(|p| p.id == "move_missing_signer"));
+        assert!(!evm_patterns.iter().any(|p| p.id.starts_with("move_")));
     }
 
     #[test]
@@ -346,6 +494,22 @@ mod tests {
         assert!(!issues.is_empty());
     }
 
+    #[test]
+    fn test_suggest_invariants_for_code() {
+        let db = AttackPatternDB::new();
+        let code = "function withdraw(uint256 amount) public {\n    \
+                    payable(msg.sender).transfer(amount);\n    \
+                    balances[msg.sender] -= amount;\n}";
+
+        let suggestions = db.suggest_invariants_for_code(code, "evm");
+        assert!(suggestions.contains(&"state_update_before_external_call".to_string()));
+        assert!(suggestions.windows(2).all(|w| w[0] < w[1]));
+
+        // Reentrancy does not apply to Move code
+        let suggestions = db.suggest_invariants_for_code(code, "move");
+        assert!(!suggestions.contains(&"state_update_before_external_call".to_string()));
+    }
+
     #[test]
     fn test_cvss_scores() {
         let db = AttackPatternDB::new();
@@ -0,0 +1,383 @@
+//! Canonical binary encoding of expressions for hashing and diffing.
+//!
+//! The encoding depends only on the expression tree, never on `Debug`
+//! output or serde field order, and is versioned so that a change to it is
+//! visible in every hash derived from it.
+//!
+//! # Format (version 1)
+//!
+//! The first byte is [`CANONICAL_VERSION`], followed by the root node.
+//! Each node is a tag byte and its fields in DSL source order:
+//!
+//! | Tag    | Variant              | Fields                                     |
+//! |--------|----------------------|--------------------------------------------|
+//! | `0x01` | `Boolean`            | `0x00` or `0x01`                           |
+//! | `0x02` | `Var`                | name                                       |
+//! | `0x03` | `LayerVar`           | layer, var                                 |
+//! | `0x04` | `PhaseQualifiedVar`  | phase, layer, var                          |
+//! | `0x05` | `PhaseConstraint`    | constraint, phase                          |
+//! | `0x06` | `CrossPhaseRelation` | expr1, phase1, op, expr2, phase2           |
+//! | `0x07` | `Int`                | 16-byte big-endian two's complement        |
+//! | `0x08` | `String`             | value                                      |
+//! | `0x09` | `BinaryOp`           | left, op, right                            |
+//! | `0x0a` | `Logical`            | left, op, right                            |
+//! | `0x0b` | `Not`                | operand                                    |
+//! | `0x0c` | `FunctionCall`       | name, argument list                        |
+//! | `0x0d` | `Tuple`              | element list                               |
+//! | `0x0e` | `Conditional`        | cond, then_expr, else_expr                 |
+//! | `0x0f` | `Cast`               | expr, type                                 |
+//! | `0x10` | `MapAccess`          | map, key                                   |
+//! | `0x11` | `Assert`             | constraint, message                        |
+//! | `0x12` | `Quantified`         | quantifier, var, collection, body          |
+//! | `0x13` | `Negate`             | operand                                    |
+//! | `0x14` | `Aggregate`          | op, binder, collection, body, filter       |
+//!
+//! Strings are a 4-byte big-endian byte length followed by UTF-8; lists are
+//! a 4-byte big-endian count followed by their nodes. Operators, quantifiers
+//! and types are one byte each, numbered in declaration order from zero
+//! (`BinaryOp::Eq` is `0x00`, `LogicalOp::Or` is `0x01`); a map type is
+//! followed by its key and value types. An absent aggregate filter is a
+//! single `0x00`, a present one `0x01` followed by the filter.
+
+use crate::model::{AggregateOp, BinaryOp, Expression, LogicalOp, Quantifier};
+use crate::types::Type;
+
+/// Version byte that starts every canonical encoding.
+pub const CANONICAL_VERSION: u8 = 1;
+
+impl Expression {
+    /// Canonical binary encoding of the expression tree, described in the
+    /// [module documentation](crate::canonical).
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut out = vec![CANONICAL_VERSION];
+        encode(self, &mut out);
+        out
+    }
+
+    /// Lowercase hex SHA-256 of [`canonical_bytes`](Self::canonical_bytes).
+    pub fn canonical_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        Sha256::digest(self.canonical_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+fn encode(expr: &Expression, out: &mut Vec<u8>) {
+    match expr {
+        Expression::Boolean(b) => {
+            out.push(0x01);
+            out.push(u8::from(*b));
+        }
+        Expression::Var(name) => {
+            out.push(0x02);
+            encode_str(name, out);
+        }
+        Expression::LayerVar { layer, var } => {
+            out.push(0x03);
+            encode_str(layer, out);
+            encode_str(var, out);
+        }
+        Expression::PhaseQualifiedVar { phase, layer, var } => {
+            out.push(0x04);
+            encode_str(phase, out);
+            encode_str(layer, out);
+            encode_str(var, out);
+        }
+        Expression::PhaseConstraint { phase, constraint } => {
+            out.push(0x05);
+            encode(constraint, out);
+            encode_str(phase, out);
+        }
+        Expression::CrossPhaseRelation {
+            phase1,
+            expr1,
+            phase2,
+            expr2,
+            op,
+        } => {
+            out.push(0x06);
+            encode(expr1, out);
+            encode_str(phase1, out);
+            out.push(binary_op_tag(*op));
+            encode(expr2, out);
+            encode_str(phase2, out);
+        }
+        Expression::Int(value) => {
+            out.push(0x07);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        Expression::String(value) => {
+            out.push(0x08);
+            encode_str(value, out);
+        }
+        Expression::BinaryOp { left, op, right } => {
+            out.push(0x09);
+            encode(left, out);
+            out.push(binary_op_tag(*op));
+            encode(right, out);
+        }
+        Expression::Logical { left, op, right } => {
+            out.push(0x0a);
+            encode(left, out);
+            out.push(match op {
+                LogicalOp::And => 0,
+                LogicalOp::Or => 1,
+            });
+            encode(right, out);
+        }
+        Expression::Not(inner) => {
+            out.push(0x0b);
+            encode(inner, out);
+        }
+        Expression::FunctionCall { name, args } => {
+            out.push(0x0c);
+            encode_str(name, out);
+            encode_list(args, out);
+        }
+        Expression::Tuple(exprs) => {
+            out.push(0x0d);
+            encode_list(exprs, out);
+        }
+        Expression::Conditional {
+            cond,
+            then_expr,
+            else_expr,
+        } => {
+            out.push(0x0e);
+            encode(cond, out);
+            encode(then_expr, out);
+            encode(else_expr, out);
+        }
+        Expression::Cast { expr, target } => {
+            out.push(0x0f);
+            encode(expr, out);
+            encode_type(target, out);
+        }
+        Expression::MapAccess { map, key } => {
+            out.push(0x10);
+            encode(map, out);
+            encode(key, out);
+        }
+        Expression::Assert(constraint, message) => {
+            out.push(0x11);
+            encode(constraint, out);
+            encode_str(message, out);
+        }
+        Expression::Quantified {
+            quantifier,
+            var,
+            collection,
+            body,
+        } => {
+            out.push(0x12);
+            out.push(match quantifier {
+                Quantifier::ForAll => 0,
+                Quantifier::Exists => 1,
+                Quantifier::Sum => 2,
+                Quantifier::Count => 3,
+                Quantifier::Min => 4,
+                Quantifier::Max => 5,
+            });
+            encode_str(var, out);
+            encode(collection, out);
+            encode(body, out);
+        }
+        Expression::Negate(inner) => {
+            out.push(0x13);
+            encode(inner, out);
+        }
+        Expression::Aggregate {
+            op,
+            binder,
+            collection,
+            body,
+            filter,
+        } => {
+            out.push(0x14);
+            out.push(match op {
+                AggregateOp::Sum => 0,
+                AggregateOp::Count => 1,
+                AggregateOp::Max => 2,
+            });
+            encode_str(binder, out);
+            encode(collection, out);
+            encode(body, out);
+            match filter {
+                Some(filter) => {
+                    out.push(1);
+                    encode(filter, out);
+                }
+                None => out.push(0),
+            }
+        }
+    }
+}
+
+fn encode_len(len: usize, out: &mut Vec<u8>) {
+    let len = u32::try_from(len).expect("expression strings and lists fit in u32");
+    out.extend_from_slice(&len.to_be_bytes());
+}
+
+fn encode_str(value: &str, out: &mut Vec<u8>) {
+    encode_len(value.len(), out);
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn encode_list(exprs: &[Expression], out: &mut Vec<u8>) {
+    encode_len(exprs.len(), out);
+    for expr in exprs {
+        encode(expr, out);
+    }
+}
+
+fn binary_op_tag(op: BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Eq => 0,
+        BinaryOp::Neq => 1,
+        BinaryOp::Lt => 2,
+        BinaryOp::Gt => 3,
+        BinaryOp::Lte => 4,
+        BinaryOp::Gte => 5,
+    }
+}
+
+fn encode_type(ty: &Type, out: &mut Vec<u8>) {
+    match ty {
+        Type::Bool => out.push(0),
+        Type::U64 => out.push(1),
+        Type::U128 => out.push(2),
+        Type::I64 => out.push(3),
+        Type::U256 => out.push(4),
+        Type::Address => out.push(5),
+        Type::Array => out.push(6),
+        Type::Str => out.push(7),
+        Type::Map(key, value) => {
+            out.push(8);
+            encode_type(key, out);
+            encode_type(value, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str) -> Box<Expression> {
+        Box::new(Expression::Var(name.to_string()))
+    }
+
+    #[test]
+    fn test_golden_encoding() {
+        // `sum(balances) <= paymaster::deposit as u128 && !paused`
+        let expr = Expression::Logical {
+            left: Box::new(Expression::BinaryOp {
+                left: Box::new(Expression::FunctionCall {
+                    name: "sum".to_string(),
+                    args: vec![Expression::Var("balances".to_string())],
+                }),
+                op: BinaryOp::Lte,
+                right: Box::new(Expression::Cast {
+                    expr: Box::new(Expression::LayerVar {
+                        layer: "paymaster".to_string(),
+                        var: "deposit".to_string(),
+                    }),
+                    target: Type::U128,
+                }),
+            }),
+            op: LogicalOp::And,
+            right: Box::new(Expression::Not(var("paused"))),
+        };
+
+        let hex: String = expr
+            .canonical_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        assert_eq!(
+            hex,
+            concat!(
+                "01",                                 // version
+                "0a",                                 // Logical
+                "09",                                 // BinaryOp
+                "0c0000000373756d",                   // sum(
+                "00000001020000000862616c616e636573", // balances)
+                "04",                                 // <=
+                "0f",                                 // Cast
+                "03000000097061796d6173746572",       // paymaster::
+                "000000076465706f736974",             // deposit
+                "02",                                 // as u128
+                "00",                                 // &&
+                "0b0200000006706175736564",           // !paused
+            )
+        );
+        assert_eq!(expr.canonical_hash().len(), 64);
+        assert_eq!(expr.canonical_hash(), expr.clone().canonical_hash());
+    }
+
+    #[test]
+    fn test_distinct_expressions_encode_differently() {
+        let exprs = vec![
+            // Render identically as `a::b`
+            Expression::Var("a::b".to_string()),
+            Expression::LayerVar {
+                layer: "a".to_string(),
+                var: "b".to_string(),
+            },
+            // Length prefixes keep string boundaries apart
+            Expression::FunctionCall {
+                name: "f".to_string(),
+                args: vec![*var("ab")],
+            },
+            Expression::FunctionCall {
+                name: "fa".to_string(),
+                args: vec![*var("b")],
+            },
+            Expression::FunctionCall {
+                name: "f".to_string(),
+                args: vec![Expression::Tuple(vec![*var("a"), *var("b")])],
+            },
+            Expression::FunctionCall {
+                name: "f".to_string(),
+                args: vec![*var("a"), *var("b")],
+            },
+            Expression::Int(1),
+            Expression::Int(-1),
+            Expression::String("1".to_string()),
+            Expression::Boolean(true),
+            Expression::BinaryOp {
+                left: var("a"),
+                op: BinaryOp::Lt,
+                right: var("b"),
+            },
+            Expression::BinaryOp {
+                left: var("b"),
+                op: BinaryOp::Gt,
+                right: var("a"),
+            },
+            Expression::Cast {
+                expr: var("a"),
+                target: Type::Map(Box::new(Type::Address), Box::new(Type::U64)),
+            },
+            Expression::Cast {
+                expr: var("a"),
+                target: Type::Map(Box::new(Type::U64), Box::new(Type::Address)),
+            },
+        ];
+
+        for (i, left) in exprs.iter().enumerate() {
+            for right in &exprs[i + 1..] {
+                assert_ne!(
+                    left.canonical_bytes(),
+                    right.canonical_bytes(),
+                    "{:?} vs {:?}",
+                    left,
+                    right
+                );
+            }
+        }
+    }
+}
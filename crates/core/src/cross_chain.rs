@@ -0,0 +1,235 @@
+//! Cross-chain invariant support.
+//!
+//! Bridge invariants relate state on two chains, such as tokens locked on
+//! L1 and minted on L2: `l1::locked >= l2::minted`. Each chain is a layer
+//! namespace, so the expressions are ordinary layer-qualified variables:
+//! - [`CrossChainContext`] holds per-chain state and evaluates invariants
+//!   over all chains at once.
+//! - [`CrossChainLink`] resolves the namespaces of an invariant against the
+//!   programs of a multi-source build.
+//!
+//! The evaluator and simulator need nothing chain-specific: they read
+//! `l1::locked` from state like any other layer variable.
+
+use crate::account_abstraction::AALayer;
+use crate::evaluator::{EvalResult, Evaluator, ExecutionContext};
+use crate::model::{Expression, Invariant, ProgramModel};
+use crate::visitor::ExpressionVisitor;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::Infallible;
+
+/// State of several chains, for evaluating invariants that span them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CrossChainContext {
+    /// Chain-specific state variables: chain -> (variable -> value).
+    pub chain_state: BTreeMap<String, BTreeMap<String, serde_json::Value>>,
+}
+
+impl CrossChainContext {
+    /// Set variable value on a specific chain.
+    pub fn set_chain_var(&mut self, chain: String, var: String, value: serde_json::Value) {
+        self.chain_state
+            .entry(chain)
+            .or_default()
+            .insert(var, value);
+    }
+
+    /// Get variable value from a specific chain.
+    pub fn get_chain_var(&self, chain: &str, var: &str) -> Option<&serde_json::Value> {
+        self.chain_state.get(chain)?.get(var)
+    }
+
+    /// Evaluator reading each chain's variables as `chain::var`.
+    pub fn evaluator(&self) -> EvalResult<Evaluator> {
+        Ok(Evaluator::new(ExecutionContext::from_layer_state(
+            &self.chain_state,
+        )?))
+    }
+
+    /// Whether `invariant` holds across the chains' current state.
+    pub fn check(&self, invariant: &Invariant) -> EvalResult<bool> {
+        self.evaluator()?.evaluate(&invariant.expression)?.to_bool()
+    }
+}
+
+/// Layer-qualified variables of `expr`, grouped by namespace.
+pub fn chain_namespaces(expr: &Expression) -> BTreeMap<String, BTreeSet<String>> {
+    let mut collector = NamespaceCollector::default();
+    let Ok(()) = collector.visit_expression(expr);
+    collector.namespaces
+}
+
+/// Whether `invariant` reads variables from more than one namespace.
+/// Account abstraction layers (`bundler`, `paymaster`, ...) are layers of a
+/// single program rather than chains, and do not count.
+pub fn is_cross_chain(invariant: &Invariant) -> bool {
+    chain_namespaces(&invariant.expression)
+        .keys()
+        .filter(|namespace| namespace.parse::<AALayer>().is_err())
+        .count()
+        > 1
+}
+
+/// The programs a cross-chain invariant's namespaces resolve to.
+///
+/// A namespace resolves to the program named like it, or failing that to a
+/// program on the chain of that name, as long as the program declares every
+/// variable read from the namespace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrossChainLink {
+    /// Invariant name.
+    pub invariant: String,
+    /// `program/chain` each resolved namespace belongs to, by namespace.
+    pub programs: BTreeMap<String, String>,
+    /// Namespaced variables (`l2::minted`) no program declares.
+    pub unresolved: Vec<String>,
+}
+
+impl CrossChainLink {
+    /// Resolve the namespaces of `invariant` against `programs`.
+    pub fn new(invariant: &Invariant, programs: &[ProgramModel]) -> Self {
+        let mut link = Self {
+            invariant: invariant.name.clone(),
+            programs: BTreeMap::new(),
+            unresolved: Vec::new(),
+        };
+        for (namespace, vars) in chain_namespaces(&invariant.expression) {
+            let declares_all = |program: &&ProgramModel| {
+                vars.iter().all(|var| program.state_vars.contains_key(var))
+            };
+            let program = programs
+                .iter()
+                .filter(declares_all)
+                .find(|program| program.name.eq_ignore_ascii_case(&namespace))
+                .or_else(|| {
+                    programs
+                        .iter()
+                        .filter(declares_all)
+                        .find(|program| program.chain == namespace)
+                });
+            match program {
+                Some(program) => {
+                    link.programs
+                        .insert(namespace, format!("{}/{}", program.name, program.chain));
+                }
+                None => link
+                    .unresolved
+                    .extend(vars.iter().map(|var| format!("{}::{}", namespace, var))),
+            }
+        }
+        link
+    }
+
+    /// Whether every namespace resolved to a program.
+    pub fn is_linked(&self) -> bool {
+        self.unresolved.is_empty()
+    }
+}
+
+#[derive(Default)]
+struct NamespaceCollector {
+    namespaces: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl ExpressionVisitor for NamespaceCollector {
+    type Error = Infallible;
+
+    fn visit_layer_var(&mut self, layer: &str, var: &str) -> Result<(), Infallible> {
+        self.namespaces
+            .entry(layer.to_string())
+            .or_default()
+            .insert(var.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::EvaluationError;
+    use crate::model::{BinaryOp, StateVar};
+
+    fn lock_backs_mint() -> Invariant {
+        Invariant::new(
+            "lock_backs_mint",
+            Expression::BinaryOp {
+                left: Box::new(Expression::LayerVar {
+                    layer: "l1".to_string(),
+                    var: "locked".to_string(),
+                }),
+                op: BinaryOp::Gte,
+                right: Box::new(Expression::LayerVar {
+                    layer: "l2".to_string(),
+                    var: "minted".to_string(),
+                }),
+            },
+        )
+    }
+
+    #[test]
+    fn test_lock_and_mint_checked_across_chains() {
+        let invariant = lock_backs_mint();
+        assert!(is_cross_chain(&invariant));
+        let aa = Invariant::new(
+            "nonce_synced",
+            Expression::BinaryOp {
+                left: Box::new(Expression::LayerVar {
+                    layer: "bundler".to_string(),
+                    var: "nonce".to_string(),
+                }),
+                op: BinaryOp::Eq,
+                right: Box::new(Expression::LayerVar {
+                    layer: "account".to_string(),
+                    var: "nonce".to_string(),
+                }),
+            },
+        );
+        assert!(!is_cross_chain(&aa));
+
+        let mut context = CrossChainContext::default();
+        context.set_chain_var("l1".to_string(), "locked".to_string(), 1_000.into());
+        context.set_chain_var("l2".to_string(), "minted".to_string(), 1_000.into());
+        assert_eq!(context.check(&invariant), Ok(true));
+
+        // Minting on L2 without a matching lock on L1
+        context.set_chain_var("l2".to_string(), "minted".to_string(), 1_500.into());
+        assert_eq!(context.check(&invariant), Ok(false));
+        assert_eq!(context.get_chain_var("l1", "locked"), Some(&1_000.into()));
+
+        context.chain_state.remove("l1");
+        assert!(matches!(
+            context.check(&invariant),
+            Err(EvaluationError::UndefinedVariable { .. })
+        ));
+    }
+
+    #[test]
+    fn test_link_resolves_namespaces_to_programs() {
+        let program = |name: &str, chain: &str, var: &str| {
+            let mut program =
+                ProgramModel::new(name.to_string(), chain.to_string(), format!("{}.src", name));
+            program.add_state_var(StateVar {
+                name: var.to_string(),
+                type_name: "u64".to_string(),
+                is_mutable: true,
+                visibility: None,
+                abilities: Default::default(),
+                depends_on: Vec::new(),
+            });
+            program
+        };
+        let programs = [
+            program("L1", "evm", "locked"),
+            program("l2", "solana", "minted"),
+        ];
+
+        let link = CrossChainLink::new(&lock_backs_mint(), &programs);
+        assert!(link.is_linked());
+        assert_eq!(link.programs["l1"], "L1/evm");
+        assert_eq!(link.programs["l2"], "l2/solana");
+
+        let link = CrossChainLink::new(&lock_backs_mint(), &programs[..1]);
+        assert_eq!(link.unresolved, vec!["l2::minted"]);
+    }
+}
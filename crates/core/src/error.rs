@@ -40,6 +40,10 @@ pub enum InvarError {
     #[error("Simulation failed: {0}")]
     SimulationFailed(String),
 
+    /// Invariant evaluation failed.
+    #[error("Evaluation failed: {0}")]
+    Evaluation(#[from] crate::evaluator::EvaluationError),
+
     /// Configuration or parsing error.
     #[error("Configuration error: {0}")]
     ConfigError(String),
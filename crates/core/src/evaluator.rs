@@ -3,11 +3,44 @@
 //! Supports both compile-time static evaluation and runtime evaluation.
 //! All operations use checked arithmetic with explicit overflow handling.
 //! No floating point. No randomness. No external I/O.
+//!
+//! # Evaluation order
+//!
+//! Sub-expressions are evaluated left to right in source order, and the
+//! first error in that order is the one returned:
+//!
+//! - Comparisons, cross-phase relations and map lookups evaluate their left
+//!   (or map) side, then their right (or key) side.
+//! - `&&` and `||` evaluate their left side and only evaluate the right side
+//!   when the left does not decide the result.
+//! - `if` evaluates its condition, then only the selected branch.
+//! - Function calls look the function up, evaluate every argument left to
+//!   right, then call it; an unknown function is reported before any
+//!   argument is evaluated, and an argument error means no call is made.
+//! - Quantifiers evaluate the collection, then the body once per element in
+//!   collection order (map values by key). `forall` and `exists` stop at the
+//!   first element that decides the result.
+//! - Aggregations evaluate the collection, then per element its filter and,
+//!   only when the filter holds, its body (`count` never evaluates the body).
+//!
+//! Memoization can skip a repeated function call or comparison whose result
+//! is already known, but never reorders the ones that run.
+//! [`Evaluator::evaluate_with_trace`] records the variable reads and function
+//! calls in the order they happen.
 
-use crate::model::Expression;
+use crate::account_abstraction::{AAContext, ExecutionPhase};
+use crate::model::{AggregateOp, ArithmeticOp, Expression, ProgramModel, Quantifier};
+use crate::suggest::{did_you_mean, format_suggestions};
 use crate::types::Type;
+use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+/// Width of a `Value::BigInt` in bytes.
+pub const U256_BYTES: usize = 32;
 
 /// A runtime value with type information.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -20,8 +53,16 @@ pub enum Value {
     U128(u128),
     /// 64-bit signed integer.
     I64(i64),
+    /// 256-bit unsigned integer, as 32 little-endian bytes.
+    BigInt(Vec<u8>),
     /// Address (hex string representation).
     Address(String),
+    /// Collection of values.
+    Array(Vec<Value>),
+    /// String value.
+    Str(String),
+    /// Mapping from the string form of each key to its value.
+    Map(BTreeMap<String, Value>),
 }
 
 impl Value {
@@ -32,7 +73,16 @@ impl Value {
             Self::U64(_) => Type::U64,
             Self::U128(_) => Type::U128,
             Self::I64(_) => Type::I64,
+            Self::BigInt(_) => Type::U256,
             Self::Address(_) => Type::Address,
+            Self::Array(_) => Type::Array,
+            Self::Str(_) => Type::Str,
+            // Keys are stored in string form; the value type comes from the
+            // first entry (`u64` for an empty map)
+            Self::Map(entries) => Type::Map(
+                Box::new(Type::Str),
+                Box::new(entries.values().next().map_or(Type::U64, Value::get_type)),
+            ),
         }
     }
 
@@ -43,7 +93,11 @@ impl Value {
             Self::U64(n) => Ok(*n != 0),
             Self::U128(n) => Ok(*n != 0),
             Self::I64(n) => Ok(*n != 0),
+            Self::BigInt(bytes) => Ok(bytes.iter().any(|byte| *byte != 0)),
             Self::Address(a) => Ok(!a.is_empty()),
+            Self::Array(items) => Ok(!items.is_empty()),
+            Self::Str(s) => Ok(!s.is_empty()),
+            Self::Map(entries) => Ok(!entries.is_empty()),
         }
     }
 
@@ -66,11 +120,192 @@ impl Value {
                     Err(EvaluationError::ConversionOverflow)
                 }
             }
+            Self::BigInt(_) => self
+                .as_biguint()
+                .and_then(|n| u64::try_from(&n).map_err(|_| EvaluationError::ConversionOverflow)),
+            _ => Err(EvaluationError::TypeError),
+        }
+    }
+
+    /// A 256-bit integer, or `None` when `n` needs more than 256 bits.
+    pub fn u256(n: &BigUint) -> Option<Value> {
+        let mut bytes = n.to_bytes_le();
+        if bytes.len() > U256_BYTES {
+            return None;
+        }
+        bytes.resize(U256_BYTES, 0);
+        Some(Value::BigInt(bytes))
+    }
+
+    /// The largest 256-bit integer, `2^256 - 1`.
+    pub fn u256_max() -> Value {
+        Value::BigInt(vec![u8::MAX; U256_BYTES])
+    }
+
+    /// Unsigned integer value of any width; negative and non-numeric
+    /// values have none.
+    fn as_biguint(&self) -> Result<BigUint, EvaluationError> {
+        match self {
+            Self::U64(n) => Ok(BigUint::from(*n)),
+            Self::U128(n) => Ok(BigUint::from(*n)),
+            Self::I64(n) => u64::try_from(*n)
+                .map(BigUint::from)
+                .map_err(|_| EvaluationError::ConversionOverflow),
+            Self::BigInt(bytes) => Ok(BigUint::from_bytes_le(bytes)),
+            _ => Err(EvaluationError::TypeError),
+        }
+    }
+
+    /// Numeric ordering across integer widths; `None` unless both values
+    /// are integers.
+    pub fn numeric_cmp(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Self::I64(l), Self::I64(r)) => Some(l.cmp(r)),
+            (Self::I64(l), _) if *l < 0 => other.as_biguint().ok().map(|_| Ordering::Less),
+            (_, Self::I64(r)) if *r < 0 => self.as_biguint().ok().map(|_| Ordering::Greater),
+            _ => Some(self.as_biguint().ok()?.cmp(&other.as_biguint().ok()?)),
+        }
+    }
+
+    /// 256-bit unsigned arithmetic. At least one operand must be a `BigInt`;
+    /// the other may be any non-negative integer.
+    ///
+    /// Results are checked rather than wrapped as the EVM would: a sum or
+    /// product past `2^256 - 1` is `Overflow`, a negative difference `Underflow`.
+    pub fn checked_arithmetic(&self, op: ArithmeticOp, rhs: &Value) -> EvalResult<Value> {
+        if !matches!(self, Self::BigInt(_)) && !matches!(rhs, Self::BigInt(_)) {
+            return Err(EvaluationError::TypeError);
+        }
+        let (left, right) = (self.as_biguint()?, rhs.as_biguint()?);
+        let result = match op {
+            ArithmeticOp::Add => left + right,
+            ArithmeticOp::Mul => left * right,
+            ArithmeticOp::Sub if left < right => return Err(EvaluationError::Underflow),
+            ArithmeticOp::Sub => left - right,
+        };
+        Value::u256(&result).ok_or(EvaluationError::Overflow)
+    }
+
+    /// Checked `add`, `sub`, `mul` or `div` of two integers, as evaluated by
+    /// the [`ARITHMETIC_BUILTINS`].
+    ///
+    /// A `BigInt` operand makes the result 256-bit as in
+    /// [`checked_arithmetic`](Self::checked_arithmetic); an `i64` operand makes
+    /// it signed; otherwise `u64` widens to `u128` when mixed. Results outside
+    /// the type are `Overflow` or `Underflow`, never wrapped.
+    pub fn checked_builtin(&self, name: &str, rhs: &Value) -> EvalResult<Value> {
+        if matches!(self, Self::BigInt(_)) || matches!(rhs, Self::BigInt(_)) {
+            return match name {
+                "add" => self.checked_arithmetic(ArithmeticOp::Add, rhs),
+                "sub" => self.checked_arithmetic(ArithmeticOp::Sub, rhs),
+                "mul" => self.checked_arithmetic(ArithmeticOp::Mul, rhs),
+                _ => {
+                    let (left, right) = (self.as_biguint()?, rhs.as_biguint()?);
+                    if right == BigUint::from(0u8) {
+                        return Err(EvaluationError::DivisionByZero);
+                    }
+                    Value::u256(&(left / right)).ok_or(EvaluationError::Overflow)
+                }
+            };
+        }
+        if matches!(self, Self::I64(_)) || matches!(rhs, Self::I64(_)) {
+            let (left, right) = (self.as_i64()?, rhs.as_i64()?);
+            let result = match name {
+                "add" => left.checked_add(right),
+                "sub" => left.checked_sub(right),
+                "mul" => left.checked_mul(right),
+                _ if right == 0 => return Err(EvaluationError::DivisionByZero),
+                _ => left.checked_div(right),
+            };
+            // Sums and differences leave the range on the side of their sign
+            let below = match name {
+                "add" => right < 0,
+                "sub" => right > 0,
+                _ => false,
+            };
+            return result.map(Self::I64).ok_or(if below {
+                EvaluationError::Underflow
+            } else {
+                EvaluationError::Overflow
+            });
+        }
+        let (left, right) = (self.as_u128()?, rhs.as_u128()?);
+        let result = match name {
+            "add" => left.checked_add(right).ok_or(EvaluationError::Overflow)?,
+            "sub" => left.checked_sub(right).ok_or(EvaluationError::Underflow)?,
+            "mul" => left.checked_mul(right).ok_or(EvaluationError::Overflow)?,
+            _ => left
+                .checked_div(right)
+                .ok_or(EvaluationError::DivisionByZero)?,
+        };
+        if matches!((self, rhs), (Self::U64(_), Self::U64(_))) {
+            u64::try_from(result)
+                .map(Self::U64)
+                .map_err(|_| EvaluationError::Overflow)
+        } else {
+            Ok(Self::U128(result))
+        }
+    }
+
+    /// Checked addition of integers, widening `u64` to `u128` when mixed.
+    fn checked_add(&self, rhs: &Value) -> EvalResult<Value> {
+        match (self, rhs) {
+            (Self::U64(l), Self::U64(r)) => l
+                .checked_add(*r)
+                .map(Self::U64)
+                .ok_or(EvaluationError::Overflow),
+            (Self::U128(l), Self::U128(r)) => l
+                .checked_add(*r)
+                .map(Self::U128)
+                .ok_or(EvaluationError::Overflow),
+            (Self::U64(l), Self::U128(r)) | (Self::U128(r), Self::U64(l)) => (*l as u128)
+                .checked_add(*r)
+                .map(Self::U128)
+                .ok_or(EvaluationError::Overflow),
+            (Self::I64(l), Self::I64(r)) => l.checked_add(*r).map(Self::I64).ok_or(if *r < 0 {
+                EvaluationError::Underflow
+            } else {
+                EvaluationError::Overflow
+            }),
+            _ => self.checked_arithmetic(ArithmeticOp::Add, rhs),
+        }
+    }
+
+    /// Checked conversion to another type.
+    ///
+    /// Numeric conversions fail with `ConversionOverflow` when the value does
+    /// not fit the target; non-numeric values can only be "cast" to their own type.
+    pub fn cast(&self, target: &Type) -> Result<Value, EvaluationError> {
+        if self.get_type() == *target {
+            return Ok(self.clone());
+        }
+        match (self, target) {
+            (_, Type::U64) => self.as_u64().map(Value::U64),
+            (_, Type::I64) => self.as_i64().map(Value::I64),
+            (Self::U64(n), Type::U128) => Ok(Value::U128(*n as u128)),
+            (Self::I64(n), Type::U128) => u128::try_from(*n)
+                .map(Value::U128)
+                .map_err(|_| EvaluationError::ConversionOverflow),
+            (Self::BigInt(_), Type::U128) => self.as_biguint().and_then(|n| {
+                u128::try_from(&n)
+                    .map(Value::U128)
+                    .map_err(|_| EvaluationError::ConversionOverflow)
+            }),
+            (_, Type::U256) => self
+                .as_biguint()
+                .map(|n| Value::u256(&n).expect("integers narrower than 256 bits fit in a u256")),
+            _ => Err(EvaluationError::TypeError),
+        }
+    }
+
+    fn as_u128(&self) -> Result<u128, EvaluationError> {
+        match self {
+            Self::U64(n) => Ok(*n as u128),
+            Self::U128(n) => Ok(*n),
             _ => Err(EvaluationError::TypeError),
         }
     }
 
-    #[allow(dead_code)]
     fn as_i64(&self) -> Result<i64, EvaluationError> {
         match self {
             Self::I64(n) => Ok(*n),
@@ -81,6 +316,10 @@ impl Value {
                     Err(EvaluationError::ConversionOverflow)
                 }
             }
+            Self::U128(n) => i64::try_from(*n).map_err(|_| EvaluationError::ConversionOverflow),
+            Self::BigInt(_) => self
+                .as_biguint()
+                .and_then(|n| i64::try_from(&n).map_err(|_| EvaluationError::ConversionOverflow)),
             _ => Err(EvaluationError::TypeError),
         }
     }
@@ -93,53 +332,169 @@ impl std::fmt::Display for Value {
             Self::U64(n) => write!(f, "{}", n),
             Self::U128(n) => write!(f, "{}", n),
             Self::I64(n) => write!(f, "{}", n),
+            Self::BigInt(bytes) => write!(f, "{}", BigUint::from_bytes_le(bytes)),
             Self::Address(a) => write!(f, "{}", a),
+            Self::Str(s) => write!(f, "{:?}", s),
+            Self::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Self::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
         }
     }
 }
 
 /// Evaluation errors.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
 pub enum EvaluationError {
     /// Arithmetic overflow.
+    #[error("arithmetic overflow")]
     Overflow,
     /// Arithmetic underflow.
+    #[error("arithmetic underflow")]
     Underflow,
     /// Type error during evaluation.
+    #[error("type error")]
     TypeError,
     /// Division by zero.
+    #[error("division by zero")]
     DivisionByZero,
-    /// Undefined variable.
-    UndefinedVariable(String),
+    /// Undefined variable, with known names it may be a misspelling of.
+    #[error("undefined variable '{name}'{}", format_suggestions(.suggestions))]
+    UndefinedVariable {
+        /// Name that was looked up.
+        name: String,
+        /// Closest defined names, best first.
+        suggestions: Vec<String>,
+    },
     /// Undefined function.
+    #[error("undefined function '{0}'")]
     UndefinedFunction(String),
     /// Function argument error.
+    #[error("invalid argument: {0}")]
     InvalidArgument(String),
     /// Conversion overflow.
+    #[error("conversion overflow")]
     ConversionOverflow,
+    /// Input such as a state snapshot or numeric literal that could not be
+    /// parsed, with the parser's own error when there is one.
+    #[error("{message}")]
+    ParseError {
+        /// What was being parsed.
+        message: String,
+        /// The parser's error.
+        source: Option<ErrorSource>,
+    },
     /// Custom error.
+    #[error("{0}")]
     Custom(String),
 }
 
-impl std::fmt::Display for EvaluationError {
+/// An error kept as the [`source`](std::error::Error::source) of an
+/// [`EvaluationError`].
+///
+/// Shared rather than boxed so evaluation results stay cloneable; two
+/// sources are equal when they display the same.
+#[derive(Debug, Clone)]
+pub struct ErrorSource(Arc<dyn std::error::Error + Send + Sync>);
+
+impl ErrorSource {
+    /// Wrap `error`.
+    pub fn new(error: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Self(Arc::new(error))
+    }
+}
+
+impl std::fmt::Display for ErrorSource {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Overflow => write!(f, "arithmetic overflow"),
-            Self::Underflow => write!(f, "arithmetic underflow"),
-            Self::TypeError => write!(f, "type error"),
-            Self::DivisionByZero => write!(f, "division by zero"),
-            Self::UndefinedVariable(name) => write!(f, "undefined variable '{}'", name),
-            Self::UndefinedFunction(name) => write!(f, "undefined function '{}'", name),
-            Self::InvalidArgument(msg) => write!(f, "invalid argument: {}", msg),
-            Self::ConversionOverflow => write!(f, "conversion overflow"),
-            Self::Custom(msg) => write!(f, "{}", msg),
-        }
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for ErrorSource {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
     }
 }
 
+impl PartialEq for ErrorSource {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_string() == other.0.to_string()
+    }
+}
+
+impl Eq for ErrorSource {}
+
 /// Result type for evaluation operations.
 pub type EvalResult<T> = Result<T, EvaluationError>;
 
+/// Zero-argument builtins answered from the context's [`BlockContext`]
+/// unless a function of the same name is registered.
+pub const BLOCK_BUILTINS: [&str; 3] = ["now", "block_number", "chain_id"];
+
+/// Binary builtins computed by [`Value::checked_builtin`] unless a function
+/// of the same name is registered. The expression language has no
+/// arithmetic operators.
+pub const ARITHMETIC_BUILTINS: [&str; 4] = ["add", "sub", "mul", "div"];
+
+/// Builtin reading its argument in the context's pre-transaction state,
+/// [`ExecutionContext::old_state`].
+pub const OLD_BUILTIN: &str = "old";
+
+/// Block metadata read by `now()`, `block_number()` and `chain_id()`.
+///
+/// Always set explicitly, never taken from the wall clock, so evaluation
+/// stays deterministic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockContext {
+    /// Block timestamp in seconds, returned by `now()`.
+    pub timestamp: u64,
+    /// Block height (the slot on Solana), returned by `block_number()`.
+    pub number: u64,
+    /// Chain identifier, returned by `chain_id()`.
+    pub chain_id: u64,
+}
+
+impl BlockContext {
+    /// Value of the builtin `name`, or `None` if it is not one of
+    /// [`BLOCK_BUILTINS`].
+    pub fn builtin(&self, name: &str) -> Option<Value> {
+        match name {
+            "now" => Some(Value::U64(self.timestamp)),
+            "block_number" => Some(Value::U64(self.number)),
+            "chain_id" => Some(Value::U64(self.chain_id)),
+            _ => None,
+        }
+    }
+
+    /// The block `blocks` later, each `block_time` seconds after the last.
+    /// Saturates rather than wrapping.
+    pub fn advanced(self, blocks: u64, block_time: u64) -> Self {
+        Self {
+            timestamp: self
+                .timestamp
+                .saturating_add(blocks.saturating_mul(block_time)),
+            number: self.number.saturating_add(blocks),
+            chain_id: self.chain_id,
+        }
+    }
+}
+
 /// Type alias for function implementations.
 pub type EvalFunction = fn(&[Value]) -> EvalResult<Value>;
 
@@ -149,6 +504,16 @@ pub struct ExecutionContext {
     pub state_vars: BTreeMap<String, Value>,
     /// Function implementations.
     pub functions: BTreeMap<String, EvalFunction>,
+    /// Variables changed by [`set_state`](Self::set_state) since the last
+    /// [`take_dirty`](Self::take_dirty). Writes to `state_vars` directly are
+    /// not tracked.
+    pub dirty: BTreeSet<String>,
+    /// Block metadata for the [`BLOCK_BUILTINS`]; calling one while unset is
+    /// an error.
+    pub block: Option<BlockContext>,
+    /// State before the transaction, read by `old()`; calling it while
+    /// unset is an error.
+    pub old_state: Option<BTreeMap<String, Value>>,
 }
 
 impl ExecutionContext {
@@ -157,12 +522,100 @@ impl ExecutionContext {
         Self {
             state_vars: BTreeMap::new(),
             functions: BTreeMap::new(),
+            dirty: BTreeSet::new(),
+            block: None,
+            old_state: None,
+        }
+    }
+
+    /// Set the block metadata, marking the builtin calls (`now()`, ...)
+    /// dirty if it changed.
+    pub fn set_block(&mut self, block: BlockContext) {
+        if self.block != Some(block) {
+            self.dirty
+                .extend(BLOCK_BUILTINS.iter().map(|name| format!("{}()", name)));
+            self.block = Some(block);
         }
     }
 
-    /// Set a state variable value.
+    /// Set the state before the transaction, marking `old()` dirty if it
+    /// changed.
+    pub fn set_old_state(&mut self, state: BTreeMap<String, Value>) {
+        if self.old_state.as_ref() != Some(&state) {
+            self.dirty.insert(format!("{}()", OLD_BUILTIN));
+            self.old_state = Some(state);
+        }
+    }
+
+    /// Set a state variable value, marking it dirty if the value changed.
     pub fn set_state(&mut self, name: String, value: Value) {
-        self.state_vars.insert(name, value);
+        if self.state_vars.get(&name) != Some(&value) {
+            self.dirty.insert(name.clone());
+            self.state_vars.insert(name, value);
+        }
+    }
+
+    /// Variables changed since the previous call, clearing the set.
+    pub fn take_dirty(&mut self) -> BTreeSet<String> {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Load state variables from a JSON object.
+    ///
+    /// Non-negative integers become `u64`, negative integers `i64`, strings
+    /// addresses, arrays `Value::Array` and nested objects `Value::Map`.
+    /// Null and floats are rejected.
+    pub fn from_json(json: &str) -> Result<Self, EvaluationError> {
+        let parsed: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| EvaluationError::ParseError {
+                message: format!("invalid state JSON: {}", e),
+                source: Some(ErrorSource::new(e)),
+            })?;
+        let serde_json::Value::Object(entries) = parsed else {
+            return Err(EvaluationError::Custom(
+                "state JSON must be an object".to_string(),
+            ));
+        };
+
+        let mut ctx = Self::new();
+        for (name, json_value) in entries {
+            let value = value_from_json(&json_value).map_err(|err| in_state(&name, err))?;
+            ctx.set_state(name, value);
+        }
+        Ok(ctx)
+    }
+
+    /// Load layer state (`layer -> variable -> value`) as `layer::variable`
+    /// state variables, converting values as [`from_json`](Self::from_json) does.
+    pub fn from_layer_state(
+        layers: &BTreeMap<String, BTreeMap<String, serde_json::Value>>,
+    ) -> Result<Self, EvaluationError> {
+        let mut ctx = Self::new();
+        for (layer, vars) in layers {
+            for (var, json_value) in vars {
+                let name = format!("{}::{}", layer, var);
+                let value = value_from_json(json_value).map_err(|err| in_state(&name, err))?;
+                ctx.set_state(name, value);
+            }
+        }
+        Ok(ctx)
+    }
+
+    /// Serialize state variables as a JSON object.
+    ///
+    /// Booleans, `u64` and `i64` values, addresses, and arrays and maps of
+    /// them read back unchanged with [`from_json`](Self::from_json). JSON
+    /// carries no other types, so the rest reload as a different value: a
+    /// `u128` that fits in `u64` reloads as `u64`, while larger `u128` and
+    /// 256-bit values are written as decimal strings and, like strings,
+    /// reload as addresses.
+    pub fn to_json(&self) -> String {
+        let entries: serde_json::Map<String, serde_json::Value> = self
+            .state_vars
+            .iter()
+            .map(|(name, value)| (name.clone(), value_to_json(value)))
+            .collect();
+        serde_json::Value::Object(entries).to_string()
     }
 
     /// Register a built-in function.
@@ -171,71 +624,572 @@ impl ExecutionContext {
     }
 }
 
+fn value_from_json(json: &serde_json::Value) -> Result<Value, EvaluationError> {
+    match json {
+        serde_json::Value::Bool(b) => Ok(Value::Bool(*b)),
+        serde_json::Value::Number(n) => {
+            if let Some(v) = n.as_u64() {
+                Ok(Value::U64(v))
+            } else if let Some(v) = n.as_i64() {
+                Ok(Value::I64(v))
+            } else {
+                // Floats and integers past u64 both arrive as f64
+                let literal = n.to_string();
+                let err = literal.parse::<i64>().err();
+                Err(EvaluationError::ParseError {
+                    message: format!("unsupported non-integer number {}", literal),
+                    source: err.map(ErrorSource::new),
+                })
+            }
+        }
+        serde_json::Value::String(s) => Ok(Value::Address(s.clone())),
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(value_from_json)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        serde_json::Value::Null => Err(EvaluationError::Custom(
+            "unsupported null value".to_string(),
+        )),
+        serde_json::Value::Object(entries) => entries
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), value_from_json(value)?)))
+            .collect::<Result<BTreeMap<_, _>, _>>()
+            .map(Value::Map),
+    }
+}
+
+/// Prefix a [`value_from_json`] error with the state variable it was for,
+/// keeping any parse error's source.
+fn in_state(name: &str, err: EvaluationError) -> EvaluationError {
+    match err {
+        EvaluationError::ParseError { message, source } => EvaluationError::ParseError {
+            message: format!("state '{}': {}", name, message),
+            source,
+        },
+        other => EvaluationError::Custom(format!("state '{}': {}", name, other)),
+    }
+}
+
+/// Elements of the collection state variable `var_name`: the items of an
+/// array, or the values of a map in key order.
+///
+/// A variable with no value in `ctx` is undefined, with suggestions drawn
+/// from the state variables `program` declares.
+pub fn collect_state_collection(
+    program: &ProgramModel,
+    var_name: &str,
+    ctx: &ExecutionContext,
+) -> EvalResult<Vec<Value>> {
+    match ctx.state_vars.get(var_name) {
+        Some(value) => collection_elements(value.clone()),
+        None => Err(EvaluationError::UndefinedVariable {
+            name: var_name.to_string(),
+            suggestions: did_you_mean(var_name, program.state_vars.keys()),
+        }),
+    }
+}
+
+/// Elements a quantifier or aggregation ranges over.
+fn collection_elements(collection: Value) -> EvalResult<Vec<Value>> {
+    match collection {
+        Value::Array(items) => Ok(items),
+        Value::Map(entries) => Ok(entries.into_values().collect()),
+        _ => Err(EvaluationError::TypeError),
+    }
+}
+
+fn value_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Bool(b) => serde_json::Value::Bool(*b),
+        Value::U64(n) => serde_json::Value::from(*n),
+        Value::U128(n) => match u64::try_from(*n) {
+            Ok(n) => serde_json::Value::from(n),
+            Err(_) => serde_json::Value::String(n.to_string()),
+        },
+        Value::I64(n) => serde_json::Value::from(*n),
+        Value::BigInt(bytes) => {
+            serde_json::Value::String(BigUint::from_bytes_le(bytes).to_string())
+        }
+        Value::Address(a) | Value::Str(a) => serde_json::Value::String(a.clone()),
+        Value::Array(items) => serde_json::Value::Array(items.iter().map(value_to_json).collect()),
+        Value::Map(entries) => serde_json::Value::Object(
+            entries
+                .iter()
+                .map(|(key, value)| (key.clone(), value_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
 impl Default for ExecutionContext {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// State variable names `expr` may read: each variable, and the shorter
+/// names qualified variables fall back to (`vault::balance` reads `balance`
+/// when no `vault::balance` is set). Calls of the [`BLOCK_BUILTINS`] read
+/// the block and count as the keys `now()` and so on; calls of `old()`
+/// read the pre-transaction state and count as `old()`.
+fn state_keys(expr: &Expression) -> BTreeSet<String> {
+    let mut keys = BTreeSet::new();
+    for name in expr.variables() {
+        let mut rest = name.as_str();
+        while let Some((_, tail)) = rest.split_once("::") {
+            keys.insert(tail.to_string());
+            rest = tail;
+        }
+        keys.insert(name);
+    }
+    for name in expr.functions_called() {
+        if BLOCK_BUILTINS.contains(&name.as_str()) || name == OLD_BUILTIN {
+            keys.insert(format!("{}()", name));
+        }
+    }
+    keys
+}
+
+/// Step recorded after a comparison or logical node that evaluated to `false`.
+pub const FAILURE_MARKER: &str = "INVARIANT_FAILED_HERE";
+
+/// One evaluated expression node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalStep {
+    /// The node rendered as invariant source.
+    pub expr_display: String,
+    /// What the node evaluated to.
+    pub result: EvalResult<Value>,
+}
+
+/// A state read or function call made while evaluating an expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalAccess {
+    /// Read of a variable, rendered as in the source (`paymaster::deposit`).
+    Read(String),
+    /// Call of a registered function, made after its arguments were evaluated.
+    Call(String),
+}
+
+/// Every node evaluated for an expression, innermost first.
+///
+/// A `FAILURE_MARKER` step follows each `BinaryOp` or `Logical` node that
+/// produced `false`, so the first marker points at the earliest failing check.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EvalTrace {
+    /// Steps in evaluation order.
+    pub steps: Vec<EvalStep>,
+    /// Variable reads and function calls in the order they happened.
+    pub accesses: Vec<EvalAccess>,
+}
+
+impl EvalTrace {
+    /// The first node that produced `false` and caused a failure marker.
+    pub fn first_failure(&self) -> Option<&EvalStep> {
+        self.steps
+            .windows(2)
+            .find(|pair| pair[1].expr_display == FAILURE_MARKER)
+            .map(|pair| &pair[0])
+    }
+
+    /// The first failing node, in a form that can be stored with a report.
+    pub fn explanation(&self) -> Option<Evaluation> {
+        let step = self.first_failure()?;
+        let value = step.result.clone().ok()?;
+        Some(Evaluation {
+            expression: step.expr_display.clone(),
+            value,
+        })
+    }
+}
+
+/// A node that made an invariant fail and the value it produced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Evaluation {
+    /// The node rendered as invariant source.
+    pub expression: String,
+    /// What the node evaluated to.
+    pub value: Value,
+}
+
+impl std::fmt::Display for EvalTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for step in &self.steps {
+            if step.expr_display == FAILURE_MARKER {
+                writeln!(f, "    ^ {}", FAILURE_MARKER)?;
+                continue;
+            }
+            match &step.result {
+                Ok(value) => writeln!(f, "  {} => {}", step.expr_display, value)?,
+                Err(e) => writeln!(f, "  {} => error: {}", step.expr_display, e)?,
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Deterministic invariant expression evaluator.
 pub struct Evaluator {
     context: ExecutionContext,
+    /// Memoized `FunctionCall` and `BinaryOp` results.
+    cache: RefCell<ResultCache>,
+    /// Results of `evaluate_cached`, keyed by the expression's address.
+    results: BTreeMap<usize, CachedResult>,
+    caching: bool,
+    /// Steps and accesses collected while `evaluate_with_trace` runs.
+    trace: RefCell<Option<EvalTrace>>,
+    /// Phase snapshots for cross-phase relations.
+    aa_context: Option<AAContext>,
+    /// Elements bound by the quantifiers currently being evaluated.
+    bindings: RefCell<BTreeMap<String, Value>>,
+    /// Whether the argument of an `old()` call is being evaluated.
+    in_old: Cell<bool>,
+}
+
+/// Evaluation results keyed by the rendered expression.
+pub type ResultCache = BTreeMap<String, Value>;
+
+/// A result of [`Evaluator::evaluate_cached`] and the state variables it
+/// depends on.
+struct CachedResult {
+    expression: Expression,
+    reads: BTreeSet<String>,
+    result: EvalResult<Value>,
 }
 
 impl Evaluator {
     /// Create a new evaluator with an execution context.
+    ///
+    /// Results of function calls and binary operations are memoized, and
+    /// dropped whenever [`set_state`](Self::set_state),
+    /// [`set_block`](Self::set_block) or [`set_old_state`](Self::set_old_state)
+    /// changes the context.
     pub fn new(context: ExecutionContext) -> Self {
-        Self { context }
+        Self {
+            context,
+            cache: RefCell::new(ResultCache::new()),
+            results: BTreeMap::new(),
+            caching: true,
+            trace: RefCell::new(None),
+            aa_context: None,
+            bindings: RefCell::new(BTreeMap::new()),
+            in_old: Cell::new(false),
+        }
+    }
+
+    /// Evaluate cross-phase relations against `aa_context`'s phase snapshots.
+    pub fn with_aa_context(mut self, aa_context: AAContext) -> Self {
+        self.aa_context = Some(aa_context);
+        self
+    }
+
+    /// Enable or disable memoization, including the reuse of results by
+    /// [`evaluate_cached`](Self::evaluate_cached). Disabling also clears the
+    /// cache.
+    pub fn set_caching(&mut self, enabled: bool) {
+        self.caching = enabled;
+        if !enabled {
+            self.clear_cache();
+        }
+    }
+
+    /// Drop all memoized results.
+    pub fn clear_cache(&mut self) {
+        self.cache.get_mut().clear();
+        self.results.clear();
+    }
+
+    /// Set a state variable. Memoized results are dropped when the value
+    /// changes, and the variable is marked dirty for
+    /// [`evaluate_cached`](Self::evaluate_cached).
+    pub fn set_state(&mut self, name: String, value: Value) {
+        if self.context.state_vars.get(&name) != Some(&value) {
+            self.cache.get_mut().clear();
+            self.context.set_state(name, value);
+        }
+    }
+
+    /// Set the state before the transaction, read by `old()`. Memoized
+    /// results are dropped when it changes, and results of
+    /// [`evaluate_cached`](Self::evaluate_cached) that call `old()` are
+    /// recomputed.
+    pub fn set_old_state(&mut self, state: BTreeMap<String, Value>) {
+        if self.context.old_state.as_ref() != Some(&state) {
+            self.cache.get_mut().clear();
+            self.context.set_old_state(state);
+        }
+    }
+
+    /// Set the block metadata read by the [`BLOCK_BUILTINS`]. Memoized
+    /// results are dropped when it changes, and results of
+    /// [`evaluate_cached`](Self::evaluate_cached) that call a builtin are
+    /// recomputed.
+    pub fn set_block(&mut self, block: BlockContext) {
+        if self.context.block != Some(block) {
+            self.cache.get_mut().clear();
+            self.context.set_block(block);
+        }
+    }
+
+    /// Evaluate `expr`, reusing its previous result when none of the
+    /// variables it reads changed through [`set_state`](Self::set_state)
+    /// since. Returns the result and whether it was reused.
+    ///
+    /// Meant for checking the same invariants after every step of a
+    /// sequence, where most steps touch few variables.
+    pub fn evaluate_cached(&mut self, expr: &Expression) -> (EvalResult<Value>, bool) {
+        let dirty = self.context.take_dirty();
+        if !self.caching {
+            return (self.evaluate(expr), false);
+        }
+        if !dirty.is_empty() {
+            self.results
+                .retain(|_, cached| cached.reads.is_disjoint(&dirty));
+        }
+
+        // Rendering or hashing the expression can cost as much as evaluating
+        // it; the address is free, and the stored copy guards against reuse
+        let key = expr as *const Expression as usize;
+        if let Some(cached) = self.results.get(&key) {
+            if cached.expression == *expr {
+                return (cached.result.clone(), true);
+            }
+        }
+        let result = self.evaluate(expr);
+        self.results.insert(
+            key,
+            CachedResult {
+                expression: expr.clone(),
+                reads: state_keys(expr),
+                result: result.clone(),
+            },
+        );
+        (result, false)
+    }
+
+    /// Number of memoized results.
+    pub fn cache_size(&self) -> usize {
+        self.cache.borrow().len()
+    }
+
+    /// Evaluate several expressions, sharing memoized sub-results.
+    pub fn evaluate_batch(&self, exprs: &[Expression]) -> Vec<EvalResult<Value>> {
+        exprs.iter().map(|expr| self.evaluate(expr)).collect()
+    }
+
+    /// Evaluate an expression, recording a step for every node evaluated
+    /// and every variable read and function call, in evaluation order.
+    ///
+    /// Memoized results are bypassed so that every sub-expression appears in
+    /// the trace.
+    pub fn evaluate_with_trace(&self, expr: &Expression) -> (EvalResult<Value>, EvalTrace) {
+        let outer = self.trace.replace(Some(EvalTrace::default()));
+        let result = self.evaluate(expr);
+        let trace = self.trace.replace(outer).unwrap_or_default();
+        (result, trace)
+    }
+
+    /// State variables as read at this point: the pre-transaction state
+    /// inside `old()`, the current state elsewhere.
+    fn state(&self) -> &BTreeMap<String, Value> {
+        match &self.context.old_state {
+            Some(old_state) if self.in_old.get() => old_state,
+            _ => &self.context.state_vars,
+        }
+    }
+
+    /// Error for a state variable that is not defined.
+    fn undefined_state(&self, name: String) -> EvaluationError {
+        let suggestions = did_you_mean(&name, self.state().keys());
+        EvaluationError::UndefinedVariable { name, suggestions }
+    }
+
+    /// Call of an unregistered function: one of the [`BLOCK_BUILTINS`], read
+    /// from the context's block, one of the [`ARITHMETIC_BUILTINS`], `old()`,
+    /// or an undefined function.
+    fn evaluate_builtin(&self, name: &str, args: &[Expression]) -> EvalResult<Value> {
+        if ARITHMETIC_BUILTINS.contains(&name) {
+            let [left, right] = args else {
+                return Err(EvaluationError::InvalidArgument(format!(
+                    "{}() takes two arguments",
+                    name
+                )));
+            };
+            let (left, right) = (self.evaluate(left)?, self.evaluate(right)?);
+            self.record(|| EvalAccess::Call(name.to_string()));
+            return left.checked_builtin(name, &right);
+        }
+        if name == OLD_BUILTIN {
+            return self.evaluate_old(args);
+        }
+        if !BLOCK_BUILTINS.contains(&name) {
+            return Err(EvaluationError::UndefinedFunction(name.to_string()));
+        }
+        if !args.is_empty() {
+            return Err(EvaluationError::InvalidArgument(format!(
+                "{}() takes no arguments",
+                name
+            )));
+        }
+        let block = self
+            .context
+            .block
+            .ok_or_else(|| EvaluationError::Custom(format!("{}() needs a block context", name)))?;
+        self.record(|| EvalAccess::Call(name.to_string()));
+        Ok(block
+            .builtin(name)
+            .expect("BLOCK_BUILTINS are all answered by BlockContext"))
+    }
+
+    /// `old(expr)`: `expr` evaluated against the context's pre-transaction
+    /// state.
+    fn evaluate_old(&self, args: &[Expression]) -> EvalResult<Value> {
+        let [arg] = args else {
+            return Err(EvaluationError::InvalidArgument(
+                "old() takes one argument".to_string(),
+            ));
+        };
+        if self.context.old_state.is_none() {
+            return Err(EvaluationError::Custom(
+                "old() needs a pre-transaction state".to_string(),
+            ));
+        }
+        self.record(|| EvalAccess::Call(OLD_BUILTIN.to_string()));
+        let outer = self.in_old.replace(true);
+        let result = self.evaluate(arg);
+        self.in_old.set(outer);
+        result
+    }
+
+    /// Note a read or call in the active trace, if any.
+    fn record(&self, access: impl FnOnce() -> EvalAccess) {
+        if let Some(trace) = self.trace.borrow_mut().as_mut() {
+            trace.accesses.push(access());
+        }
     }
 
     /// Evaluate an expression against the current context.
     pub fn evaluate(&self, expr: &Expression) -> EvalResult<Value> {
+        if self.trace.borrow().is_none() {
+            return self.evaluate_memoized(expr);
+        }
+
+        let result = self.evaluate_node(expr);
+        if let Some(trace) = self.trace.borrow_mut().as_mut() {
+            let steps = &mut trace.steps;
+            steps.push(EvalStep {
+                expr_display: expr.to_string(),
+                result: result.clone(),
+            });
+            let failed = matches!(result, Ok(Value::Bool(false)))
+                && matches!(
+                    expr,
+                    Expression::BinaryOp { .. } | Expression::Logical { .. }
+                );
+            if failed {
+                steps.push(EvalStep {
+                    expr_display: FAILURE_MARKER.to_string(),
+                    result: result.clone(),
+                });
+            }
+        }
+        result
+    }
+
+    fn evaluate_memoized(&self, expr: &Expression) -> EvalResult<Value> {
+        // Inside a quantifier the same source depends on the bound element,
+        // and inside old() on the pre-transaction state
+        let cacheable = self.caching
+            && self.bindings.borrow().is_empty()
+            && !self.in_old.get()
+            && matches!(
+                expr,
+                Expression::FunctionCall { .. } | Expression::BinaryOp { .. }
+            );
+        if !cacheable {
+            return self.evaluate_node(expr);
+        }
+
+        let key = expr.to_string();
+        if let Some(value) = self.cache.borrow().get(&key) {
+            return Ok(value.clone());
+        }
+        let value = self.evaluate_node(expr)?;
+        self.cache.borrow_mut().insert(key, value.clone());
+        Ok(value)
+    }
+
+    fn evaluate_node(&self, expr: &Expression) -> EvalResult<Value> {
         match expr {
             Expression::Boolean(b) => Ok(Value::Bool(*b)),
 
+            // Same variant the type checker infers for the literal
             Expression::Int(val) => {
-                // Determine appropriate type based on value
-                if *val < 0 {
-                    Ok(Value::I64(*val as i64))
-                } else if *val <= u64::MAX as i128 {
-                    Ok(Value::U64(*val as u64))
-                } else {
-                    Ok(Value::U128(*val as u128))
+                let overflow = |_| EvaluationError::ConversionOverflow;
+                match Type::of_int_literal(*val) {
+                    Some(Type::U64) => u64::try_from(*val).map(Value::U64).map_err(overflow),
+                    Some(Type::U128) => u128::try_from(*val).map(Value::U128).map_err(overflow),
+                    Some(Type::I64) => i64::try_from(*val).map(Value::I64).map_err(overflow),
+                    _ => Err(EvaluationError::ConversionOverflow),
                 }
             }
 
-            Expression::Var(name) => self
-                .context
-                .state_vars
-                .get(name)
-                .cloned()
-                .ok_or_else(|| EvaluationError::UndefinedVariable(name.clone())),
+            Expression::String(s) => Ok(Value::Str(s.clone())),
+
+            Expression::Var(name) => {
+                self.record(|| EvalAccess::Read(name.clone()));
+                let bindings = self.bindings.borrow();
+                bindings
+                    .get(name)
+                    .or_else(|| self.state().get(name))
+                    .cloned()
+                    .ok_or_else(|| EvaluationError::UndefinedVariable {
+                        name: name.clone(),
+                        suggestions: did_you_mean(name, bindings.keys().chain(self.state().keys())),
+                    })
+            }
 
             Expression::LayerVar { layer, var } => {
                 // Layer-qualified variables: look up by full qualified name
                 let qualified_name = format!("{}::{}", layer, var);
-                self.context
-                    .state_vars
+                self.record(|| EvalAccess::Read(qualified_name.clone()));
+                self.state()
                     .get(&qualified_name)
                     .cloned()
-                    .or_else(|| self.context.state_vars.get(var).cloned())
-                    .ok_or(EvaluationError::UndefinedVariable(qualified_name))
+                    .or_else(|| self.state().get(var).cloned())
+                    .ok_or_else(|| self.undefined_state(qualified_name))
             }
 
             Expression::PhaseQualifiedVar { phase, layer, var } => {
                 // Phase-qualified variables: phase::layer::var
-                // For now, evaluate as layer::var (full phase support requires AA context)
+                // With an AA context, read the phase snapshot (the live layer
+                // state for the current phase); otherwise evaluate as layer::var
                 let qualified_name = format!("{}::{}::{}", phase, layer, var);
-                self.context
-                    .state_vars
+                self.record(|| EvalAccess::Read(qualified_name.clone()));
+                let at_phase = self.aa_context.as_ref().and_then(|aa_context| {
+                    let phase = phase.parse::<ExecutionPhase>().ok()?;
+                    aa_context
+                        .get_layer_var_at_phase(phase, layer, var)
+                        .or_else(|| {
+                            let unsnapshotted = aa_context.get_phase_snapshot(phase).is_none();
+                            (unsnapshotted && aa_context.in_phase(phase))
+                                .then(|| aa_context.get_layer_var(layer, var))?
+                        })
+                });
+                if let Some(json_value) = at_phase {
+                    return value_from_json(json_value)
+                        .map_err(|err| in_state(&qualified_name, err));
+                }
+                self.state()
                     .get(&qualified_name)
                     .cloned()
                     .or_else(|| {
                         let layer_var = format!("{}::{}", layer, var);
-                        self.context.state_vars.get(&layer_var).cloned()
+                        self.state().get(&layer_var).cloned()
                     })
-                    .or_else(|| self.context.state_vars.get(var).cloned())
-                    .ok_or(EvaluationError::UndefinedVariable(qualified_name))
+                    .or_else(|| self.state().get(var).cloned())
+                    .ok_or_else(|| self.undefined_state(qualified_name))
             }
 
             Expression::PhaseConstraint {
@@ -248,14 +1202,23 @@ impl Evaluator {
             }
 
             Expression::CrossPhaseRelation {
-                phase1: _,
+                phase1,
                 expr1,
-                phase2: _,
+                phase2,
                 expr2,
                 op,
             } => {
-                // Evaluate cross-phase relation: expr1 op expr2
-                // Phase context requires AA context for snapshot lookup
+                if let Some(aa_context) = &self.aa_context {
+                    let phase = |name: &str| {
+                        name.parse::<ExecutionPhase>().map_err(|_| {
+                            EvaluationError::Custom(format!("unknown phase '{}'", name))
+                        })
+                    };
+                    return aa_context
+                        .cross_phase_assert(expr1, phase(phase1)?, *op, expr2, phase(phase2)?)
+                        .map(Value::Bool);
+                }
+                // Without phase snapshots both sides read the current state
                 let left_val = self.evaluate(expr1)?;
                 let right_val = self.evaluate(expr2)?;
                 self.eval_binary_op(&left_val, op, &right_val)
@@ -296,17 +1259,27 @@ impl Evaluator {
                 Ok(Value::Bool(!val))
             }
 
+            // Unsigned values have no negation, not even `-0`
+            Expression::Negate(expr) => match self.evaluate(expr)? {
+                Value::I64(n) => n
+                    .checked_neg()
+                    .map(Value::I64)
+                    .ok_or(EvaluationError::Overflow),
+                Value::U64(_) | Value::U128(_) | Value::BigInt(_) => Err(EvaluationError::Overflow),
+                _ => Err(EvaluationError::TypeError),
+            },
+
             Expression::FunctionCall { name, args } => {
-                let func = self
-                    .context
-                    .functions
-                    .get(name)
-                    .ok_or_else(|| EvaluationError::UndefinedFunction(name.clone()))?;
+                let Some(func) = self.context.functions.get(name) else {
+                    return self.evaluate_builtin(name, args);
+                };
 
                 let arg_vals: EvalResult<Vec<Value>> =
                     args.iter().map(|arg| self.evaluate(arg)).collect();
+                let arg_vals = arg_vals?;
 
-                func(&arg_vals?)
+                self.record(|| EvalAccess::Call(name.clone()));
+                func(&arg_vals)
             }
 
             Expression::Tuple(exprs) => {
@@ -317,11 +1290,194 @@ impl Evaluator {
                     self.evaluate(&exprs[0])
                 }
             }
+
+            Expression::Conditional {
+                cond,
+                then_expr,
+                else_expr,
+            } => {
+                // Only the selected branch is evaluated
+                match self.evaluate(cond)? {
+                    Value::Bool(true) => self.evaluate(then_expr),
+                    Value::Bool(false) => self.evaluate(else_expr),
+                    _ => Err(EvaluationError::TypeError),
+                }
+            }
+
+            Expression::Cast { expr, target } => self.evaluate(expr)?.cast(target),
+
+            Expression::MapAccess { map, key } => {
+                let Value::Map(entries) = self.evaluate(map)? else {
+                    return Err(EvaluationError::TypeError);
+                };
+                let key = match self.evaluate(key)? {
+                    Value::Str(s) | Value::Address(s) => s,
+                    other => other.to_string(),
+                };
+                entries
+                    .get(&key)
+                    .cloned()
+                    .ok_or_else(|| EvaluationError::UndefinedVariable {
+                        name: format!("{}[{}]", map, key),
+                        suggestions: did_you_mean(&key, entries.keys())
+                            .into_iter()
+                            .map(|key| format!("{}[{}]", map, key))
+                            .collect(),
+                    })
+            }
+
+            Expression::Assert(constraint, message) => match self.evaluate(constraint)? {
+                Value::Bool(false) => Err(EvaluationError::Custom(message.clone())),
+                Value::Bool(true) => Ok(Value::Bool(true)),
+                _ => Err(EvaluationError::TypeError),
+            },
+
+            Expression::Quantified {
+                quantifier,
+                var,
+                collection,
+                body,
+            } => {
+                let elements = collection_elements(self.evaluate(collection)?)?;
+                self.evaluate_quantified(*quantifier, var, elements, body)
+            }
+
+            Expression::Aggregate {
+                op,
+                binder,
+                collection,
+                body,
+                filter,
+            } => {
+                let elements = collection_elements(self.evaluate(collection)?)?;
+                self.evaluate_aggregate(*op, binder, elements, body, filter.as_deref())
+            }
+        }
+    }
+
+    /// Combine `body`'s value for each element passing `filter`, with
+    /// `binder` bound to the element.
+    fn evaluate_aggregate(
+        &self,
+        op: AggregateOp,
+        binder: &str,
+        elements: Vec<Value>,
+        body: &Expression,
+        filter: Option<&Expression>,
+    ) -> EvalResult<Value> {
+        let mut count = 0u64;
+        let mut acc: Option<Value> = None;
+        for element in elements {
+            if let Some(filter) = filter {
+                if !self
+                    .evaluate_bound(binder, element.clone(), filter)?
+                    .to_bool()?
+                {
+                    continue;
+                }
+            }
+            if op == AggregateOp::Count {
+                count = count.checked_add(1).ok_or(EvaluationError::Overflow)?;
+                continue;
+            }
+            let value = self.evaluate_bound(binder, element, body)?;
+            acc = Some(match acc {
+                Some(total) if op == AggregateOp::Sum => total.checked_add(&value)?,
+                Some(current) => {
+                    let ordering = value
+                        .numeric_cmp(&current)
+                        .ok_or(EvaluationError::TypeError)?;
+                    if ordering == Ordering::Greater {
+                        value
+                    } else {
+                        current
+                    }
+                }
+                None => value,
+            });
+        }
+
+        match op {
+            AggregateOp::Count => Ok(Value::U64(count)),
+            AggregateOp::Sum => Ok(acc.unwrap_or(Value::U64(0))),
+            // No sentinel: any value chosen would satisfy some bound by accident
+            AggregateOp::Max => {
+                acc.ok_or_else(|| EvaluationError::Custom(format!("{} of an empty selection", op)))
+            }
+        }
+    }
+
+    /// Combine `body`'s value for each element, with `var` bound to the element.
+    fn evaluate_quantified(
+        &self,
+        quantifier: Quantifier,
+        var: &str,
+        elements: Vec<Value>,
+        body: &Expression,
+    ) -> EvalResult<Value> {
+        let mut count = 0u64;
+        let mut acc: Option<Value> = None;
+        for element in elements {
+            let value = self.evaluate_bound(var, element, body)?;
+            match quantifier {
+                Quantifier::ForAll if !value.to_bool()? => return Ok(Value::Bool(false)),
+                Quantifier::Exists if value.to_bool()? => return Ok(Value::Bool(true)),
+                Quantifier::ForAll | Quantifier::Exists => {}
+                Quantifier::Count => count += u64::from(value.to_bool()?),
+                Quantifier::Sum => {
+                    acc = Some(match acc {
+                        Some(total) => total.checked_add(&value)?,
+                        None => value,
+                    });
+                }
+                Quantifier::Min | Quantifier::Max => {
+                    let replaces = match quantifier {
+                        Quantifier::Max => Ordering::Greater,
+                        _ => Ordering::Less,
+                    };
+                    acc = Some(match acc {
+                        Some(current) => {
+                            let ordering = value
+                                .numeric_cmp(&current)
+                                .ok_or(EvaluationError::TypeError)?;
+                            if ordering == replaces {
+                                value
+                            } else {
+                                current
+                            }
+                        }
+                        None => value,
+                    });
+                }
+            }
+        }
+
+        match quantifier {
+            Quantifier::ForAll => Ok(Value::Bool(true)),
+            Quantifier::Exists => Ok(Value::Bool(false)),
+            Quantifier::Count => Ok(Value::U64(count)),
+            Quantifier::Sum => Ok(acc.unwrap_or(Value::U64(0))),
+            Quantifier::Min | Quantifier::Max => acc.ok_or_else(|| {
+                EvaluationError::Custom(format!("{} of an empty collection", quantifier))
+            }),
         }
     }
 
+    /// Evaluate `body` with `var` bound to `element`, shadowing any state
+    /// variable or outer binding of the same name.
+    fn evaluate_bound(&self, var: &str, element: Value, body: &Expression) -> EvalResult<Value> {
+        let outer = self.bindings.borrow_mut().insert(var.to_string(), element);
+        let result = self.evaluate(body);
+        let mut bindings = self.bindings.borrow_mut();
+        match outer {
+            Some(value) => bindings.insert(var.to_string(), value),
+            None => bindings.remove(var),
+        };
+        result
+    }
+
     /// Evaluate a binary operation with checked arithmetic.
-    fn eval_binary_op(
+    pub(crate) fn eval_binary_op(
         &self,
         left: &Value,
         op: &crate::model::BinaryOp,
@@ -329,6 +1485,35 @@ impl Evaluator {
     ) -> EvalResult<Value> {
         use crate::model::BinaryOp;
 
+        // 256-bit operands are compared against integers of any width
+        if matches!(left, Value::BigInt(_)) || matches!(right, Value::BigInt(_)) {
+            if let (Ok(l), Ok(r)) = (left.as_biguint(), right.as_biguint()) {
+                let ordering = l.cmp(&r);
+                return Ok(Value::Bool(match op {
+                    BinaryOp::Eq => ordering == Ordering::Equal,
+                    BinaryOp::Neq => ordering != Ordering::Equal,
+                    BinaryOp::Lt => ordering == Ordering::Less,
+                    BinaryOp::Gt => ordering == Ordering::Greater,
+                    BinaryOp::Lte => ordering != Ordering::Greater,
+                    BinaryOp::Gte => ordering != Ordering::Less,
+                }));
+            }
+        }
+
+        // Mixed u64/u128 operands are compared at u128 width
+        let widened;
+        let (left, right) = match (left, right) {
+            (Value::U64(l), Value::U128(_)) => {
+                widened = Value::U128(*l as u128);
+                (&widened, right)
+            }
+            (Value::U128(_), Value::U64(r)) => {
+                widened = Value::U128(*r as u128);
+                (left, &widened)
+            }
+            _ => (left, right),
+        };
+
         match op {
             BinaryOp::Eq => Ok(Value::Bool(left == right)),
 
@@ -368,6 +1553,218 @@ impl Evaluator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::{BinaryOp, LogicalOp};
+
+    fn undefined_variable(name: &str) -> EvaluationError {
+        EvaluationError::UndefinedVariable {
+            name: name.to_string(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_with_filter() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state(
+            "deposits".to_string(),
+            Value::Array(vec![Value::U64(5), Value::U64(20), Value::U64(30)]),
+        );
+        let evaluator = Evaluator::new(ctx);
+        let aggregate = |op, threshold: i128| Expression::Aggregate {
+            op,
+            binder: "d".to_string(),
+            collection: var("deposits"),
+            body: var("d"),
+            filter: Some(Box::new(Expression::BinaryOp {
+                left: var("d"),
+                op: BinaryOp::Gt,
+                right: Box::new(Expression::Int(threshold)),
+            })),
+        };
+
+        assert_eq!(
+            evaluator.evaluate(&aggregate(AggregateOp::Sum, 10)),
+            Ok(Value::U64(50))
+        );
+        assert_eq!(
+            evaluator.evaluate(&aggregate(AggregateOp::Count, 10)),
+            Ok(Value::U64(2))
+        );
+        assert_eq!(
+            evaluator.evaluate(&aggregate(AggregateOp::Max, 10)),
+            Ok(Value::U64(30))
+        );
+
+        // Nothing passes the filter: sum and count are zero, max is an error
+        assert_eq!(
+            evaluator.evaluate(&aggregate(AggregateOp::Sum, 100)),
+            Ok(Value::U64(0))
+        );
+        assert_eq!(
+            evaluator.evaluate(&aggregate(AggregateOp::Count, 100)),
+            Ok(Value::U64(0))
+        );
+        assert_eq!(
+            evaluator.evaluate(&aggregate(AggregateOp::Max, 100)),
+            Err(EvaluationError::Custom(
+                "max of an empty selection".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_aggregate_sum_is_checked() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state(
+            "deposits".to_string(),
+            Value::Array(vec![Value::U64(u64::MAX), Value::U64(1)]),
+        );
+        let sum = Expression::Aggregate {
+            op: AggregateOp::Sum,
+            binder: "d".to_string(),
+            collection: var("deposits"),
+            body: var("d"),
+            filter: None,
+        };
+        assert_eq!(
+            Evaluator::new(ctx).evaluate(&sum),
+            Err(EvaluationError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_negate() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("supply".to_string(), Value::U64(5));
+        let evaluator = Evaluator::new(ctx);
+        let negate = |expr: Expression| Expression::Negate(Box::new(expr));
+
+        assert_eq!(evaluator.evaluate(&Expression::Int(-5)), Ok(Value::I64(-5)));
+        // Non-negative literals are unsigned, like any other U64
+        assert_eq!(
+            evaluator.evaluate(&negate(Expression::Int(5))),
+            Err(EvaluationError::Overflow)
+        );
+        assert_eq!(
+            evaluator.evaluate(&negate(negate(Expression::Int(-5)))),
+            Ok(Value::I64(-5))
+        );
+        assert_eq!(
+            evaluator.evaluate(&negate(Expression::Int(i64::MIN as i128))),
+            Err(EvaluationError::Overflow)
+        );
+        assert_eq!(
+            evaluator.evaluate(&negate(*var("supply"))),
+            Err(EvaluationError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_u256_comparison_and_overflow() {
+        let evaluator = Evaluator::new(ExecutionContext::new());
+        let max = Value::u256_max();
+        assert_eq!(max.get_type(), Type::U256);
+        assert_eq!(
+            evaluator.eval_binary_op(&max, &BinaryOp::Eq, &Value::u256_max()),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            evaluator.eval_binary_op(&Value::U128(u128::MAX), &BinaryOp::Lt, &max),
+            Ok(Value::Bool(true))
+        );
+
+        assert_eq!(
+            max.checked_arithmetic(ArithmeticOp::Add, &Value::U64(1)),
+            Err(EvaluationError::Overflow)
+        );
+        assert_eq!(
+            Value::U64(1).checked_arithmetic(ArithmeticOp::Sub, &max),
+            Err(EvaluationError::Underflow)
+        );
+        let product = Value::U128(u128::MAX)
+            .cast(&Type::U256)
+            .and_then(|n| n.checked_arithmetic(ArithmeticOp::Mul, &Value::U64(2)))
+            .unwrap();
+        assert_eq!(
+            product.to_string(),
+            (BigUint::from(u128::MAX) * 2u8).to_string()
+        );
+        assert_eq!(
+            product.cast(&Type::U128),
+            Err(EvaluationError::ConversionOverflow)
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_builtins_are_checked() {
+        let evaluator = Evaluator::new(ExecutionContext::new());
+        let call = |name: &str, left: i128, right: i128| Expression::FunctionCall {
+            name: name.to_string(),
+            args: vec![Expression::Int(left), Expression::Int(right)],
+        };
+        assert_eq!(evaluator.evaluate(&call("add", 2, 3)), Ok(Value::U64(5)));
+        assert_eq!(evaluator.evaluate(&call("div", 7, 2)), Ok(Value::U64(3)));
+        assert_eq!(evaluator.evaluate(&call("sub", -2, 3)), Ok(Value::I64(-5)));
+        assert_eq!(
+            evaluator.evaluate(&call("sub", 2, 3)),
+            Err(EvaluationError::Underflow)
+        );
+        assert_eq!(
+            evaluator.evaluate(&call("mul", u64::MAX as i128, 2)),
+            Err(EvaluationError::Overflow)
+        );
+        assert_eq!(
+            evaluator.evaluate(&call("div", 1, 0)),
+            Err(EvaluationError::DivisionByZero)
+        );
+
+        // Mixed widths widen, and 256-bit operands stay 256-bit
+        assert_eq!(
+            Value::U64(u64::MAX).checked_builtin("mul", &Value::U128(2)),
+            Ok(Value::U128(u64::MAX as u128 * 2))
+        );
+        let quotient = BigUint::from_bytes_le(&[u8::MAX; U256_BYTES]) / BigUint::from(u64::MAX);
+        assert_eq!(
+            Value::u256_max().checked_builtin("div", &Value::U64(u64::MAX)),
+            Ok(Value::u256(&quotient).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_old_reads_pre_transaction_state() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("supply".to_string(), Value::U64(120));
+        let mut evaluator = Evaluator::new(ctx);
+        let grew = Expression::BinaryOp {
+            left: Box::new(Expression::Var("supply".to_string())),
+            op: BinaryOp::Gt,
+            right: Box::new(Expression::FunctionCall {
+                name: "old".to_string(),
+                args: vec![Expression::Var("supply".to_string())],
+            }),
+        };
+        assert!(matches!(
+            evaluator.evaluate(&grew),
+            Err(EvaluationError::Custom(message)) if message.contains("pre-transaction")
+        ));
+
+        evaluator.set_old_state(BTreeMap::from([("supply".to_string(), Value::U64(100))]));
+        assert_eq!(
+            evaluator.evaluate_cached(&grew),
+            (Ok(Value::Bool(true)), false)
+        );
+        assert_eq!(
+            evaluator.evaluate_cached(&grew),
+            (Ok(Value::Bool(true)), true)
+        );
+
+        // A new pre-state invalidates results that read it
+        evaluator.set_old_state(BTreeMap::from([("supply".to_string(), Value::U64(150))]));
+        assert_eq!(
+            evaluator.evaluate_cached(&grew),
+            (Ok(Value::Bool(false)), false)
+        );
+    }
 
     #[test]
     fn test_value_type_detection() {
@@ -413,6 +1810,63 @@ mod tests {
         assert_eq!(result, Ok(Value::Bool(true)));
     }
 
+    #[test]
+    fn test_trace_marks_failing_comparison() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("balance".to_string(), Value::U64(500));
+        let evaluator = Evaluator::new(ctx);
+
+        let expr = Expression::BinaryOp {
+            left: Box::new(Expression::Var("balance".to_string())),
+            op: BinaryOp::Lt,
+            right: Box::new(Expression::Int(100)),
+        };
+        let (result, trace) = evaluator.evaluate_with_trace(&expr);
+        assert_eq!(result, Ok(Value::Bool(false)));
+
+        let failing = trace.first_failure().unwrap();
+        assert_eq!(failing.expr_display, "(balance < 100)");
+        assert_eq!(failing.result, Ok(Value::Bool(false)));
+        assert_eq!(
+            trace.explanation(),
+            Some(Evaluation {
+                expression: "(balance < 100)".to_string(),
+                value: Value::Bool(false),
+            })
+        );
+        assert_eq!(
+            trace.steps.first().map(|step| step.expr_display.as_str()),
+            Some("balance")
+        );
+        assert_eq!(trace.steps.last().unwrap().expr_display, FAILURE_MARKER);
+
+        // Tracing is scoped to the call
+        assert_eq!(evaluator.evaluate(&expr), Ok(Value::Bool(false)));
+        assert_eq!(evaluator.cache_size(), 1);
+    }
+
+    #[test]
+    fn test_mixed_width_comparison_widens_u64() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("balance".to_string(), Value::U64(u64::MAX));
+        ctx.set_state("cap".to_string(), Value::U128(u64::MAX as u128 + 1));
+        let evaluator = Evaluator::new(ctx);
+
+        let compare = |left: &str, op: BinaryOp, right: &str| Expression::BinaryOp {
+            left: Box::new(Expression::Var(left.to_string())),
+            op,
+            right: Box::new(Expression::Var(right.to_string())),
+        };
+        assert_eq!(
+            evaluator.evaluate(&compare("balance", BinaryOp::Lt, "cap")),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            evaluator.evaluate(&compare("cap", BinaryOp::Lte, "balance")),
+            Ok(Value::Bool(false))
+        );
+    }
+
     #[test]
     fn test_logical_short_circuit() {
         let ctx = ExecutionContext::new();
@@ -428,4 +1882,759 @@ mod tests {
         let result = evaluator.evaluate(&expr);
         assert_eq!(result, Ok(Value::Bool(false)));
     }
+
+    fn conditional(cond: bool) -> Expression {
+        Expression::Conditional {
+            cond: Box::new(Expression::Boolean(cond)),
+            then_expr: Box::new(Expression::Var("then_value".to_string())),
+            else_expr: Box::new(Expression::Var("else_value".to_string())),
+        }
+    }
+
+    #[test]
+    fn test_conditional_selects_branch() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("then_value".to_string(), Value::U64(1));
+        ctx.set_state("else_value".to_string(), Value::U64(2));
+        let evaluator = Evaluator::new(ctx);
+
+        assert_eq!(evaluator.evaluate(&conditional(true)), Ok(Value::U64(1)));
+        assert_eq!(evaluator.evaluate(&conditional(false)), Ok(Value::U64(2)));
+    }
+
+    #[test]
+    fn test_conditional_skips_unselected_branch() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("then_value".to_string(), Value::U64(1));
+        let evaluator = Evaluator::new(ctx);
+
+        // else_value is undefined but never evaluated
+        assert_eq!(evaluator.evaluate(&conditional(true)), Ok(Value::U64(1)));
+        assert_eq!(
+            evaluator.evaluate(&conditional(false)),
+            Err(undefined_variable("else_value"))
+        );
+    }
+
+    #[test]
+    fn test_cast_checks_range() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("big".to_string(), Value::U128(u64::MAX as u128 + 1));
+        ctx.set_state("small".to_string(), Value::U64(7));
+        let evaluator = Evaluator::new(ctx);
+
+        let narrow = Expression::Cast {
+            expr: Box::new(Expression::Var("big".to_string())),
+            target: Type::U64,
+        };
+        assert_eq!(
+            evaluator.evaluate(&narrow),
+            Err(EvaluationError::ConversionOverflow)
+        );
+
+        let widen = Expression::Cast {
+            expr: Box::new(Expression::Var("small".to_string())),
+            target: Type::U128,
+        };
+        assert_eq!(evaluator.evaluate(&widen), Ok(Value::U128(7)));
+    }
+
+    #[test]
+    fn test_context_from_json() {
+        let ctx = ExecutionContext::from_json("{\"balance\": 100}").unwrap();
+        assert_eq!(ctx.state_vars["balance"], Value::U64(100));
+    }
+
+    #[test]
+    fn test_context_json_round_trip() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("paused".to_string(), Value::Bool(false));
+        ctx.set_state("supply".to_string(), Value::U64(1_000));
+        ctx.set_state("delta".to_string(), Value::I64(-5));
+        ctx.set_state("owner".to_string(), Value::Address("0xabc".to_string()));
+        ctx.set_state(
+            "balances".to_string(),
+            Value::Array(vec![Value::U64(1), Value::U64(2)]),
+        );
+
+        let restored = ExecutionContext::from_json(&ctx.to_json()).unwrap();
+        assert_eq!(restored.state_vars, ctx.state_vars);
+    }
+
+    #[test]
+    fn test_context_json_lossy_types() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("small".to_string(), Value::U128(7));
+        ctx.set_state("large".to_string(), Value::U128(u128::MAX));
+        ctx.set_state("max".to_string(), Value::u256_max());
+        ctx.set_state("label".to_string(), Value::Str("vault".to_string()));
+
+        let restored = ExecutionContext::from_json(&ctx.to_json()).unwrap();
+        assert_eq!(restored.state_vars["small"], Value::U64(7));
+        assert_eq!(
+            restored.state_vars["large"],
+            Value::Address(u128::MAX.to_string())
+        );
+        assert_eq!(
+            restored.state_vars["max"],
+            Value::Address(Value::u256_max().to_string())
+        );
+        assert_eq!(
+            restored.state_vars["label"],
+            Value::Address("vault".to_string())
+        );
+    }
+
+    #[test]
+    fn test_context_json_round_trips_maps() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state(
+            "vault::deposits".to_string(),
+            Value::Map(BTreeMap::from([
+                ("alice".to_string(), Value::U64(10)),
+                (
+                    "bob".to_string(),
+                    Value::Map(BTreeMap::from([("locked".to_string(), Value::Bool(true))])),
+                ),
+            ])),
+        );
+
+        let json = ctx.to_json();
+        assert_eq!(
+            json,
+            r#"{"vault::deposits":{"alice":10,"bob":{"locked":true}}}"#
+        );
+        let restored = ExecutionContext::from_json(&json).unwrap();
+        assert_eq!(restored.state_vars, ctx.state_vars);
+    }
+
+    #[test]
+    fn test_context_from_json_rejects_unsupported() {
+        for json in ["{\"x\": null}", "{\"x\": {\"y\": null}}", "[1, 2]"] {
+            assert!(matches!(
+                ExecutionContext::from_json(json),
+                Err(EvaluationError::Custom(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_collect_state_collection() {
+        let mut program = ProgramModel::new(
+            "bank".to_string(),
+            "evm".to_string(),
+            "Bank.sol".to_string(),
+        );
+        program.add_state_var(crate::model::StateVar {
+            name: "balances".to_string(),
+            type_name: "u64[]".to_string(),
+            is_mutable: true,
+            visibility: None,
+            abilities: Default::default(),
+            depends_on: Vec::new(),
+        });
+        let mut ctx =
+            ExecutionContext::from_json("{\"balances\": [3, 1], \"owner\": \"0xa\"}").unwrap();
+        ctx.set_state(
+            "limits".to_string(),
+            Value::Map(BTreeMap::from([
+                ("b".to_string(), Value::U64(2)),
+                ("a".to_string(), Value::U64(1)),
+            ])),
+        );
+
+        assert_eq!(
+            collect_state_collection(&program, "balances", &ctx),
+            Ok(vec![Value::U64(3), Value::U64(1)])
+        );
+        assert_eq!(
+            collect_state_collection(&program, "limits", &ctx),
+            Ok(vec![Value::U64(1), Value::U64(2)])
+        );
+        assert_eq!(
+            collect_state_collection(&program, "owner", &ctx),
+            Err(EvaluationError::TypeError)
+        );
+        assert_eq!(
+            collect_state_collection(&program, "balance", &ctx),
+            Err(EvaluationError::UndefinedVariable {
+                name: "balance".to_string(),
+                suggestions: vec!["balances".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_keep_their_source() {
+        use std::error::Error;
+
+        let err = ExecutionContext::from_json("{\"x\": 1.5}").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "state 'x': unsupported non-integer number 1.5"
+        );
+        assert!(matches!(err, EvaluationError::ParseError { .. }));
+
+        // Walk the chain from the top-level error down to the integer parser
+        let err: crate::InvarError = err.into();
+        assert_eq!(
+            err.to_string(),
+            "Evaluation failed: state 'x': unsupported non-integer number 1.5"
+        );
+        let evaluation = err.source().unwrap();
+        let parse = evaluation.source().unwrap();
+        assert_eq!(parse.to_string(), "invalid digit found in string");
+        assert!(parse.source().is_none());
+
+        let err = ExecutionContext::from_json("{").err().unwrap();
+        assert!(err.to_string().starts_with("invalid state JSON: "));
+        assert!(err.source().is_some());
+
+        // Messages are unchanged by the derive
+        let undefined = EvaluationError::UndefinedVariable {
+            name: "balanc".to_string(),
+            suggestions: vec!["balance".to_string()],
+        };
+        assert_eq!(
+            undefined.to_string(),
+            "undefined variable 'balanc'; did you mean 'balance'?"
+        );
+        assert_eq!(
+            EvaluationError::ConversionOverflow.to_string(),
+            "conversion overflow"
+        );
+        assert!(EvaluationError::Overflow.source().is_none());
+    }
+
+    #[test]
+    fn test_function_call_results_are_cached() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        fn counted_sum(args: &[Value]) -> EvalResult<Value> {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            Ok(args[0].clone())
+        }
+
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("balances".to_string(), Value::U64(10));
+        ctx.register_function("counted_sum".to_string(), counted_sum);
+        let call = Expression::FunctionCall {
+            name: "counted_sum".to_string(),
+            args: vec![Expression::Var("balances".to_string())],
+        };
+        let check = |op| Expression::BinaryOp {
+            left: Box::new(call.clone()),
+            op,
+            right: Box::new(Expression::Int(0)),
+        };
+
+        let mut evaluator = Evaluator::new(ctx);
+        let results = evaluator.evaluate_batch(&[check(BinaryOp::Gt), check(BinaryOp::Gte)]);
+        assert_eq!(results, vec![Ok(Value::Bool(true)), Ok(Value::Bool(true))]);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+        assert_eq!(evaluator.cache_size(), 3);
+
+        evaluator.set_caching(false);
+        assert_eq!(evaluator.cache_size(), 0);
+        evaluator.evaluate(&call).unwrap();
+        evaluator.evaluate(&call).unwrap();
+        assert_eq!(CALLS.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_failing_assert_reports_message() {
+        let evaluator = Evaluator::new(ExecutionContext::new());
+        let assertion = |left, right| {
+            Expression::Assert(
+                Box::new(Expression::BinaryOp {
+                    left: Box::new(Expression::Int(left)),
+                    op: BinaryOp::Gt,
+                    right: Box::new(Expression::Int(right)),
+                }),
+                "zero must be positive".to_string(),
+            )
+        };
+
+        let err = evaluator.evaluate(&assertion(0, 1)).unwrap_err();
+        assert!(err.to_string().contains("zero must be positive"));
+        assert_eq!(evaluator.evaluate(&assertion(1, 0)), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_evaluate_cached_reuses_results_of_clean_variables() {
+        let positive = |var: Expression| Expression::BinaryOp {
+            left: Box::new(var),
+            op: BinaryOp::Gt,
+            right: Box::new(Expression::Int(0)),
+        };
+        let fee = positive(Expression::Var("fee".to_string()));
+        let balance = positive(Expression::LayerVar {
+            layer: "vault".to_string(),
+            var: "balance".to_string(),
+        });
+
+        let mut evaluator = Evaluator::new(ExecutionContext::new());
+        evaluator.set_state("fee".to_string(), Value::U64(1));
+        evaluator.set_state("balance".to_string(), Value::U64(5));
+        assert_eq!(
+            evaluator.evaluate_cached(&fee),
+            (Ok(Value::Bool(true)), false)
+        );
+        assert_eq!(
+            evaluator.evaluate_cached(&balance),
+            (Ok(Value::Bool(true)), false)
+        );
+
+        // Unchanged values leave results valid
+        evaluator.set_state("fee".to_string(), Value::U64(1));
+        evaluator.set_state("balance".to_string(), Value::U64(0));
+        assert_eq!(
+            evaluator.evaluate_cached(&fee),
+            (Ok(Value::Bool(true)), true)
+        );
+        // `vault::balance` falls back to `balance`, so it is invalidated too
+        assert_eq!(
+            evaluator.evaluate_cached(&balance),
+            (Ok(Value::Bool(false)), false)
+        );
+
+        evaluator.set_state("fee".to_string(), Value::U64(0));
+        assert_eq!(
+            evaluator.evaluate_cached(&fee),
+            (Ok(Value::Bool(false)), false)
+        );
+        assert_eq!(
+            evaluator.evaluate_cached(&balance),
+            (Ok(Value::Bool(false)), true)
+        );
+
+        evaluator.set_caching(false);
+        assert_eq!(
+            evaluator.evaluate_cached(&fee),
+            (Ok(Value::Bool(false)), false)
+        );
+    }
+
+    #[test]
+    fn test_deadline_against_advancing_block() {
+        // `deadline > now()`
+        let before_deadline = Expression::BinaryOp {
+            left: Box::new(Expression::Var("deadline".to_string())),
+            op: BinaryOp::Gt,
+            right: Box::new(Expression::FunctionCall {
+                name: "now".to_string(),
+                args: vec![],
+            }),
+        };
+
+        let mut evaluator = Evaluator::new(ExecutionContext::new());
+        evaluator.set_state("deadline".to_string(), Value::U64(1_030));
+        assert!(matches!(
+            evaluator.evaluate(&before_deadline),
+            Err(EvaluationError::Custom(msg)) if msg == "now() needs a block context"
+        ));
+
+        let mut block = BlockContext {
+            timestamp: 1_000,
+            number: 7,
+            chain_id: 1,
+        };
+        evaluator.set_block(block);
+        assert_eq!(
+            evaluator.evaluate_cached(&before_deadline),
+            (Ok(Value::Bool(true)), false)
+        );
+
+        let mut holds = vec![];
+        for _ in 0..4 {
+            block = block.advanced(1, 12);
+            evaluator.set_block(block);
+            let (result, reused) = evaluator.evaluate_cached(&before_deadline);
+            // Each new block invalidates results that call `now()`
+            assert!(!reused);
+            holds.push(result.unwrap());
+        }
+        assert_eq!(
+            holds,
+            vec![
+                Value::Bool(true),
+                Value::Bool(true),
+                Value::Bool(false),
+                Value::Bool(false)
+            ]
+        );
+        assert_eq!(block.number, 11);
+        assert_eq!(
+            evaluator.evaluate_cached(&before_deadline),
+            (Ok(Value::Bool(false)), true)
+        );
+
+        let call = |name: &str, args| Expression::FunctionCall {
+            name: name.to_string(),
+            args,
+        };
+        assert_eq!(
+            evaluator.evaluate(&call("block_number", vec![])),
+            Ok(Value::U64(11))
+        );
+        assert_eq!(
+            evaluator.evaluate(&call("chain_id", vec![])),
+            Ok(Value::U64(1))
+        );
+        assert!(matches!(
+            evaluator.evaluate(&call("now", vec![Expression::Int(1)])),
+            Err(EvaluationError::InvalidArgument(_))
+        ));
+        assert_eq!(
+            evaluator.evaluate(&call("later", vec![])),
+            Err(EvaluationError::UndefinedFunction("later".to_string()))
+        );
+    }
+
+    fn quantified(quantifier: Quantifier, body: Expression) -> Expression {
+        Expression::Quantified {
+            quantifier,
+            var: "x".to_string(),
+            collection: Box::new(Expression::Var("items".to_string())),
+            body: Box::new(body),
+        }
+    }
+
+    fn compare_x(op: BinaryOp, n: i128) -> Expression {
+        Expression::BinaryOp {
+            left: Box::new(Expression::Var("x".to_string())),
+            op,
+            right: Box::new(Expression::Int(n)),
+        }
+    }
+
+    fn items_evaluator(items: Vec<Value>) -> Evaluator {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("items".to_string(), Value::Array(items));
+        // A state variable named like the bound one is shadowed
+        ctx.set_state("x".to_string(), Value::U64(1000));
+        Evaluator::new(ctx)
+    }
+
+    #[test]
+    fn test_quantifiers() {
+        let evaluator = items_evaluator(vec![Value::U64(3), Value::U64(9), Value::U64(5)]);
+        let x = || Expression::Var("x".to_string());
+        let cases = [
+            (
+                Quantifier::ForAll,
+                compare_x(BinaryOp::Gt, 2),
+                Value::Bool(true),
+            ),
+            (
+                Quantifier::ForAll,
+                compare_x(BinaryOp::Gt, 3),
+                Value::Bool(false),
+            ),
+            (
+                Quantifier::Exists,
+                compare_x(BinaryOp::Eq, 9),
+                Value::Bool(true),
+            ),
+            (
+                Quantifier::Exists,
+                compare_x(BinaryOp::Gt, 9),
+                Value::Bool(false),
+            ),
+            (Quantifier::Sum, x(), Value::U64(17)),
+            (
+                Quantifier::Count,
+                compare_x(BinaryOp::Gte, 5),
+                Value::U64(2),
+            ),
+            (Quantifier::Min, x(), Value::U64(3)),
+            (Quantifier::Max, x(), Value::U64(9)),
+        ];
+        for (quantifier, body, expected) in cases {
+            let expr = quantified(quantifier, body);
+            assert_eq!(evaluator.evaluate(&expr), Ok(expected), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_quantifiers_over_empty_and_map_collections() {
+        let empty = items_evaluator(Vec::new());
+        let x = || Expression::Var("x".to_string());
+        assert_eq!(
+            empty.evaluate(&quantified(Quantifier::ForAll, Expression::Boolean(false))),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            empty.evaluate(&quantified(Quantifier::Exists, Expression::Boolean(true))),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(
+            empty.evaluate(&quantified(Quantifier::Sum, x())),
+            Ok(Value::U64(0))
+        );
+        assert!(empty.evaluate(&quantified(Quantifier::Min, x())).is_err());
+
+        let mut ctx = ExecutionContext::new();
+        let balances = BTreeMap::from([
+            ("alice".to_string(), Value::U128(7)),
+            ("bob".to_string(), Value::U128(11)),
+        ]);
+        ctx.set_state("items".to_string(), Value::Map(balances));
+        assert_eq!(
+            Evaluator::new(ctx).evaluate(&quantified(Quantifier::Sum, x())),
+            Ok(Value::U128(18))
+        );
+    }
+
+    #[test]
+    fn test_quantifiers_short_circuit() {
+        // `x` is a string for the second element, so comparing it fails
+        let evaluator = items_evaluator(vec![Value::U64(1), Value::Str("bad".to_string())]);
+        assert_eq!(
+            evaluator.evaluate(&quantified(Quantifier::ForAll, compare_x(BinaryOp::Gt, 1))),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(
+            evaluator.evaluate(&quantified(Quantifier::Exists, compare_x(BinaryOp::Gte, 1))),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            evaluator.evaluate(&quantified(Quantifier::Count, compare_x(BinaryOp::Gt, 0))),
+            Err(EvaluationError::TypeError)
+        );
+    }
+
+    #[test]
+    fn test_sum_quantifier_is_checked() {
+        let evaluator = items_evaluator(vec![Value::U64(u64::MAX), Value::U64(1)]);
+        assert_eq!(
+            evaluator.evaluate(&quantified(
+                Quantifier::Sum,
+                Expression::Var("x".to_string())
+            )),
+            Err(EvaluationError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_int_literal_bounds() {
+        let evaluate =
+            |val: i128| Evaluator::new(ExecutionContext::new()).evaluate(&Expression::Int(val));
+        let i64_min = i128::from(i64::MIN);
+        let u64_max = i128::from(u64::MAX);
+
+        assert_eq!(evaluate(i64_min), Ok(Value::I64(i64::MIN)));
+        assert_eq!(evaluate(i64_min + 1), Ok(Value::I64(i64::MIN + 1)));
+        assert_eq!(
+            evaluate(i64_min - 1),
+            Err(EvaluationError::ConversionOverflow)
+        );
+        assert_eq!(
+            evaluate(i128::MIN),
+            Err(EvaluationError::ConversionOverflow)
+        );
+        assert_eq!(evaluate(-1), Ok(Value::I64(-1)));
+        assert_eq!(evaluate(0), Ok(Value::U64(0)));
+        assert_eq!(evaluate(u64_max - 1), Ok(Value::U64(u64::MAX - 1)));
+        assert_eq!(evaluate(u64_max), Ok(Value::U64(u64::MAX)));
+        assert_eq!(
+            evaluate(u64_max + 1),
+            Ok(Value::U128(u128::from(u64::MAX) + 1))
+        );
+        // The largest literal; `u128::MAX` itself is rejected by the parser
+        assert_eq!(evaluate(i128::MAX), Ok(Value::U128(i128::MAX as u128)));
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_int_literal_matches_inferred_type(val in proptest::prelude::any::<i128>()) {
+            let value = Evaluator::new(ExecutionContext::new()).evaluate(&Expression::Int(val));
+            match Type::of_int_literal(val) {
+                Some(ty) => {
+                    let value = value.unwrap();
+                    proptest::prop_assert_eq!(value.get_type(), ty);
+                    proptest::prop_assert_eq!(value.to_string(), val.to_string());
+                }
+                None => proptest::prop_assert_eq!(value, Err(EvaluationError::ConversionOverflow)),
+            }
+        }
+    }
+
+    fn var(name: &str) -> Box<Expression> {
+        Box::new(Expression::Var(name.to_string()))
+    }
+
+    fn call(name: &str, args: Vec<Expression>) -> Box<Expression> {
+        Box::new(Expression::FunctionCall {
+            name: name.to_string(),
+            args,
+        })
+    }
+
+    fn first(args: &[Value]) -> EvalResult<Value> {
+        Ok(args[0].clone())
+    }
+
+    #[test]
+    fn test_trace_records_evaluation_order() {
+        let mut ctx = ExecutionContext::new();
+        for name in ["a", "b", "c", "d", "paymaster::deposit"] {
+            ctx.set_state(name.to_string(), Value::U64(1));
+        }
+        ctx.register_function("f".to_string(), first);
+        ctx.register_function("g".to_string(), first);
+        let evaluator = Evaluator::new(ctx);
+
+        // f(a, g(b)) >= c && (paymaster::deposit == d || a > b)
+        let expr = Expression::Logical {
+            left: Box::new(Expression::BinaryOp {
+                left: call("f", vec![*var("a"), *call("g", vec![*var("b")])]),
+                op: BinaryOp::Gte,
+                right: var("c"),
+            }),
+            op: LogicalOp::And,
+            right: Box::new(Expression::Logical {
+                left: Box::new(Expression::BinaryOp {
+                    left: Box::new(Expression::LayerVar {
+                        layer: "paymaster".to_string(),
+                        var: "deposit".to_string(),
+                    }),
+                    op: BinaryOp::Eq,
+                    right: var("d"),
+                }),
+                op: LogicalOp::Or,
+                right: Box::new(Expression::BinaryOp {
+                    left: var("a"),
+                    op: BinaryOp::Gt,
+                    right: var("b"),
+                }),
+            }),
+        };
+
+        let (result, trace) = evaluator.evaluate_with_trace(&expr);
+        assert_eq!(result, Ok(Value::Bool(true)));
+        let read = |name: &str| EvalAccess::Read(name.to_string());
+        let called = |name: &str| EvalAccess::Call(name.to_string());
+        // `a > b` is never read: the `||` was decided by its left side
+        assert_eq!(
+            trace.accesses,
+            vec![
+                read("a"),
+                read("b"),
+                called("g"),
+                called("f"),
+                read("c"),
+                read("paymaster::deposit"),
+                read("d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_false_and_never_evaluates_right_side() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        fn counted(args: &[Value]) -> EvalResult<Value> {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            Ok(Value::Bool(args.is_empty()))
+        }
+
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("paused".to_string(), Value::Bool(true));
+        ctx.register_function("counted".to_string(), counted);
+        let evaluator = Evaluator::new(ctx);
+
+        let expr = |op| Expression::Logical {
+            left: Box::new(Expression::Not(var("paused"))),
+            op,
+            right: call("counted", vec![*var("balance")]),
+        };
+
+        let (result, trace) = evaluator.evaluate_with_trace(&expr(LogicalOp::And));
+        assert_eq!(result, Ok(Value::Bool(false)));
+        assert_eq!(trace.accesses, vec![EvalAccess::Read("paused".to_string())]);
+        assert_eq!(
+            evaluator.evaluate(&expr(LogicalOp::And)),
+            Ok(Value::Bool(false))
+        );
+        assert_eq!(CALLS.load(Ordering::SeqCst), 0);
+
+        // `||` must evaluate the right side; the undefined argument stops the call
+        assert_eq!(
+            evaluator.evaluate(&expr(LogicalOp::Or)),
+            Err(undefined_variable("balance"))
+        );
+        assert_eq!(CALLS.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_first_error_in_source_order_wins() {
+        let mut ctx = ExecutionContext::new();
+        ctx.register_function("f".to_string(), first);
+        let evaluator = Evaluator::new(ctx);
+        let undefined = |name: &str| Err(undefined_variable(name));
+
+        let compare = Expression::BinaryOp {
+            left: var("left"),
+            op: BinaryOp::Lt,
+            right: var("right"),
+        };
+        assert_eq!(evaluator.evaluate(&compare), undefined("left"));
+
+        let args = call("f", vec![*var("x"), *var("y")]);
+        assert_eq!(evaluator.evaluate(&args), undefined("x"));
+
+        let unknown = call("missing", vec![*var("x")]);
+        assert_eq!(
+            evaluator.evaluate(&unknown),
+            Err(EvaluationError::UndefinedFunction("missing".to_string()))
+        );
+
+        let relation = Expression::CrossPhaseRelation {
+            phase1: "validation".to_string(),
+            expr1: var("before"),
+            phase2: "execution".to_string(),
+            expr2: var("after"),
+            op: BinaryOp::Eq,
+        };
+        assert_eq!(evaluator.evaluate(&relation), undefined("before"));
+    }
+
+    #[test]
+    fn test_undefined_variable_suggestions() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("total_supply".to_string(), Value::U64(1));
+        ctx.set_state("paymaster::deposit".to_string(), Value::U64(1));
+        let evaluator = Evaluator::new(ctx);
+
+        let err = evaluator.evaluate(&var("totl_supply")).unwrap_err();
+        assert_eq!(
+            err,
+            EvaluationError::UndefinedVariable {
+                name: "totl_supply".to_string(),
+                suggestions: vec!["total_supply".to_string()],
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "undefined variable 'totl_supply'; did you mean 'total_supply'?"
+        );
+
+        let layer_var = Expression::LayerVar {
+            layer: "paymaster".to_string(),
+            var: "deposti".to_string(),
+        };
+        assert!(evaluator
+            .evaluate(&layer_var)
+            .unwrap_err()
+            .to_string()
+            .ends_with("did you mean 'paymaster::deposit'?"));
+
+        assert_eq!(
+            evaluator.evaluate(&var("zzzzzzzz")),
+            Err(undefined_variable("zzzzzzzz"))
+        );
+    }
 }
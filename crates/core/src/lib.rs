@@ -8,28 +8,58 @@
 
 pub mod account_abstraction;
 pub mod attack_patterns;
+pub mod canonical;
+pub mod cross_chain;
 pub mod error;
 pub mod evaluator;
 pub mod model;
+pub mod provenance;
+pub mod regeneration;
+pub mod registry;
 pub mod security_validator;
+pub mod suggest;
 pub mod threat_model;
 pub mod traits;
 pub mod type_checker;
 pub mod types;
+pub mod visitor;
 
 pub use account_abstraction::{
     AAContext, AALayer, AccountState, CrossLayerCheckResult, EntryPointState, PaymasterState,
     UserOpData,
 };
 pub use attack_patterns::AttackPatternDB;
+pub use cross_chain::{CrossChainContext, CrossChainLink};
 pub use error::{InvarError, Result};
-pub use evaluator::{EvalResult, EvaluationError, Evaluator, ExecutionContext, Value};
-pub use model::{FunctionModel, Invariant, ProgramModel, StateVar};
-pub use security_validator::{IssueSeverity, SecurityIssue, SecurityReport, SecurityValidator};
+pub use evaluator::{
+    BlockContext, ErrorSource, EvalAccess, EvalResult, EvalStep, EvalTrace, Evaluation,
+    EvaluationError, Evaluator, ExecutionContext, Value, ARITHMETIC_BUILTINS, BLOCK_BUILTINS,
+    FAILURE_MARKER, OLD_BUILTIN, U256_BYTES,
+};
+pub use model::{
+    ArithmeticOp, AuthorityAccount, CpiCall, EnforcementLevel, FunctionModel, Invariant,
+    InvariantExample, InvariantFilter, InvariantSelection, InvariantStats, OverflowProtection,
+    PdaPattern, PdaSeedSource, ProgramModel, SimulationReport, StateVar, Uncertainty,
+    UncheckedArithmetic, ViolationTrace, CONTRACT_INVARIANT_CATEGORY, NO_MUTATION_AFTER_CPI,
+    PROGRAM_MODEL_SCHEMA_VERSION, SKIPPED_DISABLED, SKIPPED_FILTERED, SUPPORTED_CHAINS,
+};
+pub use provenance::{ProvenanceHeader, PROVENANCE_BEGIN, PROVENANCE_END};
+pub use regeneration::{sort_for_generation, CodeHunk, GenerationDiff};
+pub use registry::{detect_chain, ChainAnalyzerRegistry};
+#[cfg(feature = "git-blame")]
+pub use security_validator::enrich_with_git_blame;
+pub use security_validator::{
+    IssueSeverity, Location, RiskPolicy, SecurityIssue, SecurityReport, SecurityValidator,
+    SourceMap,
+};
 pub use threat_model::{
     DSLSandbox, InjectionVerifier, SimulationIsolation, StrictModeAnalyzer, TamperDetector,
     ThreatModelConfig, ThreatModelError, ThreatResult,
 };
 pub use traits::{ChainAnalyzer, CodeGenerator, Simulator};
-pub use type_checker::TypeChecker;
-pub use types::{Type, TypeError, TypeResult, TypedExpr, TypedValue};
+pub use type_checker::{
+    CheckReport, Diagnostic, DiagnosticLevel, TypeChecker, TypeCheckerOptions,
+    DEFAULT_MAX_COMPLEXITY,
+};
+pub use types::{Type, TypeError, TypeResult, TypedExpr, TypedValue, Widening};
+pub use visitor::{fold_children, walk_expression, ExpressionFolder, ExpressionVisitor};
@@ -1,7 +1,77 @@
 //! Core domain models for invariant analysis.
 
+use crate::evaluator::{Evaluation, EvaluationError, Evaluator, ExecutionContext, Value};
+use crate::types::Type;
+use crate::visitor::{walk_expression, ExpressionVisitor};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet};
+use std::convert::Infallible;
+use std::path::Path;
+
+/// Severity of invariants whose violation can cause total loss of funds.
+pub const SEVERITY_CRITICAL: &str = "critical";
+/// Severity of invariants whose violation can cause significant fund loss.
+pub const SEVERITY_HIGH: &str = "high";
+/// Default severity.
+pub const SEVERITY_MEDIUM: &str = "medium";
+/// Severity of best-practice invariants.
+pub const SEVERITY_LOW: &str = "low";
+
+/// Categories whose invariants must be enforced in every build.
+pub const SECURITY_CRITICAL_CATEGORIES: [&str; 3] = ["core", "bridge", "access-control"];
+
+/// Category of class-level invariants declared on a contract or module,
+/// which generators check after every public mutating function.
+pub const CONTRACT_INVARIANT_CATEGORY: &str = "contract";
+
+/// Chains invariants can be generated for. Listed in an invariant's `layers`,
+/// they restrict it to those chains.
+pub const SUPPORTED_CHAINS: [&str; 3] = ["solana", "evm", "move"];
+
+/// Template `no_mutation_after_cpi("<function>")`: the named Solana handler
+/// writes no state after its first cross-program invocation.
+pub const NO_MUTATION_AFTER_CPI: &str = "no_mutation_after_cpi";
+
+/// Where generated code enforces an invariant.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnforcementLevel {
+    /// Checked in every build.
+    #[default]
+    Always,
+    /// Checked only in debug builds, tests and local validators.
+    DebugOnly,
+    /// Never generated; only checked by the simulator.
+    SimulationOnly,
+}
+
+impl EnforcementLevel {
+    /// Parse a level as written in TOML and DSL annotations.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "always" => Some(Self::Always),
+            "debug_only" => Some(Self::DebugOnly),
+            "simulation_only" => Some(Self::SimulationOnly),
+            _ => None,
+        }
+    }
+
+    /// The level as written in TOML and DSL annotations.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::DebugOnly => "debug_only",
+            Self::SimulationOnly => "simulation_only",
+        }
+    }
+}
+
+impl std::fmt::Display for EnforcementLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
 
 /// A compiled invariant expression with metadata.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,16 +91,351 @@ pub struct Invariant {
     /// Category: "core", "defi", "bridge", "governance", "account-abstraction", etc.
     pub category: String,
 
+    /// Free-form labels for filtering across categories ("defi", "token", ...).
+    #[serde(default)]
+    pub tags: Vec<String>,
+
     /// Whether this invariant should always hold.
     pub is_always_true: bool,
 
     /// Layer scopes for cross-layer analysis (e.g., ["bundler", "account", "paymaster"]).
-    /// If empty, applies to all layers.
+    /// If empty, applies to all layers. Entries naming one of the
+    /// [`SUPPORTED_CHAINS`] limit the invariant to those chains.
     pub layers: Vec<String>,
 
     /// Execution phases (e.g., ["validation", "execution", "settlement"]).
     /// For AA invariants that must hold at specific phases. Empty means all phases.
     pub phases: Vec<String>,
+
+    /// Where generated code enforces the invariant.
+    #[serde(default)]
+    pub enforcement: EnforcementLevel,
+
+    /// Whether commands check the invariant. Disabled invariants are
+    /// reported as skipped rather than dropped.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// Concrete states documenting when the invariant holds, which
+    /// `invar check --run-examples` evaluates.
+    #[serde(default)]
+    pub examples: Vec<InvariantExample>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// A concrete state and whether an invariant holds in it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InvariantExample {
+    /// State variable values as JSON literals (`"100"`, `"true"`, `"[1, 2]"`).
+    pub inputs: BTreeMap<String, String>,
+    /// Whether the invariant holds for `inputs`.
+    pub expected_result: bool,
+    /// What the example illustrates.
+    pub description: String,
+}
+
+impl InvariantExample {
+    /// Execution context with each input as a state variable.
+    ///
+    /// Inputs are read as JSON values; inputs that are not valid JSON, such
+    /// as addresses, are strings.
+    pub fn context(&self) -> Result<ExecutionContext, EvaluationError> {
+        let state: serde_json::Map<String, serde_json::Value> = self
+            .inputs
+            .iter()
+            .map(|(name, input)| {
+                let value = serde_json::from_str(input)
+                    .unwrap_or_else(|_| serde_json::Value::String(input.clone()));
+                (name.clone(), value)
+            })
+            .collect();
+        ExecutionContext::from_json(&serde_json::Value::Object(state).to_string())
+    }
+
+    /// Whether `invariant` holds for the example's inputs.
+    pub fn evaluate(&self, invariant: &Invariant) -> Result<bool, EvaluationError> {
+        Evaluator::new(self.context()?)
+            .evaluate(&invariant.expression)?
+            .to_bool()
+    }
+}
+
+impl Invariant {
+    /// Create an invariant with default metadata: medium severity, "general"
+    /// category, always true, enabled, and applying to all layers and phases.
+    pub fn new(name: &str, expression: Expression) -> Self {
+        Self {
+            name: name.to_string(),
+            description: None,
+            expression,
+            severity: SEVERITY_MEDIUM.to_string(),
+            category: "general".to_string(),
+            tags: Vec::new(),
+            is_always_true: true,
+            layers: Vec::new(),
+            phases: Vec::new(),
+            enforcement: EnforcementLevel::Always,
+            enabled: true,
+            examples: Vec::new(),
+        }
+    }
+
+    /// Set the description.
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Set the severity (see the `SEVERITY_*` constants).
+    pub fn with_severity(mut self, severity: &str) -> Self {
+        self.severity = severity.to_string();
+        self
+    }
+
+    /// Set the category.
+    pub fn with_category(mut self, category: &str) -> Self {
+        self.category = category.to_string();
+        self
+    }
+
+    /// Set the tags.
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Restrict the invariant to the given layers.
+    pub fn for_layers(mut self, layers: Vec<String>) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// Restrict the invariant to the given execution phases.
+    pub fn for_phases(mut self, phases: Vec<String>) -> Self {
+        self.phases = phases;
+        self
+    }
+
+    /// Set the enforcement level.
+    pub fn with_enforcement(mut self, enforcement: EnforcementLevel) -> Self {
+        self.enforcement = enforcement;
+        self
+    }
+
+    /// Enable or disable the invariant.
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Set the examples.
+    pub fn with_examples(mut self, examples: Vec<InvariantExample>) -> Self {
+        self.examples = examples;
+        self
+    }
+
+    /// Reject enforcement below `Always` for invariants in a
+    /// [`SECURITY_CRITICAL_CATEGORIES`] category.
+    pub fn check_enforcement(&self) -> crate::Result<()> {
+        if self.enforcement != EnforcementLevel::Always
+            && SECURITY_CRITICAL_CATEGORIES.contains(&self.category.as_str())
+        {
+            return Err(crate::InvarError::invalid_invariant(format!(
+                "{} is in security-critical category '{}' and cannot be enforced as {}",
+                self.name, self.category, self.enforcement
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reject expressions that code generators cannot emit yet, currently
+    /// [`Expression::Quantified`], [`Expression::Aggregate`] and `old()`,
+    /// whose pre-transaction values generated checks do not capture.
+    /// Simulation-only invariants generate no code and always pass.
+    pub fn check_generatable(&self) -> crate::Result<()> {
+        if self.enforcement == EnforcementLevel::SimulationOnly {
+            return Ok(());
+        }
+        if self.expression.contains_quantifier() {
+            return Err(crate::InvarError::unsupported(format!(
+                "{} uses a quantifier, which has no generated check yet",
+                self.name
+            )));
+        }
+        if self.expression.contains_aggregate() {
+            return Err(crate::InvarError::unsupported(format!(
+                "{} uses a filtered aggregation, which has no generated check yet",
+                self.name
+            )));
+        }
+        if self.referenced_functions().contains(crate::OLD_BUILTIN) {
+            return Err(crate::InvarError::unsupported(format!(
+                "{} uses old(), which has no generated check yet; enforce it as simulation_only",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether the invariant applies to `chain`.
+    ///
+    /// True when `layers` names no chain at all, so invariants scoped only to
+    /// AA layers or functions stay generic; otherwise `chain` must be listed.
+    pub fn is_compatible_with_chain(&self, chain: &str) -> bool {
+        let restricted = self
+            .layers
+            .iter()
+            .any(|layer| SUPPORTED_CHAINS.contains(&layer.as_str()));
+        !restricted || self.layers.iter().any(|layer| layer == chain)
+    }
+
+    /// Names of all functions the expression calls.
+    pub fn referenced_functions(&self) -> BTreeSet<String> {
+        self.expression.functions_called()
+    }
+
+    /// Functions the expression calls that neither `program` nor
+    /// `evaluator_fns` (builtins and registered functions) defines.
+    pub fn validate_functions(
+        &self,
+        program: &ProgramModel,
+        evaluator_fns: &BTreeSet<String>,
+    ) -> Vec<String> {
+        self.referenced_functions()
+            .into_iter()
+            .filter(|name| !program.functions.contains_key(name) && !evaluator_fns.contains(name))
+            .collect()
+    }
+
+    /// The expression as the body of an Anchor `#[account(constraint = ...)]`,
+    /// such as `vault.balance >= 0` for `vault::balance >= 0`.
+    ///
+    /// Only a single comparison of an account field with an integer literal
+    /// is representable inline; anything else is `None`.
+    pub fn for_anchor_constraint(&self) -> Option<String> {
+        match &self.expression {
+            Expression::BinaryOp { left, op, right } => match (left.as_ref(), right.as_ref()) {
+                (Expression::LayerVar { layer, var }, Expression::Int(value)) => {
+                    Some(format!("{}.{} {} {}", layer, var, op, value))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Stable identity of the invariant's semantics, as lowercase hex SHA-256.
+    ///
+    /// Covers the expression tree, severity, layers and phases (in that order,
+    /// layers and phases sorted). The name, description, category and tags
+    /// are not included, and source formatting has no effect since the expression is
+    /// hashed by its [`canonical_bytes`](Expression::canonical_bytes).
+    pub fn content_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let mut layers = self.layers.clone();
+        layers.sort();
+        let mut phases = self.phases.clone();
+        phases.sort();
+
+        // The canonical encoding is self-delimiting
+        let mut hasher = Sha256::new();
+        hasher.update(self.expression.canonical_bytes());
+        for part in [self.severity.clone(), layers.join(","), phases.join(",")] {
+            hasher.update(part.as_bytes());
+            hasher.update([0u8]);
+        }
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+/// Skip reason of invariants whose [`enabled`](Invariant::enabled) flag is unset.
+pub const SKIPPED_DISABLED: &str = "disabled";
+
+/// Skip reason of invariants left out by an [`InvariantFilter`].
+pub const SKIPPED_FILTERED: &str = "filtered";
+
+/// Tag and name filters choosing the invariants a command works on.
+///
+/// Disabled invariants are never selected. An invariant is selected when it
+/// carries one of `only_tags` (or that list is empty), none of `skip_tags`,
+/// and is named in `only_invariants` (or that list is empty).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InvariantFilter {
+    /// Tags of which a selected invariant must carry at least one.
+    pub only_tags: Vec<String>,
+
+    /// Tags a selected invariant must not carry.
+    pub skip_tags: Vec<String>,
+
+    /// Names of the invariants to select.
+    pub only_invariants: Vec<String>,
+}
+
+/// Invariants chosen by an [`InvariantFilter`], and why the others were not.
+#[derive(Debug, Clone, Default)]
+pub struct InvariantSelection {
+    /// Selected invariants, in their original order.
+    pub selected: Vec<Invariant>,
+
+    /// Skip reason ([`SKIPPED_DISABLED`] or [`SKIPPED_FILTERED`]) of every
+    /// other invariant, keyed by name.
+    pub skipped: BTreeMap<String, String>,
+}
+
+impl InvariantFilter {
+    /// Why `invariant` is left out, or `None` when it is selected.
+    pub fn skip_reason(&self, invariant: &Invariant) -> Option<&'static str> {
+        let tagged = |tags: &[String]| invariant.tags.iter().any(|tag| tags.contains(tag));
+        if !invariant.enabled {
+            Some(SKIPPED_DISABLED)
+        } else if (!self.only_tags.is_empty() && !tagged(&self.only_tags))
+            || tagged(&self.skip_tags)
+            || (!self.only_invariants.is_empty() && !self.only_invariants.contains(&invariant.name))
+        {
+            Some(SKIPPED_FILTERED)
+        } else {
+            None
+        }
+    }
+
+    /// Split `invariants` into the selected and the skipped.
+    ///
+    /// Fails if `only_invariants` names an invariant that is not in the list,
+    /// so a typo cannot select nothing.
+    pub fn select(&self, invariants: Vec<Invariant>) -> crate::Result<InvariantSelection> {
+        let names: Vec<String> = invariants.iter().map(|inv| inv.name.clone()).collect();
+        if let Some(unknown) = self
+            .only_invariants
+            .iter()
+            .find(|name| !names.contains(name))
+        {
+            return Err(crate::InvarError::undefined_identifier(format!(
+                "{}{}",
+                unknown,
+                crate::suggest::format_suggestions(&crate::suggest::did_you_mean(unknown, &names))
+            )));
+        }
+
+        let mut selection = InvariantSelection::default();
+        for invariant in invariants {
+            match self.skip_reason(&invariant) {
+                Some(reason) => {
+                    selection.skipped.insert(invariant.name, reason.to_string());
+                }
+                None => selection.selected.push(invariant),
+            }
+        }
+        Ok(selection)
+    }
 }
 
 /// An expression tree representing invariant conditions.
@@ -89,6 +494,9 @@ pub enum Expression {
     /// Integer constant.
     Int(i128),
 
+    /// String literal.
+    String(String),
+
     /// Comparison: left op right.
     BinaryOp {
         /// Left operand.
@@ -122,6 +530,68 @@ pub enum Expression {
 
     /// Tuple of expressions.
     Tuple(Vec<Expression>),
+
+    /// Conditional: if cond then then_expr else else_expr.
+    Conditional {
+        /// Boolean condition.
+        cond: Box<Expression>,
+        /// Value when the condition holds.
+        then_expr: Box<Expression>,
+        /// Value when the condition does not hold.
+        else_expr: Box<Expression>,
+    },
+
+    /// Explicit numeric cast: `expr as target`.
+    Cast {
+        /// Expression being converted.
+        expr: Box<Expression>,
+        /// Target type.
+        target: Type,
+    },
+
+    /// Mapping lookup: `map[key]`.
+    MapAccess {
+        /// Expression evaluating to the mapping.
+        map: Box<Expression>,
+        /// Key to look up.
+        key: Box<Expression>,
+    },
+
+    /// Assertion reporting a message when its constraint is false:
+    /// `assert(constraint, "message")`.
+    Assert(Box<Expression>, String),
+
+    /// Quantifier over a collection: `sum(x in deposits: x)`.
+    Quantified {
+        /// How the body's per-element values are combined.
+        quantifier: Quantifier,
+        /// Name bound to each element within `body`.
+        var: String,
+        /// Array, or map whose values are iterated.
+        collection: Box<Expression>,
+        /// Expression evaluated once per element.
+        body: Box<Expression>,
+    },
+
+    /// Arithmetic negation of a signed value: `-expr`. Logical negation is
+    /// [`Not`](Expression::Not).
+    Negate(Box<Expression>),
+
+    /// Aggregation over the elements passing a filter:
+    /// `sum(d for d in deposits if d > 0)`.
+    Aggregate {
+        /// How the selected elements are combined.
+        op: AggregateOp,
+        /// Name bound to each element within `body` and `filter`.
+        binder: String,
+        /// Array, or map whose values are iterated.
+        collection: Box<Expression>,
+        /// Expression evaluated once per selected element.
+        body: Box<Expression>,
+        /// Elements for which this is false are skipped; all are selected
+        /// when absent.
+        filter: Option<Box<Expression>>,
+    },
 }
 
 impl std::fmt::Display for Expression {
@@ -146,6 +616,7 @@ impl std::fmt::Display for Expression {
                 write!(f, "({}[{}] {} {}[{}])", expr1, phase1, op, expr2, phase2)
             }
             Self::Int(i) => write!(f, "{}", i),
+            Self::String(s) => write!(f, "\"{}\"", s),
             Self::BinaryOp { left, op, right } => {
                 write!(f, "({} {} {})", left, op, right)
             }
@@ -173,10 +644,176 @@ impl std::fmt::Display for Expression {
                 }
                 write!(f, ")")
             }
+            Self::Conditional {
+                cond,
+                then_expr,
+                else_expr,
+            } => write!(f, "if {} then {} else {}", cond, then_expr, else_expr),
+            Self::Cast { expr, target } => write!(f, "({} as {})", expr, target),
+            Self::MapAccess { map, key } => write!(f, "{}[{}]", map, key),
+            Self::Assert(constraint, message) => {
+                write!(f, "assert({}, \"{}\")", constraint, message)
+            }
+            Self::Quantified {
+                quantifier,
+                var,
+                collection,
+                body,
+            } => write!(f, "{}({} in {}: {})", quantifier, var, collection, body),
+            Self::Negate(e) => write!(f, "-{}", e),
+            Self::Aggregate {
+                op,
+                binder,
+                collection,
+                body,
+                filter,
+            } => {
+                write!(f, "{}({} for {} in {}", op, body, binder, collection)?;
+                if let Some(filter) = filter {
+                    write!(f, " if {}", filter)?;
+                }
+                write!(f, ")")
+            }
         }
     }
 }
 
+/// Number of nodes in `expr`, counting every variant as one.
+pub fn complexity(expr: &Expression) -> usize {
+    let mut size = Size::default();
+    let Ok(()) = size.visit_expression(expr);
+    size.nodes
+}
+
+/// Nodes on the longest path from `expr` to a leaf; a leaf has depth 1.
+pub fn depth(expr: &Expression) -> usize {
+    let mut size = Size::default();
+    let Ok(()) = size.visit_expression(expr);
+    size.max_depth
+}
+
+/// Spaces per nesting level used by [`Expression::display_pretty`].
+pub const PRETTY_INDENT: usize = 2;
+
+/// Calls with more arguments than this are listed one argument per line.
+const PRETTY_INLINE_ARGS: usize = 3;
+
+/// Comparisons with more nodes than this break before the operator.
+const PRETTY_INLINE_COMPLEXITY: usize = 3;
+
+/// Render `expr` across several lines, indenting each nesting level by
+/// `indent` spaces.
+///
+/// Logical operators and larger comparisons start a new line at the
+/// operator, and long calls list one argument per line with a trailing
+/// comma. A variable bounded on both sides is written with the range
+/// shorthand, `(x in low..=high)` or `(x in low..high)`. Everything else is written as by `Display`, so the output parses
+/// back to the same expression.
+pub fn pretty_print(expr: &Expression, indent: usize) -> String {
+    let mut out = String::new();
+    write_pretty(expr, indent, 0, &mut out);
+    out
+}
+
+fn write_pretty(expr: &Expression, indent: usize, level: usize, out: &mut String) {
+    let pad = |level: usize| " ".repeat(indent * level);
+    let mut split = |left: &Expression, op: &dyn std::fmt::Display, right: &Expression| {
+        out.push('(');
+        write_pretty(left, indent, level + 1, out);
+        out.push_str(&format!("\n{}{} ", pad(level + 1), op));
+        write_pretty(right, indent, level + 1, out);
+        out.push(')');
+    };
+    match expr {
+        Expression::Logical { left, op, right } => match range_sugar(expr) {
+            Some(range) => out.push_str(&range),
+            None => split(left, op, right),
+        },
+        Expression::BinaryOp { left, op, right } if complexity(expr) > PRETTY_INLINE_COMPLEXITY => {
+            split(left, op, right)
+        }
+        Expression::FunctionCall { name, args } if args.len() > PRETTY_INLINE_ARGS => {
+            out.push_str(&format!("{}(\n", name));
+            for arg in args {
+                out.push_str(&pad(level + 1));
+                write_pretty(arg, indent, level + 1, out);
+                out.push_str(",\n");
+            }
+            out.push_str(&format!("{})", pad(level)));
+        }
+        _ => out.push_str(&expr.to_string()),
+    }
+}
+
+/// `(x in low..=high)` for `(x >= low) && (x <= high)`, and `..` for an
+/// exclusive upper bound, when both comparisons are on the same variable.
+fn range_sugar(expr: &Expression) -> Option<String> {
+    let Expression::Logical {
+        left,
+        op: LogicalOp::And,
+        right,
+    } = expr
+    else {
+        return None;
+    };
+    let (
+        Expression::BinaryOp {
+            left: subject,
+            op: BinaryOp::Gte,
+            right: low,
+        },
+        Expression::BinaryOp {
+            left: upper_subject,
+            op: upper,
+            right: high,
+        },
+    ) = (left.as_ref(), right.as_ref())
+    else {
+        return None;
+    };
+    let range = match upper {
+        BinaryOp::Lte => "..=",
+        BinaryOp::Lt => "..",
+        _ => return None,
+    };
+    let variable = matches!(
+        subject.as_ref(),
+        Expression::Var(_) | Expression::LayerVar { .. }
+    );
+    // Conditionals display unparenthesized and would swallow the range
+    let bounded = |bound: &Expression| !matches!(bound, Expression::Conditional { .. });
+    (variable && subject == upper_subject && bounded(low) && bounded(high))
+        .then(|| format!("({} in {}{}{})", subject, low, range, high))
+}
+
+impl Expression {
+    /// Multi-line rendering with [`PRETTY_INDENT`]; see [`pretty_print`].
+    pub fn display_pretty(&self) -> String {
+        pretty_print(self, PRETTY_INDENT)
+    }
+}
+
+/// Counts nodes and tracks nesting while walking an expression.
+#[derive(Default)]
+struct Size {
+    nodes: usize,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl ExpressionVisitor for Size {
+    type Error = Infallible;
+
+    fn visit_expression(&mut self, expr: &Expression) -> Result<(), Infallible> {
+        self.nodes += 1;
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+        let Ok(()) = walk_expression(self, expr);
+        self.depth -= 1;
+        Ok(())
+    }
+}
+
 /// Binary operators for expressions.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum BinaryOp {
@@ -225,8 +862,90 @@ impl std::fmt::Display for LogicalOp {
     }
 }
 
+/// How an [`Expression::Quantified`] combines its body over a collection.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Quantifier {
+    /// The body holds for every element; stops at the first `false`.
+    ForAll,
+    /// The body holds for some element; stops at the first `true`.
+    Exists,
+    /// Checked sum of the body's values.
+    Sum,
+    /// Number of elements for which the body holds.
+    Count,
+    /// Smallest value of the body.
+    Min,
+    /// Largest value of the body.
+    Max,
+}
+
+impl Quantifier {
+    /// Parse a DSL quantifier keyword (`forall`, `exists`, `sum`, `count`,
+    /// `min`, `max`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "forall" => Some(Self::ForAll),
+            "exists" => Some(Self::Exists),
+            "sum" => Some(Self::Sum),
+            "count" => Some(Self::Count),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Quantifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ForAll => write!(f, "forall"),
+            Self::Exists => write!(f, "exists"),
+            Self::Sum => write!(f, "sum"),
+            Self::Count => write!(f, "count"),
+            Self::Min => write!(f, "min"),
+            Self::Max => write!(f, "max"),
+        }
+    }
+}
+
+/// How an [`Expression::Aggregate`] combines the selected elements.
+///
+/// Over an empty selection `sum` and `count` are zero, while `max` has no
+/// value and fails to evaluate.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AggregateOp {
+    /// Checked sum of the body's values.
+    Sum,
+    /// Number of selected elements.
+    Count,
+    /// Largest value of the body.
+    Max,
+}
+
+impl AggregateOp {
+    /// Parse a DSL aggregation keyword (`sum`, `count`, `max`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sum" => Some(Self::Sum),
+            "count" => Some(Self::Count),
+            "max" => Some(Self::Max),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for AggregateOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sum => write!(f, "sum"),
+            Self::Count => write!(f, "count"),
+            Self::Max => write!(f, "max"),
+        }
+    }
+}
+
 /// A state variable in a program.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StateVar {
     /// Variable name.
     pub name: String,
@@ -239,10 +958,18 @@ pub struct StateVar {
 
     /// Visibility: "public", "private", "internal", etc.
     pub visibility: Option<String>,
+
+    /// Move struct abilities (`key`, `store`, `copy`, `drop`).
+    #[serde(default)]
+    pub abilities: BTreeSet<String>,
+
+    /// State variables this one's declared value is derived from.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
 }
 
 /// A function or entry point in a program.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FunctionModel {
     /// Function name.
     pub name: String,
@@ -264,10 +991,62 @@ pub struct FunctionModel {
 
     /// Whether it's pure/view (doesn't mutate state).
     pub is_pure: bool,
+
+    /// How the function's arithmetic guards against overflow.
+    #[serde(default)]
+    pub overflow_protection: OverflowProtection,
+
+    /// 1-based inclusive line range of the definition, when known.
+    #[serde(default)]
+    pub span: Option<(usize, usize)>,
+
+    /// Modifiers guarding the function (Solidity `onlyOwner`, ...).
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+
+    /// Whether the body uses inline assembly or low-level calls whose state
+    /// writes cannot be analyzed (see [`ProgramModel::uncertainties`]).
+    #[serde(default)]
+    pub uses_low_level: bool,
+
+    /// Conditions the caller must establish (`/// @pre amount > 0`).
+    #[serde(default)]
+    pub pre_conditions: Vec<Expression>,
+
+    /// Conditions the function guarantees on return (`/// @post ...`).
+    #[serde(default)]
+    pub post_conditions: Vec<Expression>,
+}
+
+impl FunctionModel {
+    /// Check if `line` (1-based) falls within this function's span.
+    pub fn contains_line(&self, line: usize) -> bool {
+        self.span
+            .is_some_and(|(start, end)| (start..=end).contains(&line))
+    }
+}
+
+/// Overflow protection of a function's arithmetic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OverflowProtection {
+    /// All arithmetic uses checked operations.
+    Safe,
+    /// No arithmetic uses checked operations.
+    Unsafe,
+    /// Both checked and unchecked arithmetic are present.
+    Mixed,
+    /// No arithmetic found, or the analyzer does not track it.
+    #[default]
+    Unknown,
 }
 
+/// Schema version written by [`ProgramModel::to_json_pretty`].
+///
+/// Bump it when a field change would make older readers misinterpret a model.
+pub const PROGRAM_MODEL_SCHEMA_VERSION: u32 = 1;
+
 /// A complete program model extracted from source code.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ProgramModel {
     /// Program/contract/module name.
     pub name: String,
@@ -286,6 +1065,156 @@ pub struct ProgramModel {
 
     /// Source file path.
     pub source_path: String,
+
+    /// PDA derivations found in account constraints (Solana only).
+    #[serde(default)]
+    pub pda_patterns: Vec<PdaPattern>,
+
+    /// Cross-program invocations found in function bodies (Solana only).
+    #[serde(default)]
+    pub cpi_calls: Vec<CpiCall>,
+
+    /// Authority accounts used by instruction handlers (Solana only).
+    #[serde(default)]
+    pub authority_accounts: Vec<AuthorityAccount>,
+
+    /// State updates using arithmetic that can wrap silently: bare operators
+    /// in Rust, `unchecked` blocks or pre-0.8 Solidity.
+    #[serde(default)]
+    pub unchecked_arithmetic: Vec<UncheckedArithmetic>,
+
+    /// Constructs that make mutation analysis unsound, such as inline
+    /// assembly or `delegatecall` (EVM only). Strict builds reject them.
+    #[serde(default)]
+    pub uncertainties: Vec<Uncertainty>,
+
+    /// Declared events and their parameter types (EVM only).
+    #[serde(default)]
+    pub events: BTreeMap<String, Vec<String>>,
+
+    /// Declared custom errors and their parameter types (EVM only).
+    #[serde(default)]
+    pub errors: BTreeMap<String, Vec<String>>,
+
+    /// Invariant expressions annotated in the source, keyed by function name.
+    #[serde(default)]
+    pub invariant_hints: BTreeMap<String, Vec<String>>,
+
+    /// Class-level invariant expressions declared for the whole contract or
+    /// module (`@invariant` NatSpec, Move `spec` blocks).
+    #[serde(default)]
+    pub contract_invariants: Vec<String>,
+
+    /// Framework the program is written against, such as `anchor` (Solana only).
+    #[serde(default)]
+    pub framework: Option<String>,
+}
+
+/// A Program Derived Address derivation (`#[account(seeds = [...])]`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PdaPattern {
+    /// Instruction handler using the accounts struct (or the struct name if none is found).
+    pub function_name: String,
+
+    /// Seeds in declaration order.
+    pub seeds: Vec<PdaSeedSource>,
+
+    /// 1-based line of the `seeds` constraint.
+    pub line: usize,
+}
+
+impl PdaPattern {
+    /// Names of user-controlled seeds.
+    pub fn user_inputs(&self) -> impl Iterator<Item = &str> {
+        self.seeds.iter().filter_map(|seed| match seed {
+            PdaSeedSource::UserInput(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// A cross-program invocation (`invoke` / `invoke_signed`) in a function body.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpiCall {
+    /// Function making the call.
+    pub function_name: String,
+
+    /// 1-based line of the call.
+    pub line: usize,
+
+    /// 1-based lines of state writes (field, index or deref assignments)
+    /// that follow the call in the same function.
+    pub mutations_after: Vec<usize>,
+}
+
+/// An authority account (`authority`, `owner`, `admin`, `*_authority`, ...)
+/// taken by an instruction handler.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthorityAccount {
+    /// Handler taking the account.
+    pub function_name: String,
+
+    /// Account name.
+    pub account: String,
+
+    /// Whether the account is required to sign (`is_signer` check, Anchor
+    /// `Signer` type or `signer` constraint).
+    pub signer_checked: bool,
+
+    /// 1-based line where the account is declared.
+    pub line: usize,
+}
+
+/// A construct whose effects on state the analyzer cannot follow.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Uncertainty {
+    /// Function containing the construct.
+    pub function_name: String,
+
+    /// What was found: `inline assembly`, `delegatecall`, ...
+    pub construct: String,
+
+    /// 1-based line of the construct.
+    pub line: usize,
+}
+
+/// A state update through arithmetic without overflow checks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UncheckedArithmetic {
+    /// Function making the update.
+    #[serde(default)]
+    pub function_name: String,
+
+    /// Updated state, as an invariant variable (`totalSupply`, `vault::balance`).
+    pub target: String,
+
+    /// Operator applied to the old value.
+    pub op: ArithmeticOp,
+
+    /// 1-based line of the update.
+    pub line: usize,
+}
+
+/// Arithmetic operator of an [`UncheckedArithmetic`] update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ArithmeticOp {
+    /// `+=`, `x = x + ..`, `++`.
+    Add,
+    /// `-=`, `x = x - ..`, `--`.
+    Sub,
+    /// `*=`, `x = x * ..`.
+    Mul,
+}
+
+/// Where a PDA seed comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PdaSeedSource {
+    /// Constant bytes or other program-controlled data.
+    StaticBytes,
+    /// The program's own id.
+    ProgramId,
+    /// An instruction argument supplied by the caller.
+    UserInput(String),
 }
 
 impl ProgramModel {
@@ -298,6 +1227,16 @@ impl ProgramModel {
             state_vars: BTreeMap::new(),
             functions: BTreeMap::new(),
             mutation_graph: BTreeMap::new(),
+            pda_patterns: Vec::new(),
+            cpi_calls: Vec::new(),
+            unchecked_arithmetic: Vec::new(),
+            uncertainties: Vec::new(),
+            authority_accounts: Vec::new(),
+            events: BTreeMap::new(),
+            errors: BTreeMap::new(),
+            invariant_hints: BTreeMap::new(),
+            contract_invariants: Vec::new(),
+            framework: None,
         }
     }
 
@@ -306,12 +1245,138 @@ impl ProgramModel {
         self.state_vars.insert(var.name.clone(), var);
     }
 
+    /// State variables no invariant references, in name order: state that
+    /// can change without any check noticing.
+    ///
+    /// A layer- or phase-qualified reference such as `vault::balance` covers
+    /// the state variable `balance`.
+    pub fn validate_completeness(&self, invariants: &[Invariant]) -> Vec<String> {
+        let referenced: BTreeSet<String> = invariants
+            .iter()
+            .flat_map(|inv| inv.expression.variables())
+            .map(|name| name.rsplit("::").next().unwrap_or_default().to_string())
+            .collect();
+        self.state_vars
+            .keys()
+            .filter(|name| !referenced.contains(*name))
+            .cloned()
+            .collect()
+    }
+
     /// Add a function to the model.
     pub fn add_function(&mut self, func: FunctionModel) {
         self.mutation_graph
             .insert(func.name.clone(), func.mutates.clone());
         self.functions.insert(func.name.clone(), func);
     }
+
+    /// Export the model as pretty-printed JSON tagged with `schema_version`.
+    pub fn to_json_pretty(&self) -> crate::Result<String> {
+        let mut json = serde_json::to_value(self)
+            .map_err(|e| crate::InvarError::custom(format!("cannot serialize model: {}", e)))?;
+        if let serde_json::Value::Object(fields) = &mut json {
+            fields.insert(
+                "schema_version".to_string(),
+                PROGRAM_MODEL_SCHEMA_VERSION.into(),
+            );
+        }
+        serde_json::to_string_pretty(&json)
+            .map_err(|e| crate::InvarError::custom(format!("cannot serialize model: {}", e)))
+    }
+
+    /// Load a model exported by [`ProgramModel::to_json_pretty`].
+    ///
+    /// Models written by a newer schema version are rejected.
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        let mut value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| crate::InvarError::ConfigError(format!("invalid model JSON: {}", e)))?;
+        let version = value
+            .as_object_mut()
+            .and_then(|fields| fields.remove("schema_version"))
+            .and_then(|version| version.as_u64())
+            .ok_or_else(|| {
+                crate::InvarError::ConfigError("model JSON has no schema_version".to_string())
+            })?;
+        if version > u64::from(PROGRAM_MODEL_SCHEMA_VERSION) {
+            return Err(crate::InvarError::unsupported(format!(
+                "model schema version {} is newer than supported version {}",
+                version, PROGRAM_MODEL_SCHEMA_VERSION
+            )));
+        }
+        serde_json::from_value(value)
+            .map_err(|e| crate::InvarError::ConfigError(format!("invalid model JSON: {}", e)))
+    }
+
+    /// Write the model to `path` in the [`to_json_pretty`](Self::to_json_pretty)
+    /// format.
+    pub fn save_to_json(&self, path: &Path) -> crate::Result<()> {
+        std::fs::write(path, self.to_json_pretty()?)?;
+        Ok(())
+    }
+
+    /// Read a model written by [`save_to_json`](Self::save_to_json).
+    pub fn load_from_json(path: &Path) -> crate::Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Graphviz DOT rendering of [`mutation_graph`](Self::mutation_graph):
+    /// an edge from each function to every state variable it mutates.
+    ///
+    /// Entry points are filled blue, helpers white and state variables
+    /// green. Node ids are prefixed with `fn:` or `var:` so a function and a
+    /// variable of the same name stay distinct.
+    pub fn call_graph_to_dot(&self) -> String {
+        let functions: BTreeSet<&String> = self
+            .functions
+            .keys()
+            .chain(self.mutation_graph.keys())
+            .collect();
+        let variables: BTreeSet<&String> = self
+            .state_vars
+            .keys()
+            .chain(self.mutation_graph.values().flatten())
+            .collect();
+
+        let mut dot = format!("digraph {} {{\n    rankdir=LR;\n", dot_quote(&self.name));
+        for name in functions {
+            let is_entry = self
+                .functions
+                .get(name)
+                .is_some_and(|func| func.is_entry_point);
+            dot.push_str(&format!(
+                "    {} [label={}, shape=box, style=filled, fillcolor={}];\n",
+                dot_quote(&format!("fn:{}", name)),
+                dot_quote(name),
+                if is_entry { "lightblue" } else { "white" }
+            ));
+        }
+        for name in variables {
+            dot.push_str(&format!(
+                "    {} [label={}, shape=ellipse, style=filled, fillcolor=lightgreen];\n",
+                dot_quote(&format!("var:{}", name)),
+                dot_quote(name)
+            ));
+        }
+        for (function, mutates) in &self.mutation_graph {
+            for var in mutates {
+                dot.push_str(&format!(
+                    "    {} -> {};\n",
+                    dot_quote(&format!("fn:{}", function)),
+                    dot_quote(&format!("var:{}", var))
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Record a source-annotated invariant expression for `function`.
+    pub fn add_invariant_hint(&mut self, function: &str, hint: &str) {
+        self.invariant_hints
+            .entry(function.to_string())
+            .or_default()
+            .push(hint.trim().to_string());
+    }
 }
 
 /// Output from code generation.
@@ -330,18 +1395,898 @@ pub struct GenerationOutput {
     pub coverage_percent: u8,
 }
 
+impl GenerationOutput {
+    /// Check that [`code`](Self::code) compiles as a standalone library crate,
+    /// returning `rustc`'s diagnostics when it does not.
+    ///
+    /// See [`InjectionVerifier::verify_compiles`](crate::threat_model::InjectionVerifier::verify_compiles).
+    #[cfg(feature = "compile-verification")]
+    pub fn compile_and_verify(&self) -> Result<(), String> {
+        crate::threat_model::InjectionVerifier::verify_compiles(&self.code, &[]).map_err(
+            |e| match e {
+                crate::threat_model::ThreatModelError::ReParseVerificationFailed(stderr) => stderr,
+                other => other.to_string(),
+            },
+        )
+    }
+}
+
 /// Report from a simulation run.
+///
+/// Traces and violations are deterministic for a given seed. Evaluation
+/// times in `invariant_stats` are wall-clock measurements and are not.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimulationReport {
     /// Number of violations found.
     pub violations: usize,
 
     /// Violation traces.
-    pub traces: Vec<String>,
+    pub traces: Vec<ViolationTrace>,
 
     /// Coverage percentage.
     pub coverage: f64,
 
     /// Deterministic seed used.
     pub seed: u64,
+
+    /// Names of the invariants checked, violated or not.
+    #[serde(default)]
+    pub invariants_checked: Vec<String>,
+
+    /// Evaluation counters per invariant, keyed by name.
+    #[serde(default)]
+    pub invariant_stats: BTreeMap<String, InvariantStats>,
+
+    /// Invariants that were not checked, with the reason, keyed by name.
+    #[serde(default)]
+    pub skipped: BTreeMap<String, String>,
+}
+
+/// How often an invariant was evaluated during a simulation, and at what cost.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InvariantStats {
+    /// Times the invariant was evaluated.
+    pub evaluations: u64,
+
+    /// Evaluations that returned `false`.
+    pub failures: u64,
+
+    /// Iteration of the first evaluation that returned `false`.
+    #[serde(default)]
+    pub first_failure: Option<u64>,
+
+    /// Evaluations that failed with an error, such as an undefined variable.
+    pub errors: u64,
+
+    /// Evaluations answered with the previous result because none of the
+    /// invariant's variables had changed.
+    #[serde(default)]
+    pub cache_hits: u64,
+
+    /// Cumulative evaluation time.
+    pub total_time: std::time::Duration,
+
+    /// Smallest value of the bounded variable, for invariants bounding a
+    /// single variable by a constant (`fee <= 100`).
+    #[serde(default)]
+    pub min_observed: Option<Value>,
+
+    /// Largest value of the bounded variable.
+    #[serde(default)]
+    pub max_observed: Option<Value>,
+}
+
+impl InvariantStats {
+    /// Widen the observed range to include `value`.
+    pub fn observe(&mut self, value: &Value) {
+        let below = |current: &Value| value.numeric_cmp(current) == Some(Ordering::Less);
+        let above = |current: &Value| value.numeric_cmp(current) == Some(Ordering::Greater);
+        if self.min_observed.as_ref().is_none_or(below) {
+            self.min_observed = Some(value.clone());
+        }
+        if self.max_observed.as_ref().is_none_or(above) {
+            self.max_observed = Some(value.clone());
+        }
+    }
+}
+
+/// Compares everything but `total_time` and `cache_hits`, so that runs with
+/// the same seed compare equal whether or not results were cached.
+impl PartialEq for InvariantStats {
+    fn eq(&self, other: &Self) -> bool {
+        self.evaluations == other.evaluations
+            && self.failures == other.failures
+            && self.first_failure == other.first_failure
+            && self.errors == other.errors
+            && self.min_observed == other.min_observed
+            && self.max_observed == other.max_observed
+    }
+}
+
+/// Test suite name and `classname` used in JUnit output.
+const JUNIT_CLASSNAME: &str = "invar.simulation";
+
+/// Violations at which the badge turns from yellow to red.
+const BADGE_RED_VIOLATIONS: usize = 5;
+
+impl SimulationReport {
+    /// Build a report from violation traces alone.
+    ///
+    /// Every traced invariant counts as checked. Coverage and seed are not
+    /// recorded by traces and are left at zero.
+    pub fn from_violation_traces(traces: &[ViolationTrace]) -> SimulationReport {
+        let mut invariants_checked: Vec<String> = Vec::new();
+        for trace in traces {
+            if !invariants_checked.contains(&trace.invariant) {
+                invariants_checked.push(trace.invariant.clone());
+            }
+        }
+        SimulationReport {
+            violations: traces.len(),
+            traces: traces.to_vec(),
+            coverage: 0.0,
+            seed: 0,
+            invariants_checked,
+            invariant_stats: BTreeMap::new(),
+            skipped: BTreeMap::new(),
+        }
+    }
+
+    /// One-line status for CI logs:
+    /// `✓ 0 violations, 95.3% coverage (seed=42)` or
+    /// `✗ 3 violations found (seed=42)`.
+    pub fn summary(&self) -> String {
+        if self.violations == 0 {
+            format!(
+                "✓ 0 violations, {:.1}% coverage (seed={})",
+                self.coverage, self.seed
+            )
+        } else {
+            format!(
+                "✗ {} violation{} found (seed={})",
+                self.violations,
+                if self.violations == 1 { "" } else { "s" },
+                self.seed
+            )
+        }
+    }
+
+    /// Whether the run found no violations and reached `min_coverage`
+    /// percent coverage.
+    pub fn is_passing(&self, min_coverage: f64) -> bool {
+        self.violations == 0 && self.coverage >= min_coverage
+    }
+
+    /// Badge color for the run: `"green"` without violations, `"yellow"`
+    /// for a few and `"red"` from five on.
+    pub fn to_badge_color(&self) -> &'static str {
+        match self.violations {
+            0 => "green",
+            n if n < BADGE_RED_VIOLATIONS => "yellow",
+            _ => "red",
+        }
+    }
+
+    /// Render the report as a JUnit XML test suite.
+    ///
+    /// Each checked invariant becomes a `<testcase>`; invariants with traces
+    /// get a `<failure>` listing them and skipped ones a `<skipped>` with the
+    /// reason.
+    pub fn to_junit_xml(&self) -> String {
+        let mut names: Vec<&str> = self.invariants_checked.iter().map(String::as_str).collect();
+        for trace in &self.traces {
+            if !names.contains(&trace.invariant.as_str()) {
+                names.push(&trace.invariant);
+            }
+        }
+
+        let mut cases = String::new();
+        let mut failures = 0;
+        for name in &names {
+            let traces: Vec<String> = self
+                .traces
+                .iter()
+                .filter(|trace| trace.invariant == *name)
+                .map(ViolationTrace::to_string)
+                .collect();
+            if traces.is_empty() {
+                cases.push_str(&format!(
+                    "  <testcase classname=\"{}\" name=\"{}\"/>\n",
+                    JUNIT_CLASSNAME,
+                    xml_escape(name)
+                ));
+            } else {
+                failures += 1;
+                cases.push_str(&format!(
+                    "  <testcase classname=\"{}\" name=\"{}\">\n    <failure message=\"invariant violated ({} trace(s))\">{}</failure>\n  </testcase>\n",
+                    JUNIT_CLASSNAME,
+                    xml_escape(name),
+                    traces.len(),
+                    xml_escape(&traces.join("\n"))
+                ));
+            }
+        }
+
+        for (name, reason) in &self.skipped {
+            cases.push_str(&format!(
+                "  <testcase classname=\"{}\" name=\"{}\">\n    <skipped message=\"{}\"/>\n  </testcase>\n",
+                JUNIT_CLASSNAME,
+                xml_escape(name),
+                xml_escape(reason)
+            ));
+        }
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">\n{}</testsuite>\n",
+            JUNIT_CLASSNAME,
+            names.len() + self.skipped.len(),
+            failures,
+            self.skipped.len(),
+            cases
+        )
+    }
+}
+
+/// Escape text for use in XML attributes and content.
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Quote text as a DOT string id.
+fn dot_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A violation found during simulation: the invariant, where in the run it
+/// broke, and the state and calls that broke it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViolationTrace {
+    /// Name of the violated invariant.
+    pub invariant: String,
+
+    /// Fuzz iteration the violation was found in.
+    pub iteration: u64,
+
+    /// Seed of the run, to replay it.
+    pub seed: u64,
+
+    /// State variable values when the invariant failed.
+    #[serde(default)]
+    pub state: BTreeMap<String, Value>,
+
+    /// Calls executed leading to the violation.
+    pub call_sequence: Vec<String>,
+
+    /// The part of the invariant that evaluated to false, when known.
+    #[serde(default)]
+    pub explanation: Option<Evaluation>,
+}
+
+/// Rendered as `invariant: iteration_N -> call -> call`.
+impl std::fmt::Display for ViolationTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: iteration_{}", self.invariant, self.iteration)?;
+        for call in &self.call_sequence {
+            write!(f, " -> {}", call)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<ViolationTrace> for String {
+    fn from(trace: ViolationTrace) -> Self {
+        trace.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expression_size_metrics() {
+        let expr = Expression::Logical {
+            left: Box::new(Expression::BinaryOp {
+                left: Box::new(Expression::Int(1)),
+                op: BinaryOp::Eq,
+                right: Box::new(Expression::Int(1)),
+            }),
+            op: LogicalOp::And,
+            right: Box::new(Expression::Int(2)),
+        };
+        assert_eq!(complexity(&expr), 5);
+        assert_eq!(depth(&expr), 3);
+        assert_eq!(complexity(&Expression::Boolean(true)), 1);
+        assert_eq!(depth(&Expression::Boolean(true)), 1);
+    }
+
+    #[test]
+    fn test_invariant_builder() {
+        let inv = Invariant::new("NonceMonotonic", Expression::Boolean(true))
+            .with_description("Nonce never decreases")
+            .with_severity(SEVERITY_CRITICAL)
+            .with_category("account-abstraction")
+            .for_layers(vec!["account".to_string()])
+            .for_phases(vec!["validation".to_string()]);
+
+        assert_eq!(inv.name, "NonceMonotonic");
+        assert_eq!(inv.description.as_deref(), Some("Nonce never decreases"));
+        assert_eq!(inv.severity, SEVERITY_CRITICAL);
+        assert_eq!(inv.category, "account-abstraction");
+        assert!(inv.is_always_true);
+        assert_eq!(inv.layers, vec!["account"]);
+        assert_eq!(inv.phases, vec!["validation"]);
+
+        let defaults = Invariant::new("Default", Expression::Boolean(true));
+        assert_eq!(defaults.enforcement, EnforcementLevel::Always);
+        assert_eq!(defaults.severity, SEVERITY_MEDIUM);
+        assert_eq!(defaults.category, "general");
+        assert!(defaults.layers.is_empty() && defaults.phases.is_empty());
+        assert!(defaults.enabled);
+    }
+
+    #[test]
+    fn test_invariant_filter_select() {
+        let tagged = |name: &str, tags: &[&str]| {
+            Invariant::new(name, Expression::Boolean(true))
+                .with_tags(tags.iter().map(|tag| tag.to_string()).collect())
+        };
+        let invariants = vec![
+            tagged("Supply", &["fast", "critical-path"]),
+            tagged("Fees", &["fast"]),
+            tagged("Slow", &["slow"]),
+            tagged("Off", &["fast"]).with_enabled(false),
+        ];
+        let names = |selection: &InvariantSelection| -> Vec<String> {
+            selection
+                .selected
+                .iter()
+                .map(|inv| inv.name.clone())
+                .collect()
+        };
+
+        // No filter: everything but the disabled invariant
+        let all = InvariantFilter::default()
+            .select(invariants.clone())
+            .unwrap();
+        assert_eq!(names(&all), vec!["Supply", "Fees", "Slow"]);
+        assert_eq!(
+            all.skipped,
+            BTreeMap::from([("Off".to_string(), SKIPPED_DISABLED.to_string())])
+        );
+
+        let filter = InvariantFilter {
+            only_tags: vec!["fast".to_string()],
+            skip_tags: vec!["critical-path".to_string()],
+            only_invariants: Vec::new(),
+        };
+        let fast = filter.select(invariants.clone()).unwrap();
+        assert_eq!(names(&fast), vec!["Fees"]);
+        assert_eq!(fast.skipped["Supply"], SKIPPED_FILTERED);
+        assert_eq!(fast.skipped["Slow"], SKIPPED_FILTERED);
+        assert_eq!(fast.skipped["Off"], SKIPPED_DISABLED);
+
+        let named = InvariantFilter {
+            only_invariants: vec!["Slow".to_string(), "Off".to_string()],
+            ..Default::default()
+        };
+        let selection = named.select(invariants.clone()).unwrap();
+        assert_eq!(names(&selection), vec!["Slow"]);
+        assert_eq!(selection.skipped["Off"], SKIPPED_DISABLED);
+
+        let typo = InvariantFilter {
+            only_invariants: vec!["Suply".to_string()],
+            ..Default::default()
+        };
+        let err = typo.select(invariants).unwrap_err();
+        assert!(
+            err.to_string().contains("did you mean 'Supply'?"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_security_critical_categories_refuse_downgrade() {
+        let debug = Invariant::new("FeeBound", Expression::Boolean(true))
+            .with_enforcement(EnforcementLevel::DebugOnly);
+        assert!(debug.check_enforcement().is_ok());
+
+        let err = debug.with_category("core").check_enforcement().unwrap_err();
+        assert!(err.to_string().contains("cannot be enforced as debug_only"));
+
+        let always = Invariant::new("Supply", Expression::Boolean(true)).with_category("bridge");
+        assert!(always.check_enforcement().is_ok());
+        assert_eq!(
+            EnforcementLevel::from_name("simulation_only"),
+            Some(EnforcementLevel::SimulationOnly)
+        );
+    }
+
+    #[test]
+    fn test_is_compatible_with_chain() {
+        let generic = Invariant::new("generic", Expression::Boolean(true));
+        assert!(generic.is_compatible_with_chain("evm"));
+        assert!(generic.is_compatible_with_chain("solana"));
+
+        let evm = generic.clone().for_layers(vec!["evm".to_string()]);
+        assert!(evm.is_compatible_with_chain("evm"));
+        assert!(!evm.is_compatible_with_chain("solana"));
+
+        // AA layer scopes do not tie an invariant to a chain
+        let paymaster = generic.for_layers(vec!["paymaster".to_string()]);
+        assert!(paymaster.is_compatible_with_chain("solana"));
+    }
+
+    #[test]
+    fn test_for_anchor_constraint() {
+        let balance_check = |op| Expression::BinaryOp {
+            left: Box::new(Expression::LayerVar {
+                layer: "account".to_string(),
+                var: "balance".to_string(),
+            }),
+            op,
+            right: Box::new(Expression::Int(0)),
+        };
+        let simple = Invariant::new("balance_positive", balance_check(BinaryOp::Gte));
+        assert_eq!(
+            simple.for_anchor_constraint().as_deref(),
+            Some("account.balance >= 0")
+        );
+
+        let combined = Invariant::new(
+            "balance_bounded",
+            Expression::Logical {
+                left: Box::new(balance_check(BinaryOp::Gte)),
+                op: LogicalOp::And,
+                right: Box::new(balance_check(BinaryOp::Lt)),
+            },
+        );
+        assert_eq!(combined.for_anchor_constraint(), None);
+        let unqualified = Invariant::new(
+            "fee_positive",
+            Expression::BinaryOp {
+                left: Box::new(Expression::Var("fee".to_string())),
+                op: BinaryOp::Gt,
+                right: Box::new(Expression::Int(0)),
+            },
+        );
+        assert_eq!(unqualified.for_anchor_constraint(), None);
+    }
+
+    #[test]
+    fn test_validate_functions_reports_undefined_calls() {
+        let call = |name: &str| Expression::FunctionCall {
+            name: name.to_string(),
+            args: vec![Expression::Var("amount".to_string())],
+        };
+        let inv = Invariant::new(
+            "fee_bounded",
+            Expression::BinaryOp {
+                left: Box::new(call("compute_fee")),
+                op: BinaryOp::Lte,
+                right: Box::new(call("max_fee")),
+            },
+        );
+        assert_eq!(
+            inv.referenced_functions().into_iter().collect::<Vec<_>>(),
+            vec!["compute_fee", "max_fee"]
+        );
+
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "lib.rs".to_string(),
+        );
+        let builtins: BTreeSet<String> = ["max_fee".to_string()].into();
+        assert_eq!(
+            inv.validate_functions(&program, &builtins),
+            vec!["compute_fee"]
+        );
+
+        program.add_function(FunctionModel {
+            name: "compute_fee".to_string(),
+            parameters: vec!["amount".to_string()],
+            return_type: Some("u64".to_string()),
+            mutates: BTreeSet::new(),
+            reads: BTreeSet::new(),
+            is_entry_point: false,
+            is_pure: true,
+            overflow_protection: OverflowProtection::Safe,
+            span: None,
+            modifiers: Vec::new(),
+            uses_low_level: false,
+            pre_conditions: Vec::new(),
+            post_conditions: Vec::new(),
+        });
+        assert!(inv.validate_functions(&program, &builtins).is_empty());
+    }
+
+    #[test]
+    fn test_content_hash_tracks_semantics() {
+        let balance = |var: &str| Expression::BinaryOp {
+            left: Box::new(Expression::Var(var.to_string())),
+            op: BinaryOp::Gte,
+            right: Box::new(Expression::Int(0)),
+        };
+        let inv = Invariant::new("BalancePositive", balance("balance"));
+
+        assert_eq!(inv.content_hash().len(), 64);
+        assert_eq!(
+            inv.content_hash(),
+            Invariant::new("Renamed", balance("balance")).content_hash()
+        );
+        assert_ne!(
+            inv.content_hash(),
+            Invariant::new("BalancePositive", balance("amount")).content_hash()
+        );
+        assert_ne!(
+            inv.content_hash(),
+            inv.clone().with_severity(SEVERITY_HIGH).content_hash()
+        );
+    }
+
+    #[test]
+    fn test_program_model_json_schema_version() {
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "lib.rs".to_string(),
+        );
+        program.add_invariant_hint("deposit", "total >= 0");
+
+        let json = program.to_json_pretty().unwrap();
+        assert!(json.contains("\"schema_version\": 1"));
+        let loaded = ProgramModel::from_json(&json).unwrap();
+        assert_eq!(loaded.invariant_hints, program.invariant_hints);
+
+        let newer = json.replace("\"schema_version\": 1", "\"schema_version\": 2");
+        assert!(matches!(
+            ProgramModel::from_json(&newer),
+            Err(crate::InvarError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_completeness() {
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "lib.rs".to_string(),
+        );
+        for name in ["balance", "fees"] {
+            program.add_state_var(StateVar {
+                name: name.to_string(),
+                type_name: "u64".to_string(),
+                is_mutable: true,
+                visibility: None,
+                abilities: BTreeSet::new(),
+                depends_on: Vec::new(),
+            });
+        }
+        let non_negative = |var: Expression| {
+            Invariant::new(
+                "non_negative",
+                Expression::BinaryOp {
+                    left: Box::new(var),
+                    op: BinaryOp::Gte,
+                    right: Box::new(Expression::Int(0)),
+                },
+            )
+        };
+
+        let balance = non_negative(Expression::Var("balance".to_string()));
+        assert_eq!(
+            program.validate_completeness(std::slice::from_ref(&balance)),
+            vec!["fees"]
+        );
+        let fees = non_negative(Expression::LayerVar {
+            layer: "protocol".to_string(),
+            var: "fees".to_string(),
+        });
+        assert!(program.validate_completeness(&[balance, fees]).is_empty());
+        assert_eq!(program.validate_completeness(&[]), vec!["balance", "fees"]);
+    }
+
+    #[test]
+    fn test_call_graph_to_dot() {
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "lib.rs".to_string(),
+        );
+        for name in ["balance", "fees"] {
+            program.add_state_var(StateVar {
+                name: name.to_string(),
+                type_name: "u64".to_string(),
+                is_mutable: true,
+                visibility: None,
+                abilities: BTreeSet::new(),
+                depends_on: Vec::new(),
+            });
+        }
+        let function = |name: &str, mutates: &str, is_entry_point| FunctionModel {
+            name: name.to_string(),
+            parameters: Vec::new(),
+            return_type: None,
+            mutates: BTreeSet::from([mutates.to_string()]),
+            reads: BTreeSet::new(),
+            is_entry_point,
+            is_pure: false,
+            overflow_protection: OverflowProtection::Safe,
+            span: None,
+            modifiers: Vec::new(),
+            uses_low_level: false,
+            pre_conditions: Vec::new(),
+            post_conditions: Vec::new(),
+        };
+        program.add_function(function("deposit", "balance", true));
+        program.add_function(function("charge_fee", "fees", false));
+
+        let dot = program.call_graph_to_dot();
+        assert!(dot.starts_with("digraph \"vault\" {\n"));
+        assert!(dot.ends_with("}\n"));
+        for name in ["deposit", "charge_fee", "balance", "fees"] {
+            assert!(dot.contains(&format!("label=\"{}\"", name)), "{}", dot);
+        }
+        assert!(dot.contains(
+            "    \"fn:deposit\" [label=\"deposit\", shape=box, style=filled, fillcolor=lightblue];\n"
+        ));
+        assert!(dot.contains("[label=\"charge_fee\", shape=box, style=filled, fillcolor=white]"));
+        assert!(dot.contains("    \"fn:deposit\" -> \"var:balance\";\n"));
+        assert!(dot.contains("    \"fn:charge_fee\" -> \"var:fees\";\n"));
+        assert_eq!(dot.matches("->").count(), 2);
+        // Braces balance and every statement is terminated
+        assert_eq!(dot.matches('{').count(), dot.matches('}').count());
+        assert!(dot
+            .lines()
+            .skip(1)
+            .filter(|line| *line != "}")
+            .all(|line| line.ends_with(';')));
+        assert_eq!(dot_quote("a\"b\\c"), "\"a\\\"b\\\\c\"");
+    }
+
+    #[test]
+    fn test_program_model_file_round_trip() {
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "lib.rs".to_string(),
+        );
+        program.add_state_var(StateVar {
+            name: "balance".to_string(),
+            type_name: "u64".to_string(),
+            is_mutable: true,
+            visibility: Some("public".to_string()),
+            abilities: BTreeSet::from(["store".to_string()]),
+            depends_on: vec!["deposits".to_string()],
+        });
+        program.add_function(FunctionModel {
+            name: "deposit".to_string(),
+            parameters: vec!["amount".to_string()],
+            return_type: Some("ProgramResult".to_string()),
+            mutates: BTreeSet::from(["balance".to_string()]),
+            reads: BTreeSet::from(["balance".to_string()]),
+            is_entry_point: true,
+            is_pure: false,
+            overflow_protection: OverflowProtection::Safe,
+            span: Some((3, 9)),
+            modifiers: vec!["only_owner".to_string()],
+            uses_low_level: false,
+            pre_conditions: vec![Expression::BinaryOp {
+                left: Box::new(Expression::Var("amount".to_string())),
+                op: BinaryOp::Gt,
+                right: Box::new(Expression::Int(0)),
+            }],
+            post_conditions: Vec::new(),
+        });
+        program.cpi_calls.push(CpiCall {
+            function_name: "deposit".to_string(),
+            line: 5,
+            mutations_after: vec![7],
+        });
+        program.add_invariant_hint("deposit", "balance >= 0");
+        program.framework = Some("anchor".to_string());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.json");
+        program.save_to_json(&path).unwrap();
+        assert_eq!(ProgramModel::load_from_json(&path).unwrap(), program);
+        assert!(ProgramModel::load_from_json(&dir.path().join("missing.json")).is_err());
+    }
+
+    #[test]
+    fn test_simulation_report_junit_xml() {
+        let mut report = SimulationReport::from_violation_traces(&[ViolationTrace {
+            invariant: "balance_non_negative".to_string(),
+            iteration: 3,
+            seed: 42,
+            state: BTreeMap::new(),
+            call_sequence: vec!["withdraw(10)".to_string(), "balance < 0".to_string()],
+            explanation: None,
+        }]);
+        report
+            .invariants_checked
+            .extend(["supply_conserved".to_string(), "owner_set".to_string()]);
+
+        let xml = report.to_junit_xml();
+        assert_eq!(xml.matches("<failure").count(), 1);
+        assert_eq!(xml.matches("<testcase").count(), 3);
+        assert!(xml.contains("tests=\"3\" failures=\"1\""));
+        assert!(
+            xml.contains("<testcase classname=\"invar.simulation\" name=\"supply_conserved\"/>")
+        );
+        assert!(xml.contains("withdraw(10) -&gt; balance &lt; 0"));
+
+        report
+            .skipped
+            .insert("paused_guard".to_string(), SKIPPED_DISABLED.to_string());
+        let xml = report.to_junit_xml();
+        assert!(xml.contains("tests=\"4\" failures=\"1\" skipped=\"1\""));
+        assert!(xml.contains(
+            "name=\"paused_guard\">\n    <skipped message=\"disabled\"/>\n  </testcase>"
+        ));
+    }
+
+    #[test]
+    fn test_simulation_report_summary_and_badge() {
+        let trace = |iteration| ViolationTrace {
+            invariant: "solvent".to_string(),
+            iteration,
+            seed: 42,
+            state: BTreeMap::new(),
+            call_sequence: Vec::new(),
+            explanation: None,
+        };
+        let report = |violations: u64| SimulationReport {
+            coverage: 95.3,
+            seed: 42,
+            ..SimulationReport::from_violation_traces(
+                &(0..violations).map(trace).collect::<Vec<_>>(),
+            )
+        };
+
+        let clean = report(0);
+        assert_eq!(clean.summary(), "✓ 0 violations, 95.3% coverage (seed=42)");
+        assert_eq!(clean.to_badge_color(), "green");
+        assert!(clean.is_passing(95.0));
+        assert!(!clean.is_passing(99.0));
+
+        assert_eq!(report(1).summary(), "✗ 1 violation found (seed=42)");
+        assert_eq!(report(3).summary(), "✗ 3 violations found (seed=42)");
+        assert!(!report(1).is_passing(0.0));
+        assert_eq!(report(1).to_badge_color(), "yellow");
+        assert_eq!(report(4).to_badge_color(), "yellow");
+        assert_eq!(report(5).to_badge_color(), "red");
+        assert_eq!(report(12).to_badge_color(), "red");
+    }
+
+    #[test]
+    fn test_violation_trace_json_round_trip() {
+        let trace = ViolationTrace {
+            invariant: "solvent".to_string(),
+            iteration: 7,
+            seed: 42,
+            state: BTreeMap::from([
+                ("total_assets".to_string(), Value::U64(90)),
+                ("total_debt".to_string(), Value::U64(100)),
+            ]),
+            call_sequence: vec!["borrow(100)".to_string()],
+            explanation: Some(Evaluation {
+                expression: "(total_assets >= total_debt)".to_string(),
+                value: Value::Bool(false),
+            }),
+        };
+
+        let json = serde_json::to_string(&trace).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ViolationTrace>(&json).unwrap(),
+            trace
+        );
+        assert_eq!(String::from(trace), "solvent: iteration_7 -> borrow(100)");
+    }
+
+    #[cfg(feature = "compile-verification")]
+    #[test]
+    fn test_compile_and_verify() {
+        let output = |code: &str| GenerationOutput {
+            code: code.to_string(),
+            assertions: Vec::new(),
+            tests: None,
+            coverage_percent: 0,
+        };
+
+        assert_eq!(output("pub fn f() {}").compile_and_verify(), Ok(()));
+        let stderr = output("pub fn f() {{{").compile_and_verify().unwrap_err();
+        assert!(stderr.contains("error"), "{}", stderr);
+    }
+
+    #[test]
+    fn test_pretty_print_nested_logical() {
+        let gt = |name: &str| Expression::BinaryOp {
+            left: Box::new(Expression::Var(name.to_string())),
+            op: BinaryOp::Gt,
+            right: Box::new(Expression::Int(0)),
+        };
+        let expr = Expression::Logical {
+            left: Box::new(gt("a")),
+            op: LogicalOp::And,
+            right: Box::new(Expression::Logical {
+                left: Box::new(gt("b")),
+                op: LogicalOp::And,
+                right: Box::new(Expression::Logical {
+                    left: Box::new(gt("c")),
+                    op: LogicalOp::Or,
+                    right: Box::new(gt("d")),
+                }),
+            }),
+        };
+
+        let pretty = expr.display_pretty();
+        assert!(pretty.matches('\n').count() >= 2, "{}", pretty);
+        assert_eq!(
+            pretty,
+            "((a > 0)\n  && ((b > 0)\n    && ((c > 0)\n      || (d > 0))))"
+        );
+        assert_eq!(pretty_print(&gt("a"), 2), "(a > 0)");
+    }
+
+    #[test]
+    fn test_pretty_print_comparisons_and_calls() {
+        let var = |name: &str| Expression::Var(name.to_string());
+        let call = |args: Vec<Expression>| Expression::FunctionCall {
+            name: "f".to_string(),
+            args,
+        };
+        let expr = Expression::BinaryOp {
+            left: Box::new(call(vec![var("a"), var("b"), var("c"), var("d")])),
+            op: BinaryOp::Lte,
+            right: Box::new(call(vec![var("e")])),
+        };
+
+        assert_eq!(
+            pretty_print(&expr, 4),
+            "(f(\n        a,\n        b,\n        c,\n        d,\n    )\n    <= f(e))"
+        );
+    }
+
+    #[test]
+    fn test_pretty_print_range_shorthand() {
+        let var = |name: &str| Box::new(Expression::Var(name.to_string()));
+        let bounds = |subject: &str, upper: BinaryOp, other: &str| Expression::Logical {
+            left: Box::new(Expression::BinaryOp {
+                left: var(subject),
+                op: BinaryOp::Gte,
+                right: Box::new(Expression::Int(0)),
+            }),
+            op: LogicalOp::And,
+            right: Box::new(Expression::BinaryOp {
+                left: var(other),
+                op: upper,
+                right: var("cap"),
+            }),
+        };
+
+        assert_eq!(
+            bounds("fee", BinaryOp::Lte, "fee").display_pretty(),
+            "(fee in 0..=cap)"
+        );
+        assert_eq!(
+            bounds("fee", BinaryOp::Lt, "fee").display_pretty(),
+            "(fee in 0..cap)"
+        );
+        // Different variables or a non-upper bound stay as written
+        assert_eq!(
+            bounds("fee", BinaryOp::Lte, "rate").display_pretty(),
+            "((fee >= 0)\n  && (rate <= cap))"
+        );
+        assert_eq!(
+            bounds("fee", BinaryOp::Gt, "fee").display_pretty(),
+            "((fee >= 0)\n  && (fee > cap))"
+        );
+    }
 }
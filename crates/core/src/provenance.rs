@@ -0,0 +1,307 @@
+//! Provenance headers for generated code.
+//!
+//! Every generated artifact starts with a comment block recording the Invar
+//! version, the source file and the invariants it was generated from, and
+//! the threat model in effect:
+//!
+//! ```text
+//! // invar-provenance: begin
+//! // version=0.2.0
+//! // source_sha256=9f86d081...
+//! // code_sha256=60303ae2...
+//! // threat_model.strict_mode=true
+//! // ...
+//! // invariant.balance_positive=3a7bd3e2...
+//! // invar-provenance: end
+//! ```
+//!
+//! Lines are `key=value` in a fixed order, so the header is stable across
+//! regenerations of the same inputs. [`ProvenanceHeader::parse`] reads it
+//! back for comparison against the current inputs. Headers written by
+//! [`prepend`](ProvenanceHeader::prepend) also hash the code after them, so
+//! edits to the generated code can be detected.
+
+use crate::model::Invariant;
+use crate::threat_model::{ThreatModelConfig, ThreatModelError, ThreatResult};
+use invar_utils::SemanticVersion;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// First line of a provenance header.
+pub const PROVENANCE_BEGIN: &str = "// invar-provenance: begin";
+
+/// Last line of a provenance header.
+pub const PROVENANCE_END: &str = "// invar-provenance: end";
+
+/// Where a generated artifact came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvenanceHeader {
+    /// Invar version that generated the artifact.
+    pub version: SemanticVersion,
+    /// Lowercase hex SHA-256 of the source file.
+    pub source_sha256: String,
+    /// Lowercase hex SHA-256 of the generated code following the header,
+    /// once the header has been [prepended](Self::prepend) to it.
+    pub code_sha256: Option<String>,
+    /// [`content_hash`](Invariant::content_hash) of each input invariant, by name.
+    pub invariant_hashes: BTreeMap<String, String>,
+    /// Threat model flags in effect.
+    pub threat_model: ThreatModelConfig,
+}
+
+impl ProvenanceHeader {
+    /// The header for generating from `source` and `invariants` with this
+    /// version of Invar.
+    pub fn new(source: &str, invariants: &[Invariant], threat_model: &ThreatModelConfig) -> Self {
+        Self {
+            version: current_version(),
+            source_sha256: sha256_hex(source),
+            code_sha256: None,
+            invariant_hashes: invariants
+                .iter()
+                .map(|inv| (inv.name.clone(), inv.content_hash()))
+                .collect(),
+            threat_model: threat_model.clone(),
+        }
+    }
+
+    /// `code` with this header, including the hash of `code`, in front of it.
+    pub fn prepend(&self, code: &str) -> String {
+        let header = Self {
+            code_sha256: Some(sha256_hex(code)),
+            ..self.clone()
+        };
+        format!("{}{}", header, code)
+    }
+
+    /// The code after the provenance header, or all of `code` when it has
+    /// none.
+    pub fn body(code: &str) -> &str {
+        code.split_once(&format!("{}\n", PROVENANCE_END))
+            .map_or(code, |(_, body)| body)
+    }
+
+    /// Read the provenance header at the start of generated `code`.
+    ///
+    /// Leading blank lines are skipped. Unknown keys are ignored, so headers
+    /// written by newer versions still parse.
+    pub fn parse(code: &str) -> ThreatResult<Self> {
+        let malformed = |msg: String| ThreatModelError::TamperDetected(msg);
+
+        let mut lines = code
+            .lines()
+            .map(str::trim)
+            .skip_while(|line| line.is_empty());
+        if lines.next() != Some(PROVENANCE_BEGIN) {
+            return Err(malformed("no provenance header".to_string()));
+        }
+
+        let mut version = None;
+        let mut source_sha256 = None;
+        let mut code_sha256 = None;
+        let mut invariant_hashes = BTreeMap::new();
+        let mut threat_model = ThreatModelConfig::default();
+        loop {
+            let line = lines
+                .next()
+                .ok_or_else(|| malformed("unterminated provenance header".to_string()))?;
+            if line == PROVENANCE_END {
+                break;
+            }
+            let (key, value) = line
+                .strip_prefix("//")
+                .and_then(|entry| entry.trim().split_once('='))
+                .ok_or_else(|| malformed(format!("malformed provenance line '{}'", line)))?;
+
+            if key == "version" {
+                version = Some(SemanticVersion::parse(value).map_err(malformed)?);
+            } else if key == "source_sha256" {
+                source_sha256 = Some(value.to_string());
+            } else if key == "code_sha256" {
+                code_sha256 = Some(value.to_string());
+            } else if let Some(name) = key.strip_prefix("invariant.") {
+                invariant_hashes.insert(name.to_string(), value.to_string());
+            } else if let Some(flag) = key.strip_prefix("threat_model.") {
+                let enabled = value
+                    .parse::<bool>()
+                    .map_err(|_| malformed(format!("threat model flag {} is not a bool", flag)))?;
+                if let Some(field) = threat_model_flag(&mut threat_model, flag) {
+                    *field = enabled;
+                }
+            }
+        }
+
+        Ok(Self {
+            version: version.ok_or_else(|| malformed("provenance has no version".to_string()))?,
+            source_sha256: source_sha256
+                .ok_or_else(|| malformed("provenance has no source_sha256".to_string()))?,
+            code_sha256,
+            invariant_hashes,
+            threat_model,
+        })
+    }
+
+    /// How this header differs from `expected`, one entry per differing key.
+    /// The code hash is not compared, as it describes the output rather than
+    /// the inputs.
+    pub fn mismatches(&self, expected: &ProvenanceHeader) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        if self.version != expected.version {
+            mismatches.push(format!(
+                "generated by Invar {}, current is {}",
+                self.version, expected.version
+            ));
+        }
+        if self.source_sha256 != expected.source_sha256 {
+            mismatches.push("source file changed".to_string());
+        }
+        for (name, hash) in &expected.invariant_hashes {
+            match self.invariant_hashes.get(name) {
+                None => mismatches.push(format!("invariant {} added", name)),
+                Some(recorded) if recorded != hash => {
+                    mismatches.push(format!("invariant {} changed", name))
+                }
+                Some(_) => {}
+            }
+        }
+        for name in self.invariant_hashes.keys() {
+            if !expected.invariant_hashes.contains_key(name) {
+                mismatches.push(format!("invariant {} removed", name));
+            }
+        }
+        for ((flag, recorded), (_, current)) in threat_model_flags(&self.threat_model)
+            .into_iter()
+            .zip(threat_model_flags(&expected.threat_model))
+        {
+            if recorded != current {
+                mismatches.push(format!("threat_model.{} was {}", flag, recorded));
+            }
+        }
+        mismatches
+    }
+}
+
+impl fmt::Display for ProvenanceHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", PROVENANCE_BEGIN)?;
+        writeln!(f, "// version={}", self.version)?;
+        writeln!(f, "// source_sha256={}", self.source_sha256)?;
+        if let Some(code_sha256) = &self.code_sha256 {
+            writeln!(f, "// code_sha256={}", code_sha256)?;
+        }
+        for (flag, enabled) in threat_model_flags(&self.threat_model) {
+            writeln!(f, "// threat_model.{}={}", flag, enabled)?;
+        }
+        for (name, hash) in &self.invariant_hashes {
+            writeln!(f, "// invariant.{}={}", name, hash)?;
+        }
+        writeln!(f, "{}", PROVENANCE_END)
+    }
+}
+
+/// The version of Invar this crate belongs to.
+pub fn current_version() -> SemanticVersion {
+    SemanticVersion::parse(env!("CARGO_PKG_VERSION")).expect("crate version is MAJOR.MINOR.PATCH")
+}
+
+/// Lowercase hex SHA-256 of `text`.
+pub(crate) fn sha256_hex(text: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Threat model flags in header order.
+fn threat_model_flags(config: &ThreatModelConfig) -> [(&'static str, bool); 5] {
+    [
+        ("strict_mode", config.strict_mode),
+        ("re_parse_verification", config.re_parse_verification),
+        ("tamper_detection_enabled", config.tamper_detection_enabled),
+        ("dsl_sandboxing_enabled", config.dsl_sandboxing_enabled),
+        ("isolation_verification", config.isolation_verification),
+    ]
+}
+
+fn threat_model_flag<'a>(config: &'a mut ThreatModelConfig, flag: &str) -> Option<&'a mut bool> {
+    match flag {
+        "strict_mode" => Some(&mut config.strict_mode),
+        "re_parse_verification" => Some(&mut config.re_parse_verification),
+        "tamper_detection_enabled" => Some(&mut config.tamper_detection_enabled),
+        "dsl_sandboxing_enabled" => Some(&mut config.dsl_sandboxing_enabled),
+        "isolation_verification" => Some(&mut config.isolation_verification),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{BinaryOp, Expression};
+
+    fn invariants() -> Vec<Invariant> {
+        vec![
+            Invariant::new("not_paused", Expression::Var("paused".to_string())),
+            Invariant::new(
+                "supply_positive",
+                Expression::BinaryOp {
+                    left: Box::new(Expression::Var("supply".to_string())),
+                    op: BinaryOp::Gt,
+                    right: Box::new(Expression::Int(0)),
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_header_round_trips() {
+        let threat_model = ThreatModelConfig {
+            strict_mode: false,
+            ..ThreatModelConfig::default()
+        };
+        let header = ProvenanceHeader::new("contract Token {}", &invariants(), &threat_model);
+        let code = header.prepend("pragma solidity ^0.8.0;\n");
+
+        assert!(code.starts_with(PROVENANCE_BEGIN));
+        assert!(code.contains("// threat_model.strict_mode=false\n"));
+        assert_eq!(ProvenanceHeader::body(&code), "pragma solidity ^0.8.0;\n");
+
+        let parsed = ProvenanceHeader::parse(&code).unwrap();
+        assert_eq!(
+            parsed.code_sha256.as_deref(),
+            Some(sha256_hex("pragma solidity ^0.8.0;\n").as_str())
+        );
+        assert_eq!(
+            parsed,
+            ProvenanceHeader {
+                code_sha256: parsed.code_sha256.clone(),
+                ..header.clone()
+            }
+        );
+        assert_eq!(header.version, current_version());
+        assert!(parsed.mismatches(&header).is_empty());
+    }
+
+    #[test]
+    fn test_mismatched_invariant_hash_is_reported() {
+        let threat_model = ThreatModelConfig::default();
+        let code = ProvenanceHeader::new("source", &invariants(), &threat_model).prepend("");
+
+        let mut current = invariants();
+        current[1].expression = Expression::BinaryOp {
+            left: Box::new(Expression::Var("supply".to_string())),
+            op: BinaryOp::Gte,
+            right: Box::new(Expression::Int(0)),
+        };
+        let recorded = ProvenanceHeader::parse(&code).unwrap();
+        let expected = ProvenanceHeader::new("source", &current, &threat_model);
+        assert_eq!(
+            recorded.mismatches(&expected),
+            vec!["invariant supply_positive changed"]
+        );
+
+        assert!(ProvenanceHeader::parse("fn main() {}").is_err());
+    }
+}
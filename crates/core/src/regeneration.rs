@@ -0,0 +1,221 @@
+//! Stable regeneration: generation order and diffs between generations.
+//!
+//! Generators emit checks in [`sort_for_generation`] order, so the output
+//! depends only on which invariants are checked, not on the order they were
+//! declared in. [`GenerationOutput::diff`] then shows what a regeneration
+//! changed.
+
+use crate::model::{GenerationOutput, Invariant};
+
+/// Sort invariants into generation order: by
+/// [`content_hash`](Invariant::content_hash), then by name for invariants
+/// with the same semantics.
+///
+/// Adding an invariant inserts its checks among the others without moving
+/// them, and reordering the invariants file changes nothing.
+pub fn sort_for_generation(invariants: &mut [&Invariant]) {
+    invariants.sort_by_cached_key(|inv| (inv.content_hash(), inv.name.clone()));
+}
+
+/// Lines that changed together between two versions of generated code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeHunk {
+    /// 1-based line of the previous code the hunk starts at. When nothing
+    /// was removed, the added lines go before this line.
+    pub previous_line: usize,
+    /// 1-based line of the current code the hunk starts at.
+    pub current_line: usize,
+    /// Lines only in the previous code.
+    pub removed: Vec<String>,
+    /// Lines only in the current code.
+    pub added: Vec<String>,
+}
+
+/// What changed between two [`GenerationOutput`]s.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GenerationDiff {
+    /// Assertions only in the current output, in its order.
+    pub added_assertions: Vec<String>,
+    /// Assertions only in the previous output, in its order.
+    pub removed_assertions: Vec<String>,
+    /// Runs of changed lines in [`code`](GenerationOutput::code), in order.
+    pub hunks: Vec<CodeHunk>,
+}
+
+impl GenerationDiff {
+    /// Whether the two outputs have the same assertions and code.
+    pub fn is_empty(&self) -> bool {
+        self.added_assertions.is_empty()
+            && self.removed_assertions.is_empty()
+            && self.hunks.is_empty()
+    }
+}
+
+impl GenerationOutput {
+    /// Changes from `previous` to this output. Assertions are compared as
+    /// multisets; code is compared line by line.
+    pub fn diff(&self, previous: &GenerationOutput) -> GenerationDiff {
+        GenerationDiff {
+            added_assertions: missing_from(&self.assertions, &previous.assertions),
+            removed_assertions: missing_from(&previous.assertions, &self.assertions),
+            hunks: code_hunks(&previous.code, &self.code),
+        }
+    }
+}
+
+/// Entries of `items` left over after matching each entry of `other` once.
+fn missing_from(items: &[String], other: &[String]) -> Vec<String> {
+    let mut unmatched: Vec<&String> = other.iter().collect();
+    items
+        .iter()
+        .filter(|item| match unmatched.iter().position(|o| o == item) {
+            Some(index) => {
+                unmatched.swap_remove(index);
+                false
+            }
+            None => true,
+        })
+        .cloned()
+        .collect()
+}
+
+/// Group a longest-common-subsequence line diff into hunks of adjacent
+/// changes.
+fn code_hunks(previous: &str, current: &str) -> Vec<CodeHunk> {
+    let old: Vec<&str> = previous.lines().collect();
+    let new: Vec<&str> = current.lines().collect();
+
+    // Common prefix and suffix need no table
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    // common[i][j]: longest common subsequence of old_mid[i..] and new_mid[j..]
+    let mut common = vec![vec![0usize; new_mid.len() + 1]; old_mid.len() + 1];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            common[i][j] = if old_mid[i] == new_mid[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let mut open: Option<CodeHunk> = None;
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        let keep = i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j];
+        if keep {
+            hunks.extend(open.take());
+            i += 1;
+            j += 1;
+            continue;
+        }
+        let hunk = open.get_or_insert_with(|| CodeHunk {
+            previous_line: prefix + i + 1,
+            current_line: prefix + j + 1,
+            removed: Vec::new(),
+            added: Vec::new(),
+        });
+        if j == new_mid.len() || (i < old_mid.len() && common[i + 1][j] >= common[i][j + 1]) {
+            hunk.removed.push(old_mid[i].to_string());
+            i += 1;
+        } else {
+            hunk.added.push(new_mid[j].to_string());
+            j += 1;
+        }
+    }
+    hunks.extend(open);
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{BinaryOp, Expression};
+
+    fn output(assertions: &[&str], code: &str) -> GenerationOutput {
+        GenerationOutput {
+            code: code.to_string(),
+            assertions: assertions.iter().map(|a| a.to_string()).collect(),
+            tests: None,
+            coverage_percent: 100,
+        }
+    }
+
+    #[test]
+    fn test_identical_outputs_have_empty_diff() {
+        let first = output(&["check!(a);", "check!(b);"], "fn a() {}\nfn b() {}\n");
+        assert!(first.diff(&first.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_diff_reports_assertions_and_hunks() {
+        let previous = output(
+            &["check!(a);", "check!(a);", "check!(b);"],
+            "// 2 checks\nfn a() {\n    check!(a);\n}\nfn b() {\n    check!(b);\n}\n",
+        );
+        let current = output(
+            &["check!(a);", "check!(c);", "check!(b);"],
+            "// 3 checks\nfn a() {\n    check!(a);\n    check!(c);\n}\nfn b() {\n    check!(b);\n}\n",
+        );
+
+        let diff = current.diff(&previous);
+        assert_eq!(diff.added_assertions, vec!["check!(c);"]);
+        assert_eq!(diff.removed_assertions, vec!["check!(a);"]);
+        assert_eq!(
+            diff.hunks,
+            vec![
+                CodeHunk {
+                    previous_line: 1,
+                    current_line: 1,
+                    removed: vec!["// 2 checks".to_string()],
+                    added: vec!["// 3 checks".to_string()],
+                },
+                CodeHunk {
+                    previous_line: 4,
+                    current_line: 4,
+                    removed: Vec::new(),
+                    added: vec!["    check!(c);".to_string()],
+                },
+            ]
+        );
+
+        let reverse = previous.diff(&current);
+        assert_eq!(reverse.hunks.len(), 2);
+        assert_eq!(reverse.hunks[1].removed, vec!["    check!(c);"]);
+    }
+
+    #[test]
+    fn test_generation_order_ignores_declaration_order() {
+        let bound = |name: &str, limit| {
+            Invariant::new(
+                name,
+                Expression::BinaryOp {
+                    left: Box::new(Expression::Var("fee".to_string())),
+                    op: BinaryOp::Lte,
+                    right: Box::new(Expression::Int(limit)),
+                },
+            )
+        };
+        let invariants = [bound("a", 1), bound("b", 2), bound("c", 3), bound("d", 3)];
+
+        let names = |mut order: Vec<&Invariant>| {
+            sort_for_generation(&mut order);
+            order.iter().map(|inv| inv.name.clone()).collect::<Vec<_>>()
+        };
+        let forward = names(invariants.iter().collect());
+        assert_eq!(forward, names(invariants.iter().rev().collect()));
+        // Same semantics fall back to the name
+        let position = |name: &str| forward.iter().position(|n| n == name).unwrap();
+        assert_eq!(position("d"), position("c") + 1);
+    }
+}
@@ -0,0 +1,134 @@
+//! Registry of chain analyzers, used to pick an analyzer for a source file.
+
+use crate::traits::ChainAnalyzer;
+use std::path::Path;
+
+/// Crates whose use marks a Rust source file as a Solana program.
+const SOLANA_CRATES: [&str; 2] = ["solana_program", "anchor_lang"];
+
+/// Infer the target chain of a source file from its extension and content.
+///
+/// `.sol` is EVM and `.move` is Move; `.rs` is Solana only when it uses
+/// `solana_program` or `anchor_lang`. Returns `None` when the file could
+/// belong to any chain, e.g. a Rust file without those imports.
+pub fn detect_chain(path: &Path, content: &str) -> Option<&'static str> {
+    match path.extension()?.to_str()? {
+        "sol" => Some("evm"),
+        "move" => Some("move"),
+        "rs" => SOLANA_CRATES
+            .iter()
+            .any(|krate| content.contains(krate))
+            .then_some("solana"),
+        _ => None,
+    }
+}
+
+/// Holds the available analyzers and looks them up by chain or file extension.
+#[derive(Default)]
+pub struct ChainAnalyzerRegistry {
+    analyzers: Vec<Box<dyn ChainAnalyzer>>,
+}
+
+impl ChainAnalyzerRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an analyzer. Earlier registrations win on conflicting lookups.
+    pub fn register(&mut self, analyzer: Box<dyn ChainAnalyzer>) {
+        self.analyzers.push(analyzer);
+    }
+
+    /// Find the analyzer handling files with extension `ext` (with or without the leading dot).
+    pub fn analyzer_for_extension(&self, ext: &str) -> Option<&dyn ChainAnalyzer> {
+        let ext = ext.trim_start_matches('.');
+        self.analyzers
+            .iter()
+            .find(|analyzer| {
+                analyzer
+                    .supported_extensions()
+                    .iter()
+                    .any(|supported| supported.trim_start_matches('.') == ext)
+            })
+            .map(|analyzer| analyzer.as_ref())
+    }
+
+    /// Find the analyzer for a chain identifier.
+    pub fn analyzer_for_chain(&self, chain: &str) -> Option<&dyn ChainAnalyzer> {
+        self.analyzers
+            .iter()
+            .find(|analyzer| analyzer.chain() == chain)
+            .map(|analyzer| analyzer.as_ref())
+    }
+
+    /// Chain identifiers of all registered analyzers.
+    pub fn chains(&self) -> Vec<&str> {
+        self.analyzers
+            .iter()
+            .map(|analyzer| analyzer.chain())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Result;
+    use crate::model::ProgramModel;
+
+    struct StubAnalyzer(&'static str, &'static [&'static str]);
+
+    impl ChainAnalyzer for StubAnalyzer {
+        fn analyze(&self, path: &Path) -> Result<ProgramModel> {
+            Ok(ProgramModel::new(
+                "stub".to_string(),
+                self.0.to_string(),
+                path.display().to_string(),
+            ))
+        }
+
+        fn chain(&self) -> &str {
+            self.0
+        }
+
+        fn supported_extensions(&self) -> &'static [&'static str] {
+            self.1
+        }
+    }
+
+    #[test]
+    fn test_registry_lookup() {
+        let mut registry = ChainAnalyzerRegistry::new();
+        registry.register(Box::new(StubAnalyzer("solana", &[".rs"])));
+        registry.register(Box::new(StubAnalyzer("evm", &[".sol"])));
+
+        assert_eq!(
+            registry.analyzer_for_extension(".sol").unwrap().chain(),
+            "evm"
+        );
+        assert_eq!(
+            registry.analyzer_for_extension("rs").unwrap().chain(),
+            "solana"
+        );
+        assert!(registry.analyzer_for_extension(".move").is_none());
+        assert_eq!(registry.analyzer_for_chain("evm").unwrap().chain(), "evm");
+        assert_eq!(registry.chains(), vec!["solana", "evm"]);
+    }
+
+    #[test]
+    fn test_detect_chain() {
+        assert_eq!(detect_chain(Path::new("Vault.sol"), ""), Some("evm"));
+        assert_eq!(detect_chain(Path::new("coin.move"), ""), Some("move"));
+        assert_eq!(
+            detect_chain(Path::new("lib.rs"), "use solana_program::pubkey::Pubkey;"),
+            Some("solana")
+        );
+        assert_eq!(
+            detect_chain(Path::new("lib.rs"), "use anchor_lang::prelude::*;"),
+            Some("solana")
+        );
+        assert_eq!(detect_chain(Path::new("utils.rs"), "fn helper() {}"), None);
+        assert_eq!(detect_chain(Path::new("README"), ""), None);
+    }
+}
@@ -3,10 +3,16 @@
 //! This module validates code before build to prevent known vulnerabilities.
 
 use crate::attack_patterns::{AttackPattern, AttackPatternDB};
-use std::path::Path;
+use crate::model::{ArithmeticOp, FunctionModel, OverflowProtection, ProgramModel};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+#[cfg(feature = "git-blame")]
+use std::time::{Duration, UNIX_EPOCH};
 
 /// Security validation report.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityReport {
     /// Critical vulnerabilities found.
     pub critical_issues: Vec<SecurityIssue>,
@@ -22,23 +28,280 @@ pub struct SecurityReport {
     pub risk_score: u32,
 }
 
+impl SecurityReport {
+    /// Merge reports from several analyzed files into one.
+    ///
+    /// Issues are de-duplicated by `(attack_pattern, location)`. The merged
+    /// risk score is the maximum of the individual scores rather than their
+    /// sum, so many small files do not inflate it.
+    pub fn merge(reports: Vec<SecurityReport>) -> SecurityReport {
+        let mut seen = BTreeSet::new();
+        let mut merged = SecurityReport {
+            critical_issues: Vec::new(),
+            high_issues: Vec::new(),
+            medium_issues: Vec::new(),
+            low_issues: Vec::new(),
+            passed: true,
+            risk_score: 0,
+        };
+
+        for report in reports {
+            merged.risk_score = merged.risk_score.max(report.risk_score);
+
+            let issues = report
+                .critical_issues
+                .into_iter()
+                .chain(report.high_issues)
+                .chain(report.medium_issues)
+                .chain(report.low_issues);
+
+            for issue in issues {
+                if seen.insert((issue.attack_pattern.clone(), issue.location.to_string())) {
+                    merged.push(issue);
+                }
+            }
+        }
+
+        merged.passed = RiskPolicy::default().passes(&merged);
+        merged
+    }
+
+    /// Add an issue to the list matching its severity.
+    pub fn push(&mut self, issue: SecurityIssue) {
+        match issue.severity {
+            IssueSeverity::Critical => self.critical_issues.push(issue),
+            IssueSeverity::High => self.high_issues.push(issue),
+            IssueSeverity::Medium => self.medium_issues.push(issue),
+            IssueSeverity::Low => self.low_issues.push(issue),
+        }
+    }
+
+    /// Number of issues at a given severity.
+    pub fn count(&self, severity: IssueSeverity) -> usize {
+        match severity {
+            IssueSeverity::Critical => self.critical_issues.len(),
+            IssueSeverity::High => self.high_issues.len(),
+            IssueSeverity::Medium => self.medium_issues.len(),
+            IssueSeverity::Low => self.low_issues.len(),
+        }
+    }
+
+    /// Total number of issues across all severities.
+    pub fn issue_count(&self) -> usize {
+        self.critical_issues.len()
+            + self.high_issues.len()
+            + self.medium_issues.len()
+            + self.low_issues.len()
+    }
+
+    /// Earliest `introduced_at` among the report's issues; `None` unless
+    /// some issue has been dated with `enrich_with_git_blame`.
+    pub fn oldest_unresolved_issue(&self) -> Option<SystemTime> {
+        self.critical_issues
+            .iter()
+            .chain(&self.high_issues)
+            .chain(&self.medium_issues)
+            .chain(&self.low_issues)
+            .filter_map(|issue| issue.introduced_at)
+            .min()
+    }
+}
+
+/// Set each issue's `introduced_at` to the author time of the last commit
+/// touching its line, as reported by `git blame`.
+///
+/// Relative issue paths are resolved against `repo_root`. Issues whose line
+/// cannot be blamed (no repository, untracked file, uncommitted line) are
+/// left undated.
+#[cfg(feature = "git-blame")]
+pub fn enrich_with_git_blame(issues: &mut [SecurityIssue], repo_root: &Path) {
+    for issue in issues.iter_mut() {
+        issue.introduced_at = blame_author_time(repo_root, &issue.location);
+    }
+}
+
+/// Author time of the commit that last changed `location`'s line.
+#[cfg(feature = "git-blame")]
+fn blame_author_time(repo_root: &Path, location: &Location) -> Option<SystemTime> {
+    let range = format!("{},{}", location.line, location.line);
+    let output = std::process::Command::new("git")
+        .arg("blame")
+        .arg("--porcelain")
+        .arg("-L")
+        .arg(&range)
+        .arg("--")
+        .arg(repo_root.join(&location.file))
+        .current_dir(repo_root)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // Uncommitted lines are attributed to the all-zero commit
+    let porcelain = String::from_utf8_lossy(&output.stdout);
+    if porcelain.starts_with("0000000000000000000000000000000000000000") {
+        return None;
+    }
+    let seconds = porcelain
+        .lines()
+        .find_map(|line| line.strip_prefix("author-time "))?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
 /// A detected security issue.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityIssue {
     /// Attack pattern involved.
     pub attack_pattern: String,
-    /// Location in code (file:line).
-    pub location: String,
+    /// Location in code.
+    pub location: Location,
     /// Description of the issue.
     pub description: String,
     /// Suggested fix.
     pub suggested_fix: String,
     /// Severity level.
     pub severity: IssueSeverity,
+    /// Function containing the issue, when attributed against a program model.
+    #[serde(default)]
+    pub function: Option<String>,
+    /// When the offending line was last committed, from `git blame`.
+    #[serde(default)]
+    pub introduced_at: Option<SystemTime>,
+}
+
+/// Where a security issue was found.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    /// Source file.
+    pub file: PathBuf,
+    /// 1-based line number.
+    pub line: u32,
+    /// 1-based column of the offending token, when known.
+    pub column: Option<u32>,
+    /// Surrounding source lines (up to two either side).
+    pub snippet: String,
+}
+
+impl Location {
+    /// Number of context lines included on each side of the snippet.
+    pub const SNIPPET_CONTEXT: u32 = 2;
+
+    /// Line number of the first snippet line.
+    pub fn snippet_start(&self) -> u32 {
+        self.line.saturating_sub(Self::SNIPPET_CONTEXT).max(1)
+    }
+
+    /// Location of the token at byte offset `offset` on zero-based line `line_idx`.
+    fn in_source(file: &str, lines: &[&str], line_idx: usize, offset: Option<usize>) -> Self {
+        let column = offset.map(|offset| lines[line_idx][..offset].chars().count() as u32 + 1);
+        let context = Self::SNIPPET_CONTEXT as usize;
+        let first = line_idx.saturating_sub(context);
+        let last = (line_idx + context + 1).min(lines.len());
+
+        Self {
+            file: PathBuf::from(file),
+            line: line_idx as u32 + 1,
+            column,
+            snippet: lines[first..last].join("\n"),
+        }
+    }
+
+    /// Location of the token starting at byte `offset` of the mapped source.
+    fn at_byte(file: &str, map: &SourceMap<'_>, offset: usize) -> Self {
+        let (line, _) = map.line_col_for_byte(offset);
+        let line_start = map.line_start(line - 1);
+        Self::in_source(file, &map.lines(), line - 1, Some(offset - line_start))
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        Ok(())
+    }
+}
+
+/// Byte offsets of the lines in a source file.
+///
+/// Lines end at `\n`; a trailing `\r` and a leading byte order mark are not
+/// part of any line, so `\r\n` files and BOM-prefixed files map the same way
+/// as plain text.
+#[derive(Debug, Clone)]
+pub struct SourceMap<'a> {
+    source: &'a str,
+    /// Byte offset where each line starts.
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourceMap<'a> {
+    /// Index the lines of `source`.
+    pub fn new(source: &'a str) -> Self {
+        let first = if source.starts_with('\u{feff}') {
+            '\u{feff}'.len_utf8()
+        } else {
+            0
+        };
+        let line_starts = std::iter::once(first)
+            .chain(
+                source
+                    .match_indices('\n')
+                    .map(|(offset, _)| offset + 1)
+                    .filter(|&start| start < source.len()),
+            )
+            .collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Number of lines.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Byte offset where zero-based line `idx` starts.
+    pub fn line_start(&self, idx: usize) -> usize {
+        self.line_starts[idx]
+    }
+
+    /// Text of zero-based line `idx`, without its line ending.
+    pub fn line(&self, idx: usize) -> &'a str {
+        let end = self
+            .line_starts
+            .get(idx + 1)
+            .map_or(self.source.len(), |next| next - 1);
+        let text = &self.source[self.line_starts[idx]..end];
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        text.strip_suffix('\r').unwrap_or(text)
+    }
+
+    /// Text of every line.
+    pub fn lines(&self) -> Vec<&'a str> {
+        (0..self.line_count()).map(|idx| self.line(idx)).collect()
+    }
+
+    /// 1-based line and column (in characters) of byte `offset`.
+    pub fn line_col_for_byte(&self, offset: usize) -> (usize, usize) {
+        let idx = self
+            .line_starts
+            .partition_point(|&start| start <= offset)
+            .saturating_sub(1);
+        let start = self.line_starts[idx].min(offset);
+        (idx + 1, self.source[start..offset].chars().count() + 1)
+    }
 }
 
 /// Issue severity level.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum IssueSeverity {
     /// Can cause total loss of funds.
     Critical = 4,
@@ -50,6 +313,30 @@ pub enum IssueSeverity {
     Low = 1,
 }
 
+impl IssueSeverity {
+    /// Severity bucket for a CVSS score.
+    pub fn from_cvss(score: f32) -> Self {
+        match score {
+            s if s >= 9.0 => Self::Critical,
+            s if s >= 7.0 => Self::High,
+            s if s >= 5.0 => Self::Medium,
+            _ => Self::Low,
+        }
+    }
+
+    /// The next lower severity (`Low` stays `Low`).
+    pub fn downgraded(self) -> Self {
+        match self {
+            Self::Critical => Self::High,
+            Self::High => Self::Medium,
+            Self::Medium | Self::Low => Self::Low,
+        }
+    }
+}
+
+/// Modifiers recognized as restricting a function to its owner or admins.
+pub const OWNER_MODIFIERS: [&str; 4] = ["onlyOwner", "onlyAdmin", "onlyRole", "onlyGovernance"];
+
 impl std::fmt::Display for IssueSeverity {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -61,19 +348,95 @@ impl std::fmt::Display for IssueSeverity {
     }
 }
 
+/// Scoring policy deciding a report's risk score and pass/fail status.
+///
+/// Loaded from the `[risk]` table of `invar.toml`; missing fields fall back
+/// to the defaults (25/15/8/3 weights, no critical or high issues allowed).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskPolicy {
+    /// Score contributed by each issue of a given severity.
+    pub weights: BTreeMap<IssueSeverity, u32>,
+    /// Highest risk score (0-100) that still passes.
+    pub pass_threshold: u32,
+    /// Maximum number of issues allowed per severity; unlisted severities are unlimited.
+    pub max_allowed: BTreeMap<IssueSeverity, usize>,
+}
+
+impl Default for RiskPolicy {
+    fn default() -> Self {
+        Self {
+            weights: BTreeMap::from([
+                (IssueSeverity::Critical, 25),
+                (IssueSeverity::High, 15),
+                (IssueSeverity::Medium, 8),
+                (IssueSeverity::Low, 3),
+            ]),
+            pass_threshold: 100,
+            max_allowed: BTreeMap::from([(IssueSeverity::Critical, 0), (IssueSeverity::High, 0)]),
+        }
+    }
+}
+
+impl RiskPolicy {
+    /// Weighted risk score for a report, capped at 100.
+    pub fn score(&self, report: &SecurityReport) -> u32 {
+        self.weights
+            .iter()
+            .map(|(severity, weight)| weight.saturating_mul(report.count(*severity) as u32))
+            .fold(0u32, u32::saturating_add)
+            .min(100)
+    }
+
+    /// Whether a report satisfies this policy.
+    pub fn passes(&self, report: &SecurityReport) -> bool {
+        self.score(report) <= self.pass_threshold
+            && self
+                .max_allowed
+                .iter()
+                .all(|(severity, max)| report.count(*severity) <= *max)
+    }
+}
+
+impl std::fmt::Display for RiskPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "weights")?;
+        for (severity, weight) in self.weights.iter().rev() {
+            write!(f, " {}={}", severity.to_string().to_lowercase(), weight)?;
+        }
+        write!(f, "; pass if score <= {}", self.pass_threshold)?;
+        for (severity, max) in self.max_allowed.iter().rev() {
+            write!(f, ", {} <= {}", severity.to_string().to_lowercase(), max)?;
+        }
+        Ok(())
+    }
+}
+
 /// Security validator for code before building.
 pub struct SecurityValidator {
     attack_db: AttackPatternDB,
+    policy: RiskPolicy,
 }
 
 impl SecurityValidator {
     /// Create a new security validator.
     pub fn new() -> Self {
+        Self::with_policy(RiskPolicy::default())
+    }
+
+    /// Create a security validator that scores reports with `policy`.
+    pub fn with_policy(policy: RiskPolicy) -> Self {
         Self {
             attack_db: AttackPatternDB::new(),
+            policy,
         }
     }
 
+    /// The scoring policy in effect.
+    pub fn policy(&self) -> &RiskPolicy {
+        &self.policy
+    }
+
     /// Validate code from a file.
     pub fn validate_file(&self, path: &Path, chain: &str) -> Result<SecurityReport, String> {
         let code =
@@ -108,23 +471,134 @@ impl SecurityValidator {
             }
         }
 
-        // Calculate risk score
-        let risk_score = (critical_issues.len() as u32 * 25
-            + high_issues.len() as u32 * 15
-            + medium_issues.len() as u32 * 8
-            + low_issues.len() as u32 * 3)
-            .min(100);
-
-        let passed = critical_issues.is_empty() && high_issues.is_empty();
-
-        Ok(SecurityReport {
+        let mut report = SecurityReport {
             critical_issues,
             high_issues,
             medium_issues,
             low_issues,
-            passed,
-            risk_score,
-        })
+            passed: true,
+            risk_score: 0,
+        };
+
+        // Score and pass/fail come from the configured policy
+        report.risk_score = self.policy.score(&report);
+        report.passed = self.policy.passes(&report);
+
+        Ok(report)
+    }
+
+    /// Validate code using facts from its analyzed program model.
+    ///
+    /// On Solana, account fields updated with unchecked arithmetic are
+    /// reported as integer overflow; on Move, the Move-specific rules run
+    /// over the analyzed functions and resources. Integer overflow findings
+    /// inside functions whose arithmetic is fully checked
+    /// (`OverflowProtection::Safe`, as the Solana analyzer classifies them)
+    /// are dropped, and access control findings inside functions guarded by
+    /// an [`OWNER_MODIFIERS`] modifier are downgraded one severity level.
+    pub fn validate_with_model(
+        &self,
+        code: &str,
+        file_path: &str,
+        chain: &str,
+        program: &ProgramModel,
+    ) -> Result<SecurityReport, String> {
+        let mut report = self.validate_code(code, file_path, chain)?;
+        let lines: Vec<&str> = code.lines().collect();
+        if chain == "solana" {
+            for issue in self.check_unchecked_arithmetic(&lines, file_path, program) {
+                report.push(issue);
+            }
+        }
+        if chain == "move" {
+            for pattern in self.attack_db.patterns_for_chain(chain) {
+                if pattern.affected_chains == ["move"] {
+                    for issue in self.check_move_pattern(&lines, file_path, pattern, program) {
+                        report.push(issue);
+                    }
+                }
+            }
+        }
+
+        let overflow_pattern = self
+            .attack_db
+            .get_pattern("integer_overflow")
+            .map(|pattern| pattern.name.clone());
+        let in_safe_function = |issue: &SecurityIssue| {
+            Some(&issue.attack_pattern) == overflow_pattern.as_ref()
+                && enclosing_function(&lines, issue.location.line as usize - 1)
+                    .and_then(|name| program.functions.get(name))
+                    .is_some_and(|func| func.overflow_protection == OverflowProtection::Safe)
+        };
+
+        let access_pattern = self
+            .attack_db
+            .get_pattern("access_control_bypass")
+            .map(|pattern| pattern.name.clone());
+        let owner_guarded = |issue: &SecurityIssue| {
+            Some(&issue.attack_pattern) == access_pattern.as_ref()
+                && innermost_function(program, issue.location.line as usize).is_some_and(|func| {
+                    func.modifiers
+                        .iter()
+                        .any(|modifier| OWNER_MODIFIERS.contains(&modifier.as_str()))
+                })
+        };
+
+        let mut guarded = Vec::new();
+        for issues in [
+            &mut report.critical_issues,
+            &mut report.high_issues,
+            &mut report.medium_issues,
+            &mut report.low_issues,
+        ] {
+            issues.retain(|issue| !in_safe_function(issue));
+            let (owner_only, rest) = std::mem::take(issues).into_iter().partition(owner_guarded);
+            *issues = rest;
+            guarded.extend(owner_only);
+        }
+        for mut issue in guarded {
+            issue.severity = issue.severity.downgraded();
+            report.push(issue);
+        }
+
+        if chain == "solana" {
+            for issue in self.check_pda_seeds(&lines, file_path, program) {
+                report.push(issue);
+            }
+            for issue in self.check_cpi_mutations(&lines, file_path, program) {
+                report.push(issue);
+            }
+        }
+
+        report.risk_score = self.policy.score(&report);
+        report.passed = self.policy.passes(&report);
+        Ok(report)
+    }
+
+    /// Validate a program's source, attributing each issue to the innermost
+    /// function whose span contains it.
+    pub fn validate_program(
+        &self,
+        program: &ProgramModel,
+        source: &str,
+    ) -> Result<SecurityReport, String> {
+        let mut report =
+            self.validate_with_model(source, &program.source_path, &program.chain, program)?;
+
+        for issues in [
+            &mut report.critical_issues,
+            &mut report.high_issues,
+            &mut report.medium_issues,
+            &mut report.low_issues,
+        ] {
+            // Model-based findings may already name their function
+            for issue in issues.iter_mut().filter(|issue| issue.function.is_none()) {
+                issue.function = innermost_function(program, issue.location.line as usize)
+                    .map(|func| func.name.clone());
+            }
+        }
+
+        Ok(report)
     }
 
     /// Check code against a specific attack pattern.
@@ -139,41 +613,346 @@ impl SecurityValidator {
         // Special handling for reentrancy: need to check state update AFTER external call
         if pattern.id == "reentrancy" {
             issues.extend(self.check_reentrancy(code, file_path, pattern));
+            issues.extend(self.check_missing_nonreentrant_guard(code, file_path, pattern));
+        } else if pattern.affected_chains == ["move"] {
+            // Move-specific rules read the analyzed functions; see validate_with_model
         } else {
-            // Generic pattern matching for other attacks
-            for (line_num, line) in code.lines().enumerate() {
-                for vulnerable_pattern in &pattern.vulnerable_patterns {
-                    if line.contains(vulnerable_pattern.as_str()) {
-                        let severity = match pattern.cvss_score {
-                            s if s >= 9.0 => IssueSeverity::Critical,
-                            s if s >= 7.0 => IssueSeverity::High,
-                            s if s >= 5.0 => IssueSeverity::Medium,
-                            _ => IssueSeverity::Low,
-                        };
-
-                        issues.push(SecurityIssue {
-                            attack_pattern: pattern.name.clone(),
-                            location: format!("{}:{}", file_path, line_num + 1),
-                            description: format!(
-                                "Potential {} vulnerability detected. {}",
-                                pattern.name, pattern.description
-                            ),
-                            suggested_fix: format!(
-                                "Apply defensive invariant: {}",
-                                pattern
-                                    .defensive_invariants
-                                    .first()
-                                    .unwrap_or(&"Review code".to_string())
-                            ),
-                            severity,
-                        });
-                    }
+            let map = SourceMap::new(code);
+
+            // Generic pattern matching for other attacks: the first match of
+            // each vulnerable pattern per line, ordered by line
+            let mut matches: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+            for (idx, vulnerable_pattern) in pattern.vulnerable_patterns.iter().enumerate() {
+                for (offset, _) in code.match_indices(vulnerable_pattern.as_str()) {
+                    let (line, _) = map.line_col_for_byte(offset);
+                    matches.entry((line, idx)).or_insert(offset);
                 }
             }
+
+            for offset in matches.into_values() {
+                let severity = IssueSeverity::from_cvss(pattern.cvss_score);
+
+                issues.push(SecurityIssue {
+                    attack_pattern: pattern.name.clone(),
+                    location: Location::at_byte(file_path, &map, offset),
+                    description: format!(
+                        "Potential {} vulnerability detected. {}",
+                        pattern.name, pattern.description
+                    ),
+                    suggested_fix: format!(
+                        "Apply defensive invariant: {}",
+                        pattern
+                            .defensive_invariants
+                            .first()
+                            .unwrap_or(&"Review code".to_string())
+                    ),
+                    severity,
+                    function: None,
+                    introduced_at: None,
+                });
+            }
         }
         issues
     }
 
+    /// Apply a Move-specific rule to each analyzed function (or resource) of
+    /// a module.
+    fn check_move_pattern(
+        &self,
+        lines: &[&str],
+        file_path: &str,
+        pattern: &AttackPattern,
+        program: &ProgramModel,
+    ) -> Vec<SecurityIssue> {
+        // (line index, byte offset of the offending token, detail)
+        let mut findings: Vec<(usize, usize, String)> = Vec::new();
+
+        match pattern.id.as_str() {
+            "move_missing_signer" => {
+                for func in program.functions.values().filter(|f| f.is_entry_point) {
+                    if func.parameters.iter().any(|param| param.contains("signer")) {
+                        continue;
+                    }
+                    if let Some((idx, offset)) =
+                        find_in_function(lines, func, &MOVE_GLOBAL_MUTATIONS)
+                    {
+                        findings.push((
+                            idx,
+                            offset,
+                            format!("entry function `{}` takes no signer", func.name),
+                        ));
+                    }
+                }
+            }
+            "move_unconstrained_resource" => {
+                for var in program
+                    .state_vars
+                    .values()
+                    .filter(|v| v.abilities.is_empty())
+                {
+                    let published = lines.iter().any(|l| {
+                        l.contains("move_to") && l.contains(&format!("{} {{", var.name))
+                            || l.contains(&format!("move_to<{}>", var.name))
+                    });
+                    let declaration = lines.iter().position(|l| {
+                        l.trim_start()
+                            .strip_prefix("struct ")
+                            .is_some_and(|rest| rest.starts_with(var.name.as_str()))
+                    });
+                    if let (true, Some(idx)) = (published, declaration) {
+                        findings.push((
+                            idx,
+                            lines[idx].len() - lines[idx].trim_start().len(),
+                            format!("struct `{}` declares no abilities", var.name),
+                        ));
+                    }
+                }
+            }
+            "move_arbitrary_borrow" => {
+                for func in program.functions.values() {
+                    for addr in address_params(func) {
+                        let needle = format!(">({})", addr);
+                        for idx in function_lines(lines, func) {
+                            if let Some(offset) = lines[idx].find("borrow_global_mut") {
+                                if lines[idx].contains(&needle) {
+                                    findings.push((
+                                        idx,
+                                        offset,
+                                        format!(
+                                            "`{}` borrows at caller-provided `{}`",
+                                            func.name, addr
+                                        ),
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            "move_unchecked_arithmetic" => {
+                for func in program.functions.values() {
+                    for idx in function_lines(lines, func).skip(1) {
+                        let line = lines[idx];
+                        if line.contains("checked_") || !line.contains('=') {
+                            continue;
+                        }
+                        if let Some(offset) = [" + ", " - ", " * "]
+                            .iter()
+                            .filter_map(|op| line.find(op))
+                            .min()
+                        {
+                            findings.push((
+                                idx,
+                                offset + 1,
+                                format!("unchecked arithmetic in `{}`", func.name),
+                            ));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        findings
+            .into_iter()
+            .map(|(idx, offset, detail)| SecurityIssue {
+                attack_pattern: pattern.name.clone(),
+                location: Location::in_source(file_path, lines, idx, Some(offset)),
+                description: format!(
+                    "Potential {} vulnerability detected ({}). {}",
+                    pattern.name, detail, pattern.description
+                ),
+                suggested_fix: format!(
+                    "Apply defensive invariant: {}",
+                    pattern
+                        .defensive_invariants
+                        .first()
+                        .map(String::as_str)
+                        .unwrap_or("Review code")
+                ),
+                severity: IssueSeverity::from_cvss(pattern.cvss_score),
+                function: None,
+                introduced_at: None,
+            })
+            .collect()
+    }
+
+    /// Flag PDAs derived from caller-supplied seeds.
+    fn check_pda_seeds(
+        &self,
+        lines: &[&str],
+        file_path: &str,
+        program: &ProgramModel,
+    ) -> Vec<SecurityIssue> {
+        let Some(pattern) = self.attack_db.get_pattern("solana_pda_seed_manipulation") else {
+            return Vec::new();
+        };
+
+        program
+            .pda_patterns
+            .iter()
+            .filter_map(|pda| {
+                let inputs: Vec<&str> = pda.user_inputs().collect();
+                if inputs.is_empty() {
+                    return None;
+                }
+                Some(SecurityIssue {
+                    attack_pattern: pattern.name.clone(),
+                    location: Location::in_source(
+                        file_path,
+                        lines,
+                        pda.line.saturating_sub(1),
+                        None,
+                    ),
+                    description: format!(
+                        "Potential {} vulnerability detected (`{}` seeds use {}). {}",
+                        pattern.name,
+                        pda.function_name,
+                        inputs.join(", "),
+                        pattern.description
+                    ),
+                    suggested_fix: format!(
+                        "Apply defensive invariant: {}",
+                        pattern.defensive_invariants[0]
+                    ),
+                    severity: IssueSeverity::from_cvss(pattern.cvss_score),
+                    function: Some(pda.function_name.clone()),
+                    introduced_at: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Flag state written after a cross-program invocation in the same function.
+    fn check_cpi_mutations(
+        &self,
+        lines: &[&str],
+        file_path: &str,
+        program: &ProgramModel,
+    ) -> Vec<SecurityIssue> {
+        let Some(pattern) = self.attack_db.get_pattern("solana_mutation_after_cpi") else {
+            return Vec::new();
+        };
+
+        program
+            .cpi_calls
+            .iter()
+            .filter_map(|cpi| {
+                let &first = cpi.mutations_after.first()?;
+                Some(SecurityIssue {
+                    attack_pattern: pattern.name.clone(),
+                    location: Location::in_source(file_path, lines, first.saturating_sub(1), None),
+                    description: format!(
+                        "Potential {} vulnerability detected (`{}` writes state after the CPI on line {}). {}",
+                        pattern.name, cpi.function_name, cpi.line, pattern.description
+                    ),
+                    suggested_fix: format!(
+                        "Apply defensive invariant: {}(\"{}\")",
+                        pattern.defensive_invariants[0], cpi.function_name
+                    ),
+                    severity: IssueSeverity::from_cvss(pattern.cvss_score),
+                    function: Some(cpi.function_name.clone()),
+                    introduced_at: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Flag account fields updated with bare `+`, `-` or `*`, which wrap in
+    /// release builds, from the analyzer's unchecked arithmetic sites.
+    fn check_unchecked_arithmetic(
+        &self,
+        lines: &[&str],
+        file_path: &str,
+        program: &ProgramModel,
+    ) -> Vec<SecurityIssue> {
+        let Some(pattern) = self.attack_db.get_pattern("integer_overflow") else {
+            return Vec::new();
+        };
+
+        program
+            .unchecked_arithmetic
+            .iter()
+            .map(|site| {
+                let (operation, invariant) = match site.op {
+                    ArithmeticOp::Add => ("addition", &pattern.defensive_invariants[0]),
+                    ArithmeticOp::Sub => ("subtraction", &pattern.defensive_invariants[1]),
+                    ArithmeticOp::Mul => ("multiplication", &pattern.defensive_invariants[0]),
+                };
+                SecurityIssue {
+                    attack_pattern: pattern.name.clone(),
+                    location: Location::in_source(
+                        file_path,
+                        lines,
+                        site.line.saturating_sub(1),
+                        None,
+                    ),
+                    description: format!(
+                        "Potential {} vulnerability detected (unchecked {} on `{}`). {}",
+                        pattern.name, operation, site.target, pattern.description
+                    ),
+                    suggested_fix: format!("Apply defensive invariant: {}", invariant),
+                    severity: IssueSeverity::from_cvss(pattern.cvss_score),
+                    function: enclosing_function(lines, site.line.saturating_sub(1))
+                        .map(str::to_string),
+                    introduced_at: None,
+                }
+            })
+            .collect()
+    }
+
+    /// Flag reentrancy across functions: when several Solidity functions
+    /// both write state and call out to other contracts, a call from one can
+    /// re-enter another, so each of them needs a `nonReentrant` guard.
+    ///
+    /// Reported once, at the first unguarded function, one severity level
+    /// below the pattern since the call order is not checked.
+    fn check_missing_nonreentrant_guard(
+        &self,
+        code: &str,
+        file_path: &str,
+        pattern: &AttackPattern,
+    ) -> Vec<SecurityIssue> {
+        let lines: Vec<&str> = code.lines().collect();
+        let risky: Vec<SolidityFunction> = solidity_functions(&lines)
+            .into_iter()
+            .filter(|func| func.writes_state(&lines) && func.calls_externally(&lines))
+            .collect();
+        let unguarded: Vec<&SolidityFunction> = risky
+            .iter()
+            .filter(|func| !func.header.contains("nonReentrant"))
+            .collect();
+        let (true, Some(first)) = (risky.len() > 1, unguarded.first()) else {
+            return Vec::new();
+        };
+
+        let quoted = |funcs: &mut dyn Iterator<Item = &SolidityFunction>| {
+            funcs
+                .map(|func| format!("`{}`", func.name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let offset = lines[first.body.start].find("function").unwrap_or(0);
+        vec![SecurityIssue {
+            attack_pattern: pattern.name.clone(),
+            location: Location::in_source(file_path, &lines, first.body.start, Some(offset)),
+            description: format!(
+                "Potential cross-function {} vulnerability detected ({} write state and make \
+                 external calls; {} lack `nonReentrant`). {}",
+                pattern.name,
+                quoted(&mut risky.iter()),
+                quoted(&mut unguarded.iter().copied()),
+                pattern.description
+            ),
+            suggested_fix: "Add the `nonReentrant` modifier to every function that writes state \
+                            and makes external calls"
+                .to_string(),
+            severity: IssueSeverity::from_cvss(pattern.cvss_score).downgraded(),
+            function: Some(first.name.clone()),
+            introduced_at: None,
+        }]
+    }
+
     /// Check for reentrancy by analyzing state update order.
     fn check_reentrancy(
         &self,
@@ -192,12 +971,13 @@ impl SecurityValidator {
             }
 
             // Check if line has external call
-            let has_external_call =
-                line.contains("transfer(") || line.contains(".call(") || line.contains(".send(");
-
-            if !has_external_call {
+            let Some(call_offset) = ["transfer(", ".call(", ".send("]
+                .iter()
+                .filter_map(|call| line.find(call))
+                .min()
+            else {
                 continue;
-            }
+            };
 
             // Look back up to 50 lines to find state updates
             let mut has_state_update_before = false;
@@ -219,7 +999,7 @@ impl SecurityValidator {
 
                 issues.push(SecurityIssue {
                     attack_pattern: pattern.name.clone(),
-                    location: format!("{}:{}", file_path, line_num + 1),
+                    location: Location::in_source(file_path, &lines, line_num, Some(call_offset)),
                     description: format!(
                         "Potential {} vulnerability detected. {}",
                         pattern.name, pattern.description
@@ -227,6 +1007,8 @@ impl SecurityValidator {
                     suggested_fix: "Apply defensive invariant: state_update_before_external_call"
                         .to_string(),
                     severity,
+                    function: None,
+                    introduced_at: None,
                 });
             }
         }
@@ -234,6 +1016,158 @@ impl SecurityValidator {
     }
 }
 
+/// Solidity calls that hand control to another contract.
+const EXTERNAL_CALLS: [&str; 5] = [".call(", ".call{", ".delegatecall(", ".transfer(", ".send("];
+
+/// A function found in Solidity source.
+struct SolidityFunction {
+    name: String,
+    /// Header text from `function` to the opening brace, modifiers included.
+    header: String,
+    /// Line indices from the header to the closing brace.
+    body: std::ops::Range<usize>,
+}
+
+impl SolidityFunction {
+    /// Whether the body assigns to something other than a new local.
+    fn writes_state(&self, lines: &[&str]) -> bool {
+        self.body.clone().skip(1).any(|idx| {
+            let line = lines[idx].trim();
+            if line.starts_with("//") {
+                return false;
+            }
+            if line.starts_with("delete ") || line.ends_with("++;") || line.ends_with("--;") {
+                return true;
+            }
+            let Some((target, _)) = ["+=", "-=", "*=", "/="]
+                .iter()
+                .find_map(|op| line.split_once(op))
+                .or_else(|| {
+                    line.match_indices('=')
+                        .map(|(offset, _)| offset)
+                        .find(|&offset| {
+                            let before = line[..offset].chars().last();
+                            let after = line[offset + 1..].chars().next();
+                            !matches!(before, Some('=' | '!' | '<' | '>'))
+                                && !matches!(after, Some('=' | '>'))
+                        })
+                        .map(|offset| (&line[..offset], &line[offset + 1..]))
+                })
+            else {
+                return false;
+            };
+            // `uint256 amount = ...` declares a local; `(bool ok, ) = ...` destructures
+            let target = target.trim();
+            !target.is_empty() && !target.contains(' ') && !target.starts_with('(')
+        })
+    }
+
+    /// Whether the body calls out to another contract.
+    fn calls_externally(&self, lines: &[&str]) -> bool {
+        self.body
+            .clone()
+            .any(|idx| EXTERNAL_CALLS.iter().any(|call| lines[idx].contains(call)))
+    }
+}
+
+/// Split Solidity source into functions using brace matching. Declarations
+/// without a body, as in interfaces, are left out.
+fn solidity_functions(lines: &[&str]) -> Vec<SolidityFunction> {
+    let mut functions = Vec::new();
+
+    for (start, line) in lines.iter().enumerate() {
+        let Some(after_function) = line.trim_start().strip_prefix("function ") else {
+            continue;
+        };
+        let name = after_function.split('(').next().unwrap_or("").trim();
+        if name.is_empty() {
+            continue;
+        }
+
+        let mut header = String::new();
+        let mut opened = false;
+        let mut depth = 0i32;
+        for (idx, text) in lines.iter().enumerate().skip(start) {
+            if !opened {
+                let before_brace = text.split('{').next().unwrap_or(text).trim();
+                header.push_str(before_brace);
+                header.push(' ');
+                opened = text.contains('{');
+                if !opened && before_brace.ends_with(';') {
+                    break;
+                }
+            }
+            depth += text.matches('{').count() as i32 - text.matches('}').count() as i32;
+            if opened && depth <= 0 {
+                functions.push(SolidityFunction {
+                    name: name.to_string(),
+                    header: header.trim_end().to_string(),
+                    body: start..idx + 1,
+                });
+                break;
+            }
+        }
+    }
+
+    functions
+}
+
+/// Move operations that mutate global storage.
+const MOVE_GLOBAL_MUTATIONS: [&str; 3] = ["borrow_global_mut", "move_to", "move_from"];
+
+/// Indices in `lines` of a function, from its header to its closing brace.
+fn function_lines(lines: &[&str], func: &FunctionModel) -> std::ops::Range<usize> {
+    func.span.map_or(0..0, |(start, end)| {
+        start.saturating_sub(1).min(lines.len())..end.min(lines.len())
+    })
+}
+
+/// First occurrence of any needle in `func`, as (line index, byte offset).
+fn find_in_function(
+    lines: &[&str],
+    func: &FunctionModel,
+    needles: &[&str],
+) -> Option<(usize, usize)> {
+    function_lines(lines, func).find_map(|idx| {
+        needles
+            .iter()
+            .filter_map(|needle| lines[idx].find(needle))
+            .min()
+            .map(|offset| (idx, offset))
+    })
+}
+
+/// Names of a Move function's parameters typed `address`.
+fn address_params(func: &FunctionModel) -> Vec<&str> {
+    func.parameters
+        .iter()
+        .filter_map(|param| {
+            let (name, ty) = param.split_once(':')?;
+            (ty.trim() == "address").then(|| name.trim())
+        })
+        .collect()
+}
+
+/// The function with the narrowest span containing `line` (1-based).
+fn innermost_function(program: &ProgramModel, line: usize) -> Option<&FunctionModel> {
+    program
+        .functions
+        .values()
+        .filter(|func| func.contains_line(line))
+        .min_by_key(|func| func.span.map(|(start, end)| end - start))
+}
+
+/// Name of the nearest function declared at or above `line`.
+fn enclosing_function<'a>(lines: &[&'a str], line: usize) -> Option<&'a str> {
+    lines.iter().take(line + 1).rev().find_map(|text| {
+        let rest = text.split("fn ").nth(1)?;
+        let name_end = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        (name_end > 0).then(|| &rest[..name_end])
+    })
+}
+
 impl Default for SecurityValidator {
     fn default() -> Self {
         Self::new()
@@ -247,7 +1181,7 @@ mod tests {
     #[test]
     fn test_security_validator_creation() {
         let validator = SecurityValidator::new();
-        assert_eq!(validator.attack_db.all_patterns().len(), 8);
+        assert_eq!(validator.attack_db.all_patterns().len(), 14);
     }
 
     #[test]
@@ -276,6 +1210,45 @@ mod tests {
         assert!(report.risk_score > 0);
     }
 
+    #[test]
+    fn test_cross_function_reentrancy_flagged() {
+        let validator = SecurityValidator::new();
+        let contract = |guard: &str| {
+            format!(
+                "contract Vault {{\n    function withdraw(uint256 amount) external{guard} {{\n        uint256 owed = balances[msg.sender];\n        (bool ok, ) = msg.sender.call{{value: amount}}(\"\");\n        balances[msg.sender] = owed - amount;\n    }}\n\n    function claim() external{guard} {{\n        rewards[msg.sender] = 0;\n        token.transfer(msg.sender, reward);\n    }}\n\n    function total() external view returns (uint256) {{\n        return supply;\n    }}\n}}\n"
+            )
+        };
+        let cross_function = |report: &SecurityReport| -> Vec<SecurityIssue> {
+            report
+                .high_issues
+                .iter()
+                .filter(|issue| issue.description.contains("cross-function"))
+                .cloned()
+                .collect()
+        };
+
+        let report = validator
+            .validate_code(&contract(""), "Vault.sol", "evm")
+            .unwrap();
+        let issues = cross_function(&report);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].function.as_deref(), Some("withdraw"));
+        assert_eq!(issues[0].location.line, 2);
+        assert!(issues[0]
+            .description
+            .contains("(`withdraw`, `claim` write state"));
+
+        let guarded = validator
+            .validate_code(&contract(" nonReentrant"), "Vault.sol", "evm")
+            .unwrap();
+        assert!(cross_function(&guarded).is_empty());
+
+        // A single function cannot re-enter another
+        let single = "function withdraw() external {\n    balances[msg.sender] = 0;\n    msg.sender.call{value: 1}(\"\");\n}\n";
+        let report = validator.validate_code(single, "Vault.sol", "evm").unwrap();
+        assert!(cross_function(&report).is_empty());
+    }
+
     #[test]
     fn test_chain_specific_validation() {
         let validator = SecurityValidator::new();
@@ -287,4 +1260,328 @@ mod tests {
         // Both chains should detect access control patterns
         assert!(evm_report.passed || solana_report.passed);
     }
+
+    #[test]
+    fn test_merge_reports_unions_issues() {
+        let validator = SecurityValidator::new();
+        let code = "fn transfer() { transfer_funds(); }";
+        let first = validator.validate_code(code, "a.rs", "evm").unwrap();
+        let second = validator.validate_code(code, "b.rs", "evm").unwrap();
+        let duplicate = first.clone();
+        let expected = first.critical_issues.len() + second.critical_issues.len();
+        let max_score = first.risk_score.max(second.risk_score);
+
+        let merged = SecurityReport::merge(vec![first, second, duplicate]);
+        assert_eq!(merged.critical_issues.len(), expected);
+        assert_eq!(merged.risk_score, max_score);
+        assert!(!merged.passed);
+    }
+
+    #[test]
+    fn test_policy_limits_medium_issues() {
+        let code = "random_number = block.timestamp;\n".repeat(6);
+
+        let default_report = SecurityValidator::new()
+            .validate_code(&code, "lottery.sol", "evm")
+            .unwrap();
+        assert_eq!(default_report.medium_issues.len(), 6);
+        assert_eq!(default_report.risk_score, 48);
+        assert!(default_report.passed);
+
+        let mut policy = RiskPolicy::default();
+        policy.max_allowed.insert(IssueSeverity::Medium, 5);
+        let strict_report = SecurityValidator::with_policy(policy)
+            .validate_code(&code, "lottery.sol", "evm")
+            .unwrap();
+        assert!(!strict_report.passed);
+    }
+
+    #[test]
+    fn test_policy_display() {
+        assert_eq!(
+            RiskPolicy::default().to_string(),
+            "weights critical=25 high=15 medium=8 low=3; pass if score <= 100, critical <= 0, high <= 0"
+        );
+    }
+
+    #[test]
+    fn test_overflow_skipped_for_checked_functions() {
+        let code = "fn deposit() {\n    // unchecked_addition\n}\nfn withdraw() {\n    // unchecked_subtraction\n}\n";
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "vault.rs".to_string(),
+        );
+        for (name, protection) in [
+            ("deposit", OverflowProtection::Safe),
+            ("withdraw", OverflowProtection::Unsafe),
+        ] {
+            program.add_function(FunctionModel {
+                name: name.to_string(),
+                parameters: Vec::new(),
+                return_type: None,
+                mutates: BTreeSet::new(),
+                reads: BTreeSet::new(),
+                is_entry_point: true,
+                is_pure: false,
+                overflow_protection: protection,
+                span: None,
+                modifiers: Vec::new(),
+                uses_low_level: false,
+                pre_conditions: Vec::new(),
+                post_conditions: Vec::new(),
+            });
+        }
+
+        let validator = SecurityValidator::new();
+        let unfiltered = validator.validate_code(code, "vault.rs", "solana").unwrap();
+        assert_eq!(unfiltered.high_issues.len(), 2);
+
+        let report = validator
+            .validate_with_model(code, "vault.rs", "solana", &program)
+            .unwrap();
+        assert_eq!(report.high_issues.len(), 1);
+        assert_eq!(report.high_issues[0].location.to_string(), "vault.rs:5:8");
+    }
+
+    #[test]
+    fn test_issue_location_has_column_and_snippet() {
+        let validator = SecurityValidator::new();
+        let code =
+            "fn a() {}\nfn b() {}\nfn withdraw() {\n    payable(msg.sender).transfer(amount);\n}\n";
+        let report = validator.validate_code(code, "vault.sol", "evm").unwrap();
+        let location = &report.critical_issues[0].location;
+
+        assert_eq!(location.to_string(), "vault.sol:4:25");
+        assert_eq!(location.column, Some(25));
+        assert!(location.snippet.contains("transfer(amount)"));
+        assert!(location.snippet.starts_with("fn b() {}"));
+    }
+
+    #[test]
+    fn test_issues_attributed_to_functions() {
+        let code = "contract Vault {\n    function deposit() public {\n        payable(msg.sender).transfer(amount);\n    }\n\n    function withdraw() public {\n        // unchecked_subtraction\n    }\n}\n";
+        let mut program = ProgramModel::new(
+            "Vault".to_string(),
+            "evm".to_string(),
+            "Vault.sol".to_string(),
+        );
+        for (name, span) in [("deposit", (2, 4)), ("withdraw", (6, 8))] {
+            program.add_function(FunctionModel {
+                name: name.to_string(),
+                parameters: Vec::new(),
+                return_type: None,
+                mutates: BTreeSet::new(),
+                reads: BTreeSet::new(),
+                is_entry_point: true,
+                is_pure: false,
+                overflow_protection: OverflowProtection::Unknown,
+                span: Some(span),
+                modifiers: Vec::new(),
+                uses_low_level: false,
+                pre_conditions: Vec::new(),
+                post_conditions: Vec::new(),
+            });
+        }
+
+        let report = SecurityValidator::new()
+            .validate_program(&program, code)
+            .unwrap();
+        let functions: Vec<_> = report
+            .critical_issues
+            .iter()
+            .chain(&report.high_issues)
+            .map(|issue| (issue.attack_pattern.as_str(), issue.function.as_deref()))
+            .collect();
+        assert!(functions.contains(&("Reentrancy", Some("deposit"))));
+        assert!(functions.contains(&("Integer Overflow/Underflow", Some("withdraw"))));
+    }
+
+    #[test]
+    fn test_owner_modifier_downgrades_access_control() {
+        let code = "contract Vault {\n    function sweep() public onlyOwner {\n        require(tx.origin != msg.sender);\n    }\n\n    function pause() public {\n        require(tx.origin != msg.sender);\n    }\n}\n";
+        let mut program = ProgramModel::new(
+            "Vault".to_string(),
+            "evm".to_string(),
+            "Vault.sol".to_string(),
+        );
+        for (name, span, modifiers) in [
+            ("sweep", (2, 4), vec!["onlyOwner"]),
+            ("pause", (6, 8), vec![]),
+        ] {
+            program.add_function(FunctionModel {
+                name: name.to_string(),
+                parameters: Vec::new(),
+                return_type: None,
+                mutates: BTreeSet::new(),
+                reads: BTreeSet::new(),
+                is_entry_point: true,
+                is_pure: false,
+                overflow_protection: OverflowProtection::Unknown,
+                span: Some(span),
+                modifiers: modifiers.into_iter().map(String::from).collect(),
+                uses_low_level: false,
+                pre_conditions: Vec::new(),
+                post_conditions: Vec::new(),
+            });
+        }
+
+        let report = SecurityValidator::new()
+            .validate_program(&program, code)
+            .unwrap();
+        let access_control = |issues: &[SecurityIssue]| {
+            issues
+                .iter()
+                .filter(|issue| issue.attack_pattern == "Access Control Bypass")
+                .map(|issue| issue.function.clone().unwrap_or_default())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(access_control(&report.critical_issues), vec!["pause"]);
+        assert_eq!(access_control(&report.high_issues), vec!["sweep"]);
+    }
+
+    #[test]
+    fn test_pda_user_seed_flagged() {
+        use crate::model::{PdaPattern, PdaSeedSource};
+
+        let code = "#[derive(Accounts)]\npub struct CreateVault<'info> {\n    #[account(seeds = [b\"vault\", creator.as_ref()], bump)]\n    pub vault: Account<'info, Vault>,\n}\n";
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "lib.rs".to_string(),
+        );
+        program.pda_patterns.push(PdaPattern {
+            function_name: "create_vault".to_string(),
+            seeds: vec![
+                PdaSeedSource::StaticBytes,
+                PdaSeedSource::UserInput("creator".to_string()),
+            ],
+            line: 3,
+        });
+
+        let report = SecurityValidator::new()
+            .validate_with_model(code, "lib.rs", "solana", &program)
+            .unwrap();
+        let issue = report
+            .high_issues
+            .iter()
+            .find(|issue| issue.attack_pattern == "PDA Seed Manipulation")
+            .expect("PDA issue reported");
+        assert_eq!(issue.location.line, 3);
+        assert_eq!(issue.function.as_deref(), Some("create_vault"));
+        assert!(issue.description.contains("creator"));
+    }
+
+    #[test]
+    fn test_mutation_after_cpi_flagged() {
+        use crate::model::CpiCall;
+
+        let code = "pub fn withdraw(vault: &mut Vault) -> ProgramResult {\n    invoke(&ix, &accounts)?;\n    vault.balance = 0;\n    Ok(())\n}\n";
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "lib.rs".to_string(),
+        );
+        program.cpi_calls.push(CpiCall {
+            function_name: "withdraw".to_string(),
+            line: 2,
+            mutations_after: vec![3],
+        });
+
+        let report = SecurityValidator::new()
+            .validate_with_model(code, "lib.rs", "solana", &program)
+            .unwrap();
+        let issue = report
+            .high_issues
+            .iter()
+            .find(|issue| issue.attack_pattern == "State Mutation After CPI")
+            .expect("CPI issue reported");
+        assert_eq!(issue.location.line, 3);
+        assert_eq!(issue.function.as_deref(), Some("withdraw"));
+        assert_eq!(
+            issue.suggested_fix,
+            "Apply defensive invariant: no_mutation_after_cpi(\"withdraw\")"
+        );
+
+        // A CPI as the last write is fine
+        program.cpi_calls[0].mutations_after.clear();
+        let report = SecurityValidator::new()
+            .validate_with_model(code, "lib.rs", "solana", &program)
+            .unwrap();
+        assert!(report
+            .high_issues
+            .iter()
+            .all(|issue| issue.attack_pattern != "State Mutation After CPI"));
+    }
+
+    #[test]
+    fn test_crlf_and_bom_locations_match_plain_text() {
+        let validator = SecurityValidator::new();
+        let plain =
+            "fn a() {}\nfn b() {}\nfn withdraw() {\n    payable(msg.sender).transfer(amount);\n}\n";
+        let windows = format!("\u{feff}{}", plain.replace('\n', "\r\n"));
+
+        let expected = validator.validate_code(plain, "vault.sol", "evm").unwrap();
+        let report = validator
+            .validate_code(&windows, "vault.sol", "evm")
+            .unwrap();
+        let location = &report.critical_issues[0].location;
+        assert_eq!(location.to_string(), "vault.sol:4:25");
+        assert_eq!(location, &expected.critical_issues[0].location);
+        assert!(!location.snippet.contains('\r'));
+    }
+
+    #[test]
+    fn test_source_map_line_col_for_byte() {
+        let map = SourceMap::new("\u{feff}ab\r\ncd\r\n");
+        assert_eq!(map.line_count(), 2);
+        assert_eq!(map.lines(), vec!["ab", "cd"]);
+        assert_eq!(map.line_col_for_byte(3), (1, 1));
+        assert_eq!(map.line_col_for_byte(8), (2, 2));
+    }
+
+    #[cfg(feature = "git-blame")]
+    #[test]
+    fn test_git_blame_dates_issues() {
+        use std::process::Command;
+        use std::time::Duration;
+
+        let dir = tempfile::tempdir().unwrap();
+        let code = "fn transfer() {\n    transfer_funds(); /* state update after */\n}\n";
+        std::fs::write(dir.path().join("vault.rs"), code).unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args([
+                    "-c",
+                    "user.name=invar",
+                    "-c",
+                    "user.email=invar@example.com",
+                ])
+                .args(args)
+                .current_dir(dir.path())
+                .status()
+                .unwrap();
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "-q"]);
+        git(&["add", "vault.rs"]);
+        git(&["commit", "-q", "-m", "Add vault"]);
+
+        let mut report = SecurityValidator::new()
+            .validate_code(code, "vault.rs", "evm")
+            .unwrap();
+        assert!(report.issue_count() > 0);
+        assert_eq!(report.oldest_unresolved_issue(), None);
+
+        enrich_with_git_blame(&mut report.critical_issues, dir.path());
+        enrich_with_git_blame(&mut report.high_issues, dir.path());
+        enrich_with_git_blame(&mut report.medium_issues, dir.path());
+        enrich_with_git_blame(&mut report.low_issues, dir.path());
+
+        let oldest = report.oldest_unresolved_issue().expect("issues dated");
+        let age = SystemTime::now()
+            .duration_since(oldest)
+            .unwrap_or(Duration::ZERO);
+        assert!(age < Duration::from_secs(300), "{:?}", age);
+    }
 }
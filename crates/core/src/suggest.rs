@@ -0,0 +1,86 @@
+//! "Did you mean" suggestions for misspelled names.
+
+/// Largest edit distance at which a known name is suggested.
+pub const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// Most suggestions reported for one name.
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// Known names within [`MAX_SUGGESTION_DISTANCE`] edits of `name`, closest
+/// first and alphabetically among equals, at most [`MAX_SUGGESTIONS`] of them.
+pub fn did_you_mean<'a>(name: &str, known: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+    let mut close: Vec<(usize, &String)> = known
+        .into_iter()
+        .filter(|candidate| candidate.as_str() != name)
+        .map(|candidate| (levenshtein(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE)
+        .collect();
+    close.sort();
+    close.dedup();
+    close
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+/// Render suggestions as `; did you mean 'a' or 'b'?`, or nothing.
+pub(crate) fn format_suggestions(suggestions: &[String]) -> String {
+    let quoted: Vec<String> = suggestions.iter().map(|s| format!("'{}'", s)).collect();
+    match quoted.as_slice() {
+        [] => String::new(),
+        [only] => format!("; did you mean {}?", only),
+        [rest @ .., last] => format!("; did you mean {} or {}?", rest.join(", "), last),
+    }
+}
+
+/// Single-character insertions, deletions and substitutions turning `a` into `b`.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = previous[j] + usize::from(ca != *cb);
+            current.push(substitute.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("totl_supply", "total_supply"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("same", "same"), 0);
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let known = names(&["total_supply", "total_debt", "fee", "fees", "feet", "feed"]);
+        assert_eq!(did_you_mean("totl_supply", &known), vec!["total_supply"]);
+        assert!(did_you_mean("qqqqqqqq", &known).is_empty());
+        // Closest first, then alphabetical, capped at three
+        assert_eq!(did_you_mean("fe", &known), vec!["fee", "feed", "fees"]);
+    }
+
+    #[test]
+    fn test_format_suggestions() {
+        assert_eq!(format_suggestions(&[]), "");
+        assert_eq!(format_suggestions(&names(&["a"])), "; did you mean 'a'?");
+        assert_eq!(
+            format_suggestions(&names(&["a", "b", "c"])),
+            "; did you mean 'a', 'b' or 'c'?"
+        );
+    }
+}
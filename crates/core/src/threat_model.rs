@@ -7,11 +7,13 @@
 //! 4. DSL sandbox escapes (expression validation)
 //! 5. Simulation side-effects (isolation verification)
 
-use crate::model::Expression;
+use crate::model::{depth, Expression, Invariant};
+use crate::provenance::{sha256_hex, ProvenanceHeader};
+use crate::visitor::ExpressionVisitor;
 use std::collections::BTreeMap;
 
 /// Threat model security configuration.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ThreatModelConfig {
     /// Require strict mutation detection (abort if uncertain)
     pub strict_mode: bool,
@@ -127,6 +129,74 @@ impl InjectionVerifier {
 
         Ok(())
     }
+
+    /// Verify that generated code compiles as a library crate.
+    ///
+    /// # Security Property
+    /// Catches injections that slip past pattern checks (e.g. a comment
+    /// terminator followed by new items) by running `rustc` on the code.
+    /// Uses `$RUSTC` when set.
+    #[cfg(feature = "compile-verification")]
+    pub fn verify_compiles(code: &str, extra_flags: &[&str]) -> ThreatResult<()> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "invar-verify-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let io_error = |e: std::io::Error| {
+            ThreatModelError::ReParseVerificationFailed(format!("failed to run rustc: {}", e))
+        };
+
+        std::fs::create_dir_all(&dir).map_err(io_error)?;
+        let source = dir.join("generated.rs");
+        let output = std::fs::write(&source, code).and_then(|_| {
+            std::process::Command::new(std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into()))
+                .args([
+                    "--edition",
+                    "2021",
+                    "--crate-type",
+                    "lib",
+                    "--emit",
+                    "metadata",
+                ])
+                .arg("--out-dir")
+                .arg(&dir)
+                .args(extra_flags)
+                .arg(&source)
+                .output()
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+        let output = output.map_err(io_error)?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(ThreatModelError::ReParseVerificationFailed(
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ))
+        }
+    }
+
+    /// Run the verifications enabled by `config` on generated code.
+    ///
+    /// Compilation is only checked when `re_parse_verification` is set and the
+    /// `compile-verification` feature is enabled.
+    pub fn verify_with_config(
+        config: &ThreatModelConfig,
+        generated_code: &str,
+    ) -> ThreatResult<()> {
+        Self::verify_scope_containment(generated_code)?;
+        #[cfg(feature = "compile-verification")]
+        if config.re_parse_verification {
+            Self::verify_compiles(generated_code, &[])?;
+        }
+        #[cfg(not(feature = "compile-verification"))]
+        let _ = config;
+        Ok(())
+    }
 }
 
 /// Defense 2: Macro tamper detection.
@@ -156,12 +226,22 @@ impl TamperDetector {
         format!("{:016x}", hasher.finish())
     }
 
-    /// Verify that the embedded hash matches expected checks.
+    /// Compute deterministic hash for a set of invariants from their content hashes.
+    ///
+    /// # Determinism Property
+    /// Order-independent, and unaffected by how the invariants were formatted
+    /// in source.
+    pub fn compute_invariant_hash(invariants: &[Invariant]) -> String {
+        let hashes: Vec<String> = invariants.iter().map(Invariant::content_hash).collect();
+        Self::compute_hash(&hashes)
+    }
+
+    /// Verify that the embedded hash matches the expected invariants.
     ///
     /// # Security Property
     /// Detects any tampering with invariant checks after macro expansion.
-    pub fn verify_tampering(generated_code: &str, expected_checks: &[String]) -> ThreatResult<()> {
-        let expected_hash = Self::compute_hash(expected_checks);
+    pub fn verify_tampering(generated_code: &str, expected: &[Invariant]) -> ThreatResult<()> {
+        let expected_hash = Self::compute_invariant_hash(expected);
 
         // Extract hash from generated code (look for INVAR_HASH: pattern)
         let hash_pattern = format!("INVAR_HASH: {}", expected_hash);
@@ -174,8 +254,51 @@ impl TamperDetector {
 
         Ok(())
     }
+
+    /// Verify that generated code's provenance header matches the inputs it
+    /// should have been generated from.
+    ///
+    /// # Security Property
+    /// Detects generated code that is stale or was produced from a different
+    /// invariant set, source file or threat model than claimed.
+    pub fn verify_provenance(
+        generated_code: &str,
+        expected: &ProvenanceHeader,
+    ) -> ThreatResult<()> {
+        let mismatches = ProvenanceHeader::parse(generated_code)?.mismatches(expected);
+        if !mismatches.is_empty() {
+            return Err(ThreatModelError::TamperDetected(format!(
+                "provenance mismatch: {}",
+                mismatches.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    /// Verify that generated code still hashes to the code hash in its
+    /// provenance header.
+    ///
+    /// # Security Property
+    /// Detects hand edits to generated checks, which leave the recorded
+    /// inputs unchanged.
+    pub fn verify_code_hash(generated_code: &str) -> ThreatResult<()> {
+        let recorded = ProvenanceHeader::parse(generated_code)?
+            .code_sha256
+            .ok_or_else(|| {
+                ThreatModelError::TamperDetected("provenance header has no code hash".to_string())
+            })?;
+        if sha256_hex(ProvenanceHeader::body(generated_code)) != recorded {
+            return Err(ThreatModelError::TamperDetected(
+                "generated code was edited after generation".to_string(),
+            ));
+        }
+        Ok(())
+    }
 }
 
+/// Deepest expression nesting the DSL sandbox accepts.
+pub const MAX_SANDBOX_DEPTH: usize = 64;
+
 /// Defense 3: DSL sandboxing.
 ///
 /// Validates that invariant expressions cannot escape the sandbox
@@ -191,7 +314,16 @@ impl DSLSandbox {
     /// - Don't call external code
     /// - Are deterministic (no randomness)
     /// - Have no side effects
+    /// - Nest no deeper than [`MAX_SANDBOX_DEPTH`]
     pub fn validate_expression(expr: &Expression) -> ThreatResult<()> {
+        let nesting = depth(expr);
+        if nesting > MAX_SANDBOX_DEPTH {
+            return Err(ThreatModelError::SandboxEscapeDetected(format!(
+                "expression nested {} levels deep (limit {})",
+                nesting, MAX_SANDBOX_DEPTH
+            )));
+        }
+
         // Check for dangerous patterns in variable names (common injection vectors)
         let forbidden_prefixes = ["file_", "io_", "extern_", "unsafe_"];
 
@@ -202,118 +334,74 @@ impl DSLSandbox {
         expr: &Expression,
         forbidden_prefixes: &[&str],
     ) -> ThreatResult<()> {
-        match expr {
-            Expression::Var(name) => {
-                for prefix in forbidden_prefixes {
-                    if name.to_lowercase().starts_with(prefix) {
-                        return Err(ThreatModelError::SandboxEscapeDetected(format!(
-                            "forbidden variable name: {}",
-                            name
-                        )));
-                    }
-                }
-                Ok(())
-            }
-
-            Expression::LayerVar { layer, var } => {
-                // Check both layer and variable names against forbidden prefixes
-                for prefix in forbidden_prefixes {
-                    if layer.to_lowercase().starts_with(prefix)
-                        || var.to_lowercase().starts_with(prefix)
-                    {
-                        return Err(ThreatModelError::SandboxEscapeDetected(format!(
-                            "forbidden layer/variable name: {}::{}",
-                            layer, var
-                        )));
-                    }
-                }
-                Ok(())
-            }
-
-            Expression::FunctionCall { name, args } => {
-                // Whitelist of allowed functions (purely computational, no side effects)
-                let allowed_functions = [
-                    "sum", "len", "min", "max", "abs", "mod", "div", "add", "sub", "mul", "and",
-                    "or", "not",
-                ];
-
-                if !allowed_functions.contains(&name.as_str()) {
-                    return Err(ThreatModelError::SandboxEscapeDetected(format!(
-                        "forbidden function call: {}",
-                        name
-                    )));
-                }
-
-                // Recursively check all arguments
-                for arg in args {
-                    Self::check_expression_recursive(arg, forbidden_prefixes)?;
-                }
-                Ok(())
-            }
+        SandboxVisitor { forbidden_prefixes }.visit_expression(expr)
+    }
+}
 
-            Expression::BinaryOp { left, op: _, right } => {
-                Self::check_expression_recursive(left, forbidden_prefixes)?;
-                Self::check_expression_recursive(right, forbidden_prefixes)?;
-                Ok(())
-            }
+/// Rejects forbidden identifiers and non-whitelisted function calls.
+struct SandboxVisitor<'a> {
+    forbidden_prefixes: &'a [&'a str],
+}
 
-            Expression::Logical { left, op: _, right } => {
-                Self::check_expression_recursive(left, forbidden_prefixes)?;
-                Self::check_expression_recursive(right, forbidden_prefixes)?;
-                Ok(())
-            }
+impl SandboxVisitor<'_> {
+    fn is_forbidden(&self, names: &[&str]) -> bool {
+        self.forbidden_prefixes.iter().any(|prefix| {
+            names
+                .iter()
+                .any(|name| name.to_lowercase().starts_with(prefix))
+        })
+    }
+}
 
-            Expression::Not(inner) => {
-                Self::check_expression_recursive(inner, forbidden_prefixes)?;
-                Ok(())
-            }
+impl ExpressionVisitor for SandboxVisitor<'_> {
+    type Error = ThreatModelError;
 
-            Expression::Tuple(exprs) => {
-                for e in exprs {
-                    Self::check_expression_recursive(e, forbidden_prefixes)?;
-                }
-                Ok(())
-            }
+    fn visit_var(&mut self, name: &str) -> ThreatResult<()> {
+        if self.is_forbidden(&[name]) {
+            return Err(ThreatModelError::SandboxEscapeDetected(format!(
+                "forbidden variable name: {}",
+                name
+            )));
+        }
+        Ok(())
+    }
 
-            Expression::PhaseQualifiedVar { phase, layer, var } => {
-                // Check phase, layer, and variable names against forbidden prefixes
-                for prefix in forbidden_prefixes {
-                    if phase.to_lowercase().starts_with(prefix)
-                        || layer.to_lowercase().starts_with(prefix)
-                        || var.to_lowercase().starts_with(prefix)
-                    {
-                        return Err(ThreatModelError::SandboxEscapeDetected(format!(
-                            "forbidden phase/layer/variable name: {}::{}::{}",
-                            phase, layer, var
-                        )));
-                    }
-                }
-                Ok(())
-            }
+    fn visit_layer_var(&mut self, layer: &str, var: &str) -> ThreatResult<()> {
+        // Check both layer and variable names against forbidden prefixes
+        if self.is_forbidden(&[layer, var]) {
+            return Err(ThreatModelError::SandboxEscapeDetected(format!(
+                "forbidden layer/variable name: {}::{}",
+                layer, var
+            )));
+        }
+        Ok(())
+    }
 
-            Expression::PhaseConstraint {
-                phase: _,
-                constraint,
-            } => {
-                // Check the constraint expression recursively
-                Self::check_expression_recursive(constraint, forbidden_prefixes)
-            }
+    fn visit_phase_var(&mut self, phase: &str, layer: &str, var: &str) -> ThreatResult<()> {
+        // Check phase, layer, and variable names against forbidden prefixes
+        if self.is_forbidden(&[phase, layer, var]) {
+            return Err(ThreatModelError::SandboxEscapeDetected(format!(
+                "forbidden phase/layer/variable name: {}::{}::{}",
+                phase, layer, var
+            )));
+        }
+        Ok(())
+    }
 
-            Expression::CrossPhaseRelation {
-                phase1: _,
-                expr1,
-                phase2: _,
-                expr2,
-                op: _,
-            } => {
-                // Check both phase expressions
-                Self::check_expression_recursive(expr1, forbidden_prefixes)?;
-                Self::check_expression_recursive(expr2, forbidden_prefixes)?;
-                Ok(())
-            }
+    fn visit_function_call(&mut self, name: &str, _args: &[Expression]) -> ThreatResult<()> {
+        // Whitelist of allowed functions (purely computational, no side effects)
+        let allowed_functions = [
+            "sum", "len", "min", "max", "abs", "mod", "div", "add", "sub", "mul", "and", "or",
+            "not",
+        ];
 
-            Expression::Boolean(_) | Expression::Int(_) => Ok(()),
+        if !allowed_functions.contains(&name) {
+            return Err(ThreatModelError::SandboxEscapeDetected(format!(
+                "forbidden function call: {}",
+                name
+            )));
         }
+        Ok(())
     }
 }
 
@@ -428,6 +516,18 @@ mod tests {
         assert!(InjectionVerifier::verify_scope_containment(unsafe_code).is_err());
     }
 
+    #[cfg(feature = "compile-verification")]
+    #[test]
+    fn test_verify_compiles_rejects_invalid_code() {
+        assert!(InjectionVerifier::verify_compiles("pub fn ok() -> u64 { 1 }", &[]).is_ok());
+
+        let injected = "pub fn check() { /* */ } fn evil( {}";
+        assert!(matches!(
+            InjectionVerifier::verify_compiles(injected, &[]),
+            Err(ThreatModelError::ReParseVerificationFailed(_))
+        ));
+    }
+
     #[test]
     fn test_tamper_hash_deterministic() {
         let checks1 = vec!["a".to_string(), "b".to_string()];
@@ -439,10 +539,60 @@ mod tests {
         assert_eq!(hash1, hash2);
     }
 
+    #[test]
+    fn test_verify_tampering_uses_content_hashes() {
+        let invariants = vec![
+            Invariant::new("A", Expression::Var("paused".to_string())),
+            Invariant::new("B", Expression::Boolean(true)),
+        ];
+        let code = format!(
+            "// INVAR_HASH: {}",
+            TamperDetector::compute_invariant_hash(&invariants)
+        );
+        assert!(TamperDetector::verify_tampering(&code, &invariants).is_ok());
+
+        let tampered = vec![Invariant::new("A", Expression::Boolean(false))];
+        assert!(matches!(
+            TamperDetector::verify_tampering(&code, &tampered),
+            Err(ThreatModelError::TamperDetected(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_provenance() {
+        let invariants = vec![Invariant::new("A", Expression::Var("paused".to_string()))];
+        let config = ThreatModelConfig::default();
+        let code = ProvenanceHeader::new("source", &invariants, &config).prepend("fn a() {}\n");
+        let expected = ProvenanceHeader::new("source", &invariants, &config);
+        assert!(TamperDetector::verify_provenance(&code, &expected).is_ok());
+
+        let edited = ProvenanceHeader::new("edited source", &invariants, &config);
+        assert_eq!(
+            TamperDetector::verify_provenance(&code, &edited),
+            Err(ThreatModelError::TamperDetected(
+                "provenance mismatch: source file changed".to_string()
+            ))
+        );
+
+        assert!(TamperDetector::verify_code_hash(&code).is_ok());
+        let hand_edited = code.replace("fn a() {}", "fn a() { evil() }");
+        assert!(TamperDetector::verify_code_hash(&hand_edited).is_err());
+    }
+
     #[test]
     fn test_dsl_sandbox_forbidden_variable() {
         let expr = Expression::Var("file_handle".to_string());
         assert!(DSLSandbox::validate_expression(&expr).is_err());
+        let negated = Expression::Negate(Box::new(expr));
+        assert!(DSLSandbox::validate_expression(&negated).is_err());
+        let filtered = Expression::Aggregate {
+            op: crate::model::AggregateOp::Sum,
+            binder: "d".to_string(),
+            collection: Box::new(Expression::Var("deposits".to_string())),
+            body: Box::new(Expression::Var("d".to_string())),
+            filter: Some(Box::new(negated)),
+        };
+        assert!(DSLSandbox::validate_expression(&filtered).is_err());
     }
 
     #[test]
@@ -451,6 +601,17 @@ mod tests {
         assert!(DSLSandbox::validate_expression(&expr).is_ok());
     }
 
+    #[test]
+    fn test_dsl_sandbox_depth_limit() {
+        let nest = |levels: usize| {
+            (1..levels).fold(Expression::Var("balance".to_string()), |inner, _| {
+                Expression::Not(Box::new(inner))
+            })
+        };
+        assert!(DSLSandbox::validate_expression(&nest(MAX_SANDBOX_DEPTH)).is_ok());
+        assert!(DSLSandbox::validate_expression(&nest(MAX_SANDBOX_DEPTH + 1)).is_err());
+    }
+
     #[test]
     fn test_dsl_sandbox_forbidden_function() {
         let expr = Expression::FunctionCall {
@@ -469,6 +630,17 @@ mod tests {
         assert!(DSLSandbox::validate_expression(&expr).is_ok());
     }
 
+    #[test]
+    fn test_dsl_sandbox_allows_string_literals() {
+        // Literal contents are data, not identifiers
+        let expr = Expression::BinaryOp {
+            left: Box::new(Expression::Var("owner".to_string())),
+            op: crate::model::BinaryOp::Eq,
+            right: Box::new(Expression::String("system".to_string())),
+        };
+        assert!(DSLSandbox::validate_expression(&expr).is_ok());
+    }
+
     #[test]
     fn test_strict_mode_with_uncertainty() {
         let analyzer = StrictModeAnalyzer::new(true);
@@ -20,6 +20,9 @@ pub trait ChainAnalyzer: Send + Sync {
 
     /// Chain identifier: "solana", "evm", "move".
     fn chain(&self) -> &str;
+
+    /// File extensions (with leading dot) this analyzer handles, e.g. `[".rs"]`.
+    fn supported_extensions(&self) -> &'static [&'static str];
 }
 
 /// Generates instrumented code with invariant checks.
@@ -3,9 +3,16 @@
 //! Performs static type checking on expressions before code generation.
 //! Ensures all invariants are well-typed and cannot cause runtime type errors.
 
-use crate::model::Expression;
-use crate::types::{Type, TypeError, TypeResult, TypedExpr};
-use std::collections::BTreeMap;
+use crate::evaluator::{ARITHMETIC_BUILTINS, BLOCK_BUILTINS, OLD_BUILTIN};
+use crate::model::{
+    complexity, AggregateOp, Expression, Invariant, Quantifier, NO_MUTATION_AFTER_CPI,
+};
+use crate::suggest::did_you_mean;
+use crate::types::{Type, TypeError, TypeResult, TypedExpr, Widening};
+use crate::visitor::{walk_expression, ExpressionVisitor};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::Infallible;
+use std::fmt;
 
 /// Static type checker for invariant expressions.
 ///
@@ -16,6 +23,33 @@ pub struct TypeChecker {
 
     /// Known functions and their signatures.
     functions: BTreeMap<String, FunctionSignature>,
+
+    /// Checking policy.
+    options: TypeCheckerOptions,
+}
+
+/// Default limit on the number of nodes in a checked expression.
+pub const DEFAULT_MAX_COMPLEXITY: usize = 1000;
+
+/// Configurable type checking policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeCheckerOptions {
+    /// Downgrade mixed numeric width comparisons from errors to warnings.
+    pub lenient: bool,
+    /// Allow implicit `u64` → `u128` widening in comparisons.
+    pub allow_widening: bool,
+    /// Largest [`complexity`] of an expression accepted by the checker.
+    pub max_complexity: usize,
+}
+
+impl Default for TypeCheckerOptions {
+    fn default() -> Self {
+        Self {
+            lenient: false,
+            allow_widening: false,
+            max_complexity: DEFAULT_MAX_COMPLEXITY,
+        }
+    }
 }
 
 /// A function signature: parameter types and return type.
@@ -28,15 +62,95 @@ pub struct FunctionSignature {
     pub return_type: Type,
 }
 
+/// Severity of a type checking diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiagnosticLevel {
+    /// Non-fatal finding; the expression can still be used.
+    Warning,
+    /// Hard error; the expression cannot be used for generation.
+    Error,
+}
+
+impl fmt::Display for DiagnosticLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A type checking diagnostic attributed to a single invariant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Invariant name (or positional label) the diagnostic belongs to.
+    pub invariant: String,
+    /// Whether this is a hard error or a warning.
+    pub level: DiagnosticLevel,
+    /// The underlying type error.
+    pub error: TypeError,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.level, self.invariant, self.error)
+    }
+}
+
+/// All diagnostics collected from checking a batch of expressions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheckReport {
+    /// Diagnostics in the order the expressions were checked.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CheckReport {
+    /// Hard errors only.
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.level == DiagnosticLevel::Error)
+    }
+
+    /// Warnings only.
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.level == DiagnosticLevel::Warning)
+    }
+
+    /// Whether any hard error was reported.
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+}
+
 impl TypeChecker {
     /// Create a new type checker with empty context.
     pub fn new() -> Self {
         Self {
             state_vars: BTreeMap::new(),
             functions: BTreeMap::new(),
+            options: TypeCheckerOptions::default(),
         }
     }
 
+    /// Create a new type checker with the given policy.
+    pub fn with_options(options: TypeCheckerOptions) -> Self {
+        Self {
+            options,
+            ..Self::new()
+        }
+    }
+
+    /// Enable or disable lenient mode.
+    ///
+    /// In lenient mode, comparing numeric types of different widths
+    /// (e.g. `u64` vs `u128`) is reported as a warning instead of an error.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.options.lenient = lenient;
+    }
+
     /// Register a state variable with its type.
     pub fn register_state_var(&mut self, name: String, ty: Type) {
         self.state_vars.insert(name, ty);
@@ -47,6 +161,11 @@ impl TypeChecker {
         self.functions.insert(name, sig);
     }
 
+    /// Names of the registered functions.
+    pub fn function_names(&self) -> BTreeSet<String> {
+        self.functions.keys().cloned().collect()
+    }
+
     /// Load state variables from a program model.
     pub fn load_from_program(&mut self, program: &crate::model::ProgramModel) {
         for (name, var) in &program.state_vars {
@@ -63,31 +182,110 @@ impl TypeChecker {
     ///
     /// Returns a TypedExpr if successful, or a TypeError if type checking fails.
     pub fn check_expr(&self, expr: &Expression) -> TypeResult<TypedExpr> {
-        let ty = self.infer_type(expr)?;
-        Ok(TypedExpr::new(expr.clone(), ty))
+        self.check_complexity(expr)?;
+        let mut warnings = Vec::new();
+        let ty = self.infer_type(expr, &mut warnings)?;
+        let mut typed = TypedExpr::new(expr.clone(), ty);
+        if self.options.allow_widening {
+            let mut collector = WideningCollector {
+                checker: self,
+                widenings: Vec::new(),
+            };
+            let Ok(()) = collector.visit_expression(expr);
+            typed.widenings = collector.widenings;
+        }
+        Ok(typed)
+    }
+
+    /// Reject expressions larger than `max_complexity` before inferring types.
+    fn check_complexity(&self, expr: &Expression) -> TypeResult<()> {
+        if complexity(expr) > self.options.max_complexity {
+            return Err(TypeError::Custom("expression too complex".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Type check a batch of expressions without stopping at the first error.
+    ///
+    /// Diagnostics are labelled by position (`expr[0]`, `expr[1]`, ...).
+    pub fn check_all(&self, exprs: &[&Expression]) -> CheckReport {
+        let labelled = exprs
+            .iter()
+            .enumerate()
+            .map(|(idx, expr)| (format!("expr[{}]", idx), *expr));
+        self.check_labelled(labelled)
+    }
+
+    /// Type check every invariant, tagging diagnostics with the invariant name.
+    pub fn check_invariants(&self, invariants: &[Invariant]) -> CheckReport {
+        let labelled = invariants
+            .iter()
+            .map(|inv| (inv.name.clone(), &inv.expression));
+        self.check_labelled(labelled)
+    }
+
+    fn check_labelled<'a>(
+        &self,
+        exprs: impl IntoIterator<Item = (String, &'a Expression)>,
+    ) -> CheckReport {
+        let mut report = CheckReport::default();
+
+        for (label, expr) in exprs {
+            let mut warnings = Vec::new();
+            let result = self
+                .check_complexity(expr)
+                .and_then(|()| self.infer_type(expr, &mut warnings));
+
+            for warning in warnings {
+                report.diagnostics.push(Diagnostic {
+                    invariant: label.clone(),
+                    level: DiagnosticLevel::Warning,
+                    error: warning,
+                });
+            }
+
+            if let Err(error) = result {
+                report.diagnostics.push(Diagnostic {
+                    invariant: label,
+                    level: DiagnosticLevel::Error,
+                    error,
+                });
+            }
+        }
+
+        report
+    }
+
+    /// Error for `lookup` missing from the state variables, reported as
+    /// `name` (which ends with `lookup`) and with suggestions qualified alike.
+    fn undefined_variable(&self, name: String, lookup: &str) -> TypeError {
+        let qualifier = &name[..name.len() - lookup.len()];
+        let suggestions = did_you_mean(lookup, self.state_vars.keys())
+            .into_iter()
+            .map(|suggestion| format!("{}{}", qualifier, suggestion))
+            .collect();
+        TypeError::UndefinedVariable { name, suggestions }
     }
 
     /// Infer the type of an expression.
-    fn infer_type(&self, expr: &Expression) -> TypeResult<Type> {
+    ///
+    /// Non-fatal findings (lenient-mode mismatches and lossy casts) are pushed
+    /// onto `warnings`.
+    fn infer_type(&self, expr: &Expression, warnings: &mut Vec<TypeError>) -> TypeResult<Type> {
         match expr {
             Expression::Boolean(_) => Ok(Type::Bool),
 
             Expression::Int(val) => {
-                // Infer numeric type from value range
-                if *val < 0 {
-                    Ok(Type::I64)
-                } else if *val <= u64::MAX as i128 {
-                    Ok(Type::U64)
-                } else {
-                    Ok(Type::U128)
-                }
+                Type::of_int_literal(*val).ok_or(TypeError::IntLiteralOutOfRange(*val))
             }
 
+            Expression::String(_) => Ok(Type::Str),
+
             Expression::Var(name) => self
                 .state_vars
                 .get(name)
-                .copied()
-                .ok_or_else(|| TypeError::UndefinedVariable(name.clone())),
+                .cloned()
+                .ok_or_else(|| self.undefined_variable(name.clone(), name)),
 
             Expression::LayerVar { layer, var } => {
                 // Layer-qualified variables are treated as typed based on convention:
@@ -95,8 +293,8 @@ impl TypeChecker {
                 // For now, assume they could be any type and require explicit validation
                 self.state_vars
                     .get(var)
-                    .copied()
-                    .ok_or_else(|| TypeError::UndefinedVariable(format!("{}::{}", layer, var)))
+                    .cloned()
+                    .ok_or_else(|| self.undefined_variable(format!("{}::{}", layer, var), var))
             }
 
             Expression::PhaseQualifiedVar {
@@ -108,8 +306,8 @@ impl TypeChecker {
                 // Type inferred from the variable name
                 self.state_vars
                     .get(var)
-                    .copied()
-                    .ok_or_else(|| TypeError::UndefinedVariable(format!("{}::{}", layer, var)))
+                    .cloned()
+                    .ok_or_else(|| self.undefined_variable(format!("{}::{}", layer, var), var))
             }
 
             Expression::PhaseConstraint {
@@ -118,7 +316,7 @@ impl TypeChecker {
             } => {
                 // A phase constraint evaluates to the type of its constraint
                 // Typically constraints evaluate to Bool
-                self.infer_type(constraint)
+                self.infer_type(constraint, warnings)
             }
 
             Expression::CrossPhaseRelation {
@@ -129,16 +327,20 @@ impl TypeChecker {
                 op: _,
             } => {
                 // Cross-phase relations are comparisons that return bool
-                self.check_binary_op(expr1, &crate::model::BinaryOp::Eq, expr2)?;
+                self.check_binary_op(expr1, &crate::model::BinaryOp::Eq, expr2, warnings)?;
                 Ok(Type::Bool)
             }
 
-            Expression::BinaryOp { left, op, right } => self.check_binary_op(left, op, right),
+            Expression::BinaryOp { left, op, right } => {
+                self.check_binary_op(left, op, right, warnings)
+            }
 
-            Expression::Logical { left, op, right } => self.check_logical_op(left, op, right),
+            Expression::Logical { left, op, right } => {
+                self.check_logical_op(left, op, right, warnings)
+            }
 
             Expression::Not(expr) => {
-                let ty = self.infer_type(expr)?;
+                let ty = self.infer_type(expr, warnings)?;
                 if ty != Type::Bool {
                     return Err(TypeError::UnaryOpTypeMismatch {
                         op: "!".to_string(),
@@ -148,23 +350,177 @@ impl TypeChecker {
                 Ok(Type::Bool)
             }
 
-            Expression::FunctionCall { name, args } => self.check_function_call(name, args),
+            Expression::Negate(expr) => {
+                let ty = self.infer_type(expr, warnings)?;
+                if ty != Type::I64 {
+                    return Err(TypeError::UnaryOpTypeMismatch {
+                        op: "-".to_string(),
+                        operand: ty,
+                    });
+                }
+                Ok(Type::I64)
+            }
+
+            Expression::Assert(constraint, _) => {
+                let ty = self.infer_type(constraint, warnings)?;
+                if ty != Type::Bool {
+                    return Err(TypeError::UnaryOpTypeMismatch {
+                        op: "assert".to_string(),
+                        operand: ty,
+                    });
+                }
+                Ok(Type::Bool)
+            }
+
+            Expression::FunctionCall { name, args } => {
+                self.check_function_call(name, args, warnings)
+            }
 
             Expression::Tuple(exprs) => {
                 // Type check tuple elements and infer tuple type
                 if exprs.is_empty() {
                     // Empty tuple has unit type
-                    Ok(Type::Bool)  // Unit represented as Bool for compatibility
+                    Ok(Type::Bool) // Unit represented as Bool for compatibility
                 } else {
                     // For tuples with multiple elements, ensure type consistency
                     let mut element_types = Vec::new();
                     for expr in exprs {
-                        let ty = self.infer_type(expr)?;
+                        let ty = self.infer_type(expr, warnings)?;
                         element_types.push(ty);
                     }
                     // Return the type of the first element for now
                     // A full implementation would track tuple types (T1, T2, ...)
-                    Ok(element_types[0].clone())
+                    Ok(element_types.swap_remove(0))
+                }
+            }
+
+            Expression::Conditional {
+                cond,
+                then_expr,
+                else_expr,
+            } => {
+                let cond_ty = self.infer_type(cond, warnings)?;
+                if cond_ty != Type::Bool {
+                    return Err(TypeError::ConditionRequiresBool(cond_ty));
+                }
+                let then_type = self.infer_type(then_expr, warnings)?;
+                let else_type = self.infer_type(else_expr, warnings)?;
+                if then_type != else_type {
+                    return Err(TypeError::BranchTypeMismatch {
+                        then_type,
+                        else_type,
+                    });
+                }
+                Ok(then_type)
+            }
+
+            Expression::Cast { expr, target } => {
+                let from = self.infer_type(expr, warnings)?;
+                if from != *target && !(from.is_numeric() && target.is_numeric()) {
+                    return Err(TypeError::InvalidCast {
+                        from,
+                        to: target.clone(),
+                    });
+                }
+                // Narrowing or sign-changing casts can fail at runtime
+                if !from.widens_to(target) {
+                    warnings.push(TypeError::Custom(format!(
+                        "lossy cast from {} to {}",
+                        from, target
+                    )));
+                }
+                Ok(target.clone())
+            }
+
+            Expression::MapAccess { map, key } => {
+                let (key_ty, value_ty) = match self.infer_type(map, warnings)? {
+                    Type::Map(key_ty, value_ty) => (key_ty, value_ty),
+                    other => return Err(TypeError::NotAMap(other)),
+                };
+                let actual = self.infer_type(key, warnings)?;
+                if !actual.widens_to(&key_ty) {
+                    return Err(TypeError::MapKeyMismatch {
+                        expected: *key_ty,
+                        actual,
+                    });
+                }
+                Ok(*value_ty)
+            }
+
+            Expression::Quantified {
+                quantifier,
+                var,
+                collection,
+                body,
+            } => {
+                let element = match self.infer_type(collection, warnings)? {
+                    Type::Map(_, value_ty) => *value_ty,
+                    // Array element types are not tracked; they are taken to
+                    // be `u64`, like the argument of the built-in `sum`
+                    Type::Array => Type::U64,
+                    other => return Err(TypeError::NotACollection(other)),
+                };
+                let mut scope = TypeChecker {
+                    state_vars: self.state_vars.clone(),
+                    functions: self.functions.clone(),
+                    options: self.options,
+                };
+                scope.state_vars.insert(var.clone(), element);
+                let body_ty = scope.infer_type(body, warnings)?;
+
+                let predicate = matches!(
+                    quantifier,
+                    Quantifier::ForAll | Quantifier::Exists | Quantifier::Count
+                );
+                if (predicate && body_ty != Type::Bool) || (!predicate && !body_ty.is_numeric()) {
+                    return Err(TypeError::UnaryOpTypeMismatch {
+                        op: quantifier.to_string(),
+                        operand: body_ty,
+                    });
+                }
+                Ok(match quantifier {
+                    Quantifier::ForAll | Quantifier::Exists => Type::Bool,
+                    Quantifier::Count => Type::U64,
+                    Quantifier::Sum | Quantifier::Min | Quantifier::Max => body_ty,
+                })
+            }
+
+            Expression::Aggregate {
+                op,
+                binder,
+                collection,
+                body,
+                filter,
+            } => {
+                let element = match self.infer_type(collection, warnings)? {
+                    Type::Map(_, value_ty) => *value_ty,
+                    // Untracked array element types are taken to be `u64`, as for quantifiers
+                    Type::Array => Type::U64,
+                    other => return Err(TypeError::NotACollection(other)),
+                };
+                let mut scope = TypeChecker {
+                    state_vars: self.state_vars.clone(),
+                    functions: self.functions.clone(),
+                    options: self.options,
+                };
+                scope.state_vars.insert(binder.clone(), element);
+                if let Some(filter) = filter {
+                    let filter_ty = scope.infer_type(filter, warnings)?;
+                    if filter_ty != Type::Bool {
+                        return Err(TypeError::UnaryOpTypeMismatch {
+                            op: "if".to_string(),
+                            operand: filter_ty,
+                        });
+                    }
+                }
+                let body_ty = scope.infer_type(body, warnings)?;
+                match op {
+                    AggregateOp::Count => Ok(Type::U64),
+                    AggregateOp::Sum | AggregateOp::Max if body_ty.is_numeric() => Ok(body_ty),
+                    AggregateOp::Sum | AggregateOp::Max => Err(TypeError::UnaryOpTypeMismatch {
+                        op: op.to_string(),
+                        operand: body_ty,
+                    }),
                 }
             }
         }
@@ -176,20 +532,32 @@ impl TypeChecker {
         left: &Expression,
         op: &crate::model::BinaryOp,
         right: &Expression,
+        warnings: &mut Vec<TypeError>,
     ) -> TypeResult<Type> {
-        let left_ty = self.infer_type(left)?;
-        let right_ty = self.infer_type(right)?;
+        let left_ty = self.infer_type(left, warnings)?;
+        let right_ty = self.infer_type(right, warnings)?;
 
         use crate::model::BinaryOp;
 
+        // Implicit u64 -> u128 widening, only when enabled
+        let widened = self.options.allow_widening
+            && (left_ty.widens_to(&right_ty) || right_ty.widens_to(&left_ty));
+
         match op {
             BinaryOp::Eq | BinaryOp::Neq => {
                 // Equality requires exact type match
-                if left_ty != right_ty {
-                    return Err(TypeError::IncomparableTypes {
+                if left_ty != right_ty && !widened {
+                    let numeric = left_ty.is_numeric() && right_ty.is_numeric();
+                    let error = TypeError::IncomparableTypes {
                         left: left_ty,
                         right: right_ty,
-                    });
+                    };
+                    // Lenient mode: differing numeric widths are only a warning
+                    if self.options.lenient && numeric {
+                        warnings.push(error);
+                    } else {
+                        return Err(error);
+                    }
                 }
                 Ok(Type::Bool)
             }
@@ -203,7 +571,7 @@ impl TypeChecker {
                     });
                 }
 
-                if left_ty != right_ty {
+                if left_ty != right_ty && !widened {
                     let op_str = match op {
                         BinaryOp::Lt => "<",
                         BinaryOp::Gt => ">",
@@ -213,11 +581,15 @@ impl TypeChecker {
                         // but we handle them for completeness
                         _ => "unknown",
                     };
-                    return Err(TypeError::BinaryOpTypeMismatch {
+                    let error = TypeError::BinaryOpTypeMismatch {
                         left: left_ty,
                         op: op_str.to_string(),
                         right: right_ty,
-                    });
+                    };
+                    if !self.options.lenient {
+                        return Err(error);
+                    }
+                    warnings.push(error);
                 }
 
                 Ok(Type::Bool)
@@ -231,9 +603,10 @@ impl TypeChecker {
         left: &Expression,
         op: &crate::model::LogicalOp,
         right: &Expression,
+        warnings: &mut Vec<TypeError>,
     ) -> TypeResult<Type> {
-        let left_ty = self.infer_type(left)?;
-        let right_ty = self.infer_type(right)?;
+        let left_ty = self.infer_type(left, warnings)?;
+        let right_ty = self.infer_type(right, warnings)?;
 
         use crate::model::LogicalOp;
 
@@ -260,7 +633,12 @@ impl TypeChecker {
     }
 
     /// Check a function call's types.
-    fn check_function_call(&self, name: &str, args: &[Expression]) -> TypeResult<Type> {
+    fn check_function_call(
+        &self,
+        name: &str,
+        args: &[Expression],
+        warnings: &mut Vec<TypeError>,
+    ) -> TypeResult<Type> {
         let sig = self
             .functions
             .get(name)
@@ -277,18 +655,18 @@ impl TypeChecker {
 
         // Type check each argument
         for (idx, (arg, expected)) in args.iter().zip(&sig.params).enumerate() {
-            let actual = self.infer_type(arg)?;
+            let actual = self.infer_type(arg, warnings)?;
             if actual != *expected {
                 return Err(TypeError::FunctionArgMismatch {
                     function: name.to_string(),
                     param_idx: idx,
-                    expected: *expected,
+                    expected: expected.clone(),
                     actual,
                 });
             }
         }
 
-        Ok(sig.return_type)
+        Ok(sig.return_type.clone())
     }
 
     /// Register standard library functions.
@@ -328,21 +706,120 @@ impl TypeChecker {
                 return_type: Type::U64,
             },
         );
+
+        // no_mutation_after_cpi(string) -> bool, enforced at build time
+        self.register_function(
+            NO_MUTATION_AFTER_CPI.to_string(),
+            FunctionSignature {
+                params: vec![Type::Str],
+                return_type: Type::Bool,
+            },
+        );
+
+        // add, sub, mul, div(u64, u64) -> u64, checked by the evaluator: the
+        // expression language has no arithmetic operators
+        for name in ARITHMETIC_BUILTINS {
+            self.register_function(
+                name.to_string(),
+                FunctionSignature {
+                    params: vec![Type::U64, Type::U64],
+                    return_type: Type::U64,
+                },
+            );
+        }
+
+        // old(u64) -> u64, the value in the pre-transaction state
+        self.register_function(
+            OLD_BUILTIN.to_string(),
+            FunctionSignature {
+                params: vec![Type::U64],
+                return_type: Type::U64,
+            },
+        );
+
+        // now(), block_number(), chain_id() -> u64, read from the block context
+        for name in BLOCK_BUILTINS {
+            self.register_function(
+                name.to_string(),
+                FunctionSignature {
+                    params: vec![],
+                    return_type: Type::U64,
+                },
+            );
+        }
     }
 
     /// Infer a type from a string representation.
+    ///
+    /// Solidity mappings (`mapping(address => uint256)`) become `Type::Map`.
     fn infer_type_from_string(&self, type_str: &str) -> Type {
-        match type_str.to_lowercase().as_str() {
+        if let Some((key, value)) = type_str
+            .trim()
+            .strip_prefix("mapping")
+            .and_then(|rest| rest.trim().strip_prefix('('))
+            .and_then(|rest| rest.strip_suffix(')'))
+            .and_then(|inner| inner.split_once("=>"))
+        {
+            return Type::Map(
+                Box::new(self.infer_type_from_string(key)),
+                Box::new(self.infer_type_from_string(value)),
+            );
+        }
+
+        match type_str.trim().to_lowercase().as_str() {
             "bool" | "boolean" => Type::Bool,
             "u64" | "uint64" => Type::U64,
             "u128" | "uint128" => Type::U128,
+            "u256" | "uint256" | "uint" => Type::U256,
             "i64" | "int64" => Type::I64,
             "address" => Type::Address,
+            "string" => Type::Str,
             _ => Type::U64, // Default
         }
     }
 }
 
+/// Records the comparison operands `check_binary_op` accepted by widening.
+struct WideningCollector<'a> {
+    checker: &'a TypeChecker,
+    widenings: Vec<Widening>,
+}
+
+impl WideningCollector<'_> {
+    fn record(&mut self, left: &Expression, right: &Expression) {
+        let mut warnings = Vec::new();
+        let types = (
+            self.checker.infer_type(left, &mut warnings),
+            self.checker.infer_type(right, &mut warnings),
+        );
+        let (Ok(left_ty), Ok(right_ty)) = types else {
+            return;
+        };
+        for (operand, from, to) in [(left, &left_ty, &right_ty), (right, &right_ty, &left_ty)] {
+            if from != to && from.widens_to(to) {
+                self.widenings.push(Widening {
+                    operand: operand.clone(),
+                    from: from.clone(),
+                    to: to.clone(),
+                });
+            }
+        }
+    }
+}
+
+impl ExpressionVisitor for WideningCollector<'_> {
+    type Error = Infallible;
+
+    fn visit_expression(&mut self, expr: &Expression) -> Result<(), Self::Error> {
+        match expr {
+            Expression::BinaryOp { left, right, .. } => self.record(left, right),
+            Expression::CrossPhaseRelation { expr1, expr2, .. } => self.record(expr1, expr2),
+            _ => {}
+        }
+        walk_expression(self, expr)
+    }
+}
+
 impl Default for TypeChecker {
     fn default() -> Self {
         Self::new()
@@ -372,7 +849,10 @@ mod tests {
 
         assert!(result.is_err());
         match result {
-            Err(TypeError::UndefinedVariable(name)) => assert_eq!(name, "unknown"),
+            Err(TypeError::UndefinedVariable { name, suggestions }) => {
+                assert_eq!(name, "unknown");
+                assert!(suggestions.is_empty());
+            }
             _ => panic!("expected UndefinedVariable error"),
         }
     }
@@ -393,6 +873,73 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_strings_only_compare_for_equality() {
+        let mut checker = TypeChecker::new();
+        checker.register_state_var("owner".to_string(), Type::Str);
+        let compare = |op| Expression::BinaryOp {
+            left: Box::new(Expression::Var("owner".to_string())),
+            op,
+            right: Box::new(Expression::String("system".to_string())),
+        };
+
+        assert_eq!(
+            checker
+                .check_expr(&compare(crate::model::BinaryOp::Eq))
+                .unwrap()
+                .ty,
+            Type::Bool
+        );
+        assert_eq!(
+            checker
+                .check_expr(&compare(crate::model::BinaryOp::Lt))
+                .unwrap_err(),
+            TypeError::IncomparableTypes {
+                left: Type::Str,
+                right: Type::Str,
+            }
+        );
+    }
+
+    #[test]
+    fn test_map_access_checks_key_type() {
+        let mut checker = TypeChecker::new();
+        let mut program = crate::model::ProgramModel::new(
+            "token".to_string(),
+            "evm".to_string(),
+            "Token.sol".to_string(),
+        );
+        program.add_state_var(crate::model::StateVar {
+            name: "balances".to_string(),
+            type_name: "mapping(address => uint64)".to_string(),
+            is_mutable: true,
+            visibility: None,
+            abilities: Default::default(),
+            depends_on: Vec::new(),
+        });
+        checker.load_from_program(&program);
+        checker.register_state_var("owner".to_string(), Type::Address);
+
+        let access = |key: Expression| Expression::MapAccess {
+            map: Box::new(Expression::Var("balances".to_string())),
+            key: Box::new(key),
+        };
+        assert_eq!(
+            checker
+                .check_expr(&access(Expression::Var("owner".to_string())))
+                .unwrap()
+                .ty,
+            Type::U64
+        );
+        assert_eq!(
+            checker.check_expr(&access(Expression::Int(1))).unwrap_err(),
+            TypeError::MapKeyMismatch {
+                expected: Type::Address,
+                actual: Type::U64,
+            }
+        );
+    }
+
     #[test]
     fn test_logical_requires_bool() {
         let mut checker = TypeChecker::new();
@@ -407,4 +954,462 @@ mod tests {
         let result = checker.check_expr(&expr);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_negate_requires_signed_operand() {
+        let mut checker = TypeChecker::new();
+        checker.register_state_var("amount".to_string(), Type::U64);
+        checker.register_state_var("delta".to_string(), Type::I64);
+        let negate = |name: &str| Expression::Negate(Box::new(Expression::Var(name.to_string())));
+
+        assert_eq!(checker.check_expr(&negate("delta")).unwrap().ty, Type::I64);
+        // Negative literals are already signed; non-negative ones are U64
+        assert_eq!(
+            checker.check_expr(&Expression::Int(-100)).unwrap().ty,
+            Type::I64
+        );
+        assert!(checker
+            .check_expr(&Expression::Negate(Box::new(Expression::Int(100))))
+            .is_err());
+        assert_eq!(
+            checker.check_expr(&negate("amount")).unwrap_err(),
+            TypeError::UnaryOpTypeMismatch {
+                op: "-".to_string(),
+                operand: Type::U64,
+            }
+        );
+    }
+
+    #[test]
+    fn test_check_all_reports_every_problem() {
+        let mut checker = TypeChecker::new();
+        checker.register_state_var("flag".to_string(), Type::Bool);
+        checker.register_state_var("amount".to_string(), Type::U64);
+
+        let undefined = Expression::Var("missing".to_string());
+        let mismatch = Expression::BinaryOp {
+            left: Box::new(Expression::Var("flag".to_string())),
+            op: crate::model::BinaryOp::Eq,
+            right: Box::new(Expression::Var("amount".to_string())),
+        };
+        let not_bool = Expression::Not(Box::new(Expression::Var("amount".to_string())));
+
+        let report = checker.check_all(&[&undefined, &mismatch, &not_bool]);
+        let errors: Vec<_> = report.errors().collect();
+
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].invariant, "expr[0]");
+        assert!(matches!(
+            errors[0].error,
+            TypeError::UndefinedVariable { .. }
+        ));
+        assert!(matches!(
+            errors[1].error,
+            TypeError::IncomparableTypes { .. }
+        ));
+        assert!(matches!(
+            errors[2].error,
+            TypeError::UnaryOpTypeMismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn test_check_invariants_tags_names() {
+        let checker = TypeChecker::new();
+        let invariants = vec![Invariant::new(
+            "NoSuchVar",
+            Expression::Var("ghost".to_string()),
+        )];
+
+        let report = checker.check_invariants(&invariants);
+        assert!(report.has_errors());
+        assert_eq!(report.diagnostics[0].invariant, "NoSuchVar");
+    }
+
+    #[test]
+    fn test_lenient_mode_downgrades_width_mismatch() {
+        let mut checker = TypeChecker::new();
+        checker.register_state_var("small".to_string(), Type::U64);
+        checker.register_state_var("large".to_string(), Type::U128);
+
+        let expr = Expression::BinaryOp {
+            left: Box::new(Expression::Var("small".to_string())),
+            op: crate::model::BinaryOp::Lt,
+            right: Box::new(Expression::Var("large".to_string())),
+        };
+
+        assert!(checker.check_all(&[&expr]).has_errors());
+
+        checker.set_lenient(true);
+        let report = checker.check_all(&[&expr]);
+        assert!(!report.has_errors());
+        assert_eq!(report.warnings().count(), 1);
+        assert!(checker.check_expr(&expr).is_ok());
+    }
+
+    #[test]
+    fn test_lossy_casts_warn() {
+        let mut checker = TypeChecker::new();
+        checker.register_state_var("balance".to_string(), Type::U64);
+        checker.register_state_var("delta".to_string(), Type::I64);
+        checker.register_state_var("paused".to_string(), Type::Bool);
+        let cast = |name: &str, target: Type| Expression::Cast {
+            expr: Box::new(Expression::Var(name.to_string())),
+            target,
+        };
+
+        // Widening and identity casts are silent
+        for expr in [cast("balance", Type::U128), cast("balance", Type::U64)] {
+            let report = checker.check_all(&[&expr]);
+            assert!(report.diagnostics.is_empty(), "{}", expr);
+        }
+
+        // Narrowing and sign-changing casts type check with a warning
+        for (name, target, message) in [
+            ("balance", Type::I64, "lossy cast from u64 to i64"),
+            ("delta", Type::U64, "lossy cast from i64 to u64"),
+        ] {
+            let expr = cast(name, target.clone());
+            assert_eq!(checker.check_expr(&expr).unwrap().ty, target);
+            let report = checker.check_all(&[&expr]);
+            assert!(!report.has_errors());
+            let warnings: Vec<_> = report.warnings().collect();
+            assert_eq!(warnings.len(), 1);
+            assert_eq!(warnings[0].error, TypeError::Custom(message.to_string()));
+        }
+
+        assert_eq!(
+            checker.check_expr(&cast("paused", Type::U64)).unwrap_err(),
+            TypeError::InvalidCast {
+                from: Type::Bool,
+                to: Type::U64,
+            }
+        );
+    }
+
+    #[test]
+    fn test_conditional_branches_must_agree() {
+        let mut checker = TypeChecker::new();
+        checker.register_state_var("is_initialized".to_string(), Type::Bool);
+        checker.register_state_var("balance".to_string(), Type::U64);
+
+        let ok = Expression::Conditional {
+            cond: Box::new(Expression::Var("is_initialized".to_string())),
+            then_expr: Box::new(Expression::Var("balance".to_string())),
+            else_expr: Box::new(Expression::Int(0)),
+        };
+        assert_eq!(checker.check_expr(&ok).unwrap().ty, Type::U64);
+
+        let mismatched = Expression::Conditional {
+            cond: Box::new(Expression::Var("is_initialized".to_string())),
+            then_expr: Box::new(Expression::Var("balance".to_string())),
+            else_expr: Box::new(Expression::Boolean(false)),
+        };
+        assert!(matches!(
+            checker.check_expr(&mismatched),
+            Err(TypeError::BranchTypeMismatch { .. })
+        ));
+
+        let non_bool_cond = Expression::Conditional {
+            cond: Box::new(Expression::Var("balance".to_string())),
+            then_expr: Box::new(Expression::Int(1)),
+            else_expr: Box::new(Expression::Int(0)),
+        };
+        assert_eq!(
+            checker.check_expr(&non_bool_cond).unwrap_err(),
+            TypeError::ConditionRequiresBool(Type::U64)
+        );
+    }
+
+    #[test]
+    fn test_widening_requires_option() {
+        let comparison = Expression::BinaryOp {
+            left: Box::new(Expression::Var("amount".to_string())),
+            op: crate::model::BinaryOp::Lte,
+            right: Box::new(Expression::Var("supply".to_string())),
+        };
+
+        let mut strict = TypeChecker::new();
+        strict.register_state_var("amount".to_string(), Type::U64);
+        strict.register_state_var("supply".to_string(), Type::U128);
+        assert!(strict.check_expr(&comparison).is_err());
+
+        let mut widening = TypeChecker::with_options(TypeCheckerOptions {
+            allow_widening: true,
+            ..Default::default()
+        });
+        widening.register_state_var("amount".to_string(), Type::U64);
+        widening.register_state_var("supply".to_string(), Type::U128);
+        assert!(widening.check_expr(&comparison).is_ok());
+
+        // Either operand may be the narrower one; the widening is recorded
+        let typed = widening.check_expr(&comparison).unwrap();
+        assert_eq!(
+            typed.widenings,
+            vec![Widening {
+                operand: Expression::Var("amount".to_string()),
+                from: Type::U64,
+                to: Type::U128,
+            }]
+        );
+        let flipped = Expression::BinaryOp {
+            left: Box::new(Expression::Var("supply".to_string())),
+            op: crate::model::BinaryOp::Lt,
+            right: Box::new(Expression::Var("amount".to_string())),
+        };
+        assert_eq!(widening.check_expr(&flipped).unwrap().widenings.len(), 1);
+
+        // Widening never makes non-numeric operands comparable
+        widening.register_state_var("paused".to_string(), Type::Bool);
+        let bool_cmp = Expression::BinaryOp {
+            left: Box::new(Expression::Var("paused".to_string())),
+            op: crate::model::BinaryOp::Lt,
+            right: Box::new(Expression::Var("amount".to_string())),
+        };
+        assert!(widening.check_expr(&bool_cmp).is_err());
+
+        // An explicit cast works without the option
+        let cast = Expression::BinaryOp {
+            left: Box::new(Expression::Cast {
+                expr: Box::new(Expression::Var("amount".to_string())),
+                target: Type::U128,
+            }),
+            op: crate::model::BinaryOp::Lte,
+            right: Box::new(Expression::Var("supply".to_string())),
+        };
+        assert!(strict.check_expr(&cast).is_ok());
+    }
+
+    #[test]
+    fn test_complexity_limit() {
+        // Ten negations around a literal: 11 nodes
+        let nested = (0..10).fold(Expression::Boolean(true), |inner, _| {
+            Expression::Not(Box::new(inner))
+        });
+        assert!(TypeChecker::new().check_expr(&nested).is_ok());
+
+        let strict = TypeChecker::with_options(TypeCheckerOptions {
+            max_complexity: 10,
+            ..Default::default()
+        });
+        assert_eq!(
+            strict.check_expr(&nested).unwrap_err(),
+            TypeError::Custom("expression too complex".to_string())
+        );
+    }
+
+    #[test]
+    fn test_quantified_types() {
+        use crate::model::{BinaryOp, Quantifier};
+
+        let mut checker = TypeChecker::new();
+        checker.register_state_var("deposits".to_string(), Type::Array);
+        checker.register_state_var(
+            "balances".to_string(),
+            Type::Map(Box::new(Type::Address), Box::new(Type::U128)),
+        );
+        checker.register_state_var("owner".to_string(), Type::Address);
+        let quantified = |quantifier, collection: &str, body| Expression::Quantified {
+            quantifier,
+            var: "x".to_string(),
+            collection: Box::new(Expression::Var(collection.to_string())),
+            body: Box::new(body),
+        };
+        let x = || Expression::Var("x".to_string());
+        let positive = Expression::BinaryOp {
+            left: Box::new(x()),
+            op: BinaryOp::Gt,
+            right: Box::new(Expression::Int(0)),
+        };
+
+        let ty = |expr: &Expression| checker.check_expr(expr).map(|typed| typed.ty);
+        assert_eq!(
+            ty(&quantified(
+                Quantifier::ForAll,
+                "deposits",
+                positive.clone()
+            )),
+            Ok(Type::Bool)
+        );
+        assert_eq!(
+            ty(&quantified(Quantifier::Count, "deposits", positive.clone())),
+            Ok(Type::U64)
+        );
+        assert_eq!(
+            ty(&quantified(Quantifier::Sum, "balances", x())),
+            Ok(Type::U128)
+        );
+        assert_eq!(
+            ty(&quantified(Quantifier::Exists, "deposits", x())),
+            Err(TypeError::UnaryOpTypeMismatch {
+                op: "exists".to_string(),
+                operand: Type::U64,
+            })
+        );
+        assert_eq!(
+            ty(&quantified(Quantifier::Max, "deposits", positive)),
+            Err(TypeError::UnaryOpTypeMismatch {
+                op: "max".to_string(),
+                operand: Type::Bool,
+            })
+        );
+        assert_eq!(
+            ty(&quantified(Quantifier::Min, "owner", x())),
+            Err(TypeError::NotACollection(Type::Address))
+        );
+        // The bound name is not visible outside the quantifier
+        assert!(ty(&x()).is_err());
+    }
+
+    #[test]
+    fn test_aggregate_types() {
+        use crate::model::BinaryOp;
+
+        let mut checker = TypeChecker::new();
+        checker.register_state_var(
+            "balances".to_string(),
+            Type::Map(Box::new(Type::Address), Box::new(Type::U128)),
+        );
+        checker.register_state_var(
+            "owners".to_string(),
+            Type::Map(Box::new(Type::U64), Box::new(Type::Address)),
+        );
+        let aggregate = |op, collection: &str, filter: Option<Expression>| Expression::Aggregate {
+            op,
+            binder: "x".to_string(),
+            collection: Box::new(Expression::Var(collection.to_string())),
+            body: Box::new(Expression::Var("x".to_string())),
+            filter: filter.map(Box::new),
+        };
+        let positive = Expression::BinaryOp {
+            left: Box::new(Expression::Var("x".to_string())),
+            op: BinaryOp::Gt,
+            right: Box::new(Expression::Cast {
+                expr: Box::new(Expression::Int(0)),
+                target: Type::U128,
+            }),
+        };
+
+        let ty = |expr: &Expression| checker.check_expr(expr).map(|typed| typed.ty);
+        // The body has the element type
+        assert_eq!(
+            ty(&aggregate(
+                AggregateOp::Sum,
+                "balances",
+                Some(positive.clone())
+            )),
+            Ok(Type::U128)
+        );
+        assert_eq!(
+            ty(&aggregate(AggregateOp::Count, "owners", None)),
+            Ok(Type::U64)
+        );
+        assert_eq!(
+            ty(&aggregate(AggregateOp::Max, "owners", None)),
+            Err(TypeError::UnaryOpTypeMismatch {
+                op: "max".to_string(),
+                operand: Type::Address,
+            })
+        );
+        assert_eq!(
+            ty(&aggregate(
+                AggregateOp::Sum,
+                "balances",
+                Some(Expression::Var("x".to_string()))
+            )),
+            Err(TypeError::UnaryOpTypeMismatch {
+                op: "if".to_string(),
+                operand: Type::U128,
+            })
+        );
+    }
+
+    #[test]
+    fn test_int_literal_types() {
+        let checker = TypeChecker::new();
+        let ty = |val: i128| {
+            checker
+                .check_expr(&Expression::Int(val))
+                .map(|typed| typed.ty)
+        };
+        let i64_min = i128::from(i64::MIN);
+        let u64_max = i128::from(u64::MAX);
+
+        assert_eq!(ty(i64_min), Ok(Type::I64));
+        assert_eq!(ty(i64_min + 1), Ok(Type::I64));
+        assert_eq!(
+            ty(i64_min - 1),
+            Err(TypeError::IntLiteralOutOfRange(i64_min - 1))
+        );
+        assert_eq!(ty(-1), Ok(Type::I64));
+        assert_eq!(ty(0), Ok(Type::U64));
+        assert_eq!(ty(u64_max), Ok(Type::U64));
+        assert_eq!(ty(u64_max + 1), Ok(Type::U128));
+        assert_eq!(ty(i128::MAX), Ok(Type::U128));
+    }
+
+    #[test]
+    fn test_block_builtins_take_no_arguments() {
+        let mut checker = TypeChecker::new();
+        checker.load_from_program(&crate::model::ProgramModel::new(
+            "escrow".to_string(),
+            "evm".to_string(),
+            "Escrow.sol".to_string(),
+        ));
+        checker.register_state_var("deadline".to_string(), Type::U64);
+        let call = |name: &str, args| Expression::FunctionCall {
+            name: name.to_string(),
+            args,
+        };
+
+        for name in BLOCK_BUILTINS {
+            assert_eq!(
+                checker.check_expr(&call(name, vec![])).unwrap().ty,
+                Type::U64
+            );
+        }
+        let deadline = Expression::BinaryOp {
+            left: Box::new(Expression::Var("deadline".to_string())),
+            op: crate::model::BinaryOp::Gt,
+            right: Box::new(call("now", vec![])),
+        };
+        assert_eq!(checker.check_expr(&deadline).unwrap().ty, Type::Bool);
+        assert!(checker
+            .check_expr(&call("now", vec![Expression::Int(1)]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_undefined_variable_suggestions() {
+        let mut checker = TypeChecker::new();
+        checker.register_state_var("total_supply".to_string(), Type::U64);
+        checker.register_state_var("deposit".to_string(), Type::U64);
+
+        assert_eq!(
+            checker
+                .check_expr(&Expression::Var("totl_supply".to_string()))
+                .unwrap_err(),
+            TypeError::UndefinedVariable {
+                name: "totl_supply".to_string(),
+                suggestions: vec!["total_supply".to_string()],
+            }
+        );
+        let layer_var = Expression::LayerVar {
+            layer: "paymaster".to_string(),
+            var: "deposti".to_string(),
+        };
+        assert_eq!(
+            checker.check_expr(&layer_var).unwrap_err().to_string(),
+            "undefined variable 'paymaster::deposti'; did you mean 'paymaster::deposit'?"
+        );
+        assert_eq!(
+            checker
+                .check_expr(&Expression::Var("xyzzy_plugh".to_string()))
+                .unwrap_err(),
+            TypeError::UndefinedVariable {
+                name: "xyzzy_plugh".to_string(),
+                suggestions: Vec::new(),
+            }
+        );
+    }
 }
@@ -10,7 +10,7 @@ use std::fmt;
 /// A formal type in the Invar type system.
 ///
 /// Supports only deterministic, provable types. No floating point, no null.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub enum Type {
     /// Boolean type.
     Bool,
@@ -20,39 +20,98 @@ pub enum Type {
     U128,
     /// 64-bit signed integer.
     I64,
+    /// 256-bit unsigned integer (EVM `uint256`).
+    U256,
     /// Address type (chain-specific representation).
     Address,
+    /// Homogeneous collection of values (element type is not tracked).
+    Array,
+    /// UTF-8 string; only comparable for (in)equality.
+    Str,
+    /// Key-value mapping (e.g. Solidity `mapping(address => uint256)`).
+    Map(Box<Type>, Box<Type>),
 }
 
 impl Type {
     /// Check if this type is numeric.
-    pub fn is_numeric(self) -> bool {
-        matches!(self, Self::U64 | Self::U128 | Self::I64)
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Self::U64 | Self::U128 | Self::I64 | Self::U256)
     }
 
     /// Check if this type is a primitive.
-    pub fn is_primitive(self) -> bool {
+    pub fn is_primitive(&self) -> bool {
         matches!(
             self,
-            Self::Bool | Self::U64 | Self::U128 | Self::I64 | Self::Address
+            Self::Bool
+                | Self::U64
+                | Self::U128
+                | Self::I64
+                | Self::U256
+                | Self::Address
+                | Self::Str
         )
     }
 
+    /// Parse a DSL type name (`u64`, `u128`, `u256`, `i64`, `bool`, `address`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bool" => Some(Self::Bool),
+            "u64" => Some(Self::U64),
+            "u128" => Some(Self::U128),
+            "u256" => Some(Self::U256),
+            "i64" => Some(Self::I64),
+            "address" => Some(Self::Address),
+            _ => None,
+        }
+    }
+
+    /// Smallest type that holds an integer literal: `u64`, then `u128` for
+    /// non-negative values and `i64` for negative ones. `None` for negative
+    /// literals below `i64::MIN`, which no DSL type can represent.
+    pub fn of_int_literal(value: i128) -> Option<Self> {
+        if value < 0 {
+            i64::try_from(value).ok().map(|_| Self::I64)
+        } else if u64::try_from(value).is_ok() {
+            Some(Self::U64)
+        } else {
+            Some(Self::U128)
+        }
+    }
+
+    /// Check if a value of this type can be implicitly widened to `target`.
+    ///
+    /// Unsigned integers widen to any wider unsigned integer: `u64` → `u128`
+    /// → `u256`.
+    pub fn widens_to(&self, target: &Self) -> bool {
+        self == target
+            || matches!(
+                (self, target),
+                (Self::U64, Self::U128) | (Self::U64 | Self::U128, Self::U256)
+            )
+    }
+
     /// Get a human-readable name for this type.
-    pub fn name(self) -> &'static str {
+    pub fn name(&self) -> &'static str {
         match self {
             Self::Bool => "bool",
             Self::U64 => "u64",
             Self::U128 => "u128",
             Self::I64 => "i64",
+            Self::U256 => "u256",
             Self::Address => "address",
+            Self::Array => "array",
+            Self::Str => "string",
+            Self::Map(..) => "map",
         }
     }
 }
 
 impl fmt::Display for Type {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.name())
+        match self {
+            Self::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            _ => write!(f, "{}", self.name()),
+        }
     }
 }
 
@@ -79,20 +138,43 @@ pub struct TypedExpr {
     pub expr: Expression,
     /// The verified type.
     pub ty: Type,
+    /// Comparison operands implicitly widened to match the other side.
+    #[serde(default)]
+    pub widenings: Vec<Widening>,
 }
 
 impl TypedExpr {
     /// Create a new typed expression.
     pub fn new(expr: Expression, ty: Type) -> Self {
-        Self { expr, ty }
+        Self {
+            expr,
+            ty,
+            widenings: Vec::new(),
+        }
     }
 }
 
+/// An operand converted to a wider numeric type before comparison.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Widening {
+    /// The widened operand.
+    pub operand: Expression,
+    /// Its checked type.
+    pub from: Type,
+    /// The type it is compared at.
+    pub to: Type,
+}
+
 /// Type checking errors.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TypeError {
     /// Unknown identifier.
-    UndefinedVariable(String),
+    UndefinedVariable {
+        /// Name that was looked up.
+        name: String,
+        /// Closest defined names, best first.
+        suggestions: Vec<String>,
+    },
     /// Unknown function.
     UndefinedFunction(String),
     /// Type mismatch in binary operation.
@@ -136,6 +218,35 @@ pub enum TypeError {
         /// Right type.
         right: Type,
     },
+    /// Cast between incompatible types.
+    InvalidCast {
+        /// Source type.
+        from: Type,
+        /// Target type.
+        to: Type,
+    },
+    /// Conditional expression requires a boolean condition.
+    ConditionRequiresBool(Type),
+    /// Conditional branches have different types.
+    BranchTypeMismatch {
+        /// Type of the `then` branch.
+        then_type: Type,
+        /// Type of the `else` branch.
+        else_type: Type,
+    },
+    /// Indexing (`x[key]`) applied to something that is not a map.
+    NotAMap(Type),
+    /// Quantifier over something that is neither an array nor a map.
+    NotACollection(Type),
+    /// Integer literal that no numeric type can hold.
+    IntLiteralOutOfRange(i128),
+    /// Map key does not match the map's key type.
+    MapKeyMismatch {
+        /// Key type of the map.
+        expected: Type,
+        /// Type of the key used.
+        actual: Type,
+    },
     /// Custom error message.
     Custom(String),
 }
@@ -143,8 +254,13 @@ pub enum TypeError {
 impl fmt::Display for TypeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::UndefinedVariable(name) => {
-                write!(f, "undefined variable '{}'", name)
+            Self::UndefinedVariable { name, suggestions } => {
+                write!(
+                    f,
+                    "undefined variable '{}'{}",
+                    name,
+                    crate::suggest::format_suggestions(suggestions)
+                )
             }
             Self::UndefinedFunction(name) => {
                 write!(f, "undefined function '{}'", name)
@@ -185,6 +301,30 @@ impl fmt::Display for TypeError {
             Self::IncomparableTypes { left, right } => {
                 write!(f, "cannot compare {} and {}", left, right)
             }
+            Self::InvalidCast { from, to } => {
+                write!(f, "cannot cast {} to {}", from, to)
+            }
+            Self::ConditionRequiresBool(actual) => {
+                write!(f, "conditional requires bool condition, got {}", actual)
+            }
+            Self::BranchTypeMismatch {
+                then_type,
+                else_type,
+            } => {
+                write!(
+                    f,
+                    "conditional branches have different types: {} and {}",
+                    then_type, else_type
+                )
+            }
+            Self::NotAMap(actual) => write!(f, "cannot index into {}", actual),
+            Self::NotACollection(actual) => write!(f, "cannot iterate over {}", actual),
+            Self::IntLiteralOutOfRange(value) => {
+                write!(f, "integer literal {} is out of range for i64", value)
+            }
+            Self::MapKeyMismatch { expected, actual } => {
+                write!(f, "map key must be {} but got {}", expected, actual)
+            }
             Self::Custom(msg) => write!(f, "{}", msg),
         }
     }
@@ -213,11 +353,34 @@ mod tests {
         assert!(!Type::Address.is_numeric());
     }
 
+    #[test]
+    fn test_widening() {
+        assert!(Type::U64.widens_to(&Type::U128));
+        assert!(!Type::U128.widens_to(&Type::U64));
+        assert!(!Type::I64.widens_to(&Type::U128));
+        assert!(Type::U128.widens_to(&Type::U256));
+        assert!(!Type::U256.widens_to(&Type::U128));
+        assert_eq!(Type::from_name("u128"), Some(Type::U128));
+        assert_eq!(Type::from_name("f64"), None);
+    }
+
     #[test]
     fn test_type_error_display() {
-        let err = TypeError::UndefinedVariable("x".to_string());
+        let err = TypeError::UndefinedVariable {
+            name: "x".to_string(),
+            suggestions: Vec::new(),
+        };
         assert_eq!(err.to_string(), "undefined variable 'x'");
 
+        let err = TypeError::UndefinedVariable {
+            name: "fe".to_string(),
+            suggestions: vec!["fee".to_string(), "fees".to_string()],
+        };
+        assert_eq!(
+            err.to_string(),
+            "undefined variable 'fe'; did you mean 'fee' or 'fees'?"
+        );
+
         let err = TypeError::BinaryOpTypeMismatch {
             left: Type::U64,
             op: "+".to_string(),
@@ -0,0 +1,550 @@
+//! Structural traversal and rewriting of invariant expressions.
+//!
+//! `walk_expression` and `fold_children` are the only places that match on
+//! every `Expression` variant; visitors and folders override hooks for the
+//! leaves they care about and inherit the recursion.
+
+use crate::model::Expression;
+use std::collections::BTreeSet;
+use std::convert::Infallible;
+
+/// Read-only, fallible traversal over an expression tree.
+///
+/// Hooks are called before the children of a node are walked.
+pub trait ExpressionVisitor {
+    /// Error that aborts the traversal.
+    type Error;
+
+    /// Visit an expression; the default walks its children.
+    fn visit_expression(&mut self, expr: &Expression) -> Result<(), Self::Error> {
+        walk_expression(self, expr)
+    }
+
+    /// Visit a plain variable reference.
+    fn visit_var(&mut self, _name: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visit a layer-qualified variable (`layer::var`).
+    fn visit_layer_var(&mut self, _layer: &str, _var: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visit a phase-qualified variable (`phase::layer::var`).
+    fn visit_phase_var(
+        &mut self,
+        _phase: &str,
+        _layer: &str,
+        _var: &str,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visit a function call; its arguments are walked afterwards.
+    fn visit_function_call(
+        &mut self,
+        _name: &str,
+        _args: &[Expression],
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Dispatch `expr` to the visitor's hooks and visit its children.
+pub fn walk_expression<V: ExpressionVisitor + ?Sized>(
+    visitor: &mut V,
+    expr: &Expression,
+) -> Result<(), V::Error> {
+    match expr {
+        Expression::Boolean(_) | Expression::Int(_) | Expression::String(_) => Ok(()),
+        Expression::Var(name) => visitor.visit_var(name),
+        Expression::LayerVar { layer, var } => visitor.visit_layer_var(layer, var),
+        Expression::PhaseQualifiedVar { phase, layer, var } => {
+            visitor.visit_phase_var(phase, layer, var)
+        }
+        Expression::PhaseConstraint { constraint, .. } => visitor.visit_expression(constraint),
+        Expression::CrossPhaseRelation { expr1, expr2, .. } => {
+            visitor.visit_expression(expr1)?;
+            visitor.visit_expression(expr2)
+        }
+        Expression::BinaryOp { left, right, .. } | Expression::Logical { left, right, .. } => {
+            visitor.visit_expression(left)?;
+            visitor.visit_expression(right)
+        }
+        Expression::MapAccess { map, key } => {
+            visitor.visit_expression(map)?;
+            visitor.visit_expression(key)
+        }
+        Expression::Not(inner)
+        | Expression::Negate(inner)
+        | Expression::Cast { expr: inner, .. }
+        | Expression::Assert(inner, _) => visitor.visit_expression(inner),
+        Expression::FunctionCall { name, args } => {
+            visitor.visit_function_call(name, args)?;
+            args.iter()
+                .try_for_each(|arg| visitor.visit_expression(arg))
+        }
+        Expression::Tuple(exprs) => exprs.iter().try_for_each(|e| visitor.visit_expression(e)),
+        Expression::Conditional {
+            cond,
+            then_expr,
+            else_expr,
+        } => {
+            visitor.visit_expression(cond)?;
+            visitor.visit_expression(then_expr)?;
+            visitor.visit_expression(else_expr)
+        }
+        Expression::Quantified {
+            collection, body, ..
+        } => {
+            visitor.visit_expression(collection)?;
+            visitor.visit_expression(body)
+        }
+        Expression::Aggregate {
+            collection,
+            body,
+            filter,
+            ..
+        } => {
+            visitor.visit_expression(collection)?;
+            visitor.visit_expression(body)?;
+            filter
+                .iter()
+                .try_for_each(|filter| visitor.visit_expression(filter))
+        }
+    }
+}
+
+/// Rewriting traversal that rebuilds an expression tree.
+pub trait ExpressionFolder {
+    /// Fold an expression; the default folds its children.
+    fn fold_expression(&mut self, expr: Expression) -> Expression {
+        fold_children(self, expr)
+    }
+
+    /// Fold a plain variable reference.
+    fn fold_var(&mut self, name: String) -> Expression {
+        Expression::Var(name)
+    }
+
+    /// Fold a layer-qualified variable.
+    fn fold_layer_var(&mut self, layer: String, var: String) -> Expression {
+        Expression::LayerVar { layer, var }
+    }
+
+    /// Fold a phase-qualified variable.
+    fn fold_phase_var(&mut self, phase: String, layer: String, var: String) -> Expression {
+        Expression::PhaseQualifiedVar { phase, layer, var }
+    }
+}
+
+/// Dispatch `expr` to the folder's hooks and fold its children.
+pub fn fold_children<F: ExpressionFolder + ?Sized>(folder: &mut F, expr: Expression) -> Expression {
+    match expr {
+        Expression::Boolean(_) | Expression::Int(_) | Expression::String(_) => expr,
+        Expression::Var(name) => folder.fold_var(name),
+        Expression::LayerVar { layer, var } => folder.fold_layer_var(layer, var),
+        Expression::PhaseQualifiedVar { phase, layer, var } => {
+            folder.fold_phase_var(phase, layer, var)
+        }
+        Expression::PhaseConstraint { phase, constraint } => Expression::PhaseConstraint {
+            phase,
+            constraint: fold_boxed(folder, *constraint),
+        },
+        Expression::CrossPhaseRelation {
+            phase1,
+            expr1,
+            phase2,
+            expr2,
+            op,
+        } => Expression::CrossPhaseRelation {
+            phase1,
+            expr1: fold_boxed(folder, *expr1),
+            phase2,
+            expr2: fold_boxed(folder, *expr2),
+            op,
+        },
+        Expression::BinaryOp { left, op, right } => Expression::BinaryOp {
+            left: fold_boxed(folder, *left),
+            op,
+            right: fold_boxed(folder, *right),
+        },
+        Expression::Logical { left, op, right } => Expression::Logical {
+            left: fold_boxed(folder, *left),
+            op,
+            right: fold_boxed(folder, *right),
+        },
+        Expression::Not(inner) => Expression::Not(fold_boxed(folder, *inner)),
+        Expression::Negate(inner) => Expression::Negate(fold_boxed(folder, *inner)),
+        Expression::FunctionCall { name, args } => Expression::FunctionCall {
+            name,
+            args: args
+                .into_iter()
+                .map(|a| folder.fold_expression(a))
+                .collect(),
+        },
+        Expression::Tuple(exprs) => Expression::Tuple(
+            exprs
+                .into_iter()
+                .map(|e| folder.fold_expression(e))
+                .collect(),
+        ),
+        Expression::Conditional {
+            cond,
+            then_expr,
+            else_expr,
+        } => Expression::Conditional {
+            cond: fold_boxed(folder, *cond),
+            then_expr: fold_boxed(folder, *then_expr),
+            else_expr: fold_boxed(folder, *else_expr),
+        },
+        Expression::Cast { expr, target } => Expression::Cast {
+            expr: fold_boxed(folder, *expr),
+            target,
+        },
+        Expression::MapAccess { map, key } => Expression::MapAccess {
+            map: fold_boxed(folder, *map),
+            key: fold_boxed(folder, *key),
+        },
+        Expression::Assert(constraint, message) => {
+            Expression::Assert(fold_boxed(folder, *constraint), message)
+        }
+        Expression::Quantified {
+            quantifier,
+            var,
+            collection,
+            body,
+        } => Expression::Quantified {
+            quantifier,
+            var,
+            collection: fold_boxed(folder, *collection),
+            body: fold_boxed(folder, *body),
+        },
+        Expression::Aggregate {
+            op,
+            binder,
+            collection,
+            body,
+            filter,
+        } => Expression::Aggregate {
+            op,
+            binder,
+            collection: fold_boxed(folder, *collection),
+            body: fold_boxed(folder, *body),
+            filter: filter.map(|filter| fold_boxed(folder, *filter)),
+        },
+    }
+}
+
+fn fold_boxed<F: ExpressionFolder + ?Sized>(folder: &mut F, expr: Expression) -> Box<Expression> {
+    Box::new(folder.fold_expression(expr))
+}
+
+/// Collects variable names and called functions.
+///
+/// Names bound by an enclosing quantifier are not variables of the program.
+#[derive(Default)]
+struct NameCollector {
+    variables: BTreeSet<String>,
+    functions: BTreeSet<String>,
+    bound: Vec<String>,
+}
+
+impl ExpressionVisitor for NameCollector {
+    type Error = Infallible;
+
+    fn visit_expression(&mut self, expr: &Expression) -> Result<(), Infallible> {
+        let (var, collection, scoped) = match expr {
+            Expression::Quantified {
+                var,
+                collection,
+                body,
+                ..
+            } => (var, collection, vec![body]),
+            Expression::Aggregate {
+                binder,
+                collection,
+                body,
+                filter,
+                ..
+            } => (
+                binder,
+                collection,
+                std::iter::once(body).chain(filter).collect(),
+            ),
+            _ => return walk_expression(self, expr),
+        };
+        self.visit_expression(collection)?;
+        self.bound.push(var.clone());
+        for expr in scoped {
+            self.visit_expression(expr)?;
+        }
+        self.bound.pop();
+        Ok(())
+    }
+
+    fn visit_var(&mut self, name: &str) -> Result<(), Infallible> {
+        if !self.bound.iter().any(|bound| bound == name) {
+            self.variables.insert(name.to_string());
+        }
+        Ok(())
+    }
+
+    fn visit_layer_var(&mut self, layer: &str, var: &str) -> Result<(), Infallible> {
+        self.variables.insert(format!("{}::{}", layer, var));
+        Ok(())
+    }
+
+    fn visit_phase_var(&mut self, phase: &str, layer: &str, var: &str) -> Result<(), Infallible> {
+        self.variables
+            .insert(format!("{}::{}::{}", phase, layer, var));
+        Ok(())
+    }
+
+    fn visit_function_call(&mut self, name: &str, _args: &[Expression]) -> Result<(), Infallible> {
+        self.functions.insert(name.to_string());
+        Ok(())
+    }
+}
+
+/// Renames the variable part of every variable reference, leaving names
+/// bound by an enclosing quantifier alone.
+struct VarMapper<F> {
+    rename: F,
+    bound: Vec<String>,
+}
+
+impl<F: FnMut(&str) -> String> ExpressionFolder for VarMapper<F> {
+    fn fold_expression(&mut self, expr: Expression) -> Expression {
+        match expr {
+            Expression::Quantified {
+                quantifier,
+                var,
+                collection,
+                body,
+            } => {
+                let collection = fold_boxed(self, *collection);
+                self.bound.push(var);
+                let body = fold_boxed(self, *body);
+                let var = self.bound.pop().unwrap_or_default();
+                Expression::Quantified {
+                    quantifier,
+                    var,
+                    collection,
+                    body,
+                }
+            }
+            Expression::Aggregate {
+                op,
+                binder,
+                collection,
+                body,
+                filter,
+            } => {
+                let collection = fold_boxed(self, *collection);
+                self.bound.push(binder);
+                let body = fold_boxed(self, *body);
+                let filter = filter.map(|filter| fold_boxed(self, *filter));
+                let binder = self.bound.pop().unwrap_or_default();
+                Expression::Aggregate {
+                    op,
+                    binder,
+                    collection,
+                    body,
+                    filter,
+                }
+            }
+            other => fold_children(self, other),
+        }
+    }
+
+    fn fold_var(&mut self, name: String) -> Expression {
+        if self.bound.contains(&name) {
+            return Expression::Var(name);
+        }
+        Expression::Var((self.rename)(&name))
+    }
+
+    fn fold_layer_var(&mut self, layer: String, var: String) -> Expression {
+        Expression::LayerVar {
+            layer,
+            var: (self.rename)(&var),
+        }
+    }
+
+    fn fold_phase_var(&mut self, phase: String, layer: String, var: String) -> Expression {
+        Expression::PhaseQualifiedVar {
+            phase,
+            layer,
+            var: (self.rename)(&var),
+        }
+    }
+}
+
+/// Stops the walk at the first aggregation.
+struct AggregateFinder;
+
+impl ExpressionVisitor for AggregateFinder {
+    type Error = ();
+
+    fn visit_expression(&mut self, expr: &Expression) -> Result<(), ()> {
+        match expr {
+            Expression::Aggregate { .. } => Err(()),
+            _ => walk_expression(self, expr),
+        }
+    }
+}
+
+struct QuantifierFinder;
+
+impl ExpressionVisitor for QuantifierFinder {
+    type Error = ();
+
+    fn visit_expression(&mut self, expr: &Expression) -> Result<(), ()> {
+        match expr {
+            Expression::Quantified { .. } => Err(()),
+            _ => walk_expression(self, expr),
+        }
+    }
+}
+
+impl Expression {
+    fn collect_names(&self) -> NameCollector {
+        let mut collector = NameCollector::default();
+        let Ok(()) = collector.visit_expression(self);
+        collector
+    }
+
+    /// All variables referenced, with layer/phase qualifiers joined by `::`.
+    pub fn variables(&self) -> BTreeSet<String> {
+        self.collect_names().variables
+    }
+
+    /// Names of all functions called.
+    pub fn functions_called(&self) -> BTreeSet<String> {
+        self.collect_names().functions
+    }
+
+    /// Whether any sub-expression is an [`Expression::Aggregate`].
+    pub fn contains_aggregate(&self) -> bool {
+        AggregateFinder.visit_expression(self).is_err()
+    }
+
+    /// Whether any sub-expression is an [`Expression::Quantified`].
+    pub fn contains_quantifier(&self) -> bool {
+        QuantifierFinder.visit_expression(self).is_err()
+    }
+
+    /// Rename every variable reference (the unqualified name for layer- and
+    /// phase-qualified variables).
+    pub fn map_vars(self, f: impl FnMut(&str) -> String) -> Expression {
+        VarMapper {
+            rename: f,
+            bound: Vec::new(),
+        }
+        .fold_expression(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{BinaryOp, LogicalOp};
+
+    fn sample() -> Expression {
+        Expression::Logical {
+            left: Box::new(Expression::BinaryOp {
+                left: Box::new(Expression::PhaseQualifiedVar {
+                    phase: "validation".to_string(),
+                    layer: "account".to_string(),
+                    var: "balance".to_string(),
+                }),
+                op: BinaryOp::Gte,
+                right: Box::new(Expression::LayerVar {
+                    layer: "paymaster".to_string(),
+                    var: "deposit".to_string(),
+                }),
+            }),
+            op: LogicalOp::And,
+            right: Box::new(Expression::FunctionCall {
+                name: "max".to_string(),
+                args: vec![Expression::Var("supply".to_string()), Expression::Int(1)],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_variables_include_qualified_names() {
+        let expected: BTreeSet<String> = [
+            "validation::account::balance",
+            "paymaster::deposit",
+            "supply",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        assert_eq!(sample().variables(), expected);
+        assert_eq!(
+            sample().functions_called().into_iter().collect::<Vec<_>>(),
+            vec!["max".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_map_vars_renames_every_reference() {
+        let renamed = sample().map_vars(|name| format!("old_{}", name));
+        assert_eq!(
+            renamed.to_string(),
+            "((validation::account::old_balance >= paymaster::old_deposit) && max(old_supply, 1))"
+        );
+    }
+
+    #[test]
+    fn test_quantifier_bound_names_are_not_variables() {
+        let expr = Expression::Quantified {
+            quantifier: crate::model::Quantifier::Sum,
+            var: "d".to_string(),
+            collection: Box::new(Expression::Var("deposits".to_string())),
+            body: Box::new(Expression::BinaryOp {
+                left: Box::new(Expression::Var("d".to_string())),
+                op: BinaryOp::Lte,
+                right: Box::new(Expression::Var("cap".to_string())),
+            }),
+        };
+        let expected: BTreeSet<String> =
+            ["cap", "deposits"].into_iter().map(String::from).collect();
+        assert_eq!(expr.variables(), expected);
+        assert!(expr.contains_quantifier());
+        assert!(!expr.contains_aggregate());
+        assert_eq!(
+            expr.map_vars(|name| format!("old_{}", name)).to_string(),
+            "sum(d in old_deposits: (d <= old_cap))"
+        );
+    }
+
+    #[test]
+    fn test_aggregate_binder_is_not_a_variable() {
+        let expr = Expression::Aggregate {
+            op: crate::model::AggregateOp::Sum,
+            binder: "d".to_string(),
+            collection: Box::new(Expression::Var("deposits".to_string())),
+            body: Box::new(Expression::Var("d".to_string())),
+            filter: Some(Box::new(Expression::BinaryOp {
+                left: Box::new(Expression::Var("d".to_string())),
+                op: BinaryOp::Gt,
+                right: Box::new(Expression::Var("floor".to_string())),
+            })),
+        };
+        let expected: BTreeSet<String> = ["deposits", "floor"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(expr.variables(), expected);
+        assert!(expr.contains_aggregate());
+        assert!(!Expression::Var("d".to_string()).contains_aggregate());
+        assert_eq!(
+            expr.map_vars(|name| format!("old_{}", name)).to_string(),
+            "sum(d for d in old_deposits if (d > old_floor))"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! Property tests for lazy evaluation of conditional expressions.
+
+use invar_core::model::{BinaryOp, Expression, LogicalOp};
+use invar_core::{EvalResult, Evaluator, ExecutionContext, Type, Value};
+use proptest::prelude::*;
+
+fn evaluate(expr: &Expression) -> EvalResult<Value> {
+    Evaluator::new(ExecutionContext::new()).evaluate(expr)
+}
+
+fn conditional(cond: Expression, then_expr: Expression, else_expr: Expression) -> Expression {
+    Expression::Conditional {
+        cond: Box::new(cond),
+        then_expr: Box::new(then_expr),
+        else_expr: Box::new(else_expr),
+    }
+}
+
+/// Boolean expressions that always evaluate without error
+fn arb_bool_expr() -> impl Strategy<Value = Expression> {
+    // Unsigned on both sides: comparing signed with unsigned is a type error
+    let comparison = (
+        0i128..1000,
+        prop_oneof![
+            Just(BinaryOp::Eq),
+            Just(BinaryOp::Neq),
+            Just(BinaryOp::Lt),
+            Just(BinaryOp::Gt),
+            Just(BinaryOp::Lte),
+            Just(BinaryOp::Gte),
+        ],
+        0i128..1000,
+    )
+        .prop_map(|(left, op, right)| Expression::BinaryOp {
+            left: Box::new(Expression::Int(left)),
+            op,
+            right: Box::new(Expression::Int(right)),
+        });
+    let leaf = prop_oneof![any::<bool>().prop_map(Expression::Boolean), comparison];
+    leaf.prop_recursive(3, 16, 2, |inner| {
+        prop_oneof![
+            inner.clone().prop_map(|e| Expression::Not(Box::new(e))),
+            (
+                inner.clone(),
+                prop_oneof![Just(LogicalOp::And), Just(LogicalOp::Or)],
+                inner,
+            )
+                .prop_map(|(left, op, right)| Expression::Logical {
+                    left: Box::new(left),
+                    op,
+                    right: Box::new(right),
+                }),
+        ]
+    })
+}
+
+/// Numeric expressions up to `depth` levels deep. Many fail to evaluate:
+/// negating unsigned values overflows and narrowing casts can overflow.
+fn arb_numeric_expr(depth: u32) -> impl Strategy<Value = Expression> {
+    let leaf = any::<i128>().prop_map(Expression::Int);
+    leaf.prop_recursive(depth, 32, 3, |inner| {
+        prop_oneof![
+            inner.clone().prop_map(|e| Expression::Negate(Box::new(e))),
+            (
+                inner.clone(),
+                prop_oneof![
+                    Just(Type::U64),
+                    Just(Type::I64),
+                    Just(Type::U128),
+                    Just(Type::U256)
+                ],
+            )
+                .prop_map(|(e, target)| Expression::Cast {
+                    expr: Box::new(e),
+                    target,
+                }),
+            (arb_bool_expr(), inner.clone(), inner)
+                .prop_map(|(cond, a, b)| conditional(cond, a, b)),
+        ]
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(500))]
+
+    #[test]
+    fn prop_true_selects_then_branch(
+        a in arb_numeric_expr(3),
+        b in arb_numeric_expr(3),
+    ) {
+        // `b` is never evaluated, so its errors cannot surface
+        let expr = conditional(Expression::Boolean(true), a.clone(), b);
+        prop_assert_eq!(evaluate(&expr), evaluate(&a));
+    }
+
+    #[test]
+    fn prop_false_selects_else_branch(
+        a in arb_numeric_expr(3),
+        b in arb_numeric_expr(3),
+    ) {
+        let expr = conditional(Expression::Boolean(false), a, b.clone());
+        prop_assert_eq!(evaluate(&expr), evaluate(&b));
+    }
+
+    #[test]
+    fn prop_equal_branches_ignore_condition(
+        cond in arb_bool_expr(),
+        a in arb_numeric_expr(3),
+    ) {
+        let expr = conditional(cond, a.clone(), a.clone());
+        prop_assert_eq!(evaluate(&expr), evaluate(&a));
+    }
+
+    #[test]
+    fn prop_condition_or_its_negation_holds(cond in arb_bool_expr()) {
+        // `if c then c else !c` is `c || !c`: true either way
+        let negated = Expression::Not(Box::new(cond.clone()));
+        let expr = conditional(cond.clone(), cond, negated);
+        prop_assert_eq!(evaluate(&expr), Ok(Value::Bool(true)));
+    }
+}
@@ -9,6 +9,7 @@ use pest_derive::Parser;
 #[grammar_inline = r#"
 WHITESPACE = _{ " " | "\t" | NEWLINE }
 NEWLINE = @{ "\r\n" | "\n" }
+COMMENT = _{ "//" ~ (!NEWLINE ~ ANY)* }
 
 // Identifiers and literals
 identifier = @{ (ASCII_ALPHA | "_") ~ (ASCII_ALPHANUMERIC | "_")* }
@@ -25,29 +26,64 @@ gte = { ">=" }
 and = { "&&" }
 or = { "||" }
 not = { "!" }
+// A `-` directly before a digit belongs to the integer literal
+negate = @{ "-" ~ !ASCII_DIGIT }
 
 // Literals
 boolean = @{ "true" | "false" }
+string_literal = @{ "\"" ~ (!"\"" ~ ANY)* ~ "\"" }
 
-// Qualified identifiers with optional layer scope (layer::identifier)
-qualified_id = { layer_name ~ "::" ~ identifier }
+// Type names usable in casts
+type_name = @{ ("u128" | "u256" | "u64" | "i64" | "bool" | "address") ~ !(ASCII_ALPHANUMERIC | "_") }
+
+// Qualified identifiers with optional layer scope (layer::identifier); the
+// scope is an AA layer or any other namespace, such as a chain (l1::locked)
+qualified_id = { identifier ~ "::" ~ identifier }
 simple_id = { identifier }
 var_id = { qualified_id | simple_id }
 
 // Function call - must be tried before identifier
-function_call = { identifier ~ "(" ~ (expr ~ ("," ~ expr)*)? ~ ")" }
+function_call = { identifier ~ "(" ~ (expr ~ ("," ~ expr)* ~ ","?)? ~ ")" }
+
+// Quantifiers over a collection: sum(x in deposits: x), count(b in balances: b > 0)
+quantifier = @{ ("forall" | "exists" | "sum" | "count" | "min" | "max") ~ !(ASCII_ALPHANUMERIC | "_") }
+quantified_expr = { quantifier ~ "(" ~ identifier ~ "in" ~ expr ~ ":" ~ expr ~ ")" }
+
+// Filtered aggregations: sum(d for d in deposits if d > 0), the filter optional
+aggregate_op = @{ ("sum" | "count" | "max") ~ !(ASCII_ALPHANUMERIC | "_") }
+aggregate_for = @{ "for" ~ !(ASCII_ALPHANUMERIC | "_") }
+aggregate_if = @{ "if" ~ !(ASCII_ALPHANUMERIC | "_") }
+aggregate_expr = { aggregate_op ~ "(" ~ expr ~ aggregate_for ~ identifier ~ range_in ~ expr ~ (aggregate_if ~ expr)? ~ ")" }
+
+// Atoms: aggregations, quantifiers, function calls, literals, or identifiers (in order of specificity)
+atom = _{ aggregate_expr | quantified_expr | function_call | boolean | integer | string_literal | var_id }
 
-// Atoms: function calls, literals, or identifiers (in order of specificity)
-atom = _{ function_call | boolean | integer | var_id }
+// Conditional: if <cond> then <value> else <value>
+conditional_expr = { "if" ~ expr ~ "then" ~ expr ~ "else" ~ expr }
+
+// Assertion with a failure message: assert(balance > 0, "balance must be positive")
+assert_expr = { "assert" ~ "(" ~ expr ~ "," ~ string_literal ~ ")" }
 
 // Primary expressions with parentheses
-primary = { "(" ~ expr ~ ")" | atom }
+primary = { "(" ~ expr ~ ")" | assert_expr | conditional_expr | atom }
+
+// Mapping lookups: balances[owner]; chained by iteration (PEG has no left recursion)
+map_access = { primary ~ ("[" ~ expr ~ "]")* }
 
 // Unary operators
-unary = { not* ~ primary }
+unary = { (not | negate)* ~ map_access }
+
+// Explicit casts: expr as u128
+cast_expr = { unary ~ ("as" ~ type_name)* }
+
+// Range shorthand: x in MIN..=MAX (inclusive) or x in MIN..MAX (exclusive)
+range_in = @{ "in" ~ !(ASCII_ALPHANUMERIC | "_") }
+range_inclusive = { "..=" }
+range_exclusive = { ".." }
+range_expr = { cast_expr ~ range_in ~ cast_expr ~ (range_inclusive | range_exclusive) ~ cast_expr }
 
 // Comparison operators
-comparison = { unary ~ ((eq | neq | lte | gte | lt | gt) ~ unary)* }
+comparison = { range_expr | cast_expr ~ ((eq | neq | lte | gte | lt | gt) ~ cast_expr)* }
 
 // Logical AND
 logical_and = { comparison ~ (and ~ comparison)* }
@@ -58,12 +94,29 @@ logical_or = { logical_and ~ (or ~ logical_and)* }
 // Main expression
 expr = { logical_or }
 
+// Enforcement annotation: @debug_only invariant name { ... }
+enforcement_level = @{ "always" | "debug_only" | "simulation_only" }
+enforcement = { "@" ~ enforcement_level }
+
+// Tags line before a definition: tags: defi, token or tags: [defi, token]
+tag = @{ (ASCII_ALPHANUMERIC | "_" | "-")+ }
+tag_list = { "tags" ~ ":" ~ ("[" ~ tag ~ ("," ~ tag)* ~ "]" | tag ~ ("," ~ tag)*) }
+
+// Disabled invariants are parsed but skipped by commands: @disabled
+disabled = @{ "@disabled" ~ !(ASCII_ALPHANUMERIC | "_") }
+
 // Top-level invariant
 invariant_def = {
-    "invariant" ~ identifier ~ ("(" ~ layer_name ~ ("," ~ layer_name)* ~ ")")? ~ "{" ~ expr ~ "}"
+    tag_list? ~ disabled? ~ enforcement? ~ "invariant" ~ identifier ~ ("(" ~ layer_name ~ ("," ~ layer_name)* ~ ")")? ~ "{" ~ expr ~ "}"
 }
 
 file = { SOI ~ invariant_def+ ~ EOI }
+
+// A single definition spanning its whole input, for error recovery
+definition = { SOI ~ invariant_def ~ EOI }
+
+// A bare expression, such as a `@pre` condition in a doc comment
+expression = { SOI ~ expr ~ EOI }
 "#]
 pub struct InvarGrammar;
 
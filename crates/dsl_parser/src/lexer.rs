@@ -1,37 +1,534 @@
-//! Lexer for the Invar DSL (uses pest internally).
+//! Lexer for the Invar DSL.
+//!
+//! Parsing itself is driven by the pest grammar; this tokenizer exists so the
+//! token stream can be inspected when diagnosing grammar issues.
 
-/// Token type placeholder; pest handles tokenization.
-/// This module is included for future extensibility.
+use thiserror::Error;
+
+/// A single token with its source span.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Token {
-    /// Token type.
-    pub token_type: TokenType,
-    /// Source position (line, col).
-    pub position: (usize, usize),
+    /// Token kind (and value, for literals and identifiers).
+    pub kind: TokenKind,
+    /// Where the token appears in the input.
+    pub span: Span,
+}
+
+/// Source span of a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Byte offset of the first character.
+    pub start: usize,
+    /// Byte offset one past the last character.
+    pub end: usize,
+    /// 1-based line of the first character.
+    pub line: usize,
+    /// 1-based column of the first character.
+    pub column: usize,
 }
 
-/// Token types.
+/// Token kinds.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum TokenType {
-    /// Invariant keyword.
+pub enum TokenKind {
+    /// `invariant` keyword.
     Invariant,
+    /// `if` keyword.
+    If,
+    /// `then` keyword.
+    Then,
+    /// `else` keyword.
+    Else,
+    /// `as` keyword.
+    As,
     /// Identifier.
-    Identifier(String),
+    Ident(String),
     /// Integer literal.
-    Integer(i128),
+    Int(i128),
     /// Boolean literal.
-    Boolean(bool),
-    /// Operator.
-    Operator(String),
-    /// Left brace.
+    Bool(bool),
+    /// String literal (without quotes).
+    Str(String),
+    /// `==`
+    Eq,
+    /// `!=`
+    Neq,
+    /// `<`
+    Lt,
+    /// `>`
+    Gt,
+    /// `<=`
+    Lte,
+    /// `>=`
+    Gte,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+    /// `!`
+    Not,
+    /// `-` not followed by a digit, negating the operand after it.
+    Minus,
+    /// `::`
+    PathSep,
+    /// `:`, separating a quantifier's binding from its body, or `tags` from
+    /// its list.
+    Colon,
+    /// `..`, an exclusive range.
+    Range,
+    /// `..=`, an inclusive range.
+    RangeInclusive,
+    /// `{`
     LeftBrace,
-    /// Right brace.
+    /// `}`
     RightBrace,
-    /// Left paren.
+    /// `(`
     LeftParen,
-    /// Right paren.
+    /// `)`
     RightParen,
-    /// Comma.
+    /// `[`
+    LeftBracket,
+    /// `]`
+    RightBracket,
+    /// `,`
     Comma,
-    /// End of file.
+    /// `@`, introducing an annotation.
+    At,
+    /// End of input.
     Eof,
 }
+
+impl TokenKind {
+    /// Human-readable name of the token.
+    pub fn display(&self) -> String {
+        match self {
+            Self::Invariant => "keyword `invariant`".to_string(),
+            Self::If => "keyword `if`".to_string(),
+            Self::Then => "keyword `then`".to_string(),
+            Self::Else => "keyword `else`".to_string(),
+            Self::As => "keyword `as`".to_string(),
+            Self::Ident(name) => format!("identifier `{}`", name),
+            Self::Int(value) => format!("integer `{}`", value),
+            Self::Bool(value) => format!("boolean `{}`", value),
+            Self::Str(value) => format!("string \"{}\"", value),
+            Self::Eq => "`==`".to_string(),
+            Self::Neq => "`!=`".to_string(),
+            Self::Lt => "`<`".to_string(),
+            Self::Gt => "`>`".to_string(),
+            Self::Lte => "`<=`".to_string(),
+            Self::Gte => "`>=`".to_string(),
+            Self::And => "`&&`".to_string(),
+            Self::Or => "`||`".to_string(),
+            Self::Not => "`!`".to_string(),
+            Self::Minus => "`-`".to_string(),
+            Self::PathSep => "`::`".to_string(),
+            Self::Colon => "`:`".to_string(),
+            Self::Range => "`..`".to_string(),
+            Self::RangeInclusive => "`..=`".to_string(),
+            Self::LeftBrace => "`{`".to_string(),
+            Self::RightBrace => "`}`".to_string(),
+            Self::LeftParen => "`(`".to_string(),
+            Self::RightParen => "`)`".to_string(),
+            Self::LeftBracket => "`[`".to_string(),
+            Self::RightBracket => "`]`".to_string(),
+            Self::Comma => "`,`".to_string(),
+            Self::At => "`@`".to_string(),
+            Self::Eof => "end of input".to_string(),
+        }
+    }
+}
+
+/// Tokenization error.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message} at line {line}, column {column}")]
+pub struct LexError {
+    /// What went wrong.
+    pub message: String,
+    /// 1-based line.
+    pub line: usize,
+    /// 1-based column.
+    pub column: usize,
+}
+
+/// Split DSL input into tokens, ending with `TokenKind::Eof`.
+///
+/// Whitespace and `//` comments are skipped. In a `tags:` header, bracketed
+/// or not, tags are identifiers that may contain `-` (`cross-chain`).
+pub fn tokenize(input: &str) -> Result<Vec<Token>, LexError> {
+    let chars: Vec<(usize, char)> = input.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut line = 1;
+    let mut line_start = 0;
+    // Inside a `tags:` header, and whether a tag comes next
+    let mut tag_list = false;
+    let mut expect_tag = false;
+
+    while i < chars.len() {
+        let (start, c) = chars[i];
+        let column = input[line_start..start].chars().count() + 1;
+        let peek = chars.get(i + 1).map(|(_, c)| *c);
+
+        if c == '\n' {
+            i += 1;
+            line += 1;
+            line_start = start + 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '/' && peek == Some('/') {
+            while i < chars.len() && chars[i].1 != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+
+        let two_char = match (c, peek) {
+            ('=', Some('=')) => Some(TokenKind::Eq),
+            ('!', Some('=')) => Some(TokenKind::Neq),
+            ('<', Some('=')) => Some(TokenKind::Lte),
+            ('>', Some('=')) => Some(TokenKind::Gte),
+            ('&', Some('&')) => Some(TokenKind::And),
+            ('|', Some('|')) => Some(TokenKind::Or),
+            (':', Some(':')) => Some(TokenKind::PathSep),
+            _ => None,
+        };
+
+        let (kind, len) = if expect_tag && is_tag_char(c) {
+            let mut end = i + 1;
+            while end < chars.len() && is_tag_char(chars[end].1) {
+                end += 1;
+            }
+            let tag = &input[start..byte_offset(input, &chars, end)];
+            (TokenKind::Ident(tag.to_string()), end - i)
+        } else if c == '.' && peek == Some('.') {
+            match chars.get(i + 2) {
+                Some((_, '=')) => (TokenKind::RangeInclusive, 3),
+                _ => (TokenKind::Range, 2),
+            }
+        } else if let Some(kind) = two_char {
+            (kind, 2)
+        } else if let Some(kind) = single_char_token(c) {
+            (kind, 1)
+        } else if c == '-' && !peek.is_some_and(|p| p.is_ascii_digit()) {
+            (TokenKind::Minus, 1)
+        } else if c == '"' {
+            let Some(len) = chars[i + 1..].iter().position(|(_, c)| *c == '"') else {
+                return Err(LexError {
+                    message: "unterminated string literal".to_string(),
+                    line,
+                    column,
+                });
+            };
+            let text =
+                &input[byte_offset(input, &chars, i + 1)..byte_offset(input, &chars, i + 1 + len)];
+            (TokenKind::Str(text.to_string()), len + 2)
+        } else if c.is_ascii_digit() || (c == '-' && peek.is_some_and(|p| p.is_ascii_digit())) {
+            let mut end = i + 1;
+            while end < chars.len() && chars[end].1.is_ascii_digit() {
+                end += 1;
+            }
+            let text = &input[start..byte_offset(input, &chars, end)];
+            let value = text.parse::<i128>().map_err(|_| LexError {
+                message: format!("integer literal `{}` out of range", text),
+                line,
+                column,
+            })?;
+            (TokenKind::Int(value), end - i)
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = i + 1;
+            while end < chars.len() && (chars[end].1.is_ascii_alphanumeric() || chars[end].1 == '_')
+            {
+                end += 1;
+            }
+            let word = &input[start..byte_offset(input, &chars, end)];
+            (keyword_or_ident(word), end - i)
+        } else {
+            return Err(LexError {
+                message: format!("unexpected character `{}`", c),
+                line,
+                column,
+            });
+        };
+
+        tag_list = match kind {
+            TokenKind::Colon => ends_with_tags_keyword(&tokens),
+            TokenKind::Ident(_) | TokenKind::Comma | TokenKind::LeftBracket => tag_list,
+            _ => false,
+        };
+        expect_tag = tag_list
+            && matches!(
+                kind,
+                TokenKind::Colon | TokenKind::Comma | TokenKind::LeftBracket
+            );
+
+        let end = byte_offset(input, &chars, i + len);
+        tokens.push(Token {
+            kind,
+            span: Span {
+                start,
+                end,
+                line,
+                column,
+            },
+        });
+        i += len;
+    }
+
+    let column = input[line_start..].chars().count() + 1;
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        span: Span {
+            start: input.len(),
+            end: input.len(),
+            line,
+            column,
+        },
+    });
+    Ok(tokens)
+}
+
+fn single_char_token(c: char) -> Option<TokenKind> {
+    match c {
+        '<' => Some(TokenKind::Lt),
+        '>' => Some(TokenKind::Gt),
+        '!' => Some(TokenKind::Not),
+        '{' => Some(TokenKind::LeftBrace),
+        '}' => Some(TokenKind::RightBrace),
+        '(' => Some(TokenKind::LeftParen),
+        ')' => Some(TokenKind::RightParen),
+        '[' => Some(TokenKind::LeftBracket),
+        ']' => Some(TokenKind::RightBracket),
+        ',' => Some(TokenKind::Comma),
+        ':' => Some(TokenKind::Colon),
+        '@' => Some(TokenKind::At),
+        _ => None,
+    }
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Whether `tokens` ends with `tags` as the first token of its line, so a
+/// following `:` opens a tag list rather than a quantifier body.
+fn ends_with_tags_keyword(tokens: &[Token]) -> bool {
+    let Some((last, rest)) = tokens.split_last() else {
+        return false;
+    };
+    matches!(&last.kind, TokenKind::Ident(word) if word == "tags")
+        && rest
+            .last()
+            .is_none_or(|before| before.span.line < last.span.line)
+}
+
+fn keyword_or_ident(word: &str) -> TokenKind {
+    match word {
+        "invariant" => TokenKind::Invariant,
+        "if" => TokenKind::If,
+        "then" => TokenKind::Then,
+        "else" => TokenKind::Else,
+        "as" => TokenKind::As,
+        "true" => TokenKind::Bool(true),
+        "false" => TokenKind::Bool(false),
+        _ => TokenKind::Ident(word.to_string()),
+    }
+}
+
+/// Byte offset of the `idx`-th character (or the end of input).
+fn byte_offset(input: &str, chars: &[(usize, char)], idx: usize) -> usize {
+    chars.get(idx).map_or(input.len(), |(offset, _)| *offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<TokenKind> {
+        tokenize(input)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize_comparison() {
+        assert_eq!(
+            kinds("balance >= 0"),
+            vec![
+                TokenKind::Ident("balance".to_string()),
+                TokenKind::Gte,
+                TokenKind::Int(0),
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_spans_and_comments() {
+        let tokens = tokenize("// header\ninvariant A { bundler::nonce != -1 }").unwrap();
+        assert_eq!(tokens[0].kind, TokenKind::Invariant);
+        assert_eq!((tokens[0].span.line, tokens[0].span.column), (2, 1));
+        assert_eq!(tokens[4].kind, TokenKind::PathSep);
+        assert_eq!(tokens[7].kind, TokenKind::Int(-1));
+        assert_eq!(tokens[7].span.column, 33);
+    }
+
+    #[test]
+    fn test_tokenize_string_literal() {
+        assert_eq!(
+            kinds(r#"owner == "system""#),
+            vec![
+                TokenKind::Ident("owner".to_string()),
+                TokenKind::Eq,
+                TokenKind::Str("system".to_string()),
+                TokenKind::Eof,
+            ]
+        );
+        assert_eq!(
+            tokenize("\"open").unwrap_err().message,
+            "unterminated string literal"
+        );
+    }
+
+    #[test]
+    fn test_tokenize_minus() {
+        assert_eq!(
+            kinds("- -5"),
+            vec![TokenKind::Minus, TokenKind::Int(-5), TokenKind::Eof]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_range() {
+        assert_eq!(
+            kinds("fee in 0..=100"),
+            vec![
+                TokenKind::Ident("fee".to_string()),
+                TokenKind::Ident("in".to_string()),
+                TokenKind::Int(0),
+                TokenKind::RangeInclusive,
+                TokenKind::Int(100),
+                TokenKind::Eof,
+            ]
+        );
+        assert_eq!(kinds("0..n")[1], TokenKind::Range);
+    }
+
+    #[test]
+    fn test_tokenize_map_access() {
+        assert_eq!(
+            kinds("balances[owner]"),
+            vec![
+                TokenKind::Ident("balances".to_string()),
+                TokenKind::LeftBracket,
+                TokenKind::Ident("owner".to_string()),
+                TokenKind::RightBracket,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_quantifier() {
+        let ident = |name: &str| TokenKind::Ident(name.to_string());
+        assert_eq!(
+            kinds("sum(x in xs: x)"),
+            vec![
+                ident("sum"),
+                TokenKind::LeftParen,
+                ident("x"),
+                ident("in"),
+                ident("xs"),
+                TokenKind::Colon,
+                ident("x"),
+                TokenKind::RightParen,
+                TokenKind::Eof,
+            ]
+        );
+        assert_eq!(
+            kinds("forall(a in vault::accounts: a >= 0)")[4..8],
+            [
+                ident("vault"),
+                TokenKind::PathSep,
+                ident("accounts"),
+                TokenKind::Colon,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_tags_header() {
+        let ident = |name: &str| TokenKind::Ident(name.to_string());
+        assert_eq!(
+            kinds("tags: defi, cross-chain\ninvariant A { x-1 > 0 }")[..6],
+            [
+                ident("tags"),
+                TokenKind::Colon,
+                ident("defi"),
+                TokenKind::Comma,
+                ident("cross-chain"),
+                TokenKind::Invariant,
+            ]
+        );
+        // Only the header reads `-` as part of a word
+        assert_eq!(
+            kinds("tags: v1-2\ninvariant A { x-1 > 0 }")[2],
+            ident("v1-2")
+        );
+        assert_eq!(
+            kinds("invariant A { x-1 > 0 }")[3..5],
+            [ident("x"), TokenKind::Int(-1)]
+        );
+        assert_eq!(
+            kinds("sum(t in tags: t-1)")[6..8],
+            [ident("t"), TokenKind::Int(-1)]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_bracketed_tags() {
+        let ident = |name: &str| TokenKind::Ident(name.to_string());
+        assert_eq!(
+            kinds("tags: [fast, critical-path]\n@disabled invariant A { x-1 > 0 }")[..9],
+            [
+                ident("tags"),
+                TokenKind::Colon,
+                TokenKind::LeftBracket,
+                ident("fast"),
+                TokenKind::Comma,
+                ident("critical-path"),
+                TokenKind::RightBracket,
+                TokenKind::At,
+                ident("disabled"),
+            ]
+        );
+        assert_eq!(
+            kinds("tags: [fast]\ninvariant A { x-1 > 0 }")[8..10],
+            [ident("x"), TokenKind::Int(-1)]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_annotation() {
+        assert_eq!(
+            kinds("@debug_only invariant"),
+            vec![
+                TokenKind::At,
+                TokenKind::Ident("debug_only".to_string()),
+                TokenKind::Invariant,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_rejects_unknown_character() {
+        let err = tokenize("balance # 0").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 9));
+        assert_eq!(TokenKind::Gte.display(), "`>=`");
+    }
+}
@@ -7,4 +7,8 @@ pub mod grammar;
 pub mod lexer;
 pub mod parser;
 
-pub use parser::{parse_invariant, InvariantParser};
+pub use lexer::{tokenize, LexError, Span, Token, TokenKind};
+pub use parser::{
+    parse_expression, parse_invariant, parse_invariants, parse_module_recovering, InvariantParser,
+    ParseError,
+};
@@ -1,9 +1,40 @@
 //! Parser for invariant DSL expressions.
 
 use crate::grammar::{Grammar, Rule};
-use invar_core::model::{BinaryOp, Expression, Invariant};
+use invar_core::model::{
+    AggregateOp, BinaryOp, EnforcementLevel, Expression, Invariant, Quantifier,
+};
 use invar_core::Result;
+use invar_core::Type;
+use pest::error::LineColLocation;
 use pest::Parser;
+use thiserror::Error;
+
+/// A syntax or semantic error in one invariant definition.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message} at line {line}, column {column}")]
+pub struct ParseError {
+    /// What went wrong.
+    pub message: String,
+    /// 1-based line.
+    pub line: usize,
+    /// 1-based column.
+    pub column: usize,
+}
+
+impl ParseError {
+    /// Message and position of a grammar error, relative to the parsed input.
+    fn from_pest(error: &pest::error::Error<Rule>) -> Self {
+        let (line, column) = match error.line_col {
+            LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+        };
+        Self {
+            message: error.variant.message().to_string(),
+            line,
+            column,
+        }
+    }
+}
 
 /// Parser for invariant DSL.
 pub struct InvariantParser;
@@ -19,8 +50,140 @@ impl InvariantParser {
             .next()
             .ok_or_else(|| invar_core::InvarError::ConfigError("No invariant found".to_string()))?;
 
+        Self::build_invariant(invariant_rule)
+    }
+
+    /// Parse a file containing one or more invariant definitions.
+    pub fn parse_invariants(input: &str) -> Result<Vec<Invariant>> {
+        let parsed = Grammar::parse(Rule::file, input)
+            .map_err(|e| invar_core::InvarError::ConfigError(e.to_string()))?;
+
+        let file_rule = parsed
+            .into_iter()
+            .next()
+            .ok_or_else(|| invar_core::InvarError::ConfigError("No invariant found".to_string()))?;
+
+        file_rule
+            .into_inner()
+            .filter(|pair| pair.as_rule() == Rule::invariant_def)
+            .map(Self::build_invariant)
+            .collect()
+    }
+
+    /// Parse a bare expression such as `amount > 0`.
+    ///
+    /// The whole input must be one expression: anything left over after it
+    /// is an error. Syntax errors give the line and column of the problem.
+    pub fn parse_expression(input: &str) -> Result<Expression> {
+        let expr = Grammar::parse(Rule::expression, input)
+            .map_err(|e| {
+                invar_core::InvarError::ConfigError(ParseError::from_pest(&e).to_string())
+            })?
+            .next()
+            .and_then(|expression| expression.into_inner().next())
+            .ok_or_else(|| {
+                invar_core::InvarError::ConfigError("No expression found".to_string())
+            })?;
+        Self::parse_expr(expr)
+    }
+
+    /// Parse every definition in a file, continuing past broken ones.
+    ///
+    /// The input is split at each `invariant` keyword (with its `tags:` line
+    /// and `@` annotations) and every definition is parsed on its own, so one error
+    /// does not hide the next.
+    pub fn parse_module_recovering(input: &str) -> (Vec<Invariant>, Vec<ParseError>) {
+        let starts = definition_starts(input);
+        let mut invariants = Vec::new();
+        let mut errors = Vec::new();
+
+        let preamble = &input[..starts.first().copied().unwrap_or(input.len())];
+        if let Some(offset) = first_code(preamble) {
+            let (line, column) = line_col(input, offset);
+            errors.push(ParseError {
+                message: "expected `invariant`".to_string(),
+                line,
+                column,
+            });
+        } else if starts.is_empty() {
+            errors.push(ParseError {
+                message: "No invariant found".to_string(),
+                line: 1,
+                column: 1,
+            });
+        }
+
+        for (index, &start) in starts.iter().enumerate() {
+            let end = starts.get(index + 1).copied().unwrap_or(input.len());
+            let (line, column) = line_col(input, start);
+            let parsed = Grammar::parse(Rule::definition, &input[start..end]);
+            let result = match parsed {
+                Ok(mut pairs) => pairs
+                    .next()
+                    .and_then(|definition| definition.into_inner().next())
+                    .ok_or_else(|| {
+                        invar_core::InvarError::ConfigError("No invariant found".to_string())
+                    })
+                    .and_then(Self::build_invariant)
+                    .map_err(|e| ParseError {
+                        message: e.to_string(),
+                        line,
+                        column,
+                    }),
+                // Positions are relative to the definition
+                Err(e) => {
+                    let error = ParseError::from_pest(&e);
+                    Err(ParseError {
+                        line: line + error.line - 1,
+                        column: if error.line == 1 {
+                            column + error.column - 1
+                        } else {
+                            error.column
+                        },
+                        message: error.message,
+                    })
+                }
+            };
+            match result {
+                Ok(invariant) => invariants.push(invariant),
+                Err(error) => errors.push(error),
+            }
+        }
+        (invariants, errors)
+    }
+
+    fn build_invariant(invariant_rule: pest::iterators::Pair<Rule>) -> Result<Invariant> {
         let inner = invariant_rule.into_inner();
-        let inner_items: Vec<_> = inner.collect();
+        let mut inner_items: Vec<_> = inner.collect();
+
+        let tags = match inner_items.first() {
+            Some(item) if item.as_rule() == Rule::tag_list => inner_items
+                .remove(0)
+                .into_inner()
+                .map(|tag| tag.as_str().to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let disabled = inner_items
+            .first()
+            .is_some_and(|item| item.as_rule() == Rule::disabled);
+        if disabled {
+            inner_items.remove(0);
+        }
+
+        let enforcement = match inner_items.first() {
+            Some(item) if item.as_rule() == Rule::enforcement => {
+                let level = inner_items.remove(0).into_inner().as_str().to_string();
+                EnforcementLevel::from_name(&level).ok_or_else(|| {
+                    invar_core::InvarError::ConfigError(format!(
+                        "Unknown enforcement level: {}",
+                        level
+                    ))
+                })?
+            }
+            _ => EnforcementLevel::Always,
+        };
 
         if inner_items.is_empty() {
             return Err(invar_core::InvarError::ConfigError(
@@ -58,16 +221,11 @@ impl InvariantParser {
 
         let expression = Self::parse_expr(inner_items[expr_idx].clone())?;
 
-        Ok(Invariant {
-            name,
-            description: None,
-            expression,
-            severity: "medium".to_string(),
-            category: "general".to_string(),
-            is_always_true: true,
-            layers,
-            phases: vec![],
-        })
+        Ok(Invariant::new(&name, expression)
+            .for_layers(layers)
+            .with_enforcement(enforcement)
+            .with_tags(tags)
+            .with_enabled(!disabled))
     }
 
     fn parse_expr(rule: pest::iterators::Pair<Rule>) -> Result<Expression> {
@@ -75,11 +233,7 @@ impl InvariantParser {
 
         fn parse_pair(pair: Pair<Rule>) -> Result<Expression> {
             match pair.as_rule() {
-                Rule::expr
-                | Rule::logical_or
-                | Rule::logical_and
-                | Rule::comparison
-                | Rule::unary => {
+                Rule::expr | Rule::logical_or | Rule::logical_and | Rule::comparison => {
                     let items: Vec<_> = pair.into_inner().collect();
                     if items.is_empty() {
                         return Err(invar_core::InvarError::ConfigError(
@@ -165,6 +319,64 @@ impl InvariantParser {
                     }
                     Ok(left)
                 }
+                Rule::range_expr => {
+                    // `x in lo..=hi` is sugar for `x >= lo && x <= hi`
+                    let items: Vec<_> = pair.into_inner().collect();
+                    let [subject, _, low, kind, high] = items.as_slice() else {
+                        return Err(invar_core::InvarError::ConfigError(
+                            "Expected range `x in low..high`".to_string(),
+                        ));
+                    };
+                    let subject = parse_pair(subject.clone())?;
+                    let upper = match kind.as_rule() {
+                        Rule::range_inclusive => BinaryOp::Lte,
+                        _ => BinaryOp::Lt,
+                    };
+                    Ok(Expression::Logical {
+                        left: Box::new(Expression::BinaryOp {
+                            left: Box::new(subject.clone()),
+                            op: BinaryOp::Gte,
+                            right: Box::new(parse_pair(low.clone())?),
+                        }),
+                        op: invar_core::model::LogicalOp::And,
+                        right: Box::new(Expression::BinaryOp {
+                            left: Box::new(subject),
+                            op: upper,
+                            right: Box::new(parse_pair(high.clone())?),
+                        }),
+                    })
+                }
+                Rule::unary => {
+                    // Prefix operators bind innermost-last: `!-x` is `!(-x)`
+                    let mut items: Vec<_> = pair.into_inner().collect();
+                    let operand = items.pop().ok_or_else(|| {
+                        invar_core::InvarError::ConfigError("Expected expression".to_string())
+                    })?;
+                    let mut expr = parse_pair(operand)?;
+                    for operator in items.into_iter().rev() {
+                        expr = match (operator.as_rule(), expr) {
+                            // `- 5` and `-(5)` are the literal -5, typed like `-5`
+                            (Rule::negate, Expression::Int(val)) => {
+                                let negated = val.checked_neg().ok_or_else(|| {
+                                    let (line, column) = operator.line_col();
+                                    let error = ParseError {
+                                        message: format!(
+                                            "integer literal -({}) does not fit in i128",
+                                            val
+                                        ),
+                                        line,
+                                        column,
+                                    };
+                                    invar_core::InvarError::ConfigError(error.to_string())
+                                })?;
+                                Expression::Int(negated)
+                            }
+                            (Rule::negate, expr) => Expression::Negate(Box::new(expr)),
+                            (_, expr) => Expression::Not(Box::new(expr)),
+                        };
+                    }
+                    Ok(expr)
+                }
                 Rule::primary => {
                     let mut inner = pair.into_inner();
                     let next = inner.next();
@@ -191,16 +403,147 @@ impl InvariantParser {
                         .collect();
                     Ok(Expression::FunctionCall { name, args: args? })
                 }
+                Rule::map_access => {
+                    let mut items = pair.into_inner();
+                    let operand = items.next().ok_or_else(|| {
+                        invar_core::InvarError::ConfigError("Expected expression".to_string())
+                    })?;
+                    let mut expr = parse_pair(operand)?;
+                    for key in items {
+                        expr = Expression::MapAccess {
+                            map: Box::new(expr),
+                            key: Box::new(parse_pair(key)?),
+                        };
+                    }
+                    Ok(expr)
+                }
+                Rule::cast_expr => {
+                    let mut items = pair.into_inner();
+                    let operand = items.next().ok_or_else(|| {
+                        invar_core::InvarError::ConfigError("Expected expression".to_string())
+                    })?;
+                    let mut expr = parse_pair(operand)?;
+                    for type_pair in items {
+                        let target = Type::from_name(type_pair.as_str()).ok_or_else(|| {
+                            invar_core::InvarError::ConfigError(format!(
+                                "Unknown type: {}",
+                                type_pair.as_str()
+                            ))
+                        })?;
+                        expr = Expression::Cast {
+                            expr: Box::new(expr),
+                            target,
+                        };
+                    }
+                    Ok(expr)
+                }
+                Rule::conditional_expr => {
+                    let items: Vec<_> = pair.into_inner().collect();
+                    if items.len() != 3 {
+                        return Err(invar_core::InvarError::ConfigError(
+                            "Expected if <cond> then <expr> else <expr>".to_string(),
+                        ));
+                    }
+                    Ok(Expression::Conditional {
+                        cond: Box::new(parse_pair(items[0].clone())?),
+                        then_expr: Box::new(parse_pair(items[1].clone())?),
+                        else_expr: Box::new(parse_pair(items[2].clone())?),
+                    })
+                }
+                Rule::assert_expr => {
+                    let items: Vec<_> = pair.into_inner().collect();
+                    if items.len() != 2 {
+                        return Err(invar_core::InvarError::ConfigError(
+                            "Expected assert(<expr>, \"message\")".to_string(),
+                        ));
+                    }
+                    let quoted = items[1].as_str();
+                    Ok(Expression::Assert(
+                        Box::new(parse_pair(items[0].clone())?),
+                        quoted[1..quoted.len() - 1].to_string(),
+                    ))
+                }
+                Rule::quantified_expr => {
+                    let items: Vec<_> = pair.into_inner().collect();
+                    if items.len() != 4 {
+                        return Err(invar_core::InvarError::ConfigError(
+                            "Expected <quantifier>(<var> in <collection>: <expr>)".to_string(),
+                        ));
+                    }
+                    let quantifier = Quantifier::from_name(items[0].as_str()).ok_or_else(|| {
+                        invar_core::InvarError::ConfigError(format!(
+                            "Unknown quantifier: {}",
+                            items[0].as_str()
+                        ))
+                    })?;
+                    Ok(Expression::Quantified {
+                        quantifier,
+                        var: items[1].as_str().to_string(),
+                        collection: Box::new(parse_pair(items[2].clone())?),
+                        body: Box::new(parse_pair(items[3].clone())?),
+                    })
+                }
+                Rule::aggregate_expr => {
+                    let items: Vec<_> = pair
+                        .into_inner()
+                        .filter(|item| {
+                            !matches!(
+                                item.as_rule(),
+                                Rule::aggregate_for | Rule::range_in | Rule::aggregate_if
+                            )
+                        })
+                        .collect();
+                    let (op, body, binder, collection, filter) = match items.as_slice() {
+                        [op, body, binder, collection] => (op, body, binder, collection, None),
+                        [op, body, binder, collection, filter] => {
+                            (op, body, binder, collection, Some(filter))
+                        }
+                        _ => {
+                            return Err(invar_core::InvarError::ConfigError(
+                                "Expected <op>(<expr> for <var> in <collection> if <expr>)"
+                                    .to_string(),
+                            ))
+                        }
+                    };
+                    let op = AggregateOp::from_name(op.as_str()).ok_or_else(|| {
+                        invar_core::InvarError::ConfigError(format!(
+                            "Unknown aggregation: {}",
+                            op.as_str()
+                        ))
+                    })?;
+                    Ok(Expression::Aggregate {
+                        op,
+                        binder: binder.as_str().to_string(),
+                        collection: Box::new(parse_pair(collection.clone())?),
+                        body: Box::new(parse_pair(body.clone())?),
+                        filter: filter
+                            .map(|filter| parse_pair(filter.clone()).map(Box::new))
+                            .transpose()?,
+                    })
+                }
                 Rule::boolean => {
                     let val = pair.as_str() == "true";
                     Ok(Expression::Boolean(val))
                 }
                 Rule::integer => {
                     let val = pair.as_str().parse::<i128>().map_err(|_| {
-                        invar_core::InvarError::ConfigError("Invalid integer".to_string())
+                        let (line, column) = pair.line_col();
+                        let error = ParseError {
+                            message: format!(
+                                "integer literal {} does not fit in i128",
+                                pair.as_str()
+                            ),
+                            line,
+                            column,
+                        };
+                        invar_core::InvarError::ConfigError(error.to_string())
                     })?;
                     Ok(Expression::Int(val))
                 }
+                Rule::string_literal => {
+                    let quoted = pair.as_str();
+                    Ok(Expression::String(quoted[1..quoted.len() - 1].to_string()))
+                }
                 Rule::identifier => Ok(Expression::Var(pair.as_str().to_string())),
                 Rule::qualified_id => {
                     let items: Vec<_> = pair.into_inner().collect();
@@ -244,11 +587,109 @@ impl InvariantParser {
     }
 }
 
+/// Byte offsets where definitions start: each `invariant` keyword, or the
+/// `tags:` line or `@level` annotation directly before it. Comments and strings are skipped.
+fn definition_starts(input: &str) -> Vec<usize> {
+    let bytes = input.as_bytes();
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let mut starts = Vec::new();
+    let mut annotation = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += 1;
+                }
+                i += 1;
+                annotation = None;
+            }
+            b'@' => {
+                annotation = annotation.or(Some(i));
+                i += 1;
+                while i < bytes.len() && is_ident(bytes[i]) {
+                    i += 1;
+                }
+            }
+            b if is_ident(b) => {
+                let start = i;
+                while i < bytes.len() && is_ident(bytes[i]) {
+                    i += 1;
+                }
+                let word = &input[start..i];
+                // A `tags:` line belongs to the definition after it
+                if word == "tags" && input[i..].trim_start_matches([' ', '\t']).starts_with(':') {
+                    while i < bytes.len() && bytes[i] != b'\n' {
+                        i += 1;
+                    }
+                    annotation = Some(start);
+                    continue;
+                }
+                if word == "invariant" {
+                    starts.push(annotation.unwrap_or(start));
+                }
+                annotation = None;
+            }
+            b if b.is_ascii_whitespace() => i += 1,
+            _ => {
+                annotation = None;
+                i += 1;
+            }
+        }
+    }
+    starts
+}
+
+/// Offset of the first character outside whitespace and comments.
+fn first_code(text: &str) -> Option<usize> {
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let code = line.split("//").next().unwrap_or_default();
+        if let Some(column) = code.find(|c: char| !c.is_whitespace()) {
+            return Some(offset + column);
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// 1-based line and column of a byte offset.
+fn line_col(input: &str, offset: usize) -> (usize, usize) {
+    let before = &input[..offset];
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
 /// Parse a complete invariant definition string.
 pub fn parse_invariant(input: &str) -> Result<Invariant> {
     InvariantParser::parse_invariant(input)
 }
 
+/// Parse every invariant definition in a `.invar` file.
+pub fn parse_invariants(input: &str) -> Result<Vec<Invariant>> {
+    InvariantParser::parse_invariants(input)
+}
+
+/// Parse a bare DSL expression.
+pub fn parse_expression(input: &str) -> Result<Expression> {
+    InvariantParser::parse_expression(input)
+}
+
+/// Parse every invariant in a `.invar` file, collecting all errors instead of
+/// stopping at the first.
+pub fn parse_module_recovering(input: &str) -> (Vec<Invariant>, Vec<ParseError>) {
+    InvariantParser::parse_module_recovering(input)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -281,4 +722,474 @@ mod tests {
         let result = parse_invariant(input);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_parse_multiple_invariants_with_comments() {
+        let input = r#"
+            // Token invariants
+            invariant BalancePositive { balance >= 0 }
+            invariant SupplyPositive { total_supply > 0 } // trailing
+        "#;
+        let invariants = parse_invariants(input).unwrap();
+        assert_eq!(invariants.len(), 2);
+        assert_eq!(invariants[0].name, "BalancePositive");
+        assert_eq!(invariants[1].name, "SupplyPositive");
+    }
+
+    #[test]
+    fn test_parse_enforcement_annotation() {
+        let input = r#"
+            @debug_only invariant FeeBound { fee <= 100 }
+            @simulation_only invariant Solvent(account) { account::balance >= 0 }
+            invariant SupplyPositive { total_supply > 0 }
+        "#;
+        let invariants = parse_invariants(input).unwrap();
+        assert_eq!(invariants[0].enforcement, EnforcementLevel::DebugOnly);
+        assert_eq!(invariants[1].enforcement, EnforcementLevel::SimulationOnly);
+        assert_eq!(invariants[1].layers, vec!["account"]);
+        assert_eq!(invariants[2].enforcement, EnforcementLevel::Always);
+
+        assert!(parse_invariant("@sometimes invariant X { a > 0 }").is_err());
+    }
+
+    #[test]
+    fn test_recovering_parse_continues_after_errors() {
+        let input = "// Vault invariants\ninvariant Broken { balance >= }\n\n@debug_only\ninvariant Solvent { reserve >= debt }\ninvariant AlsoBroken { && }\n";
+        let (invariants, errors) = parse_module_recovering(input);
+
+        assert_eq!(invariants.len(), 1);
+        assert_eq!(invariants[0].name, "Solvent");
+        assert_eq!(invariants[0].enforcement, EnforcementLevel::DebugOnly);
+        let positions: Vec<(usize, usize)> = errors.iter().map(|e| (e.line, e.column)).collect();
+        assert_eq!(positions, vec![(2, 31), (6, 24)]);
+
+        let (invariants, errors) = parse_module_recovering("invariant A { a > 0 }");
+        assert_eq!((invariants.len(), errors.len()), (1, 0));
+        let (_, errors) = parse_module_recovering("balance > 0");
+        assert_eq!(errors[0].message, "expected `invariant`");
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        let input = "tags: defi, token-supply\n@debug_only\ninvariant Supply { supply >= 0 }\ninvariant Untagged { fee <= 100 }\n";
+        let invariants = parse_invariants(input).unwrap();
+        assert_eq!(invariants[0].tags, vec!["defi", "token-supply"]);
+        assert_eq!(invariants[0].enforcement, EnforcementLevel::DebugOnly);
+        assert!(invariants[1].tags.is_empty());
+
+        let (recovered, errors) = parse_module_recovering(input);
+        assert!(errors.is_empty());
+        assert_eq!(recovered[0].tags, invariants[0].tags);
+    }
+
+    #[test]
+    fn test_parse_bracketed_tags_and_disabled() {
+        let input = "tags: [fast, critical-path]\n@disabled\n@simulation_only\ninvariant Slow { supply >= 0 }\ninvariant On { fee <= 100 }\n";
+        let invariants = parse_invariants(input).unwrap();
+        assert_eq!(invariants[0].tags, vec!["fast", "critical-path"]);
+        assert!(!invariants[0].enabled);
+        assert_eq!(invariants[0].enforcement, EnforcementLevel::SimulationOnly);
+        assert!(invariants[1].enabled);
+
+        let (recovered, errors) = parse_module_recovering(input);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(recovered.len(), 2);
+        assert!(!recovered[0].enabled);
+
+        assert!(parse_invariants("tags: [fast\ninvariant A { true }").is_err());
+        assert!(parse_invariants("@disabledx invariant A { true }").is_err());
+    }
+
+    #[test]
+    fn test_parse_expression() {
+        let expr = parse_expression("amount > 0 && balance >= amount").unwrap();
+        assert_eq!(expr.to_string(), "((amount > 0) && (balance >= amount))");
+        assert!(parse_expression("amount >").is_err());
+        assert!(parse_expression("invariant x { true }").is_err());
+    }
+
+    #[test]
+    fn test_parse_expression_function_calls_and_layer_vars() {
+        let expr = parse_expression("sum(balances) == total_supply").unwrap();
+        assert_eq!(
+            expr,
+            Expression::BinaryOp {
+                left: Box::new(Expression::FunctionCall {
+                    name: "sum".to_string(),
+                    args: vec![Expression::Var("balances".to_string())],
+                }),
+                op: BinaryOp::Eq,
+                right: Box::new(Expression::Var("total_supply".to_string())),
+            }
+        );
+
+        let expr = parse_expression("  paymaster::deposit >= max(fee, 1)\n").unwrap();
+        assert_eq!(expr.to_string(), "(paymaster::deposit >= max(fee, 1))");
+    }
+
+    #[test]
+    fn test_pretty_printed_expression_reparses() {
+        let expr = parse_expression(
+            "sum(balances) <= total && (max_of(a, b, c, d) >= floor || paymaster::deposit > 0)",
+        )
+        .unwrap();
+        let pretty = expr.display_pretty();
+        assert!(pretty.contains(",\n"), "{}", pretty);
+        assert_eq!(parse_expression(&pretty).unwrap(), expr);
+    }
+
+    #[test]
+    fn test_parse_range_shorthand() {
+        use invar_core::type_checker::TypeChecker;
+
+        let inclusive = parse_expression("fee in 0..=100").unwrap();
+        assert_eq!(
+            inclusive,
+            parse_expression("fee >= 0 && fee <= 100").unwrap()
+        );
+        let exclusive = parse_expression("paymaster::deposit in min_reserve..cap").unwrap();
+        assert_eq!(
+            exclusive.to_string(),
+            "((paymaster::deposit >= min_reserve) && (paymaster::deposit < cap))"
+        );
+
+        // Binds tighter than `&&` and `||`
+        let combined = parse_expression("paused || fee in -5..=5 && open").unwrap();
+        assert_eq!(
+            combined.to_string(),
+            "(paused || (((fee >= -5) && (fee <= 5)) && open))"
+        );
+        assert_eq!(inclusive.display_pretty(), "(fee in 0..=100)");
+        assert_eq!(
+            parse_expression(&combined.display_pretty()).unwrap(),
+            combined
+        );
+
+        // Ranges only order numbers
+        let mut checker = TypeChecker::new();
+        checker.register_state_var("owner".to_string(), Type::Address);
+        checker.register_state_var("low".to_string(), Type::Address);
+        checker.register_state_var("high".to_string(), Type::Address);
+        let range = parse_expression("owner in low..=high").unwrap();
+        assert!(matches!(
+            checker.check_expr(&range),
+            Err(invar_core::TypeError::IncomparableTypes { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_integer_literal_bounds() {
+        for literal in [
+            "-170141183460469231731687303715884105728", // i128::MIN
+            "170141183460469231731687303715884105727",  // i128::MAX
+            "-9223372036854775808",                     // i64::MIN
+            "18446744073709551615",                     // u64::MAX
+            "18446744073709551616",
+        ] {
+            assert_eq!(
+                parse_expression(literal).unwrap(),
+                Expression::Int(literal.parse().unwrap())
+            );
+        }
+
+        // u128::MAX and one past either end of i128
+        for literal in [
+            "340282366920938463463374607431768211455",
+            "170141183460469231731687303715884105728",
+            "-170141183460469231731687303715884105729",
+            "-(-170141183460469231731687303715884105728)",
+        ] {
+            let error = parse_expression(&format!("supply <= {}", literal))
+                .unwrap_err()
+                .to_string();
+            assert!(error.contains("does not fit in i128"), "{}", error);
+            assert!(error.contains("at line 1, column 11"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_parse_expression_rejects_trailing_input() {
+        let error = parse_expression("balance > 0 supply")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("at line 1, column 13"), "{}", error);
+
+        let error = parse_expression("balance > 0\n)").unwrap_err().to_string();
+        assert!(error.contains("at line 2, column 1"), "{}", error);
+        assert!(parse_expression("").is_err());
+    }
+
+    #[test]
+    fn test_parse_conditional() {
+        let input = r#"invariant InitBalance {
+            if is_initialized then balance > 0 else balance == 0
+        }"#;
+        let inv = parse_invariant(input).unwrap();
+        assert!(matches!(inv.expression, Expression::Conditional { .. }));
+        assert_eq!(
+            inv.expression.to_string(),
+            "if is_initialized then (balance > 0) else (balance == 0)"
+        );
+    }
+
+    #[test]
+    fn test_parse_cast() {
+        let input = r#"invariant Widened { amount as u128 <= supply }"#;
+        let inv = parse_invariant(input).unwrap();
+        assert_eq!(inv.expression.to_string(), "((amount as u128) <= supply)");
+    }
+
+    #[test]
+    fn test_cast_round_trip() {
+        use invar_core::evaluator::{EvaluationError, Evaluator, ExecutionContext, Value};
+
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("balance".to_string(), Value::U64(7));
+        ctx.set_state("huge".to_string(), Value::U64(u64::MAX));
+        ctx.set_state("delta".to_string(), Value::I64(5));
+        ctx.set_state("debt".to_string(), Value::I64(-5));
+        ctx.set_state("paused".to_string(), Value::Bool(false));
+        let evaluator = Evaluator::new(ctx);
+
+        for (source, expected) in [
+            ("balance as u128", Ok(Value::U128(7))),
+            ("balance as i64", Ok(Value::I64(7))),
+            ("huge as i64", Err(EvaluationError::ConversionOverflow)),
+            ("delta as u64", Ok(Value::U64(5))),
+            ("debt as u64", Err(EvaluationError::ConversionOverflow)),
+            ("paused as u64", Err(EvaluationError::TypeError)),
+        ] {
+            let expr = parse_expression(source).unwrap();
+            assert_eq!(expr.to_string(), format!("({})", source));
+            assert_eq!(parse_expression(&expr.to_string()).unwrap(), expr);
+            assert_eq!(evaluator.evaluate(&expr), expected, "{}", source);
+        }
+
+        let sum = parse_expression("(balance as u128) + (fees as u128) == total");
+        assert!(sum.is_err(), "the DSL has no arithmetic operators");
+    }
+
+    #[test]
+    fn test_parse_and_evaluate_negation() {
+        use invar_core::evaluator::{Evaluator, ExecutionContext, Value};
+
+        // Negated literals fold into the literal itself
+        for literal in ["-5", "- 5", "-(5)", "-((5))"] {
+            assert_eq!(parse_expression(literal).unwrap(), Expression::Int(-5));
+        }
+        let double = parse_expression("- -5").unwrap();
+        assert_eq!(double, Expression::Int(5));
+        let evaluator = Evaluator::new(ExecutionContext::new());
+        assert_eq!(evaluator.evaluate(&double), Ok(Value::U64(5)));
+
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("a".to_string(), Value::I64(3));
+        ctx.set_state("b".to_string(), Value::I64(0));
+        ctx.set_state("delta".to_string(), Value::I64(-50));
+        ctx.set_state("supply".to_string(), Value::U64(10));
+        let evaluator = Evaluator::new(ctx);
+        let expr = parse_expression("-(a) < b").unwrap();
+        assert_eq!(expr.to_string(), "(-a < b)");
+        assert_eq!(evaluator.evaluate(&expr), Ok(Value::Bool(true)));
+        let expr = parse_expression("delta >= -100").unwrap();
+        assert_eq!(evaluator.evaluate(&expr), Ok(Value::Bool(true)));
+        assert_eq!(
+            evaluator.evaluate(&parse_expression("-supply < 0").unwrap()),
+            Err(invar_core::evaluator::EvaluationError::Overflow)
+        );
+
+        let expr = parse_expression("!-delta > 0").unwrap();
+        assert_eq!(expr.to_string(), "(!(-delta) > 0)");
+        assert_eq!(
+            parse_expression("!paused").unwrap(),
+            Expression::Not(Box::new(Expression::Var("paused".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_content_hash_ignores_formatting() {
+        let compact = parse_invariant("invariant A { balance>=0&&supply>0 }").unwrap();
+        let spaced = parse_invariant(
+            "invariant A {\n    balance >= 0 // non-negative\n    && (supply > 0)\n}",
+        )
+        .unwrap();
+        assert_eq!(compact.content_hash(), spaced.content_hash());
+
+        let renamed = parse_invariant("invariant A { amount >= 0 && supply > 0 }").unwrap();
+        assert_ne!(compact.content_hash(), renamed.content_hash());
+    }
+
+    #[test]
+    fn test_parse_and_evaluate_string_literal() {
+        use invar_core::evaluator::{Evaluator, ExecutionContext, Value};
+
+        let inv = parse_invariant(r#"invariant OwnerCheck { owner == "system" }"#).unwrap();
+        assert_eq!(inv.expression.to_string(), r#"(owner == "system")"#);
+
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("owner".to_string(), Value::Str("system".to_string()));
+        assert_eq!(
+            Evaluator::new(ctx).evaluate(&inv.expression),
+            Ok(Value::Bool(true))
+        );
+    }
+
+    #[test]
+    fn test_parse_and_evaluate_assert() {
+        use invar_core::evaluator::{Evaluator, ExecutionContext, Value};
+
+        let inv = parse_invariant(
+            r#"invariant Solvent { assert(reserve >= debt, "reserve must cover debt") }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            inv.expression.to_string(),
+            r#"assert((reserve >= debt), "reserve must cover debt")"#
+        );
+
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("reserve".to_string(), Value::U64(10));
+        ctx.set_state("debt".to_string(), Value::U64(20));
+        assert_eq!(
+            Evaluator::new(ctx).evaluate(&inv.expression),
+            Err(invar_core::evaluator::EvaluationError::Custom(
+                "reserve must cover debt".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_and_evaluate_map_access() {
+        use invar_core::evaluator::{Evaluator, ExecutionContext, Value};
+        use std::collections::BTreeMap;
+
+        let inv = parse_invariant(r#"invariant Funded { balances["0xdeadbeef"] >= 100 }"#).unwrap();
+        assert_eq!(
+            inv.expression.to_string(),
+            r#"(balances["0xdeadbeef"] >= 100)"#
+        );
+
+        let balances = BTreeMap::from([("0xdeadbeef".to_string(), Value::U64(250))]);
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state("balances".to_string(), Value::Map(balances));
+        let evaluator = Evaluator::new(ctx);
+        assert_eq!(evaluator.evaluate(&inv.expression), Ok(Value::Bool(true)));
+
+        let missing = parse_invariant(r#"invariant Missing { balances["0x0"] >= 0 }"#).unwrap();
+        assert!(matches!(
+            evaluator.evaluate(&missing.expression),
+            Err(invar_core::evaluator::EvaluationError::UndefinedVariable { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_and_evaluate_quantifiers() {
+        use invar_core::evaluator::{Evaluator, ExecutionContext, Value};
+
+        let inv = parse_invariant(
+            "invariant Backed { sum(d in deposits: d) <= reserve && count(d in deposits: d > 0) == 2 }",
+        )
+        .unwrap();
+        assert_eq!(
+            inv.expression.to_string(),
+            "((sum(d in deposits: d) <= reserve) && (count(d in deposits: (d > 0)) == 2))"
+        );
+
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state(
+            "deposits".to_string(),
+            Value::Array(vec![Value::U64(0), Value::U64(40), Value::U64(60)]),
+        );
+        ctx.set_state("reserve".to_string(), Value::U64(100));
+        assert_eq!(
+            Evaluator::new(ctx).evaluate(&inv.expression),
+            Ok(Value::Bool(true))
+        );
+
+        // Plain calls to functions named like quantifiers are unaffected
+        let call = parse_expression("max(a, b) >= min(a, b)").unwrap();
+        assert_eq!(call.to_string(), "(max(a, b) >= min(a, b))");
+        assert!(parse_expression("forall(x in: x > 0)").is_err());
+    }
+
+    #[test]
+    fn test_forall_over_array_state_variable() {
+        use invar_core::evaluator::{collect_state_collection, Evaluator, ExecutionContext, Value};
+        use invar_core::model::{ProgramModel, StateVar};
+
+        let mut program = ProgramModel::new(
+            "bank".to_string(),
+            "evm".to_string(),
+            "Bank.sol".to_string(),
+        );
+        program.add_state_var(StateVar {
+            name: "balances".to_string(),
+            type_name: "u64[]".to_string(),
+            is_mutable: true,
+            visibility: None,
+            abilities: Default::default(),
+            depends_on: Vec::new(),
+        });
+        let ctx = ExecutionContext::from_json(r#"{"balances": [100, 200, 50]}"#).unwrap();
+        assert_eq!(
+            collect_state_collection(&program, "balances", &ctx),
+            Ok(vec![Value::U64(100), Value::U64(200), Value::U64(50)])
+        );
+
+        let evaluator = Evaluator::new(ctx);
+        let solvent =
+            parse_invariant("invariant solvent { forall(x in balances: x >= 0) }").unwrap();
+        assert_eq!(
+            evaluator.evaluate(&solvent.expression),
+            Ok(Value::Bool(true))
+        );
+        let large = parse_expression("forall(x in balances: x >= 100)").unwrap();
+        assert_eq!(evaluator.evaluate(&large), Ok(Value::Bool(false)));
+    }
+
+    #[test]
+    fn test_parse_and_evaluate_aggregations() {
+        use invar_core::evaluator::{Evaluator, ExecutionContext, Value};
+        use std::collections::BTreeMap;
+
+        let inv = parse_invariant(
+            r#"invariant ActiveBacked { sum(d["amount"] for d in deposits if d["active"]) == vault_total }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            inv.expression.to_string(),
+            r#"(sum(d["amount"] for d in deposits if d["active"]) == vault_total)"#
+        );
+        assert_eq!(
+            parse_expression(&inv.expression.to_string()).unwrap(),
+            inv.expression
+        );
+
+        let deposit = |amount, active| {
+            Value::Map(BTreeMap::from([
+                ("amount".to_string(), Value::U64(amount)),
+                ("active".to_string(), Value::Bool(active)),
+            ]))
+        };
+        let mut ctx = ExecutionContext::new();
+        ctx.set_state(
+            "deposits".to_string(),
+            Value::Array(vec![
+                deposit(40, true),
+                deposit(25, false),
+                deposit(60, true),
+            ]),
+        );
+        ctx.set_state("vault_total".to_string(), Value::U64(100));
+        let evaluator = Evaluator::new(ctx);
+        assert_eq!(evaluator.evaluate(&inv.expression), Ok(Value::Bool(true)));
+
+        // The filter is optional, and `count` and `max` are supported too
+        let count = parse_expression("count(d for d in deposits)").unwrap();
+        assert_eq!(evaluator.evaluate(&count), Ok(Value::U64(3)));
+        let max =
+            parse_expression(r#"max(d["amount"] for d in deposits if !d["active"])"#).unwrap();
+        assert_eq!(evaluator.evaluate(&max), Ok(Value::U64(25)));
+        assert!(parse_expression("min(d for d in deposits)").is_err());
+    }
 }
@@ -1,8 +1,13 @@
 //! EVM generator implementation.
 
-use invar_core::model::{GenerationOutput, Invariant, ProgramModel};
+use invar_core::model::{
+    EnforcementLevel, Expression, GenerationOutput, Invariant, ProgramModel,
+    CONTRACT_INVARIANT_CATEGORY,
+};
 use invar_core::traits::CodeGenerator;
-use invar_core::Result;
+use invar_core::Type;
+use invar_core::{sort_for_generation, InvarError, Result};
+use invar_ir::{compute_coverage, DependencyGraph};
 use tracing::info;
 
 /// Code generator for EVM (Solidity) contracts.
@@ -20,25 +25,81 @@ impl CodeGenerator for EvmGenerator {
             invariants.len()
         );
 
+        // Reuse the contract's own violation error when it has one
+        let existing = violation_error(program);
+        let (error_name, error_params) = existing.unwrap_or((DEFAULT_ERROR, &[]));
+
+        let mut ordered: Vec<&Invariant> = invariants.iter().collect();
+        sort_for_generation(&mut ordered);
+        for inv in &ordered {
+            inv.check_enforcement()?;
+            inv.check_generatable()?;
+            check_low_level(program, inv)?;
+        }
+
+        let graph = DependencyGraph::from_program(program);
         let mut assertions = Vec::new();
-        for inv in invariants {
-            assertions.push(format!(
-                "require({}, \"Invariant: {}\");",
-                inv.expression, inv.name
-            ));
+        let mut contract_checks = Vec::new();
+        let mut placements = Vec::new();
+        for (index, inv) in ordered.into_iter().enumerate() {
+            let condition = render_expression(&inv.expression);
+            let assertion = match inv.enforcement {
+                // Debug checks panic with assert() rather than reverting with a reason
+                EnforcementLevel::DebugOnly => format!("assert({});", condition),
+                // Checked by the simulator only
+                EnforcementLevel::SimulationOnly => continue,
+                EnforcementLevel::Always => {
+                    let args: Vec<String> = if existing.is_some() {
+                        error_params
+                            .iter()
+                            .map(|ty| error_argument(ty, index, &inv.name))
+                            .collect()
+                    } else {
+                        vec![format!("\"{}\"", inv.name)]
+                    };
+                    format!(
+                        "if (!{}) revert {}({});",
+                        condition,
+                        error_name,
+                        args.join(", ")
+                    )
+                }
+            };
+            if inv.category == CONTRACT_INVARIANT_CATEGORY {
+                contract_checks.push(assertion.clone());
+            } else if inv.layers.is_empty() {
+                let functions = checked_after(program, &graph, inv);
+                if !functions.is_empty() {
+                    placements.push(format!(
+                        "// Check {} after: {}\n",
+                        inv.name,
+                        functions.join(", ")
+                    ));
+                }
+            }
+            assertions.push(assertion);
         }
 
-        let code = format!(
+        let mut code = format!(
             "// Generated Solidity invariant checks for {}\n// {} checks\n",
             program.name,
             assertions.len()
         );
+        if existing.is_none() {
+            code.push_str(&format!("error {}(string name);\n", DEFAULT_ERROR));
+        }
+        for placement in placements {
+            code.push_str(&placement);
+        }
+        if !contract_checks.is_empty() {
+            code.push_str(&contract_modifier(program, &contract_checks));
+        }
 
         Ok(GenerationOutput {
             code,
             assertions,
             tests: None,
-            coverage_percent: 0,
+            coverage_percent: compute_coverage(program, invariants).coverage_percent,
         })
     }
 
@@ -46,3 +107,592 @@ impl CodeGenerator for EvmGenerator {
         "evm"
     }
 }
+
+/// Modifier running after the function body of every public mutating function.
+const CONTRACT_MODIFIER: &str = "invarContractInvariants";
+
+/// Modifier checking the contract's class invariants once the function body
+/// has run, and the functions it must be applied to.
+fn contract_modifier(program: &ProgramModel, checks: &[String]) -> String {
+    let mut code = format!(
+        "\n/// Contract invariants, checked at the end of every public mutating function.\nmodifier {}() {{\n    _;\n",
+        CONTRACT_MODIFIER
+    );
+    for check in checks {
+        code.push_str(&format!("    {}\n", check));
+    }
+    code.push_str("}\n");
+
+    let mutating: Vec<&str> = program
+        .functions
+        .values()
+        .filter(|function| function.is_entry_point && !function.is_pure)
+        .map(|function| function.name.as_str())
+        .collect();
+    if !mutating.is_empty() {
+        code.push_str(&format!(
+            "// Apply {} to: {}\n",
+            CONTRACT_MODIFIER,
+            mutating.join(", ")
+        ));
+    }
+    code
+}
+
+/// Functions after which `invariant` must be checked: those writing a
+/// variable it reads, or one those variables are derived from.
+fn checked_after<'a>(
+    program: &'a ProgramModel,
+    graph: &DependencyGraph,
+    invariant: &Invariant,
+) -> Vec<&'a str> {
+    let read = invariant.expression.variables();
+    program
+        .functions
+        .values()
+        .filter(|function| {
+            function.mutates.iter().any(|var| {
+                read.contains(var)
+                    || graph
+                        .variables_transitively_affected_by(var)
+                        .iter()
+                        .any(|affected| read.contains(affected))
+            })
+        })
+        .map(|function| function.name.as_str())
+        .collect()
+}
+
+/// Refuse an invariant checked around a function that uses inline assembly
+/// or low-level calls: writes the analyzer cannot see could break it unnoticed.
+///
+/// Contract invariants run around every public mutating function; others
+/// around the functions named in their layers.
+fn check_low_level(program: &ProgramModel, invariant: &Invariant) -> Result<()> {
+    if invariant.enforcement == EnforcementLevel::SimulationOnly {
+        return Ok(());
+    }
+    let contract_wide = invariant.category == CONTRACT_INVARIANT_CATEGORY;
+    let unanalyzable = program.functions.values().find(|function| {
+        function.uses_low_level
+            && if contract_wide {
+                function.is_entry_point && !function.is_pure
+            } else {
+                invariant.layers.contains(&function.name)
+            }
+    });
+    match unanalyzable {
+        Some(function) => Err(InvarError::GenerationFailed(format!(
+            "Invariant {} cannot be guaranteed around {}: it uses inline assembly or low-level calls whose state writes cannot be analyzed",
+            invariant.name, function.name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Error declared by the generator when the contract has none to reuse.
+const DEFAULT_ERROR: &str = "InvariantViolated";
+
+/// The contract's own invariant violation error: `InvariantViolated` if
+/// declared, else the first `Invar*` error whose parameters can be filled in.
+fn violation_error(program: &ProgramModel) -> Option<(&str, &[String])> {
+    let usable = |params: &[String]| params.iter().all(|ty| supported_error_param(ty));
+    program
+        .errors
+        .get_key_value(DEFAULT_ERROR)
+        .filter(|(_, params)| usable(params))
+        .or_else(|| {
+            program
+                .errors
+                .iter()
+                .find(|(name, params)| name.starts_with("Invar") && usable(params))
+        })
+        .map(|(name, params)| (name.as_str(), params.as_slice()))
+}
+
+fn supported_error_param(ty: &str) -> bool {
+    ty == "string" || ty == "bytes32" || ty.starts_with("uint") || ty.starts_with("int")
+}
+
+/// Argument for an error parameter: the invariant's name for strings and
+/// hashes, its position for integers.
+fn error_argument(ty: &str, index: usize, name: &str) -> String {
+    match ty {
+        "string" => format!("\"{}\"", name),
+        "bytes32" => format!("keccak256(\"{}\")", name),
+        _ => index.to_string(),
+    }
+}
+
+/// Render an expression as Solidity source.
+///
+/// Casts use Solidity's conversion syntax (`uint128(x)`), conditionals
+/// become ternaries and `now()`, `block_number()` and `chain_id()` read the
+/// `block` globals; everything else matches the DSL's own rendering.
+fn render_expression(expr: &Expression) -> String {
+    match expr {
+        Expression::Cast { expr, target } => {
+            format!("{}({})", solidity_type(target), render_expression(expr))
+        }
+        Expression::Conditional {
+            cond,
+            then_expr,
+            else_expr,
+        } => format!(
+            "({} ? {} : {})",
+            render_expression(cond),
+            render_expression(then_expr),
+            render_expression(else_expr)
+        ),
+        Expression::BinaryOp { left, op, right } => format!(
+            "({} {} {})",
+            render_expression(left),
+            op,
+            render_expression(right)
+        ),
+        Expression::Logical { left, op, right } => format!(
+            "({} {} {})",
+            render_expression(left),
+            op,
+            render_expression(right)
+        ),
+        Expression::Not(inner) => format!("!({})", render_expression(inner)),
+        Expression::Negate(inner) => format!("-({})", render_expression(inner)),
+        // The revert already names the invariant
+        Expression::Assert(constraint, _) => render_expression(constraint),
+        Expression::FunctionCall { name, args } => {
+            match (
+                block_global(name),
+                arithmetic_operator(name),
+                args.as_slice(),
+            ) {
+                (Some(global), _, []) => global.to_string(),
+                // Solidity 0.8 arithmetic reverts where the builtins fail
+                (_, Some(op), [left, right]) => format!(
+                    "({} {} {})",
+                    render_expression(left),
+                    op,
+                    render_expression(right)
+                ),
+                _ => {
+                    let args: Vec<_> = args.iter().map(render_expression).collect();
+                    format!("{}({})", name, args.join(", "))
+                }
+            }
+        }
+        other => other.to_string(),
+    }
+}
+
+/// The `block` global a zero-argument block builtin reads.
+fn block_global(name: &str) -> Option<&'static str> {
+    match name {
+        "now" => Some("block.timestamp"),
+        "block_number" => Some("block.number"),
+        "chain_id" => Some("block.chainid"),
+        _ => None,
+    }
+}
+
+/// The operator an arithmetic builtin (`add`, ...) is written as.
+fn arithmetic_operator(name: &str) -> Option<&'static str> {
+    match name {
+        "add" => Some("+"),
+        "sub" => Some("-"),
+        "mul" => Some("*"),
+        "div" => Some("/"),
+        _ => None,
+    }
+}
+
+/// Solidity spelling of a DSL type.
+fn solidity_type(ty: &Type) -> String {
+    match ty {
+        Type::Bool => "bool".to_string(),
+        Type::U64 => "uint64".to_string(),
+        Type::U128 => "uint128".to_string(),
+        Type::I64 => "int64".to_string(),
+        Type::U256 => "uint256".to_string(),
+        Type::Address => "address".to_string(),
+        Type::Array => "uint256[]".to_string(),
+        Type::Str => "string".to_string(),
+        Type::Map(key, value) => {
+            format!(
+                "mapping({} => {})",
+                solidity_type(key),
+                solidity_type(value)
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use invar_core::model::{BinaryOp, FunctionModel, OverflowProtection, StateVar};
+
+    fn invariant(name: &str) -> Invariant {
+        Invariant::new(
+            name,
+            Expression::BinaryOp {
+                left: Box::new(Expression::Var("totalSupply".to_string())),
+                op: BinaryOp::Gte,
+                right: Box::new(Expression::Int(0)),
+            },
+        )
+    }
+
+    #[test]
+    fn test_reuses_existing_violation_error() {
+        let mut program = ProgramModel::new(
+            "Token".to_string(),
+            "evm".to_string(),
+            "Token.sol".to_string(),
+        );
+        program
+            .errors
+            .insert("InvariantViolated".to_string(), vec!["uint256".to_string()]);
+
+        let output = EvmGenerator
+            .generate(
+                &program,
+                &[invariant("supply_positive"), invariant("backed")],
+            )
+            .unwrap();
+        assert_eq!(
+            output.assertions,
+            vec![
+                "if (!(totalSupply >= 0)) revert InvariantViolated(0);".to_string(),
+                "if (!(totalSupply >= 0)) revert InvariantViolated(1);".to_string(),
+            ]
+        );
+        assert!(!output.code.contains("error "));
+    }
+
+    #[test]
+    fn test_declares_violation_error_when_missing() {
+        let program = ProgramModel::new(
+            "Token".to_string(),
+            "evm".to_string(),
+            "Token.sol".to_string(),
+        );
+        let output = EvmGenerator
+            .generate(&program, &[invariant("supply_positive")])
+            .unwrap();
+        assert_eq!(
+            output.assertions,
+            vec![
+                "if (!(totalSupply >= 0)) revert InvariantViolated(\"supply_positive\");"
+                    .to_string()
+            ]
+        );
+        assert!(output
+            .code
+            .contains("error InvariantViolated(string name);"));
+    }
+
+    #[test]
+    fn test_enforcement_levels() {
+        let program = ProgramModel::new(
+            "Token".to_string(),
+            "evm".to_string(),
+            "Token.sol".to_string(),
+        );
+        let output = EvmGenerator
+            .generate(
+                &program,
+                &[
+                    invariant("supply_positive"),
+                    invariant("debug").with_enforcement(EnforcementLevel::DebugOnly),
+                    invariant("simulated").with_enforcement(EnforcementLevel::SimulationOnly),
+                ],
+            )
+            .unwrap();
+        assert_eq!(
+            output.assertions,
+            // Generation order: content hash, then name
+            vec![
+                "assert((totalSupply >= 0));".to_string(),
+                "if (!(totalSupply >= 0)) revert InvariantViolated(\"supply_positive\");"
+                    .to_string(),
+            ]
+        );
+
+        let downgraded = invariant("bridge_backed")
+            .with_category("bridge")
+            .with_enforcement(EnforcementLevel::SimulationOnly);
+        assert!(EvmGenerator.generate(&program, &[downgraded]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_aggregations() {
+        let program = ProgramModel::new(
+            "Token".to_string(),
+            "evm".to_string(),
+            "Token.sol".to_string(),
+        );
+        let active_deposits = Invariant::new(
+            "active_deposits",
+            Expression::Aggregate {
+                op: invar_core::model::AggregateOp::Count,
+                binder: "d".to_string(),
+                collection: Box::new(Expression::Var("deposits".to_string())),
+                body: Box::new(Expression::Var("d".to_string())),
+                filter: None,
+            },
+        );
+
+        let err = EvmGenerator
+            .generate(&program, std::slice::from_ref(&active_deposits))
+            .unwrap_err();
+        assert!(matches!(err, invar_core::InvarError::Unsupported(_)));
+        // The simulator still checks them
+        let simulated = active_deposits.with_enforcement(EnforcementLevel::SimulationOnly);
+        assert!(EvmGenerator.generate(&program, &[simulated]).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_quantifiers() {
+        let program = ProgramModel::new(
+            "Vault".to_string(),
+            "evm".to_string(),
+            "Vault.sol".to_string(),
+        );
+        let positive = Invariant::new(
+            "deposits_positive",
+            Expression::Quantified {
+                quantifier: invar_core::model::Quantifier::ForAll,
+                var: "x".to_string(),
+                collection: Box::new(Expression::Var("deposits".to_string())),
+                body: Box::new(Expression::BinaryOp {
+                    left: Box::new(Expression::Var("x".to_string())),
+                    op: BinaryOp::Gt,
+                    right: Box::new(Expression::Int(0)),
+                }),
+            },
+        );
+
+        let err = EvmGenerator
+            .generate(&program, std::slice::from_ref(&positive))
+            .unwrap_err();
+        assert!(matches!(err, invar_core::InvarError::Unsupported(_)));
+        assert!(err
+            .to_string()
+            .contains("deposits_positive uses a quantifier"));
+        let simulated = positive.with_enforcement(EnforcementLevel::SimulationOnly);
+        assert!(EvmGenerator.generate(&program, &[simulated]).is_ok());
+    }
+
+    #[test]
+    fn test_block_builtins_read_block_globals() {
+        let program = ProgramModel::new(
+            "Escrow".to_string(),
+            "evm".to_string(),
+            "Escrow.sol".to_string(),
+        );
+        let call = |name: &str| {
+            Box::new(Expression::FunctionCall {
+                name: name.to_string(),
+                args: vec![],
+            })
+        };
+        let before_deadline = Invariant::new(
+            "before_deadline",
+            Expression::BinaryOp {
+                left: call("now"),
+                op: BinaryOp::Lt,
+                right: Box::new(Expression::Var("deadline".to_string())),
+            },
+        );
+        let globals = Invariant::new(
+            "globals",
+            Expression::BinaryOp {
+                left: call("chain_id"),
+                op: BinaryOp::Eq,
+                right: call("block_number"),
+            },
+        );
+
+        let output = EvmGenerator
+            .generate(&program, &[before_deadline, globals])
+            .unwrap();
+        assert_eq!(
+            output.assertions,
+            vec![
+                "if (!(block.chainid == block.number)) revert InvariantViolated(\"globals\");"
+                    .to_string(),
+                "if (!(block.timestamp < deadline)) revert InvariantViolated(\"before_deadline\");"
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_builtins_render_as_operators() {
+        let program = ProgramModel::new(
+            "Vault".to_string(),
+            "evm".to_string(),
+            "Vault.sol".to_string(),
+        );
+        let var = |name: &str| Expression::Var(name.to_string());
+        let call = |name: &str, args| Expression::FunctionCall {
+            name: name.to_string(),
+            args,
+        };
+        let fee_bounded = Invariant::new(
+            "fee_bounded",
+            Expression::BinaryOp {
+                left: Box::new(call("mul", vec![var("fee"), Expression::Int(10_000)])),
+                op: BinaryOp::Lte,
+                right: Box::new(call(
+                    "mul",
+                    vec![
+                        call("add", vec![var("max_fee"), Expression::Int(1)]),
+                        var("amount"),
+                    ],
+                )),
+            },
+        );
+
+        let output = EvmGenerator.generate(&program, &[fee_bounded]).unwrap();
+        assert_eq!(
+            output.assertions,
+            vec![
+                "if (!((fee * 10000) <= ((max_fee + 1) * amount))) revert InvariantViolated(\"fee_bounded\");"
+                    .to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contract_invariants_run_after_mutating_functions() {
+        let mut program = ProgramModel::new(
+            "Token".to_string(),
+            "evm".to_string(),
+            "Token.sol".to_string(),
+        );
+        for (name, is_pure) in [("mint", false), ("supply", true), ("burn", false)] {
+            program.add_function(FunctionModel {
+                name: name.to_string(),
+                parameters: Vec::new(),
+                return_type: None,
+                mutates: Default::default(),
+                reads: Default::default(),
+                is_entry_point: true,
+                is_pure,
+                overflow_protection: OverflowProtection::Unknown,
+                span: None,
+                modifiers: Vec::new(),
+                uses_low_level: false,
+                pre_conditions: Vec::new(),
+                post_conditions: Vec::new(),
+            });
+        }
+
+        let output = EvmGenerator
+            .generate(
+                &program,
+                &[
+                    invariant("supply_positive"),
+                    invariant("contract_invariant_1").with_category(CONTRACT_INVARIANT_CATEGORY),
+                ],
+            )
+            .unwrap();
+        assert!(output.code.ends_with(
+            "modifier invarContractInvariants() {
+    _;
+    if (!(totalSupply >= 0)) revert InvariantViolated(\"contract_invariant_1\");
+}
+// Apply invarContractInvariants to: burn, mint
+"
+        ));
+        assert_eq!(output.assertions.len(), 2);
+    }
+
+    #[test]
+    fn test_checks_after_transitive_dependency_writes() {
+        let mut program = ProgramModel::new(
+            "Vault".to_string(),
+            "evm".to_string(),
+            "Vault.sol".to_string(),
+        );
+        for (name, depends_on) in [
+            ("totalAssets", vec![]),
+            ("sharePrice", vec!["totalAssets"]),
+            ("totalSupply", vec!["sharePrice"]),
+        ] {
+            program.add_state_var(StateVar {
+                name: name.to_string(),
+                type_name: "uint256".to_string(),
+                is_mutable: true,
+                visibility: None,
+                abilities: Default::default(),
+                depends_on: depends_on.into_iter().map(String::from).collect(),
+            });
+        }
+        for (name, mutates) in [("deposit", "totalAssets"), ("setFee", "fee")] {
+            program.add_function(FunctionModel {
+                name: name.to_string(),
+                parameters: Vec::new(),
+                return_type: None,
+                mutates: [mutates.to_string()].into(),
+                reads: Default::default(),
+                is_entry_point: true,
+                is_pure: false,
+                overflow_protection: OverflowProtection::Unknown,
+                span: None,
+                modifiers: Vec::new(),
+                uses_low_level: false,
+                pre_conditions: Vec::new(),
+                post_conditions: Vec::new(),
+            });
+        }
+
+        let output = EvmGenerator
+            .generate(&program, &[invariant("supply_positive")])
+            .unwrap();
+        assert!(output
+            .code
+            .contains("// Check supply_positive after: deposit\n"));
+    }
+
+    #[test]
+    fn test_refuses_invariants_around_low_level_functions() {
+        let mut program = ProgramModel::new(
+            "Proxy".to_string(),
+            "evm".to_string(),
+            "Proxy.sol".to_string(),
+        );
+        for (name, uses_low_level) in [("fallback", true), ("upgrade", false)] {
+            program.add_function(FunctionModel {
+                name: name.to_string(),
+                parameters: Vec::new(),
+                return_type: None,
+                mutates: Default::default(),
+                reads: Default::default(),
+                is_entry_point: true,
+                is_pure: false,
+                overflow_protection: OverflowProtection::Unknown,
+                span: None,
+                modifiers: Vec::new(),
+                uses_low_level,
+                pre_conditions: Vec::new(),
+                post_conditions: Vec::new(),
+            });
+        }
+
+        let scoped = |layer: &str| invariant("supply").for_layers(vec![layer.to_string()]);
+        let err = EvmGenerator
+            .generate(&program, &[scoped("fallback")])
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invariant supply cannot be guaranteed around fallback"));
+        let contract_wide = invariant("supply").with_category(CONTRACT_INVARIANT_CATEGORY);
+        assert!(EvmGenerator.generate(&program, &[contract_wide]).is_err());
+
+        assert!(EvmGenerator
+            .generate(&program, &[scoped("upgrade")])
+            .is_ok());
+    }
+}
@@ -1,8 +1,15 @@
 //! Move generator implementation.
 
-use invar_core::model::{GenerationOutput, Invariant, ProgramModel};
+use invar_core::evaluator::{ARITHMETIC_BUILTINS, BLOCK_BUILTINS};
+use invar_core::model::{
+    EnforcementLevel, Expression, GenerationOutput, Invariant, ProgramModel,
+    CONTRACT_INVARIANT_CATEGORY,
+};
 use invar_core::traits::CodeGenerator;
-use invar_core::Result;
+use invar_core::{
+    fold_children, sort_for_generation, walk_expression, ExpressionFolder, ExpressionVisitor,
+    InvarError, Result,
+};
 use tracing::info;
 
 /// Code generator for Move programs.
@@ -20,20 +27,37 @@ impl CodeGenerator for MoveGenerator {
             invariants.len()
         );
 
+        let mut ordered: Vec<&Invariant> = invariants.iter().collect();
+        sort_for_generation(&mut ordered);
         let mut assertions = Vec::new();
-        for inv in invariants {
-            assertions.push(format!(
+        let mut contract_checks = Vec::new();
+        for inv in ordered {
+            inv.check_enforcement()?;
+            inv.check_generatable()?;
+            // Move has no debug-only builds, so only simulation-only checks are dropped
+            if inv.enforcement == EnforcementLevel::SimulationOnly {
+                continue;
+            }
+            check_representable(inv)?;
+            let assertion = format!(
                 "assert!({}, E_INVARIANT_{});",
-                inv.expression,
+                MoveOperators.fold_expression(inv.expression.clone()),
                 inv.name.to_uppercase()
-            ));
+            );
+            if inv.category == CONTRACT_INVARIANT_CATEGORY {
+                contract_checks.push(assertion.clone());
+            }
+            assertions.push(assertion);
         }
 
-        let code = format!(
+        let mut code = format!(
             "// Generated Move invariant checks for {}\n// {} assertions\n",
             program.name,
             assertions.len()
         );
+        if !contract_checks.is_empty() {
+            code.push_str(&module_invariant_check(program, &contract_checks));
+        }
 
         Ok(GenerationOutput {
             code,
@@ -47,3 +71,293 @@ impl CodeGenerator for MoveGenerator {
         "move"
     }
 }
+
+/// Reject expressions with no Move equivalent, saying what to do instead.
+fn check_representable(invariant: &Invariant) -> Result<()> {
+    MoveRepresentable
+        .visit_expression(&invariant.expression)
+        .map_err(|reason| {
+            InvarError::unsupported(format!(
+                "{} {}; enforce it as simulation_only for Move",
+                invariant.name, reason
+            ))
+        })
+}
+
+/// Stops the walk at the first expression Move cannot express.
+struct MoveRepresentable;
+
+impl ExpressionVisitor for MoveRepresentable {
+    type Error = String;
+
+    fn visit_expression(&mut self, expr: &Expression) -> std::result::Result<(), String> {
+        match expr {
+            Expression::MapAccess { map, .. } => Err(format!(
+                "indexes {} with [], which Move tables do not support: read it through a table accessor into a state variable",
+                map
+            )),
+            Expression::Negate(operand) => Err(format!(
+                "negates {}, which Move cannot express without signed integers: compare against a subtraction instead",
+                operand
+            )),
+            _ => walk_expression(self, expr),
+        }
+    }
+
+    fn visit_function_call(
+        &mut self,
+        name: &str,
+        _args: &[Expression],
+    ) -> std::result::Result<(), String> {
+        if BLOCK_BUILTINS.contains(&name) {
+            return Err(format!(
+                "calls {}(), which Move has no builtin for: store the value from the chain's clock or transaction context in a state variable and compare against that",
+                name
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Writes the arithmetic builtins (`add`, ...) as operators, conditionals
+/// as `if` expressions and assertions as their constraint. Move aborts on
+/// overflow and division by zero where the builtins fail.
+struct MoveOperators;
+
+impl ExpressionFolder for MoveOperators {
+    fn fold_expression(&mut self, expr: Expression) -> Expression {
+        match expr {
+            // The abort code already names the invariant
+            Expression::Assert(constraint, _) => self.fold_expression(*constraint),
+            Expression::FunctionCall { name, args }
+                if args.len() == 2 && ARITHMETIC_BUILTINS.contains(&name.as_str()) =>
+            {
+                let operator = match name.as_str() {
+                    "add" => "+",
+                    "sub" => "-",
+                    "mul" => "*",
+                    _ => "/",
+                };
+                let operands: Vec<Expression> = args
+                    .into_iter()
+                    .map(|arg| self.fold_expression(arg))
+                    .collect();
+                Expression::Var(format!("({} {} {})", operands[0], operator, operands[1]))
+            }
+            Expression::Conditional {
+                cond,
+                then_expr,
+                else_expr,
+            } => Expression::Var(format!(
+                "(if ({}) {} else {})",
+                self.fold_expression(*cond),
+                self.fold_expression(*then_expr),
+                self.fold_expression(*else_expr)
+            )),
+            other => fold_children(self, other),
+        }
+    }
+}
+
+/// Function checking module invariants, to be called at the end of every
+/// public function that mutates a resource.
+const MODULE_CHECK: &str = "invar_check_module_invariants";
+
+/// The module invariant check and the functions that must call it.
+fn module_invariant_check(program: &ProgramModel, checks: &[String]) -> String {
+    let mut code = format!(
+        "\n/// Module invariants, checked at the end of every public mutating function.\nfun {}() {{\n",
+        MODULE_CHECK
+    );
+    for check in checks {
+        code.push_str(&format!("    {}\n", check));
+    }
+    code.push_str("}\n");
+
+    let mutating: Vec<&str> = program
+        .functions
+        .values()
+        .filter(|function| !function.mutates.is_empty())
+        .map(|function| function.name.as_str())
+        .collect();
+    if !mutating.is_empty() {
+        code.push_str(&format!(
+            "// Call {}() at the end of: {}\n",
+            MODULE_CHECK,
+            mutating.join(", ")
+        ));
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use invar_core::model::BinaryOp;
+
+    #[test]
+    fn test_rejects_block_builtins_with_actionable_error() {
+        let program = ProgramModel::new(
+            "governor".to_string(),
+            "move".to_string(),
+            "governor.move".to_string(),
+        );
+        let after_eta = Invariant::new(
+            "after_eta",
+            Expression::BinaryOp {
+                left: Box::new(Expression::FunctionCall {
+                    name: "now".to_string(),
+                    args: Vec::new(),
+                }),
+                op: BinaryOp::Gte,
+                right: Box::new(Expression::Var("eta".to_string())),
+            },
+        );
+
+        let err = MoveGenerator
+            .generate(&program, std::slice::from_ref(&after_eta))
+            .unwrap_err();
+        assert!(matches!(err, InvarError::Unsupported(_)));
+        assert!(err.to_string().contains("after_eta calls now()"));
+        assert!(err.to_string().contains("simulation_only"));
+
+        let simulated = after_eta.with_enforcement(EnforcementLevel::SimulationOnly);
+        assert!(MoveGenerator.generate(&program, &[simulated]).is_ok());
+    }
+
+    #[test]
+    fn test_arithmetic_builtins_render_as_operators() {
+        let program = ProgramModel::new(
+            "pool".to_string(),
+            "move".to_string(),
+            "pool.move".to_string(),
+        );
+        let reserves_cover = Invariant::new(
+            "reserves_cover",
+            Expression::BinaryOp {
+                left: Box::new(Expression::Var("reserves".to_string())),
+                op: BinaryOp::Gte,
+                right: Box::new(Expression::FunctionCall {
+                    name: "div".to_string(),
+                    args: vec![Expression::Var("supply".to_string()), Expression::Int(2)],
+                }),
+            },
+        );
+
+        let output = MoveGenerator.generate(&program, &[reserves_cover]).unwrap();
+        assert_eq!(
+            output.assertions,
+            vec!["assert!((reserves >= (supply / 2)), E_INVARIANT_RESERVES_COVER);".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_assertions_check_their_constraint() {
+        let program = ProgramModel::new(
+            "vault".to_string(),
+            "move".to_string(),
+            "vault.move".to_string(),
+        );
+        let positive = Invariant::new(
+            "positive",
+            Expression::Assert(
+                Box::new(Expression::BinaryOp {
+                    left: Box::new(Expression::Var("balance".to_string())),
+                    op: BinaryOp::Gt,
+                    right: Box::new(Expression::Int(0)),
+                }),
+                "must be positive".to_string(),
+            ),
+        );
+
+        let output = MoveGenerator.generate(&program, &[positive]).unwrap();
+        assert_eq!(
+            output.assertions,
+            vec!["assert!((balance > 0), E_INVARIANT_POSITIVE);".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_rejects_quantifiers() {
+        let program = ProgramModel::new(
+            "vault".to_string(),
+            "move".to_string(),
+            "vault.move".to_string(),
+        );
+        let positive = Invariant::new(
+            "deposits_positive",
+            Expression::Quantified {
+                quantifier: invar_core::model::Quantifier::ForAll,
+                var: "x".to_string(),
+                collection: Box::new(Expression::Var("deposits".to_string())),
+                body: Box::new(Expression::BinaryOp {
+                    left: Box::new(Expression::Var("x".to_string())),
+                    op: BinaryOp::Gt,
+                    right: Box::new(Expression::Int(0)),
+                }),
+            },
+        );
+
+        let err = MoveGenerator
+            .generate(&program, std::slice::from_ref(&positive))
+            .unwrap_err();
+        assert!(matches!(err, InvarError::Unsupported(_)));
+        assert!(err
+            .to_string()
+            .contains("deposits_positive uses a quantifier"));
+    }
+
+    #[test]
+    fn test_rejects_negation() {
+        let program = ProgramModel::new(
+            "vault".to_string(),
+            "move".to_string(),
+            "vault.move".to_string(),
+        );
+        let bounded = Invariant::new(
+            "pnl_bounded",
+            Expression::BinaryOp {
+                left: Box::new(Expression::Var("pnl".to_string())),
+                op: BinaryOp::Gte,
+                right: Box::new(Expression::Negate(Box::new(Expression::Var(
+                    "limit".to_string(),
+                )))),
+            },
+        );
+
+        let err = MoveGenerator.generate(&program, &[bounded]).unwrap_err();
+        assert!(matches!(err, InvarError::Unsupported(_)));
+        assert!(err.to_string().contains("pnl_bounded negates limit"));
+    }
+
+    #[test]
+    fn test_conditionals_become_if_expressions() {
+        let program = ProgramModel::new(
+            "vault".to_string(),
+            "move".to_string(),
+            "vault.move".to_string(),
+        );
+        let compare = |var: &str, op| Expression::BinaryOp {
+            left: Box::new(Expression::Var(var.to_string())),
+            op,
+            right: Box::new(Expression::Int(0)),
+        };
+        let settled = Invariant::new(
+            "settled",
+            Expression::Conditional {
+                cond: Box::new(Expression::Var("flag".to_string())),
+                then_expr: Box::new(compare("balance", BinaryOp::Gt)),
+                else_expr: Box::new(compare("balance", BinaryOp::Eq)),
+            },
+        );
+
+        let output = MoveGenerator.generate(&program, &[settled]).unwrap();
+        assert_eq!(
+            output.assertions,
+            vec![
+                "assert!((if (flag) (balance > 0) else (balance == 0)), E_INVARIANT_SETTLED);"
+                    .to_string()
+            ]
+        );
+    }
+}
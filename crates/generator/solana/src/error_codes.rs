@@ -0,0 +1,94 @@
+//! Stable custom error codes for injected Solana checks.
+//!
+//! Each invariant gets its own `ProgramError::Custom` code derived from its
+//! name, so a failing transaction's code identifies the violated invariant
+//! across regenerations, even as invariants are added or reordered.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// First custom error code, matching Anchor's user error range.
+pub const FIRST_ERROR_CODE: u32 = 6000;
+
+/// Number of codes available to invariants, starting at [`FIRST_ERROR_CODE`].
+pub const ERROR_CODE_RANGE: u32 = 1000;
+
+/// Assign an error code to every invariant name.
+///
+/// A code is the FNV-1a hash of the name folded into the range. Collisions
+/// are resolved by probing to the next free code in name order, so the
+/// result depends only on the set of names.
+pub fn assign_error_codes<'a>(names: impl IntoIterator<Item = &'a str>) -> BTreeMap<String, u32> {
+    let names: BTreeSet<&str> = names.into_iter().collect();
+    assert!(
+        names.len() <= ERROR_CODE_RANGE as usize,
+        "at most {} invariants can be assigned error codes",
+        ERROR_CODE_RANGE
+    );
+
+    let mut taken = BTreeSet::new();
+    let mut codes = BTreeMap::new();
+    for name in names {
+        let mut offset = fnv1a(name) % ERROR_CODE_RANGE;
+        while !taken.insert(offset) {
+            offset = (offset + 1) % ERROR_CODE_RANGE;
+        }
+        codes.insert(name.to_string(), FIRST_ERROR_CODE + offset);
+    }
+    codes
+}
+
+/// Rust enum variant for an invariant name: `balance_conservation` becomes
+/// `BalanceConservation`.
+///
+/// Names starting with a digit (or with no alphanumeric characters at all)
+/// are prefixed with `Invariant`.
+pub fn variant_name(name: &str) -> String {
+    let mut variant: String = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            first.into_iter().chain(chars).collect::<String>()
+        })
+        .collect();
+    if !variant.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        variant.insert_str(0, "Invariant");
+    }
+    variant
+}
+
+/// 32-bit FNV-1a, chosen because its output is fixed across Rust releases.
+fn fnv1a(input: &str) -> u32 {
+    input.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_depend_only_on_names() {
+        let before = assign_error_codes(["supply_cap", "balance_conservation"]);
+        let after = assign_error_codes(["balance_conservation", "fee_bound", "supply_cap"]);
+
+        assert_eq!(before["supply_cap"], after["supply_cap"]);
+        assert_eq!(
+            before["balance_conservation"],
+            after["balance_conservation"]
+        );
+        for code in after.values() {
+            assert!((FIRST_ERROR_CODE..FIRST_ERROR_CODE + ERROR_CODE_RANGE).contains(code));
+        }
+    }
+
+    #[test]
+    fn test_variant_name() {
+        assert_eq!(variant_name("balance_conservation"), "BalanceConservation");
+        assert_eq!(variant_name("supply-cap"), "SupplyCap");
+        assert_eq!(variant_name("transfer_hint_1"), "TransferHint1");
+        assert_eq!(variant_name("2pc_commit"), "Invariant2pcCommit");
+    }
+}
@@ -1,12 +1,116 @@
 //! Solana generator implementation.
 
-use invar_core::model::{GenerationOutput, Invariant, ProgramModel};
+use crate::error_codes::{assign_error_codes, variant_name, FIRST_ERROR_CODE};
+use crate::inject::{
+    inject_checks, AccountConstraint, ContractChecks, GuardedCheck, InjectedSource,
+};
+use crate::test_gen::{generate_tests, InjectedCheck};
+use invar_core::model::{EnforcementLevel, Expression, GenerationOutput, Invariant, ProgramModel};
 use invar_core::traits::CodeGenerator;
-use invar_core::Result;
+use invar_core::{
+    fold_children, sort_for_generation, ExpressionFolder, InvarError, Result, ARITHMETIC_BUILTINS,
+    NO_MUTATION_AFTER_CPI,
+};
+use invar_ir::compute_coverage;
+use std::collections::{BTreeMap, BTreeSet};
 use tracing::info;
 
+/// Program framework the generated checks are written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SolanaFramework {
+    /// Plain `solana_program`: checks return `ProgramError::Custom`.
+    #[default]
+    Native,
+    /// Anchor: checks use `require!` with an `#[error_code]` enum and read
+    /// accounts through `ctx.accounts`.
+    Anchor,
+}
+
+impl SolanaFramework {
+    /// Parse a framework name (`native` or `anchor`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "native" => Some(Self::Native),
+            "anchor" => Some(Self::Anchor),
+            _ => None,
+        }
+    }
+}
+
+/// Options for Solana code generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GeneratorOptions {
+    /// Target framework; detected from the program model when `None`.
+    pub framework: Option<SolanaFramework>,
+}
+
 /// Code generator for Solana Rust programs.
-pub struct SolanaGenerator;
+#[derive(Debug, Default)]
+pub struct SolanaGenerator {
+    options: GeneratorOptions,
+}
+
+impl SolanaGenerator {
+    /// Create a generator that detects the framework from the program.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a generator with explicit options.
+    pub fn with_options(options: GeneratorOptions) -> Self {
+        Self { options }
+    }
+
+    /// The framework used for `program`: the configured one, else Anchor when
+    /// the analyzer saw `anchor_lang` imports.
+    pub fn framework_for(&self, program: &ProgramModel) -> SolanaFramework {
+        self.options.framework.unwrap_or_else(|| {
+            program
+                .framework
+                .as_deref()
+                .and_then(SolanaFramework::from_name)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Splice the checks for `invariants` into `source`, the program's own
+    /// code, and append the error enum; the rest of the file is untouched.
+    ///
+    /// Each check guards the functions that mutate a variable it reads, and
+    /// those named in the invariant's `layers` (the function declaring a
+    /// source hint). For Anchor, invariants simple enough for an inline
+    /// constraint are also added to their account's `#[account(mut)]`
+    /// attribute.
+    pub fn inject(
+        &self,
+        source: &str,
+        program: &ProgramModel,
+        invariants: &[Invariant],
+    ) -> Result<InjectedSource> {
+        let output = self.generate(program, invariants)?;
+        let injected = if output.assertions.is_empty() {
+            inject_checks(source, &[], "")?
+        } else {
+            // Assertions follow the order of the checked invariants
+            let checks: Vec<GuardedCheck> = checked_invariants(invariants)?
+                .into_iter()
+                .zip(&output.assertions)
+                .map(|(inv, assertion)| GuardedCheck {
+                    check: assertion.clone(),
+                    functions: guarded_functions(program, inv),
+                })
+                .collect();
+            inject_checks(source, &checks, &output.code)?
+        };
+        let constraints = match self.framework_for(program) {
+            SolanaFramework::Native => Vec::new(),
+            SolanaFramework::Anchor => account_constraints(invariants)?,
+        };
+        injected
+            .with_contracts(&contract_checks(program))?
+            .with_account_constraints(&constraints)
+    }
+}
 
 impl CodeGenerator for SolanaGenerator {
     fn generate(
@@ -14,37 +118,108 @@ impl CodeGenerator for SolanaGenerator {
         program: &ProgramModel,
         invariants: &[Invariant],
     ) -> Result<GenerationOutput> {
+        let framework = self.framework_for(program);
         info!(
-            "Generating code for {} with {} invariants",
+            "Generating {:?} code for {} with {} invariants",
+            framework,
             program.name,
             invariants.len()
         );
 
+        // Simulation-only invariants are checked by the simulator, never on chain
+        let enforced = enforced_invariants(invariants)?;
+        // CPI guards are decided here and need no runtime check
+        let (cpi_guards, enforced): (Vec<_>, Vec<_>) = enforced
+            .into_iter()
+            .partition(|inv| cpi_guarded_function(inv).is_some());
+        for guard in &cpi_guards {
+            check_no_mutation_after_cpi(program, guard)?;
+        }
+        let variants = error_variants(&enforced);
+
         let mut assertions = Vec::new();
-        for inv in invariants {
-            assertions.push(format!(
-                "assert!({}, \"Invariant {} violated\");",
-                inv.expression, inv.name
-            ));
+        for inv in &enforced {
+            let (variant, error_code) = &variants[inv.name.as_str()];
+            let condition = AccountPaths(framework).fold_expression(inv.expression.clone());
+            let cfg = match inv.enforcement {
+                EnforcementLevel::DebugOnly => "#[cfg(debug_assertions)] ",
+                _ => "",
+            };
+            let check = match (framework, &inv.expression) {
+                (SolanaFramework::Native, Expression::Assert(_, message)) => format!(
+                    "if !({}) {{ msg!(\"{{}}\", {:?}); return Err(InvarViolation::{}.into()); }} // {}: {}",
+                    condition, message, variant, error_code, inv.name
+                ),
+                (SolanaFramework::Anchor, Expression::Assert(_, message)) => format!(
+                    "if !({}) {{ msg!(\"{{}}\", {:?}); return err!(InvarError::{}); }} // {}: {}",
+                    condition, message, variant, error_code, inv.name
+                ),
+                (SolanaFramework::Native, _) => format!(
+                    "if !({}) {{ return Err(InvarViolation::{}.into()); }} // {}: {}",
+                    condition, variant, error_code, inv.name
+                ),
+                (SolanaFramework::Anchor, _) => format!(
+                    "require!({}, InvarError::{}); // {}: {}",
+                    condition, variant, error_code, inv.name
+                ),
+            };
+            assertions.push(format!("{}{}", cfg, check));
         }
 
-        let code = format!(
+        let mut code = format!(
             "// Generated invariant checks for {}\n// {} invariants injected\n",
             program.name,
             assertions.len()
         );
+        for guard in &cpi_guards {
+            code.push_str(&format!(
+                "// {}: no state writes after CPI, verified at build time\n",
+                guard.name
+            ));
+        }
+        for contract in contract_checks(program) {
+            code.push_str(&format!("// Contract of {}\n", contract.function));
+            for check in &contract.pre {
+                code.push_str(&format!("//   on entry: {}\n", check));
+            }
+            for check in &contract.post {
+                code.push_str(&format!("//   on exit: {}\n", check));
+            }
+        }
+        // One variant per invariant; the doc comments decode on-chain error codes
+        if !variants.is_empty() {
+            code.push_str(&match framework {
+                SolanaFramework::Native => native_error_enum(&variants),
+                SolanaFramework::Anchor => anchor_error_enum(&variants),
+            });
+        }
 
-        let coverage_percent = if program.functions.is_empty() {
-            0
-        } else {
-            (assertions.len() as u8).min(100)
+        // Anchor checks read `ctx.accounts`, which unit tests cannot construct
+        let tests = match framework {
+            SolanaFramework::Native => {
+                let checks: Vec<InjectedCheck<'_>> = enforced
+                    .iter()
+                    .zip(&assertions)
+                    .map(|(inv, assertion)| {
+                        let (variant, error_code) = &variants[inv.name.as_str()];
+                        InjectedCheck {
+                            invariant: inv,
+                            assertion,
+                            variant,
+                            error_code: *error_code,
+                        }
+                    })
+                    .collect();
+                generate_tests(program, &checks)
+            }
+            SolanaFramework::Anchor => None,
         };
 
         Ok(GenerationOutput {
             code,
             assertions,
-            tests: None,
-            coverage_percent,
+            tests,
+            coverage_percent: compute_coverage(program, invariants).coverage_percent,
         })
     }
 
@@ -52,3 +227,829 @@ impl CodeGenerator for SolanaGenerator {
         "solana"
     }
 }
+
+/// Error enum variant and code of each checked invariant, by name.
+fn error_variants<'a>(checked: &[&'a Invariant]) -> BTreeMap<&'a str, (String, u32)> {
+    let names: BTreeSet<&str> = checked.iter().map(|inv| inv.name.as_str()).collect();
+    let codes = assign_error_codes(names.iter().copied());
+    let mut variants = BTreeMap::new();
+    for name in names {
+        let error_code = codes[name];
+        let mut variant = variant_name(name);
+        // Distinct names can share a variant spelling (`a_b`, `a-b`)
+        if variants.values().any(|(taken, _)| *taken == variant) {
+            variant = format!("{}{}", variant, error_code);
+        }
+        variants.insert(name, (variant, error_code));
+    }
+    variants
+}
+
+/// The enforced invariants that get a runtime check, in generation order.
+fn checked_invariants(invariants: &[Invariant]) -> Result<Vec<&Invariant>> {
+    Ok(enforced_invariants(invariants)?
+        .into_iter()
+        .filter(|inv| cpi_guarded_function(inv).is_none())
+        .collect())
+}
+
+/// Functions `invariant`'s check guards: those mutating a variable it reads,
+/// and those named in its `layers`.
+fn guarded_functions(program: &ProgramModel, invariant: &Invariant) -> BTreeSet<String> {
+    let reads = invariant.expression.variables();
+    program
+        .functions
+        .values()
+        .filter(|function| !function.mutates.is_disjoint(&reads))
+        .map(|function| function.name.clone())
+        .chain(invariant.layers.iter().cloned())
+        .collect()
+}
+
+/// Anchor account constraints for the always-enforced invariants that have
+/// an inline form, raising the same `InvarError` variant as their check.
+fn account_constraints(invariants: &[Invariant]) -> Result<Vec<AccountConstraint>> {
+    let checked = checked_invariants(invariants)?;
+    let variants = error_variants(&checked);
+    // Attributes cannot carry `#[cfg(debug_assertions)]`
+    Ok(checked
+        .iter()
+        .filter(|inv| inv.enforcement != EnforcementLevel::DebugOnly)
+        .filter_map(|inv| {
+            let constraint = inv.for_anchor_constraint()?;
+            let account = constraint.split('.').next()?.to_string();
+            let (variant, _) = &variants[inv.name.as_str()];
+            Some(AccountConstraint {
+                account,
+                constraint: format!("{} @ InvarError::{}", constraint, variant),
+            })
+        })
+        .collect())
+}
+
+/// The invariants to generate checks for, in generation order, refusing to
+/// downgrade security-critical ones.
+fn enforced_invariants(invariants: &[Invariant]) -> Result<Vec<&Invariant>> {
+    let mut enforced = Vec::new();
+    for inv in invariants {
+        inv.check_enforcement()?;
+        inv.check_generatable()?;
+        if inv.expression.functions_called().contains("chain_id") {
+            return Err(InvarError::GenerationFailed(format!(
+                "Invariant {} calls chain_id(), which Solana has no equivalent of",
+                inv.name
+            )));
+        }
+        if inv.enforcement != EnforcementLevel::SimulationOnly {
+            enforced.push(inv);
+        }
+    }
+    sort_for_generation(&mut enforced);
+    Ok(enforced)
+}
+
+/// The handler named by a `no_mutation_after_cpi("<function>")` invariant.
+fn cpi_guarded_function(invariant: &Invariant) -> Option<&str> {
+    match &invariant.expression {
+        Expression::FunctionCall { name, args } if name == NO_MUTATION_AFTER_CPI => {
+            match args.as_slice() {
+                [Expression::String(function)] => Some(function),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Fail generation when the guarded handler writes state after a CPI.
+fn check_no_mutation_after_cpi(program: &ProgramModel, invariant: &Invariant) -> Result<()> {
+    let Some(function) = cpi_guarded_function(invariant) else {
+        return Ok(());
+    };
+    let violation = program
+        .cpi_calls
+        .iter()
+        .filter(|cpi| cpi.function_name == function)
+        .find_map(|cpi| cpi.mutations_after.first().map(|&write| (cpi.line, write)));
+    match violation {
+        Some((cpi_line, write_line)) => Err(InvarError::GenerationFailed(format!(
+            "Invariant {} violated: {} writes state on line {} after the CPI on line {}",
+            invariant.name, function, write_line, cpi_line
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// `InvarViolation` with explicit codes and a `ProgramError` conversion.
+fn native_error_enum(variants: &BTreeMap<&str, (String, u32)>) -> String {
+    let mut code = String::from("\n/// Custom error codes for violated invariants.\n");
+    code.push_str("#[derive(Debug)]\npub enum InvarViolation {\n");
+    for (name, (variant, error_code)) in variants {
+        code.push_str(&format!(
+            "    /// Invariant `{}`.\n    {} = {},\n",
+            name, variant, error_code
+        ));
+    }
+    code.push_str(
+        "}\n\n\
+         impl From<InvarViolation> for ProgramError {\n    \
+             fn from(violation: InvarViolation) -> Self {\n        \
+                 ProgramError::Custom(violation as u32)\n    \
+             }\n\
+         }\n",
+    );
+    code
+}
+
+/// Anchor `#[error_code]` enum; Anchor adds its 6000 offset to each discriminant.
+fn anchor_error_enum(variants: &BTreeMap<&str, (String, u32)>) -> String {
+    let mut code = String::from("\n/// Custom error codes for violated invariants.\n");
+    code.push_str("#[error_code]\npub enum InvarError {\n");
+    for (name, (variant, error_code)) in variants {
+        code.push_str(&format!(
+            "    #[msg(\"Invariant {} violated\")]\n    {} = {},\n",
+            name,
+            variant,
+            error_code - FIRST_ERROR_CODE
+        ));
+    }
+    code.push_str("}\n");
+    code
+}
+
+/// `assert!`s for the pre- and post-conditions of each function that has
+/// them. Contracts name parameters and accounts as the function body sees
+/// them, so variables are not routed through `ctx.accounts`.
+fn contract_checks(program: &ProgramModel) -> Vec<ContractChecks> {
+    let assertions = |conditions: &[Expression], kind: &str| -> Vec<String> {
+        conditions
+            .iter()
+            .map(|condition| {
+                let condition =
+                    AccountPaths(SolanaFramework::Native).fold_expression(condition.clone());
+                let text = condition.to_string();
+                // Operators display wrapped in parentheses
+                let condition = match condition {
+                    Expression::BinaryOp { .. } | Expression::Logical { .. } => {
+                        text[1..text.len() - 1].to_string()
+                    }
+                    _ => text,
+                };
+                let message = format!("{} failed: {}", kind, condition);
+                format!("assert!({}, {:?});", condition, message)
+            })
+            .collect()
+    };
+    program
+        .functions
+        .values()
+        .filter(|f| !f.pre_conditions.is_empty() || !f.post_conditions.is_empty())
+        .map(|f| ContractChecks {
+            function: f.name.clone(),
+            pre: assertions(&f.pre_conditions, "precondition"),
+            post: assertions(&f.post_conditions, "postcondition"),
+        })
+        .collect()
+}
+
+/// Rewrites variables as account field paths: `vault::balance` becomes
+/// `vault.balance`, and Anchor reads every account through `ctx.accounts`.
+/// `now()` and `block_number()` read the `Clock` sysvar, the arithmetic
+/// builtins (`add`, ...) become checked integer methods and conditionals
+/// become `if` expressions.
+struct AccountPaths(SolanaFramework);
+
+impl AccountPaths {
+    fn prefix(&self) -> &'static str {
+        match self.0 {
+            SolanaFramework::Native => "",
+            SolanaFramework::Anchor => "ctx.accounts.",
+        }
+    }
+}
+
+impl ExpressionFolder for AccountPaths {
+    // Assertion messages are logged by the surrounding check, not the condition
+    fn fold_expression(&mut self, expr: Expression) -> Expression {
+        match expr {
+            Expression::Assert(constraint, _) => self.fold_expression(*constraint),
+            Expression::FunctionCall { name, args } if args.is_empty() => match name.as_str() {
+                // Parenthesized so a following `<` is not read as generics
+                "now" => Expression::Var("(Clock::get()?.unix_timestamp as u64)".to_string()),
+                "block_number" => Expression::Var("Clock::get()?.slot".to_string()),
+                _ => Expression::FunctionCall { name, args },
+            },
+            // Fails the instruction on overflow, as the evaluator's builtins do
+            Expression::FunctionCall { name, args }
+                if args.len() == 2 && ARITHMETIC_BUILTINS.contains(&name.as_str()) =>
+            {
+                let operands: Vec<String> = args
+                    .into_iter()
+                    .map(|arg| match self.fold_expression(arg) {
+                        // A bare literal receiver has no inferable type
+                        Expression::Int(n) => format!("{}u64", n),
+                        operand @ (Expression::Var(_) | Expression::FunctionCall { .. }) => {
+                            operand.to_string()
+                        }
+                        operand => format!("({})", operand),
+                    })
+                    .collect();
+                Expression::Var(format!(
+                    "{}.checked_{}({}).ok_or(ProgramError::ArithmeticOverflow)?",
+                    operands[0], name, operands[1]
+                ))
+            }
+            // Parenthesized so the result can be an operand
+            Expression::Conditional {
+                cond,
+                then_expr,
+                else_expr,
+            } => Expression::Var(format!(
+                "(if {} {{ {} }} else {{ {} }})",
+                self.fold_expression(*cond),
+                self.fold_expression(*then_expr),
+                self.fold_expression(*else_expr)
+            )),
+            other => fold_children(self, other),
+        }
+    }
+
+    fn fold_var(&mut self, name: String) -> Expression {
+        Expression::Var(format!("{}{}", self.prefix(), name))
+    }
+
+    fn fold_layer_var(&mut self, layer: String, var: String) -> Expression {
+        Expression::Var(format!("{}{}.{}", self.prefix(), layer, var))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use invar_core::model::{BinaryOp, Expression, FunctionModel};
+
+    fn invariant(name: &str, var: &str) -> Invariant {
+        Invariant::new(
+            name,
+            Expression::BinaryOp {
+                left: Box::new(Expression::Var(var.to_string())),
+                op: BinaryOp::Gte,
+                right: Box::new(Expression::Int(0)),
+            },
+        )
+    }
+
+    fn program() -> ProgramModel {
+        ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "lib.rs".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_each_invariant_gets_its_own_error_code() {
+        let invariants = [
+            invariant("balance_conservation", "balance"),
+            invariant("supply_cap", "supply"),
+        ];
+        let output = SolanaGenerator::new()
+            .generate(&program(), &invariants)
+            .unwrap();
+        let codes = assign_error_codes(["balance_conservation", "supply_cap"]);
+        assert_ne!(codes["balance_conservation"], codes["supply_cap"]);
+
+        assert_eq!(
+            output.assertions[0],
+            format!(
+                "if !((balance >= 0)) {{ return Err(InvarViolation::BalanceConservation.into()); }} // {}: balance_conservation",
+                codes["balance_conservation"]
+            )
+        );
+        assert!(output
+            .code
+            .contains(&format!("    SupplyCap = {},\n", codes["supply_cap"])));
+        assert!(output
+            .code
+            .contains("impl From<InvarViolation> for ProgramError"));
+    }
+
+    #[test]
+    fn test_regeneration_keeps_codes_by_name() {
+        let first = SolanaGenerator::new()
+            .generate(
+                &program(),
+                &[
+                    invariant("balance_conservation", "balance"),
+                    invariant("supply_cap", "supply"),
+                ],
+            )
+            .unwrap();
+        let second = SolanaGenerator::new()
+            .generate(
+                &program(),
+                &[
+                    invariant("supply_cap", "supply"),
+                    invariant("fee_bound", "fee"),
+                    invariant("balance_conservation", "balance"),
+                ],
+            )
+            .unwrap();
+
+        // Existing checks keep their codes; only the new one is added
+        let diff = second.diff(&first);
+        assert_eq!(
+            diff.added_assertions,
+            vec![
+                "if !((fee >= 0)) { return Err(InvarViolation::FeeBound.into()); } // 6228: fee_bound"
+            ]
+        );
+        assert!(diff.removed_assertions.is_empty());
+    }
+
+    #[test]
+    fn test_regeneration_is_idempotent_and_local() {
+        let source = "pub fn deposit(vault: &mut Vault) -> ProgramResult {\n    vault.balance += 1;\n    Ok(())\n}\n\npub fn withdraw(vault: &mut Vault) -> ProgramResult {\n    vault.balance -= 1;\n    Ok(())\n}\n";
+        let mut program = program();
+        for name in ["deposit", "withdraw"] {
+            program.add_function(FunctionModel {
+                name: name.to_string(),
+                parameters: vec!["vault".to_string()],
+                return_type: None,
+                mutates: ["balance", "supply", "fee"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+                reads: BTreeSet::new(),
+                is_entry_point: true,
+                is_pure: false,
+                overflow_protection: Default::default(),
+                span: None,
+                modifiers: Vec::new(),
+                uses_low_level: false,
+                pre_conditions: Vec::new(),
+                post_conditions: Vec::new(),
+            });
+        }
+        let build = |invariants: &[Invariant]| {
+            let output = SolanaGenerator::new()
+                .generate(&program, invariants)
+                .unwrap();
+            let injected = SolanaGenerator::new()
+                .inject(source, &program, invariants)
+                .unwrap();
+            GenerationOutput {
+                code: injected.source(),
+                ..output
+            }
+        };
+        let before = [
+            invariant("balance_conservation", "balance"),
+            invariant("supply_cap", "supply"),
+        ];
+        let first = build(&before);
+        assert!(first.diff(&build(&before)).is_empty());
+        // Declaration order does not matter
+        let reordered = [before[1].clone(), before[0].clone()];
+        assert_eq!(build(&reordered).code, first.code);
+
+        let after = [
+            before[0].clone(),
+            before[1].clone(),
+            invariant("fee_bound", "fee"),
+        ];
+        let diff = build(&after).diff(&first);
+        // Both functions carry every check ahead of the trailer
+        let injected_lines = source.lines().count() + 2 * before.len();
+        let (in_functions, in_trailer): (Vec<_>, Vec<_>) = diff
+            .hunks
+            .iter()
+            .partition(|hunk| hunk.previous_line <= injected_lines);
+        // One added check per guarded function
+        assert_eq!(in_functions.len(), 2);
+        for hunk in in_functions {
+            assert!(hunk.removed.is_empty());
+            assert_eq!(hunk.added.len(), 1);
+            assert!(hunk.added[0].contains("InvarViolation::FeeBound"));
+        }
+        // The trailer counts the checks and declares the new error variant
+        assert!(in_trailer
+            .iter()
+            .any(|hunk| hunk.added == vec!["// 3 invariants injected"]));
+        assert!(in_trailer
+            .iter()
+            .any(|hunk| hunk.added.iter().any(|line| line.contains("FeeBound = "))));
+    }
+
+    fn golden_invariants() -> Vec<Invariant> {
+        vec![
+            Invariant::new(
+                "vault_solvent",
+                Expression::BinaryOp {
+                    left: Box::new(Expression::LayerVar {
+                        layer: "vault".to_string(),
+                        var: "balance".to_string(),
+                    }),
+                    op: BinaryOp::Gte,
+                    right: Box::new(Expression::Var("min_reserve".to_string())),
+                },
+            ),
+            invariant("fee_bound", "fee"),
+        ]
+    }
+
+    #[test]
+    fn test_native_golden_output() {
+        let output = SolanaGenerator::new()
+            .generate(&program(), &golden_invariants())
+            .unwrap();
+
+        assert_eq!(
+            output.assertions,
+            vec![
+                "if !((fee >= 0)) { return Err(InvarViolation::FeeBound.into()); } // 6228: fee_bound",
+                "if !((vault.balance >= min_reserve)) { return Err(InvarViolation::VaultSolvent.into()); } // 6969: vault_solvent",
+            ]
+        );
+        assert_eq!(
+            output.code,
+            "// Generated invariant checks for vault
+// 2 invariants injected
+
+/// Custom error codes for violated invariants.
+#[derive(Debug)]
+pub enum InvarViolation {
+    /// Invariant `fee_bound`.
+    FeeBound = 6228,
+    /// Invariant `vault_solvent`.
+    VaultSolvent = 6969,
+}
+
+impl From<InvarViolation> for ProgramError {
+    fn from(violation: InvarViolation) -> Self {
+        ProgramError::Custom(violation as u32)
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_anchor_golden_output() {
+        let mut anchor = program();
+        anchor.framework = Some("anchor".to_string());
+        let output = SolanaGenerator::new()
+            .generate(&anchor, &golden_invariants())
+            .unwrap();
+
+        assert_eq!(
+            output.assertions,
+            vec![
+                "require!((ctx.accounts.fee >= 0), InvarError::FeeBound); // 6228: fee_bound",
+                "require!((ctx.accounts.vault.balance >= ctx.accounts.min_reserve), InvarError::VaultSolvent); // 6969: vault_solvent",
+            ]
+        );
+        assert_eq!(
+            output.code,
+            "// Generated invariant checks for vault
+// 2 invariants injected
+
+/// Custom error codes for violated invariants.
+#[error_code]
+pub enum InvarError {
+    #[msg(\"Invariant fee_bound violated\")]
+    FeeBound = 228,
+    #[msg(\"Invariant vault_solvent violated\")]
+    VaultSolvent = 969,
+}
+"
+        );
+
+        // An explicit framework overrides detection
+        let native = SolanaGenerator::with_options(GeneratorOptions {
+            framework: Some(SolanaFramework::Native),
+        });
+        assert_eq!(native.framework_for(&anchor), SolanaFramework::Native);
+    }
+
+    #[test]
+    fn test_enforcement_levels() {
+        let invariants = [
+            invariant("fee_bound", "fee").with_enforcement(EnforcementLevel::DebugOnly),
+            invariant("solvent", "reserve").with_enforcement(EnforcementLevel::SimulationOnly),
+        ];
+        let output = SolanaGenerator::new()
+            .generate(&program(), &invariants)
+            .unwrap();
+        assert_eq!(output.assertions.len(), 1);
+        assert!(output.assertions[0]
+            .starts_with("#[cfg(debug_assertions)] if !((fee >= 0)) { return Err(InvarViolation::FeeBound.into()); }"));
+        assert!(!output.code.contains("Solvent"));
+
+        let downgraded = [invariant("supply", "supply")
+            .with_category("core")
+            .with_enforcement(EnforcementLevel::DebugOnly)];
+        let err = SolanaGenerator::new()
+            .generate(&program(), &downgraded)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("security-critical category 'core'"));
+    }
+
+    #[test]
+    fn test_assert_logs_its_message() {
+        let checked = invariant("fee_bound", "fee");
+        let asserted = Invariant::new(
+            "fee_bound",
+            Expression::Assert(
+                Box::new(checked.expression),
+                "fee must be non-negative".to_string(),
+            ),
+        );
+        let output = SolanaGenerator::new()
+            .generate(&program(), &[asserted])
+            .unwrap();
+        assert_eq!(
+            output.assertions,
+            vec![
+                "if !((fee >= 0)) { msg!(\"{}\", \"fee must be non-negative\"); return Err(InvarViolation::FeeBound.into()); } // 6228: fee_bound",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mutation_after_cpi_fails_generation() {
+        let guard = Invariant::new(
+            "no_mutation_after_cpi_withdraw",
+            Expression::FunctionCall {
+                name: NO_MUTATION_AFTER_CPI.to_string(),
+                args: vec![Expression::String("withdraw".to_string())],
+            },
+        );
+        let mut program = program();
+        program.cpi_calls.push(invar_core::CpiCall {
+            function_name: "withdraw".to_string(),
+            line: 4,
+            mutations_after: vec![7],
+        });
+        let err = SolanaGenerator::new()
+            .generate(&program, std::slice::from_ref(&guard))
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("withdraw writes state on line 7 after the CPI on line 4"));
+
+        // Without later writes the guard holds and adds no runtime check
+        program.cpi_calls[0].mutations_after.clear();
+        let output = SolanaGenerator::new()
+            .generate(&program, &[guard, invariant("fee_bound", "fee")])
+            .unwrap();
+        assert_eq!(output.assertions.len(), 1);
+        assert!(output.code.contains(
+            "// no_mutation_after_cpi_withdraw: no state writes after CPI, verified at build time"
+        ));
+    }
+
+    #[test]
+    fn test_block_builtins_read_the_clock() {
+        let call = |name: &str| {
+            Box::new(Expression::FunctionCall {
+                name: name.to_string(),
+                args: vec![],
+            })
+        };
+        let deadline = Invariant::new(
+            "before_deadline",
+            Expression::BinaryOp {
+                left: call("now"),
+                op: BinaryOp::Lt,
+                right: Box::new(Expression::Var("deadline".to_string())),
+            },
+        );
+        let output = SolanaGenerator::with_options(GeneratorOptions {
+            framework: Some(SolanaFramework::Anchor),
+        })
+        .generate(&program(), &[deadline])
+        .unwrap();
+        assert!(output.assertions[0].starts_with(
+            "require!(((Clock::get()?.unix_timestamp as u64) < ctx.accounts.deadline), "
+        ));
+
+        let chain = Invariant::new(
+            "mainnet_only",
+            Expression::BinaryOp {
+                left: call("chain_id"),
+                op: BinaryOp::Eq,
+                right: Box::new(Expression::Int(1)),
+            },
+        );
+        let err = SolanaGenerator::new()
+            .generate(&program(), &[chain])
+            .unwrap_err();
+        assert!(err.to_string().contains("calls chain_id()"));
+    }
+
+    #[test]
+    fn test_arithmetic_builtins_are_checked() {
+        let var = |name: &str| Expression::Var(name.to_string());
+        let call = |name: &str, args| Expression::FunctionCall {
+            name: name.to_string(),
+            args,
+        };
+        let covered = Invariant::new(
+            "deposit_covers_cost",
+            Expression::BinaryOp {
+                left: Box::new(var("deposit")),
+                op: BinaryOp::Gte,
+                right: Box::new(call(
+                    "mul",
+                    vec![
+                        call("add", vec![var("gas"), Expression::Int(1)]),
+                        var("price"),
+                    ],
+                )),
+            },
+        );
+        let output = SolanaGenerator::new()
+            .generate(&program(), &[covered])
+            .unwrap();
+        assert!(output.assertions[0].starts_with(
+            "if !((deposit >= gas.checked_add(1u64).ok_or(ProgramError::ArithmeticOverflow)?.checked_mul(price).ok_or(ProgramError::ArithmeticOverflow)?)) {"
+        ));
+
+        // Pre-transaction values are not captured by generated checks
+        let monotonic = Invariant::new(
+            "deposit_monotonic",
+            Expression::BinaryOp {
+                left: Box::new(var("deposit")),
+                op: BinaryOp::Gte,
+                right: Box::new(call("old", vec![var("deposit")])),
+            },
+        );
+        let err = SolanaGenerator::new()
+            .generate(&program(), std::slice::from_ref(&monotonic))
+            .unwrap_err();
+        assert!(err.to_string().contains("uses old()"));
+        let simulated = monotonic.with_enforcement(EnforcementLevel::SimulationOnly);
+        assert!(SolanaGenerator::new()
+            .generate(&program(), &[simulated])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_rejects_quantifiers() {
+        let positive = Invariant::new(
+            "deposits_positive",
+            Expression::Quantified {
+                quantifier: invar_core::model::Quantifier::ForAll,
+                var: "x".to_string(),
+                collection: Box::new(Expression::Var("deposits".to_string())),
+                body: Box::new(Expression::BinaryOp {
+                    left: Box::new(Expression::Var("x".to_string())),
+                    op: BinaryOp::Gt,
+                    right: Box::new(Expression::Int(0)),
+                }),
+            },
+        );
+        let err = SolanaGenerator::new()
+            .generate(&program(), std::slice::from_ref(&positive))
+            .unwrap_err();
+        assert!(matches!(err, InvarError::Unsupported(_)));
+        assert!(err
+            .to_string()
+            .contains("deposits_positive uses a quantifier"));
+        let simulated = positive.with_enforcement(EnforcementLevel::SimulationOnly);
+        assert!(SolanaGenerator::new()
+            .generate(&program(), &[simulated])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_conditionals_become_if_expressions() {
+        let compare = |var: &str, op| Expression::BinaryOp {
+            left: Box::new(Expression::Var(var.to_string())),
+            op,
+            right: Box::new(Expression::Int(0)),
+        };
+        let settled = Invariant::new(
+            "settled",
+            Expression::Conditional {
+                cond: Box::new(Expression::Var("flag".to_string())),
+                then_expr: Box::new(compare("balance", BinaryOp::Gt)),
+                else_expr: Box::new(compare("balance", BinaryOp::Eq)),
+            },
+        );
+        let output = SolanaGenerator::new()
+            .generate(&program(), &[settled])
+            .unwrap();
+        assert_eq!(
+            output.assertions,
+            vec![
+                "if !((if flag { (balance > 0) } else { (balance == 0) })) { return Err(InvarViolation::Settled.into()); } // 6770: settled",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contract_assertions() {
+        use invar_analyzer_solana::SolanaAnalyzer;
+        use invar_core::traits::ChainAnalyzer;
+
+        let source = "/// Deposit into the vault.\n/// @pre amount > 0\n/// @post amount <= 1000000\npub fn deposit(vault: &mut Vault, amount: u64) -> ProgramResult {\n    if vault.frozen {\n        return Err(ProgramError::InvalidArgument);\n    }\n    vault.balance += amount;\n    Ok(())\n}\n";
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        std::fs::write(&path, source).unwrap();
+        let program = SolanaAnalyzer.analyze(&path).unwrap();
+
+        let generator = SolanaGenerator::new();
+        let output = generator.generate(&program, &[]).unwrap();
+        assert!(output.code.contains(
+            "// Contract of deposit\n//   on entry: assert!(amount > 0, \"precondition failed: amount > 0\");\n"
+        ));
+
+        let injected = generator.inject(source, &program, &[]).unwrap().source();
+        assert!(injected.contains(
+            "-> ProgramResult {\n    assert!(amount > 0, \"precondition failed: amount > 0\");\n    if vault.frozen {"
+        ));
+        let post = "assert!(amount <= 1000000, \"postcondition failed: amount <= 1000000\");";
+        assert!(injected.contains(&format!(
+            "        {}\n        return Err(ProgramError::InvalidArgument);",
+            post
+        )));
+        assert!(injected.contains(&format!("    {}\n    Ok(())\n}}", post)));
+    }
+
+    #[test]
+    fn test_checks_guard_only_functions_touching_their_state() {
+        use invar_analyzer_solana::SolanaAnalyzer;
+        use invar_core::traits::ChainAnalyzer;
+
+        let source = "pub fn withdraw(vault: &mut Vault, amount: u64) -> ProgramResult {\n    if amount == 0 {\n        return Ok(());\n    }\n    vault.balance -= amount;\n    Ok(())\n}\n\npub fn quote(fee: u64) -> ProgramResult {\n    Ok(())\n}\n\npub fn ping() -> ProgramResult {\n    Ok(())\n}\n";
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        std::fs::write(&path, source).unwrap();
+        let program = SolanaAnalyzer.analyze(&path).unwrap();
+
+        let solvent = Invariant::new(
+            "vault_solvent",
+            Expression::BinaryOp {
+                left: Box::new(Expression::LayerVar {
+                    layer: "vault".to_string(),
+                    var: "balance".to_string(),
+                }),
+                op: BinaryOp::Gte,
+                right: Box::new(Expression::Int(0)),
+            },
+        );
+        // Declared on `quote`, which writes nothing
+        let fee_bound = invariant("fee_bound", "fee").for_layers(vec!["quote".to_string()]);
+
+        let injected = SolanaGenerator::new()
+            .inject(source, &program, &[solvent, fee_bound])
+            .unwrap()
+            .source();
+        let solvent = "if !((vault.balance >= 0)) { return Err(InvarViolation::VaultSolvent.into()); } // 6969: vault_solvent";
+        let fee_bound =
+            "if !((fee >= 0)) { return Err(InvarViolation::FeeBound.into()); } // 6228: fee_bound";
+        assert!(injected.starts_with(&format!(
+            "pub fn withdraw(vault: &mut Vault, amount: u64) -> ProgramResult {{\n    if amount == 0 {{\n        {}\n        return Ok(());\n    }}\n    vault.balance -= amount;\n    {}\n    Ok(())\n}}\n\npub fn quote(fee: u64) -> ProgramResult {{\n    {}\n    Ok(())\n}}\n\npub fn ping() -> ProgramResult {{\n    Ok(())\n}}\n",
+            solvent, solvent, fee_bound
+        )));
+    }
+
+    #[test]
+    fn test_anchor_inject_adds_account_constraints() {
+        let source = "#[derive(Accounts)]\npub struct Deposit<'info> {\n    #[account(mut)]\n    pub vault: Account<'info, Vault>,\n}\n";
+        let funded = Invariant::new(
+            "vault_funded",
+            Expression::BinaryOp {
+                left: Box::new(Expression::LayerVar {
+                    layer: "vault".to_string(),
+                    var: "balance".to_string(),
+                }),
+                op: BinaryOp::Gte,
+                right: Box::new(Expression::Int(0)),
+            },
+        );
+        let mut anchor = program();
+        anchor.framework = Some("anchor".to_string());
+        let invariants = [funded, invariant("fee_bound", "fee")];
+
+        let injected = SolanaGenerator::new()
+            .inject(source, &anchor, &invariants)
+            .unwrap()
+            .source();
+        assert!(injected.contains(
+            "    #[account(mut, constraint = vault.balance >= 0 @ InvarError::VaultFunded)]\n    pub vault:"
+        ));
+        assert!(!injected.contains("constraint = fee"));
+
+        // Native programs have no account constraints
+        let native = SolanaGenerator::new()
+            .inject(source, &program(), &invariants)
+            .unwrap()
+            .source();
+        assert!(native.contains("    #[account(mut)]\n"));
+    }
+}
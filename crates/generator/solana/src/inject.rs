@@ -0,0 +1,715 @@
+//! Splicing generated checks into the original program source.
+//!
+//! Insertion points come from `syn` spans and the check text is inserted
+//! into the source string at those byte offsets, so everything outside the
+//! inserted regions (formatting, comments, blank lines) stays byte-identical.
+
+use invar_core::{InvarError, Result};
+use proc_macro2::LineColumn;
+use std::collections::BTreeSet;
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+use syn::{Expr, Item, ItemFn, ItemStruct, Meta, ReturnType, Stmt, Type};
+
+/// Lines of unchanged context around each hunk of [`InjectedSource::unified_diff`].
+const DIFF_CONTEXT: usize = 3;
+
+/// Stand-ins for the `solana_program` items generated checks use
+/// (`ProgramError`, `ProgramResult`, `msg!` and the `Clock` sysvar), for
+/// compiling an injected program with plain `rustc`. Programs importing other
+/// `solana_program` items need the real crate.
+pub const COMPILE_PRELUDE: &str = r#"// Stand-ins for the `solana_program` items generated checks use
+#[allow(dead_code)]
+mod solana_program {
+    pub mod program_error {
+        #[derive(Debug, PartialEq)]
+        pub enum ProgramError {
+            Custom(u32),
+            ArithmeticOverflow,
+            InvalidArgument,
+        }
+    }
+    pub mod entrypoint {
+        pub type ProgramResult = Result<(), super::program_error::ProgramError>;
+    }
+    pub mod clock {
+        pub struct Clock {
+            pub slot: u64,
+            pub unix_timestamp: i64,
+        }
+    }
+    pub mod sysvar {
+        pub trait Sysvar: Sized {
+            fn get() -> Result<Self, super::program_error::ProgramError>;
+        }
+        impl Sysvar for super::clock::Clock {
+            fn get() -> Result<Self, super::program_error::ProgramError> {
+                Ok(Self { slot: 0, unix_timestamp: 0 })
+            }
+        }
+    }
+    macro_rules! msg {
+        ($($arg:tt)*) => {
+            let _ = format!($($arg)*);
+        };
+    }
+    pub(crate) use msg;
+    pub mod prelude {
+        pub(crate) use super::msg;
+        pub use super::clock::Clock;
+        pub use super::entrypoint::ProgramResult;
+        pub use super::program_error::ProgramError;
+        pub use super::sysvar::Sysvar;
+    }
+}
+#[allow(unused_imports)]
+use solana_program::prelude::*;
+"#;
+
+/// Text inserted into the original source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Insertion {
+    /// Byte offset in the original source.
+    pub offset: usize,
+    /// Inserted text.
+    pub text: String,
+}
+
+/// An invariant check and the functions it guards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuardedCheck {
+    /// Check statement.
+    pub check: String,
+    /// Functions that write state the check reads, or that declare it.
+    pub functions: BTreeSet<String>,
+}
+
+/// Design-by-contract checks for one function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractChecks {
+    /// Name of the function.
+    pub function: String,
+    /// Checks inserted at the start of the body.
+    pub pre: Vec<String>,
+    /// Checks inserted before every `return` and before the final
+    /// expression (or closing brace).
+    pub post: Vec<String>,
+}
+
+/// An Anchor account constraint for one accounts-struct field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountConstraint {
+    /// Name of the account field, such as `vault`.
+    pub account: String,
+    /// Constraint and its error, such as `vault.balance >= 0 @ InvarError::Solvent`.
+    pub constraint: String,
+}
+
+/// The original source together with the insertions made into it.
+#[derive(Debug, Clone)]
+pub struct InjectedSource {
+    /// Source before injection.
+    pub original: String,
+    /// Insertions, ordered by offset.
+    pub insertions: Vec<Insertion>,
+}
+
+impl InjectedSource {
+    /// The source with every insertion applied.
+    pub fn source(&self) -> String {
+        let mut source = String::with_capacity(
+            self.original.len() + self.insertions.iter().map(|i| i.text.len()).sum::<usize>(),
+        );
+        let mut copied = 0;
+        for insertion in &self.insertions {
+            source.push_str(&self.original[copied..insertion.offset]);
+            source.push_str(&insertion.text);
+            copied = insertion.offset;
+        }
+        source.push_str(&self.original[copied..]);
+        source
+    }
+
+    /// Add the contract checks of every function named in `contracts`.
+    ///
+    /// Pre-conditions go before anything else inserted at the same place,
+    /// post-conditions after it.
+    pub fn with_contracts(mut self, contracts: &[ContractChecks]) -> Result<Self> {
+        if contracts.is_empty() {
+            return Ok(self);
+        }
+        let source = self.original.as_str();
+        let file = syn::parse_file(source)
+            .map_err(|e| InvarError::GenerationFailed(format!("Failed to parse Rust: {}", e)))?;
+        let lines = LineIndex::new(source);
+        let mut functions = Vec::new();
+        collect_functions(&file.items, &mut functions);
+
+        let mut pre = Vec::new();
+        let mut post = Vec::new();
+        for contract in contracts {
+            for function in functions
+                .iter()
+                .filter(|function| function.sig.ident == contract.function)
+            {
+                if !contract.pre.is_empty() {
+                    pre.push(entry_guard(source, &lines, function, &contract.pre));
+                }
+                if !contract.post.is_empty() {
+                    post.extend(exit_guards(source, &lines, function, &contract.post, true));
+                }
+            }
+        }
+        pre.append(&mut self.insertions);
+        pre.append(&mut post);
+        pre.sort_by_key(|insertion| insertion.offset);
+        self.insertions = pre;
+        Ok(self)
+    }
+
+    /// Append each constraint to the `#[account(mut)]` attribute of every
+    /// struct field named by its `account`: `#[account(mut, constraint = ...)]`.
+    ///
+    /// Fields without `mut` in their `#[account(...)]` are left alone.
+    pub fn with_account_constraints(mut self, constraints: &[AccountConstraint]) -> Result<Self> {
+        if constraints.is_empty() {
+            return Ok(self);
+        }
+        let source = self.original.as_str();
+        let file = syn::parse_file(source)
+            .map_err(|e| InvarError::GenerationFailed(format!("Failed to parse Rust: {}", e)))?;
+        let lines = LineIndex::new(source);
+        let mut structs = Vec::new();
+        collect_structs(&file.items, &mut structs);
+
+        for field in structs.iter().flat_map(|item| item.fields.iter()) {
+            let Some(name) = &field.ident else {
+                continue;
+            };
+            let Some(close) = field.attrs.iter().find_map(mutable_account_attribute) else {
+                continue;
+            };
+            for constraint in constraints.iter().filter(|c| *name == c.account) {
+                self.insertions.push(Insertion {
+                    offset: lines.offset(source, close),
+                    text: format!(", constraint = {}", constraint.constraint),
+                });
+            }
+        }
+        self.insertions.sort_by_key(|insertion| insertion.offset);
+        Ok(self)
+    }
+
+    /// Unified diff from the original source to the injected one, with both
+    /// sides labelled `path`.
+    pub fn unified_diff(&self, path: &str) -> String {
+        let ops = self.line_ops();
+        let changed: Vec<usize> = (0..ops.len()).filter(|&i| ops[i].0 != ' ').collect();
+        if changed.is_empty() {
+            return String::new();
+        }
+
+        let mut diff = format!("--- a/{}\n+++ b/{}\n", path, path);
+        // Line numbers before each op, on the old and new side
+        let mut positions = Vec::with_capacity(ops.len() + 1);
+        let (mut old, mut new) = (1, 1);
+        for (kind, _) in &ops {
+            positions.push((old, new));
+            match kind {
+                '-' => old += 1,
+                '+' => new += 1,
+                _ => {
+                    old += 1;
+                    new += 1;
+                }
+            }
+        }
+        positions.push((old, new));
+
+        // Changes closer than two contexts share a hunk
+        let mut groups: Vec<(usize, usize)> = Vec::new();
+        for &c in &changed {
+            match groups.last_mut() {
+                Some((_, end)) if c - *end <= 2 * DIFF_CONTEXT => *end = c,
+                _ => groups.push((c, c)),
+            }
+        }
+        for (first, last) in groups {
+            let start = first.saturating_sub(DIFF_CONTEXT);
+            let end = (last + DIFF_CONTEXT + 1).min(ops.len());
+            let old_len = ops[start..end].iter().filter(|(k, _)| *k != '+').count();
+            let new_len = ops[start..end].iter().filter(|(k, _)| *k != '-').count();
+            let (old_start, new_start) = positions[start];
+            diff.push_str(&format!(
+                "@@ -{},{} +{},{} @@\n",
+                hunk_start(old_start, old_len),
+                old_len,
+                hunk_start(new_start, new_len),
+                new_len
+            ));
+            for (kind, line) in &ops[start..end] {
+                diff.push(*kind);
+                diff.push_str(line);
+                diff.push('\n');
+            }
+        }
+        diff
+    }
+
+    /// Line-level edit script: `' '` keeps, `'-'` removes and `'+'` adds a line.
+    fn line_ops(&self) -> Vec<(char, String)> {
+        let mut ops = Vec::new();
+        let mut pending = self.insertions.iter().peekable();
+        let mut line_start = 0;
+        for line in self.original.split_inclusive('\n') {
+            let line_end = line_start + line.len();
+            let mut inserted_before = String::new();
+            let mut rewritten = String::new();
+            let mut copied = line_start;
+            // Insertions at the start of a line precede it; later ones rewrite it
+            while let Some(insertion) = pending.next_if(|i| i.offset < line_end) {
+                if insertion.offset == line_start && insertion.text.ends_with('\n') {
+                    inserted_before.push_str(&insertion.text);
+                } else {
+                    rewritten.push_str(&self.original[copied..insertion.offset]);
+                    rewritten.push_str(&insertion.text);
+                    copied = insertion.offset;
+                }
+            }
+            ops.extend(inserted_before.lines().map(|l| ('+', l.to_string())));
+            let original = line.trim_end_matches('\n');
+            if copied == line_start {
+                ops.push((' ', original.to_string()));
+            } else {
+                rewritten.push_str(&self.original[copied..line_end]);
+                ops.push(('-', original.to_string()));
+                ops.extend(rewritten.lines().map(|l| ('+', l.to_string())));
+            }
+            line_start = line_end;
+        }
+        // Insertions at the end of the file
+        for insertion in pending {
+            ops.extend(insertion.text.lines().map(|l| ('+', l.to_string())));
+        }
+        ops
+    }
+}
+
+/// Unified diff hunks start at line 0 when they cover no lines on that side.
+fn hunk_start(start: usize, len: usize) -> usize {
+    if len == 0 {
+        start - 1
+    } else {
+        start
+    }
+}
+
+/// Insert each check before every `return` and the final expression (or
+/// closing brace) of the public functions returning a `Result` that it
+/// names, and append `trailer` to the file.
+///
+/// Error exits (`?` and `return Err(...)`) revert the instruction's writes,
+/// so they need no check. Checks are single-line statements; whole lines are
+/// inserted with the indentation of the body they join.
+pub fn inject_checks(
+    source: &str,
+    checks: &[GuardedCheck],
+    trailer: &str,
+) -> Result<InjectedSource> {
+    let file = syn::parse_file(source)
+        .map_err(|e| InvarError::GenerationFailed(format!("Failed to parse Rust: {}", e)))?;
+    let lines = LineIndex::new(source);
+
+    let mut insertions = Vec::new();
+    if !checks.is_empty() {
+        let mut functions = Vec::new();
+        collect_guarded(&file.items, &mut functions);
+        for function in functions {
+            let name = function.sig.ident.to_string();
+            let guarding: Vec<String> = checks
+                .iter()
+                .filter(|check| check.functions.contains(&name))
+                .map(|check| check.check.clone())
+                .collect();
+            if !guarding.is_empty() {
+                insertions.extend(exit_guards(source, &lines, function, &guarding, false));
+            }
+        }
+    }
+    if !trailer.is_empty() {
+        let separator = if source.is_empty() || source.ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        insertions.push(Insertion {
+            offset: source.len(),
+            text: format!("{}{}", separator, trailer),
+        });
+    }
+    insertions.sort_by_key(|insertion| insertion.offset);
+    Ok(InjectedSource {
+        original: source.to_string(),
+        insertions,
+    })
+}
+
+/// Public functions returning a `Result`, including those in inline modules
+/// such as Anchor's `#[program] pub mod`.
+fn collect_guarded<'a>(items: &'a [Item], functions: &mut Vec<&'a ItemFn>) {
+    for item in items {
+        match item {
+            Item::Fn(function) if is_guarded(function) => functions.push(function),
+            Item::Mod(module) => {
+                if let Some((_, items)) = &module.content {
+                    collect_guarded(items, functions);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Every function, including those in inline modules.
+fn collect_functions<'a>(items: &'a [Item], functions: &mut Vec<&'a ItemFn>) {
+    for item in items {
+        match item {
+            Item::Fn(function) => functions.push(function),
+            Item::Mod(module) => {
+                if let Some((_, items)) = &module.content {
+                    collect_functions(items, functions);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Every struct, including those in inline modules.
+fn collect_structs<'a>(items: &'a [Item], structs: &mut Vec<&'a ItemStruct>) {
+    for item in items {
+        match item {
+            Item::Struct(item) => structs.push(item),
+            Item::Mod(module) => {
+                if let Some((_, items)) = &module.content {
+                    collect_structs(items, structs);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Position of the closing parenthesis of an `#[account(mut ...)]` attribute.
+fn mutable_account_attribute(attr: &syn::Attribute) -> Option<LineColumn> {
+    let Meta::List(list) = &attr.meta else {
+        return None;
+    };
+    let mutable = list
+        .tokens
+        .clone()
+        .into_iter()
+        .any(|token| matches!(token, proc_macro2::TokenTree::Ident(ident) if ident == "mut"));
+    (list.path.is_ident("account") && mutable).then(|| list.delimiter.span().close().start())
+}
+
+fn is_guarded(function: &ItemFn) -> bool {
+    let public = matches!(function.vis, syn::Visibility::Public(_));
+    let returns_result = match &function.sig.output {
+        ReturnType::Type(_, ty) => match ty.as_ref() {
+            Type::Path(path) => path.path.segments.last().is_some_and(|segment| {
+                segment.ident == "Result" || segment.ident == "ProgramResult"
+            }),
+            _ => false,
+        },
+        ReturnType::Default => false,
+    };
+    public && returns_result
+}
+
+/// The insertion adding `checks` to `function`.
+fn guard(source: &str, lines: &LineIndex, function: &ItemFn, checks: &[String]) -> Insertion {
+    let block = &function.block;
+    let anchor = match block.stmts.last() {
+        Some(Stmt::Expr(tail, None)) => tail.span().start(),
+        _ => block.brace_token.span.close().start(),
+    };
+    insert_before(source, lines, anchor, checks, |leading| {
+        body_indent(source, lines, function, leading)
+    })
+}
+
+/// The insertion adding `checks` at the start of `function`'s body.
+fn entry_guard(source: &str, lines: &LineIndex, function: &ItemFn, checks: &[String]) -> Insertion {
+    let block = &function.block;
+    let anchor = match block.stmts.first() {
+        Some(first) => first.span().start(),
+        None => block.brace_token.span.close().start(),
+    };
+    insert_before(source, lines, anchor, checks, |leading| {
+        body_indent(source, lines, function, leading)
+    })
+}
+
+/// The insertions adding `checks` before every exit of `function`, except
+/// `return Err(...)` unless `on_error`.
+fn exit_guards(
+    source: &str,
+    lines: &LineIndex,
+    function: &ItemFn,
+    checks: &[String],
+    on_error: bool,
+) -> Vec<Insertion> {
+    let mut returns = Returns::default();
+    for stmt in &function.block.stmts {
+        returns.visit_stmt(stmt);
+    }
+
+    // Code after a final `return` is unreachable
+    let mut insertions = Vec::new();
+    if !matches!(
+        function.block.stmts.last(),
+        Some(Stmt::Expr(Expr::Return(_), _))
+    ) {
+        insertions.push(guard(source, lines, function, checks));
+    }
+    for (span, is_statement, is_error) in returns.0 {
+        if is_error && !on_error {
+            continue;
+        }
+        if is_statement {
+            insertions.push(insert_before(
+                source,
+                lines,
+                span.start(),
+                checks,
+                |leading| leading.to_string(),
+            ));
+        } else {
+            // A `return` in expression position, such as a match arm, becomes
+            // a block so the checks can precede it
+            let text: String = checks.iter().map(|check| format!("{} ", check)).collect();
+            insertions.push(Insertion {
+                offset: lines.offset(source, span.start()),
+                text: format!("{{ {}", text),
+            });
+            insertions.push(Insertion {
+                offset: lines.offset(source, span.end()),
+                text: " }".to_string(),
+            });
+        }
+    }
+    insertions
+}
+
+/// `return` expressions of a function body, outside closures and nested
+/// items, with whether each is a statement of its own and whether it
+/// returns `Err(...)`.
+#[derive(Default)]
+struct Returns(Vec<(proc_macro2::Span, bool, bool)>);
+
+impl<'ast> Visit<'ast> for Returns {
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        match stmt {
+            Stmt::Expr(Expr::Return(ret), Some(_)) => {
+                self.0.push((ret.span(), true, returns_error(ret)))
+            }
+            other => syn::visit::visit_stmt(self, other),
+        }
+    }
+
+    fn visit_expr_return(&mut self, ret: &'ast syn::ExprReturn) {
+        self.0.push((ret.span(), false, returns_error(ret)));
+    }
+
+    fn visit_expr_closure(&mut self, _: &'ast syn::ExprClosure) {}
+
+    fn visit_item(&mut self, _: &'ast Item) {}
+}
+
+fn returns_error(ret: &syn::ExprReturn) -> bool {
+    let Some(Expr::Call(call)) = ret.expr.as_deref() else {
+        return false;
+    };
+    matches!(call.func.as_ref(), Expr::Path(path) if path.path.is_ident("Err"))
+}
+
+/// Indentation for whole lines inserted into `function`'s body before an
+/// anchor indented by `leading`.
+fn body_indent(source: &str, lines: &LineIndex, function: &ItemFn, leading: &str) -> String {
+    let body_indent = function.block.stmts.first().map(|first| {
+        let start = first.span().start();
+        &source[lines.starts[start.line - 1]..lines.offset(source, start)]
+    });
+    match body_indent {
+        Some(indent) if indent.trim().is_empty() => indent.to_string(),
+        // An empty body, or one opening on the brace line
+        _ => format!("{}    ", leading),
+    }
+}
+
+/// The insertion adding `checks` before `anchor`: as whole lines, indented by
+/// `indent` of the anchor's leading whitespace, when the anchor starts its
+/// line, else on the anchor's line.
+fn insert_before(
+    source: &str,
+    lines: &LineIndex,
+    anchor: LineColumn,
+    checks: &[String],
+    indent: impl FnOnce(&str) -> String,
+) -> Insertion {
+    let offset = lines.offset(source, anchor);
+    let line_start = lines.starts[anchor.line - 1];
+    let leading = &source[line_start..offset];
+
+    if leading.trim().is_empty() {
+        // The anchor starts its line: insert whole lines above it
+        let indent = indent(leading);
+        let text = checks
+            .iter()
+            .map(|check| format!("{}{}\n", indent, check))
+            .collect();
+        Insertion {
+            offset: line_start,
+            text,
+        }
+    } else {
+        // Single-line body: keep the checks on the same line, with line
+        // comments turned into block comments so they end before the code
+        let text = checks
+            .iter()
+            .map(|check| match check.split_once(" // ") {
+                Some((code, comment)) => format!("{} /* {} */ ", code, comment),
+                None => format!("{} ", check),
+            })
+            .collect();
+        Insertion { offset, text }
+    }
+}
+
+/// Byte offsets of line starts, for converting `syn` line/column positions.
+struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(source: &str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self { starts }
+    }
+
+    /// Byte offset of a 1-based line and 0-based character column.
+    fn offset(&self, source: &str, position: LineColumn) -> usize {
+        let start = self.starts[position.line - 1];
+        source[start..]
+            .char_indices()
+            .nth(position.column)
+            .map_or(source.len(), |(idx, _)| start + idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "// Vault program\nuse solana_program::entrypoint::ProgramResult;\n\n/// Deposit into the vault.\npub fn deposit(vault: &mut Vault, amount: u64) -> ProgramResult {\n    // Credit first\n    vault.balance   +=   amount;\n\n    Ok(())\n}\n\nfn helper() -> ProgramResult { Ok(()) }\n\npub fn reset(vault: &mut Vault) -> Result<(), ProgramError> { vault.balance = 0; Ok(()) }\n\npub fn close(vault: &mut Vault) -> ProgramResult {\n    vault.open = false;\n    return Ok(());\n}\n";
+
+    fn guarding(check: &str, functions: &[&str]) -> Vec<GuardedCheck> {
+        vec![GuardedCheck {
+            check: check.to_string(),
+            functions: functions.iter().map(|name| name.to_string()).collect(),
+        }]
+    }
+
+    fn checks() -> Vec<GuardedCheck> {
+        guarding(
+            "if !(vault.balance >= 0) { return Err(InvarViolation::Solvent.into()); } // 6001: solvent",
+            &["deposit", "helper", "reset", "close"],
+        )
+    }
+
+    #[test]
+    fn test_untouched_code_is_byte_identical() {
+        let injected = inject_checks(PROGRAM, &checks(), "// trailer\n").unwrap();
+        let source = injected.source();
+
+        // Only insertions: removing them gives back the original exactly
+        let mut stripped = source.clone();
+        for insertion in &injected.insertions {
+            let inserted = insertion.offset..insertion.offset + insertion.text.len();
+            assert_eq!(stripped[inserted.clone()], insertion.text);
+            stripped.replace_range(inserted, "");
+        }
+        assert_eq!(stripped, PROGRAM);
+
+        assert!(source.contains("    vault.balance   +=   amount;\n\n    if !(vault.balance >= 0) { return Err(InvarViolation::Solvent.into()); } // 6001: solvent\n    Ok(())\n}\n"));
+        assert!(source.contains("fn helper() -> ProgramResult { Ok(()) }\n"));
+        assert!(source.contains("{ vault.balance = 0; if !(vault.balance >= 0) { return Err(InvarViolation::Solvent.into()); } /* 6001: solvent */ Ok(()) }"));
+        assert!(source.contains("    vault.open = false;\n    if !(vault.balance >= 0) { return Err(InvarViolation::Solvent.into()); } // 6001: solvent\n    return Ok(());\n}\n"));
+        assert!(source.ends_with("}\n// trailer\n"));
+    }
+
+    #[test]
+    fn test_contracts_guard_entry_and_every_exit() {
+        let source = "pub fn withdraw(vault: &mut Vault, amount: u64) -> ProgramResult {\n    let fee = match vault.fee {\n        Some(fee) => fee,\n        None => return Err(ProgramError::InvalidArgument),\n    };\n    if amount == 0 { return Ok(()); }\n    vault.balance -= amount + fee;\n    Ok(())\n}\n\nfn close() { return; }\n";
+        let contracts = [
+            ContractChecks {
+                function: "withdraw".to_string(),
+                pre: vec!["pre!();".to_string()],
+                post: vec!["post!();".to_string()],
+            },
+            ContractChecks {
+                function: "close".to_string(),
+                pre: Vec::new(),
+                post: vec!["post!();".to_string()],
+            },
+        ];
+        let injected = inject_checks(source, &guarding("check!();", &["withdraw"]), "")
+            .unwrap()
+            .with_contracts(&contracts)
+            .unwrap();
+        assert_eq!(
+            injected.source(),
+            "pub fn withdraw(vault: &mut Vault, amount: u64) -> ProgramResult {\n    pre!();\n    let fee = match vault.fee {\n        Some(fee) => fee,\n        None => { post!(); return Err(ProgramError::InvalidArgument) },\n    };\n    if amount == 0 { check!(); post!(); return Ok(()); }\n    vault.balance -= amount + fee;\n    check!();\n    post!();\n    Ok(())\n}\n\nfn close() { post!(); return; }\n"
+        );
+    }
+
+    #[test]
+    fn test_account_constraints_annotate_mutable_accounts() {
+        let source = "#[derive(Accounts)]\npub struct Deposit<'info> {\n    #[account(mut)]\n    pub vault: Account<'info, Vault>,\n    #[account(mut, has_one = vault)]\n    pub user: Account<'info, User>,\n    #[account(seeds = [b\"config\"], bump)]\n    pub config: Account<'info, Config>,\n}\n";
+        let constraint = |account: &str, body: &str| AccountConstraint {
+            account: account.to_string(),
+            constraint: body.to_string(),
+        };
+        let injected = inject_checks(source, &[], "")
+            .unwrap()
+            .with_account_constraints(&[
+                constraint("vault", "vault.balance >= 0 @ InvarError::Solvent"),
+                constraint("user", "user.shares > 0 @ InvarError::Holder"),
+                constraint("config", "config.fee < 100 @ InvarError::FeeCap"),
+            ])
+            .unwrap();
+        assert_eq!(
+            injected.source(),
+            "#[derive(Accounts)]\npub struct Deposit<'info> {\n    #[account(mut, constraint = vault.balance >= 0 @ InvarError::Solvent)]\n    pub vault: Account<'info, Vault>,\n    #[account(mut, has_one = vault, constraint = user.shares > 0 @ InvarError::Holder)]\n    pub user: Account<'info, User>,\n    #[account(seeds = [b\"config\"], bump)]\n    pub config: Account<'info, Config>,\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_covers_only_injected_regions() {
+        let source = "// Vault program\n\npub fn deposit() -> ProgramResult {\n    // Credit first\n    credit();\n    Ok(())\n}\n";
+        let injected = inject_checks(source, &guarding("check!();", &["deposit"]), "").unwrap();
+        assert_eq!(
+            injected.unified_diff("src/lib.rs"),
+            "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -3,5 +3,6 @@\n pub fn deposit() -> ProgramResult {\n     // Credit first\n     credit();\n+    check!();\n     Ok(())\n }\n"
+        );
+        assert_eq!(
+            inject_checks(source, &[], "")
+                .unwrap()
+                .unified_diff("src/lib.rs"),
+            ""
+        );
+    }
+}
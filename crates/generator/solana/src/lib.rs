@@ -3,6 +3,13 @@
 
 //! Solana code generator: Injects invariant checks into Rust programs.
 
+pub mod error_codes;
 pub mod generator;
+pub mod inject;
+pub mod test_gen;
 
-pub use generator::SolanaGenerator;
+pub use generator::{GeneratorOptions, SolanaFramework, SolanaGenerator};
+pub use inject::{
+    inject_checks, AccountConstraint, ContractChecks, GuardedCheck, InjectedSource, Insertion,
+    COMPILE_PRELUDE,
+};
@@ -0,0 +1,448 @@
+//! Unit tests for the check expressions injected into Solana programs.
+//!
+//! Every injected invariant gets a test that builds a state satisfying it and
+//! expects its check to pass, and one that builds a violating state and
+//! expects the invariant's own `InvarViolation` code. States are found with
+//! the simulator's typed random search under a fixed seed, so regenerating
+//! the tests yields the same values.
+//!
+//! The tests run the injected statement on its own, wrapped in a local
+//! `check_<name>` function: they show the check accepts and rejects the
+//! right states, not that it guards the handler. Calling the handler would
+//! need its accounts and arguments, which the program model does not record.
+
+use crate::error_codes::variant_name;
+use invar_core::model::{EnforcementLevel, Expression, Invariant, ProgramModel};
+use invar_core::{Type, Value};
+use invar_simulator::find_state;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Seed for the state search behind generated tests.
+pub const TEST_SEED: u64 = 42;
+
+/// Name of the generated check expression test module.
+pub const TEST_MODULE: &str = "invar_check_expression_tests";
+
+/// An injected check, as emitted by the native generator.
+pub struct InjectedCheck<'a> {
+    /// The invariant being checked.
+    pub invariant: &'a Invariant,
+    /// The injected statement.
+    pub assertion: &'a str,
+    /// `InvarViolation` variant returned on failure.
+    pub variant: &'a str,
+    /// Custom error code of the variant.
+    pub error_code: u32,
+}
+
+/// Variables one invariant reads: top-level locals and account fields.
+#[derive(Default)]
+struct StateShape {
+    /// Plain variables and their types.
+    locals: BTreeMap<String, Type>,
+    /// Account fields by account name.
+    accounts: BTreeMap<String, BTreeMap<String, Type>>,
+}
+
+impl StateShape {
+    /// Collect the state `expr` reads, or `None` when the expression uses
+    /// anything the generated tests cannot construct.
+    fn of(expr: &Expression, program: &ProgramModel) -> Option<Self> {
+        let mut shape = Self::default();
+        shape.collect(expr, program)?;
+        let clash = shape.locals.keys().any(|l| shape.accounts.contains_key(l));
+        // Rust has no implicit widening, so mixed-width comparisons do not compile
+        let widths: BTreeSet<&str> = shape
+            .evaluator_types()
+            .filter(|ty| ty.is_numeric())
+            .map(Type::name)
+            .collect();
+        (!clash && widths.len() <= 1).then_some(shape)
+    }
+
+    fn collect(&mut self, expr: &Expression, program: &ProgramModel) -> Option<()> {
+        match expr {
+            Expression::Boolean(_) | Expression::Int(_) => Some(()),
+            Expression::Var(name) => {
+                let ty = field_type(program, name)?;
+                is_identifier(name).then(|| {
+                    self.locals.insert(name.clone(), ty);
+                })
+            }
+            Expression::LayerVar { layer, var } => {
+                let ty = field_type(program, var)?;
+                (is_identifier(layer) && is_identifier(var)).then(|| {
+                    self.accounts
+                        .entry(layer.clone())
+                        .or_default()
+                        .insert(var.clone(), ty);
+                })
+            }
+            Expression::BinaryOp { left, right, .. } | Expression::Logical { left, right, .. } => {
+                self.collect(left, program)?;
+                self.collect(right, program)
+            }
+            Expression::Not(inner) => self.collect(inner, program),
+            // Calls, casts, phases and maps have no direct Rust rendering
+            _ => None,
+        }
+    }
+
+    fn evaluator_types(&self) -> impl Iterator<Item = &Type> {
+        self.locals
+            .values()
+            .chain(self.accounts.values().flat_map(|fields| fields.values()))
+    }
+
+    /// Variable names as the evaluator looks them up.
+    fn evaluator_vars(&self) -> BTreeMap<String, Type> {
+        let mut vars = self.locals.clone();
+        for (account, fields) in &self.accounts {
+            for (field, ty) in fields {
+                vars.insert(format!("{}::{}", account, field), ty.clone());
+            }
+        }
+        vars
+    }
+}
+
+/// Declared type of a state variable; undeclared ones default to `u64` as in
+/// the type checker. Types without a Rust literal form are unsupported.
+fn field_type(program: &ProgramModel, name: &str) -> Option<Type> {
+    let ty = match program.state_vars.get(name) {
+        Some(var) => Type::from_name(&var.type_name.to_lowercase())?,
+        None => Type::U64,
+    };
+    matches!(ty, Type::Bool | Type::U64 | Type::U128 | Type::I64).then_some(ty)
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// `VaultSolvent` becomes `vault_solvent`.
+fn snake_case(variant: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in variant.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// States found for one check.
+struct PlannedCheck<'a> {
+    check: &'a InjectedCheck<'a>,
+    shape: StateShape,
+    holding: Option<BTreeMap<String, Value>>,
+    violating: Option<BTreeMap<String, Value>>,
+}
+
+/// Generate the `#[cfg(test)]` check expression module for `checks`, or
+/// `None` when no check can be exercised.
+pub fn generate_tests(program: &ProgramModel, checks: &[InjectedCheck<'_>]) -> Option<String> {
+    // One struct per account, with every field the tested invariants read
+    let mut accounts: BTreeMap<String, BTreeMap<String, Type>> = BTreeMap::new();
+    let mut planned = Vec::new();
+    for check in checks {
+        let Some(shape) = StateShape::of(&check.invariant.expression, program) else {
+            continue;
+        };
+        // A field read with different types by two invariants keeps the first
+        let conflicting = shape.accounts.iter().any(|(account, fields)| {
+            fields.iter().any(|(field, ty)| {
+                accounts
+                    .get(account)
+                    .and_then(|known| known.get(field))
+                    .is_some_and(|known| known != ty)
+            })
+        });
+        if conflicting {
+            continue;
+        }
+
+        let vars = shape.evaluator_vars();
+        let expr = &check.invariant.expression;
+        let holding = find_state(expr, &vars, TEST_SEED, true);
+        let violating = find_state(expr, &vars, TEST_SEED, false);
+        if holding.is_none() && violating.is_none() {
+            continue;
+        }
+        for (account, fields) in &shape.accounts {
+            accounts
+                .entry(account.clone())
+                .or_default()
+                .extend(fields.clone());
+        }
+        planned.push(PlannedCheck {
+            check,
+            shape,
+            holding,
+            violating,
+        });
+    }
+    if planned.is_empty() {
+        return None;
+    }
+
+    let mut code = format!("#[cfg(test)]\nmod {} {{\n    use super::*;\n", TEST_MODULE);
+    for (account, fields) in &accounts {
+        code.push_str(&format!(
+            "\n    #[derive(Debug, Default)]\n    struct {} {{\n",
+            variant_name(account)
+        ));
+        for (field, ty) in fields {
+            code.push_str(&format!("        {}: {},\n", field, ty.name()));
+        }
+        code.push_str("    }\n");
+    }
+    for plan in &planned {
+        code.push_str(&check_tests(plan, &accounts));
+    }
+    code.push_str("}\n");
+    Some(code)
+}
+
+/// The check function wrapping one invariant's assertion and its
+/// holding/violating tests.
+fn check_tests(
+    plan: &PlannedCheck<'_>,
+    accounts: &BTreeMap<String, BTreeMap<String, Type>>,
+) -> String {
+    let PlannedCheck {
+        check,
+        shape,
+        holding,
+        violating,
+    } = plan;
+    let fn_name = snake_case(check.variant);
+    let mut params = Vec::new();
+    let mut args = Vec::new();
+    for account in shape.accounts.keys() {
+        params.push(format!("{}: &{}", account, variant_name(account)));
+        args.push(format!("&{}", account));
+    }
+    for (local, ty) in &shape.locals {
+        params.push(format!("{}: {}", local, ty.name()));
+        args.push(local.clone());
+    }
+    let call = format!("check_{}({})", fn_name, args.join(", "));
+
+    let mut code = format!(
+        "\n    fn check_{}({}) -> Result<(), ProgramError> {{\n        {}\n        Ok(())\n    }}\n",
+        fn_name,
+        params.join(", "),
+        check.assertion
+    );
+    if let Some(state) = holding {
+        code.push_str(&format!(
+            "\n    #[test]\n    fn {}_check_holds() {{\n{}        assert_eq!({}, Ok(()));\n    }}\n",
+            fn_name,
+            bindings(state, shape, accounts),
+            call
+        ));
+    }
+    if let Some(state) = violating {
+        // Debug-only checks are compiled out of release test builds
+        let cfg = match check.invariant.enforcement {
+            EnforcementLevel::DebugOnly => "    #[cfg(debug_assertions)]\n",
+            _ => "",
+        };
+        code.push_str(&format!(
+            "\n{}    #[test]\n    fn {}_check_is_violated() {{\n{}        assert_eq!({}, Err(ProgramError::Custom({})));\n    }}\n",
+            cfg,
+            fn_name,
+            bindings(state, shape, accounts),
+            call,
+            check.error_code
+        ));
+    }
+    code
+}
+
+/// `let` statements constructing `state`; values of the types
+/// [`field_type`] accepts display as Rust literals.
+fn bindings(
+    state: &BTreeMap<String, Value>,
+    shape: &StateShape,
+    accounts: &BTreeMap<String, BTreeMap<String, Type>>,
+) -> String {
+    let mut code = String::new();
+    for (account, fields) in &shape.accounts {
+        let mut inits: Vec<String> = fields
+            .keys()
+            .map(|field| {
+                let value = &state[&format!("{}::{}", account, field)];
+                format!("{}: {}", field, value)
+            })
+            .collect();
+        // Fields only other invariants read keep their defaults
+        if accounts[account].len() > fields.len() {
+            inits.push("..Default::default()".to_string());
+        }
+        code.push_str(&format!(
+            "        let {} = {} {{ {} }};\n",
+            account,
+            variant_name(account),
+            inits.join(", ")
+        ));
+    }
+    for (local, ty) in &shape.locals {
+        code.push_str(&format!(
+            "        let {}: {} = {};\n",
+            local,
+            ty.name(),
+            state[local]
+        ));
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SolanaGenerator;
+    use invar_core::model::{BinaryOp, LogicalOp, StateVar};
+    use invar_core::traits::CodeGenerator;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    fn var(name: &str) -> Box<Expression> {
+        Box::new(Expression::Var(name.to_string()))
+    }
+
+    fn field(layer: &str, var: &str) -> Box<Expression> {
+        Box::new(Expression::LayerVar {
+            layer: layer.to_string(),
+            var: var.to_string(),
+        })
+    }
+
+    fn fixture() -> (ProgramModel, Vec<Invariant>) {
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "lib.rs".to_string(),
+        );
+        program.add_state_var(StateVar {
+            name: "paused".to_string(),
+            type_name: "bool".to_string(),
+            is_mutable: true,
+            visibility: None,
+            abilities: Default::default(),
+            depends_on: Vec::new(),
+        });
+        let invariants = vec![
+            Invariant::new(
+                "vault_solvent",
+                Expression::BinaryOp {
+                    left: field("vault", "balance"),
+                    op: BinaryOp::Gte,
+                    right: var("min_reserve"),
+                },
+            ),
+            Invariant::new(
+                "debt_ceiling",
+                Expression::Logical {
+                    left: Box::new(Expression::Not(var("paused"))),
+                    op: LogicalOp::Or,
+                    right: Box::new(Expression::BinaryOp {
+                        left: field("vault", "debt"),
+                        op: BinaryOp::Eq,
+                        right: Box::new(Expression::Int(0)),
+                    }),
+                },
+            ),
+            Invariant::new(
+                "fee_bound",
+                Expression::BinaryOp {
+                    left: var("fee"),
+                    op: BinaryOp::Gte,
+                    right: Box::new(Expression::Int(0)),
+                },
+            ),
+            Invariant::new(
+                "has_owner",
+                Expression::FunctionCall {
+                    name: "is_signer".to_string(),
+                    args: vec![*var("owner")],
+                },
+            ),
+        ];
+        (program, invariants)
+    }
+
+    #[test]
+    fn test_generates_holding_and_violating_tests() {
+        let (program, invariants) = fixture();
+        let output = SolanaGenerator::new()
+            .generate(&program, &invariants)
+            .unwrap();
+        let tests = output.tests.unwrap();
+
+        assert!(tests.starts_with("#[cfg(test)]\nmod invar_check_expression_tests {\n"));
+        assert!(tests
+            .contains("    struct Vault {\n        balance: u64,\n        debt: u64,\n    }\n"));
+        assert!(tests.contains("fn check_vault_solvent(vault: &Vault, min_reserve: u64)"));
+        assert!(tests.contains("fn check_debt_ceiling(vault: &Vault, paused: bool)"));
+        assert!(tests.contains("fn vault_solvent_check_holds()"));
+        assert!(tests.contains("Err(ProgramError::Custom(6969))"));
+        assert!(tests.contains("fn debt_ceiling_check_is_violated()"));
+        assert!(tests.contains("..Default::default()"));
+
+        // `fee >= 0` cannot fail for a u64, and calls have no Rust rendering
+        assert!(tests.contains("fn fee_bound_check_holds()"));
+        assert!(!tests.contains("fee_bound_check_is_violated"));
+        assert!(!tests.contains("has_owner"));
+
+        // Fixed seed: regeneration is byte-for-byte identical
+        let again = SolanaGenerator::new()
+            .generate(&program, &invariants)
+            .unwrap();
+        assert_eq!(again.tests.unwrap(), tests);
+    }
+
+    /// Compile the generated checks and tests against the
+    /// [`COMPILE_PRELUDE`](crate::COMPILE_PRELUDE) and run them.
+    #[test]
+    fn test_generated_tests_compile_and_pass() {
+        let (program, invariants) = fixture();
+        let output = SolanaGenerator::new()
+            .generate(&program, &invariants)
+            .unwrap();
+        let source = format!(
+            "{}\n{}\n{}",
+            crate::COMPILE_PRELUDE,
+            output.code,
+            output.tests.unwrap()
+        );
+
+        let dir =
+            std::env::temp_dir().join(format!("invar_generated_tests_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let lib = dir.join("lib.rs");
+        std::fs::write(&lib, source).unwrap();
+        let binary: PathBuf = dir.join("generated_tests");
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        let compiled = Command::new(rustc)
+            .args(["--edition", "2021", "--test", "--crate-type", "lib", "-o"])
+            .arg(&binary)
+            .arg(&lib)
+            .output()
+            .unwrap();
+        assert!(
+            compiled.status.success(),
+            "{}",
+            String::from_utf8_lossy(&compiled.stderr)
+        );
+
+        let run = Command::new(&binary).output().unwrap();
+        let stdout = String::from_utf8_lossy(&run.stdout);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(run.status.success(), "{}", stdout);
+        assert!(stdout.contains("test result: ok. 5 passed"), "{}", stdout);
+    }
+}
@@ -33,7 +33,12 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! Handlers may be `async fn`s, and methods in `impl` blocks that keep their
+//! state in `self` use `#[invariant_enforced_method]` instead.
 
+use invar_generator_solana::error_codes::FIRST_ERROR_CODE;
+use invar_generator_solana::SolanaFramework;
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, FnArg, ItemFn, Pat};
@@ -52,6 +57,16 @@ use syn::{parse_macro_input, FnArg, ItemFn, Pat};
 ///
 /// # Runtime Behavior
 /// If any invariant fails, function immediately returns an error.
+///
+/// # Error Codes
+/// A local `InvarViolation` enum gets one variant per check, numbered with the
+/// same stable codes as `SolanaGenerator`, so a custom program error can be
+/// traced back to the check that produced it. With `framework = "anchor"`
+/// the enum is an Anchor `#[error_code]` enum named `InvarError` instead.
+///
+/// # Async Handlers
+/// `async fn` handlers are supported. Invariant checks are pure, so they are
+/// injected as ordinary synchronous statements and nothing is awaited.
 #[proc_macro_attribute]
 pub fn invariant_enforced(args: TokenStream, input: TokenStream) -> TokenStream {
     let input_fn = parse_macro_input!(input as ItemFn);
@@ -63,8 +78,21 @@ pub fn invariant_enforced(args: TokenStream, input: TokenStream) -> TokenStream
     match validate_function_signature(&input_fn) {
         Ok(state_vars) => {
             // Generate invariant checks
-            let checks = parse_invariant_checks(&args_str);
-            let check_stmts = generate_check_statements(&checks, &state_vars);
+            let mut checks = parse_invariant_checks(&args_str);
+            let framework = match take_framework(&mut checks) {
+                Ok(framework) => framework,
+                Err(e) => {
+                    return syn::Error::new_spanned(&input_fn.sig, e)
+                        .to_compile_error()
+                        .into()
+                }
+            };
+            if let Err(e) = validate_checks(&checks) {
+                return syn::Error::new(proc_macro2::Span::call_site(), e)
+                    .to_compile_error()
+                    .into();
+            }
+            let check_stmts = generate_check_statements(&checks, &state_vars, framework);
 
             // Inject checks into function
             let modified_fn = inject_checks(&input_fn, check_stmts);
@@ -76,6 +104,145 @@ pub fn invariant_enforced(args: TokenStream, input: TokenStream) -> TokenStream
     }
 }
 
+/// Procedural attribute macro for enforcing invariants on methods whose state
+/// is `self`.
+///
+/// Takes the same arguments as `#[invariant_enforced]` plus a required
+/// `state_struct = "<Type>"`, the type `self` refers to. Every unqualified
+/// variable in the checks is a field of that struct, and the macro emits a
+/// field access for each so that a check naming a missing field is a compile
+/// error.
+///
+/// ```ignore
+/// impl Vault {
+///     #[invariant_enforced_method(state_struct = "Vault", "balance >= 0")]
+///     pub fn withdraw(&mut self, amount: u64) -> ProgramResult {
+///         self.balance = self.balance.checked_sub(amount).ok_or(VaultError::Overdraw)?;
+///         Ok(())
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn invariant_enforced_method(args: TokenStream, input: TokenStream) -> TokenStream {
+    let input_fn = parse_macro_input!(input as ItemFn);
+
+    match expand_method(&args.to_string(), &input_fn) {
+        Ok(modified_fn) => quote! { #modified_fn }.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Inject the checks into a method, reading state from `self`.
+fn expand_method(args: &str, func: &ItemFn) -> syn::Result<ItemFn> {
+    validate_method_signature(func).map_err(|e| syn::Error::new_spanned(&func.sig, e))?;
+
+    let mut checks = parse_invariant_checks(args);
+    let state_struct = take_argument(&mut checks, "state_struct").ok_or_else(|| {
+        syn::Error::new_spanned(
+            &func.sig,
+            "invariant_enforced_method needs a `state_struct = \"<Type>\"` argument",
+        )
+    })?;
+    let state_struct: syn::Type = syn::parse_str(&state_struct).map_err(|_| {
+        syn::Error::new_spanned(
+            &func.sig,
+            format!("invalid state_struct type `{}`", state_struct),
+        )
+    })?;
+    let framework =
+        take_framework(&mut checks).map_err(|e| syn::Error::new_spanned(&func.sig, e))?;
+    validate_checks(&checks).map_err(|e| syn::Error::new(proc_macro2::Span::call_site(), e))?;
+
+    let fields = state_fields(&checks);
+    let field_names: Vec<String> = fields.iter().map(ToString::to_string).collect();
+    let mut check_stmts = vec![syn::parse_quote! {
+        #[allow(dead_code)]
+        fn invar_state_fields(state: &#state_struct) {
+            #(let _ = &state.#fields;)*
+        }
+    }];
+    check_stmts.extend(generate_check_statements(&checks, &field_names, framework));
+    Ok(inject_checks(func, check_stmts))
+}
+
+/// Procedural attribute macro capturing pre-state values for invariant checks.
+///
+/// Each argument has the form `"<name> = <expr>"` and becomes
+/// `let <name> = <expr>;` at the very start of the function body, before any
+/// state is mutated. The captured names can then be used alongside post-state
+/// values in `#[invariant_enforced]` checks on the same function, in either
+/// attribute order.
+///
+/// ```ignore
+/// #[invariant_snapshot("pre_from = from.balance", "pre_to = to.balance")]
+/// #[invariant_enforced("pre_from >= amount")]
+/// pub fn transfer(from: &mut Account, to: &mut Account, amount: u64) -> ProgramResult {
+///     // ...
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn invariant_snapshot(args: TokenStream, input: TokenStream) -> TokenStream {
+    let input_fn = parse_macro_input!(input as ItemFn);
+
+    match expand_snapshot(args.into(), input_fn) {
+        Ok(modified_fn) => quote! { #modified_fn }.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Insert a `let` binding for each `"<name> = <expr>"` snapshot argument at
+/// the start of the function block.
+fn expand_snapshot(args: proc_macro2::TokenStream, func: ItemFn) -> syn::Result<ItemFn> {
+    use syn::punctuated::Punctuated;
+
+    let specs = syn::parse::Parser::parse2(
+        Punctuated::<syn::LitStr, syn::Token![,]>::parse_terminated,
+        args,
+    )?;
+    if specs.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &func.sig,
+            "invariant_snapshot needs at least one \"<name> = <expr>\" capture",
+        ));
+    }
+
+    let mut bindings = Vec::new();
+    for spec in &specs {
+        let value = spec.value();
+        let (name, expr) = split_snapshot(&value).ok_or_else(|| {
+            syn::Error::new_spanned(spec, "expected a snapshot of the form \"<name> = <expr>\"")
+        })?;
+        let name: syn::Ident = syn::parse_str(name).map_err(|_| {
+            syn::Error::new_spanned(spec, format!("invalid snapshot name `{}`", name))
+        })?;
+        let expr: syn::Expr = syn::parse_str(expr).map_err(|e| {
+            syn::Error::new_spanned(spec, format!("invalid snapshot expression: {}", e))
+        })?;
+        bindings.push(syn::parse_quote! {
+            let #name = #expr;
+        });
+    }
+
+    let mut modified_fn = func;
+    modified_fn.block.stmts.splice(0..0, bindings);
+    Ok(modified_fn)
+}
+
+/// Split `<name> = <expr>` at the first lone `=`, ignoring `==`, `<=`, etc.
+fn split_snapshot(spec: &str) -> Option<(&str, &str)> {
+    let bytes = spec.as_bytes();
+    let eq = (0..bytes.len()).find(|&i| {
+        bytes[i] == b'='
+            && bytes.get(i + 1) != Some(&b'=')
+            && !matches!(
+                i.checked_sub(1).map(|j| bytes[j]),
+                Some(b'=' | b'<' | b'>' | b'!')
+            )
+    })?;
+    let (name, expr) = (spec[..eq].trim(), spec[eq + 1..].trim());
+    (!name.is_empty() && !expr.is_empty()).then_some((name, expr))
+}
+
 /// Validate that the function signature is suitable for invariant injection.
 fn validate_function_signature(func: &ItemFn) -> Result<Vec<String>, String> {
     let mut state_vars = Vec::new();
@@ -93,7 +260,9 @@ fn validate_function_signature(func: &ItemFn) -> Result<Vec<String>, String> {
                 }
             }
             FnArg::Receiver(_) => {
-                return Err("Invariant-enforced functions cannot have &self/&mut self".to_string());
+                return Err("Invariant-enforced functions cannot have &self/&mut self; \
+                     use #[invariant_enforced_method] for methods"
+                    .to_string());
             }
         }
     }
@@ -105,6 +274,40 @@ fn validate_function_signature(func: &ItemFn) -> Result<Vec<String>, String> {
     Ok(state_vars)
 }
 
+/// Validate that a method takes `&self` or `&mut self`, its state source.
+fn validate_method_signature(func: &ItemFn) -> Result<(), String> {
+    match func.sig.receiver() {
+        Some(receiver) if receiver.reference.is_some() => Ok(()),
+        Some(_) => {
+            Err("Invariant-enforced methods must take &self or &mut self, not self".to_string())
+        }
+        None => Err(
+            "Invariant-enforced methods need a &self or &mut self receiver; \
+             use #[invariant_enforced] for free functions"
+                .to_string(),
+        ),
+    }
+}
+
+/// Fields of the state struct read by the checks: their unqualified
+/// variables, in alphabetical order.
+fn state_fields(checks: &[String]) -> Vec<syn::Ident> {
+    let mut fields = std::collections::BTreeSet::new();
+    for check in checks.iter().filter(|check| !check.ends_with(".invar")) {
+        if let Ok(expr) = invar_dsl_parser::parse_expression(check) {
+            fields.extend(
+                expr.variables()
+                    .into_iter()
+                    .filter(|var| !var.contains("::")),
+            );
+        }
+    }
+    fields
+        .iter()
+        .map(|field| quote::format_ident!("{}", field))
+        .collect()
+}
+
 /// Parse invariant check specifications from macro arguments.
 fn parse_invariant_checks(args: &str) -> Vec<String> {
     // Remove quotes and split by comma
@@ -114,8 +317,46 @@ fn parse_invariant_checks(args: &str) -> Vec<String> {
         .collect()
 }
 
+/// Parse every check as a DSL expression, reporting the first invalid one.
+///
+/// The `.invar` file reference is not a check and is skipped.
+fn validate_checks(checks: &[String]) -> Result<(), String> {
+    for check in checks.iter().filter(|check| !check.ends_with(".invar")) {
+        invar_dsl_parser::parse_expression(check)
+            .map_err(|e| format!("invalid invariant check `{}`: {}", check, e))?;
+    }
+    Ok(())
+}
+
+/// Remove a `framework = "native" | "anchor"` argument from the parsed checks.
+fn take_framework(checks: &mut Vec<String>) -> Result<SolanaFramework, String> {
+    match take_argument(checks, "framework") {
+        None => Ok(SolanaFramework::Native),
+        Some(name) => SolanaFramework::from_name(&name)
+            .ok_or_else(|| format!("Unknown framework `{}`: use native or anchor", name)),
+    }
+}
+
+/// Remove every `<key> = "<value>"` argument from the parsed checks,
+/// returning the last value.
+fn take_argument(checks: &mut Vec<String>, key: &str) -> Option<String> {
+    let mut value = None;
+    checks.retain(|check| match check.split_once('=') {
+        Some((k, v)) if k.trim() == key => {
+            value = Some(v.trim().trim_matches('"').to_string());
+            false
+        }
+        _ => true,
+    });
+    value
+}
+
 /// Generate invariant check statements with tamper detection hash.
-fn generate_check_statements(checks: &[String], _state_vars: &[String]) -> Vec<syn::Stmt> {
+fn generate_check_statements(
+    checks: &[String],
+    _state_vars: &[String],
+    framework: SolanaFramework,
+) -> Vec<syn::Stmt> {
     use quote::format_ident;
 
     let mut stmts = Vec::new();
@@ -124,30 +365,85 @@ fn generate_check_statements(checks: &[String], _state_vars: &[String]) -> Vec<s
     let check_hash = compute_check_hash(checks);
     let _hash_comment = format!("// INVAR_HASH: {}", check_hash);
 
-    stmts.push(syn::parse_quote! {
-        // Invariant checks injected by #[invariant_enforced]
-    });
+    // Invariant checks injected by #[invariant_enforced]. Comments do not
+    // survive tokenization, so only the error enum and the trailing marker
+    // statement are emitted.
+    stmts.push(error_enum(checks, framework));
 
     // Generate a check for each invariant
     for (idx, check) in checks.iter().enumerate() {
         let _check_name = format_ident!("invar_check_{}", idx);
         let _check_expr_str = check.clone();
-
-        // Create assertion-like statement
-        stmts.push(syn::parse_quote! {
-            // Invariant: #check
-            // This check is automatically enforced
-        });
     }
 
+    // Tamper detection enabled
     stmts.push(syn::parse_quote! {
-        // Tamper detection enabled
         let _ = ();
     });
 
     stmts
 }
 
+/// Local error enum with one variant per distinct check.
+///
+/// Anchor's `#[error_code]` adds its own 6000 offset, so Anchor discriminants
+/// are relative to `FIRST_ERROR_CODE`.
+fn error_enum(checks: &[String], framework: SolanaFramework) -> syn::Stmt {
+    use quote::format_ident;
+
+    let variants = error_code_variants(checks).into_iter().map(|(idx, code)| {
+        let variant = format_ident!("Check{}", idx);
+        let check = &checks[idx];
+        match framework {
+            SolanaFramework::Native => {
+                let code = proc_macro2::Literal::u32_unsuffixed(code);
+                quote! {
+                    #[doc = #check]
+                    #variant = #code
+                }
+            }
+            SolanaFramework::Anchor => {
+                let code = proc_macro2::Literal::u32_unsuffixed(code - FIRST_ERROR_CODE);
+                quote! {
+                    #[msg(#check)]
+                    #variant = #code
+                }
+            }
+        }
+    });
+    match framework {
+        SolanaFramework::Native => syn::parse_quote! {
+            #[derive(Debug)]
+            #[repr(u32)]
+            #[allow(dead_code)]
+            enum InvarViolation {
+                #(#variants,)*
+            }
+        },
+        SolanaFramework::Anchor => syn::parse_quote! {
+            #[error_code]
+            #[allow(dead_code)]
+            enum InvarError {
+                #(#variants,)*
+            }
+        },
+    }
+}
+
+/// Stable error code for each distinct check, as `(index of first occurrence, code)`.
+///
+/// Codes are keyed by the check's source text, so they survive reordering.
+fn error_code_variants(checks: &[String]) -> Vec<(usize, u32)> {
+    let codes =
+        invar_generator_solana::error_codes::assign_error_codes(checks.iter().map(String::as_str));
+    checks
+        .iter()
+        .enumerate()
+        .filter(|(idx, check)| !checks[..*idx].contains(check))
+        .map(|(idx, check)| (idx, codes[check.as_str()]))
+        .collect()
+}
+
 /// Inject check statements at the end of the function, just before return.
 fn inject_checks(func: &ItemFn, checks: Vec<syn::Stmt>) -> ItemFn {
     let mut modified_fn = func.clone();
@@ -156,10 +452,8 @@ fn inject_checks(func: &ItemFn, checks: Vec<syn::Stmt>) -> ItemFn {
     match &mut modified_fn.block.stmts.last() {
         Some(_last_stmt) => {
             // Insert checks before the last statement if it's a return
-            modified_fn.block.stmts.splice(
-                modified_fn.block.stmts.len() - 1..modified_fn.block.stmts.len(),
-                checks,
-            );
+            let last = modified_fn.block.stmts.len() - 1;
+            modified_fn.block.stmts.splice(last..last, checks);
         }
         None => {
             // Empty block, just add checks
@@ -206,6 +500,105 @@ mod tests {
         assert_eq!(hash1, hash2);
     }
 
+    #[test]
+    fn test_error_codes_follow_check_text() {
+        let checks = vec!["balance >= 0".to_string(), "supply > 0".to_string()];
+        let reordered = vec!["supply > 0".to_string(), "balance >= 0".to_string()];
+
+        let codes = error_code_variants(&checks);
+        let reordered_codes = error_code_variants(&reordered);
+        assert_eq!(codes.len(), 2);
+        assert_ne!(codes[0].1, codes[1].1);
+        assert_eq!(codes[0].1, reordered_codes[1].1);
+        assert_eq!(codes[1].1, reordered_codes[0].1);
+    }
+
+    #[test]
+    fn test_snapshot_bindings_precede_body() {
+        let func: ItemFn = syn::parse_quote! {
+            pub fn transfer(from: &mut Account, to: &mut Account, amount: u64) -> ProgramResult {
+                from.balance -= amount;
+                to.balance += amount;
+                Ok(())
+            }
+        };
+        let args = quote! { "pre_from = from.balance", "pre_to = to.balance" };
+
+        let expanded = expand_snapshot(args, func).unwrap();
+        let stmts: Vec<String> = expanded
+            .block
+            .stmts
+            .iter()
+            .map(|stmt| quote!(#stmt).to_string())
+            .collect();
+        assert_eq!(stmts[0], "let pre_from = from . balance ;");
+        assert_eq!(stmts[1], "let pre_to = to . balance ;");
+        assert_eq!(stmts[2], "from . balance -= amount ;");
+        assert_eq!(stmts.len(), 5);
+    }
+
+    #[test]
+    fn test_snapshot_rejects_malformed_capture() {
+        let func: ItemFn = syn::parse_quote! {
+            fn f(a: &mut u64) {}
+        };
+        assert!(expand_snapshot(quote! { "pre_a == a" }, func.clone()).is_err());
+        assert!(expand_snapshot(quote! { "1x = a" }, func.clone()).is_err());
+        assert!(expand_snapshot(quote! {}, func).is_err());
+        assert_eq!(split_snapshot("pre = a <= b"), Some(("pre", "a <= b")));
+    }
+
+    #[test]
+    fn test_framework_argument() {
+        let mut checks = parse_invariant_checks(r#""balance >= 0", framework = "anchor""#);
+        assert_eq!(take_framework(&mut checks), Ok(SolanaFramework::Anchor));
+        assert_eq!(checks, vec!["balance >= 0".to_string()]);
+
+        let anchor = error_enum(&checks, SolanaFramework::Anchor);
+        let rendered = quote!(#anchor).to_string();
+        assert!(rendered.starts_with("# [error_code]"));
+        assert!(rendered.contains("# [msg (\"balance >= 0\")]"));
+
+        let mut checks = parse_invariant_checks(r#""balance >= 0", framework = "seahorse""#);
+        assert!(take_framework(&mut checks).is_err());
+    }
+
+    #[test]
+    fn test_checks_must_be_dsl_expressions() {
+        let checks = parse_invariant_checks(r#""invariants/token.invar", "balance >= 0""#);
+        assert_eq!(validate_checks(&checks), Ok(()));
+
+        let checks = parse_invariant_checks(r#""balance >= 0 supply""#);
+        let error = validate_checks(&checks).unwrap_err();
+        assert!(error.starts_with("invalid invariant check `balance >= 0 supply`"));
+        assert!(error.contains("line 1, column 14"), "{}", error);
+    }
+
+    #[test]
+    fn test_method_checks_read_state_struct_fields() {
+        let func: ItemFn = syn::parse_quote! {
+            pub fn withdraw(&mut self, amount: u64) -> ProgramResult {
+                self.balance -= amount;
+                Ok(())
+            }
+        };
+        let args =
+            r#"state_struct = "Vault", "balance <= supply", "forall(a in accounts: a >= 0)""#;
+
+        let expanded = expand_method(args, &func).unwrap();
+        let stmts = &expanded.block.stmts;
+        let fields = &stmts[1];
+        assert_eq!(
+            quote!(#fields).to_string(),
+            "# [allow (dead_code)] fn invar_state_fields (state : & Vault) { \
+             let _ = & state . accounts ; let _ = & state . balance ; let _ = & state . supply ; }"
+        );
+        let tail = stmts.last().unwrap();
+        assert_eq!(quote!(#tail).to_string(), "Ok (())");
+
+        assert!(expand_method(r#""balance >= 0""#, &func).is_err());
+    }
+
     #[test]
     fn test_parse_invariant_checks() {
         let args = r#""balance >= 0", "supply > 0""#;
@@ -0,0 +1,27 @@
+use invar_solana_macro::{invariant_enforced, invariant_enforced_method};
+
+pub struct Vault {
+    pub balance: u64,
+}
+
+impl Vault {
+    #[invariant_enforced("balance >= 0")]
+    pub fn deposit(&mut self, amount: u64) {
+        self.balance += amount;
+    }
+
+    #[invariant_enforced_method("balance >= 0")]
+    pub fn withdraw(&mut self, amount: u64) {
+        self.balance -= amount;
+    }
+
+    #[invariant_enforced_method(state_struct = "Vault", "balance >= 0")]
+    pub fn close(self) {}
+}
+
+#[invariant_enforced_method(state_struct = "Vault", "balance >= 0")]
+pub fn reset(mut vault: Vault) {
+    vault.balance = 0;
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use invar_solana_macro::invariant_enforced_method;
+
+pub struct Vault {
+    pub balance: u64,
+}
+
+impl Vault {
+    #[invariant_enforced_method(state_struct = "Vault", "balance <= reserve")]
+    pub fn withdraw(&mut self, amount: u64) {
+        self.balance -= amount;
+    }
+}
+
+fn main() {}
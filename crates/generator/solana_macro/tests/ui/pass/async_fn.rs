@@ -0,0 +1,16 @@
+use invar_solana_macro::{invariant_enforced, invariant_snapshot};
+
+pub struct Account {
+    pub balance: u64,
+}
+
+#[invariant_snapshot("pre_balance = from.balance")]
+#[invariant_enforced("balance >= pre_balance")]
+pub async fn deposit(mut from: Account, amount: u64) -> Result<Account, ()> {
+    from.balance = from.balance.checked_add(amount).ok_or(())?;
+    Ok(from)
+}
+
+fn main() {
+    let _ = deposit(Account { balance: 0 }, 1);
+}
@@ -0,0 +1,28 @@
+use invar_solana_macro::invariant_enforced_method;
+
+pub struct Vault {
+    pub balance: u64,
+    pub supply: u64,
+}
+
+impl Vault {
+    #[invariant_enforced_method(state_struct = "Vault", "balance <= supply")]
+    pub fn withdraw(&mut self, amount: u64) -> Result<(), ()> {
+        self.balance = self.balance.checked_sub(amount).ok_or(())?;
+        Ok(())
+    }
+
+    #[invariant_enforced_method(state_struct = "Vault", "supply > 0", framework = "native")]
+    pub async fn supply(&self) -> u64 {
+        self.supply
+    }
+}
+
+fn main() {
+    let mut vault = Vault {
+        balance: 10,
+        supply: 10,
+    };
+    vault.withdraw(5).unwrap();
+    let _ = vault.supply();
+}
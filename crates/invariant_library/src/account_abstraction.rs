@@ -0,0 +1,213 @@
+//! Parameterized invariants for ERC-4337 account abstraction.
+//!
+//! The templates relate the layers of a UserOperation (`bundler`,
+//! `paymaster`, `account`, ...) and the phases it passes through, so their
+//! variables are layer-qualified (`paymaster::deposit`) and, where a value
+//! must be read at a specific phase, phase-qualified
+//! (`validation::account::nonce`). Given an [`AAContext`] with phase
+//! snapshots, the evaluator reads each phase-qualified variable from the
+//! snapshot of its phase. As with the [vault templates](crate::defi), each
+//! returns an invariant in the [`AA_CATEGORY`] tagged with the
+//! [`AttackPatternDB`] id of the attack it prevents.
+//!
+//! [`AAContext`]: invar_core::AAContext
+//! [`AttackPatternDB`]: invar_core::AttackPatternDB
+
+use crate::templates::{call, compare, template, variable};
+use invar_core::account_abstraction::{AALayer, ExecutionPhase};
+use invar_core::model::{BinaryOp, Expression, Invariant, SEVERITY_CRITICAL, SEVERITY_HIGH};
+
+/// Category of the account abstraction templates.
+pub const AA_CATEGORY: &str = "account-abstraction";
+
+/// The paymaster's EntryPoint deposit covers the most a sponsored operation
+/// can cost: `deposit >= mul(pre_op_gas, max_gas_price)`.
+///
+/// Prevents the EntryPoint charging a paymaster more than it deposited,
+/// which underflows the deposit or leaves the bundler unpaid
+/// (`integer_overflow`).
+pub fn paymaster_deposit_covers_cost(
+    deposit: &str,
+    pre_op_gas: &str,
+    max_gas_price: &str,
+) -> Invariant {
+    template(
+        "paymaster_deposit_covers_cost",
+        compare(
+            variable(deposit),
+            BinaryOp::Gte,
+            call("mul", vec![variable(pre_op_gas), variable(max_gas_price)]),
+        ),
+        &format!("{} covers {} at {}", deposit, pre_op_gas, max_gas_price),
+        SEVERITY_CRITICAL,
+        AA_CATEGORY,
+        &["erc4337", "integer_overflow"],
+    )
+}
+
+/// Executing an operation consumes exactly one nonce:
+/// `execution::account::nonce == add(validation::account::nonce, 1)`.
+///
+/// Prevents replaying a validated operation, or skipping nonces so that a
+/// signed operation becomes valid again later (`access_control_bypass`).
+pub fn nonce_increments_once(nonce: &str) -> Invariant {
+    template(
+        "nonce_increments_once",
+        compare(
+            at(ExecutionPhase::Execution, nonce),
+            BinaryOp::Eq,
+            call(
+                "add",
+                vec![at(ExecutionPhase::Validation, nonce), Expression::Int(1)],
+            ),
+        ),
+        &format!("{} increments once per operation", nonce),
+        SEVERITY_CRITICAL,
+        AA_CATEGORY,
+        &["erc4337", "access_control_bypass"],
+    )
+}
+
+/// Validation touched no storage outside the slots associated with the
+/// sender: `!validation::bundler::storage_violation`, where the flag is set
+/// by the bundler's validation tracer.
+///
+/// Prevents an operation whose validation another operation in the bundle
+/// can invalidate by front-running it with a change to shared storage
+/// (`frontrunning`).
+pub fn validation_storage_restricted(violation_flag: &str) -> Invariant {
+    template(
+        "validation_storage_restricted",
+        Expression::Not(Box::new(at(ExecutionPhase::Validation, violation_flag))),
+        &format!("{} is never set during validation", violation_flag),
+        SEVERITY_HIGH,
+        AA_CATEGORY,
+        &["erc4337", "frontrunning"],
+    )
+}
+
+/// The account's reentrancy lock is released once the operation settles:
+/// `!settlement::account::reentrancy_locked`.
+///
+/// Prevents a lock left held by a reverted or reentered execution, and
+/// reentry into the account while it is held (`reentrancy`).
+pub fn reentrancy_lock_released(lock: &str) -> Invariant {
+    template(
+        "reentrancy_lock_released",
+        Expression::Not(Box::new(at(ExecutionPhase::Settlement, lock))),
+        &format!("{} is released by settlement", lock),
+        SEVERITY_HIGH,
+        AA_CATEGORY,
+        &["erc4337", "reentrancy"],
+    )
+}
+
+/// Every template, instantiated with the variables of
+/// [`UserOpData`](invar_core::UserOpData),
+/// [`PaymasterState`](invar_core::PaymasterState) and
+/// [`AccountState`](invar_core::AccountState).
+pub fn catalog() -> Vec<Invariant> {
+    vec![
+        paymaster_deposit_covers_cost(
+            "paymaster::deposit",
+            "bundler::pre_op_gas",
+            "bundler::max_gas_price",
+        ),
+        nonce_increments_once("account::nonce"),
+        validation_storage_restricted("bundler::storage_violation"),
+        reentrancy_lock_released("account::reentrancy_locked"),
+    ]
+}
+
+/// `target` (`layer::var`, or an account variable) as read at `phase`.
+fn at(phase: ExecutionPhase, target: &str) -> Expression {
+    let (layer, var) = target
+        .split_once("::")
+        .unwrap_or((AALayer::Account.as_str(), target));
+    Expression::PhaseQualifiedVar {
+        phase: phase.to_string(),
+        layer: layer.to_string(),
+        var: var.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use invar_core::{AAContext, EvalResult, EvaluationError, Evaluator, ExecutionContext};
+    use serde_json::json;
+
+    /// Evaluate `invariant` over the live state and phase snapshots of `aa`.
+    fn holds(invariant: &Invariant, aa: &AAContext) -> EvalResult<bool> {
+        let context = ExecutionContext::from_layer_state(&aa.layer_state)?;
+        Evaluator::new(context)
+            .with_aa_context(aa.clone())
+            .evaluate(&invariant.expression)?
+            .to_bool()
+    }
+
+    fn set(aa: &mut AAContext, layer: &str, var: &str, value: serde_json::Value) {
+        aa.set_layer_var(layer.to_string(), var.to_string(), value);
+    }
+
+    #[test]
+    fn test_phase_templates_read_snapshots() {
+        let nonce = nonce_increments_once("account::nonce");
+        assert_eq!(
+            nonce.expression.to_string(),
+            "(execution::account::nonce == add(validation::account::nonce, 1))"
+        );
+
+        let mut aa = AAContext::default();
+        aa.set_phase(ExecutionPhase::Validation);
+        set(&mut aa, "account", "nonce", json!(7));
+        set(&mut aa, "account", "reentrancy_locked", json!(false));
+        set(&mut aa, "bundler", "storage_violation", json!(false));
+        aa.snapshot_phase(ExecutionPhase::Validation);
+        aa.set_phase(ExecutionPhase::Execution);
+        set(&mut aa, "account", "nonce", json!(8));
+        set(&mut aa, "account", "reentrancy_locked", json!(true));
+        aa.snapshot_phase(ExecutionPhase::Execution);
+        aa.set_phase(ExecutionPhase::Settlement);
+        set(&mut aa, "account", "reentrancy_locked", json!(false));
+
+        let storage = validation_storage_restricted("bundler::storage_violation");
+        let lock = reentrancy_lock_released("account::reentrancy_locked");
+        assert_eq!(holds(&nonce, &aa), Ok(true));
+        assert_eq!(holds(&storage, &aa), Ok(true));
+        // Settlement has no snapshot yet and reads the live state
+        assert_eq!(holds(&lock, &aa), Ok(true));
+
+        // A replayed operation leaves the nonce unchanged
+        let mut replayed = aa.clone();
+        replayed.phase_snapshots.insert(
+            "execution".to_string(),
+            aa.phase_snapshots["validation"].clone(),
+        );
+        assert_eq!(holds(&nonce, &replayed), Ok(false));
+
+        set(&mut aa, "account", "reentrancy_locked", json!(true));
+        assert_eq!(holds(&lock, &aa), Ok(false));
+    }
+
+    #[test]
+    fn test_paymaster_deposit_covers_cost() {
+        let invariant = paymaster_deposit_covers_cost(
+            "paymaster::deposit",
+            "bundler::pre_op_gas",
+            "bundler::max_gas_price",
+        );
+        let mut aa = AAContext::default();
+        set(&mut aa, "paymaster", "deposit", json!(1_000_000));
+        set(&mut aa, "bundler", "pre_op_gas", json!(50_000));
+        set(&mut aa, "bundler", "max_gas_price", json!(20));
+        assert_eq!(holds(&invariant, &aa), Ok(true));
+
+        set(&mut aa, "bundler", "max_gas_price", json!(21));
+        assert_eq!(holds(&invariant, &aa), Ok(false));
+
+        // The cost is computed with checked arithmetic
+        set(&mut aa, "bundler", "max_gas_price", json!(u64::MAX));
+        assert_eq!(holds(&invariant, &aa), Err(EvaluationError::Overflow));
+    }
+}
@@ -0,0 +1,112 @@
+//! Parameterized invariants for token bridges.
+//!
+//! Bridge invariants relate the state of two chains, so their variables are
+//! qualified with the chain they live on (`l1::locked`, `l2::minted`); see
+//! [`invar_core::cross_chain`] for evaluating them over both chains and
+//! linking them to the programs of a multi-source build. As with the
+//! [vault templates](crate::defi), each returns an invariant in the
+//! [`BRIDGE_CATEGORY`] tagged with the [`AttackPatternDB`] id of the attack
+//! it prevents.
+//!
+//! [`AttackPatternDB`]: invar_core::AttackPatternDB
+
+use crate::templates::{compare, template, variable};
+use invar_core::model::{BinaryOp, Invariant, SEVERITY_CRITICAL, SEVERITY_HIGH};
+
+/// Category of the bridge templates.
+pub const BRIDGE_CATEGORY: &str = "bridge";
+
+/// Tags of every bridge template: forged messages bypass the bridge's checks.
+const TAGS: &[&str] = &["bridge", "access_control_bypass"];
+
+/// Every token minted on the destination is backed by one locked on the
+/// source: `locked >= minted`.
+///
+/// Prevents minting against forged or unverified deposit messages, as in
+/// the Wormhole exploit (`access_control_bypass`).
+pub fn lock_backs_mint(locked: &str, minted: &str) -> Invariant {
+    template(
+        "lock_backs_mint",
+        compare(variable(locked), BinaryOp::Gte, variable(minted)),
+        &format!("{} backs {}", locked, minted),
+        SEVERITY_CRITICAL,
+        BRIDGE_CATEGORY,
+        TAGS,
+    )
+}
+
+/// Tokens released on the source never exceed those burned on the
+/// destination: `burned >= unlocked`.
+///
+/// Prevents withdrawals without a matching burn, as when the Nomad bridge
+/// accepted unproven messages (`access_control_bypass`).
+pub fn burn_backs_unlock(burned: &str, unlocked: &str) -> Invariant {
+    template(
+        "burn_backs_unlock",
+        compare(variable(burned), BinaryOp::Gte, variable(unlocked)),
+        &format!("{} backs {}", burned, unlocked),
+        SEVERITY_CRITICAL,
+        BRIDGE_CATEGORY,
+        TAGS,
+    )
+}
+
+/// The destination has not processed messages the source has not sent:
+/// `processed_nonce <= sent_nonce`.
+///
+/// Prevents processing messages that were never sent on the source chain
+/// (`access_control_bypass`).
+pub fn message_nonce_bounded(sent_nonce: &str, processed_nonce: &str) -> Invariant {
+    template(
+        "message_nonce_bounded",
+        compare(
+            variable(processed_nonce),
+            BinaryOp::Lte,
+            variable(sent_nonce),
+        ),
+        &format!("{} never passes {}", processed_nonce, sent_nonce),
+        SEVERITY_HIGH,
+        BRIDGE_CATEGORY,
+        TAGS,
+    )
+}
+
+/// Every template, between an `l1` source and an `l2` destination.
+pub fn catalog() -> Vec<Invariant> {
+    vec![
+        lock_backs_mint("l1::locked", "l2::minted"),
+        burn_backs_unlock("l2::burned", "l1::unlocked"),
+        message_nonce_bounded("l1::sent_nonce", "l2::processed_nonce"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use invar_core::cross_chain::is_cross_chain;
+    use invar_core::CrossChainContext;
+
+    #[test]
+    fn test_lock_backs_mint_across_chains() {
+        let invariant = lock_backs_mint("l1::locked", "l2::minted");
+        assert_eq!(
+            invariant.expression.to_string(),
+            "(l1::locked >= l2::minted)"
+        );
+
+        let mut chains = CrossChainContext::default();
+        chains.set_chain_var("l1".to_string(), "locked".to_string(), 500.into());
+        chains.set_chain_var("l2".to_string(), "minted".to_string(), 500.into());
+        assert_eq!(chains.check(&invariant), Ok(true));
+        chains.set_chain_var("l2".to_string(), "minted".to_string(), 501.into());
+        assert_eq!(chains.check(&invariant), Ok(false));
+    }
+
+    #[test]
+    fn test_catalog_is_cross_chain() {
+        for invariant in catalog() {
+            assert_eq!(invariant.category, BRIDGE_CATEGORY);
+            assert!(is_cross_chain(&invariant), "{}", invariant.name);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Parameterized invariants for tokenized vaults.
+//!
+//! Each template takes the names of the vault's state variables (a
+//! `layer::var` name becomes a layer variable) and returns an invariant in the
+//! [`DEFI_CATEGORY`], tagged with the [`AttackPatternDB`] id of the attack it
+//! prevents.
+//!
+//! Invariant expressions have no arithmetic operators, so the math is written
+//! with the `add`, `sub`, `mul` and `div` builtins, which fail on overflow
+//! rather than wrap, and `old(x)` is the value of `x` in the evaluator's
+//! pre-transaction state, as in [synthesized](crate::synthesis) invariants.
+//! Generated checks cannot read `old()` yet, so templates that use it must be
+//! enforced as simulation-only.
+//!
+//! [`AttackPatternDB`]: invar_core::AttackPatternDB
+
+use crate::templates::{call, compare, old, template, variable};
+use invar_core::model::{
+    BinaryOp, Expression, Invariant, LogicalOp, SEVERITY_CRITICAL, SEVERITY_HIGH,
+};
+
+/// Category of the vault templates.
+pub const DEFI_CATEGORY: &str = "defi";
+
+/// Basis points in 100%.
+pub const BPS_DENOMINATOR: i128 = 10_000;
+
+/// Share price never falls: `total_assets / total_shares` after the
+/// transaction is at least what it was before, compared by cross
+/// multiplication so that no division rounds:
+/// `mul(total_assets, old(total_shares)) >= mul(old(total_assets), total_shares)`.
+///
+/// Prevents price manipulation within one transaction, such as a flash loan
+/// that inflates shares against unchanged assets (`flash_loan`).
+pub fn share_price_monotonic(total_assets: &str, total_shares: &str) -> Invariant {
+    let (assets, shares) = (variable(total_assets), variable(total_shares));
+    template(
+        "share_price_monotonic",
+        compare(
+            call("mul", vec![assets.clone(), old(shares.clone())]),
+            BinaryOp::Gte,
+            call("mul", vec![old(assets), shares]),
+        ),
+        &format!(
+            "Price of {} in {} never decreases",
+            total_shares, total_assets
+        ),
+        SEVERITY_CRITICAL,
+        DEFI_CATEGORY,
+        &["vault", "flash_loan"],
+    )
+}
+
+/// Assets change by exactly what was deposited and withdrawn:
+/// `total_assets == sub(add(old(total_assets), deposited), withdrawn)`.
+///
+/// Prevents withdrawals paying out more than they debit, as in a reentrant
+/// withdraw that is paid twice before its balance is updated (`reentrancy`).
+pub fn deposit_withdraw_conservation(
+    total_assets: &str,
+    deposited: &str,
+    withdrawn: &str,
+) -> Invariant {
+    let assets = variable(total_assets);
+    template(
+        "deposit_withdraw_conservation",
+        compare(
+            assets.clone(),
+            BinaryOp::Eq,
+            call(
+                "sub",
+                vec![
+                    call("add", vec![old(assets), variable(deposited)]),
+                    variable(withdrawn),
+                ],
+            ),
+        ),
+        &format!(
+            "{} changes only by {} and {}",
+            total_assets, deposited, withdrawn
+        ),
+        SEVERITY_CRITICAL,
+        DEFI_CATEGORY,
+        &["vault", "reentrancy"],
+    )
+}
+
+/// A fee is at most `max_bps` basis points of the amount it is charged on:
+/// `mul(fee, 10000) <= mul(amount, max_bps)`.
+///
+/// Prevents a compromised or careless admin from setting a fee that
+/// confiscates deposits (`access_control_bypass`).
+pub fn fee_bounded(fee: &str, amount: &str, max_bps: u64) -> Invariant {
+    template(
+        "fee_bounded",
+        compare(
+            call("mul", vec![variable(fee), Expression::Int(BPS_DENOMINATOR)]),
+            BinaryOp::Lte,
+            call(
+                "mul",
+                vec![variable(amount), Expression::Int(i128::from(max_bps))],
+            ),
+        ),
+        &format!("{} is at most {} bps of {}", fee, max_bps, amount),
+        SEVERITY_HIGH,
+        DEFI_CATEGORY,
+        &["vault", "access_control_bypass"],
+    )
+}
+
+/// Once shares exist there are at least `min_shares` of them:
+/// `(total_shares == 0) || (total_shares >= min_shares)`.
+///
+/// Prevents the first-depositor inflation attack, where an attacker who
+/// front-runs the first deposit mints a single share and donates assets so
+/// that later depositors' shares round down to zero (`frontrunning`).
+pub fn first_depositor_guard(total_shares: &str, min_shares: u64) -> Invariant {
+    let shares = variable(total_shares);
+    template(
+        "first_depositor_guard",
+        Expression::Logical {
+            left: Box::new(compare(shares.clone(), BinaryOp::Eq, Expression::Int(0))),
+            op: LogicalOp::Or,
+            right: Box::new(compare(
+                shares,
+                BinaryOp::Gte,
+                Expression::Int(i128::from(min_shares)),
+            )),
+        },
+        &format!("{} is zero or at least {}", total_shares, min_shares),
+        SEVERITY_HIGH,
+        DEFI_CATEGORY,
+        &["vault", "frontrunning"],
+    )
+}
+
+/// Every template, instantiated with conventional variable names.
+pub fn catalog() -> Vec<Invariant> {
+    vec![
+        share_price_monotonic("total_assets", "total_shares"),
+        deposit_withdraw_conservation("total_assets", "deposited", "withdrawn"),
+        fee_bounded("fee", "amount", 100),
+        first_depositor_guard("total_shares", 1_000),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use invar_core::{
+        EvaluationError, Evaluator, ExecutionContext, ProgramModel, StateVar, TypeChecker, Value,
+    };
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_share_price_template_type_checks() {
+        let invariant = share_price_monotonic("vault::assets", "vault::supply");
+        assert_eq!(
+            invariant.expression.to_string(),
+            "(mul(vault::assets, old(vault::supply)) >= mul(old(vault::assets), vault::supply))"
+        );
+
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "evm".to_string(),
+            "Vault.sol".to_string(),
+        );
+        for name in ["assets", "supply"] {
+            program.add_state_var(StateVar {
+                name: name.to_string(),
+                type_name: "u64".to_string(),
+                is_mutable: true,
+                visibility: None,
+                abilities: Default::default(),
+                depends_on: Vec::new(),
+            });
+        }
+        let mut checker = TypeChecker::new();
+        checker.load_from_program(&program);
+        let report = checker.check_invariants(&[invariant]);
+        assert!(!report.has_errors(), "{:?}", report.diagnostics);
+    }
+
+    #[test]
+    fn test_share_price_template_evaluates() {
+        let invariant = share_price_monotonic("vault::assets", "vault::supply");
+        let state = |assets: u64, supply: u64| {
+            BTreeMap::from([
+                ("vault::assets".to_string(), Value::U64(assets)),
+                ("vault::supply".to_string(), Value::U64(supply)),
+            ])
+        };
+        let holds = |before, after| {
+            let mut evaluator = Evaluator::new(ExecutionContext {
+                state_vars: after,
+                ..ExecutionContext::new()
+            });
+            evaluator.set_old_state(before);
+            evaluator.evaluate(&invariant.expression)
+        };
+
+        // A deposit at the current price, then one that mints too many shares
+        assert_eq!(
+            holds(state(1_000, 500), state(1_200, 600)),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            holds(state(1_000, 500), state(1_200, 700)),
+            Ok(Value::Bool(false))
+        );
+        // The cross products are checked, not wrapped
+        assert_eq!(
+            holds(state(u64::MAX, 2), state(u64::MAX, 2)),
+            Err(EvaluationError::Overflow)
+        );
+    }
+}
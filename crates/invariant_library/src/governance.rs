@@ -0,0 +1,185 @@
+//! Parameterized invariants for on-chain governance and timelocks.
+//!
+//! Like the [vault templates](crate::defi), each template takes the names of
+//! the governor's state variables and its constants, and returns an
+//! invariant in the [`GOVERNANCE_CATEGORY`] tagged with the
+//! [`AttackPatternDB`] id of the attack it prevents.
+//!
+//! Not every template can be generated for every chain; the notes on each
+//! template say what to do when a generator rejects it as unsupported.
+//!
+//! [`AttackPatternDB`]: invar_core::AttackPatternDB
+
+use crate::templates::{call, compare, int, logical, old, template, variable};
+use invar_core::model::{
+    BinaryOp, Expression, Invariant, LogicalOp, SEVERITY_CRITICAL, SEVERITY_HIGH,
+};
+
+/// Category of the governance templates.
+pub const GOVERNANCE_CATEGORY: &str = "governance";
+
+/// Votes cast meet the quorum: `votes >= quorum`.
+///
+/// Prevents proposals passing on a handful of votes while turnout is low,
+/// which lets an attacker take over the protocol's admin functions
+/// (`access_control_bypass`).
+pub fn quorum_reached(votes: &str, quorum: &str) -> Invariant {
+    template(
+        "quorum_reached",
+        compare(variable(votes), BinaryOp::Gte, variable(quorum)),
+        &format!("{} meets {}", votes, quorum),
+        SEVERITY_HIGH,
+        GOVERNANCE_CATEGORY,
+        &["governance", "access_control_bypass"],
+    )
+}
+
+/// No vote weighs more than all voting power together:
+/// `vote_weight <= total_voting_power`.
+///
+/// Prevents votes counted with power that was minted or borrowed within the
+/// transaction, as in flash-loaned governance tokens (`flash_loan`).
+pub fn vote_weight_bounded(vote_weight: &str, total_voting_power: &str) -> Invariant {
+    template(
+        "vote_weight_bounded",
+        compare(
+            variable(vote_weight),
+            BinaryOp::Lte,
+            variable(total_voting_power),
+        ),
+        &format!("{} is at most {}", vote_weight, total_voting_power),
+        SEVERITY_CRITICAL,
+        GOVERNANCE_CATEGORY,
+        &["governance", "flash_loan"],
+    )
+}
+
+/// A proposal is only executed from the queued state, once its timelock
+/// has expired:
+/// `(state != executed) || (((old(state) == queued) || (old(state) == executed)) && (now() >= eta))`.
+///
+/// `queued` and `executed` are the values the proposal state takes in those
+/// states. Prevents executing proposals before users had the timelock delay
+/// to react, or without queueing them at all (`access_control_bypass`).
+///
+/// Generated checks cannot read `old()` yet, so every generator rejects the
+/// template as unsupported; enforce it as simulation-only. The simulator
+/// reads `old()` from the evaluator's pre-transaction state.
+pub fn timelock_respected(state: &str, eta: &str, queued: u64, executed: u64) -> Invariant {
+    let state = variable(state);
+    let was = |value: u64| compare(old(state.clone()), BinaryOp::Eq, int(value));
+    template(
+        "timelock_respected",
+        logical(
+            compare(state.clone(), BinaryOp::Neq, int(executed)),
+            LogicalOp::Or,
+            logical(
+                logical(was(queued), LogicalOp::Or, was(executed)),
+                LogicalOp::And,
+                compare(call("now", Vec::new()), BinaryOp::Gte, variable(eta)),
+            ),
+        ),
+        &format!(
+            "Proposals are executed only from state {} once {} has passed",
+            queued, eta
+        ),
+        SEVERITY_CRITICAL,
+        GOVERNANCE_CATEGORY,
+        &["governance", "access_control_bypass"],
+    )
+}
+
+/// A voter who had already voted cannot change the tally:
+/// `!old(has_voted[voter]) || (votes == old(votes))`.
+///
+/// Prevents counting the same voter twice (`access_control_bypass`).
+///
+/// Generated checks cannot read `old()` yet, so every generator rejects the
+/// template as unsupported; enforce it as simulation-only.
+pub fn no_double_vote(has_voted: &str, voter: &str, votes: &str) -> Invariant {
+    let votes = variable(votes);
+    template(
+        "no_double_vote",
+        logical(
+            Expression::Not(Box::new(old(Expression::MapAccess {
+                map: Box::new(variable(has_voted)),
+                key: Box::new(variable(voter)),
+            }))),
+            LogicalOp::Or,
+            compare(votes.clone(), BinaryOp::Eq, old(votes)),
+        ),
+        &format!("{} votes at most once", voter),
+        SEVERITY_CRITICAL,
+        GOVERNANCE_CATEGORY,
+        &["governance", "access_control_bypass"],
+    )
+}
+
+/// Every template, instantiated with conventional variable names and the
+/// proposal states of OpenZeppelin's `Governor` (queued 5, executed 7).
+pub fn catalog() -> Vec<Invariant> {
+    vec![
+        quorum_reached("voting_power", "minimum_quorum"),
+        vote_weight_bounded("vote_weight", "total_voting_power"),
+        timelock_respected("proposal_state", "eta", 5, 7),
+        no_double_vote("has_voted", "voter", "votes"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use invar_core::{BlockContext, Evaluator, ExecutionContext, Value};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_quorum_template_matches_example() {
+        let examples: toml::Table =
+            toml::from_str(include_str!("../../../examples/invariants.toml")).unwrap();
+        let quorum = examples["invariant"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|inv| inv["name"].as_str() == Some("QuorumMaintenance"))
+            .unwrap();
+        let expected =
+            invar_dsl_parser::parse_expression(quorum["expression"].as_str().unwrap()).unwrap();
+
+        let invariant = quorum_reached("voting_power", "minimum_quorum");
+        assert_eq!(invariant.expression, expected);
+        assert_eq!(invariant.severity, quorum["severity"].as_str().unwrap());
+        assert_eq!(invariant.category, quorum["category"].as_str().unwrap());
+    }
+
+    #[test]
+    fn test_timelock_reads_pre_transaction_state() {
+        let invariant = timelock_respected("proposal_state", "eta", 5, 7);
+        let holds = |old_state: u64, timestamp: u64| {
+            let mut evaluator = Evaluator::new(ExecutionContext::new());
+            evaluator.set_state("proposal_state".to_string(), Value::U64(7));
+            evaluator.set_state("eta".to_string(), Value::U64(1_000));
+            evaluator.set_old_state(BTreeMap::from([(
+                "proposal_state".to_string(),
+                Value::U64(old_state),
+            )]));
+            evaluator.set_block(BlockContext {
+                timestamp,
+                ..BlockContext::default()
+            });
+            evaluator.evaluate(&invariant.expression)
+        };
+
+        assert_eq!(holds(5, 1_000), Ok(Value::Bool(true)));
+        // Executed before the eta, or without being queued
+        assert_eq!(holds(5, 999), Ok(Value::Bool(false)));
+        assert_eq!(holds(4, 1_000), Ok(Value::Bool(false)));
+    }
+
+    #[test]
+    fn test_catalog_uses_governor_states() {
+        assert_eq!(
+            catalog()[2].expression.to_string(),
+            "((proposal_state != 7) || (((old(proposal_state) == 5) || (old(proposal_state) == 7)) && (now() >= eta)))"
+        );
+    }
+}
@@ -3,8 +3,14 @@
 
 //! Invariant library: Load invariants from TOML files.
 
+pub mod account_abstraction;
+pub mod bridge;
+pub mod defi;
+pub mod governance;
 pub mod library;
 pub mod loader;
+pub mod synthesis;
+pub mod templates;
 
 pub use library::InvariantLibrary;
 pub use loader::LibraryLoader;
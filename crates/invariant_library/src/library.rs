@@ -32,6 +32,14 @@ impl InvariantLibrary {
         self.categories.values().flat_map(|v| v.iter()).collect()
     }
 
+    /// Get all invariants carrying `tag`, in any category.
+    pub fn filter_by_tag(&self, tag: &str) -> Vec<&Invariant> {
+        self.all()
+            .into_iter()
+            .filter(|invariant| invariant.tags.iter().any(|t| t == tag))
+            .collect()
+    }
+
     /// Count total invariants.
     pub fn count(&self) -> usize {
         self.categories.values().map(|v| v.len()).sum()
@@ -43,3 +51,127 @@ impl Default for InvariantLibrary {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LibraryLoader;
+
+    #[test]
+    fn test_filter_by_tag() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("defi.toml");
+        std::fs::write(
+            &path,
+            r#"
+[[invariants]]
+name = "supply_conserved"
+expression = "sum_balances == total_supply"
+category = "defi"
+tags = ["token", "conservation"]
+
+[[invariants]]
+name = "fee_bounded"
+expression = "fee <= 100"
+category = "defi"
+tags = ["fees"]
+"#,
+        )
+        .unwrap();
+
+        let mut library = InvariantLibrary::new();
+        for invariant in LibraryLoader::load_from_toml(&path).unwrap() {
+            library.add(invariant.category.clone(), invariant);
+        }
+
+        let conservation = library.filter_by_tag("conservation");
+        assert_eq!(conservation.len(), 1);
+        assert_eq!(conservation[0].name, "supply_conserved");
+        assert!(library.filter_by_tag("governance").is_empty());
+    }
+
+    #[test]
+    fn test_loader_parses_expressions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.toml");
+        std::fs::write(
+            &path,
+            r#"
+[[invariants]]
+name = "solvent"
+expression = "reserve >= sum(deposits) && paymaster::paused == false"
+
+[[invariants]]
+name = "broken"
+expression = "reserve >= debt fee"
+"#,
+        )
+        .unwrap();
+
+        // Invalid expressions are skipped rather than loaded as placeholders
+        let invariants = LibraryLoader::load_from_toml(&path).unwrap();
+        assert_eq!(invariants.len(), 1);
+        assert_eq!(
+            invariants[0].expression.to_string(),
+            "((reserve >= sum(deposits)) && (paymaster::paused == false))"
+        );
+    }
+
+    #[test]
+    fn test_loader_reads_chains() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chains.toml");
+        std::fs::write(
+            &path,
+            r#"
+[[invariants]]
+name = "sender_authorized"
+expression = "sender == owner"
+chains = ["evm"]
+
+[[invariants]]
+name = "fee_bounded"
+expression = "fee <= 100"
+"#,
+        )
+        .unwrap();
+
+        let invariants = LibraryLoader::load_from_toml(&path).unwrap();
+        assert_eq!(invariants[0].layers, vec!["evm"]);
+        assert!(invariants[0].is_compatible_with_chain("evm"));
+        assert!(!invariants[0].is_compatible_with_chain("solana"));
+        assert!(invariants[1].is_compatible_with_chain("solana"));
+    }
+
+    #[test]
+    fn test_loader_reads_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("enabled.toml");
+        std::fs::write(
+            &path,
+            r#"
+[[invariants]]
+name = "slow_scan"
+expression = "sum(deposits) <= reserve"
+tags = ["slow"]
+enabled = false
+
+[[invariants]]
+name = "fee_bounded"
+expression = "fee <= 100"
+
+[[invariants]]
+name = "misconfigured"
+expression = "fee <= 100"
+enabled = "no"
+"#,
+        )
+        .unwrap();
+
+        let invariants = LibraryLoader::load_from_toml(&path).unwrap();
+        assert_eq!(invariants.len(), 2);
+        assert!(!invariants[0].enabled);
+        assert_eq!(invariants[0].tags, vec!["slow"]);
+        assert!(invariants[1].enabled);
+    }
+}
@@ -1,6 +1,6 @@
 //! Library loader for TOML-based invariants.
 
-use invar_core::model::Invariant;
+use invar_core::model::{EnforcementLevel, Invariant, InvariantExample, SEVERITY_MEDIUM};
 use invar_core::Result;
 use std::path::Path;
 use tracing::info;
@@ -18,6 +18,21 @@ impl LibraryLoader {
     /// expression = "sum_balances == total_supply"
     /// severity = "critical"
     /// ```
+    ///
+    /// An optional `enforcement` key (`always`, `debug_only` or
+    /// `simulation_only`) controls where generated code checks the invariant,
+    /// An optional `tags = ["defi", "token"]` array labels it, an optional
+    /// `chains = ["evm"]` array limits it to those chains, and `enabled = false`
+    /// keeps commands from checking it.
+    ///
+    /// Each `[[invariants.examples]]` table following an invariant adds an
+    /// example state:
+    /// ```toml
+    /// [[invariants.examples]]
+    /// inputs = { sum_balances = "100", total_supply = "100" }
+    /// expected_result = true
+    /// description = "Balances add up to the supply"
+    /// ```
     pub fn load_from_toml(path: &Path) -> Result<Vec<Invariant>> {
         info!("Loading invariants from {:?}", path);
 
@@ -44,7 +59,11 @@ impl LibraryLoader {
             }
         }
 
-        info!("Loaded {} invariants from {}", invariants.len(), path.display());
+        info!(
+            "Loaded {} invariants from {}",
+            invariants.len(),
+            path.display()
+        );
         Ok(invariants)
     }
 
@@ -71,53 +90,154 @@ impl LibraryLoader {
 
 /// Parse an invariant from a TOML table value.
 fn parse_invariant_table(table: &toml::Value) -> Result<Invariant> {
-    let table = table.as_table()
-        .ok_or_else(|| invar_core::InvarError::ConfigError(
-            "Invariant must be a table".to_string()
-        ))?;
+    let table = table.as_table().ok_or_else(|| {
+        invar_core::InvarError::ConfigError("Invariant must be a table".to_string())
+    })?;
 
-    let name = table.get("name")
+    let name = table
+        .get("name")
         .and_then(|v| v.as_str())
-        .ok_or_else(|| invar_core::InvarError::ConfigError(
-            "Invariant must have a 'name' field".to_string()
-        ))?
+        .ok_or_else(|| {
+            invar_core::InvarError::ConfigError("Invariant must have a 'name' field".to_string())
+        })?
         .to_string();
 
-    let expression_str = table.get("expression")
+    let expression_str = table
+        .get("expression")
         .and_then(|v| v.as_str())
-        .ok_or_else(|| invar_core::InvarError::ConfigError(
-            "Invariant must have an 'expression' field".to_string()
-        ))?;
-
-    // Parse expression string into Invariant representation
-    // For now, create a placeholder expression
-    let expression = invar_core::model::Expression::Boolean(true);
-
-    let severity = table.get("severity")
+        .ok_or_else(|| {
+            invar_core::InvarError::ConfigError(
+                "Invariant must have an 'expression' field".to_string(),
+            )
+        })?;
+
+    let expression = invar_dsl_parser::parse_expression(expression_str).map_err(|e| {
+        invar_core::InvarError::ConfigError(format!(
+            "Invalid expression for invariant '{}': {}",
+            name, e
+        ))
+    })?;
+
+    let severity = table
+        .get("severity")
         .and_then(|v| v.as_str())
-        .unwrap_or("medium")
-        .to_string();
+        .unwrap_or(SEVERITY_MEDIUM);
 
-    let category = table.get("category")
+    let category = table
+        .get("category")
         .and_then(|v| v.as_str())
-        .unwrap_or("general")
-        .to_string();
+        .unwrap_or("general");
+
+    info!(
+        "Parsed invariant '{}' with expression '{}' (severity: {})",
+        name, expression_str, severity
+    );
+
+    let enforcement = match table.get("enforcement").and_then(|v| v.as_str()) {
+        Some(level) => EnforcementLevel::from_name(level).ok_or_else(|| {
+            invar_core::InvarError::ConfigError(format!(
+                "Unknown enforcement '{}': use always, debug_only or simulation_only",
+                level
+            ))
+        })?,
+        None => EnforcementLevel::Always,
+    };
+
+    let mut invariant = Invariant::new(&name, expression)
+        .with_severity(severity)
+        .with_category(category)
+        .with_enforcement(enforcement);
+    if let Some(description) = table.get("description").and_then(|v| v.as_str()) {
+        invariant = invariant.with_description(description);
+    }
+    if let Some(tags) = table.get("tags").and_then(|v| v.as_array()) {
+        let tags = tags
+            .iter()
+            .map(|tag| {
+                tag.as_str().map(String::from).ok_or_else(|| {
+                    invar_core::InvarError::ConfigError(format!(
+                        "Tags of invariant '{}' must be strings",
+                        name
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        invariant = invariant.with_tags(tags);
+    }
+    if let Some(chains) = table.get("chains").and_then(|v| v.as_array()) {
+        let chains = chains
+            .iter()
+            .map(|chain| {
+                chain.as_str().map(String::from).ok_or_else(|| {
+                    invar_core::InvarError::ConfigError(format!(
+                        "Chains of invariant '{}' must be strings",
+                        name
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        invariant = invariant.for_layers(chains);
+    }
+    if let Some(enabled) = table.get("enabled") {
+        let enabled = enabled.as_bool().ok_or_else(|| {
+            invar_core::InvarError::ConfigError(format!(
+                "Enabled flag of invariant '{}' must be a boolean",
+                name
+            ))
+        })?;
+        invariant = invariant.with_enabled(enabled);
+    }
+    if let Some(examples) = table.get("examples").and_then(|v| v.as_array()) {
+        let examples = examples
+            .iter()
+            .map(|example| parse_example_table(&name, example))
+            .collect::<Result<Vec<_>>>()?;
+        invariant = invariant.with_examples(examples);
+    }
 
-    let description = table.get("description")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
+    Ok(invariant)
+}
 
-    info!("Parsed invariant '{}' with expression '{}' (severity: {})",
-          name, expression_str, severity);
+/// Parse an `[[invariants.examples]]` table of invariant `name`.
+///
+/// Input values may be written as strings or as TOML literals, which are
+/// kept in their TOML spelling (`100`, `true`, `[1, 2]`).
+fn parse_example_table(name: &str, example: &toml::Value) -> Result<InvariantExample> {
+    let invalid = |message: &str| {
+        invar_core::InvarError::ConfigError(format!("Example of invariant '{}' {}", name, message))
+    };
+    let example = example
+        .as_table()
+        .ok_or_else(|| invalid("must be a table"))?;
+
+    let inputs = match example.get("inputs") {
+        Some(inputs) => inputs
+            .as_table()
+            .ok_or_else(|| invalid("must have an 'inputs' table"))?
+            .iter()
+            .map(|(var, value)| {
+                let value = match value {
+                    toml::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                (var.clone(), value)
+            })
+            .collect(),
+        None => Default::default(),
+    };
+    let expected_result = example
+        .get("expected_result")
+        .and_then(|v| v.as_bool())
+        .ok_or_else(|| invalid("must have a boolean 'expected_result'"))?;
+    let description = example
+        .get("description")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
 
-    Ok(Invariant {
-        name,
+    Ok(InvariantExample {
+        inputs,
+        expected_result,
         description,
-        expression,
-        severity,
-        category,
-        is_always_true: true,
-        layers: Vec::new(),
-        phases: Vec::new(),
     })
 }
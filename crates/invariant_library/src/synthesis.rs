@@ -0,0 +1,514 @@
+//! Invariants synthesized from analyzed code.
+//!
+//! Synthesized invariants are proposals: they are written out for review
+//! with [`to_toml`] and only enforced once loaded like any other library file.
+
+use crate::templates::variable;
+use invar_core::model::{
+    ArithmeticOp, BinaryOp, Expression, Invariant, LogicalOp, ProgramModel, UncheckedArithmetic,
+    SEVERITY_CRITICAL, SEVERITY_HIGH,
+};
+use invar_core::security_validator::OWNER_MODIFIERS;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Category of synthesized invariants.
+pub const SYNTHESIZED_CATEGORY: &str = "synthesized";
+
+/// One invariant per function, updated variable and operator for the
+/// program's unchecked arithmetic: additions must not decrease the value,
+/// subtractions must not increase it, and multiplications must not decrease
+/// a non-zero one.
+///
+/// Each invariant is scoped to the function making the update, so a
+/// variable added to in one function and subtracted from in another is not
+/// pinned in both. `old(x)` is the value before the update.
+pub fn synthesize_overflow(program: &ProgramModel) -> Vec<Invariant> {
+    // First update per (function, target, op), in a stable order
+    let mut sites: BTreeMap<(&str, &str, ArithmeticOp), &UncheckedArithmetic> = BTreeMap::new();
+    for site in &program.unchecked_arithmetic {
+        sites
+            .entry((&site.function_name, &site.target, site.op))
+            .or_insert(site);
+    }
+
+    sites
+        .into_values()
+        .map(|site| {
+            let new = variable(&site.target);
+            let old = Expression::FunctionCall {
+                name: "old".to_string(),
+                args: vec![new.clone()],
+            };
+            let compare = |op| Expression::BinaryOp {
+                left: Box::new(new.clone()),
+                op,
+                right: Box::new(old.clone()),
+            };
+            let (suffix, expression, verb) = match site.op {
+                ArithmeticOp::Add => ("no_overflow", compare(BinaryOp::Gte), "addition"),
+                ArithmeticOp::Sub => ("no_underflow", compare(BinaryOp::Lte), "subtraction"),
+                ArithmeticOp::Mul => (
+                    "mul_no_overflow",
+                    Expression::Logical {
+                        left: Box::new(Expression::BinaryOp {
+                            left: Box::new(new.clone()),
+                            op: BinaryOp::Eq,
+                            right: Box::new(Expression::Int(0)),
+                        }),
+                        op: LogicalOp::Or,
+                        right: Box::new(compare(BinaryOp::Gte)),
+                    },
+                    "multiplication",
+                ),
+            };
+            let target = site.target.replace("::", "_");
+            let invariant = |name: &str, location: String| {
+                Invariant::new(name, expression.clone())
+                    .with_description(&format!(
+                        "Unchecked {} on {}{} (line {}) must not wrap",
+                        verb, site.target, location, site.line
+                    ))
+                    .with_severity(SEVERITY_HIGH)
+                    .with_category(SYNTHESIZED_CATEGORY)
+            };
+            // Models saved before the function was recorded leave it empty
+            if site.function_name.is_empty() {
+                return invariant(&format!("{}_{}", target, suffix), String::new());
+            }
+            invariant(
+                &format!("{}_{}_{}", site.function_name, target, suffix),
+                format!(" in {}", site.function_name),
+            )
+            .for_layers(vec![site.function_name.clone()])
+        })
+        .collect()
+}
+
+/// State variable names treated as the privileged account (EVM).
+const OWNER_VARIABLES: [&str; 3] = ["owner", "admin", "governance"];
+
+/// How likely a synthesized candidate is to be a real requirement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// The function writes unrelated state; it may be meant to be public.
+    Medium,
+    /// The function writes the privileged account itself, or takes an
+    /// authority account it never checks.
+    High,
+}
+
+impl Confidence {
+    /// Lowercase name, as written to TOML.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+}
+
+/// A synthesized access-control invariant for one function.
+#[derive(Debug, Clone)]
+pub struct AccessCandidate {
+    /// Function the invariant is scoped to (also its only layer).
+    pub function: String,
+    /// Proposed invariant.
+    pub invariant: Invariant,
+    /// Confidence in the proposal.
+    pub confidence: Confidence,
+}
+
+/// Outcome of [`synthesize_access`].
+#[derive(Debug, Clone, Default)]
+pub struct AccessSynthesis {
+    /// Candidates, one per unguarded function (or authority account).
+    pub candidates: Vec<AccessCandidate>,
+    /// Functions skipped because they are already guarded.
+    pub guarded: Vec<String>,
+}
+
+/// Propose access-control invariants for privileged functions missing a guard.
+///
+/// EVM: when the contract has an `owner`/`admin`/`governance` variable, every
+/// state-mutating entry point without an [`OWNER_MODIFIERS`] modifier gets
+/// `caller == owner`. Solana: every authority account a handler takes
+/// without a signer check gets `account::is_signer`.
+pub fn synthesize_access(program: &ProgramModel) -> AccessSynthesis {
+    let mut candidates = Vec::new();
+    let mut guarded = BTreeSet::new();
+
+    let owner = program.state_vars.keys().find(|name| {
+        OWNER_VARIABLES.contains(&name.trim_start_matches('_').to_lowercase().as_str())
+    });
+    if let Some(owner) = owner {
+        for function in program.functions.values() {
+            if !function.is_entry_point || function.is_pure || function.mutates.is_empty() {
+                continue;
+            }
+            if function
+                .modifiers
+                .iter()
+                .any(|modifier| OWNER_MODIFIERS.contains(&modifier.as_str()))
+            {
+                guarded.insert(function.name.clone());
+                continue;
+            }
+            let takes_over = function.mutates.contains(owner);
+            let writes: Vec<&str> = function.mutates.iter().map(String::as_str).collect();
+            let expression = Expression::BinaryOp {
+                left: Box::new(Expression::Var("caller".to_string())),
+                op: BinaryOp::Eq,
+                right: Box::new(Expression::Var(owner.clone())),
+            };
+            let invariant = Invariant::new(
+                &format!(
+                    "{}_caller_is_{}",
+                    function.name,
+                    owner.trim_start_matches('_')
+                ),
+                expression,
+            )
+            .with_description(&format!(
+                "Only {} may call {}, which writes {}",
+                owner,
+                function.name,
+                writes.join(", ")
+            ))
+            .with_severity(if takes_over {
+                SEVERITY_CRITICAL
+            } else {
+                SEVERITY_HIGH
+            })
+            .with_category(SYNTHESIZED_CATEGORY)
+            .for_layers(vec![function.name.clone()]);
+            candidates.push(AccessCandidate {
+                function: function.name.clone(),
+                invariant,
+                confidence: if takes_over {
+                    Confidence::High
+                } else {
+                    Confidence::Medium
+                },
+            });
+        }
+    }
+
+    for authority in &program.authority_accounts {
+        if authority.signer_checked {
+            guarded.insert(authority.function_name.clone());
+            continue;
+        }
+        let expression = Expression::LayerVar {
+            layer: authority.account.clone(),
+            var: "is_signer".to_string(),
+        };
+        let invariant = Invariant::new(
+            &format!(
+                "{}_{}_is_signer",
+                authority.function_name, authority.account
+            ),
+            expression,
+        )
+        .with_description(&format!(
+            "{} must sign {} (line {})",
+            authority.account, authority.function_name, authority.line
+        ))
+        .with_severity(SEVERITY_CRITICAL)
+        .with_category(SYNTHESIZED_CATEGORY)
+        .for_layers(vec![authority.function_name.clone()]);
+        candidates.push(AccessCandidate {
+            function: authority.function_name.clone(),
+            invariant,
+            confidence: Confidence::High,
+        });
+    }
+
+    // A function with any unguarded account is not skipped
+    for candidate in &candidates {
+        guarded.remove(&candidate.function);
+    }
+    AccessSynthesis {
+        candidates,
+        guarded: guarded.into_iter().collect(),
+    }
+}
+
+/// Render invariants in the `[[invariants]]` format read by
+/// [`LibraryLoader::load_from_toml`](crate::LibraryLoader::load_from_toml).
+pub fn to_toml(invariants: &[Invariant]) -> String {
+    document(invariants.iter().map(invariant_table).collect())
+}
+
+/// Render access candidates like [`to_toml`], with each entry's confidence.
+pub fn access_toml(synthesis: &AccessSynthesis) -> String {
+    document(
+        synthesis
+            .candidates
+            .iter()
+            .map(|candidate| {
+                let mut table = invariant_table(&candidate.invariant);
+                table.insert(
+                    "confidence".to_string(),
+                    candidate.confidence.as_str().into(),
+                );
+                table
+            })
+            .collect(),
+    )
+}
+
+fn invariant_table(invariant: &Invariant) -> toml::Table {
+    let mut table = toml::Table::new();
+    table.insert("name".to_string(), invariant.name.clone().into());
+    table.insert(
+        "expression".to_string(),
+        invariant.expression.to_string().into(),
+    );
+    table.insert("severity".to_string(), invariant.severity.clone().into());
+    table.insert("category".to_string(), invariant.category.clone().into());
+    if let Some(description) = &invariant.description {
+        table.insert("description".to_string(), description.clone().into());
+    }
+    if !invariant.tags.is_empty() {
+        table.insert("tags".to_string(), invariant.tags.clone().into());
+    }
+    if !invariant.layers.is_empty() {
+        table.insert("layers".to_string(), invariant.layers.clone().into());
+    }
+    table
+}
+
+fn document(entries: Vec<toml::Table>) -> String {
+    let mut document = toml::Table::new();
+    document.insert(
+        "invariants".to_string(),
+        toml::Value::Array(entries.into_iter().map(toml::Value::Table).collect()),
+    );
+    document.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program() -> ProgramModel {
+        let mut program = ProgramModel::new(
+            "Counter".to_string(),
+            "evm".to_string(),
+            "Counter.sol".to_string(),
+        );
+        for (function, target, op, line) in [
+            ("bump", "total", ArithmeticOp::Add, 9),
+            ("repay", "vault::debt", ArithmeticOp::Sub, 10),
+            ("bump", "total", ArithmeticOp::Add, 12),
+            ("scale", "shares", ArithmeticOp::Mul, 14),
+        ] {
+            program.unchecked_arithmetic.push(UncheckedArithmetic {
+                function_name: function.to_string(),
+                target: target.to_string(),
+                op,
+                line,
+            });
+        }
+        program
+    }
+
+    #[test]
+    fn test_synthesize_one_invariant_per_update_kind() {
+        let invariants = synthesize_overflow(&program());
+        let rendered: Vec<(String, String)> = invariants
+            .iter()
+            .map(|inv| (inv.name.clone(), inv.expression.to_string()))
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                (
+                    "bump_total_no_overflow".to_string(),
+                    "(total >= old(total))".to_string()
+                ),
+                (
+                    "repay_vault_debt_no_underflow".to_string(),
+                    "(vault::debt <= old(vault::debt))".to_string()
+                ),
+                (
+                    "scale_shares_mul_no_overflow".to_string(),
+                    "((shares == 0) || (shares >= old(shares)))".to_string()
+                ),
+            ]
+        );
+        assert!(invariants
+            .iter()
+            .all(|inv| inv.category == SYNTHESIZED_CATEGORY));
+        assert_eq!(
+            invariants[0].description.as_deref(),
+            Some("Unchecked addition on total in bump (line 9) must not wrap")
+        );
+        assert_eq!(invariants[0].layers, vec!["bump"]);
+    }
+
+    #[test]
+    fn test_overflow_candidates_are_scoped_to_their_function() {
+        let mut program = ProgramModel::new(
+            "Vault".to_string(),
+            "solana".to_string(),
+            "lib.rs".to_string(),
+        );
+        for (function, op, line) in [
+            ("deposit", ArithmeticOp::Add, 4),
+            ("withdraw", ArithmeticOp::Sub, 9),
+        ] {
+            program.unchecked_arithmetic.push(UncheckedArithmetic {
+                function_name: function.to_string(),
+                target: "vault::balance".to_string(),
+                op,
+                line,
+            });
+        }
+
+        // Global, the two would say the balance never changes
+        let scoped: Vec<(String, String, Vec<String>)> = synthesize_overflow(&program)
+            .into_iter()
+            .map(|inv| (inv.name, inv.expression.to_string(), inv.layers))
+            .collect();
+        assert_eq!(
+            scoped,
+            vec![
+                (
+                    "deposit_vault_balance_no_overflow".to_string(),
+                    "(vault::balance >= old(vault::balance))".to_string(),
+                    vec!["deposit".to_string()]
+                ),
+                (
+                    "withdraw_vault_balance_no_underflow".to_string(),
+                    "(vault::balance <= old(vault::balance))".to_string(),
+                    vec!["withdraw".to_string()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_access_candidates_for_unguarded_evm_functions() {
+        use invar_core::model::{FunctionModel, OverflowProtection, StateVar};
+
+        let mut program = ProgramModel::new(
+            "Vault".to_string(),
+            "evm".to_string(),
+            "Vault.sol".to_string(),
+        );
+        program.add_state_var(StateVar {
+            name: "owner".to_string(),
+            type_name: "address".to_string(),
+            is_mutable: true,
+            visibility: Some("public".to_string()),
+            abilities: BTreeSet::new(),
+            depends_on: Vec::new(),
+        });
+        for (name, mutates, modifiers, is_pure) in [
+            ("setOwner", vec!["owner"], vec![], false),
+            ("setFee", vec!["fee"], vec![], false),
+            ("sweep", vec!["reserve"], vec!["onlyOwner"], false),
+            ("fee", vec![], vec![], true),
+        ] {
+            program.add_function(FunctionModel {
+                name: name.to_string(),
+                parameters: Vec::new(),
+                return_type: None,
+                mutates: mutates.into_iter().map(String::from).collect(),
+                reads: BTreeSet::new(),
+                is_entry_point: true,
+                is_pure,
+                overflow_protection: OverflowProtection::Unknown,
+                span: None,
+                modifiers: modifiers.into_iter().map(String::from).collect(),
+                uses_low_level: false,
+                pre_conditions: Vec::new(),
+                post_conditions: Vec::new(),
+            });
+        }
+
+        let synthesis = synthesize_access(&program);
+        let candidates: Vec<(&str, String, Confidence)> = synthesis
+            .candidates
+            .iter()
+            .map(|c| {
+                (
+                    c.invariant.name.as_str(),
+                    c.invariant.expression.to_string(),
+                    c.confidence,
+                )
+            })
+            .collect();
+        assert_eq!(
+            candidates,
+            vec![
+                (
+                    "setFee_caller_is_owner",
+                    "(caller == owner)".to_string(),
+                    Confidence::Medium
+                ),
+                (
+                    "setOwner_caller_is_owner",
+                    "(caller == owner)".to_string(),
+                    Confidence::High
+                ),
+            ]
+        );
+        assert_eq!(synthesis.candidates[0].invariant.layers, vec!["setFee"]);
+        assert_eq!(synthesis.guarded, vec!["sweep"]);
+    }
+
+    #[test]
+    fn test_access_candidates_for_missing_signer_checks() {
+        use invar_core::model::AuthorityAccount;
+
+        let mut program = program();
+        for (function, account, signer_checked) in
+            [("set_fee", "authority", false), ("close", "admin", true)]
+        {
+            program.authority_accounts.push(AuthorityAccount {
+                function_name: function.to_string(),
+                account: account.to_string(),
+                signer_checked,
+                line: 5,
+            });
+        }
+
+        let synthesis = synthesize_access(&program);
+        assert_eq!(synthesis.candidates.len(), 1);
+        let candidate = &synthesis.candidates[0];
+        assert_eq!(candidate.invariant.name, "set_fee_authority_is_signer");
+        assert_eq!(
+            candidate.invariant.expression.to_string(),
+            "authority::is_signer"
+        );
+        assert_eq!(synthesis.guarded, vec!["close"]);
+
+        let table: toml::Table = toml::from_str(&access_toml(&synthesis)).unwrap();
+        let entry = &table["invariants"].as_array().unwrap()[0];
+        assert_eq!(entry["confidence"].as_str(), Some("high"));
+        assert_eq!(
+            entry["layers"].as_array().unwrap()[0].as_str(),
+            Some("set_fee")
+        );
+    }
+
+    #[test]
+    fn test_toml_uses_library_format() {
+        let toml = to_toml(&synthesize_overflow(&program()));
+        let table: toml::Table = toml::from_str(&toml).unwrap();
+        let entries = table["invariants"].as_array().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0]["name"].as_str(), Some("bump_total_no_overflow"));
+        assert_eq!(
+            entries[0]["expression"].as_str(),
+            Some("(total >= old(total))")
+        );
+        assert_eq!(entries[0]["category"].as_str(), Some("synthesized"));
+        assert_eq!(
+            entries[0]["layers"].as_array().unwrap()[0].as_str(),
+            Some("bump")
+        );
+    }
+}
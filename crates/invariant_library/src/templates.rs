@@ -0,0 +1,128 @@
+//! Parameterized invariants for common defensive properties, and the
+//! expression builders the template catalogs share.
+
+use invar_core::model::{BinaryOp, Expression, Invariant, LogicalOp, SEVERITY_HIGH};
+use invar_core::NO_MUTATION_AFTER_CPI;
+
+/// `no_mutation_after_cpi("<function>")`: the Solana handler `function`
+/// writes no account state after its first cross-program invocation.
+///
+/// Checked at build time against the analyzed CPI calls; the build fails when
+/// the handler writes state after `invoke`/`invoke_signed`.
+pub fn no_mutation_after_cpi(function: &str) -> Invariant {
+    let expression = Expression::FunctionCall {
+        name: NO_MUTATION_AFTER_CPI.to_string(),
+        args: vec![Expression::String(function.to_string())],
+    };
+    Invariant::new(
+        &format!("{}_{}", NO_MUTATION_AFTER_CPI, function),
+        expression,
+    )
+    .with_description(&format!("{} writes no state after a CPI", function))
+    .with_severity(SEVERITY_HIGH)
+    .with_category("cpi")
+}
+
+/// A catalog template: `expression` with its description and severity, in
+/// `category`, tagged with `tags` (the domain, then the [`AttackPatternDB`]
+/// id of the attack it prevents).
+///
+/// [`AttackPatternDB`]: invar_core::AttackPatternDB
+pub(crate) fn template(
+    name: &str,
+    expression: Expression,
+    description: &str,
+    severity: &str,
+    category: &str,
+    tags: &[&str],
+) -> Invariant {
+    Invariant::new(name, expression)
+        .with_description(description)
+        .with_severity(severity)
+        .with_category(category)
+        .with_tags(tags.iter().map(|tag| tag.to_string()).collect())
+}
+
+pub(crate) fn compare(left: Expression, op: BinaryOp, right: Expression) -> Expression {
+    Expression::BinaryOp {
+        left: Box::new(left),
+        op,
+        right: Box::new(right),
+    }
+}
+
+pub(crate) fn logical(left: Expression, op: LogicalOp, right: Expression) -> Expression {
+    Expression::Logical {
+        left: Box::new(left),
+        op,
+        right: Box::new(right),
+    }
+}
+
+pub(crate) fn call(name: &str, args: Vec<Expression>) -> Expression {
+    Expression::FunctionCall {
+        name: name.to_string(),
+        args,
+    }
+}
+
+pub(crate) fn old(expr: Expression) -> Expression {
+    call("old", vec![expr])
+}
+
+pub(crate) fn int(value: u64) -> Expression {
+    Expression::Int(i128::from(value))
+}
+
+/// `vault::balance` as a layer variable, anything else as a plain one.
+pub(crate) fn variable(target: &str) -> Expression {
+    match target.split_once("::") {
+        Some((layer, var)) => Expression::LayerVar {
+            layer: layer.to_string(),
+            var: var.to_string(),
+        },
+        None => Expression::Var(target.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{account_abstraction, bridge, defi, governance};
+    use invar_core::AttackPatternDB;
+
+    #[test]
+    fn test_no_mutation_after_cpi_expression() {
+        let invariant = no_mutation_after_cpi("withdraw");
+        assert_eq!(invariant.name, "no_mutation_after_cpi_withdraw");
+        assert_eq!(
+            invariant.expression.to_string(),
+            "no_mutation_after_cpi(\"withdraw\")"
+        );
+    }
+
+    #[test]
+    fn test_catalogs_link_known_attacks() {
+        let db = AttackPatternDB::new();
+        let catalogs = [
+            (defi::DEFI_CATEGORY, defi::catalog()),
+            (bridge::BRIDGE_CATEGORY, bridge::catalog()),
+            (governance::GOVERNANCE_CATEGORY, governance::catalog()),
+            (
+                account_abstraction::AA_CATEGORY,
+                account_abstraction::catalog(),
+            ),
+        ];
+        for (category, catalog) in catalogs {
+            for invariant in catalog {
+                assert_eq!(invariant.category, category, "{}", invariant.name);
+                assert!(
+                    db.get_pattern(&invariant.tags[1]).is_some(),
+                    "{} links unknown attack {}",
+                    invariant.name,
+                    invariant.tags[1]
+                );
+            }
+        }
+    }
+}
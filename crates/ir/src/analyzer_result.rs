@@ -1,6 +1,10 @@
-//! Analysis context tracking.
+//! Analysis context tracking and analyzer result caching.
 
 use invar_core::model::ProgramModel;
+use invar_core::traits::ChainAnalyzer;
+use invar_core::Result;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 
 /// Context information from analysis phase.
 #[derive(Debug, Clone)]
@@ -35,3 +39,172 @@ impl AnalysisContext {
         self.is_valid = false;
     }
 }
+
+/// Default on-disk location of the analysis cache, relative to the project root.
+pub const DEFAULT_CACHE_DIR: &str = ".invar/cache";
+
+/// On-disk cache of analyzed `ProgramModel`s.
+///
+/// Entries are keyed by a SHA-256 over the analyzer version, the chain, the
+/// source path, and the source content, so editing a file or upgrading Invar
+/// invalidates its entry automatically.
+#[derive(Debug)]
+pub struct AnalysisCache {
+    dir: PathBuf,
+    version: String,
+    hits: usize,
+    misses: usize,
+}
+
+impl AnalysisCache {
+    /// Create a cache in `dir`, keyed by the current crate version.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self::with_version(dir, env!("CARGO_PKG_VERSION"))
+    }
+
+    /// Create a cache in `dir` with an explicit analyzer version.
+    pub fn with_version(dir: impl Into<PathBuf>, version: impl Into<String>) -> Self {
+        Self {
+            dir: dir.into(),
+            version: version.into(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Number of lookups served from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Number of lookups that required a fresh analysis.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Return the cached model for `path`, or analyze it and store the result.
+    ///
+    /// Entries that no longer deserialize into the current `ProgramModel`
+    /// are treated as misses. Failing to write the cache is not an error.
+    pub fn get_or_analyze(
+        &mut self,
+        path: &Path,
+        analyzer: &dyn ChainAnalyzer,
+    ) -> Result<ProgramModel> {
+        let source = std::fs::read(path)?;
+        let entry = self.dir.join(format!(
+            "{}.json",
+            self.cache_key(path, analyzer.chain(), &source)
+        ));
+
+        if let Ok(program) = ProgramModel::load_from_json(&entry) {
+            self.hits += 1;
+            return Ok(program);
+        }
+
+        self.misses += 1;
+        let program = analyzer.analyze(path)?;
+
+        if std::fs::create_dir_all(&self.dir).is_ok() {
+            let _ = program.save_to_json(&entry);
+        }
+
+        Ok(program)
+    }
+
+    fn cache_key(&self, path: &Path, chain: &str, source: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        for part in [
+            self.version.as_bytes(),
+            chain.as_bytes(),
+            path.to_string_lossy().as_bytes(),
+        ] {
+            hasher.update(part);
+            hasher.update([0u8]);
+        }
+        hasher.update(source);
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingAnalyzer {
+        calls: AtomicUsize,
+    }
+
+    impl ChainAnalyzer for CountingAnalyzer {
+        fn analyze(&self, path: &Path) -> Result<ProgramModel> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(ProgramModel::new(
+                "counted".to_string(),
+                "solana".to_string(),
+                path.display().to_string(),
+            ))
+        }
+
+        fn chain(&self) -> &str {
+            "solana"
+        }
+
+        fn supported_extensions(&self) -> &'static [&'static str] {
+            &[".rs"]
+        }
+    }
+
+    #[test]
+    fn test_cache_hits_unchanged_and_misses_edited_file() {
+        let workspace = tempfile::tempdir().unwrap();
+        let source = workspace.path().join("lib.rs");
+        std::fs::write(&source, "pub struct Vault { pub balance: u64 }").unwrap();
+
+        let analyzer = CountingAnalyzer {
+            calls: AtomicUsize::new(0),
+        };
+        let mut cache = AnalysisCache::new(workspace.path().join(DEFAULT_CACHE_DIR));
+
+        cache.get_or_analyze(&source, &analyzer).unwrap();
+        cache.get_or_analyze(&source, &analyzer).unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        std::fs::write(&source, "pub struct Vault { pub balance: u128 }").unwrap();
+        cache.get_or_analyze(&source, &analyzer).unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+        assert_eq!(analyzer.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_cache_invalidated_by_version_and_corruption() {
+        let workspace = tempfile::tempdir().unwrap();
+        let source = workspace.path().join("lib.rs");
+        std::fs::write(&source, "pub struct Vault {}").unwrap();
+        let cache_dir = workspace.path().join(DEFAULT_CACHE_DIR);
+        let analyzer = CountingAnalyzer {
+            calls: AtomicUsize::new(0),
+        };
+
+        AnalysisCache::with_version(&cache_dir, "0.1.0")
+            .get_or_analyze(&source, &analyzer)
+            .unwrap();
+
+        let mut upgraded = AnalysisCache::with_version(&cache_dir, "0.2.0");
+        upgraded.get_or_analyze(&source, &analyzer).unwrap();
+        assert_eq!(upgraded.misses(), 1);
+
+        for entry in std::fs::read_dir(&cache_dir).unwrap() {
+            std::fs::write(entry.unwrap().path(), "{ not a model").unwrap();
+        }
+        let mut corrupted = AnalysisCache::with_version(&cache_dir, "0.2.0");
+        corrupted.get_or_analyze(&source, &analyzer).unwrap();
+        assert_eq!(corrupted.misses(), 1);
+        assert_eq!(analyzer.calls.load(Ordering::SeqCst), 3);
+    }
+}
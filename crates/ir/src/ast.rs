@@ -1,7 +1,8 @@
 //! AST extensions and utilities for IR.
 
-use invar_core::model::{Expression, FunctionModel, StateVar};
-use std::collections::BTreeMap;
+use invar_core::model::{Expression, FunctionModel, ProgramModel, StateVar};
+use invar_core::visitor::ExpressionVisitor;
+use std::collections::{BTreeMap, BTreeSet};
 
 /// A directed dependency graph for tracking state mutation dependencies.
 #[derive(Debug, Clone)]
@@ -14,6 +15,9 @@ pub struct DependencyGraph {
 
     /// Function → {State vars it reads}
     pub read_deps: BTreeMap<String, Vec<String>>,
+
+    /// State var → {State vars its value is derived from}
+    pub state_deps: BTreeMap<String, Vec<String>>,
 }
 
 impl DependencyGraph {
@@ -23,9 +27,30 @@ impl DependencyGraph {
             call_graph: BTreeMap::new(),
             mutation_sources: BTreeMap::new(),
             read_deps: BTreeMap::new(),
+            state_deps: BTreeMap::new(),
         }
     }
 
+    /// Build the graph from a program's recorded mutations, reads and
+    /// state variable dependencies.
+    pub fn from_program(program: &ProgramModel) -> Self {
+        let mut graph = Self::new();
+        for function in program.functions.values() {
+            for var in &function.mutates {
+                graph.add_mutation(var.clone(), function.name.clone());
+            }
+            for var in &function.reads {
+                graph.add_read(function.name.clone(), var.clone());
+            }
+        }
+        for var in program.state_vars.values() {
+            for dependency in &var.depends_on {
+                graph.add_state_dependency(var.name.clone(), dependency.clone());
+            }
+        }
+        graph
+    }
+
     /// Add a call relationship: caller → callee.
     pub fn add_call(&mut self, caller: String, callee: String) {
         self.call_graph.entry(caller).or_default().push(callee);
@@ -44,6 +69,28 @@ impl DependencyGraph {
         self.read_deps.entry(function).or_default().push(state_var);
     }
 
+    /// Add a state dependency: state_var is derived from dependency.
+    pub fn add_state_dependency(&mut self, state_var: String, dependency: String) {
+        self.state_deps
+            .entry(state_var)
+            .or_default()
+            .push(dependency);
+    }
+
+    /// State vars derived, directly or through other state vars, from `var`.
+    pub fn variables_transitively_affected_by(&self, var: &str) -> BTreeSet<String> {
+        let mut affected = BTreeSet::new();
+        let mut queue = vec![var.to_string()];
+        while let Some(current) = queue.pop() {
+            for (dependent, dependencies) in &self.state_deps {
+                if dependencies.contains(&current) && affected.insert(dependent.clone()) {
+                    queue.push(dependent.clone());
+                }
+            }
+        }
+        affected
+    }
+
     /// Get all transitive mutations caused by a function.
     pub fn transitive_mutations(&self, func: &str) -> Vec<String> {
         let mut visited = std::collections::BTreeSet::new();
@@ -101,71 +148,45 @@ impl ExpressionContext {
 
     /// Validate that an expression only references available identifiers.
     pub fn validate_expression(&self, expr: &Expression) -> Result<(), String> {
-        match expr {
-            Expression::Boolean(_) | Expression::Int(_) => Ok(()),
-            Expression::Var(name) => {
-                if self.available_vars.contains_key(name) {
-                    Ok(())
-                } else {
-                    Err(format!("Undefined variable: {}", name))
-                }
-            }
-            Expression::LayerVar { layer: _, var } => {
-                if self.available_vars.contains_key(var) {
-                    Ok(())
-                } else {
-                    Err(format!("Undefined layer variable: {}", var))
-                }
-            }
-            Expression::PhaseQualifiedVar {
-                phase: _,
-                layer: _,
-                var,
-            } => {
-                if self.available_vars.contains_key(var) {
-                    Ok(())
-                } else {
-                    Err(format!("Undefined phase-qualified variable: {}", var))
-                }
-            }
-            Expression::PhaseConstraint {
-                phase: _,
-                constraint,
-            } => self.validate_expression(constraint),
-            Expression::CrossPhaseRelation {
-                phase1: _,
-                expr1,
-                phase2: _,
-                expr2,
-                op: _,
-            } => {
-                self.validate_expression(expr1)?;
-                self.validate_expression(expr2)
-            }
-            Expression::BinaryOp { left, right, .. } => {
-                self.validate_expression(left)?;
-                self.validate_expression(right)
-            }
-            Expression::Logical { left, right, .. } => {
-                self.validate_expression(left)?;
-                self.validate_expression(right)
-            }
-            Expression::Not(e) => self.validate_expression(e),
-            Expression::FunctionCall { name, args } => {
-                if !self.available_functions.contains_key(name) {
-                    return Err(format!("Undefined function: {}", name));
-                }
-                for arg in args {
-                    self.validate_expression(arg)?;
-                }
-                Ok(())
-            }
-            Expression::Tuple(exprs) => {
-                for e in exprs {
-                    self.validate_expression(e)?;
-                }
-                Ok(())
-            }
+        ContextValidator { context: self }.visit_expression(expr)
+    }
+}
+
+/// Checks identifiers against an `ExpressionContext`.
+struct ContextValidator<'a> {
+    context: &'a ExpressionContext,
+}
+
+impl ContextValidator<'_> {
+    fn require_var(&self, var: &str, kind: &str) -> Result<(), String> {
+        if self.context.available_vars.contains_key(var) {
+            Ok(())
+        } else {
+            Err(format!("Undefined {}: {}", kind, var))
+        }
+    }
+}
+
+impl ExpressionVisitor for ContextValidator<'_> {
+    type Error = String;
+
+    fn visit_var(&mut self, name: &str) -> Result<(), String> {
+        self.require_var(name, "variable")
+    }
+
+    fn visit_layer_var(&mut self, _layer: &str, var: &str) -> Result<(), String> {
+        self.require_var(var, "layer variable")
+    }
+
+    fn visit_phase_var(&mut self, _phase: &str, _layer: &str, var: &str) -> Result<(), String> {
+        self.require_var(var, "phase-qualified variable")
+    }
+
+    fn visit_function_call(&mut self, name: &str, _args: &[Expression]) -> Result<(), String> {
+        if self.context.available_functions.contains_key(name) {
+            Ok(())
+        } else {
+            Err(format!("Undefined function: {}", name))
         }
     }
 }
@@ -175,3 +196,26 @@ impl Default for ExpressionContext {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variables_transitively_affected_by() {
+        let mut graph = DependencyGraph::new();
+        graph.add_state_dependency("sharePrice".to_string(), "totalAssets".to_string());
+        graph.add_state_dependency("cap".to_string(), "sharePrice".to_string());
+        graph.add_state_dependency("fee".to_string(), "rate".to_string());
+
+        assert_eq!(
+            graph.variables_transitively_affected_by("totalAssets"),
+            BTreeSet::from(["cap".to_string(), "sharePrice".to_string()])
+        );
+        assert_eq!(
+            graph.variables_transitively_affected_by("sharePrice"),
+            BTreeSet::from(["cap".to_string()])
+        );
+        assert!(graph.variables_transitively_affected_by("cap").is_empty());
+    }
+}
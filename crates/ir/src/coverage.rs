@@ -0,0 +1,142 @@
+//! Invariant coverage of mutated program state.
+
+use invar_core::model::{Invariant, ProgramModel};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How much of the state mutated by entry points is referenced by invariants.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// State variables mutated by at least one entry point.
+    pub mutated: BTreeSet<String>,
+
+    /// Mutated variables referenced by at least one invariant.
+    pub covered: BTreeSet<String>,
+
+    /// Mutated variables no invariant references.
+    pub uncovered: BTreeSet<String>,
+
+    /// Entry point → mutated variables no invariant references.
+    pub unguarded: BTreeMap<String, BTreeSet<String>>,
+
+    /// `covered / mutated`, rounded down; 100 when nothing is mutated.
+    pub coverage_percent: u8,
+}
+
+/// Compute invariant coverage of the state mutated by `program`'s entry points.
+///
+/// A qualified reference such as `account::balance` covers the state
+/// variable `balance`.
+pub fn compute_coverage(program: &ProgramModel, invariants: &[Invariant]) -> CoverageReport {
+    let referenced: BTreeSet<String> = invariants
+        .iter()
+        .flat_map(|inv| inv.expression.variables())
+        .map(|name| name.rsplit("::").next().unwrap_or(&name).to_string())
+        .collect();
+
+    let mut report = CoverageReport::default();
+    for (function, mutates) in &program.mutation_graph {
+        let is_entry = program
+            .functions
+            .get(function)
+            .is_some_and(|func| func.is_entry_point);
+        if !is_entry {
+            continue;
+        }
+
+        report.mutated.extend(mutates.iter().cloned());
+        let unguarded: BTreeSet<String> = mutates
+            .iter()
+            .filter(|var| !referenced.contains(*var))
+            .cloned()
+            .collect();
+        if !unguarded.is_empty() {
+            report.unguarded.insert(function.clone(), unguarded);
+        }
+    }
+
+    let (covered, uncovered) = report
+        .mutated
+        .iter()
+        .cloned()
+        .partition(|var| referenced.contains(var));
+    report.covered = covered;
+    report.uncovered = uncovered;
+    report.coverage_percent = if report.mutated.is_empty() {
+        100
+    } else {
+        (report.covered.len() * 100 / report.mutated.len()) as u8
+    };
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use invar_core::model::{BinaryOp, Expression, FunctionModel, OverflowProtection};
+
+    fn function(name: &str, mutates: &[&str], is_entry_point: bool) -> FunctionModel {
+        FunctionModel {
+            name: name.to_string(),
+            parameters: Vec::new(),
+            return_type: None,
+            mutates: mutates.iter().map(|var| var.to_string()).collect(),
+            reads: BTreeSet::new(),
+            is_entry_point,
+            is_pure: false,
+            overflow_protection: OverflowProtection::Unknown,
+            span: None,
+            modifiers: Vec::new(),
+            uses_low_level: false,
+            pre_conditions: Vec::new(),
+            post_conditions: Vec::new(),
+        }
+    }
+
+    fn non_negative(var: Expression) -> Expression {
+        Expression::BinaryOp {
+            left: Box::new(var),
+            op: BinaryOp::Gte,
+            right: Box::new(Expression::Int(0)),
+        }
+    }
+
+    #[test]
+    fn test_two_of_three_mutated_vars_covered() {
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "lib.rs".to_string(),
+        );
+        program.add_function(function("deposit", &["balance", "total_supply"], true));
+        program.add_function(function("set_fee", &["fee"], true));
+        program.add_function(function("helper", &["scratch"], false));
+
+        let invariants = vec![
+            Invariant::new(
+                "Balance",
+                non_negative(Expression::Var("balance".to_string())),
+            ),
+            Invariant::new(
+                "Supply",
+                non_negative(Expression::LayerVar {
+                    layer: "protocol".to_string(),
+                    var: "total_supply".to_string(),
+                }),
+            ),
+        ];
+
+        let report = compute_coverage(&program, &invariants);
+        assert_eq!(report.coverage_percent, 66);
+        assert_eq!(report.mutated.len(), 3);
+        assert_eq!(report.uncovered.iter().collect::<Vec<_>>(), vec!["fee"]);
+        assert_eq!(report.unguarded.keys().collect::<Vec<_>>(), vec!["set_fee"]);
+    }
+
+    #[test]
+    fn test_no_mutations_is_fully_covered() {
+        let program =
+            ProgramModel::new("empty".to_string(), "evm".to_string(), "A.sol".to_string());
+        assert_eq!(compute_coverage(&program, &[]).coverage_percent, 100);
+    }
+}
@@ -14,6 +14,8 @@ pub use invar_core::{InvarError, Result};
 
 pub mod analyzer_result;
 pub mod ast;
+pub mod coverage;
 
-pub use analyzer_result::AnalysisContext;
+pub use analyzer_result::{AnalysisCache, AnalysisContext, DEFAULT_CACHE_DIR};
 pub use ast::DependencyGraph;
+pub use coverage::{compute_coverage, CoverageReport};
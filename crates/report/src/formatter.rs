@@ -1,6 +1,8 @@
 //! Report formatting (JSON, Markdown, CLI).
 
 use super::Report;
+use invar_core::{IssueSeverity, SecurityIssue, SecurityReport, SimulationReport};
+use std::collections::BTreeMap;
 
 /// Formats reports in various output formats.
 pub struct ReportFormatter;
@@ -12,8 +14,11 @@ impl ReportFormatter {
     }
 
     /// Format as Markdown.
+    ///
+    /// A merged report gets one section per program, each with its own
+    /// summary, invariants and findings.
     pub fn to_markdown(report: &Report) -> String {
-        format!(
+        let mut markdown = format!(
             "# {}\n\n**Generated:** {}\n**Program:** {}\n\n## Summary\n- Invariants Checked: {}\n- Violations: {}\n- Coverage: {}%\n",
             report.title,
             report.generated_at,
@@ -21,7 +26,127 @@ impl ReportFormatter {
             report.invariants_checked,
             report.violations_found,
             report.coverage_percent
-        )
+        );
+        if report.sections.is_empty() {
+            markdown_cross_chain(&mut markdown, report);
+            markdown_details(&mut markdown, report, 2);
+            return markdown;
+        }
+
+        markdown.push_str(&format!("- Risk Score: {}/100\n", report.risk_score));
+        if !report.warnings.is_empty() {
+            markdown.push_str("\n## Warnings\n");
+            for warning in &report.warnings {
+                markdown.push_str(&format!("- {}\n", warning));
+            }
+        }
+        markdown_cross_chain(&mut markdown, report);
+        for section in &report.sections {
+            markdown.push_str(&format!(
+                "\n## {}\n- Invariants Checked: {}\n- Violations: {}\n- Coverage: {}%\n",
+                section.namespace(),
+                section.invariants_checked,
+                section.violations_found,
+                section.coverage_percent
+            ));
+            markdown_details(&mut markdown, section, 3);
+        }
+        markdown
+    }
+
+    /// Format as GitHub Actions workflow commands.
+    ///
+    /// Each issue becomes one annotation: `::error` for critical and high
+    /// issues, `::warning` for medium and low ones.
+    pub fn to_github_actions(report: &Report) -> String {
+        let security_reports = report
+            .security_reports
+            .iter()
+            .chain(report.sections.iter().flat_map(|s| &s.security_reports))
+            .cloned()
+            .collect();
+        let merged = SecurityReport::merge(security_reports);
+        let issues = merged
+            .critical_issues
+            .iter()
+            .chain(&merged.high_issues)
+            .chain(&merged.medium_issues)
+            .chain(&merged.low_issues);
+
+        let mut output = String::new();
+        for issue in issues {
+            let command = match issue.severity {
+                IssueSeverity::Critical | IssueSeverity::High => "error",
+                IssueSeverity::Medium | IssueSeverity::Low => "warning",
+            };
+            let location = &issue.location;
+            let column = location
+                .column
+                .map(|column| format!(",col={}", column))
+                .unwrap_or_default();
+            output.push_str(&format!(
+                "::{} file={},line={}{}::{}\n",
+                command,
+                escape_property(&location.file.display().to_string()),
+                location.line,
+                column,
+                escape_data(&format!("{}: {}", issue.attack_pattern, issue.description))
+            ));
+        }
+        output
+    }
+
+    /// Format a simulation run as Markdown, one section per violation with
+    /// the calls that led to it and the state it left behind.
+    pub fn simulation_to_markdown(report: &SimulationReport) -> String {
+        let mut markdown = format!(
+            "# Simulation Report\n\n- Seed: {}\n- Violations: {}\n- Coverage: {:.1}%\n",
+            report.seed, report.violations, report.coverage
+        );
+        if !report.invariant_stats.is_empty() {
+            markdown.push_str("\n## Invariant Statistics\n\n| Invariant | Evaluations | Failures | Errors | Time | Observed range |\n|---|---|---|---|---|---|\n");
+            for (name, stats) in &report.invariant_stats {
+                let range = match (&stats.min_observed, &stats.max_observed) {
+                    (Some(min), Some(max)) => format!("{}..={}", min, max),
+                    _ => "-".to_string(),
+                };
+                markdown.push_str(&format!(
+                    "| `{}` | {} | {} | {} | {:?} | {} |\n",
+                    name, stats.evaluations, stats.failures, stats.errors, stats.total_time, range
+                ));
+            }
+        }
+        if !report.skipped.is_empty() {
+            markdown.push_str("\n## Skipped Invariants\n\n");
+            for (name, reason) in &report.skipped {
+                markdown.push_str(&format!("- `{}`: skipped ({})\n", name, reason));
+            }
+        }
+        for trace in &report.traces {
+            markdown.push_str(&format!(
+                "\n## `{}` (iteration {}, seed {})\n",
+                trace.invariant, trace.iteration, trace.seed
+            ));
+            if let Some(explanation) = &trace.explanation {
+                markdown.push_str(&format!(
+                    "\nFailed at `{}` => {}\n",
+                    explanation.expression, explanation.value
+                ));
+            }
+            if !trace.call_sequence.is_empty() {
+                markdown.push_str("\n### Calls\n");
+                for (index, call) in trace.call_sequence.iter().enumerate() {
+                    markdown.push_str(&format!("{}. `{}`\n", index + 1, call));
+                }
+            }
+            if !trace.state.is_empty() {
+                markdown.push_str("\n### State\n");
+                for (name, value) in &trace.state {
+                    markdown.push_str(&format!("- `{}` = {}\n", name, value));
+                }
+            }
+        }
+        markdown
     }
 
     /// Format for CLI table.
@@ -32,3 +157,314 @@ impl ReportFormatter {
         )
     }
 }
+
+/// Cross-chain invariants of `report` and the programs each one links,
+/// flagging those with variables no program declares.
+fn markdown_cross_chain(markdown: &mut String, report: &Report) {
+    if report.cross_chain.is_empty() {
+        return;
+    }
+    markdown.push_str("\n## Cross-Chain Invariants\n");
+    for link in &report.cross_chain {
+        if link.is_linked() {
+            let programs: Vec<String> = link
+                .programs
+                .iter()
+                .map(|(namespace, program)| format!("`{}` → {}", namespace, program))
+                .collect();
+            markdown.push_str(&format!(
+                "- ✓ `{}`: {}\n",
+                link.invariant,
+                programs.join(", ")
+            ));
+        } else {
+            markdown.push_str(&format!(
+                "- ✗ `{}`: no analyzed program declares {}\n",
+                link.invariant,
+                link.unresolved.join(", ")
+            ));
+        }
+    }
+}
+
+/// Uncovered state, invariants and findings of `report`, with top-level
+/// headings at `level`.
+fn markdown_details(markdown: &mut String, report: &Report, level: usize) {
+    let h = |depth: usize| "#".repeat(level + depth);
+    if let Some(coverage) = report.coverage.as_ref().filter(|c| !c.uncovered.is_empty()) {
+        markdown.push_str(&format!("\n{} Uncovered State\n", h(0)));
+        for var in &coverage.uncovered {
+            markdown.push_str(&format!("- `{}`\n", var));
+        }
+    }
+
+    if !report.invariant_hashes.is_empty() {
+        markdown.push_str(&format!("\n{} Invariants\n", h(0)));
+        for (name, hash) in &report.invariant_hashes {
+            markdown.push_str(&format!("- `{}` ({})\n", name, hash));
+        }
+    }
+
+    let merged = SecurityReport::merge(report.security_reports.clone());
+    if merged.issue_count() > 0 {
+        markdown.push_str(&format!("\n{} Findings\n", h(0)));
+        let issues = merged
+            .critical_issues
+            .iter()
+            .chain(&merged.high_issues)
+            .chain(&merged.medium_issues)
+            .chain(&merged.low_issues);
+
+        // Group by containing function; unattributed issues come first
+        let mut by_function: BTreeMap<Option<&str>, Vec<&SecurityIssue>> = BTreeMap::new();
+        for issue in issues {
+            by_function
+                .entry(issue.function.as_deref())
+                .or_default()
+                .push(issue);
+        }
+
+        for (function, issues) in by_function {
+            match function {
+                Some(name) => markdown.push_str(&format!("\n{} `{}`\n", h(1), name)),
+                None => markdown.push_str(&format!("\n{} Outside any function\n", h(1))),
+            }
+            for issue in issues {
+                let location = &issue.location;
+                markdown.push_str(&format!(
+                    "\n{} [{}] {} at `{}`\n\n```\n{}\n```\n",
+                    h(2),
+                    issue.severity,
+                    issue.attack_pattern,
+                    location,
+                    location.snippet
+                ));
+            }
+        }
+    }
+}
+
+/// Escape a workflow command message.
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a workflow command property value.
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use invar_core::{Invariant, Location};
+    use std::path::PathBuf;
+
+    fn issue(file: &str, line: u32, severity: IssueSeverity) -> SecurityIssue {
+        SecurityIssue {
+            attack_pattern: "Reentrancy".to_string(),
+            location: Location {
+                file: PathBuf::from(file),
+                line,
+                column: None,
+                snippet: String::new(),
+            },
+            description: "State updated after external call".to_string(),
+            suggested_fix: String::new(),
+            severity,
+            function: None,
+            introduced_at: None,
+        }
+    }
+
+    #[test]
+    fn test_github_actions_annotations() {
+        let security = SecurityReport {
+            critical_issues: vec![issue("src/lib.rs", 10, IssueSeverity::Critical)],
+            high_issues: Vec::new(),
+            medium_issues: Vec::new(),
+            low_issues: vec![issue("src/vault.rs", 3, IssueSeverity::Low)],
+            passed: false,
+            risk_score: 50,
+        };
+        let report = Report::from_security_reports(
+            "Invar Security Report".to_string(),
+            "vault".to_string(),
+            vec![security],
+        );
+
+        let output = ReportFormatter::to_github_actions(&report);
+        assert!(output.starts_with("::error file=src/lib.rs,line=10::"));
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("::warning file=src/vault.rs,line=3::Reentrancy: "));
+    }
+
+    #[test]
+    fn test_simulation_markdown_lists_state() {
+        use invar_core::{Evaluation, InvariantStats, Value, ViolationTrace};
+
+        let mut report = SimulationReport::from_violation_traces(&[ViolationTrace {
+            invariant: "solvent".to_string(),
+            iteration: 7,
+            seed: 42,
+            state: BTreeMap::from([
+                ("total_assets".to_string(), Value::U64(90)),
+                ("total_debt".to_string(), Value::U64(100)),
+            ]),
+            call_sequence: vec!["deposit(90)".to_string(), "borrow(100)".to_string()],
+            explanation: Some(Evaluation {
+                expression: "(total_assets >= total_debt)".to_string(),
+                value: Value::Bool(false),
+            }),
+        }]);
+
+        report.invariant_stats.insert(
+            "solvent".to_string(),
+            InvariantStats {
+                evaluations: 100,
+                failures: 3,
+                min_observed: Some(Value::U64(0)),
+                max_observed: Some(Value::U64(90)),
+                ..InvariantStats::default()
+            },
+        );
+
+        let markdown = ReportFormatter::simulation_to_markdown(&report);
+        assert!(markdown.contains("| `solvent` | 100 | 3 | 0 | 0ns | 0..=90 |\n"));
+        assert!(markdown.contains("## `solvent` (iteration 7, seed 42)"));
+        assert!(markdown.contains("Failed at `(total_assets >= total_debt)` => false"));
+        assert!(markdown.contains("1. `deposit(90)`\n2. `borrow(100)`\n"));
+        assert!(markdown.contains("### State\n- `total_assets` = 90\n- `total_debt` = 100\n"));
+
+        report.skipped.insert(
+            "paused_guard".to_string(),
+            invar_core::SKIPPED_DISABLED.to_string(),
+        );
+        let markdown = ReportFormatter::simulation_to_markdown(&report);
+        assert!(
+            markdown.contains("## Skipped Invariants\n\n- `paused_guard`: skipped (disabled)\n")
+        );
+    }
+
+    #[test]
+    fn test_merged_markdown_has_a_section_per_program() {
+        use invar_core::model::Expression;
+
+        // Simulation results for the Solana program
+        let mut solana = Report::new("Simulation".to_string(), "vault".to_string());
+        solana.chain = Some("solana".to_string());
+        solana.record_invariants(&[
+            Invariant::new("solvent", Expression::Boolean(true)),
+            Invariant::new("fee_bound", Expression::Boolean(true)),
+        ]);
+        solana.violations_found = 1;
+        solana.coverage_percent = 80;
+
+        // Security findings for its EVM bridge
+        let security = SecurityReport {
+            critical_issues: Vec::new(),
+            high_issues: vec![issue("src/Bridge.sol", 7, IssueSeverity::High)],
+            medium_issues: Vec::new(),
+            low_issues: vec![issue("src/Bridge.sol", 9, IssueSeverity::Low)],
+            passed: false,
+            risk_score: 35,
+        };
+        let mut evm = Report::from_security_reports(
+            "Security".to_string(),
+            "Bridge".to_string(),
+            vec![security],
+        );
+        evm.chain = Some("evm".to_string());
+        evm.record_invariants(&[Invariant::new("solvent", Expression::Boolean(true))]);
+        evm.coverage_percent = 60;
+
+        let merged = Report::merge(vec![solana, evm]);
+        assert_eq!(merged.program, "vault/solana, Bridge/evm");
+        assert_eq!(merged.invariants_checked, 3);
+        assert_eq!(merged.violations_found, 3);
+        assert_eq!(merged.coverage_percent, 70);
+        assert_eq!(merged.risk_score, 35);
+        assert!(merged
+            .invariant_hashes
+            .contains_key("vault/solana::fee_bound"));
+        assert!(merged.invariant_hashes.contains_key("Bridge/evm::solvent"));
+        assert_eq!(merged.warnings.len(), 1);
+        assert!(merged.warnings[0].contains("`solvent`"));
+
+        let markdown = ReportFormatter::to_markdown(&merged);
+        assert!(markdown.contains("- Invariants Checked: 3\n- Violations: 3\n"));
+        assert!(markdown.contains("\n## vault/solana\n- Invariants Checked: 2\n- Violations: 1\n"));
+        assert!(markdown.contains("\n## Bridge/evm\n- Invariants Checked: 1\n- Violations: 2\n"));
+        assert!(markdown.contains("\n### Findings\n"));
+        assert!(markdown.contains("#### Outside any function"));
+    }
+
+    #[test]
+    fn test_cross_chain_invariants_get_their_own_section() {
+        use invar_core::model::{BinaryOp, Expression};
+        use invar_core::{ProgramModel, StateVar};
+
+        let program = |name: &str, chain: &str, var: &str| {
+            let mut program =
+                ProgramModel::new(name.to_string(), chain.to_string(), format!("{}.src", name));
+            program.add_state_var(StateVar {
+                name: var.to_string(),
+                type_name: "u64".to_string(),
+                is_mutable: true,
+                visibility: None,
+                abilities: Default::default(),
+                depends_on: Vec::new(),
+            });
+            program
+        };
+        let layer_var = |layer: &str, var: &str| {
+            Box::new(Expression::LayerVar {
+                layer: layer.to_string(),
+                var: var.to_string(),
+            })
+        };
+        let invariants = [
+            Invariant::new(
+                "lock_backs_mint",
+                Expression::BinaryOp {
+                    left: layer_var("l1", "locked"),
+                    op: BinaryOp::Gte,
+                    right: layer_var("l2", "minted"),
+                },
+            ),
+            Invariant::new(
+                "burn_backs_unlock",
+                Expression::BinaryOp {
+                    left: layer_var("l2", "burned"),
+                    op: BinaryOp::Gte,
+                    right: layer_var("l1", "unlocked"),
+                },
+            ),
+            // Single-chain invariants are not linked
+            Invariant::new("solvent", Expression::Boolean(true)),
+        ];
+        let programs = [
+            program("l1", "evm", "locked"),
+            program("l2", "solana", "minted"),
+        ];
+
+        let mut report = Report::new("Cross-Chain".to_string(), "l1, l2".to_string());
+        report.record_cross_chain(&invariants, &programs);
+        assert_eq!(report.cross_chain.len(), 2);
+        assert_eq!(report.violations_found, 1);
+
+        let merged = Report::merge(vec![report]);
+        assert_eq!(merged.cross_chain.len(), 2);
+        let markdown = ReportFormatter::to_markdown(&merged);
+        assert!(markdown.contains(
+            "\n## Cross-Chain Invariants\n- ✓ `lock_backs_mint`: `l1` → l1/evm, `l2` → l2/solana\n"
+        ));
+        assert!(markdown.contains(
+            "- ✗ `burn_backs_unlock`: no analyzed program declares l1::unlocked, l2::burned\n"
+        ));
+    }
+}
@@ -1,6 +1,10 @@
 //! Report data structures.
 
+use invar_core::cross_chain::is_cross_chain;
+use invar_core::{CrossChainLink, Invariant, ProgramModel, SecurityReport};
+use invar_ir::CoverageReport;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 
 /// A complete analysis report.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,6 +18,10 @@ pub struct Report {
     /// Program analyzed.
     pub program: String,
 
+    /// Chain the program targets, when known.
+    #[serde(default)]
+    pub chain: Option<String>,
+
     /// Total invariants checked.
     pub invariants_checked: usize,
 
@@ -31,6 +39,34 @@ pub struct Report {
 
     /// Severity breakdown.
     pub severity_breakdown: SeverityBreakdown,
+
+    /// Highest risk score of the security reports (0-100).
+    #[serde(default)]
+    pub risk_score: u32,
+
+    /// Per-file security reports this report was built from.
+    #[serde(default)]
+    pub security_reports: Vec<SecurityReport>,
+
+    /// Content hash of each checked invariant, keyed by name.
+    #[serde(default)]
+    pub invariant_hashes: BTreeMap<String, String>,
+
+    /// Invariant coverage of mutated state, when computed.
+    #[serde(default)]
+    pub coverage: Option<CoverageReport>,
+
+    /// Per-program reports combined by [`Report::merge`].
+    #[serde(default)]
+    pub sections: Vec<Report>,
+
+    /// Problems noticed while merging, such as duplicate invariant names.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+
+    /// Invariants spanning several programs, and the programs they link.
+    #[serde(default)]
+    pub cross_chain: Vec<CrossChainLink>,
 }
 
 /// Breakdown by severity.
@@ -53,12 +89,158 @@ impl Report {
             title,
             generated_at: chrono::Utc::now().to_rfc3339(),
             program,
+            chain: None,
             invariants_checked: 0,
             violations_found: 0,
             coverage_percent: 0,
             protected_functions: Vec::new(),
             unprotected_functions: Vec::new(),
             severity_breakdown: SeverityBreakdown::default(),
+            risk_score: 0,
+            security_reports: Vec::new(),
+            invariant_hashes: BTreeMap::new(),
+            coverage: None,
+            sections: Vec::new(),
+            warnings: Vec::new(),
+            cross_chain: Vec::new(),
+        }
+    }
+
+    /// `program/chain`, or just the program when the chain is unknown.
+    pub fn namespace(&self) -> String {
+        match &self.chain {
+            Some(chain) => format!("{}/{}", self.program, chain),
+            None => self.program.clone(),
+        }
+    }
+
+    /// Record invariant coverage, updating `coverage_percent` and the
+    /// protected/unprotected function lists.
+    pub fn record_coverage(&mut self, coverage: CoverageReport, program: &ProgramModel) {
+        self.coverage_percent = coverage.coverage_percent;
+        self.unprotected_functions = coverage.unguarded.keys().cloned().collect();
+        self.protected_functions = program
+            .functions
+            .values()
+            .filter(|func| func.is_entry_point && !coverage.unguarded.contains_key(&func.name))
+            .map(|func| func.name.clone())
+            .collect();
+        self.coverage = Some(coverage);
+    }
+
+    /// Record the invariants checked, with their content hashes.
+    pub fn record_invariants(&mut self, invariants: &[Invariant]) {
+        self.invariants_checked = invariants.len();
+        self.invariant_hashes = invariants
+            .iter()
+            .map(|inv| (inv.name.clone(), inv.content_hash()))
+            .collect();
+    }
+
+    /// Link the cross-chain invariants among `invariants` to the `programs`
+    /// analyzed together, counting each one that does not resolve to a
+    /// program as a violation.
+    pub fn record_cross_chain(&mut self, invariants: &[Invariant], programs: &[ProgramModel]) {
+        for invariant in invariants.iter().filter(|inv| is_cross_chain(inv)) {
+            let link = CrossChainLink::new(invariant, programs);
+            if !link.is_linked() {
+                self.violations_found += 1;
+            }
+            self.cross_chain.push(link);
+        }
+    }
+
+    /// Build a report aggregating security findings from multiple files.
+    ///
+    /// The source reports are kept as-is; the severity breakdown and
+    /// violation count come from their de-duplicated merge.
+    pub fn from_security_reports(
+        title: String,
+        program: String,
+        reports: Vec<SecurityReport>,
+    ) -> Self {
+        let merged = SecurityReport::merge(reports.clone());
+        let mut report = Self::new(title, program);
+        report.risk_score = merged.risk_score;
+        report.violations_found = merged.issue_count();
+        report.severity_breakdown = SeverityBreakdown {
+            critical: merged.critical_issues.len(),
+            high: merged.high_issues.len(),
+            medium: merged.medium_issues.len(),
+            low: merged.low_issues.len(),
+        };
+        report.security_reports = reports;
+        report
+    }
+
+    /// Combine reports for several programs or chains into one.
+    ///
+    /// Each report becomes a section and its functions and invariants are
+    /// prefixed with its [`namespace`](Self::namespace). Counts are summed,
+    /// coverage averaged and the risk score is the highest. An invariant name
+    /// used by more than one report is kept in each and noted in `warnings`.
+    /// Cross-chain links of every report are kept.
+    pub fn merge(reports: Vec<Report>) -> Self {
+        let cross_chain: Vec<CrossChainLink> = reports
+            .iter()
+            .flat_map(|report| report.cross_chain.iter().cloned())
+            .collect();
+        // Merging merged reports flattens them
+        let sections: Vec<Report> = reports
+            .into_iter()
+            .flat_map(|report| {
+                if report.sections.is_empty() {
+                    vec![report]
+                } else {
+                    report.sections
+                }
+            })
+            .collect();
+
+        let namespaces: Vec<String> = sections.iter().map(Report::namespace).collect();
+        let mut merged = Self::new("Invar Combined Report".to_string(), namespaces.join(", "));
+        let mut owners: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (section, namespace) in sections.iter().zip(&namespaces) {
+            merged.invariants_checked += section.invariants_checked;
+            merged.violations_found += section.violations_found;
+            merged.risk_score = merged.risk_score.max(section.risk_score);
+            let breakdown = &mut merged.severity_breakdown;
+            breakdown.critical += section.severity_breakdown.critical;
+            breakdown.high += section.severity_breakdown.high;
+            breakdown.medium += section.severity_breakdown.medium;
+            breakdown.low += section.severity_breakdown.low;
+
+            let qualify = |name: &String| format!("{}::{}", namespace, name);
+            merged
+                .protected_functions
+                .extend(section.protected_functions.iter().map(qualify));
+            merged
+                .unprotected_functions
+                .extend(section.unprotected_functions.iter().map(qualify));
+            for (name, hash) in &section.invariant_hashes {
+                merged.invariant_hashes.insert(qualify(name), hash.clone());
+                owners.entry(name).or_default().push(namespace);
+            }
+        }
+        if !sections.is_empty() {
+            let total: usize = sections.iter().map(|s| s.coverage_percent as usize).sum();
+            merged.coverage_percent = (total / sections.len()) as u8;
         }
+        merged.warnings = owners
+            .into_iter()
+            .filter(|(_, namespaces)| namespaces.len() > 1)
+            .map(|(name, namespaces)| {
+                let distinct: BTreeSet<&str> = namespaces.into_iter().collect();
+                let distinct: Vec<&str> = distinct.into_iter().collect();
+                format!(
+                    "Invariant `{}` is defined in more than one report: {}",
+                    name,
+                    distinct.join(", ")
+                )
+            })
+            .collect();
+        merged.sections = sections;
+        merged.cross_chain = cross_chain;
+        merged
     }
 }
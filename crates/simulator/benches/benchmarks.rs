@@ -0,0 +1,267 @@
+//! Performance benchmarks for Invar core components.
+//!
+//! These benchmarks measure:
+//! - Parsing invariants from DSL source
+//! - Type checking and evaluating parsed expressions
+//! - Sandbox validation
+//! - The simulator's per-iteration loop
+//! - Scaling with expression depth and width
+//!
+//! `cargo bench --bench benchmarks -- --quick` runs every benchmark with a
+//! short measurement, which is enough for CI to catch regressions of an order
+//! of magnitude.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use invar_core::model::{Invariant, ProgramModel, StateVar};
+use invar_core::traits::Simulator;
+use invar_core::type_checker::TypeChecker;
+use invar_core::{DSLSandbox, Evaluator, ExecutionContext, Type, Value};
+use invar_dsl_parser::{parse_expression, parse_invariant};
+use invar_simulator::{SimulationConfig, SimulationEngine};
+use std::collections::BTreeSet;
+
+/// Nesting depths of the arithmetic in each conjunct.
+const DEPTHS: [usize; 4] = [1, 4, 16, 32];
+
+/// Numbers of conjuncts in an expression; the widest stays under the
+/// sandbox nesting limit and the type checker's complexity budget.
+const WIDTHS: [usize; 4] = [1, 4, 16, 48];
+
+/// Conjunct depth used when scaling width, and vice versa.
+const FIXED: usize = 4;
+
+/// Source of `width` conjuncts of the form `((x0 <= 1000 && x0 >= 0) && x0 >= 0)`,
+/// each nesting `depth` comparisons. Every comparison holds, so evaluation
+/// never short-circuits.
+fn expression_source(depth: usize, width: usize) -> String {
+    (0..width)
+        .map(|i| {
+            (1..depth).fold(format!("x{} <= 1000", i), |acc, _| {
+                format!("({} && x{} >= 0)", acc, i)
+            })
+        })
+        .collect::<Vec<_>>()
+        .join(" && ")
+}
+
+/// Expression-shape parameters: depth with a fixed width, then width with a
+/// fixed depth.
+fn shapes() -> impl Iterator<Item = (BenchmarkId, String)> {
+    let deep = DEPTHS.into_iter().map(|depth| {
+        (
+            BenchmarkId::new("depth", depth),
+            expression_source(depth, FIXED),
+        )
+    });
+    let wide = WIDTHS.into_iter().map(|width| {
+        (
+            BenchmarkId::new("width", width),
+            expression_source(FIXED, width),
+        )
+    });
+    deep.chain(wide)
+}
+
+fn bench_parser(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_invariant");
+    for (id, source) in shapes() {
+        let input = format!("invariant Bench {{ {} }}", source);
+        group.bench_with_input(id, &input, |b, input| {
+            b.iter(|| parse_invariant(black_box(input)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_type_checker(c: &mut Criterion) {
+    let mut checker = TypeChecker::new();
+    for i in 0..WIDTHS[WIDTHS.len() - 1] {
+        checker.register_state_var(format!("x{}", i), Type::U64);
+    }
+
+    let mut group = c.benchmark_group("type_check");
+    for (id, source) in shapes() {
+        let expr = parse_expression(&source).unwrap();
+        group.bench_with_input(id, &expr, |b, expr| {
+            b.iter(|| checker.check_expr(black_box(expr)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_evaluator(c: &mut Criterion) {
+    let mut ctx = ExecutionContext::new();
+    for i in 0..WIDTHS[WIDTHS.len() - 1] {
+        ctx.set_state(format!("x{}", i), Value::U64(i as u64));
+    }
+    let evaluator = Evaluator::new(ctx);
+
+    let mut group = c.benchmark_group("evaluate");
+    for (id, source) in shapes() {
+        let expr = parse_expression(&source).unwrap();
+        group.bench_with_input(id, &expr, |b, expr| {
+            b.iter(|| evaluator.evaluate(black_box(expr)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_sandbox(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sandbox_validate");
+    for (id, source) in shapes() {
+        let expr = parse_expression(&source).unwrap();
+        group.bench_with_input(id, &expr, |b, expr| {
+            b.iter(|| DSLSandbox::validate_expression(black_box(expr)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_simulator(c: &mut Criterion) {
+    let mut program = ProgramModel::new(
+        "bench".to_string(),
+        "solana".to_string(),
+        "bench.rs".to_string(),
+    );
+    let mut invariants = Vec::new();
+    for i in 0..FIXED {
+        program.add_state_var(StateVar {
+            name: format!("x{}", i),
+            type_name: "u64".to_string(),
+            is_mutable: true,
+            visibility: None,
+            abilities: BTreeSet::new(),
+            depends_on: Vec::new(),
+        });
+        invariants.push(Invariant::new(
+            &format!("bounded_{}", i),
+            parse_expression(&format!("x{} <= 1000", i)).unwrap(),
+        ));
+    }
+
+    let mut group = c.benchmark_group("simulate_iterations");
+    for iterations in [10, 100] {
+        for caching in [false, true] {
+            let engine = SimulationEngine::with_config(SimulationConfig {
+                max_iterations: iterations,
+                cache_evaluations: caching,
+                ..SimulationConfig::default()
+            });
+            let label = if caching { "cached" } else { "uncached" };
+            group.bench_with_input(BenchmarkId::new(label, iterations), &engine, |b, engine| {
+                b.iter(|| engine.simulate(black_box(&program), &invariants).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_result_cache(c: &mut Criterion) {
+    use invar_core::evaluator::{EvalResult, Evaluator, ExecutionContext, Value};
+    use invar_core::model::Expression;
+
+    fn sum(args: &[Value]) -> EvalResult<Value> {
+        let Value::Array(items) = &args[0] else {
+            return Ok(Value::U64(0));
+        };
+        let total = items
+            .iter()
+            .map(|item| match item {
+                Value::U64(n) => *n,
+                _ => 0,
+            })
+            .sum();
+        Ok(Value::U64(total))
+    }
+
+    let mut ctx = ExecutionContext::new();
+    ctx.set_state(
+        "balances".to_string(),
+        Value::Array((0..1000).map(Value::U64).collect()),
+    );
+    ctx.register_function("sum".to_string(), sum);
+    let calls: Vec<Expression> = (0..100)
+        .map(|_| Expression::FunctionCall {
+            name: "sum".to_string(),
+            args: vec![Expression::Var("balances".to_string())],
+        })
+        .collect();
+
+    // 100 identical calls: the cached run evaluates `sum` once
+    let mut evaluator = Evaluator::new(ctx);
+    let mut group = c.benchmark_group("eval_identical_function_calls");
+    for caching in [true, false] {
+        evaluator.set_caching(caching);
+        let label = if caching { "cached" } else { "uncached" };
+        group.bench_function(label, |b| {
+            b.iter(|| {
+                let results = evaluator.evaluate_batch(black_box(&calls));
+                black_box(results);
+            });
+        });
+        evaluator.clear_cache();
+    }
+    group.finish();
+}
+
+fn bench_parallel_build(c: &mut Criterion) {
+    use invar_core::{ChainAnalyzer, SecurityValidator};
+    use rayon::prelude::*;
+
+    // Generated fixtures: every third contract carries a reentrancy pattern
+    let dir = tempfile::tempdir().unwrap();
+    let files: Vec<_> = (0..64)
+        .map(|i| {
+            let body = if i % 3 == 0 {
+                "    function withdraw(uint256 amount) public {\n        payable(msg.sender).call{value: amount}(\"\");\n        balance -= amount;\n    }\n"
+            } else {
+                "    uint256 public totalSupply;\n"
+            };
+            let path = dir.path().join(format!("c{}.sol", i));
+            std::fs::write(&path, format!("contract C{} {{\n{}}}\n", i, body)).unwrap();
+            path
+        })
+        .collect();
+
+    // Mirrors the per-file `invar build` pipeline: read, validate, analyze
+    let validator = SecurityValidator::new();
+    let analyzer = invar_analyzer_evm::EvmAnalyzer;
+    let mut group = c.benchmark_group("build_jobs");
+    for jobs in [1, 4] {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build()
+            .unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(jobs), &jobs, |b, _| {
+            b.iter(|| {
+                let reports: Vec<_> = pool.install(|| {
+                    files
+                        .par_iter()
+                        .map(|path| {
+                            let code = std::fs::read_to_string(path).unwrap();
+                            let report = validator
+                                .validate_code(&code, &path.display().to_string(), "evm")
+                                .unwrap();
+                            let program = analyzer.analyze(path).unwrap();
+                            (report, program)
+                        })
+                        .collect()
+                });
+                black_box(reports);
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_parser,
+    bench_type_checker,
+    bench_evaluator,
+    bench_sandbox,
+    bench_simulator,
+    bench_result_cache,
+    bench_parallel_build
+);
+criterion_main!(benches);
@@ -0,0 +1,224 @@
+//! Persistent corpus of interesting simulation states.
+//!
+//! States that made an invariant fail for the first time, hit a boundary or
+//! came from a new order of calls are saved so later runs start from them
+//! instead of rediscovering them.
+//! Each entry is a JSON file named by the SHA-256 of its contents.
+
+use crate::values::StateGenerator;
+use invar_core::{InvarError, Result, Type, Value};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Conventional corpus location, relative to the project root.
+pub const DEFAULT_CORPUS_DIR: &str = ".invar/corpus";
+
+/// A saved simulation state and the calls that led to it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorpusEntry {
+    /// Variable values, keyed by name.
+    pub state: BTreeMap<String, Value>,
+
+    /// Calls executed in the iteration that produced the state.
+    #[serde(default)]
+    pub call_sequence: Vec<String>,
+}
+
+impl CorpusEntry {
+    /// Hex SHA-256 of the entry's JSON form, used as its file name.
+    pub fn content_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let json = serde_json::to_string(self).expect("corpus entries always serialize");
+        Sha256::digest(json.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// The entry's state for the variables `vars`.
+    ///
+    /// Entries may predate changes to the program: variables that no longer
+    /// exist or changed type are dropped, and missing ones are drawn from
+    /// `states`.
+    pub fn restore<R: Rng>(
+        &self,
+        vars: &BTreeMap<String, Type>,
+        states: &mut StateGenerator<R>,
+    ) -> BTreeMap<String, Value> {
+        vars.iter()
+            .map(|(name, ty)| {
+                let value = match self.state.get(name) {
+                    Some(value) if value.get_type() == *ty => value.clone(),
+                    _ => states.value(name, ty),
+                };
+                (name.clone(), value)
+            })
+            .collect()
+    }
+
+    /// A variant of the entry for the variables `vars`: one variable moved
+    /// towards a boundary, and two calls swapped.
+    pub fn mutate<R: Rng, G: Rng>(
+        &self,
+        vars: &BTreeMap<String, Type>,
+        states: &mut StateGenerator<G>,
+        rng: &mut R,
+    ) -> CorpusEntry {
+        let mut state = self.restore(vars, states);
+        if !vars.is_empty() {
+            let (name, ty) = vars
+                .iter()
+                .nth(rng.gen_range(0..vars.len()))
+                .expect("index is within bounds");
+            let mutated = states.mutate(name, ty, &state[name]);
+            state.insert(name.clone(), mutated);
+        }
+
+        let mut call_sequence = self.call_sequence.clone();
+        if call_sequence.len() > 1 {
+            let i = rng.gen_range(0..call_sequence.len());
+            let j = rng.gen_range(0..call_sequence.len());
+            call_sequence.swap(i, j);
+        }
+        CorpusEntry {
+            state,
+            call_sequence,
+        }
+    }
+}
+
+/// The call of a trace step, without its arguments: `step_0` for
+/// `step_0_value_42`.
+pub(crate) fn call_name(step: &str) -> &str {
+    step.split_once("_value_").map_or(step, |(name, _)| name)
+}
+
+/// Call-sequence prefixes seen so far.
+///
+/// Calls are compared by [`call_name`], so a sequence is new when it orders
+/// calls in a way not seen before, not whenever its argument values differ.
+#[derive(Debug, Default)]
+pub(crate) struct CallPrefixes(BTreeSet<Vec<String>>);
+
+impl CallPrefixes {
+    /// Record every prefix of `calls`. Returns whether any was new, that is
+    /// whether `calls` extends the prefixes seen so far.
+    pub(crate) fn insert(&mut self, calls: &[String]) -> bool {
+        let mut prefix = Vec::new();
+        let mut new = false;
+        for call in calls {
+            prefix.push(call_name(call).to_string());
+            new |= self.0.insert(prefix.clone());
+        }
+        new
+    }
+}
+
+/// Corpus entries stored in a directory.
+#[derive(Debug)]
+pub struct Corpus {
+    dir: PathBuf,
+    entries: Vec<CorpusEntry>,
+}
+
+impl Corpus {
+    /// Load the corpus in `dir`, creating the directory if needed.
+    ///
+    /// Entries are ordered by file name. Files that don't parse as entries
+    /// are skipped with a warning.
+    pub fn load(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<_>>()?;
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+        paths.sort();
+
+        let mut entries = Vec::new();
+        for path in paths {
+            let text = std::fs::read_to_string(&path)?;
+            match serde_json::from_str(&text) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!("Skipping corpus entry {}: {}", path.display(), e),
+            }
+        }
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            entries,
+        })
+    }
+
+    /// The loaded and added entries.
+    pub fn entries(&self) -> &[CorpusEntry] {
+        &self.entries
+    }
+
+    /// Save `entry` unless an identical one is already stored. Returns
+    /// whether it was new.
+    pub fn add(&mut self, entry: CorpusEntry) -> Result<bool> {
+        let path = self.dir.join(format!("{}.json", entry.content_hash()));
+        if path.exists() {
+            return Ok(false);
+        }
+        let json = serde_json::to_string_pretty(&entry)
+            .map_err(|e| InvarError::SimulationFailed(e.to_string()))?;
+        std::fs::write(path, json)?;
+        self.entries.push(entry);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_tolerates_stale_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("broken.json"), "{ not json").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let mut corpus = Corpus::load(dir.path()).unwrap();
+        let entry = CorpusEntry {
+            state: BTreeMap::from([
+                ("removed".to_string(), Value::U64(1)),
+                ("fee".to_string(), Value::Bool(true)),
+                ("supply".to_string(), Value::U64(7)),
+            ]),
+            call_sequence: vec!["deposit".to_string()],
+        };
+        assert!(corpus.add(entry.clone()).unwrap());
+        assert!(!corpus.add(entry.clone()).unwrap());
+
+        let reloaded = Corpus::load(dir.path()).unwrap();
+        assert_eq!(reloaded.entries().len(), 1);
+        assert_eq!(reloaded.entries()[0], entry);
+
+        let vars = BTreeMap::from([
+            ("fee".to_string(), Type::U64),
+            ("supply".to_string(), Type::U64),
+        ]);
+        let state = entry.restore(&vars, &mut StateGenerator::new(1));
+        assert_eq!(state.len(), 2);
+        assert!(matches!(state["fee"], Value::U64(_)));
+        assert_eq!(state["supply"], Value::U64(7));
+    }
+
+    #[test]
+    fn test_call_prefixes_ignore_arguments() {
+        let calls =
+            |steps: &[&str]| -> Vec<String> { steps.iter().map(|s| s.to_string()).collect() };
+        let mut prefixes = CallPrefixes::default();
+        assert!(prefixes.insert(&calls(&["step_0_value_1", "step_1_value_2"])));
+        // New argument values, same calls
+        assert!(!prefixes.insert(&calls(&["step_0_value_9", "step_1_value_8"])));
+        assert!(!prefixes.insert(&calls(&["step_0_value_3"])));
+        // Extends a seen prefix, or starts a new one
+        assert!(prefixes.insert(&calls(&["step_0", "step_1", "step_2"])));
+        assert!(prefixes.insert(&calls(&["step_1", "step_0"])));
+    }
+}
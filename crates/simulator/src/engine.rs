@@ -1,59 +1,186 @@
 //! Simulation engine.
 
-use invar_core::model::{Invariant, ProgramModel, SimulationReport};
+use crate::corpus::{CallPrefixes, Corpus, CorpusEntry};
+use crate::values::{StateGenerator, ValueRange};
+use invar_core::model::{
+    BinaryOp, Expression, Invariant, InvariantStats, LogicalOp, ProgramModel, SimulationReport,
+    ViolationTrace,
+};
 use invar_core::traits::Simulator;
-use invar_core::Result;
-use rand::SeedableRng;
+use invar_core::{BlockContext, Evaluator, ExecutionContext, Result, Type, Value};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::time::Instant;
 use tracing::info;
 
-/// Deterministic simulation engine for invariant testing.
-pub struct SimulationEngine {
+/// Tunable parameters for a simulation run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulationConfig {
     /// RNG seed for reproducibility.
     pub seed: u64,
+
+    /// Number of fuzz iterations to execute.
+    pub max_iterations: usize,
+
+    /// Number of steps in each execution trace.
+    pub trace_depth: usize,
+
+    /// Wall-clock budget for the run in milliseconds; 0 disables it.
+    pub timeout_ms: u64,
+
+    /// Stop early once this many violations have been recorded.
+    pub max_violations_before_abort: usize,
+
+    /// Directory of the corpus to replay, mutate and extend, if any.
+    pub corpus: Option<PathBuf>,
+
+    /// Reuse an invariant's previous result while its variables keep their
+    /// values.
+    pub cache_evaluations: bool,
+
+    /// Block the first iteration is checked at, read by `now()`,
+    /// `block_number()` and `chain_id()`.
+    pub start_block: BlockContext,
+
+    /// Seconds between consecutive blocks. Every trace step mines one block,
+    /// so iteration `i` is checked `i * trace_depth` blocks after `start_block`.
+    pub block_time: u64,
+}
+
+/// Share of iterations after the corpus replay that start from a mutated
+/// corpus entry rather than a fresh state.
+const MUTATION_FRACTION: f64 = 0.25;
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            max_iterations: 100,
+            trace_depth: 10,
+            timeout_ms: 0,
+            max_violations_before_abort: usize::MAX,
+            corpus: None,
+            cache_evaluations: true,
+            start_block: BlockContext {
+                timestamp: 0,
+                number: 0,
+                chain_id: 1,
+            },
+            block_time: 12,
+        }
+    }
+}
+
+/// Deterministic simulation engine for invariant testing.
+pub struct SimulationEngine {
+    /// Parameters of the run.
+    pub config: SimulationConfig,
+
+    /// Value ranges for state variables, keyed by name.
+    pub ranges: BTreeMap<String, ValueRange>,
 }
 
 impl SimulationEngine {
     /// Create a new simulation engine with a seed.
     pub fn new(seed: u64) -> Self {
-        Self { seed }
+        Self::with_config(SimulationConfig {
+            seed,
+            ..SimulationConfig::default()
+        })
+    }
+
+    /// Create a new simulation engine from a full configuration.
+    pub fn with_config(config: SimulationConfig) -> Self {
+        Self {
+            config,
+            ranges: BTreeMap::new(),
+        }
+    }
+
+    /// Keep generated state variables within `ranges`.
+    pub fn with_ranges(mut self, ranges: BTreeMap<String, ValueRange>) -> Self {
+        self.ranges = ranges;
+        self
     }
 }
 
 impl Default for SimulationEngine {
     fn default() -> Self {
-        Self { seed: 42 }
+        Self::with_config(SimulationConfig::default())
     }
 }
 
 impl Simulator for SimulationEngine {
     fn simulate(
         &self,
-        _program: &ProgramModel,
-        _invariants: &[Invariant],
+        program: &ProgramModel,
+        invariants: &[Invariant],
     ) -> Result<SimulationReport> {
         use rand::RngCore;
 
-        info!("Starting simulation with seed: {}", self.seed);
+        let config = &self.config;
+        info!("Starting simulation with seed: {}", config.seed);
+
+        // Post-conditions are checked alongside the invariants after every
+        // simulated execution
+        let checked: Vec<Invariant> = invariants
+            .iter()
+            .cloned()
+            .chain(postconditions(program))
+            .collect();
+        let invariants = checked.as_slice();
 
         // Initialize RNG with seed for deterministic fuzzing
-        let mut rng = rand::rngs::SmallRng::seed_from_u64(self.seed);
+        let mut rng = SmallRng::seed_from_u64(config.seed);
 
-        // Simulation configuration constants
-        /// Number of fuzz iterations to execute (100 provides good coverage)
-        const FUZZ_ITERATIONS: usize = 100;
-        /// Depth of each execution trace (10 steps per trace)
-        const TRACE_DEPTH: usize = 10;
         /// Probability threshold for simulating violations (10%)
         const VIOLATION_PROBABILITY_THRESHOLD: f64 = 0.1;
 
         let mut traces = Vec::new();
         let mut violations = 0;
 
+        // States for the invariant statistics come from their own stream, so
+        // the violation sampling below is unaffected by them
+        let mut states = StateGenerator::new(config.seed).with_ranges(self.ranges.clone());
+        let var_types = variable_types(program, invariants);
+        let mut invariant_stats: BTreeMap<String, InvariantStats> = invariants
+            .iter()
+            .map(|inv| (inv.name.clone(), InvariantStats::default()))
+            .collect();
+
+        let mut corpus = config.corpus.as_deref().map(Corpus::load).transpose()?;
+        let seeds = corpus
+            .as_ref()
+            .map_or_else(Vec::new, |c| c.entries().to_vec());
+        let mut mutation_rng = SmallRng::seed_from_u64(config.seed.wrapping_add(1));
+        let mut boundaries_hit = BTreeSet::new();
+        let mut prefixes = CallPrefixes::default();
+        for seed in &seeds {
+            prefixes.insert(&seed.call_sequence);
+        }
+        let mut evaluator = Evaluator::new(ExecutionContext::new());
+        evaluator.set_caching(config.cache_evaluations);
+
+        let started = Instant::now();
+        let mut iterations = 0;
+
         // Execute fuzzing iterations with the initialized RNG
-        for iteration in 0..FUZZ_ITERATIONS {
+        for iteration in 0..config.max_iterations {
+            if config.timeout_ms > 0 && started.elapsed().as_millis() >= config.timeout_ms as u128 {
+                info!("Simulation timed out after {} iterations", iteration);
+                break;
+            }
+            if violations >= config.max_violations_before_abort {
+                info!("Aborting simulation after {} violations", violations);
+                break;
+            }
+            iterations += 1;
+
             // Generate a random trace of execution steps
             let mut trace_steps = Vec::new();
-            for step in 0..TRACE_DEPTH {
+            for step in 0..config.trace_depth {
                 // Generate deterministic random values based on seed and iteration
                 let mut buf = [0u8; 4];
                 rng.fill_bytes(&mut buf);
@@ -61,10 +188,48 @@ impl Simulator for SimulationEngine {
                 trace_steps.push(format!("step_{}_value_{}", step, step_value));
             }
 
+            // Replay the corpus first, then start a share of the remaining
+            // iterations from mutated entries
+            let entry = if let Some(seed) = seeds.get(iteration) {
+                CorpusEntry {
+                    state: seed.restore(&var_types, &mut states),
+                    call_sequence: seed.call_sequence.clone(),
+                }
+            } else if !seeds.is_empty() && mutation_rng.gen_bool(MUTATION_FRACTION) {
+                let seed = &seeds[mutation_rng.gen_range(0..seeds.len())];
+                seed.mutate(&var_types, &mut states, &mut mutation_rng)
+            } else {
+                CorpusEntry {
+                    state: states.state(&var_types),
+                    call_sequence: trace_steps.clone(),
+                }
+            };
+            for (name, value) in &entry.state {
+                evaluator.set_state(name.clone(), value.clone());
+            }
+            let blocks = (iteration as u64).saturating_mul(config.trace_depth as u64);
+            evaluator.set_block(config.start_block.advanced(blocks, config.block_time));
+            let new_failure = record_evaluations(
+                invariants,
+                iteration as u64,
+                &mut evaluator,
+                &mut invariant_stats,
+            );
+            if let Some(corpus) = corpus.as_mut() {
+                let mut new_boundary = false;
+                for (name, value) in &entry.state {
+                    if states.is_boundary(name, &var_types[name], value) {
+                        new_boundary |= boundaries_hit.insert((name.clone(), value.to_string()));
+                    }
+                }
+                let new_prefix = prefixes.insert(&entry.call_sequence);
+                if new_failure || new_boundary || new_prefix {
+                    corpus.add(entry)?;
+                }
+            }
+
             // In a full implementation, would execute program with this trace
             // and check if any invariants are violated
-            let execution_trace = format!("Trace {}: {:?}", iteration, trace_steps);
-            traces.push(execution_trace);
 
             // Simulate invariant checking (would compare against actual results in real impl)
             let violation_trigger = {
@@ -72,21 +237,42 @@ impl Simulator for SimulationEngine {
                 rng.fill_bytes(&mut buf);
                 f64::from_le_bytes(buf)
             };
-            if violation_trigger < VIOLATION_PROBABILITY_THRESHOLD {
+            if violation_trigger < VIOLATION_PROBABILITY_THRESHOLD && !invariants.is_empty() {
                 violations += 1;
+                // Attribute the violation to one of the checked invariants
+                let violated = &invariants[rng.next_u32() as usize % invariants.len()];
+                // No program state is modelled yet, so there is none to record
+                traces.push(ViolationTrace {
+                    invariant: violated.name.clone(),
+                    iteration: iteration as u64,
+                    seed: config.seed,
+                    state: BTreeMap::new(),
+                    call_sequence: trace_steps,
+                    explanation: None,
+                });
             }
         }
 
         // Calculate coverage as percentage of iterations without violations
-        let coverage = ((FUZZ_ITERATIONS - violations) as f64 / FUZZ_ITERATIONS as f64) * 100.0;
+        let coverage = if iterations == 0 {
+            100.0
+        } else {
+            ((iterations - violations) as f64 / iterations as f64) * 100.0
+        };
 
-        info!("Simulation complete: {} violations found, {:.1}% coverage", violations, coverage);
+        info!(
+            "Simulation complete: {} violations found, {:.1}% coverage",
+            violations, coverage
+        );
 
         Ok(SimulationReport {
             violations,
             traces,
             coverage,
-            seed: self.seed,
+            seed: config.seed,
+            invariants_checked: invariants.iter().map(|inv| inv.name.clone()).collect(),
+            invariant_stats,
+            skipped: BTreeMap::new(),
         })
     }
 
@@ -94,3 +280,417 @@ impl Simulator for SimulationEngine {
         "generic"
     }
 }
+
+/// Post-conditions of each function as an invariant named
+/// `<function>::post`, required to hold whenever its pre-conditions do.
+fn postconditions(program: &ProgramModel) -> Vec<Invariant> {
+    let conjunction = |conditions: &[Expression]| {
+        conditions
+            .iter()
+            .cloned()
+            .reduce(|left, right| Expression::Logical {
+                left: Box::new(left),
+                op: LogicalOp::And,
+                right: Box::new(right),
+            })
+    };
+    program
+        .functions
+        .values()
+        .filter_map(|function| {
+            let post = conjunction(&function.post_conditions)?;
+            let expression = match conjunction(&function.pre_conditions) {
+                Some(pre) => Expression::Logical {
+                    left: Box::new(Expression::Not(Box::new(pre))),
+                    op: LogicalOp::Or,
+                    right: Box::new(post),
+                },
+                None => post,
+            };
+            Some(Invariant::new(
+                &format!("{}::post", function.name),
+                expression,
+            ))
+        })
+        .collect()
+}
+
+/// Types of the variables the invariants read: the declared type when the
+/// program names a DSL type, `u64` otherwise.
+fn variable_types(program: &ProgramModel, invariants: &[Invariant]) -> BTreeMap<String, Type> {
+    invariants
+        .iter()
+        .flat_map(|inv| inv.expression.variables())
+        .map(|name| {
+            let unqualified = name.rsplit("::").next().unwrap_or(&name);
+            let ty = program
+                .state_vars
+                .get(unqualified)
+                .and_then(|var| Type::from_name(&var.type_name.to_lowercase()))
+                .unwrap_or(Type::U64);
+            (name, ty)
+        })
+        .collect()
+}
+
+/// Evaluate every invariant once against the evaluator's state in
+/// `iteration`, updating its statistics. Returns whether an invariant failed
+/// for the first time.
+fn record_evaluations(
+    invariants: &[Invariant],
+    iteration: u64,
+    evaluator: &mut Evaluator,
+    stats: &mut BTreeMap<String, InvariantStats>,
+) -> bool {
+    let mut new_failure = false;
+    for inv in invariants {
+        let start = Instant::now();
+        let (result, cached) = evaluator.evaluate_cached(&inv.expression);
+        let elapsed = start.elapsed();
+
+        let entry = stats.entry(inv.name.clone()).or_default();
+        entry.evaluations += 1;
+        entry.total_time += elapsed;
+        if cached {
+            entry.cache_hits += 1;
+        }
+        match result {
+            Ok(Value::Bool(false)) => {
+                entry.failures += 1;
+                if entry.first_failure.is_none() {
+                    entry.first_failure = Some(iteration);
+                    new_failure = true;
+                }
+            }
+            Ok(_) => {}
+            Err(_) => entry.errors += 1,
+        }
+        let bounded = bounded_variable(&inv.expression)
+            .and_then(|name| evaluator.evaluate(&Expression::Var(name.to_string())).ok());
+        if let Some(value) = bounded {
+            entry.observe(&value);
+        }
+    }
+    new_failure
+}
+
+/// The variable of an invariant bounding one variable by a constant,
+/// such as `fee <= 100` or `0 < supply`.
+fn bounded_variable(expr: &Expression) -> Option<&str> {
+    let Expression::BinaryOp { left, op, right } = expr else {
+        return None;
+    };
+    if matches!(op, BinaryOp::Eq | BinaryOp::Neq) {
+        return None;
+    }
+    match (left.as_ref(), right.as_ref()) {
+        (Expression::Var(name), Expression::Int(_))
+        | (Expression::Int(_), Expression::Var(name)) => Some(name),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corpus::call_name;
+    use invar_core::model::{FunctionModel, StateVar};
+
+    fn bound(name: &str, var: &str, op: BinaryOp, limit: i128) -> Invariant {
+        Invariant::new(
+            name,
+            Expression::BinaryOp {
+                left: Box::new(Expression::Var(var.to_string())),
+                op,
+                right: Box::new(Expression::Int(limit)),
+            },
+        )
+    }
+
+    #[test]
+    fn test_invariant_stats() {
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "vault.rs".to_string(),
+        );
+        program.add_state_var(StateVar {
+            name: "fee".to_string(),
+            type_name: "u64".to_string(),
+            is_mutable: true,
+            visibility: None,
+            abilities: Default::default(),
+            depends_on: Vec::new(),
+        });
+        let invariants = vec![
+            bound("fee_cap", "fee", BinaryOp::Lte, 100),
+            Invariant::new("always", Expression::Boolean(true)),
+        ];
+
+        let engine = SimulationEngine::new(7);
+        let report = engine.simulate(&program, &invariants).unwrap();
+        assert_eq!(report.invariant_stats.len(), invariants.len());
+        for stats in report.invariant_stats.values() {
+            assert_eq!(stats.evaluations, engine.config.max_iterations as u64);
+        }
+
+        let fee_cap = &report.invariant_stats["fee_cap"];
+        assert!(fee_cap.failures > 0 && fee_cap.errors == 0);
+        assert_eq!(fee_cap.min_observed, Some(Value::U64(0)));
+        assert_eq!(fee_cap.max_observed, Some(Value::U64(u64::MAX)));
+        assert_eq!(report.invariant_stats["always"].failures, 0);
+        assert_eq!(report.invariant_stats["always"].min_observed, None);
+
+        // Everything but timing is reproducible
+        let rerun = engine.simulate(&program, &invariants).unwrap();
+        assert_eq!(rerun.invariant_stats, report.invariant_stats);
+        assert_eq!(rerun.traces, report.traces);
+    }
+
+    #[test]
+    fn test_postconditions_are_checked() {
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "vault.rs".to_string(),
+        );
+        program.add_function(FunctionModel {
+            name: "deposit".to_string(),
+            parameters: vec!["amount".to_string()],
+            return_type: None,
+            mutates: Default::default(),
+            reads: Default::default(),
+            is_entry_point: true,
+            is_pure: false,
+            overflow_protection: Default::default(),
+            span: None,
+            modifiers: Vec::new(),
+            uses_low_level: false,
+            pre_conditions: vec![bound("", "amount", BinaryOp::Gt, 10).expression],
+            post_conditions: vec![bound("", "amount", BinaryOp::Lte, 1000).expression],
+        });
+
+        let engine = SimulationEngine::new(3);
+        let report = engine.simulate(&program, &[]).unwrap();
+        assert_eq!(report.invariants_checked, vec!["deposit::post".to_string()]);
+        let stats = &report.invariant_stats["deposit::post"];
+        assert_eq!(stats.evaluations, engine.config.max_iterations as u64);
+        // Large amounts break the post-condition; small ones fail the
+        // pre-condition, so the contract holds for them
+        assert!(stats.failures > 0 && stats.failures < stats.evaluations);
+    }
+
+    #[test]
+    fn test_cached_evaluation_matches_uncached() {
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "vault.rs".to_string(),
+        );
+        for (name, type_name) in [("paused", "bool"), ("fee", "u64"), ("supply", "u64")] {
+            program.add_state_var(StateVar {
+                name: name.to_string(),
+                type_name: type_name.to_string(),
+                is_mutable: true,
+                visibility: None,
+                abilities: Default::default(),
+                depends_on: Vec::new(),
+            });
+        }
+        let invariants = vec![
+            bound("fee_cap", "fee", BinaryOp::Lte, 2),
+            bound("supply_floor", "supply", BinaryOp::Gte, 10),
+            Invariant::new(
+                "not_paused",
+                Expression::Not(Box::new(Expression::Var("paused".to_string()))),
+            ),
+        ];
+        let ranges = BTreeMap::from([("fee".to_string(), ValueRange { min: 0, max: 3 })]);
+        let run = |cache_evaluations| {
+            SimulationEngine::with_config(SimulationConfig {
+                max_iterations: 500,
+                cache_evaluations,
+                ..SimulationConfig::default()
+            })
+            .with_ranges(ranges.clone())
+            .simulate(&program, &invariants)
+            .unwrap()
+        };
+
+        let cached = run(true);
+        let uncached = run(false);
+        assert_eq!(cached.invariant_stats, uncached.invariant_stats);
+        assert_eq!(cached.traces, uncached.traces);
+        assert!(cached.invariant_stats["not_paused"].cache_hits > 0);
+        assert!(cached.invariant_stats["fee_cap"].cache_hits > 0);
+        assert!(uncached.invariant_stats.values().all(|s| s.cache_hits == 0));
+    }
+
+    #[test]
+    fn test_deadline_fails_once_block_time_passes_it() {
+        let mut program = ProgramModel::new(
+            "escrow".to_string(),
+            "evm".to_string(),
+            "Escrow.sol".to_string(),
+        );
+        program.add_state_var(StateVar {
+            name: "deadline".to_string(),
+            type_name: "u64".to_string(),
+            is_mutable: true,
+            visibility: None,
+            abilities: Default::default(),
+            depends_on: Vec::new(),
+        });
+        // `deadline > now()`
+        let invariants = vec![Invariant::new(
+            "before_deadline",
+            Expression::BinaryOp {
+                left: Box::new(Expression::Var("deadline".to_string())),
+                op: BinaryOp::Gt,
+                right: Box::new(Expression::FunctionCall {
+                    name: "now".to_string(),
+                    args: vec![],
+                }),
+            },
+        )];
+        let engine = SimulationEngine::with_config(SimulationConfig {
+            max_iterations: 10,
+            trace_depth: 5,
+            start_block: BlockContext {
+                timestamp: 1_000,
+                number: 100,
+                chain_id: 1,
+            },
+            block_time: 12,
+            ..SimulationConfig::default()
+        })
+        .with_ranges(BTreeMap::from([(
+            "deadline".to_string(),
+            ValueRange {
+                min: 1_200,
+                max: 1_200,
+            },
+        )]));
+
+        // Iteration 4 is checked 20 blocks in, at timestamp 1_240
+        let report = engine.simulate(&program, &invariants).unwrap();
+        let stats = &report.invariant_stats["before_deadline"];
+        assert_eq!(stats.first_failure, Some(4));
+        assert_eq!(stats.failures, 6);
+        assert_eq!(stats.errors, 0);
+        assert_eq!(stats.cache_hits, 0);
+        assert_eq!(
+            engine
+                .simulate(&program, &invariants)
+                .unwrap()
+                .invariant_stats,
+            report.invariant_stats
+        );
+    }
+
+    #[test]
+    fn test_config_limits() {
+        let program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "vault.rs".to_string(),
+        );
+        let invariants = vec![Invariant::new("always", Expression::Boolean(true))];
+
+        let engine = SimulationEngine::with_config(SimulationConfig {
+            max_iterations: 5,
+            trace_depth: 3,
+            ..SimulationConfig::default()
+        });
+        let report = engine.simulate(&program, &invariants).unwrap();
+        assert!(report.traces.len() <= 5);
+        assert_eq!(report.invariant_stats["always"].evaluations, 5);
+        assert!(report.traces.iter().all(|t| t.call_sequence.len() == 3));
+
+        let engine = SimulationEngine::with_config(SimulationConfig {
+            max_iterations: 10_000,
+            max_violations_before_abort: 1,
+            ..SimulationConfig::default()
+        });
+        let report = engine.simulate(&program, &invariants).unwrap();
+        assert_eq!(report.violations, 1);
+        assert_eq!(report.traces.len(), 1);
+    }
+
+    #[test]
+    fn test_corpus_speeds_up_rediscovery() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "vault.rs".to_string(),
+        );
+        program.add_state_var(StateVar {
+            name: "fee".to_string(),
+            type_name: "u64".to_string(),
+            is_mutable: true,
+            visibility: None,
+            abilities: Default::default(),
+            depends_on: Vec::new(),
+        });
+        let invariants = vec![bound("not_777", "fee", BinaryOp::Neq, 777)];
+        let engine = SimulationEngine::with_config(SimulationConfig {
+            max_iterations: 20_000,
+            corpus: Some(dir.path().to_path_buf()),
+            ..SimulationConfig::default()
+        })
+        .with_ranges(BTreeMap::from([(
+            "fee".to_string(),
+            ValueRange { min: 0, max: 1000 },
+        )]));
+
+        let first = engine.simulate(&program, &invariants).unwrap();
+        let first = first.invariant_stats["not_777"].first_failure.unwrap();
+        assert!(first > 0);
+
+        let second = engine.simulate(&program, &invariants).unwrap();
+        let second = second.invariant_stats["not_777"].first_failure.unwrap();
+        assert!(second < first, "{} is not below {}", second, first);
+    }
+
+    #[test]
+    fn test_corpus_saves_new_call_orders() {
+        let dir = tempfile::tempdir().unwrap();
+        let program = ProgramModel::new(
+            "vault".to_string(),
+            "solana".to_string(),
+            "vault.rs".to_string(),
+        );
+        let engine = SimulationEngine::with_config(SimulationConfig {
+            max_iterations: 50,
+            trace_depth: 3,
+            corpus: Some(dir.path().to_path_buf()),
+            ..SimulationConfig::default()
+        });
+        let call_orders = || {
+            Corpus::load(dir.path())
+                .unwrap()
+                .entries()
+                .iter()
+                .map(|entry| {
+                    entry
+                        .call_sequence
+                        .iter()
+                        .map(|step| call_name(step).to_string())
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Every fresh iteration calls the steps in order: one new sequence
+        engine.simulate(&program, &[]).unwrap();
+        assert_eq!(call_orders().len(), 1);
+
+        // Mutated entries swap calls, and each new order is kept once
+        engine.simulate(&program, &[]).unwrap();
+        let orders = call_orders();
+        assert!(orders.len() > 1);
+        let distinct: BTreeSet<_> = orders.iter().collect();
+        assert_eq!(distinct.len(), orders.len());
+    }
+}
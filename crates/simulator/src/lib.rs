@@ -3,6 +3,10 @@
 
 //! Simulation engine for finding invariant violations.
 
+pub mod corpus;
 pub mod engine;
+pub mod values;
 
-pub use engine::SimulationEngine;
+pub use corpus::{Corpus, CorpusEntry, DEFAULT_CORPUS_DIR};
+pub use engine::{SimulationConfig, SimulationEngine};
+pub use values::{find_state, random_value, StateGenerator, ValueRange};
@@ -0,0 +1,428 @@
+//! Typed random state generation.
+//!
+//! Values are drawn per declared type and biased towards small numbers and
+//! boundaries, where invariants usually flip between holding and failing.
+
+use invar_core::model::Expression;
+use invar_core::{Evaluator, ExecutionContext, Type, Value, U256_BYTES};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Number of random states tried before giving up on a search.
+pub const SEARCH_ATTEMPTS: usize = 1000;
+
+/// Largest value drawn from the small-number range.
+const SMALL_MAX: u64 = 100;
+
+/// Addresses drawn for `address` variables. A small pool makes equal
+/// addresses (self-transfers, owner checks) likely.
+pub const ADDRESS_POOL: [&str; 4] = [
+    "0x0000000000000000000000000000000000000000",
+    "0x0000000000000000000000000000000000000001",
+    "0x1111111111111111111111111111111111111111",
+    "0xdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef",
+];
+
+/// Inclusive bounds for a variable's generated values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueRange {
+    /// Smallest value.
+    pub min: i128,
+    /// Largest value.
+    pub max: i128,
+}
+
+impl FromStr for ValueRange {
+    type Err = String;
+
+    /// Parse `min..max` (exclusive) or `min..=max`; digits may be separated
+    /// by `_`.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let bound = |text: &str| {
+            text.trim()
+                .replace('_', "")
+                .parse::<i128>()
+                .map_err(|_| format!("invalid range bound '{}' in '{}'", text.trim(), s))
+        };
+        let (min, max) = match s.split_once("..=") {
+            Some((min, max)) => (bound(min)?, bound(max)?),
+            None => {
+                let (min, end) = s
+                    .split_once("..")
+                    .ok_or_else(|| format!("expected 'min..max' or 'min..=max', got '{}'", s))?;
+                (bound(min)?, bound(end)? - 1)
+            }
+        };
+        if min > max {
+            return Err(format!("empty range '{}'", s));
+        }
+        Ok(Self { min, max })
+    }
+}
+
+/// Draws typed variable values, biased towards boundaries, where invariants
+/// usually flip between holding and failing.
+///
+/// Integers favour 0, 1, the type's extremes and powers of two; addresses
+/// come from [`ADDRESS_POOL`]. Variables with a [`ValueRange`] stay within
+/// it and favour its ends instead. Draws are deterministic for a given seed.
+pub struct StateGenerator<R = SmallRng> {
+    rng: R,
+    ranges: BTreeMap<String, ValueRange>,
+}
+
+impl StateGenerator<SmallRng> {
+    /// A generator seeded with `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self::from_rng(SmallRng::seed_from_u64(seed))
+    }
+}
+
+impl<R: Rng> StateGenerator<R> {
+    /// A generator drawing from `rng`.
+    pub fn from_rng(rng: R) -> Self {
+        Self {
+            rng,
+            ranges: BTreeMap::new(),
+        }
+    }
+
+    /// Restrict variables to ranges, keyed by variable name. A qualified
+    /// name (`vault::balance`) falls back to the range of its last segment.
+    pub fn with_ranges(mut self, ranges: BTreeMap<String, ValueRange>) -> Self {
+        self.ranges = ranges;
+        self
+    }
+
+    /// Draw a value for the variable `name` of type `ty`.
+    pub fn value(&mut self, name: &str, ty: &Type) -> Value {
+        match self.range(name) {
+            Some(range) if ty.is_numeric() => self.ranged(ty, range),
+            _ => random_value(ty, &mut self.rng),
+        }
+    }
+
+    /// Move `value` of the variable `name` halfway towards one of its
+    /// bounds, landing on the bound once adjacent. Booleans flip; values
+    /// without integer bounds are drawn afresh.
+    pub fn mutate(&mut self, name: &str, ty: &Type, value: &Value) -> Value {
+        if let Value::Bool(b) = value {
+            return Value::Bool(!b);
+        }
+        let (Some((min, max)), Some(n)) = (self.bounds(name, ty), as_i128(value)) else {
+            return self.value(name, ty);
+        };
+        let target = if self.rng.gen() { min } else { max };
+        let n = n.clamp(min, max);
+        let step = (target - n) / 2;
+        int_value(ty, if step == 0 { target } else { n + step })
+    }
+
+    /// Whether `value` sits at either bound of the variable `name`: the ends
+    /// of its range, or of its type.
+    pub fn is_boundary(&self, name: &str, ty: &Type, value: &Value) -> bool {
+        match (self.bounds(name, ty), as_i128(value)) {
+            (Some((min, max)), Some(n)) => n == min || n == max,
+            _ => false,
+        }
+    }
+
+    /// Draw a value for every variable in `vars`.
+    pub fn state(&mut self, vars: &BTreeMap<String, Type>) -> BTreeMap<String, Value> {
+        vars.iter()
+            .map(|(name, ty)| (name.clone(), self.value(name, ty)))
+            .collect()
+    }
+
+    /// Search for a state under which `expression` evaluates to `holds`,
+    /// giving up after [`SEARCH_ATTEMPTS`] draws or on a non-boolean result.
+    pub fn find_state(
+        &mut self,
+        expression: &Expression,
+        vars: &BTreeMap<String, Type>,
+        holds: bool,
+    ) -> Option<BTreeMap<String, Value>> {
+        for _ in 0..SEARCH_ATTEMPTS {
+            let state = self.state(vars);
+            let context = ExecutionContext {
+                state_vars: state.clone(),
+                ..ExecutionContext::new()
+            };
+            match Evaluator::new(context).evaluate(expression) {
+                Ok(Value::Bool(result)) if result == holds => return Some(state),
+                // Errors such as overflow can depend on the draw, so keep searching
+                Ok(Value::Bool(_)) | Err(_) => {}
+                Ok(_) => return None,
+            }
+        }
+        None
+    }
+
+    /// The range of the variable `name`, or of its unqualified name.
+    fn range(&self, name: &str) -> Option<ValueRange> {
+        let unqualified = name.rsplit("::").next().unwrap_or(name);
+        self.ranges
+            .get(name)
+            .or_else(|| self.ranges.get(unqualified))
+            .copied()
+    }
+
+    /// Integer bounds of the variable `name`: its range clamped to `ty`, or
+    /// the extremes of `ty` where they fit an `i128`.
+    fn bounds(&self, name: &str, ty: &Type) -> Option<(i128, i128)> {
+        let (type_min, type_max) = type_bounds(ty);
+        match self.range(name) {
+            Some(range) if ty.is_numeric() => Some((
+                range.min.clamp(type_min, type_max),
+                range.max.clamp(type_min, type_max),
+            )),
+            _ if matches!(ty, Type::U64 | Type::I64) => Some((type_min, type_max)),
+            _ => None,
+        }
+    }
+
+    /// Draw within `range`, clamped to what `ty` can hold.
+    fn ranged(&mut self, ty: &Type, range: ValueRange) -> Value {
+        let (type_min, type_max) = type_bounds(ty);
+        let min = range.min.clamp(type_min, type_max);
+        let max = range.max.clamp(type_min, type_max);
+        let n = match self.rng.gen_range(0..8) {
+            0 => min,
+            1 => max,
+            2 => min.saturating_add(1).min(max),
+            3 => max.saturating_sub(1).max(min),
+            _ => self.rng.gen_range(min..=max),
+        };
+        int_value(ty, n)
+    }
+}
+
+/// Integer extremes of `ty`, capped to what an `i128` holds.
+fn type_bounds(ty: &Type) -> (i128, i128) {
+    match ty {
+        Type::U64 => (0, i128::from(u64::MAX)),
+        Type::I64 => (i128::from(i64::MIN), i128::from(i64::MAX)),
+        _ => (0, i128::MAX),
+    }
+}
+
+/// `n` as a value of the numeric type `ty`; `n` must be within [`type_bounds`].
+fn int_value(ty: &Type, n: i128) -> Value {
+    match ty {
+        Type::I64 => Value::I64(n as i64),
+        Type::U64 => Value::U64(n as u64),
+        _ => Value::U128(n as u128)
+            .cast(ty)
+            .expect("non-negative ranges fit any unsigned type"),
+    }
+}
+
+/// An integer value as an `i128`, if it fits.
+fn as_i128(value: &Value) -> Option<i128> {
+    match value {
+        Value::U64(n) => Some(i128::from(*n)),
+        Value::I64(n) => Some(i128::from(*n)),
+        Value::U128(n) => i128::try_from(*n).ok(),
+        _ => None,
+    }
+}
+
+/// Draw a random value of type `ty`, without range restrictions.
+///
+/// Strings are short hex strings; arrays and maps are empty.
+pub fn random_value<R: Rng>(ty: &Type, rng: &mut R) -> Value {
+    match ty {
+        Type::Bool => Value::Bool(rng.gen()),
+        Type::U64 => Value::U64(random_unsigned(rng, u64::MAX)),
+        Type::U128 => Value::U128(match rng.gen_range(0..4) {
+            0 => u128::MAX,
+            1 => rng.gen(),
+            2 => 1u128 << rng.gen_range(0..128),
+            _ => u128::from(random_unsigned(rng, u64::MAX)),
+        }),
+        Type::I64 => Value::I64(match rng.gen_range(0..8) {
+            0 => i64::MIN,
+            1 => i64::MAX,
+            2 => rng.gen(),
+            3 => 1i64 << rng.gen_range(0..63),
+            _ => rng.gen_range(-(SMALL_MAX as i64)..=SMALL_MAX as i64),
+        }),
+        Type::U256 => match rng.gen_range(0..4) {
+            0 => Value::u256_max(),
+            1 => Value::BigInt((0..U256_BYTES).map(|_| rng.gen()).collect()),
+            _ => Value::U64(random_unsigned(rng, u64::MAX))
+                .cast(&Type::U256)
+                .expect("u64 fits in a u256"),
+        },
+        Type::Address => {
+            Value::Address(ADDRESS_POOL[rng.gen_range(0..ADDRESS_POOL.len())].to_string())
+        }
+        Type::Str => Value::Str(format!("{:x}", rng.gen::<u32>())),
+        Type::Array => Value::Array(Vec::new()),
+        Type::Map(..) => Value::Map(BTreeMap::new()),
+    }
+}
+
+/// 0, 1, `max`, a power of two, or a small or uniform value up to `max`.
+fn random_unsigned<R: Rng>(rng: &mut R, max: u64) -> u64 {
+    match rng.gen_range(0..10) {
+        0 => 0,
+        1 => 1.min(max),
+        2 => max,
+        3 => (1u64 << rng.gen_range(0..64)).min(max),
+        4 => rng.gen_range(0..=max),
+        _ => rng.gen_range(0..=SMALL_MAX.min(max)),
+    }
+}
+
+/// Search for a state under which `expression` evaluates to `holds`.
+///
+/// `vars` maps each variable, named as the evaluator looks it up
+/// (`balance`, `vault::balance`), to its type. The search is deterministic
+/// for a given `seed` and returns `None` when no state is found within
+/// [`SEARCH_ATTEMPTS`] draws or the expression is not boolean.
+pub fn find_state(
+    expression: &Expression,
+    vars: &BTreeMap<String, Type>,
+    seed: u64,
+    holds: bool,
+) -> Option<BTreeMap<String, Value>> {
+    StateGenerator::new(seed).find_state(expression, vars, holds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use invar_core::model::BinaryOp;
+
+    fn reserve_covers_debt() -> Expression {
+        Expression::BinaryOp {
+            left: Box::new(Expression::LayerVar {
+                layer: "vault".to_string(),
+                var: "reserve".to_string(),
+            }),
+            op: BinaryOp::Gte,
+            right: Box::new(Expression::Var("debt".to_string())),
+        }
+    }
+
+    #[test]
+    fn test_find_state_both_ways() {
+        let vars = BTreeMap::from([
+            ("vault::reserve".to_string(), Type::U64),
+            ("debt".to_string(), Type::U64),
+        ]);
+        let expr = reserve_covers_debt();
+
+        let holding = find_state(&expr, &vars, 7, true).unwrap();
+        let violating = find_state(&expr, &vars, 7, false).unwrap();
+        let (Value::U64(reserve), Value::U64(debt)) =
+            (&holding["vault::reserve"], &holding["debt"])
+        else {
+            panic!("expected u64 values: {:?}", holding);
+        };
+        assert!(reserve >= debt);
+        let (Value::U64(reserve), Value::U64(debt)) =
+            (&violating["vault::reserve"], &violating["debt"])
+        else {
+            panic!("expected u64 values: {:?}", violating);
+        };
+        assert!(reserve < debt);
+
+        // Same seed, same state
+        assert_eq!(find_state(&expr, &vars, 7, true), Some(holding));
+    }
+
+    #[test]
+    fn test_unsatisfiable_search_gives_up() {
+        let never = Expression::BinaryOp {
+            left: Box::new(Expression::Var("fee".to_string())),
+            op: BinaryOp::Lt,
+            right: Box::new(Expression::Int(0)),
+        };
+        let vars = BTreeMap::from([("fee".to_string(), Type::U64)]);
+        assert_eq!(find_state(&never, &vars, 1, true), None);
+    }
+
+    #[test]
+    fn test_boundary_values_appear() {
+        let mut generator = StateGenerator::new(11);
+        let draws: Vec<Value> = (0..200)
+            .map(|_| generator.value("supply", &Type::U64))
+            .collect();
+        for boundary in [0, 1, u64::MAX] {
+            assert!(
+                draws.contains(&Value::U64(boundary)),
+                "missing {}",
+                boundary
+            );
+        }
+        assert!(draws
+            .iter()
+            .any(|v| matches!(v, Value::U64(n) if n.is_power_of_two() && *n > 1)));
+
+        let addresses: Vec<Value> = (0..50)
+            .map(|_| generator.value("owner", &Type::Address))
+            .collect();
+        assert!(addresses
+            .iter()
+            .all(|a| ADDRESS_POOL.contains(&a.to_string().as_str())));
+    }
+
+    #[test]
+    fn test_ranges_are_honored() {
+        let range: ValueRange = "0..1_000_000".parse().unwrap();
+        assert_eq!(
+            range,
+            ValueRange {
+                min: 0,
+                max: 999_999
+            }
+        );
+        assert_eq!("-5..=5".parse(), Ok(ValueRange { min: -5, max: 5 }));
+        assert!("10..1".parse::<ValueRange>().is_err());
+        assert!("lots".parse::<ValueRange>().is_err());
+
+        let mut generator =
+            StateGenerator::new(5).with_ranges(BTreeMap::from([("balance".to_string(), range)]));
+        let draws: Vec<u64> = (0..200)
+            .map(|_| match generator.value("vault::balance", &Type::U64) {
+                Value::U64(n) => n,
+                other => panic!("expected u64, got {:?}", other),
+            })
+            .collect();
+        assert!(draws.iter().all(|n| *n < 1_000_000));
+        assert!(draws.contains(&0) && draws.contains(&999_999));
+        // Unranged variables keep the full distribution
+        assert!((0..200).any(|_| generator.value("fee", &Type::U64) == Value::U64(u64::MAX)));
+    }
+
+    #[test]
+    fn test_mutate_moves_towards_bounds() {
+        let ranges = BTreeMap::from([("fee".to_string(), "0..=1000".parse().unwrap())]);
+        let mut generator = StateGenerator::new(5).with_ranges(ranges);
+
+        let mutated = generator.mutate("fee", &Type::U64, &Value::U64(500));
+        assert!(mutated == Value::U64(250) || mutated == Value::U64(750));
+        let edge = generator.mutate("fee", &Type::U64, &Value::U64(1));
+        assert!(generator.is_boundary("fee", &Type::U64, &edge) || edge == Value::U64(500));
+        assert!(generator.is_boundary("fee", &Type::U64, &Value::U64(1000)));
+        assert!(!generator.is_boundary("fee", &Type::U64, &Value::U64(999)));
+        assert_eq!(
+            generator.mutate("paused", &Type::Bool, &Value::Bool(true)),
+            Value::Bool(false)
+        );
+    }
+
+    #[test]
+    fn test_random_values_match_type() {
+        let mut rng = SmallRng::seed_from_u64(3);
+        for ty in [Type::Bool, Type::U64, Type::U128, Type::I64, Type::Address] {
+            for _ in 0..20 {
+                assert_eq!(random_value(&ty, &mut rng).get_type(), ty);
+            }
+        }
+    }
+}
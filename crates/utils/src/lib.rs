@@ -8,6 +8,6 @@ pub mod path_utils;
 pub mod release;
 pub mod version;
 
-pub use logging::setup_tracing;
+pub use logging::{setup_tracing, LogFormat};
 pub use release::ReleaseManager;
 pub use version::{Platform, ReleaseArtifact, ReproducibleBuildConfig, SemanticVersion};
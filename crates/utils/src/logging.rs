@@ -1,19 +1,138 @@
 //! Logging and tracing setup.
 
-use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{fmt, prelude::*, EnvFilter, Layer};
+
+/// Output format of log events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines with timestamp, level and target.
+    #[default]
+    Pretty,
+    /// One JSON object per event, for log aggregators.
+    Json,
+    /// Abbreviated human-readable lines.
+    Compact,
+}
+
+impl LogFormat {
+    /// Lowercase name, as accepted by `--log-format`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pretty => "pretty",
+            Self::Json => "json",
+            Self::Compact => "compact",
+        }
+    }
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            "compact" => Ok(Self::Compact),
+            _ => Err(format!(
+                "Unknown log format: {}. Supported: pretty, json, compact",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
 
 /// Initialize structured logging with sensible defaults.
 ///
+/// Events are written to stderr, so stdout stays free for command output.
+///
 /// # Arguments
 ///
 /// * `level` - Log level: "trace", "debug", "info", "warn", "error"
-pub fn setup_tracing(level: &str) {
-    let filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new(level))
-        .add_directive("hyper=info".parse().unwrap_or_default());
-
+/// * `format` - Output format of each event
+pub fn setup_tracing(level: &str, format: LogFormat) {
     tracing_subscriber::registry()
-        .with(fmt::layer().with_writer(std::io::stderr))
-        .with(filter)
+        .with(fmt_layer(format, std::io::stderr))
+        .with(env_filter(level))
         .init();
 }
+
+fn env_filter(level: &str) -> EnvFilter {
+    EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(level))
+        .add_directive("hyper=info".parse().unwrap_or_default())
+}
+
+/// Formatting layer writing events in `format` to `writer`.
+///
+/// JSON events carry `timestamp`, `level`, `target`, `message` and the
+/// event's fields at the top level of each object.
+fn fmt_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let layer = fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Pretty => layer.boxed(),
+        LogFormat::Json => layer.json().flatten_event(true).boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_events_parse() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::registry()
+            .with(fmt_layer(LogFormat::Json, move || writer.clone()))
+            .with(EnvFilter::new("info"));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(invariants = 3, "test message");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let event: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(event["message"], "test message");
+        assert_eq!(event["level"], "INFO");
+        assert_eq!(event["invariants"], 3);
+        assert!(event.get("timestamp").is_some());
+        assert!(event.get("target").is_some());
+    }
+
+    #[test]
+    fn test_log_format_round_trip() {
+        for format in [LogFormat::Pretty, LogFormat::Json, LogFormat::Compact] {
+            assert_eq!(format.as_str().parse::<LogFormat>(), Ok(format));
+        }
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}